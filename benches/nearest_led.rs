@@ -0,0 +1,68 @@
+use chrono::{DateTime, Duration, Utc};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use f1_led_circuit_master_simulation::data::LocationData;
+use f1_led_circuit_master_simulation::led_coords::read_coordinates;
+use f1_led_circuit_master_simulation::mapping::{
+    nearest_led, snap_to_index, NearestLedIndex, PARALLEL_MAPPING_THRESHOLD,
+};
+use f1_led_circuit_master_simulation::space::{LedPoint, TelemetryPoint};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// A car lapping the layout at about 4 Hz, a few meters off the racing line, so consecutive
+// samples often share a cache cell like real telemetry does
+fn lapping_samples(coordinates: &[LedPoint], count: usize) -> Vec<LocationData> {
+    let start: DateTime<Utc> = "2023-08-27T13:00:00Z".parse().unwrap();
+    let mut rng = StdRng::seed_from_u64(848);
+    (0..count)
+        .map(|index| {
+            let along = index as f64 / 8.0;
+            let from = coordinates[along as usize % coordinates.len()];
+            let to = coordinates[(along as usize + 1) % coordinates.len()];
+            let t = along.fract();
+            LocationData {
+                point: TelemetryPoint::new(
+                    from.x + (to.x - from.x) * t + rng.gen_range(-30.0..30.0),
+                    from.y + (to.y - from.y) * t + rng.gen_range(-30.0..30.0),
+                ),
+                z: None,
+                date: start + Duration::milliseconds(index as i64 * 270),
+                driver_number: 1,
+                synthetic: false,
+            }
+        })
+        .collect()
+}
+
+fn nearest_led_lookups(c: &mut Criterion) {
+    let coordinates = read_coordinates().unwrap();
+    let index = NearestLedIndex::new(&coordinates);
+    let samples = lapping_samples(&coordinates, PARALLEL_MAPPING_THRESHOLD / 2);
+
+    let mut group = c.benchmark_group("nearest_led");
+    group.bench_function("cached", |b| {
+        b.iter(|| snap_to_index(black_box(&samples), &index, false))
+    });
+    group.bench_function("index", |b| {
+        b.iter(|| {
+            samples
+                .iter()
+                .map(|sample| index.nearest(LedPoint::new(sample.point.x, sample.point.y)))
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("linear", |b| {
+        b.iter(|| {
+            samples
+                .iter()
+                .map(|sample| {
+                    nearest_led(&coordinates, LedPoint::new(sample.point.x, sample.point.y))
+                })
+                .collect::<Vec<_>>()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, nearest_led_lookups);
+criterion_main!(benches);
@@ -0,0 +1,19 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where a file is written until it's complete: its own name with `.tmp` appended, in the same
+/// directory, so renaming it into place replaces the old file in one step.
+pub fn temporary_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Writes `bytes` to `path` by way of its temporary path, so a crash or a kill midway leaves the
+/// previous file, or none, rather than a truncated one.
+pub fn write_atomically(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let temporary = temporary_path(path);
+    fs::write(&temporary, bytes)?;
+    fs::rename(&temporary, path)
+}
@@ -0,0 +1,291 @@
+use crate::race_events::RaceEvent;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+
+/// Sample rate of the built-in sounds, which are mono.
+pub const SAMPLE_RATE: u32 = 44_100;
+
+// The start lights: a beep as each of the five comes on, a second apart
+const START_BEEPS: usize = 5;
+const START_BEEP_SECS: f32 = 0.25;
+const START_BEEP_HZ: f32 = 880.0;
+// The overtake chime: two notes a fourth apart, each dying away
+const CHIME_NOTES_HZ: [f32; 2] = [1318.5, 1760.0];
+const CHIME_NOTE_DELAY_SECS: f32 = 0.12;
+const CHIME_SECS: f32 = 0.6;
+// The pit stop whoosh: noise swelling and fading while it gets brighter and darker again
+const WHOOSH_SECS: f32 = 0.7;
+// Ramps at the edges of each beep, against clicks
+const EDGE_SECS: f32 = 0.005;
+const PEAK: f32 = 0.5;
+
+/// Sounds played as race events happen, through the default audio device. They're only heard
+/// in builds with the `audio` feature; without an audio device they're skipped silently.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    pub enabled: bool,
+    pub volume: f32, // 0 to 1
+    pub muted: bool,
+    pub start_sound: Option<PathBuf>, // .ogg files played instead of the built-in sounds
+    pub overtake_sound: Option<PathBuf>,
+    pub pit_stop_sound: Option<PathBuf>,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        AudioConfig {
+            enabled: false,
+            volume: 0.8,
+            muted: false,
+            start_sound: None,
+            overtake_sound: None,
+            pit_stop_sound: None,
+        }
+    }
+}
+
+/// The sound played for a kind of race event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cue {
+    StartLights,
+    Overtake,
+    PitStop,
+}
+
+impl Cue {
+    pub const ALL: [Cue; 3] = [Cue::StartLights, Cue::Overtake, Cue::PitStop];
+
+    pub fn for_event(event: &RaceEvent) -> Cue {
+        match event {
+            RaceEvent::Start => Cue::StartLights,
+            RaceEvent::Overtake(_) => Cue::Overtake,
+            RaceEvent::PitStop { .. } => Cue::PitStop,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Cue::StartLights => "start lights",
+            Cue::Overtake => "overtake",
+            Cue::PitStop => "pit stop",
+        }
+    }
+
+    /// The file configured to play instead of the built-in sound.
+    pub fn file(self, config: &AudioConfig) -> Option<&Path> {
+        match self {
+            Cue::StartLights => config.start_sound.as_deref(),
+            Cue::Overtake => config.overtake_sound.as_deref(),
+            Cue::PitStop => config.pit_stop_sound.as_deref(),
+        }
+    }
+
+    /// The built-in sound, as mono samples at `SAMPLE_RATE`.
+    pub fn samples(self) -> Vec<f32> {
+        match self {
+            Cue::StartLights => start_beeps(),
+            Cue::Overtake => chime(),
+            Cue::PitStop => whoosh(),
+        }
+    }
+}
+
+fn samples_for(secs: f32) -> usize {
+    (secs * SAMPLE_RATE as f32) as usize
+}
+
+fn start_beeps() -> Vec<f32> {
+    let beep = samples_for(START_BEEP_SECS);
+    let edge = samples_for(EDGE_SECS);
+    let mut samples = vec![0.0; samples_for((START_BEEPS - 1) as f32) + beep];
+    for start in (0..START_BEEPS).map(|index| samples_for(index as f32)) {
+        for index in 0..beep {
+            let ramp = (index.min(beep - 1 - index) as f32 / edge as f32).min(1.0);
+            let phase = 2.0 * PI * START_BEEP_HZ * index as f32 / SAMPLE_RATE as f32;
+            samples[start + index] = PEAK * ramp * phase.sin();
+        }
+    }
+    samples
+}
+
+fn chime() -> Vec<f32> {
+    let mut samples = vec![0.0; samples_for(CHIME_SECS)];
+    for (note, hz) in CHIME_NOTES_HZ.into_iter().enumerate() {
+        let start = samples_for(CHIME_NOTE_DELAY_SECS * note as f32);
+        for (index, sample) in samples[start..].iter_mut().enumerate() {
+            let secs = index as f32 / SAMPLE_RATE as f32;
+            let attack = (secs / EDGE_SECS).min(1.0);
+            *sample += PEAK / 2.0 * attack * (-8.0 * secs).exp() * (2.0 * PI * hz * secs).sin();
+        }
+    }
+    samples
+}
+
+fn whoosh() -> Vec<f32> {
+    let length = samples_for(WHOOSH_SECS);
+    let mut noise = StdRng::seed_from_u64(0);
+    let mut filtered = 0.0;
+    (0..length)
+        .map(|index| {
+            let swell = (PI * index as f32 / length as f32).sin();
+            // A one-pole low-pass whose cutoff follows the swell
+            let brightness = 0.02 + 0.3 * swell * swell;
+            filtered += brightness * (noise.gen_range(-1.0..1.0) - filtered);
+            PEAK * 2.0 * swell * filtered
+        })
+        .collect()
+}
+
+/// Plays the cues of race events on a thread of its own, so playing never blocks the caller.
+/// Cues go unheard while muted, without the `audio` feature or without an audio device.
+pub struct AudioPlayer {
+    sender: Option<Sender<(Cue, f32)>>, // A cue and the gain to play it at
+    volume: f32,
+    muted: bool,
+}
+
+impl AudioPlayer {
+    /// Starts the audio thread when the cues are enabled; never fails.
+    pub fn open(config: &AudioConfig) -> AudioPlayer {
+        AudioPlayer {
+            sender: if config.enabled { spawn(config) } else { None },
+            volume: config.volume.clamp(0.0, 1.0),
+            muted: config.muted,
+        }
+    }
+
+    /// Whether the cues are played at all, i.e. enabled and built in.
+    pub fn is_enabled(&self) -> bool {
+        self.sender.is_some()
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    /// Plays the cue of each event, on top of any still playing.
+    pub fn play(&self, events: &[RaceEvent]) {
+        let Some(sender) = self.sender.as_ref().filter(|_| !self.muted) else {
+            return;
+        };
+        for event in events {
+            // The audio thread is gone when there's no audio device; then nothing is heard
+            let _ = sender.send((Cue::for_event(event), self.volume));
+        }
+    }
+}
+
+#[cfg(feature = "audio")]
+fn spawn(config: &AudioConfig) -> Option<Sender<(Cue, f32)>> {
+    output::spawn(config)
+}
+
+#[cfg(not(feature = "audio"))]
+fn spawn(_config: &AudioConfig) -> Option<Sender<(Cue, f32)>> {
+    log::warn!("Ignoring the audio cues: this build lacks the audio feature");
+    None
+}
+
+// The rodio half, only built with the `audio` feature
+#[cfg(feature = "audio")]
+mod output {
+    use super::{AudioConfig, Cue, SAMPLE_RATE};
+    use log::{debug, info, warn};
+    use rodio::buffer::SamplesBuffer;
+    use rodio::{Decoder, OutputStream, Source};
+    use std::collections::HashMap;
+    use std::fs::File;
+    use std::io::BufReader;
+    use std::path::Path;
+    use std::sync::mpsc::{self, Sender};
+    use std::thread;
+
+    struct Clip {
+        channels: u16,
+        sample_rate: u32,
+        samples: Vec<f32>,
+    }
+
+    pub fn spawn(config: &AudioConfig) -> Option<Sender<(Cue, f32)>> {
+        let (sender, receiver) = mpsc::channel::<(Cue, f32)>();
+        let config = config.clone();
+        let spawned = thread::Builder::new()
+            .name("audio".to_string())
+            .spawn(move || {
+                // The stream can't leave this thread, and only plays while it's alive
+                let (_stream, handle) = match OutputStream::try_default() {
+                    Ok(output) => output,
+                    Err(err) => {
+                        info!("No audio device, so the audio cues go unheard: {}", err);
+                        return;
+                    }
+                };
+                let clips: HashMap<Cue, Clip> = Cue::ALL
+                    .into_iter()
+                    .map(|cue| (cue, load(cue, cue.file(&config))))
+                    .collect();
+                for (cue, gain) in receiver {
+                    let clip = &clips[&cue];
+                    let source =
+                        SamplesBuffer::new(clip.channels, clip.sample_rate, clip.samples.clone())
+                            .amplify(gain);
+                    if let Err(err) = handle.play_raw(source) {
+                        debug!("Could not play the {} cue: {}", cue.label(), err);
+                    }
+                }
+            });
+        match spawned {
+            Ok(_) => Some(sender),
+            Err(err) => {
+                warn!("Could not start the audio thread: {}", err);
+                None
+            }
+        }
+    }
+
+    // The configured file decoded, or else the built-in sound
+    fn load(cue: Cue, file: Option<&Path>) -> Clip {
+        if let Some(path) = file {
+            let decoded = File::open(path)
+                .map_err(|err| err.to_string())
+                .and_then(|file| Decoder::new(BufReader::new(file)).map_err(|err| err.to_string()));
+            match decoded {
+                Ok(decoder) => {
+                    return Clip {
+                        channels: decoder.channels(),
+                        sample_rate: decoder.sample_rate(),
+                        samples: decoder.convert_samples().collect(),
+                    }
+                }
+                Err(err) => warn!(
+                    "Could not read {}, playing the built-in {} sound: {}",
+                    path.display(),
+                    cue.label(),
+                    err
+                ),
+            }
+        }
+        Clip {
+            channels: 1,
+            sample_rate: SAMPLE_RATE,
+            samples: cue.samples(),
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use crate::laps::RaceProgress;
+use crate::space::TelemetryPoint;
+use crate::timeline::{DriverTimelines, SpeedConfig};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+// Cars further apart than this share of a lap aren't racing each other, however close they are
+const LAPPED_SHARE: f64 = 0.5;
+
+/// When two cars count as battling.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BattleConfig {
+    pub enabled: bool,
+    pub distance_m: f64,         // A battle starts this close
+    pub release_distance_m: f64, // ... and ends this far apart, so it doesn't flicker in between
+    pub stale_secs: f64,         // Cars without a sample for this long are left out
+}
+
+impl Default for BattleConfig {
+    fn default() -> Self {
+        BattleConfig {
+            enabled: true,
+            distance_m: 20.0,
+            release_distance_m: 30.0,
+            stale_secs: 5.0,
+        }
+    }
+}
+
+/// Two cars running close together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Battle {
+    pub drivers: (u32, u32), // Lower number first
+    pub distance_m: f64,
+    pub gap_secs: Option<f64>, // The distance at the cars' average speed, when it's known
+}
+
+/// Pairs up cars by their raw locations on every update, keeping each battle until the cars
+/// are further apart than the release distance.
+#[derive(Debug, Clone, Default)]
+pub struct BattleDetector {
+    config: BattleConfig,
+    active: HashSet<(u32, u32)>,
+    battles: Vec<Battle>,
+}
+
+impl BattleDetector {
+    pub fn new(config: &BattleConfig) -> BattleDetector {
+        BattleDetector {
+            config: config.clone(),
+            ..BattleDetector::default()
+        }
+    }
+
+    /// The battles as of the last update, closest first.
+    pub fn battles(&self) -> &[Battle] {
+        &self.battles
+    }
+
+    /// Whether the driver is in one of the current battles.
+    pub fn is_battling(&self, driver_number: u32) -> bool {
+        self.battles
+            .iter()
+            .any(|battle| battle.drivers.0 == driver_number || battle.drivers.1 == driver_number)
+    }
+
+    /// Forgets every battle, e.g. when playback is rewound.
+    pub fn clear(&mut self) {
+        self.active.clear();
+        self.battles.clear();
+    }
+
+    /// Finds the battles at `time`. With `progress`, pairs where one car is lapping the other
+    /// are left out. `speed` converts coordinates to meters and estimates the gaps.
+    pub fn update(
+        &mut self,
+        timelines: &DriverTimelines,
+        time: DateTime<Utc>,
+        progress: Option<&RaceProgress>,
+        speed: &SpeedConfig,
+    ) -> &[Battle] {
+        self.battles.clear();
+        if !self.config.enabled {
+            self.active.clear();
+            return &self.battles;
+        }
+
+        let mut cars: Vec<(u32, TelemetryPoint)> = timelines
+            .drivers()
+            .filter_map(|driver_number| {
+                let run_data = timelines.position_at(driver_number, time)?;
+                let age = (time - run_data.date).num_milliseconds() as f64 / 1000.0;
+                (age <= self.config.stale_secs).then_some((driver_number, run_data.point))
+            })
+            .collect();
+        cars.sort_by_key(|&(driver_number, _)| driver_number);
+
+        let mut active = HashSet::new();
+        for (index, &(first, first_at)) in cars.iter().enumerate() {
+            for &(second, second_at) in &cars[index + 1..] {
+                let distance_m = first_at.distance(second_at) * speed.meters_per_unit;
+                let limit = if self.active.contains(&(first, second)) {
+                    self.config.release_distance_m
+                } else {
+                    self.config.distance_m
+                };
+                if distance_m > limit || is_lapping(progress, first, second, time) {
+                    continue;
+                }
+                active.insert((first, second));
+                self.battles.push(Battle {
+                    drivers: (first, second),
+                    distance_m,
+                    gap_secs: gap_secs(timelines, time, speed, (first, second), distance_m),
+                });
+            }
+        }
+        self.active = active;
+        self.battles
+            .sort_by(|a, b| a.distance_m.total_cmp(&b.distance_m));
+        &self.battles
+    }
+}
+
+fn is_lapping(
+    progress: Option<&RaceProgress>,
+    first: u32,
+    second: u32,
+    time: DateTime<Utc>,
+) -> bool {
+    let Some(progress) = progress else {
+        return false;
+    };
+    match (
+        progress.laps_covered(first, time),
+        progress.laps_covered(second, time),
+    ) {
+        (Some(first), Some(second)) => (first - second).abs() > LAPPED_SHARE,
+        _ => false,
+    }
+}
+
+fn gap_secs(
+    timelines: &DriverTimelines,
+    time: DateTime<Utc>,
+    speed: &SpeedConfig,
+    drivers: (u32, u32),
+    distance_m: f64,
+) -> Option<f64> {
+    let first = timelines.speed_at(drivers.0, time, speed)?;
+    let second = timelines.speed_at(drivers.1, time, speed)?;
+    // Back from the display unit to meters per second
+    let average = (first + second) / 2.0 / speed.unit.from_meters_per_second(1.0);
+    (average > 0.0).then(|| distance_m / average)
+}
@@ -0,0 +1,37 @@
+// The viewer in a browser. `trunk serve` builds this for wasm32-unknown-unknown with the web
+// feature and serves it from index.html; the page's query string picks the session
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    use f1_led_circuit_master_simulation::web::{WebSource, WebViewer};
+
+    eframe::WebLogger::init(log::LevelFilter::Info).ok();
+    let location = web_sys::window().map(|window| window.location());
+    let page_url = location
+        .as_ref()
+        .and_then(|location| location.href().ok())
+        .unwrap_or_default();
+    let query = location
+        .as_ref()
+        .and_then(|location| location.search().ok())
+        .unwrap_or_default();
+    let source = WebSource::from_query(&query);
+    log::info!("Playing {}", source.describe());
+
+    wasm_bindgen_futures::spawn_local(async move {
+        let started = eframe::WebRunner::new()
+            .start(
+                "track",
+                eframe::WebOptions::default(),
+                Box::new(|cc| Box::new(WebViewer::new(&cc.egui_ctx, source, page_url))),
+            )
+            .await;
+        if let Err(err) = started {
+            log::error!("Could not start the viewer: {:?}", err);
+        }
+    });
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    eprintln!("The web viewer runs in a browser; build and serve it with `trunk serve`");
+}
@@ -0,0 +1,83 @@
+use crate::laps::RaceProgress;
+use crate::simulation::Rgb;
+use crate::track_progress::TrackProgress;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::TAU;
+
+// A car this share of a lap or more behind the leader is a lap down; the leader merely
+// closing in on the car ahead on the same lap isn't lapping it
+const LAPPED_SHARE: f64 = 0.5;
+const BLUE: Rgb = [0, 80, 255];
+// The tint swells and fades this many times a second of race time
+const PULSE_HZ: f64 = 1.5;
+
+/// Blue flags for the cars the leader is about to lap.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BlueFlagConfig {
+    pub enabled: bool,
+    pub leds_behind: usize, // A lapped car is flagged once the leader is this close behind
+}
+
+impl Default for BlueFlagConfig {
+    fn default() -> Self {
+        BlueFlagConfig {
+            enabled: true,
+            leds_behind: 5,
+        }
+    }
+}
+
+/// The cars a lap or more down with the leader at most `leds_behind` LEDs behind them, by
+/// number. The laps covered come from the laps data when every car on the LEDs has them at
+/// `time`, and from the line crossings otherwise. Nothing is carried over between calls, so
+/// seeks either way need no bookkeeping.
+pub fn about_to_be_lapped(
+    track: &TrackProgress,
+    progress: Option<&RaceProgress>,
+    time: Option<DateTime<Utc>>,
+    leds_behind: usize,
+) -> Vec<u32> {
+    let led_count = track.led_count();
+    if led_count == 0 || leds_behind == 0 {
+        return Vec::new();
+    }
+    let cars: Vec<(u32, usize)> = track.leds().collect();
+    let from_laps: Option<Vec<f64>> = progress.zip(time).and_then(|(progress, time)| {
+        cars.iter()
+            .map(|&(driver_number, _)| progress.laps_covered(driver_number, time))
+            .collect()
+    });
+    let covered = from_laps.unwrap_or_else(|| {
+        cars.iter()
+            .map(|&(driver_number, _)| track.laps_covered(driver_number).unwrap_or(0.0))
+            .collect()
+    });
+    let Some(leader) = (0..cars.len()).max_by(|&a, &b| covered[a].total_cmp(&covered[b])) else {
+        return Vec::new();
+    };
+    let (leader_led, leader_laps) = (cars[leader].1, covered[leader]);
+    let mut lapped: Vec<u32> = cars
+        .iter()
+        .zip(&covered)
+        .filter(|&(&(_, led), &laps)| {
+            let gap = (led + led_count - leader_led) % led_count;
+            (1..=leds_behind).contains(&gap) && leader_laps - laps > LAPPED_SHARE
+        })
+        .map(|(&(driver_number, _), _)| driver_number)
+        .collect();
+    lapped.sort();
+    lapped
+}
+
+/// A lapped car's color pulsing towards blue at `time`.
+pub fn blue_tint(color: Rgb, time: DateTime<Utc>) -> Rgb {
+    let secs = time.timestamp_millis() as f64 / 1000.0;
+    let mix = 0.5 - 0.5 * (TAU * PULSE_HZ * secs).cos();
+    let mut tinted = color;
+    for (channel, blue) in tinted.iter_mut().zip(BLUE) {
+        *channel = (*channel as f64 + (blue as f64 - *channel as f64) * mix).round() as u8;
+    }
+    tinted
+}
@@ -0,0 +1,283 @@
+use crate::config::SessionConfig;
+use crate::data::{LocationData, PipelineStats};
+use crate::driver_info::DriverInfo;
+use crate::error::AppError;
+use crate::laps::RaceProgress;
+use crate::mapping::{
+    map_drivers, MappingOptions, MappingStats, RunRace, Smoothing, MAPPING_VERSION,
+};
+use crate::recorder::layout_hash;
+use crate::simulation::Rgb;
+use crate::space::LedPoint;
+use chrono::{DateTime, Utc};
+use eframe::egui;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+
+/// Version of the `.f1led` bundle format, written after the magic; bump it on any change to
+/// `BundleManifest` or `Bundle`. A bundle holds a session as it was loaded, so it plays again
+/// without the API, fetching or mapping.
+///
+/// | Field    | Type    | Notes                                        |
+/// |----------|---------|----------------------------------------------|
+/// | magic    | 4 bytes | `F1LB`                                       |
+/// | version  | u16     | Little endian; readers reject other versions |
+/// | manifest | bincode | `BundleManifest`, readable on its own        |
+/// | data     | bincode | `Bundle`                                     |
+pub const BUNDLE_VERSION: u16 = 4;
+
+pub const BUNDLE_EXTENSION: &str = "f1led";
+
+const MAGIC: &[u8; 4] = b"F1LB";
+
+/// Where "Export bundle" writes bundles, named after the session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BundleConfig {
+    pub dir: PathBuf,
+}
+
+impl Default for BundleConfig {
+    fn default() -> Self {
+        BundleConfig {
+            dir: PathBuf::from("bundles"),
+        }
+    }
+}
+
+/// What a bundle holds, written ahead of the data.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub created: DateTime<Utc>,
+    pub session_key: String,
+    pub mapping_version: u16,
+    pub led_count: usize,
+    pub layout_hash: u64, // `layout_hash` of the layout the records were mapped onto
+    pub record_count: usize,
+    pub driver_count: usize,
+}
+
+/// A loaded session: the session asked for, the layout and mapping options its records were
+/// mapped with, the roster with its colors and the laps and positions when fetched. The
+/// records keep each sample's location before snapping, which is what mapping them again
+/// starts from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bundle {
+    pub created: DateTime<Utc>,
+    pub mapping_version: u16, // `MAPPING_VERSION` of the build that mapped the records
+    pub session: SessionConfig,
+    pub coordinates: Vec<LedPoint>,
+    #[serde(with = "bundled_roster")]
+    pub driver_info: Vec<DriverInfo>,
+    pub mapping_options: MappingOptions,
+    pub run_race_data: Vec<RunRace>,
+    pub mapping_stats: MappingStats,
+    pub race_progress: Option<RaceProgress>,
+}
+
+impl Bundle {
+    pub fn manifest(&self) -> BundleManifest {
+        BundleManifest {
+            created: self.created,
+            session_key: self.session.key.clone(),
+            mapping_version: self.mapping_version,
+            led_count: self.coordinates.len(),
+            layout_hash: layout_hash(&self.coordinates),
+            record_count: self.run_race_data.len(),
+            driver_count: self.driver_info.len(),
+        }
+    }
+
+    pub fn write(&self, mut writer: impl Write) -> Result<(), AppError> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&BUNDLE_VERSION.to_le_bytes())?;
+        bincode::serialize_into(&mut writer, &self.manifest()).map_err(encode_error)?;
+        bincode::serialize_into(&mut writer, self).map_err(encode_error)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Reads and checks a whole bundle.
+    pub fn read(mut reader: impl Read) -> Result<Bundle, AppError> {
+        let manifest = read_manifest(&mut reader)?;
+        let bundle: Bundle =
+            bincode::deserialize_from(reader).map_err(|err| invalid(&err.to_string()))?;
+        if bundle.manifest() != manifest {
+            return Err(invalid("the data doesn't match the manifest"));
+        }
+        Ok(bundle)
+    }
+
+    pub fn open(path: &Path) -> Result<Bundle, AppError> {
+        Bundle::read(BufReader::new(File::open(path)?))
+    }
+
+    /// Writes the bundle to `path`, creating the directory it goes in.
+    pub fn save(&self, path: &Path) -> Result<(), AppError> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        self.write(BufWriter::new(File::create(path)?))
+    }
+
+    /// Whether the records must be mapped again for `coordinates`: they were mapped onto
+    /// another layout, or by a mapping that would put them on other LEDs.
+    pub fn needs_mapping(&self, coordinates: &[LedPoint]) -> bool {
+        self.mapping_version != MAPPING_VERSION
+            || layout_hash(&self.coordinates) != layout_hash(coordinates)
+    }
+
+    /// Maps the records onto `coordinates` again from their locations before snapping, when
+    /// `needs_mapping` says so; returns whether they were. Gaps were filled and samples
+    /// downsampled or aligned when the bundle was made, so that isn't done again.
+    pub fn map_onto(&mut self, coordinates: &[LedPoint]) -> Result<bool, AppError> {
+        if !self.needs_mapping(coordinates) {
+            return Ok(false);
+        }
+        let mut per_driver: BTreeMap<u32, Vec<LocationData>> = BTreeMap::new();
+        for run in &self.run_race_data {
+            per_driver
+                .entry(run.driver_number)
+                .or_default()
+                .push(LocationData {
+                    point: run.point,
+                    z: None,
+                    date: run.date,
+                    driver_number: run.driver_number,
+                    synthetic: false,
+                });
+        }
+        let options = MappingOptions {
+            fill_gaps: false,
+            downsample_ms: None,
+            align_ms: None,
+            smoothing: Smoothing::Off, // The bundled positions are smoothed already
+            ..self.mapping_options.clone()
+        };
+        let mut counts = PipelineStats::default();
+        let (run_race_data, mut stats) = map_drivers(
+            per_driver.into_iter().collect(),
+            coordinates,
+            &options,
+            &mut counts,
+        )?;
+        stats.pipeline = PipelineStats {
+            mapped_samples: counts.mapped_samples,
+            ..std::mem::take(&mut self.mapping_stats.pipeline)
+        };
+        info!(
+            "Mapped the {} bundled records of session {} onto the {} LEDs of the layout: {} records",
+            self.run_race_data.len(),
+            self.session.key,
+            coordinates.len(),
+            run_race_data.len()
+        );
+        self.run_race_data = run_race_data;
+        self.mapping_stats = stats;
+        self.coordinates = coordinates.to_vec();
+        self.mapping_version = MAPPING_VERSION;
+        Ok(true)
+    }
+}
+
+/// Reads the magic, version and manifest of a bundle, leaving the reader at the data.
+pub fn read_manifest(mut reader: impl Read) -> Result<BundleManifest, AppError> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid("not a .f1led file"));
+    }
+    let mut version = [0; 2];
+    reader.read_exact(&mut version)?;
+    let version = u16::from_le_bytes(version);
+    if version != BUNDLE_VERSION {
+        return Err(invalid(&format!(
+            "unsupported version {}, this build reads version {}",
+            version, BUNDLE_VERSION
+        )));
+    }
+    bincode::deserialize_from(reader).map_err(|err| invalid(&err.to_string()))
+}
+
+/// Writes `bundle` to `<dir>/<name>.f1led` in the background; the thread returns the path.
+pub fn export_bundle(
+    bundle: Bundle,
+    dir: &Path,
+    name: &str,
+) -> Result<JoinHandle<Result<PathBuf, AppError>>, AppError> {
+    let path = dir.join(format!("{}.{}", name, BUNDLE_EXTENSION));
+    let thread = thread::Builder::new()
+        .name("bundle".to_string())
+        .spawn(move || {
+            bundle.save(&path)?;
+            info!(
+                "Wrote {} records of session {} to {}",
+                bundle.run_race_data.len(),
+                bundle.session.key,
+                path.display()
+            );
+            Ok(path)
+        })?;
+    Ok(thread)
+}
+
+fn invalid(reason: &str) -> AppError {
+    AppError::Decode {
+        context: format!("session bundle: {}", reason),
+    }
+}
+
+fn encode_error(err: bincode::Error) -> AppError {
+    AppError::Decode {
+        context: format!("data for the session bundle: {}", err),
+    }
+}
+
+// The roster with the colors as RGB bytes, since egui's colors aren't serializable
+mod bundled_roster {
+    use super::*;
+    use serde::{Deserializer, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct BundledDriver {
+        number: u32,
+        name: String,
+        code: String,
+        team: String,
+        color: Rgb,
+    }
+
+    pub fn serialize<S: Serializer>(
+        roster: &[DriverInfo],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(roster.iter().map(|driver| BundledDriver {
+            number: driver.number,
+            name: driver.name.clone(),
+            code: driver.code.clone(),
+            team: driver.team.clone(),
+            color: [driver.color.r(), driver.color.g(), driver.color.b()],
+        }))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<DriverInfo>, D::Error> {
+        let roster = Vec::<BundledDriver>::deserialize(deserializer)?;
+        Ok(roster
+            .into_iter()
+            .map(|driver| DriverInfo {
+                number: driver.number,
+                name: driver.name,
+                code: driver.code,
+                team: driver.team,
+                color: egui::Color32::from_rgb(driver.color[0], driver.color[1], driver.color[2]),
+            })
+            .collect())
+    }
+}
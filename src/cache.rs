@@ -0,0 +1,310 @@
+#[cfg(feature = "native")]
+use crate::atomic_file::write_atomically;
+use crate::coverage::CachedLocations;
+use crate::data::{CarData, SessionDriver, TimeWindow};
+use crate::error::AppError;
+use crate::laps::RaceProgress;
+use crate::mapping::{MappingOptions, MappingStats, RunRace};
+use crate::notices;
+use crate::space::LedPoint;
+use crate::status;
+use log::debug;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+#[cfg(not(feature = "native"))]
+use std::collections::BTreeMap;
+#[cfg(feature = "native")]
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+#[cfg(not(feature = "native"))]
+use std::sync::Mutex;
+
+/// Directory for cached mapping results unless configured otherwise.
+pub const DEFAULT_CACHE_DIR: &str = "cache";
+
+// Bumped whenever the layout of the cached data changes, so old files are regenerated
+const CACHE_VERSION: u8 = 10;
+
+// FNV-1a, like `recorder::layout_hash`. Unlike `DefaultHasher`, whose output may change
+// between Rust releases, it gives the same keys in every build, so a toolchain update doesn't
+// orphan the cache.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+/// Hash of everything the mapped data depends on: the session, drivers and time window
+/// fetched, the layout, and the mapping parameters.
+pub fn mapping_cache_key(
+    session_key: &str,
+    driver_numbers: &[u32],
+    window: &TimeWindow,
+    coordinates: &[LedPoint],
+    options: &MappingOptions,
+) -> u64 {
+    let mut hasher = Fnv1a::default();
+    session_key.hash(&mut hasher);
+    driver_numbers.hash(&mut hasher);
+    window.start.hash(&mut hasher);
+    window.end.hash(&mut hasher);
+    for coord in coordinates {
+        coord.x.to_bits().hash(&mut hasher);
+        coord.y.to_bits().hash(&mut hasher);
+    }
+    options.snap_distance_factor.to_bits().hash(&mut hasher);
+    options.collapse_duplicates.hash(&mut hasher);
+    options.downsample_ms.hash(&mut hasher);
+    options.fill_gaps.hash(&mut hasher);
+    options.gap_min_ms.hash(&mut hasher);
+    options.gap_max_ms.hash(&mut hasher);
+    options.sample_interval_ms.hash(&mut hasher);
+    options.align_ms.hash(&mut hasher);
+    options.smoothing.hash(&mut hasher);
+    options.smoothing_window.hash(&mut hasher);
+    options.smoothing_alpha.to_bits().hash(&mut hasher);
+    options
+        .hysteresis_margin
+        .map(f64::to_bits)
+        .hash(&mut hasher);
+    options.hysteresis_ratio.map(f64::to_bits).hash(&mut hasher);
+    options.positioning.hash(&mut hasher);
+    options.pit_leds.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hash of what the laps and positions depend on: the session and drivers fetched.
+pub fn progress_cache_key(session_key: &str, driver_numbers: &[u32]) -> u64 {
+    let mut hasher = Fnv1a::default();
+    session_key.hash(&mut hasher);
+    driver_numbers.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hash of the session whose driver list is cached. Numbers go to other drivers in other
+/// sessions, so the list is never shared between them.
+pub fn drivers_cache_key(session_key: &str) -> u64 {
+    let mut hasher = Fnv1a::default();
+    session_key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hash of the session, driver and time window whose car data is cached.
+pub fn car_data_cache_key(session_key: &str, driver_number: u32, window: &TimeWindow) -> u64 {
+    let mut hasher = Fnv1a::default();
+    session_key.hash(&mut hasher);
+    driver_number.hash(&mut hasher);
+    window.start.hash(&mut hasher);
+    window.end.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hash of the session and driver whose location samples are cached. The window isn't part of
+/// it: the cached samples say what time they cover.
+pub fn locations_cache_key(session_key: &str, driver_number: u32) -> u64 {
+    let mut hasher = Fnv1a::default();
+    session_key.hash(&mut hasher);
+    driver_number.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn cache_path(dir: &Path, key: u64) -> PathBuf {
+    dir.join(format!("run_race_{:016x}.bin", key))
+}
+
+fn progress_cache_path(dir: &Path, key: u64) -> PathBuf {
+    dir.join(format!("progress_{:016x}.bin", key))
+}
+
+fn drivers_cache_path(dir: &Path, key: u64) -> PathBuf {
+    dir.join(format!("drivers_{:016x}.bin", key))
+}
+
+fn car_data_cache_path(dir: &Path, key: u64) -> PathBuf {
+    dir.join(format!("car_data_{:016x}.bin", key))
+}
+
+fn locations_cache_path(dir: &Path, key: u64) -> PathBuf {
+    dir.join(format!("locations_{:016x}.bin", key))
+}
+
+/// Loads a cached mapping result; a missing, outdated or unreadable file is a cache miss.
+pub fn load_mapping(dir: &Path, key: u64) -> Option<(Vec<RunRace>, MappingStats)> {
+    load(&cache_path(dir, key))
+}
+
+/// Writes a mapping result so the next start with the same key can skip fetch and mapping.
+pub fn store_mapping(
+    dir: &Path,
+    key: u64,
+    run_race_data: &[RunRace],
+    stats: &MappingStats,
+) -> Result<(), AppError> {
+    store(dir, &cache_path(dir, key), &(run_race_data, stats))
+}
+
+/// Loads cached laps and positions, like `load_mapping`.
+pub fn load_progress(dir: &Path, key: u64) -> Option<RaceProgress> {
+    load(&progress_cache_path(dir, key))
+}
+
+pub fn store_progress(dir: &Path, key: u64, progress: &RaceProgress) -> Result<(), AppError> {
+    store(dir, &progress_cache_path(dir, key), progress)
+}
+
+/// Loads a cached session driver list, like `load_mapping`.
+pub fn load_drivers(dir: &Path, key: u64) -> Option<Vec<SessionDriver>> {
+    load(&drivers_cache_path(dir, key))
+}
+
+pub fn store_drivers(dir: &Path, key: u64, drivers: &[SessionDriver]) -> Result<(), AppError> {
+    store(dir, &drivers_cache_path(dir, key), &drivers)
+}
+
+/// Loads a driver's cached car data, like `load_mapping`.
+pub fn load_car_data(dir: &Path, key: u64) -> Option<Vec<CarData>> {
+    load(&car_data_cache_path(dir, key))
+}
+
+pub fn store_car_data(dir: &Path, key: u64, samples: &[CarData]) -> Result<(), AppError> {
+    store(dir, &car_data_cache_path(dir, key), &samples)
+}
+
+/// Loads a driver's cached location samples, like `load_mapping`.
+pub fn load_locations(dir: &Path, key: u64) -> Option<CachedLocations> {
+    load(&locations_cache_path(dir, key))
+}
+
+pub fn store_locations(dir: &Path, key: u64, locations: &CachedLocations) -> Result<(), AppError> {
+    store(dir, &locations_cache_path(dir, key), locations)
+}
+
+/// Reads the cached mapping result back without reporting anything: `Ok(false)` when there's
+/// none of this version, an error when it doesn't deserialize.
+pub fn check_mapping(dir: &Path, key: u64) -> Result<bool, AppError> {
+    check::<(Vec<RunRace>, MappingStats)>(&cache_path(dir, key))
+}
+
+/// Reads the cached laps and positions back, like `check_mapping`.
+pub fn check_progress(dir: &Path, key: u64) -> Result<bool, AppError> {
+    check::<RaceProgress>(&progress_cache_path(dir, key))
+}
+
+/// Reads the cached driver list back, like `check_mapping`.
+pub fn check_drivers(dir: &Path, key: u64) -> Result<bool, AppError> {
+    check::<Vec<SessionDriver>>(&drivers_cache_path(dir, key))
+}
+
+fn check<T: DeserializeOwned>(path: &Path) -> Result<bool, AppError> {
+    let bytes = match read_file(path) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err.into()),
+    };
+    match bytes.split_first() {
+        Some((&CACHE_VERSION, payload)) => bincode::deserialize::<T>(payload)
+            .map(|_| true)
+            .map_err(|err| AppError::Cache {
+                reason: format!("{} is unreadable: {}", path.display(), err),
+            }),
+        _ => Ok(false),
+    }
+}
+
+fn load<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let bytes = read_file(path).ok()?;
+
+    match bytes.split_first() {
+        Some((&CACHE_VERSION, payload)) => match bincode::deserialize(payload) {
+            Ok(cached) => {
+                debug!("Loaded cached data from {}", path.display());
+                status::cache_used(&format!("loaded {}", path.display()));
+                Some(cached)
+            }
+            Err(err) => {
+                let reason = format!("ignored the unreadable {}: {}", path.display(), err);
+                status::cache_failed(&reason);
+                notices::report(&AppError::Cache { reason });
+                None
+            }
+        },
+        _ => {
+            debug!("Ignoring cache {} from another version", path.display());
+            None
+        }
+    }
+}
+
+fn store<T: Serialize>(dir: &Path, path: &Path, data: &T) -> Result<(), AppError> {
+    let result = write(dir, path, data);
+    match &result {
+        Ok(()) => status::cache_used(&format!("wrote {}", path.display())),
+        Err(err) => status::cache_failed(&err.to_string()),
+    }
+    result
+}
+
+// Writes `data` behind the cache version
+fn write<T: Serialize>(dir: &Path, path: &Path, data: &T) -> Result<(), AppError> {
+    let payload = bincode::serialize(data).map_err(|err| AppError::Decode {
+        context: format!("data for the cache: {}", err),
+    })?;
+
+    let mut bytes = Vec::with_capacity(payload.len() + 1);
+    bytes.push(CACHE_VERSION);
+    bytes.extend(payload);
+
+    write_file(dir, path, &bytes)?;
+    Ok(())
+}
+
+// Without the native feature, as in the browser, there are no files to keep the cache in, so
+// it lasts as long as the page
+#[cfg(not(feature = "native"))]
+static IN_MEMORY: Mutex<BTreeMap<PathBuf, Vec<u8>>> = Mutex::new(BTreeMap::new());
+
+#[cfg(feature = "native")]
+fn read_file(path: &Path) -> io::Result<Vec<u8>> {
+    fs::read(path)
+}
+
+#[cfg(feature = "native")]
+fn write_file(dir: &Path, path: &Path, bytes: &[u8]) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    write_atomically(path, bytes)
+}
+
+#[cfg(not(feature = "native"))]
+fn read_file(path: &Path) -> io::Result<Vec<u8>> {
+    let files = IN_MEMORY.lock().unwrap();
+    files
+        .get(path)
+        .cloned()
+        .ok_or_else(|| io::ErrorKind::NotFound.into())
+}
+
+#[cfg(not(feature = "native"))]
+fn write_file(_dir: &Path, path: &Path, bytes: &[u8]) -> io::Result<()> {
+    IN_MEMORY
+        .lock()
+        .unwrap()
+        .insert(path.to_path_buf(), bytes.to_vec());
+    Ok(())
+}
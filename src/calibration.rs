@@ -0,0 +1,133 @@
+use crate::error::AppError;
+use crate::simulation::Rgb;
+use eframe::egui;
+use log::warn;
+use serde::Deserialize;
+use std::path::Path;
+
+#[derive(Debug, Deserialize)]
+struct CalibrationEntry {
+    led: String, // LED label as used in the layout, e.g. "U12"
+    scale: f32,
+    r: Option<f32>,
+    g: Option<f32>,
+    b: Option<f32>,
+}
+
+/// Brightness correction for one physical LED.
+#[derive(Debug, Clone, Copy)]
+pub struct LedCalibration {
+    pub scale: f32,
+    pub channels: [f32; 3], // Per-channel multipliers for red, green and blue
+}
+
+impl Default for LedCalibration {
+    fn default() -> Self {
+        LedCalibration {
+            scale: 1.0,
+            channels: [1.0; 3],
+        }
+    }
+}
+
+impl LedCalibration {
+    /// Scales `color` by this LED's correction factors.
+    pub fn apply(&self, color: egui::Color32) -> egui::Color32 {
+        let channel = |value: u8, multiplier: f32| {
+            (value as f32 * self.scale * multiplier)
+                .round()
+                .clamp(0.0, 255.0) as u8
+        };
+        egui::Color32::from_rgb(
+            channel(color.r(), self.channels[0]),
+            channel(color.g(), self.channels[1]),
+            channel(color.b(), self.channels[2]),
+        )
+    }
+}
+
+/// Scales every channel of `color` by the global brightness.
+pub fn apply_brightness(color: egui::Color32, brightness: f32) -> egui::Color32 {
+    egui::Color32::from_rgb(
+        (color.r() as f32 * brightness).round() as u8,
+        (color.g() as f32 * brightness).round() as u8,
+        (color.b() as f32 * brightness).round() as u8,
+    )
+}
+
+/// Applies the global brightness and then each LED's calibration to a frame of LED colors.
+pub fn correct_frame(
+    leds: &[Option<Rgb>],
+    brightness: f32,
+    calibration: &[LedCalibration],
+) -> Vec<Option<Rgb>> {
+    leds.iter()
+        .enumerate()
+        .map(|(index, color)| {
+            color.map(|[r, g, b]| {
+                let color = apply_brightness(egui::Color32::from_rgb(r, g, b), brightness);
+                let color = calibration
+                    .get(index)
+                    .copied()
+                    .unwrap_or_default()
+                    .apply(color);
+                [color.r(), color.g(), color.b()]
+            })
+        })
+        .collect()
+}
+
+/// Optional per-LED calibration tables, checked in this order.
+pub const CALIBRATION_FILES: [&str; 2] = ["led_calibration.csv", "led_calibration.json"];
+
+/// Reads a CSV or JSON calibration table; LEDs without an entry keep the neutral calibration.
+pub fn read_calibration(path: &Path, led_count: usize) -> Result<Vec<LedCalibration>, AppError> {
+    let decode_error = |err: &dyn std::fmt::Display| AppError::Decode {
+        context: format!("calibration file {}: {}", path.display(), err),
+    };
+    let entries: Vec<CalibrationEntry> = if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_reader(std::fs::File::open(path)?).map_err(|err| decode_error(&err))?
+    } else {
+        csv::Reader::from_path(path)
+            .map_err(|err| decode_error(&err))?
+            .deserialize()
+            .collect::<Result<_, _>>()
+            .map_err(|err| decode_error(&err))?
+    };
+
+    let mut calibration = vec![LedCalibration::default(); led_count];
+    for entry in entries {
+        let index = match entry
+            .led
+            .trim()
+            .strip_prefix('U')
+            .and_then(|number| number.parse::<usize>().ok())
+        {
+            Some(number) if (1..=led_count).contains(&number) => number - 1,
+            _ => {
+                warn!("Ignoring calibration for unknown LED {:?}", entry.led);
+                continue;
+            }
+        };
+
+        let clamp = |name: &str, value: f32| {
+            if !(0.0..=1.0).contains(&value) {
+                warn!(
+                    "Calibration {} for LED {} is out of range, clamping to 0.0-1.0",
+                    name, entry.led
+                );
+            }
+            value.clamp(0.0, 1.0)
+        };
+        calibration[index] = LedCalibration {
+            scale: clamp("scale", entry.scale),
+            channels: [
+                clamp("r", entry.r.unwrap_or(1.0)),
+                clamp("g", entry.g.unwrap_or(1.0)),
+                clamp("b", entry.b.unwrap_or(1.0)),
+            ],
+        };
+    }
+
+    Ok(calibration)
+}
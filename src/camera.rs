@@ -0,0 +1,94 @@
+use crate::space::LedPoint;
+use crate::viewport::{Bounds, Camera};
+
+/// Most zoomed in the track view goes.
+pub const MAX_ZOOM: f64 = 20.0;
+
+// Seconds the view takes to cover 95% of the way to where it's headed
+const EASE_SECS: f64 = 0.6;
+
+/// Moves `camera` toward `target` for a frame of `dt` seconds. The share covered depends only
+/// on the time passed, so the view moves the same at any frame rate.
+pub fn ease(camera: Camera, target: Camera, dt: f64) -> Camera {
+    // e^-3 is about 5% left
+    let share = 1.0 - (-3.0 * dt.max(0.0) / EASE_SECS).exp();
+    let toward = |from: f64, to: f64| from + (to - from) * share;
+    Camera {
+        center: LedPoint::new(
+            toward(camera.center.x, target.center.x),
+            toward(camera.center.y, target.center.y),
+        ),
+        zoom: toward(camera.zoom, target.zoom),
+    }
+}
+
+/// The track view's camera: zoomed and panned by hand, or following the focused driver at
+/// `follow_zoom`. Panning by hand breaks the follow until it's turned on again.
+#[derive(Debug, Clone)]
+pub struct FollowCamera {
+    camera: Camera,
+    following: bool,
+    pub follow_zoom: f64,
+}
+
+impl FollowCamera {
+    pub fn new(bounds: &Bounds) -> FollowCamera {
+        FollowCamera {
+            camera: Camera::full(bounds),
+            following: false,
+            follow_zoom: 4.0,
+        }
+    }
+
+    pub fn camera(&self) -> Camera {
+        self.camera
+    }
+
+    pub fn is_following(&self) -> bool {
+        self.following
+    }
+
+    pub fn set_following(&mut self, following: bool) {
+        self.following = following;
+    }
+
+    /// Back to the whole layout, not following anyone.
+    pub fn reset(&mut self, bounds: &Bounds) {
+        self.camera = Camera::full(bounds);
+        self.following = false;
+    }
+
+    /// Moves the view by `dx` and `dy` in layout coordinates, which stops following.
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        self.camera.center.x += dx;
+        self.camera.center.y += dy;
+        self.following = false;
+    }
+
+    /// Zooms in by `factor`, or out below 1, between the whole layout and `MAX_ZOOM`. While
+    /// following, it's the zoom kept on the driver.
+    pub fn zoom_by(&mut self, factor: f64) {
+        if self.following {
+            self.follow_zoom = (self.follow_zoom * factor).clamp(1.0, MAX_ZOOM);
+        } else {
+            self.camera.zoom = (self.camera.zoom * factor).clamp(1.0, MAX_ZOOM);
+        }
+    }
+
+    /// Moves the view on by a frame of `dt` seconds while following: toward `target`, the
+    /// focused driver's LED, or back out to the whole layout without one, e.g. once their data
+    /// stopped.
+    pub fn update(&mut self, target: Option<LedPoint>, bounds: &Bounds, dt: f64) {
+        if !self.following {
+            return;
+        }
+        let goal = match target {
+            Some(center) => Camera {
+                center,
+                zoom: self.follow_zoom,
+            },
+            None => Camera::full(bounds),
+        };
+        self.camera = ease(self.camera, goal, dt);
+    }
+}
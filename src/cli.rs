@@ -0,0 +1,249 @@
+use crate::schedule::parse_start_time;
+use crate::test_pattern::TestPattern;
+use chrono::{DateTime, Local};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+/// A file format the `export` command writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DataFormat {
+    Parquet,
+    Csv,
+    Gif, // A clip of the playback, as the window's export renders it
+}
+
+/// What the program does with the session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Command {
+    #[default]
+    Simulate,
+    Fetch,
+    Map,
+    Export(DataFormat),
+}
+
+impl Command {
+    pub fn name(self) -> &'static str {
+        match self {
+            Command::Simulate => "simulate",
+            Command::Fetch => "fetch",
+            Command::Map => "map",
+            Command::Export(_) => "export",
+        }
+    }
+}
+
+/// Command line flags; every setting given here overrides the config file.
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    pub config: Option<PathBuf>,
+    pub session_key: Option<String>,
+    pub speed: Option<f64>,
+    pub brightness: Option<f32>,
+    pub cache_dir: Option<PathBuf>,
+    pub record: Option<PathBuf>,
+    pub play: Option<PathBuf>,   // Skips fetching and mapping entirely
+    pub bundle: Option<PathBuf>, // Skips fetching, and mapping unless made for another layout
+    pub headless: bool,
+    pub tui: bool, // Headless, with the track drawn in the terminal
+    pub start_at: Option<DateTime<Local>>,
+    pub test_pattern: Option<TestPattern>, // Skips fetching and mapping too
+    pub load_snapshot: Option<PathBuf>,    // Takes the session and settings of the snapshot
+    pub command: Command,
+    pub output: Option<PathBuf>,
+    pub check: bool, // Checks everything a run needs instead of running
+    pub verbose: bool,
+}
+
+/// Plays an F1 session on an LED model of the circuit.
+#[derive(Parser)]
+#[command(name = "f1-led-circuit-master-simulation", no_binary_name = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<CommandArgs>,
+
+    /// Config file (default: config.toml if present)
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// OpenF1 session key
+    #[arg(long = "session", global = true, value_name = "KEY")]
+    session_key: Option<String>,
+    /// Directory for cached data
+    #[arg(long, global = true, value_name = "PATH")]
+    cache_dir: Option<PathBuf>,
+    /// Initial playback speed, also of a GIF export
+    #[arg(long, global = true, value_name = "N")]
+    speed: Option<f64>,
+    /// Initial global brightness, also of a GIF export
+    #[arg(long, global = true, value_name = "0-1")]
+    brightness: Option<f32>,
+    /// Take the session saved in a .f1led bundle, without fetching it; map writes it again
+    /// mapped onto the layout
+    #[arg(long, global = true, value_name = "PATH", conflicts_with_all = ["play", "test_pattern"])]
+    bundle: Option<PathBuf>,
+    /// Check the config, layout, session, API, cache, bundle and LED outputs without playing
+    /// anything, print a report and fail if any check did
+    #[arg(long, global = true)]
+    check: bool,
+    /// Log debug output (RUST_LOG takes precedence)
+    #[arg(short, long, global = true)]
+    verbose: bool,
+
+    /// Record the LED frames to a .ledrec file
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        help_heading = "Simulate options"
+    )]
+    record: Option<PathBuf>,
+    /// Play a .ledrec recording instead of the race
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        help_heading = "Simulate options"
+    )]
+    play: Option<PathBuf>,
+    /// Play the race without a window
+    #[arg(long, global = true, help_heading = "Simulate options")]
+    headless: bool,
+    /// Play the race in the terminal instead of a window, logging to f1-led-circuit.log: space
+    /// plays and pauses, + and - change the speed and q quits
+    #[arg(
+        long,
+        global = true,
+        help_heading = "Simulate options",
+        conflicts_with_all = ["test_pattern", "start_at"]
+    )]
+    tui: bool,
+    /// Start playing at 1x at a local time, today like 15:00 or on a date like 2024-05-26 15:00;
+    /// a time already past starts right away
+    #[arg(
+        long,
+        global = true,
+        value_name = "TIME",
+        value_parser = parse_start_at,
+        help_heading = "Simulate options",
+        conflicts_with = "test_pattern"
+    )]
+    start_at: Option<DateTime<Local>>,
+    /// Light the board with a test pattern instead of the race, without fetching anything: red,
+    /// green, blue, white, chase, rainbow or index_blink
+    #[arg(
+        long,
+        global = true,
+        value_name = "P",
+        help_heading = "Simulate options",
+        conflicts_with = "play"
+    )]
+    test_pattern: Option<TestPattern>,
+    /// Play the session of a state snapshot from the cache, paused where the snapshot was taken
+    /// and set up as it was
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        help_heading = "Simulate options",
+        conflicts_with_all = ["play", "bundle", "session_key", "start_at", "test_pattern"]
+    )]
+    load_snapshot: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum CommandArgs {
+    /// Play the session in a window, or with --headless or --tui without one (the default)
+    Simulate,
+    /// Download the session's data into the cache without playing it
+    Fetch,
+    /// Map the session onto the layout and write it as a .f1led bundle
+    Map {
+        /// File written (default: <bundle.dir>/<session>.f1led)
+        #[arg(long, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+    /// Write the mapped race data of the session to a file instead of playing it
+    Export {
+        /// Format of the export
+        #[arg(long, value_enum, default_value_t = DataFormat::Parquet)]
+        format: DataFormat,
+        /// File written (default: parquet.path of the config for Parquet, <session>.csv or
+        /// <session>.gif otherwise)
+        #[arg(long, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+}
+
+impl CliArgs {
+    /// Parses the arguments after the program name. `--help` comes back as an error too, one
+    /// that prints to stdout rather than stderr.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<CliArgs, clap::Error> {
+        let cli = Cli::try_parse_from(args)?;
+        let (command, output) = match cli.command {
+            None | Some(CommandArgs::Simulate) => (Command::Simulate, None),
+            Some(CommandArgs::Fetch) => (Command::Fetch, None),
+            Some(CommandArgs::Map { output }) => (Command::Map, output),
+            Some(CommandArgs::Export { format, output }) => (Command::Export(format), output),
+        };
+        let parsed = CliArgs {
+            config: cli.config,
+            session_key: cli.session_key,
+            speed: cli.speed,
+            brightness: cli.brightness,
+            cache_dir: cli.cache_dir,
+            record: cli.record,
+            play: cli.play,
+            bundle: cli.bundle,
+            // The terminal takes the place of the window
+            headless: cli.headless || cli.tui,
+            tui: cli.tui,
+            start_at: cli.start_at,
+            test_pattern: cli.test_pattern,
+            load_snapshot: cli.load_snapshot,
+            command,
+            output,
+            check: cli.check,
+            verbose: cli.verbose,
+        };
+
+        let conflict = |message: String| Cli::command().error(ErrorKind::ArgumentConflict, message);
+        if command != Command::Simulate {
+            let name = command.name();
+            let playback_flag = [
+                (parsed.record.is_some(), "--record"),
+                (parsed.play.is_some(), "--play"),
+                (cli.headless, "--headless"),
+                (parsed.tui, "--tui"),
+                (parsed.start_at.is_some(), "--start-at"),
+                (parsed.test_pattern.is_some(), "--test-pattern"),
+                (parsed.load_snapshot.is_some(), "--load-snapshot"),
+            ]
+            .into_iter()
+            .find_map(|(given, flag)| given.then_some(flag));
+            if let Some(flag) = playback_flag {
+                return Err(conflict(format!(
+                    "{} goes with simulate, not {}",
+                    flag, name
+                )));
+            }
+            if parsed.check {
+                return Err(conflict(format!(
+                    "--check goes without the {} command",
+                    name
+                )));
+            }
+        }
+        if command == Command::Fetch && parsed.bundle.is_some() {
+            return Err(conflict(
+                "fetch gets the session from the API, not from --bundle".into(),
+            ));
+        }
+
+        Ok(parsed)
+    }
+}
+
+fn parse_start_at(value: &str) -> Result<DateTime<Local>, String> {
+    parse_start_time(value, Local::now().date_naive())
+}
@@ -0,0 +1,105 @@
+use eframe::egui::Color32;
+use std::fmt;
+
+/// Why a text isn't a hex color.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColorParseError {
+    Length { text: String, digits: usize }, // Not 6 or 8 digits
+    Digit { text: String, digit: char },
+}
+
+impl fmt::Display for ColorParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorParseError::Length { text, digits } => write!(
+                f,
+                "\"{}\" has {} digits, not 6 or 8 like \"#FF8000\"",
+                text, digits
+            ),
+            ColorParseError::Digit { text, digit } => {
+                write!(f, "\"{}\" has '{}', which isn't a hex digit", text, digit)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ColorParseError {}
+
+/// Parses `RRGGBB`, `#RRGGBB` or `#RRGGBBAA` in either case, around any whitespace. The alpha
+/// is unmultiplied, as usual in hex colors.
+pub fn parse_hex(text: &str) -> Result<Color32, ColorParseError> {
+    let hex = text.trim();
+    let hex = hex.strip_prefix('#').unwrap_or(hex);
+    if let Some(digit) = hex.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(ColorParseError::Digit {
+            text: text.to_string(),
+            digit,
+        });
+    }
+    if hex.len() != 6 && hex.len() != 8 {
+        return Err(ColorParseError::Length {
+            text: text.to_string(),
+            digits: hex.len(),
+        });
+    }
+    // Only ASCII digits are left, so every two bytes are a channel
+    let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).unwrap_or(0);
+    let alpha = if hex.len() == 8 { channel(6) } else { 255 };
+    Ok(Color32::from_rgba_unmultiplied(
+        channel(0),
+        channel(2),
+        channel(4),
+        alpha,
+    ))
+}
+
+/// `#RRGGBB` in capitals, with the unmultiplied alpha after it unless the color is opaque.
+pub fn to_hex(color: Color32) -> String {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    if a == 255 {
+        format!("#{:02X}{:02X}{:02X}", r, g, b)
+    } else {
+        format!("#{:02X}{:02X}{:02X}{:02X}", r, g, b, a)
+    }
+}
+
+/// Serde adapter writing a `Color32` as a hex string, for `#[serde(with = "color::hex")]`.
+pub mod hex {
+    use super::{parse_hex, to_hex};
+    use eframe::egui::Color32;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Color32, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_hex(*color))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color32, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        parse_hex(&text).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Like `hex`, for an optional color.
+pub mod hex_option {
+    use super::{parse_hex, to_hex};
+    use eframe::egui::Color32;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        color: &Option<Color32>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match color {
+            Some(color) => serializer.serialize_some(&to_hex(*color)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Color32>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|text| parse_hex(&text).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
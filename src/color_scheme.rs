@@ -0,0 +1,177 @@
+use crate::laps::RaceProgress;
+use crate::simulation::Rgb;
+use chrono::{DateTime, Utc};
+use eframe::egui::Color32;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// The podium, then a gradient from the first to the last of the rest of the field
+const PODIUM: [Color32; 3] = [
+    Color32::from_rgb(255, 200, 0),
+    Color32::from_rgb(200, 200, 210),
+    Color32::from_rgb(205, 120, 50),
+];
+const FIELD_FRONT: Color32 = Color32::from_rgb(0, 200, 255);
+const FIELD_BACK: Color32 = Color32::from_rgb(70, 0, 160);
+const FIELD_SIZE: u32 = 20; // Positions further back get the color of the last
+
+// Drivers a scheme has nothing to go by for, e.g. before their first position is known
+const UNKNOWN: Color32 = Color32::from_rgb(90, 90, 90);
+
+/// Data a color scheme needs besides the roster colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemeData {
+    Positions,
+    Stints,
+}
+
+impl SchemeData {
+    pub fn label(self) -> &'static str {
+        match self {
+            SchemeData::Positions => "race positions",
+            SchemeData::Stints => "tyre stints",
+        }
+    }
+}
+
+/// What a scheme colors the drivers by: the roster colors, and the laps, positions and stints
+/// at the race time when they were fetched.
+pub struct SchemeContext<'a> {
+    pub team_colors: &'a HashMap<u32, Rgb>,
+    pub progress: Option<&'a RaceProgress>,
+    pub time: Option<DateTime<Utc>>,
+}
+
+impl SchemeContext<'_> {
+    pub fn has(&self, data: SchemeData) -> bool {
+        self.progress.is_some_and(|progress| match data {
+            SchemeData::Positions => progress.has_positions(),
+            SchemeData::Stints => progress.has_stints(),
+        })
+    }
+}
+
+/// How the drivers' LEDs and legend swatches are colored.
+pub trait ColorScheme {
+    /// Data the scheme can't do without; it's only offered when the session has it.
+    fn needs(&self) -> Option<SchemeData> {
+        None
+    }
+
+    fn color_for(&self, driver: u32, context: &SchemeContext) -> Color32;
+}
+
+/// The roster's team colors; drivers without one are white.
+pub struct TeamColors;
+
+impl ColorScheme for TeamColors {
+    fn color_for(&self, driver: u32, context: &SchemeContext) -> Color32 {
+        let [r, g, b] = context
+            .team_colors
+            .get(&driver)
+            .copied()
+            .unwrap_or([255, 255, 255]);
+        Color32::from_rgb(r, g, b)
+    }
+}
+
+/// Gold, silver and bronze for the podium and a gradient from light to dark blue for the rest.
+pub struct PositionColors;
+
+impl ColorScheme for PositionColors {
+    fn needs(&self) -> Option<SchemeData> {
+        Some(SchemeData::Positions)
+    }
+
+    fn color_for(&self, driver: u32, context: &SchemeContext) -> Color32 {
+        let position = context
+            .progress
+            .zip(context.time)
+            .and_then(|(progress, time)| progress.position_at(driver, time));
+        match position {
+            Some(position @ 1..=3) => PODIUM[position as usize - 1],
+            Some(position) => {
+                let share = (position.min(FIELD_SIZE) - 4) as f32 / (FIELD_SIZE - 4) as f32;
+                blend(FIELD_FRONT, FIELD_BACK, share)
+            }
+            None => UNKNOWN,
+        }
+    }
+}
+
+fn blend(from: Color32, to: Color32, share: f32) -> Color32 {
+    let channel =
+        |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * share).round() as u8;
+    Color32::from_rgb(
+        channel(from.r(), to.r()),
+        channel(from.g(), to.g()),
+        channel(from.b(), to.b()),
+    )
+}
+
+/// The colors of the tyre compounds as on the tyre walls.
+pub struct TyreColors;
+
+impl ColorScheme for TyreColors {
+    fn needs(&self) -> Option<SchemeData> {
+        Some(SchemeData::Stints)
+    }
+
+    fn color_for(&self, driver: u32, context: &SchemeContext) -> Color32 {
+        let compound = context
+            .progress
+            .zip(context.time)
+            .and_then(|(progress, time)| progress.compound_at(driver, time));
+        compound_color(compound)
+    }
+}
+
+/// The color of a tyre compound as on the tyre walls, grey for an unknown one.
+pub fn compound_color(compound: Option<&str>) -> Color32 {
+    match compound {
+        Some("SOFT") => Color32::from_rgb(230, 30, 30),
+        Some("MEDIUM") => Color32::from_rgb(255, 210, 0),
+        Some("HARD") => Color32::from_rgb(240, 240, 240),
+        Some("INTERMEDIATE") => Color32::from_rgb(40, 180, 60),
+        Some("WET") => Color32::from_rgb(0, 100, 230),
+        _ => UNKNOWN,
+    }
+}
+
+/// The color schemes to pick from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorSchemeKind {
+    #[default]
+    Team,
+    Position,
+    Tyre,
+}
+
+impl ColorSchemeKind {
+    pub const ALL: [ColorSchemeKind; 3] = [
+        ColorSchemeKind::Team,
+        ColorSchemeKind::Position,
+        ColorSchemeKind::Tyre,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ColorSchemeKind::Team => "Team",
+            ColorSchemeKind::Position => "Position",
+            ColorSchemeKind::Tyre => "Tyre",
+        }
+    }
+
+    pub fn scheme(self) -> &'static dyn ColorScheme {
+        match self {
+            ColorSchemeKind::Team => &TeamColors,
+            ColorSchemeKind::Position => &PositionColors,
+            ColorSchemeKind::Tyre => &TyreColors,
+        }
+    }
+
+    /// Why the scheme can't be used with `context`, if it can't.
+    pub fn missing(self, context: &SchemeContext) -> Option<SchemeData> {
+        self.scheme().needs().filter(|&data| !context.has(data))
+    }
+}
@@ -0,0 +1,473 @@
+use crate::audio::AudioConfig;
+use crate::battles::BattleConfig;
+use crate::blue_flags::BlueFlagConfig;
+use crate::bundle::BundleConfig;
+use crate::cache::DEFAULT_CACHE_DIR;
+use crate::control::ControlConfig;
+use crate::data::{TimeWindow, SESSION_KEY};
+use crate::dmx::DmxConfig;
+use crate::driver_info::RosterConfig;
+#[cfg(feature = "native")]
+use crate::enttec::EnttecConfig;
+use crate::error::AppError;
+use crate::export::ScreenshotConfig;
+use crate::flags::FlagPanelConfig;
+use crate::formation::FormationConfig;
+use crate::ghost::GhostConfig;
+use crate::heatmap::HeatmapConfig;
+use crate::input::{GamepadConfig, GpioConfig};
+use crate::lap_chart::LapChartConfig;
+use crate::led_decay::LedDecayConfig;
+use crate::led_dwell::LedDwellConfig;
+use crate::led_style::LedStyle;
+use crate::mapping::MappingOptions;
+use crate::markers::MarkerConfig;
+use crate::matrix::MatrixConfig;
+use crate::metrics::MetricsConfig;
+#[cfg(feature = "native")]
+use crate::mqtt::MqttConfig;
+use crate::night::NightSchedule;
+use crate::notices;
+use crate::occupancy::OccupancyConfig;
+use crate::osc::OscConfig;
+use crate::overtakes::OvertakeConfig;
+#[cfg(feature = "native")]
+use crate::parquet_export::ParquetConfig;
+use crate::pedals::PedalConfig;
+use crate::playlist::PlaylistConfig;
+use crate::qualifying::QualifyingConfig;
+use crate::recorder::RecorderConfig;
+use crate::retirements::RetirementConfig;
+use crate::sectors::SectorConfig;
+use crate::segments::LayoutConfig;
+use crate::sink::OutputConfig;
+use crate::split::SplitConfig;
+use crate::summary::SummaryConfig;
+use crate::sync::SyncConfig;
+use crate::test_pattern::TestPatternConfig;
+use crate::timeline::SpeedConfig;
+use crate::trains::TrainConfig;
+use crate::watchdog::WatchdogConfig;
+#[cfg(feature = "native")]
+use crate::websocket::WebSocketConfig;
+#[cfg(feature = "native")]
+use crate::wled::WledConfig;
+use chrono::{DateTime, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Config file loaded from the working directory when `--config` isn't given.
+pub const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+// Gamma of a WS2812 strip without one in `[outputs.ws2812]`; its LEDs look washed out at 1.0
+const WS2812_GAMMA: f32 = 2.8;
+
+/// Environment variable with the API's bearer token, used instead of `api.bearer_token`.
+pub const API_TOKEN_ENV: &str = "OPENF1_TOKEN";
+
+/// All runtime settings. Every field has a default, so an empty file is a valid config. The
+/// settings of outputs and exports only the native build has are left out of the web one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub session: SessionConfig,
+    pub playback: PlaybackConfig,
+    pub display: DisplayConfig,
+    pub mapping: MappingOptions,
+    pub cache: CacheConfig,
+    pub api: ApiConfig,
+    pub calibration: CalibrationConfig,
+    pub ws2812: Ws2812Config,
+    pub dmx: DmxConfig,
+    #[cfg(feature = "native")]
+    pub enttec: EnttecConfig,
+    #[cfg(feature = "native")]
+    pub wled: WledConfig,
+    pub matrix: MatrixConfig,
+    pub layout: LayoutConfig,
+    #[cfg(feature = "native")]
+    pub mqtt: MqttConfig,
+    pub osc: OscConfig,
+    #[cfg(feature = "native")]
+    pub websocket: WebSocketConfig,
+    pub control: ControlConfig,
+    pub metrics: MetricsConfig,
+    pub watchdog: WatchdogConfig,
+    pub gamepad: GamepadConfig,
+    pub gpio: GpioConfig,
+    pub recorder: RecorderConfig,
+    pub screenshot: ScreenshotConfig,
+    pub occupancy: OccupancyConfig,
+    pub lap_chart: LapChartConfig,
+    pub speed: SpeedConfig,
+    pub battles: BattleConfig,
+    pub trains: TrainConfig,
+    pub blue_flags: BlueFlagConfig,
+    pub qualifying: QualifyingConfig,
+    pub markers: MarkerConfig,
+    pub led_decay: LedDecayConfig,
+    pub led_dwell: LedDwellConfig,
+    pub pedals: PedalConfig,
+    pub playlist: PlaylistConfig,
+    pub overtakes: OvertakeConfig,
+    pub audio: AudioConfig,
+    pub flag_panels: FlagPanelConfig,
+    pub formation: FormationConfig,
+    pub ghost: GhostConfig,
+    pub split: SplitConfig,
+    pub heatmap: HeatmapConfig,
+    #[cfg(feature = "native")]
+    pub parquet: ParquetConfig,
+    pub bundle: BundleConfig,
+    pub test_patterns: TestPatternConfig,
+    pub sectors: SectorConfig,
+    pub retirements: RetirementConfig,
+    pub summary: SummaryConfig,
+    pub sync: SyncConfig,
+    pub roster: RosterConfig,
+    pub colors: BTreeMap<String, String>, // Driver number to "#RRGGBB", over the roster colors
+    pub outputs: BTreeMap<String, OutputConfig>, // Settings of the LED outputs, by output name
+    #[serde(skip)]
+    explicit: HashSet<String>, // Dotted keys set in the file or on the command line
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionConfig {
+    pub key: String,
+    pub name: Option<String>, // For file names, e.g. "zandvoort-2023"; defaults to the key
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub drivers: Option<Vec<u32>>, // Defaults to the whole roster
+    pub fetch_laps: bool,          // Laps and race positions, for the lap chart and overtakes
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        SessionConfig {
+            key: SESSION_KEY.to_string(),
+            name: None,
+            start_time: None,
+            end_time: None,
+            drivers: None,
+            fetch_laps: true,
+        }
+    }
+}
+
+impl SessionConfig {
+    /// Name of the session in file names.
+    pub fn label(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => format!("session-{}", self.key),
+        }
+    }
+
+    pub fn window(&self) -> TimeWindow {
+        TimeWindow {
+            start: self.start_time,
+            end: self.end_time,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlaybackConfig {
+    pub speed: f64,
+    pub min_speed: f64,
+    pub max_speed: f64,
+    pub stop_confirmation: StopConfirmation,
+    pub pause_when_unfocused: bool, // Ignored while frames also go to hardware or the network
+    pub focus_resume_delay_secs: f64, // After focus returns, before playing on
+}
+
+impl Default for PlaybackConfig {
+    fn default() -> Self {
+        PlaybackConfig {
+            speed: 1.0,
+            min_speed: 0.5,
+            max_speed: 30.0,
+            stop_confirmation: StopConfirmation::Dialog,
+            pause_when_unfocused: false,
+            focus_resume_delay_secs: 2.0,
+        }
+    }
+}
+
+/// What it takes for STOP in the window to throw away the playback progress.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopConfirmation {
+    /// A popup asks first.
+    #[default]
+    Dialog,
+    /// The first click arms the button, a second one soon after stops.
+    TwoStage,
+    /// A single click, e.g. for kiosks.
+    None,
+}
+
+impl StopConfirmation {
+    pub const ALL: [StopConfirmation; 3] = [
+        StopConfirmation::Dialog,
+        StopConfirmation::TwoStage,
+        StopConfirmation::None,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            StopConfirmation::Dialog => "Ask first",
+            StopConfirmation::TwoStage => "Click twice",
+            StopConfirmation::None => "Single click",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplayConfig {
+    pub led_size: f32,
+    pub brightness: f32,
+    pub style: LedStyle,
+    pub glow_radius: f32, // How far the glow reaches beyond a lit LED, in LED sizes
+    pub elevation_shading: bool, // Unlit LEDs lighter the higher they are, if the session has z
+    pub night_start: Option<NaiveTime>, // Local time the outputs dim, e.g. "22:00"
+    pub night_end: Option<NaiveTime>,
+    pub night_brightness: f32, // Scales the brightness between night_start and night_end
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        DisplayConfig {
+            led_size: 20.0,
+            brightness: 1.0,
+            style: LedStyle::Squares,
+            glow_radius: 1.0,
+            elevation_shading: false,
+            night_start: None,
+            night_end: None,
+            night_brightness: 0.3,
+        }
+    }
+}
+
+impl DisplayConfig {
+    /// The dimming schedule, when both ends of the night are set.
+    pub fn night_schedule(&self) -> Option<NightSchedule> {
+        Some(NightSchedule {
+            start: self.night_start?,
+            end: self.night_end?,
+            brightness: self.night_brightness,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub dir: PathBuf,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            dir: PathBuf::from(DEFAULT_CACHE_DIR),
+        }
+    }
+}
+
+/// A value kept out of logs and the diagnostics, such as a token: it prints as `<redacted>`.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Secret {
+        Secret(value.into())
+    }
+
+    /// The value itself, for where it's sent.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiConfig {
+    pub base_url: String,
+    pub timeout_secs: u64,
+    pub retries: u32,             // Extra attempts after a network error, 5xx or 429
+    pub retry_delay_ms: u64,      // Delay before the first retry, doubled for each further one
+    pub session_margin_secs: i64, // Fetched before and after the session when the window lacks bounds
+    pub headers: BTreeMap<String, Secret>, // Sent with every request, e.g. the key of a proxy
+    pub bearer_token: Option<Secret>, // Sent as "Authorization: Bearer"; `API_TOKEN_ENV` wins
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        ApiConfig {
+            base_url: "https://api.openf1.org/v1".to_string(),
+            timeout_secs: 30,
+            retries: 3,
+            retry_delay_ms: 500,
+            session_margin_secs: 60,
+            headers: BTreeMap::new(),
+            bearer_token: None,
+        }
+    }
+}
+
+impl ApiConfig {
+    /// The bearer token to send: from `API_TOKEN_ENV` when set, else from the config.
+    pub fn token(&self) -> Option<Secret> {
+        match std::env::var(API_TOKEN_ENV) {
+            Ok(token) if !token.is_empty() => Some(Secret::new(token)),
+            _ => self.bearer_token.clone(),
+        }
+    }
+
+    /// Where requests go and what they carry, with the header names but none of the values.
+    pub fn describe(&self) -> String {
+        let mut extras: Vec<String> = self.headers.keys().cloned().collect();
+        if self.token().is_some() {
+            extras.push("a bearer token".to_string());
+        }
+        match extras.len() {
+            0 => self.base_url.clone(),
+            _ => format!("{} with {}", self.base_url, extras.join(", ")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CalibrationConfig {
+    pub file: Option<PathBuf>, // Defaults to the first of `CALIBRATION_FILES` that exists
+    pub mask_file: Option<PathBuf>, // Broken LEDs; defaults to the first of `MASK_FILES` that exists
+}
+
+/// A WS2812 strip driven from a Raspberry Pi; only used by builds with the `ws2812` feature.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Ws2812Config {
+    pub enabled: bool,
+    pub pixel_count: Option<usize>, // Strip length, defaults to the number of LEDs in the layout
+    pub pixel_offset: usize,        // Strip pixel of the first LED
+    pub pixel_order: Option<Vec<usize>>, // Strip pixel of each LED relative to the offset
+    pub max_fps: f64,
+}
+
+impl Default for Ws2812Config {
+    fn default() -> Self {
+        Ws2812Config {
+            enabled: false,
+            pixel_count: None,
+            pixel_offset: 0,
+            pixel_order: None,
+            max_fps: 60.0,
+        }
+    }
+}
+
+impl Config {
+    /// Loads `path`, or the defaults when `path` is `None` and no default config file exists.
+    pub fn load(path: Option<&Path>) -> Result<Config, AppError> {
+        let path = match path {
+            Some(path) => path,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => Path::new(DEFAULT_CONFIG_FILE),
+            None => return Ok(Config::default()),
+        };
+
+        let text = std::fs::read_to_string(path)?;
+        Config::parse(&text).map_err(|reason| AppError::Config {
+            reason: format!("{}: {}", path.display(), reason),
+        })
+    }
+
+    /// Parses a config file, warning about keys that no setting uses.
+    pub fn parse(text: &str) -> Result<Config, String> {
+        let value: toml::Value = toml::from_str(text).map_err(|err| err.to_string())?;
+        let mut config: Config = value.clone().try_into().map_err(|err| err.to_string())?;
+
+        let known = toml::Value::try_from(&config).map_err(|err| err.to_string())?;
+        let mut unknown = Vec::new();
+        collect_unknown_keys(&value, &known, "", &mut unknown);
+        if !unknown.is_empty() {
+            notices::report(&AppError::Config {
+                reason: format!("ignoring unknown keys {}", unknown.join(", ")),
+            });
+        }
+
+        config.explicit = collect_set_keys(&value, "");
+        Ok(config)
+    }
+
+    /// The settings of the LED output called `name`, e.g. `ws2812`.
+    pub fn output(&self, name: &str) -> OutputConfig {
+        let mut output = self.outputs.get(name).copied().unwrap_or_default();
+        if name == "ws2812" && !self.is_set("outputs.ws2812.gamma") {
+            output.gamma = WS2812_GAMMA;
+        }
+        output
+    }
+
+    /// Whether a dotted key such as `playback.speed` was given explicitly rather than defaulted.
+    pub fn is_set(&self, key: &str) -> bool {
+        self.explicit.contains(key)
+    }
+
+    /// Records that a key was given explicitly, e.g. by a command line override.
+    pub fn mark_set(&mut self, key: &str) {
+        self.explicit.insert(key.to_string());
+    }
+}
+
+fn collect_set_keys(value: &toml::Value, prefix: &str) -> HashSet<String> {
+    let mut keys = HashSet::new();
+    if let Some(table) = value.as_table() {
+        for (key, value) in table {
+            let path = if prefix.is_empty() {
+                key.clone()
+            } else {
+                format!("{}.{}", prefix, key)
+            };
+            keys.extend(collect_set_keys(value, &path));
+            keys.insert(path);
+        }
+    }
+    keys
+}
+
+// Keys present in the input but absent from the re-serialized config weren't used by any field
+fn collect_unknown_keys(
+    input: &toml::Value,
+    known: &toml::Value,
+    prefix: &str,
+    unknown: &mut Vec<String>,
+) {
+    let (Some(input), Some(known)) = (input.as_table(), known.as_table()) else {
+        return;
+    };
+    for (key, value) in input {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match known.get(key) {
+            Some(known_value) => collect_unknown_keys(value, known_value, &path, unknown),
+            None => unknown.push(path),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use crate::simulation::{PlaybackState, Simulation};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+/// A playback action from a remote or physical control, applied by the thread that owns the
+/// simulation. In config files it's written like `"toggle_pause"` or `{ adjust_speed = 2 }`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaybackCommand {
+    /// Resumes a paused replay, otherwise starts from the beginning.
+    Start,
+    /// Stops and rewinds, like the STOP button.
+    Stop,
+    Pause,
+    /// Pauses a playing replay, otherwise acts like `Start`.
+    TogglePause,
+    SetSpeed(f64),
+    /// Changes the speed by this much.
+    AdjustSpeed(f64),
+    Seek(#[serde(serialize_with = "to_seconds", deserialize_with = "from_seconds")] Duration),
+    /// Moves the clock this many seconds, backwards when negative.
+    Skip(f64),
+}
+
+impl PlaybackCommand {
+    /// Applies the command; speeds are kept within `speeds`.
+    pub fn apply(self, simulation: &mut Simulation, speeds: &RangeInclusive<f64>) {
+        let clamp = |speed: f64| speed.clamp(*speeds.start(), *speeds.end());
+        match self {
+            PlaybackCommand::Start if simulation.state() == PlaybackState::Paused => {
+                simulation.set_paused(false)
+            }
+            PlaybackCommand::Start => simulation.start(),
+            PlaybackCommand::Stop => simulation.reset(),
+            PlaybackCommand::Pause => simulation.set_paused(true),
+            PlaybackCommand::TogglePause if simulation.state() == PlaybackState::Playing => {
+                simulation.set_paused(true)
+            }
+            PlaybackCommand::TogglePause => PlaybackCommand::Start.apply(simulation, speeds),
+            PlaybackCommand::SetSpeed(speed) => simulation.set_speed(clamp(speed)),
+            PlaybackCommand::AdjustSpeed(step) => {
+                simulation.set_speed(clamp(simulation.speed() + step))
+            }
+            PlaybackCommand::Seek(race_time) => {
+                simulation.seek(race_time);
+            }
+            PlaybackCommand::Skip(secs) => {
+                simulation.skip(secs);
+            }
+        }
+    }
+}
+
+// Race times in config files are plain seconds
+fn to_seconds<S: Serializer>(time: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(time.as_secs_f64())
+}
+
+fn from_seconds<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let seconds = f64::deserialize(deserializer)?;
+    Duration::try_from_secs_f64(seconds).map_err(serde::de::Error::custom)
+}
+
+/// Settings of the HTTP control API, served when the `http-control` feature is built in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControlConfig {
+    pub enabled: bool,
+    pub bind: String,
+    pub port: u16,
+    pub token: Option<String>, // Required as `Authorization: Bearer <token>` when set
+}
+
+impl Default for ControlConfig {
+    fn default() -> Self {
+        ControlConfig {
+            enabled: false,
+            bind: "127.0.0.1".to_string(),
+            port: 8080,
+            token: None,
+        }
+    }
+}
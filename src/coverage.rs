@@ -0,0 +1,132 @@
+use crate::data::{LocationData, TimeWindow};
+use crate::space::TelemetryPoint;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A stretch of time from `start` up to but excluding `end`, like the API's window filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl Span {
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> Span {
+        Span { start, end }
+    }
+
+    /// The span of a window with both bounds; `None` for an open one.
+    pub fn of(window: &TimeWindow) -> Option<Span> {
+        Some(Span::new(window.start?, window.end?))
+    }
+
+    pub fn window(&self) -> TimeWindow {
+        TimeWindow {
+            start: Some(self.start),
+            end: Some(self.end),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
+
+    fn contains(&self, date: DateTime<Utc>) -> bool {
+        self.start <= date && date < self.end
+    }
+}
+
+/// The parts of `wanted` that none of `covered` overlaps, in order. `covered` is disjoint and
+/// in order, as `cover` leaves it.
+pub fn uncovered(covered: &[Span], wanted: Span) -> Vec<Span> {
+    let mut gaps = Vec::new();
+    let mut from = wanted.start;
+    for span in covered {
+        if span.end <= from || span.is_empty() {
+            continue;
+        }
+        if span.start >= wanted.end {
+            break;
+        }
+        if span.start > from {
+            gaps.push(Span::new(from, span.start));
+        }
+        from = from.max(span.end);
+    }
+    if from < wanted.end {
+        gaps.push(Span::new(from, wanted.end));
+    }
+    gaps
+}
+
+/// `covered` with `added`, as disjoint spans in order; spans that overlap or touch are joined.
+pub fn cover(covered: &[Span], added: Span) -> Vec<Span> {
+    let mut spans: Vec<Span> = covered.iter().copied().chain([added]).collect();
+    spans.retain(|span| !span.is_empty());
+    spans.sort_by_key(|span| span.start);
+    let mut joined: Vec<Span> = Vec::with_capacity(spans.len());
+    for span in spans {
+        match joined.last_mut() {
+            Some(last) if span.start <= last.end => last.end = last.end.max(span.end),
+            _ => joined.push(span),
+        }
+    }
+    joined
+}
+
+// A location sample as cached; the driver is the cache's
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct CachedSample {
+    date: DateTime<Utc>,
+    point: TelemetryPoint,
+    z: Option<f64>,
+}
+
+/// A driver's location samples as received, with the time they were fetched for, so a wider
+/// window only fetches what's missing. Samples at (0, 0) are kept, as they count towards what
+/// arrived.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CachedLocations {
+    covered: Vec<Span>,
+    samples: Vec<CachedSample>, // By date
+}
+
+impl CachedLocations {
+    pub fn covered(&self) -> &[Span] {
+        &self.covered
+    }
+
+    /// The parts of `wanted` to fetch.
+    pub fn missing(&self, wanted: Span) -> Vec<Span> {
+        uncovered(&self.covered, wanted)
+    }
+
+    /// Adds the samples fetched for `span`. A sample at a date already held is dropped, as a
+    /// row where two spans meet can arrive with both.
+    pub fn add(&mut self, span: Span, fetched: &[LocationData]) {
+        self.covered = cover(&self.covered, span);
+        self.samples
+            .extend(fetched.iter().map(|sample| CachedSample {
+                date: sample.date,
+                point: sample.point,
+                z: sample.z,
+            }));
+        self.samples.sort_by_key(|sample| sample.date);
+        self.samples.dedup_by_key(|sample| sample.date);
+    }
+
+    /// The samples within `span` as location data of `driver_number`, by date.
+    pub fn samples(&self, driver_number: u32, span: Span) -> Vec<LocationData> {
+        self.samples
+            .iter()
+            .filter(|sample| span.contains(sample.date))
+            .map(|sample| LocationData {
+                point: sample.point,
+                z: sample.z,
+                date: sample.date,
+                driver_number,
+                synthetic: false,
+            })
+            .collect()
+    }
+}
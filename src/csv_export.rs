@@ -0,0 +1,33 @@
+use crate::error::AppError;
+use crate::mapping::RunRace;
+use chrono::SecondsFormat;
+use std::io::Write;
+
+/// Header of the written file: the columns of the Parquet export, with the timestamp in
+/// RFC 3339 UTC to the microsecond.
+pub const CSV_HEADER: [&str; 5] = ["timestamp", "driver_number", "led_index", "x", "y"];
+
+/// Writes `run_race_data` to `writer` as CSV, a row per record.
+pub fn write_csv<W: Write>(run_race_data: &[RunRace], writer: W) -> Result<(), AppError> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(CSV_HEADER).map_err(csv_error)?;
+    for row in run_race_data {
+        writer
+            .write_record([
+                row.date.to_rfc3339_opts(SecondsFormat::Micros, true),
+                row.driver_number.to_string(),
+                row.led_index.to_string(),
+                row.point.x.to_string(),
+                row.point.y.to_string(),
+            ])
+            .map_err(csv_error)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn csv_error(err: csv::Error) -> AppError {
+    AppError::Export {
+        reason: format!("could not write the CSV file: {}", err),
+    }
+}
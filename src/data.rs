@@ -0,0 +1,808 @@
+use crate::cache::{load_locations, locations_cache_key, store_locations};
+use crate::config::ApiConfig;
+use crate::coverage::Span;
+use crate::error::AppError;
+use crate::metrics;
+use crate::notices;
+use crate::space::TelemetryPoint;
+use crate::status;
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+use log::{debug, info, warn};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use reqwest::{Client, Response};
+use serde::de::{self, DeserializeOwned, Deserializer};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+use web_time::Instant;
+
+/// A raw location sample as returned by the OpenF1 `location` endpoint.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LocationData {
+    #[serde(flatten)]
+    pub point: TelemetryPoint, // The endpoint's x and y
+    #[serde(default)]
+    pub z: Option<f64>, // Elevation in the same units; not every session has it
+    #[serde(deserialize_with = "deserialize_datetime")]
+    pub date: DateTime<Utc>,
+    pub driver_number: u32,
+    #[serde(skip)]
+    pub synthetic: bool, // Interpolated across a dropout rather than received
+}
+
+/// Counters and timings of getting a session's race data, collected by the stages that fetch
+/// and map it, for the diagnostics window.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PipelineStats {
+    pub fetched_per_driver: BTreeMap<u32, usize>, // Location samples as received
+    pub zero_coordinate_samples: usize,           // Dropped as placeholders at (0, 0)
+    pub downsampled_samples: usize, // Dropped to keep one sample per driver and interval
+    pub synthetic_samples: usize,   // Interpolated across short dropouts
+    pub align_ms: Option<u64>,      // Grid every driver was resampled onto, when aligned
+    pub aligned_samples: usize,     // Samples on that grid, all interpolated
+    pub mapped_samples: usize,      // Records before repeated positions were collapsed
+    pub window: TimeWindow,         // What was asked for, with the session's bounds filled in
+    pub fetch_time: Duration,
+    pub mapping_time: Duration,
+}
+
+/// When a session ran, as returned by the OpenF1 `sessions` endpoint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionInfo {
+    #[serde(deserialize_with = "deserialize_datetime")]
+    pub date_start: DateTime<Utc>,
+    #[serde(deserialize_with = "deserialize_datetime")]
+    pub date_end: DateTime<Utc>,
+}
+
+/// A driver as listed by the OpenF1 `drivers` endpoint for a session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionDriver {
+    pub driver_number: u32,
+    pub full_name: Option<String>,
+    pub name_acronym: Option<String>,
+    pub team_name: Option<String>,
+    pub team_colour: Option<String>, // Hex like "3671C6", without the `#`
+}
+
+/// A lap as returned by the OpenF1 `laps` endpoint. The start is missing for some first laps
+/// and the duration for laps that weren't completed; sector times are often missing for in and
+/// out laps.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LapData {
+    pub driver_number: u32,
+    pub lap_number: u32,
+    #[serde(default, deserialize_with = "deserialize_optional_datetime")]
+    pub date_start: Option<DateTime<Utc>>,
+    pub lap_duration: Option<f64>,      // Seconds
+    pub duration_sector_1: Option<f64>, // Seconds
+    pub duration_sector_2: Option<f64>,
+    pub duration_sector_3: Option<f64>,
+    #[serde(default, deserialize_with = "deserialize_null_as_false")]
+    pub is_pit_out_lap: bool,
+}
+
+/// A change of a driver's race position as returned by the OpenF1 `position` endpoint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PositionData {
+    #[serde(deserialize_with = "deserialize_datetime")]
+    pub date: DateTime<Utc>,
+    pub driver_number: u32,
+    pub position: u32,
+}
+
+/// A driver's gap to the race leader as returned by the OpenF1 `intervals` endpoint, which only
+/// has data for races. The gap is missing while a driver is a lap or more down.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct IntervalData {
+    pub date: DateTime<Utc>,
+    pub driver_number: u32,
+    pub gap_to_leader: Option<f64>, // Seconds
+}
+
+/// A driver's stint on one set of tyres as returned by the OpenF1 `stints` endpoint. The last
+/// lap is missing while the stint is running.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StintData {
+    pub driver_number: u32,
+    pub stint_number: u32,
+    pub lap_start: Option<u32>,
+    pub lap_end: Option<u32>,
+    pub compound: Option<String>, // E.g. "SOFT", "MEDIUM", "HARD", "INTERMEDIATE" or "WET"
+}
+
+/// A message from race control as returned by the OpenF1 `race_control` endpoint: a flag,
+/// safety car or note for the whole track, one marshal sector or one driver.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RaceControlData {
+    #[serde(deserialize_with = "deserialize_datetime")]
+    pub date: DateTime<Utc>,
+    #[serde(default)]
+    pub category: String, // E.g. "Flag", "SafetyCar" or "Other"
+    pub flag: Option<String>, // E.g. "GREEN", "YELLOW", "DOUBLE YELLOW", "CLEAR", "RED" or "BLUE"
+    pub scope: Option<String>, // "Track", "Sector" or "Driver"
+    pub sector: Option<u32>,  // Marshal sector, counted from 1
+    pub driver_number: Option<u32>,
+    #[serde(default)]
+    pub message: String,
+}
+
+/// A car telemetry sample as returned by the OpenF1 `car_data` endpoint, a few a second. The
+/// brake is only ever 0 or 100.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CarData {
+    #[serde(deserialize_with = "deserialize_datetime")]
+    pub date: DateTime<Utc>,
+    pub driver_number: u32,
+    pub speed: Option<f64>,    // km/h
+    pub n_gear: Option<u8>,    // 0 in neutral
+    pub throttle: Option<f64>, // Percent
+    pub brake: Option<f64>,    // Percent
+}
+
+// An `intervals` row as sent: the gap is a number, or text like "+1 LAP" for lapped cars
+#[derive(Deserialize)]
+struct IntervalRow {
+    #[serde(deserialize_with = "deserialize_datetime")]
+    date: DateTime<Utc>,
+    driver_number: u32,
+    #[serde(default)]
+    gap_to_leader: serde_json::Value,
+}
+
+/// The OpenF1 session replayed by default (2023 Dutch Grand Prix).
+pub const SESSION_KEY: &str = "9149";
+
+/// Optional bounds on the sample dates fetched; `start` is inclusive, `end` exclusive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TimeWindow {
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+impl TimeWindow {
+    /// The window with the bounds it lacks taken from when the session ran, `margin` further
+    /// out; bounds that are set stay.
+    pub fn within_session(&self, info: &SessionInfo, margin: chrono::Duration) -> TimeWindow {
+        TimeWindow {
+            start: self.start.or(Some(info.date_start - margin)),
+            end: self.end.or(Some(info.date_end + margin)),
+        }
+    }
+
+    // OpenF1 filters on fields with comparison operators in the query string
+    fn query(&self) -> String {
+        let mut query = String::new();
+        if let Some(start) = self.start {
+            query.push_str(&format!(
+                "&date>={}",
+                start.to_rfc3339_opts(SecondsFormat::Millis, true)
+            ));
+        }
+        if let Some(end) = self.end {
+            query.push_str(&format!(
+                "&date<{}",
+                end.to_rfc3339_opts(SecondsFormat::Millis, true)
+            ));
+        }
+        query
+    }
+}
+
+/// Fetches the location samples of the given drivers, sorted by date, with how many arrived
+/// and how long it took. Bounds the window lacks come from the session's own, as a whole
+/// session at once is more than the API likes to return.
+pub async fn fetch_data(
+    api: &ApiConfig,
+    session_key: &str,
+    driver_numbers: &[u32],
+    window: &TimeWindow,
+) -> Result<(Vec<LocationData>, PipelineStats), AppError> {
+    let (per_driver, stats) = fetch_driver_data(api, session_key, driver_numbers, window).await?;
+    let mut all_data: Vec<LocationData> =
+        per_driver.into_iter().flat_map(|(_, data)| data).collect();
+    all_data.sort_by_key(|d| d.date);
+    Ok((all_data, stats))
+}
+
+/// Like `fetch_data`, but keeps each driver's samples apart, in the order of `driver_numbers`
+/// and each sorted by date, so they can be mapped a driver at a time.
+pub async fn fetch_driver_data(
+    api: &ApiConfig,
+    session_key: &str,
+    driver_numbers: &[u32],
+    window: &TimeWindow,
+) -> Result<(Vec<(u32, Vec<LocationData>)>, PipelineStats), AppError> {
+    fetch_locations(api, session_key, driver_numbers, window, None).await
+}
+
+/// Like `fetch_driver_data`, with each driver's samples kept in `cache_dir` along with the time
+/// they cover: only the parts of the window not fetched before are fetched, and the cache
+/// then covers both.
+pub async fn fetch_driver_data_cached(
+    api: &ApiConfig,
+    session_key: &str,
+    driver_numbers: &[u32],
+    window: &TimeWindow,
+    cache_dir: &Path,
+) -> Result<(Vec<(u32, Vec<LocationData>)>, PipelineStats), AppError> {
+    fetch_locations(api, session_key, driver_numbers, window, Some(cache_dir)).await
+}
+
+async fn fetch_locations(
+    api: &ApiConfig,
+    session_key: &str,
+    driver_numbers: &[u32],
+    window: &TimeWindow,
+    cache_dir: Option<&Path>,
+) -> Result<(Vec<(u32, Vec<LocationData>)>, PipelineStats), AppError> {
+    let client = client(api)?;
+    let mut per_driver = Vec::with_capacity(driver_numbers.len());
+    let started = Instant::now();
+    let mut stats = PipelineStats::default();
+    let window = match (window.start, window.end) {
+        (Some(_), Some(_)) => *window,
+        _ => session_window(api, session_key, window).await,
+    };
+    status::fetching(session_key, &window);
+    stats.window = window;
+
+    let mut kept = 0;
+    for &driver_number in driver_numbers {
+        let mut data: Vec<LocationData> = match (cache_dir, Span::of(&window)) {
+            (Some(dir), Some(span)) => {
+                fetch_cached_locations(&client, api, session_key, driver_number, span, dir).await?
+            }
+            // Without both bounds there's no telling what a window covers
+            _ => {
+                fetch_driver_rows(
+                    &client,
+                    api,
+                    "location",
+                    session_key,
+                    driver_number,
+                    &window.query(),
+                )
+                .await?
+            }
+        };
+        let fetched = data.len();
+        data.retain(|d| d.point.x != 0.0 && d.point.y != 0.0);
+        data.sort_by_key(|d| d.date);
+        stats.fetched_per_driver.insert(driver_number, fetched);
+        stats.zero_coordinate_samples += fetched - data.len();
+        kept += data.len();
+        per_driver.push((driver_number, data));
+    }
+
+    if kept == 0 {
+        return Err(AppError::EmptyData {
+            drivers: driver_numbers.to_vec(),
+        });
+    }
+
+    info!(
+        "Fetched {} location samples for {} drivers",
+        kept,
+        driver_numbers.len()
+    );
+    stats.fetch_time = started.elapsed();
+    Ok((per_driver, stats))
+}
+
+// The window filled in from the session's bounds; as given when they can't be had
+async fn session_window(api: &ApiConfig, session_key: &str, window: &TimeWindow) -> TimeWindow {
+    let margin = chrono::Duration::seconds(api.session_margin_secs);
+    match fetch_session(api, session_key).await {
+        Ok(Some(info)) => window.within_session(&info, margin),
+        Ok(None) => *window,
+        Err(err) => {
+            warn!("{}; fetching without the session's bounds", err);
+            *window
+        }
+    }
+}
+
+/// Fetches when a session ran; `None` when the API doesn't know the session.
+pub async fn fetch_session(
+    api: &ApiConfig,
+    session_key: &str,
+) -> Result<Option<SessionInfo>, AppError> {
+    let client = client(api)?;
+    let url = format!(
+        "{}/sessions?session_key={}",
+        api.base_url.trim_end_matches('/'),
+        session_key
+    );
+    let sessions: Vec<SessionInfo> = get_with_retry(&client, api, &url, None)
+        .await?
+        .json()
+        .await
+        .map_err(|err| AppError::Decode {
+            context: format!("session {}: {}", session_key, err),
+        })?;
+    Ok(sessions.into_iter().next())
+}
+
+/// Fetches the drivers listed for a session.
+pub async fn fetch_drivers(
+    api: &ApiConfig,
+    session_key: &str,
+) -> Result<Vec<SessionDriver>, AppError> {
+    let client = client(api)?;
+    let url = format!(
+        "{}/drivers?session_key={}",
+        api.base_url.trim_end_matches('/'),
+        session_key
+    );
+    get_with_retry(&client, api, &url, None)
+        .await?
+        .json()
+        .await
+        .map_err(|err| AppError::Decode {
+            context: format!("the drivers of session {}: {}", session_key, err),
+        })
+}
+
+/// Fetches the laps of the given drivers, sorted by driver and lap number.
+pub async fn fetch_laps(
+    api: &ApiConfig,
+    session_key: &str,
+    driver_numbers: &[u32],
+) -> Result<Vec<LapData>, AppError> {
+    let client = client(api)?;
+    let mut laps: Vec<LapData> = Vec::new();
+    for &driver_number in driver_numbers {
+        laps.extend(fetch_driver_rows(&client, api, "laps", session_key, driver_number, "").await?);
+    }
+    laps.sort_by_key(|lap| (lap.driver_number, lap.lap_number));
+    info!("Fetched {} laps", laps.len());
+    Ok(laps)
+}
+
+/// Fetches the race position changes of the given drivers, sorted by driver and date.
+pub async fn fetch_positions(
+    api: &ApiConfig,
+    session_key: &str,
+    driver_numbers: &[u32],
+) -> Result<Vec<PositionData>, AppError> {
+    let client = client(api)?;
+    let mut positions: Vec<PositionData> = Vec::new();
+    for &driver_number in driver_numbers {
+        positions.extend(
+            fetch_driver_rows(&client, api, "position", session_key, driver_number, "").await?,
+        );
+    }
+    positions.sort_by_key(|position| (position.driver_number, position.date));
+    info!("Fetched {} position changes", positions.len());
+    Ok(positions)
+}
+
+/// Fetches the gaps to the leader of the given drivers, sorted by driver and date.
+pub async fn fetch_intervals(
+    api: &ApiConfig,
+    session_key: &str,
+    driver_numbers: &[u32],
+) -> Result<Vec<IntervalData>, AppError> {
+    let client = client(api)?;
+    let mut intervals: Vec<IntervalData> = Vec::new();
+    for &driver_number in driver_numbers {
+        let rows: Vec<IntervalRow> =
+            fetch_driver_rows(&client, api, "intervals", session_key, driver_number, "").await?;
+        intervals.extend(rows.into_iter().map(|row| IntervalData {
+            date: row.date,
+            driver_number: row.driver_number,
+            gap_to_leader: row.gap_to_leader.as_f64(),
+        }));
+    }
+    intervals.sort_by_key(|interval| (interval.driver_number, interval.date));
+    info!("Fetched {} intervals", intervals.len());
+    Ok(intervals)
+}
+
+/// Fetches the tyre stints of the given drivers, sorted by driver and stint.
+pub async fn fetch_stints(
+    api: &ApiConfig,
+    session_key: &str,
+    driver_numbers: &[u32],
+) -> Result<Vec<StintData>, AppError> {
+    let client = client(api)?;
+    let mut stints: Vec<StintData> = Vec::new();
+    for &driver_number in driver_numbers {
+        stints.extend(
+            fetch_driver_rows(&client, api, "stints", session_key, driver_number, "").await?,
+        );
+    }
+    stints.sort_by_key(|stint| (stint.driver_number, stint.stint_number));
+    info!("Fetched {} stints", stints.len());
+    Ok(stints)
+}
+
+/// Fetches one driver's car data within `window`, sorted by date. It's sampled as often as the
+/// locations, so a driver at a time is all that's asked for.
+pub async fn fetch_car_data(
+    api: &ApiConfig,
+    session_key: &str,
+    driver_number: u32,
+    window: &TimeWindow,
+) -> Result<Vec<CarData>, AppError> {
+    let client = client(api)?;
+    let mut samples: Vec<CarData> = fetch_driver_rows(
+        &client,
+        api,
+        "car_data",
+        session_key,
+        driver_number,
+        &window.query(),
+    )
+    .await?;
+    samples.sort_by_key(|sample| sample.date);
+    info!(
+        "Fetched {} car data samples of driver {}",
+        samples.len(),
+        driver_number
+    );
+    Ok(samples)
+}
+
+/// Fetches the race control messages of a session, sorted by date. A failed request is
+/// reported as a notice and leaves the session without any.
+pub async fn fetch_race_control(
+    api: &ApiConfig,
+    session_key: &str,
+) -> Result<Vec<RaceControlData>, AppError> {
+    let client = client(api)?;
+    let url = format!(
+        "{}/race_control?session_key={}",
+        api.base_url.trim_end_matches('/'),
+        session_key
+    );
+    let mut messages: Vec<RaceControlData> = match get_with_retry(&client, api, &url, None).await {
+        Ok(resp) => resp.json().await.map_err(|err| AppError::Decode {
+            context: format!("race control messages of session {}: {}", session_key, err),
+        })?,
+        Err(err @ AppError::Http { .. }) => {
+            notices::report(&err);
+            Vec::new()
+        }
+        Err(err) => return Err(err),
+    };
+    messages.sort_by_key(|message| message.date);
+    info!("Fetched {} race control messages", messages.len());
+    Ok(messages)
+}
+
+/// An OpenF1 endpoint the program reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endpoint {
+    Sessions,
+    Drivers,
+    Location,
+    Laps,
+    Position,
+    Intervals,
+    Stints,
+    RaceControl,
+}
+
+impl Endpoint {
+    pub const ALL: [Endpoint; 8] = [
+        Endpoint::Sessions,
+        Endpoint::Drivers,
+        Endpoint::Location,
+        Endpoint::Laps,
+        Endpoint::Position,
+        Endpoint::Intervals,
+        Endpoint::Stints,
+        Endpoint::RaceControl,
+    ];
+
+    /// The path of the endpoint, e.g. `race_control`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Endpoint::Sessions => "sessions",
+            Endpoint::Drivers => "drivers",
+            Endpoint::Location => "location",
+            Endpoint::Laps => "laps",
+            Endpoint::Position => "position",
+            Endpoint::Intervals => "intervals",
+            Endpoint::Stints => "stints",
+            Endpoint::RaceControl => "race_control",
+        }
+    }
+
+    /// Whether the endpoint is only read for the laps and positions.
+    pub fn is_progress(self) -> bool {
+        !matches!(
+            self,
+            Endpoint::Sessions | Endpoint::Drivers | Endpoint::Location
+        )
+    }
+}
+
+/// Sends a single request to `endpoint` for one driver of the session, without retrying, and
+/// decodes the rows like the fetches do; returns how many came back. Location samples are only
+/// asked for within `window`, since a whole session of them is a large download.
+pub async fn probe_endpoint(
+    api: &ApiConfig,
+    endpoint: Endpoint,
+    session_key: &str,
+    driver_number: u32,
+    window: &TimeWindow,
+) -> Result<usize, AppError> {
+    let client = client(api)?;
+    let (filter, driver) = match endpoint {
+        Endpoint::Sessions | Endpoint::RaceControl => (String::new(), None),
+        Endpoint::Location => (
+            format!("&driver_number={}{}", driver_number, window.query()),
+            Some(driver_number),
+        ),
+        _ => (
+            format!("&driver_number={}", driver_number),
+            Some(driver_number),
+        ),
+    };
+    let url = format!(
+        "{}/{}?session_key={}{}",
+        api.base_url.trim_end_matches('/'),
+        endpoint.name(),
+        session_key,
+        filter
+    );
+    let once = ApiConfig {
+        retries: 0,
+        ..api.clone()
+    };
+    let resp = get_with_retry(&client, &once, &url, driver).await?;
+    match endpoint {
+        Endpoint::Sessions => count_rows::<SessionInfo>(resp, endpoint).await,
+        Endpoint::Drivers => count_rows::<SessionDriver>(resp, endpoint).await,
+        Endpoint::Location => count_rows::<LocationData>(resp, endpoint).await,
+        Endpoint::Laps => count_rows::<LapData>(resp, endpoint).await,
+        Endpoint::Position => count_rows::<PositionData>(resp, endpoint).await,
+        Endpoint::Intervals => count_rows::<IntervalRow>(resp, endpoint).await,
+        Endpoint::Stints => count_rows::<StintData>(resp, endpoint).await,
+        Endpoint::RaceControl => count_rows::<RaceControlData>(resp, endpoint).await,
+    }
+}
+
+async fn count_rows<T: DeserializeOwned>(
+    resp: Response,
+    endpoint: Endpoint,
+) -> Result<usize, AppError> {
+    let rows: Vec<T> = resp.json().await.map_err(|err| AppError::Decode {
+        context: format!("{} rows: {}", endpoint.name(), err),
+    })?;
+    Ok(rows.len())
+}
+
+// Every request, retries included, carries the configured headers and token. Their values are
+// marked sensitive, which keeps them out of the HTTP stack's own logging
+fn client(api: &ApiConfig) -> Result<Client, AppError> {
+    let mut headers = HeaderMap::new();
+    let token = api.token().map(|token| {
+        (
+            AUTHORIZATION.to_string(),
+            format!("Bearer {}", token.expose()),
+        )
+    });
+    let extras = api
+        .headers
+        .iter()
+        .map(|(name, value)| (name.clone(), value.expose().to_string()));
+    for (name, value) in extras.chain(token) {
+        let invalid = || AppError::Config {
+            reason: format!("api header {} is not a valid HTTP header", name),
+        };
+        let header_name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid())?;
+        let mut header_value = HeaderValue::from_str(&value).map_err(|_| invalid())?;
+        header_value.set_sensitive(true);
+        headers.insert(header_name, header_value);
+    }
+    let builder = Client::builder().default_headers(headers);
+    // The browser's fetch has no timeout to set; it gives up by itself
+    #[cfg(not(target_arch = "wasm32"))]
+    let builder = builder.timeout(Duration::from_secs(api.timeout_secs));
+    builder.build().map_err(|source| AppError::Network {
+        url: api.base_url.clone(),
+        source,
+    })
+}
+
+// Fetches one driver's rows of an OpenF1 endpoint; `query` holds further filters. A driver the
+// API has no data for yields no rows, as a single missing driver shouldn't stop the replay
+async fn fetch_driver_rows<T: DeserializeOwned>(
+    client: &Client,
+    api: &ApiConfig,
+    endpoint: &str,
+    session_key: &str,
+    driver_number: u32,
+    query: &str,
+) -> Result<Vec<T>, AppError> {
+    match try_fetch_driver_rows(client, api, endpoint, session_key, driver_number, query).await {
+        Err(err @ AppError::Http { .. }) => {
+            notices::report(&err);
+            Ok(Vec::new())
+        }
+        result => result,
+    }
+}
+
+// Like `fetch_driver_rows`, but with the HTTP error of a driver the API has no data for
+async fn try_fetch_driver_rows<T: DeserializeOwned>(
+    client: &Client,
+    api: &ApiConfig,
+    endpoint: &str,
+    session_key: &str,
+    driver_number: u32,
+    query: &str,
+) -> Result<Vec<T>, AppError> {
+    let url = format!(
+        "{}/{}?session_key={}&driver_number={}{}",
+        api.base_url.trim_end_matches('/'),
+        endpoint,
+        session_key,
+        driver_number,
+        query
+    );
+    let resp = get_with_retry(client, api, &url, Some(driver_number)).await?;
+    resp.json().await.map_err(|err| AppError::Decode {
+        context: format!("{} data for driver {}: {}", endpoint, driver_number, err),
+    })
+}
+
+// A driver's location samples in `span`, fetching only the parts the cache in `cache_dir`
+// doesn't cover yet. A part that failed with an HTTP error isn't marked covered, so it's asked
+// for again next time
+async fn fetch_cached_locations(
+    client: &Client,
+    api: &ApiConfig,
+    session_key: &str,
+    driver_number: u32,
+    span: Span,
+    cache_dir: &Path,
+) -> Result<Vec<LocationData>, AppError> {
+    let key = locations_cache_key(session_key, driver_number);
+    let mut cached = load_locations(cache_dir, key).unwrap_or_default();
+    let missing = cached.missing(span);
+    if missing.is_empty() {
+        debug!(
+            "Location samples of driver {} are all cached",
+            driver_number
+        );
+        return Ok(cached.samples(driver_number, span));
+    }
+    let mut changed = false;
+    for part in missing {
+        let query = part.window().query();
+        match try_fetch_driver_rows::<LocationData>(
+            client,
+            api,
+            "location",
+            session_key,
+            driver_number,
+            &query,
+        )
+        .await
+        {
+            Ok(rows) => {
+                debug!(
+                    "Fetched {} location samples of driver {} from {} to {}",
+                    rows.len(),
+                    driver_number,
+                    part.start,
+                    part.end
+                );
+                cached.add(part, &rows);
+                changed = true;
+            }
+            Err(err @ AppError::Http { .. }) => notices::report(&err),
+            Err(err) => return Err(err),
+        }
+    }
+    if changed {
+        if let Err(err) = store_locations(cache_dir, key, &cached) {
+            notices::report(&AppError::Cache {
+                reason: format!("could not write the location cache: {}", err),
+            });
+        }
+    }
+    Ok(cached.samples(driver_number, span))
+}
+
+// Sends a GET, retrying network errors and retryable statuses with exponential backoff. An HTTP
+// error names `driver_number`, if the request is about one driver
+async fn get_with_retry(
+    client: &Client,
+    api: &ApiConfig,
+    url: &str,
+    driver_number: Option<u32>,
+) -> Result<Response, AppError> {
+    let mut attempt = 0;
+    loop {
+        debug!("Fetching {}", url);
+        let err = match client.get(url).send().await {
+            Ok(resp) if resp.status().is_success() => {
+                metrics::api_request(true);
+                status::api_ok();
+                return Ok(resp);
+            }
+            Ok(resp) => AppError::Http {
+                driver: driver_number,
+                status: resp.status(),
+                url: url.to_string(),
+            },
+            Err(source) => AppError::Network {
+                url: url.to_string(),
+                source,
+            },
+        };
+        metrics::api_request(false);
+
+        if !err.is_retryable() || attempt >= api.retries {
+            status::api_failed(&err.to_string(), false);
+            return Err(err);
+        }
+        status::api_failed(&err.to_string(), true);
+        let delay = Duration::from_millis(api.retry_delay_ms.saturating_mul(1 << attempt.min(16)));
+        warn!("{}; retrying in {:?}", err, delay);
+        sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(feature = "native")]
+async fn sleep(delay: Duration) {
+    tokio::time::sleep(delay).await;
+}
+
+// The web build has no tokio runtime, so this waits on the browser's timer. Should there be no
+// window to set one on, the retry goes ahead at once
+#[cfg(not(feature = "native"))]
+async fn sleep(delay: Duration) {
+    let millis = delay.as_millis().min(i32::MAX as u128) as i32;
+    let timer = js_sys::Promise::new(&mut |resolve, _| {
+        let scheduled = web_sys::window().and_then(|window| {
+            window
+                .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, millis)
+                .ok()
+        });
+        if scheduled.is_none() {
+            let _ = resolve.call0(&resolve);
+        }
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(timer).await;
+}
+
+// Accepts RFC 3339 timestamps and, as some OpenF1 rows have them, naive ones taken as UTC
+fn deserialize_datetime<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: String = Deserialize::deserialize(deserializer)?;
+    match DateTime::parse_from_rfc3339(&s) {
+        Ok(dt) => Ok(dt.with_timezone(&Utc)),
+        Err(err) => NaiveDateTime::parse_from_str(&s, "%Y-%m-%dT%H:%M:%S%.f")
+            .map(|dt| dt.and_utc())
+            .map_err(|_| de::Error::custom(err)),
+    }
+}
+
+fn deserialize_optional_datetime<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Wrapper(#[serde(deserialize_with = "deserialize_datetime")] DateTime<Utc>);
+
+    let wrapper: Option<Wrapper> = Deserialize::deserialize(deserializer)?;
+    Ok(wrapper.map(|Wrapper(date)| date))
+}
+
+fn deserialize_null_as_false<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<bool>::deserialize(deserializer)?.unwrap_or(false))
+}
@@ -0,0 +1,287 @@
+use crate::error::AppError;
+use crate::simulation::Rgb;
+use crate::sink::{LedSink, OutputFrame, DEFAULT_MAX_FPS};
+use log::info;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs, UdpSocket};
+
+pub const ARTNET_PORT: u16 = 6454;
+pub const SACN_PORT: u16 = 5568;
+
+// A DMX universe carries 512 channels, i.e. 170 whole RGB pixels
+const UNIVERSE_CHANNELS: usize = 512;
+
+// Size of the E1.31 root, framing and DMP layer headers including the start code
+const SACN_HEADER_LEN: usize = 126;
+const SACN_SOURCE_NAME_LEN: usize = 64;
+const SACN_PRIORITY: u8 = 100;
+
+/// DMX-over-IP protocol used by the `DmxSink`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DmxProtocol {
+    #[default]
+    ArtNet,
+    Sacn,
+}
+
+/// Where and how to send DMX frames.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DmxConfig {
+    pub enabled: bool,
+    pub protocol: DmxProtocol,
+    pub target: Option<String>, // host or host:port; defaults to broadcast or sACN multicast
+    pub universe: u16,          // First universe; pixels past it spill into the next ones
+    pub start_address: u16,     // DMX channel (1-512) of the first pixel's red value
+    pub fps: f64,
+    pub source_name: String, // Shown by sACN receivers
+}
+
+impl Default for DmxConfig {
+    fn default() -> Self {
+        DmxConfig {
+            enabled: false,
+            protocol: DmxProtocol::default(),
+            target: None,
+            universe: 1,
+            start_address: 1,
+            fps: DEFAULT_MAX_FPS,
+            source_name: "F1-LED-CIRCUIT".to_string(),
+        }
+    }
+}
+
+/// Sends frames as Art-Net or sACN (E1.31) DMX packets over UDP.
+pub struct DmxSink {
+    socket: UdpSocket,
+    config: DmxConfig,
+    explicit_target: Option<SocketAddr>,
+    cid: [u8; 16],      // sACN component identifier, fixed for the lifetime of the sink
+    sequences: Vec<u8>, // Per-universe sequence numbers
+}
+
+impl DmxSink {
+    pub fn open(config: &DmxConfig) -> Result<DmxSink, AppError> {
+        if !(1..=UNIVERSE_CHANNELS as u16).contains(&config.start_address) {
+            return Err(AppError::Config {
+                reason: format!(
+                    "dmx.start_address must be 1-512, got {}",
+                    config.start_address
+                ),
+            });
+        }
+        if config.protocol == DmxProtocol::Sacn && config.universe == 0 {
+            return Err(AppError::Config {
+                reason: "sACN universes start at 1".to_string(),
+            });
+        }
+
+        let output_error = |err: std::io::Error| AppError::Output {
+            reason: format!("could not set up the DMX socket: {}", err),
+        };
+        let default_port = match config.protocol {
+            DmxProtocol::ArtNet => ARTNET_PORT,
+            DmxProtocol::Sacn => SACN_PORT,
+        };
+        let explicit_target = match &config.target {
+            Some(target) => Some(resolve(target, default_port)?),
+            None => None,
+        };
+        let socket = UdpSocket::bind(("0.0.0.0", 0)).map_err(output_error)?;
+        socket.set_broadcast(true).map_err(output_error)?;
+
+        // A version 4 UUID
+        let mut cid: [u8; 16] = rand::thread_rng().gen();
+        cid[6] = (cid[6] & 0x0f) | 0x40;
+        cid[8] = (cid[8] & 0x3f) | 0x80;
+
+        info!(
+            "Sending {:?} frames from universe {}",
+            config.protocol, config.universe
+        );
+        Ok(DmxSink {
+            socket,
+            config: config.clone(),
+            explicit_target,
+            cid,
+            sequences: Vec::new(),
+        })
+    }
+
+    /// Sends a frame of LED colors.
+    pub fn show(&mut self, leds: &[Rgb]) -> Result<(), AppError> {
+        let channels = leds.concat();
+        let universes = split_universes(&channels, self.config.start_address);
+        self.sequences.resize(universes.len(), 0);
+
+        for (offset, data) in universes.iter().enumerate() {
+            let universe = u16::try_from(offset)
+                .ok()
+                .and_then(|offset| self.config.universe.checked_add(offset))
+                .ok_or_else(|| AppError::Config {
+                    reason: format!(
+                        "{} LEDs from universe {} run past the last DMX universe",
+                        leds.len(),
+                        self.config.universe
+                    ),
+                })?;
+            // Art-Net reserves sequence 0 for "no sequencing"; sACN uses the full range
+            let sequence = &mut self.sequences[offset];
+            *sequence = match (self.config.protocol, sequence.wrapping_add(1)) {
+                (DmxProtocol::ArtNet, 0) => 1,
+                (_, next) => next,
+            };
+            let (packet, target) = match self.config.protocol {
+                DmxProtocol::ArtNet => (
+                    artnet_packet(universe, *sequence, data),
+                    self.explicit_target
+                        .unwrap_or_else(|| SocketAddr::from(([255, 255, 255, 255], ARTNET_PORT))),
+                ),
+                DmxProtocol::Sacn => (
+                    sacn_packet(
+                        &self.cid,
+                        &self.config.source_name,
+                        universe,
+                        *sequence,
+                        data,
+                    ),
+                    self.explicit_target
+                        .unwrap_or_else(|| sacn_multicast_address(universe)),
+                ),
+            };
+            self.socket
+                .send_to(&packet, target)
+                .map_err(|err| AppError::Output {
+                    reason: format!("could not send DMX to {}: {}", target, err),
+                })?;
+        }
+
+        Ok(())
+    }
+}
+
+impl LedSink for DmxSink {
+    fn name(&self) -> &str {
+        "dmx"
+    }
+
+    fn submit(&mut self, frame: &OutputFrame) -> Result<(), AppError> {
+        self.show(&frame.dimmed())
+    }
+}
+
+// An address with a port, a bare IPv4 or IPv6 address at the default port, or else a host name
+// with or without a port
+fn resolve(target: &str, default_port: u16) -> Result<SocketAddr, AppError> {
+    if let Ok(address) = target.parse::<SocketAddr>() {
+        return Ok(address);
+    }
+    if let Ok(ip) = target.parse::<IpAddr>() {
+        return Ok(SocketAddr::new(ip, default_port));
+    }
+    let with_port = if target.contains(':') {
+        target.to_string()
+    } else {
+        format!("{}:{}", target, default_port)
+    };
+    with_port
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .ok_or_else(|| AppError::Config {
+            reason: format!("could not resolve the DMX target {}", target),
+        })
+}
+
+/// Splits RGB channel values into universe payloads: the first starts at `start_address`,
+/// later ones at channel 1, and a pixel never straddles two universes.
+pub fn split_universes(channels: &[u8], start_address: u16) -> Vec<Vec<u8>> {
+    let mut universes = Vec::new();
+    let mut offset = start_address.saturating_sub(1) as usize;
+    let mut remaining = channels;
+    loop {
+        let pixels = (UNIVERSE_CHANNELS - offset) / 3;
+        let (now, later) = remaining.split_at((pixels * 3).min(remaining.len()));
+        let mut data = vec![0; offset];
+        data.extend_from_slice(now);
+        // Art-Net wants an even payload length
+        if data.len() % 2 == 1 {
+            data.push(0);
+        }
+        universes.push(data);
+
+        if later.is_empty() {
+            return universes;
+        }
+        remaining = later;
+        offset = 0;
+    }
+}
+
+/// An ArtDmx packet carrying `data` for `universe` (15-bit port address).
+pub fn artnet_packet(universe: u16, sequence: u8, data: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(18 + data.len());
+    packet.extend_from_slice(b"Art-Net\0");
+    packet.extend_from_slice(&0x5000u16.to_le_bytes()); // OpDmx
+    packet.extend_from_slice(&14u16.to_be_bytes()); // Protocol version
+    packet.push(sequence);
+    packet.push(0); // Physical input port
+    packet.extend_from_slice(&(universe & 0x7fff).to_le_bytes()); // SubUni, then Net
+    packet.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    packet.extend_from_slice(data);
+    packet
+}
+
+/// An E1.31 data packet carrying `data` for `universe`.
+pub fn sacn_packet(
+    cid: &[u8; 16],
+    source_name: &str,
+    universe: u16,
+    sequence: u8,
+    data: &[u8],
+) -> Vec<u8> {
+    let total = SACN_HEADER_LEN + data.len();
+    // Each layer starts with 0x7 flags and its length from there to the end of the packet
+    let flags_and_length = |start: usize| (0x7000 | (total - start) as u16).to_be_bytes();
+
+    let mut packet = Vec::with_capacity(total);
+    // Root layer
+    packet.extend_from_slice(&0x0010u16.to_be_bytes()); // Preamble size
+    packet.extend_from_slice(&0u16.to_be_bytes()); // Postamble size
+    packet.extend_from_slice(b"ASC-E1.17\0\0\0");
+    packet.extend_from_slice(&flags_and_length(16));
+    packet.extend_from_slice(&0x0000_0004u32.to_be_bytes()); // VECTOR_ROOT_E131_DATA
+    packet.extend_from_slice(cid);
+
+    // Framing layer
+    packet.extend_from_slice(&flags_and_length(38));
+    packet.extend_from_slice(&0x0000_0002u32.to_be_bytes()); // VECTOR_E131_DATA_PACKET
+    let mut name = [0u8; SACN_SOURCE_NAME_LEN];
+    let name_len = source_name.len().min(SACN_SOURCE_NAME_LEN - 1);
+    name[..name_len].copy_from_slice(&source_name.as_bytes()[..name_len]);
+    packet.extend_from_slice(&name);
+    packet.push(SACN_PRIORITY);
+    packet.extend_from_slice(&0u16.to_be_bytes()); // Synchronization address
+    packet.push(sequence);
+    packet.push(0); // Options
+    packet.extend_from_slice(&universe.to_be_bytes());
+
+    // DMP layer
+    packet.extend_from_slice(&flags_and_length(115));
+    packet.push(0x02); // VECTOR_DMP_SET_PROPERTY
+    packet.push(0xa1); // Address and data type
+    packet.extend_from_slice(&0u16.to_be_bytes()); // First property address
+    packet.extend_from_slice(&1u16.to_be_bytes()); // Address increment
+    packet.extend_from_slice(&(data.len() as u16 + 1).to_be_bytes());
+    packet.push(0); // DMX start code
+    packet.extend_from_slice(data);
+    packet
+}
+
+/// The multicast group sACN receivers of `universe` listen on.
+pub fn sacn_multicast_address(universe: u16) -> SocketAddr {
+    let [high, low] = universe.to_be_bytes();
+    SocketAddr::from(([239, 255, high, low], SACN_PORT))
+}
@@ -0,0 +1,458 @@
+use crate::color::{self, parse_hex};
+use crate::data::SessionDriver;
+use crate::error::AppError;
+use crate::prefs::LegendOrder;
+use crate::simulation::Rgb;
+use eframe::egui;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+// Colors of drivers the roster lacks and the session's driver list doesn't give one for,
+// picked by driver number so a driver keeps theirs
+const FALLBACK_COLORS: [egui::Color32; 6] = [
+    egui::Color32::from_rgb(200, 200, 170),
+    egui::Color32::from_rgb(150, 170, 200),
+    egui::Color32::from_rgb(200, 160, 190),
+    egui::Color32::from_rgb(160, 200, 160),
+    egui::Color32::from_rgb(210, 180, 140),
+    egui::Color32::from_rgb(170, 150, 210),
+];
+
+/// The season whose roster is used when a session's can't be found out.
+pub const DEFAULT_SEASON: u32 = 2023;
+
+/// Which season's roster a session starts from, before its own driver list is applied.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RosterConfig {
+    pub season: Option<u32>, // Instead of the season the session ran in
+    pub dir: PathBuf,        // Rosters as <season>.toml, over the built-in ones
+}
+
+impl Default for RosterConfig {
+    fn default() -> Self {
+        RosterConfig {
+            season: None,
+            dir: PathBuf::from("rosters"),
+        }
+    }
+}
+
+/// A driver on the roster with the color used for their LED.
+#[derive(Debug, Clone)]
+pub struct DriverInfo {
+    pub number: u32,
+    pub name: String,
+    pub code: String, // Three-letter abbreviation, e.g. "VER"
+    pub team: String,
+    pub color: egui::Color32,
+}
+
+impl DriverInfo {
+    /// A driver from the session's driver list; what the list lacks is filled in like for
+    /// `placeholder`.
+    pub fn listed(driver: &SessionDriver) -> DriverInfo {
+        let placeholder = DriverInfo::placeholder(driver.driver_number);
+        DriverInfo {
+            number: driver.driver_number,
+            name: driver.full_name.clone().unwrap_or(placeholder.name),
+            code: driver.name_acronym.clone().unwrap_or(placeholder.code),
+            team: driver.team_name.clone().unwrap_or(placeholder.team),
+            color: driver
+                .team_colour
+                .as_deref()
+                .and_then(|color| parse_hex(color).ok())
+                .unwrap_or(placeholder.color),
+        }
+    }
+
+    /// A made-up entry for a driver known by number only, e.g. "Driver 3".
+    pub fn placeholder(number: u32) -> DriverInfo {
+        DriverInfo {
+            number,
+            name: format!("Driver {}", number),
+            code: number.to_string(),
+            team: "Unknown".to_string(),
+            color: FALLBACK_COLORS[number as usize % FALLBACK_COLORS.len()],
+        }
+    }
+}
+
+/// Numbers of every driver on the roster, in roster order.
+pub fn driver_numbers(driver_info: &[DriverInfo]) -> Vec<u32> {
+    driver_info.iter().map(|driver| driver.number).collect()
+}
+
+/// The roster as it was in a session. A driver the session lists by name takes the place of
+/// the roster's driver with that number, keeping the car's color; drivers among `numbers` the
+/// roster lacks are added from the list, or else made up. Returns the roster and the numbers
+/// of the made-up drivers.
+pub fn session_roster(
+    mut roster: Vec<DriverInfo>,
+    listed: &[SessionDriver],
+    numbers: impl IntoIterator<Item = u32>,
+) -> (Vec<DriverInfo>, Vec<u32>) {
+    let entry = |number: u32| listed.iter().find(|entry| entry.driver_number == number);
+    for driver in &mut roster {
+        if let Some(entry) = entry(driver.number).filter(|entry| entry.full_name.is_some()) {
+            let occupant = DriverInfo::listed(entry);
+            driver.name = occupant.name;
+            driver.code = occupant.code;
+            driver.team = occupant.team;
+        }
+    }
+
+    let mut made_up = Vec::new();
+    for number in unknown_drivers(&driver_numbers(&roster), numbers) {
+        match entry(number) {
+            Some(entry) => roster.push(DriverInfo::listed(entry)),
+            None => {
+                roster.push(DriverInfo::placeholder(number));
+                made_up.push(number);
+            }
+        }
+    }
+    (roster, made_up)
+}
+
+/// The numbers among `numbers` that aren't on the roster, sorted and once each.
+pub fn unknown_drivers(roster: &[u32], numbers: impl IntoIterator<Item = u32>) -> Vec<u32> {
+    let unknown: BTreeSet<u32> = numbers
+        .into_iter()
+        .filter(|number| !roster.contains(number))
+        .collect();
+    unknown.into_iter().collect()
+}
+
+/// Numbers of every driver on the roster in legend `order`; ties go by number. `position`
+/// gives a driver's race position for `LegendOrder::Position`.
+pub fn legend_order(
+    driver_info: &[DriverInfo],
+    order: LegendOrder,
+    position: impl Fn(u32) -> Option<u32>,
+) -> Vec<u32> {
+    let mut drivers: Vec<&DriverInfo> = driver_info.iter().collect();
+    match order {
+        LegendOrder::Number => drivers.sort_by_key(|driver| driver.number),
+        LegendOrder::Name => drivers.sort_by_key(|driver| (&driver.name, driver.number)),
+        LegendOrder::Team => drivers.sort_by_key(|driver| (&driver.team, driver.number)),
+        LegendOrder::Position => drivers.sort_by_key(|driver| {
+            // Drivers without a position go last
+            let position = position(driver.number);
+            (position.is_none(), position, driver.number)
+        }),
+    }
+    drivers.into_iter().map(|driver| driver.number).collect()
+}
+
+/// Each driver's color as plain RGB, for the simulation.
+pub fn driver_colors(driver_info: &[DriverInfo]) -> HashMap<u32, Rgb> {
+    driver_info
+        .iter()
+        .map(|driver| {
+            let color = driver.color;
+            (driver.number, [color.r(), color.g(), color.b()])
+        })
+        .collect()
+}
+
+/// The team of each driver, as the team view groups the cars.
+pub fn driver_teams(driver_info: &[DriverInfo]) -> HashMap<u32, String> {
+    driver_info
+        .iter()
+        .map(|driver| (driver.number, driver.team.clone()))
+        .collect()
+}
+
+/// Parses the `[colors]` section, driver number to `#RRGGBB`. Every bad entry is named in the
+/// error.
+pub fn color_overrides(
+    colors: &BTreeMap<String, String>,
+) -> Result<BTreeMap<u32, egui::Color32>, AppError> {
+    let mut overrides = BTreeMap::new();
+    let mut invalid = Vec::new();
+    for (key, value) in colors {
+        match (key.trim().parse::<u32>(), parse_hex(value)) {
+            (Ok(driver_number), Ok(color)) => {
+                overrides.insert(driver_number, color);
+            }
+            (Err(_), _) => invalid.push(format!("colors.{}: not a driver number", key)),
+            (_, Err(err)) => invalid.push(format!("colors.{}: {}", key, err)),
+        }
+    }
+    if !invalid.is_empty() {
+        return Err(AppError::Config {
+            reason: invalid.join(", "),
+        });
+    }
+    Ok(overrides)
+}
+
+/// Replaces the roster colors of the drivers in `overrides`.
+pub fn apply_color_overrides(
+    driver_info: &mut [DriverInfo],
+    overrides: &BTreeMap<u32, egui::Color32>,
+) {
+    for driver in driver_info {
+        if let Some(&color) = overrides.get(&driver.number) {
+            driver.color = color;
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RosterFile {
+    drivers: Vec<RosterEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RosterEntry {
+    number: u32,
+    name: String,
+    code: String,
+    team: String,
+    #[serde(with = "color::hex")]
+    color: egui::Color32,
+}
+
+/// Reads a roster from a TOML file of `[[drivers]]` tables.
+pub fn read_roster(path: &Path) -> Result<Vec<DriverInfo>, AppError> {
+    let text = std::fs::read_to_string(path)?;
+    let file: RosterFile = toml::from_str(&text).map_err(|err| AppError::Decode {
+        context: format!("roster file {}: {}", path.display(), err),
+    })?;
+    Ok(file
+        .drivers
+        .into_iter()
+        .map(|entry| DriverInfo {
+            number: entry.number,
+            name: entry.name,
+            code: entry.code,
+            team: entry.team,
+            color: entry.color,
+        })
+        .collect())
+}
+
+/// The roster of `season`: the one in the configured directory, or else the built-in one. A
+/// roster file that can't be read is warned about and passed over.
+pub fn season_roster(season: u32, config: &RosterConfig) -> Vec<DriverInfo> {
+    let path = config.dir.join(format!("{}.toml", season));
+    if path.exists() {
+        match read_roster(&path) {
+            Ok(roster) => return roster,
+            Err(err) => warn!("Using the built-in roster instead: {}", err),
+        }
+    }
+    get_driver_info_for_year(season)
+}
+
+/// The built-in roster of `year`; seasons before or after the ones built in get the first or
+/// the last of them.
+pub fn get_driver_info_for_year(year: u32) -> Vec<DriverInfo> {
+    if year >= 2024 {
+        roster_2024()
+    } else {
+        roster_2023()
+    }
+}
+
+/// The roster of the default season.
+pub fn get_driver_info() -> Vec<DriverInfo> {
+    get_driver_info_for_year(DEFAULT_SEASON)
+}
+
+// Everyone who drove in 2024 at their team of the time: Bearman at Ferrari, where he first
+// stood in, and Sargeant, Colapinto, Ricciardo and Lawson who shared two seats
+fn roster_2024() -> Vec<DriverInfo> {
+    const RED_BULL: Rgb = [54, 113, 198];
+    const MERCEDES: Rgb = [39, 244, 210];
+    const FERRARI: Rgb = [232, 0, 45];
+    const MCLAREN: Rgb = [255, 128, 0];
+    const ASTON_MARTIN: Rgb = [34, 153, 113];
+    const ALPINE: Rgb = [255, 135, 188];
+    const WILLIAMS: Rgb = [100, 196, 255];
+    const RB: Rgb = [102, 146, 255];
+    const SAUBER: Rgb = [82, 226, 82];
+    const HAAS: Rgb = [182, 186, 189];
+    let driver = |number: u32, name: &str, code: &str, team: &str, [r, g, b]: Rgb| DriverInfo {
+        number,
+        name: name.to_string(),
+        code: code.to_string(),
+        team: team.to_string(),
+        color: egui::Color32::from_rgb(r, g, b),
+    };
+    vec![
+        driver(1, "Max Verstappen", "VER", "Red Bull", RED_BULL),
+        driver(2, "Logan Sargeant", "SAR", "Williams", WILLIAMS),
+        driver(3, "Daniel Ricciardo", "RIC", "RB", RB),
+        driver(4, "Lando Norris", "NOR", "McLaren", MCLAREN),
+        driver(10, "Pierre Gasly", "GAS", "Alpine", ALPINE),
+        driver(11, "Sergio Perez", "PER", "Red Bull", RED_BULL),
+        driver(14, "Fernando Alonso", "ALO", "Aston Martin", ASTON_MARTIN),
+        driver(16, "Charles Leclerc", "LEC", "Ferrari", FERRARI),
+        driver(18, "Lance Stroll", "STR", "Aston Martin", ASTON_MARTIN),
+        driver(20, "Kevin Magnussen", "MAG", "Haas", HAAS),
+        driver(22, "Yuki Tsunoda", "TSU", "RB", RB),
+        driver(23, "Alex Albon", "ALB", "Williams", WILLIAMS),
+        driver(24, "Zhou Guanyu", "ZHO", "Kick Sauber", SAUBER),
+        driver(27, "Nico Hulkenberg", "HUL", "Haas", HAAS),
+        driver(30, "Liam Lawson", "LAW", "RB", RB),
+        driver(31, "Esteban Ocon", "OCO", "Alpine", ALPINE),
+        driver(38, "Oliver Bearman", "BEA", "Ferrari", FERRARI),
+        driver(43, "Franco Colapinto", "COL", "Williams", WILLIAMS),
+        driver(44, "Lewis Hamilton", "HAM", "Mercedes", MERCEDES),
+        driver(55, "Carlos Sainz", "SAI", "Ferrari", FERRARI),
+        driver(61, "Jack Doohan", "DOO", "Alpine", ALPINE),
+        driver(63, "George Russell", "RUS", "Mercedes", MERCEDES),
+        driver(77, "Valtteri Bottas", "BOT", "Kick Sauber", SAUBER),
+        driver(81, "Oscar Piastri", "PIA", "McLaren", MCLAREN),
+    ]
+}
+
+fn roster_2023() -> Vec<DriverInfo> {
+    vec![
+        DriverInfo {
+            number: 1,
+            name: "Max Verstappen".to_string(),
+            code: "VER".to_string(),
+            team: "Red Bull".to_string(),
+            color: egui::Color32::from_rgb(30, 65, 255),
+        },
+        DriverInfo {
+            number: 2,
+            name: "Logan Sargeant".to_string(),
+            code: "SAR".to_string(),
+            team: "Williams".to_string(),
+            color: egui::Color32::from_rgb(0, 82, 255),
+        },
+        DriverInfo {
+            number: 4,
+            name: "Lando Norris".to_string(),
+            code: "NOR".to_string(),
+            team: "McLaren".to_string(),
+            color: egui::Color32::from_rgb(255, 135, 0),
+        },
+        DriverInfo {
+            number: 10,
+            name: "Pierre Gasly".to_string(),
+            code: "GAS".to_string(),
+            team: "Alpine".to_string(),
+            color: egui::Color32::from_rgb(2, 144, 240),
+        },
+        DriverInfo {
+            number: 11,
+            name: "Sergio Perez".to_string(),
+            code: "PER".to_string(),
+            team: "Red Bull".to_string(),
+            color: egui::Color32::from_rgb(30, 65, 255),
+        },
+        DriverInfo {
+            number: 14,
+            name: "Fernando Alonso".to_string(),
+            code: "ALO".to_string(),
+            team: "Aston Martin".to_string(),
+            color: egui::Color32::from_rgb(0, 110, 120),
+        },
+        DriverInfo {
+            number: 16,
+            name: "Charles Leclerc".to_string(),
+            code: "LEC".to_string(),
+            team: "Ferrari".to_string(),
+            color: egui::Color32::from_rgb(220, 0, 0),
+        },
+        DriverInfo {
+            number: 18,
+            name: "Lance Stroll".to_string(),
+            code: "STR".to_string(),
+            team: "Aston Martin".to_string(),
+            color: egui::Color32::from_rgb(0, 110, 120),
+        },
+        DriverInfo {
+            number: 20,
+            name: "Kevin Magnussen".to_string(),
+            code: "MAG".to_string(),
+            team: "Haas".to_string(),
+            color: egui::Color32::from_rgb(160, 207, 205),
+        },
+        DriverInfo {
+            number: 22,
+            name: "Yuki Tsunoda".to_string(),
+            code: "TSU".to_string(),
+            team: "AlphaTauri".to_string(),
+            color: egui::Color32::from_rgb(60, 130, 200),
+        },
+        DriverInfo {
+            number: 23,
+            name: "Alex Albon".to_string(),
+            code: "ALB".to_string(),
+            team: "Williams".to_string(),
+            color: egui::Color32::from_rgb(0, 82, 255),
+        },
+        DriverInfo {
+            number: 24,
+            name: "Zhou Guanyu".to_string(),
+            code: "ZHO".to_string(),
+            team: "Stake F1".to_string(),
+            color: egui::Color32::from_rgb(165, 160, 155),
+        },
+        DriverInfo {
+            number: 27,
+            name: "Nico Hulkenberg".to_string(),
+            code: "HUL".to_string(),
+            team: "Haas".to_string(),
+            color: egui::Color32::from_rgb(160, 207, 205),
+        },
+        DriverInfo {
+            number: 31,
+            name: "Esteban Ocon".to_string(),
+            code: "OCO".to_string(),
+            team: "Alpine".to_string(),
+            color: egui::Color32::from_rgb(2, 144, 240),
+        },
+        DriverInfo {
+            number: 40,
+            name: "Liam Lawson".to_string(),
+            code: "LAW".to_string(),
+            team: "AlphaTauri".to_string(),
+            color: egui::Color32::from_rgb(60, 130, 200),
+        },
+        DriverInfo {
+            number: 44,
+            name: "Lewis Hamilton".to_string(),
+            code: "HAM".to_string(),
+            team: "Mercedes".to_string(),
+            color: egui::Color32::from_rgb(0, 210, 190),
+        },
+        DriverInfo {
+            number: 55,
+            name: "Carlos Sainz".to_string(),
+            code: "SAI".to_string(),
+            team: "Ferrari".to_string(),
+            color: egui::Color32::from_rgb(220, 0, 0),
+        },
+        DriverInfo {
+            number: 63,
+            name: "George Russell".to_string(),
+            code: "RUS".to_string(),
+            team: "Mercedes".to_string(),
+            color: egui::Color32::from_rgb(0, 210, 190),
+        },
+        DriverInfo {
+            number: 77,
+            name: "Valtteri Bottas".to_string(),
+            code: "BOT".to_string(),
+            team: "Stake F1".to_string(),
+            color: egui::Color32::from_rgb(165, 160, 155),
+        },
+        DriverInfo {
+            number: 81,
+            name: "Oscar Piastri".to_string(),
+            code: "PIA".to_string(),
+            team: "McLaren".to_string(),
+            color: egui::Color32::from_rgb(255, 135, 0),
+        },
+    ]
+}
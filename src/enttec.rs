@@ -0,0 +1,156 @@
+use crate::error::AppError;
+use crate::simulation::Rgb;
+use crate::sink::{LedSink, OutputFrame, DEFAULT_MAX_FPS};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::time::Duration;
+
+/// Channels in the single DMX universe an Enttec USB Pro drives.
+pub const DMX_CHANNELS: usize = 512;
+
+// Enttec USB Pro message framing
+const START_OF_MESSAGE: u8 = 0x7e;
+const END_OF_MESSAGE: u8 = 0xe7;
+const OUTPUT_ONLY_SEND_DMX: u8 = 6; // Message label
+const DMX_START_CODE: u8 = 0;
+
+// The widget is a USB virtual COM port, so the baud rate only matters to the driver
+const BAUD_RATE: u32 = 57_600;
+const WRITE_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnttecConfig {
+    pub enabled: bool,
+    pub port: String,       // Serial device of the widget, e.g. /dev/ttyUSB0 or COM3
+    pub start_channel: u16, // Channel of the first LED's red value, LEDs follow in order
+    pub patch: Option<Vec<u16>>, // Channel of each LED's red value, instead of start_channel
+    pub fps: f64,
+}
+
+impl Default for EnttecConfig {
+    fn default() -> Self {
+        EnttecConfig {
+            enabled: false,
+            port: "/dev/ttyUSB0".to_string(),
+            start_channel: 1,
+            patch: None,
+            fps: DEFAULT_MAX_FPS,
+        }
+    }
+}
+
+/// Sends frames to a DMX chain through an Enttec USB Pro widget.
+pub struct EnttecSink {
+    port: Box<dyn serialport::SerialPort>,
+    patch: Vec<usize>,
+}
+
+impl EnttecSink {
+    pub fn open(config: &EnttecConfig, led_count: usize) -> Result<EnttecSink, AppError> {
+        let patch = patch(config, led_count)?;
+        let port = serialport::new(&config.port, BAUD_RATE)
+            .timeout(WRITE_TIMEOUT)
+            .open()
+            .map_err(|err| AppError::Output {
+                reason: format!(
+                    "could not open the Enttec widget on {}: {}",
+                    config.port, err
+                ),
+            })?;
+
+        info!("Sending DMX through the Enttec widget on {}", config.port);
+        Ok(EnttecSink { port, patch })
+    }
+
+    /// Sends a frame of LED colors.
+    pub fn show(&mut self, leds: &[Rgb]) -> Result<(), AppError> {
+        let packet = enttec_packet(&dmx_channels(leds, &self.patch));
+        self.port
+            .write_all(&packet)
+            .map_err(|err| AppError::Output {
+                reason: format!("could not write to the Enttec widget: {}", err),
+            })?;
+
+        Ok(())
+    }
+}
+
+impl LedSink for EnttecSink {
+    fn name(&self) -> &str {
+        "enttec"
+    }
+
+    fn submit(&mut self, frame: &OutputFrame) -> Result<(), AppError> {
+        self.show(&frame.dimmed())
+    }
+}
+
+/// The channel index (0-based) of each LED's red value, checked to fit in one universe.
+pub fn patch(config: &EnttecConfig, led_count: usize) -> Result<Vec<usize>, AppError> {
+    let starts: Vec<u16> = match &config.patch {
+        Some(patch) if patch.len() != led_count => {
+            return Err(AppError::Config {
+                reason: format!(
+                    "enttec.patch lists {} channels but the layout has {} LEDs",
+                    patch.len(),
+                    led_count
+                ),
+            });
+        }
+        Some(patch) => patch.clone(),
+        None => {
+            let last = (config.start_channel as usize + led_count * 3).saturating_sub(1);
+            if last > DMX_CHANNELS {
+                return Err(AppError::Config {
+                    reason: format!(
+                        "{} LEDs from channel {} need channels up to {}, but a DMX universe has {}",
+                        led_count, config.start_channel, last, DMX_CHANNELS
+                    ),
+                });
+            }
+            (0..led_count)
+                .map(|led| config.start_channel + led as u16 * 3)
+                .collect()
+        }
+    };
+
+    starts
+        .into_iter()
+        .enumerate()
+        .map(|(led, start)| {
+            if start == 0 || start as usize + 2 > DMX_CHANNELS {
+                return Err(AppError::Config {
+                    reason: format!(
+                        "LED {} starts at channel {}, but its RGB values must fit in channels 1-{}",
+                        led, start, DMX_CHANNELS
+                    ),
+                });
+            }
+            Ok(start as usize - 1)
+        })
+        .collect()
+}
+
+/// Lays out LED colors in a DMX universe.
+pub fn dmx_channels(leds: &[Rgb], patch: &[usize]) -> [u8; DMX_CHANNELS] {
+    let mut channels = [0; DMX_CHANNELS];
+    for (color, &start) in leds.iter().zip(patch) {
+        channels[start..start + 3].copy_from_slice(color);
+    }
+    channels
+}
+
+/// An "Output Only Send DMX" message carrying a full universe after the null start code.
+pub fn enttec_packet(channels: &[u8; DMX_CHANNELS]) -> Vec<u8> {
+    let data_len = (DMX_CHANNELS + 1) as u16;
+    let mut packet = Vec::with_capacity(DMX_CHANNELS + 6);
+    packet.push(START_OF_MESSAGE);
+    packet.push(OUTPUT_ONLY_SEND_DMX);
+    packet.extend_from_slice(&data_len.to_le_bytes());
+    packet.push(DMX_START_CODE);
+    packet.extend_from_slice(channels);
+    packet.push(END_OF_MESSAGE);
+    packet
+}
@@ -0,0 +1,150 @@
+use reqwest::StatusCode;
+use thiserror::Error;
+
+/// Everything that can go wrong while loading and preparing race data.
+#[derive(Debug, Error)]
+pub enum AppError {
+    #[error("request to {url} failed: {source}")]
+    Network {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    #[error("HTTP {status} fetching data{} from {url}", for_driver(.driver))]
+    Http {
+        driver: Option<u32>, // None for a request about the whole session
+        status: StatusCode,
+        url: String,
+    },
+
+    #[error("could not decode {context}")]
+    Decode { context: String },
+
+    #[error("no location data for drivers {drivers:?}")]
+    EmptyData { drivers: Vec<u32> },
+
+    #[error("invalid LED layout: {reason}")]
+    LayoutInvalid { reason: String },
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error("could not start the window: {reason}")]
+    Gui { reason: String },
+
+    #[error("invalid configuration: {reason}")]
+    Config { reason: String },
+
+    #[error("LED output failed: {reason}")]
+    Output { reason: String },
+
+    #[error("playback controls failed: {reason}")]
+    Input { reason: String },
+
+    #[error("export failed: {reason}")]
+    Export { reason: String },
+
+    #[error("ghost unavailable: {reason}")]
+    Ghost { reason: String },
+
+    #[error("cache problem: {reason}")]
+    Cache { reason: String },
+
+    #[error("{failed} of {total} checks failed")]
+    CheckFailed { failed: usize, total: usize },
+}
+
+impl AppError {
+    /// Whether trying the same request again might succeed.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            AppError::Network { .. } => true,
+            AppError::Http { status, .. } => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            _ => false,
+        }
+    }
+
+    /// A message for the user that suggests what to do about the error.
+    pub fn user_message(&self) -> String {
+        match self {
+            AppError::Network { url, .. } => format!(
+                "Could not reach the OpenF1 API ({}). Check your network connection.",
+                url
+            ),
+            AppError::Http { driver, status, .. } => format!(
+                "The OpenF1 API answered {}{}. Check the session key or try again later.",
+                status,
+                for_driver(driver)
+            ),
+            AppError::Decode { context } => format!(
+                "Could not read {}. The data may be corrupt or the API format may have changed.",
+                context
+            ),
+            AppError::EmptyData { drivers } => format!(
+                "No location data was returned for drivers {:?}. Check the session key and time window.",
+                drivers
+            ),
+            AppError::LayoutInvalid { reason } => {
+                format!("The LED layout is invalid: {}. Fix the layout file.", reason)
+            }
+            AppError::Io(err) => format!("File error: {}", err),
+            AppError::Gui { reason } => format!("Could not open the window: {}", reason),
+            AppError::Config { reason } => format!(
+                "The configuration is invalid: {}. Fix the config file or command line.",
+                reason
+            ),
+            AppError::Output { reason } => format!(
+                "Could not drive the LEDs: {}. Check the wiring and output settings.",
+                reason
+            ),
+            AppError::Input { reason } => format!(
+                "Could not read the playback controls: {}. Check the gamepad or buttons and the input settings.",
+                reason
+            ),
+            AppError::Export { reason } => format!(
+                "Could not export the clip: {}. Check the output path and export settings.",
+                reason
+            ),
+            AppError::Ghost { reason } => format!(
+                "Could not show the ghost: {}. Check the ghost session, driver and alignment.",
+                reason
+            ),
+            AppError::Cache { reason } => format!(
+                "Cache problem: {}. The data is fetched again; delete the cache directory if this keeps happening.",
+                reason
+            ),
+            AppError::CheckFailed { failed, total } => format!(
+                "{} of {} checks failed. Fix the items marked FAIL above and check again.",
+                failed, total
+            ),
+        }
+    }
+
+    /// Process exit code, distinct per kind of failure.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            AppError::Network { .. } => 2,
+            AppError::Http { .. } => 3,
+            AppError::Decode { .. } => 4,
+            AppError::EmptyData { .. } => 5,
+            AppError::LayoutInvalid { .. } => 6,
+            AppError::Io(_) => 7,
+            AppError::Gui { .. } => 8,
+            AppError::Config { .. } => 9,
+            AppError::Output { .. } => 10,
+            AppError::Input { .. } => 11,
+            AppError::Export { .. } => 12,
+            AppError::Ghost { .. } => 13,
+            AppError::Cache { .. } => 14,
+            AppError::CheckFailed { .. } => 15,
+        }
+    }
+}
+
+// " for driver 44", or nothing when the error isn't about one driver
+fn for_driver(driver: &Option<u32>) -> String {
+    driver.map_or(String::new(), |driver| format!(" for driver {}", driver))
+}
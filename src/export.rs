@@ -0,0 +1,563 @@
+use crate::calibration::{correct_frame, LedCalibration};
+use crate::error::AppError;
+use crate::render::{format_duration, FrameRenderer};
+use crate::simulation::{Rgb, Simulation};
+use crate::space::LedPoint;
+use gif::{Encoder, Frame, Repeat};
+use image::{ImageFormat, RgbaImage};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+// Color quantization effort per frame, from 1 (best) to 30 (fastest)
+const GIF_QUANTIZE_SPEED: i32 = 10;
+
+// Run from the PATH for video exports
+const FFMPEG: &str = "ffmpeg";
+
+/// What an export produces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    #[default]
+    Gif,
+    /// Numbered PNG files in a directory.
+    PngSequence,
+    /// An MP4 video encoded by ffmpeg from the PATH.
+    Video,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [
+        ExportFormat::Gif,
+        ExportFormat::PngSequence,
+        ExportFormat::Video,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportFormat::Gif => "GIF",
+            ExportFormat::PngSequence => "PNG frames",
+            ExportFormat::Video => "MP4 (ffmpeg)",
+        }
+    }
+
+    /// Extension of the output path; a PNG sequence goes into a directory without one.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Gif => "gif",
+            ExportFormat::PngSequence => "",
+            ExportFormat::Video => "mp4",
+        }
+    }
+}
+
+/// Whether `ffmpeg` can be run from the PATH, which video exports need. Checked once.
+pub fn ffmpeg_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        Command::new(FFMPEG)
+            .arg("-version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    })
+}
+
+/// Where screenshots of the window are saved.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScreenshotConfig {
+    pub dir: PathBuf,
+}
+
+impl Default for ScreenshotConfig {
+    fn default() -> Self {
+        ScreenshotConfig {
+            dir: PathBuf::from("screenshots"),
+        }
+    }
+}
+
+/// File name of a screenshot, e.g. `zandvoort-2023_01-12-30.250.png` at 1:12:30.25 of race
+/// time. Characters that don't belong in a file name are replaced in `session`.
+pub fn screenshot_name(session: &str, race_time: f64) -> String {
+    let session: String = session
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+            _ => '-',
+        })
+        .collect();
+    let millis = (race_time.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{}_{}.{:03}.png",
+        session,
+        format_duration((millis / 1000) as f64).replace(':', "-"),
+        millis % 1000
+    )
+}
+
+/// Saves `image` in `dir`, which is created if needed, and returns the path of the file. A
+/// screenshot at the same race time as an earlier one, e.g. while paused, gets a counter
+/// instead of replacing it.
+pub fn save_screenshot(
+    image: &RgbaImage,
+    dir: &Path,
+    session: &str,
+    race_time: f64,
+) -> Result<PathBuf, AppError> {
+    let name = screenshot_name(session, race_time);
+    let mut path = dir.join(&name);
+    for counter in 2.. {
+        if !path.exists() {
+            break;
+        }
+        let stem = name.strip_suffix(".png").unwrap_or(&name);
+        path = dir.join(format!("{}_{}.png", stem, counter));
+    }
+    let write_error = |err: &dyn std::fmt::Display| AppError::Export {
+        reason: format!("could not write {}: {}", path.display(), err),
+    };
+    std::fs::create_dir_all(dir).map_err(|err| write_error(&err))?;
+    image
+        .save_with_format(&path, ImageFormat::Png)
+        .map_err(|err| write_error(&err))?;
+    info!("Saved screenshot {}", path.display());
+    Ok(path)
+}
+
+/// What to export: a clip of the playback rendered at a fixed size.
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    pub start: Duration,  // Race time of the first frame
+    pub length: Duration, // Length of the clip itself, which covers `length * speed` of race
+    pub speed: f64,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub led_size: f32,    // Side length of an LED square in pixels
+    pub race_clock: bool, // Burn the race time into the top left corner
+    pub labels: bool,     // Write each driver's number next to their LED
+    pub path: PathBuf,    // The file, or the directory of a PNG sequence
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            format: ExportFormat::Gif,
+            start: Duration::ZERO,
+            length: Duration::from_secs(30),
+            speed: 1.0,
+            width: 1280,
+            height: 720,
+            fps: 20,
+            led_size: 10.0,
+            race_clock: true,
+            labels: false,
+            path: PathBuf::from("race.gif"),
+        }
+    }
+}
+
+impl ExportOptions {
+    /// Number of frames in the clip.
+    pub fn frame_count(&self) -> usize {
+        (self.length.as_secs_f64() * self.fps as f64).round() as usize
+    }
+
+    fn check(&self) -> Result<(), AppError> {
+        let invalid = |reason: &str| {
+            Err(AppError::Config {
+                reason: format!("export: {}", reason),
+            })
+        };
+        if self.width == 0 || self.height == 0 {
+            return invalid("the resolution must not be zero");
+        }
+        if self.fps == 0 || self.fps > 100 {
+            return invalid("the frame rate must be between 1 and 100 fps");
+        }
+        if self.frame_count() == 0 {
+            return invalid("the clip is shorter than one frame");
+        }
+        match self.format {
+            ExportFormat::Gif if self.width > u16::MAX as u32 || self.height > u16::MAX as u32 => {
+                invalid("a GIF can be at most 65535 pixels wide and high")
+            }
+            // The video is encoded with colors subsampled in 2x2 blocks
+            ExportFormat::Video if (self.width | self.height) & 1 != 0 => {
+                invalid("a video needs an even width and height")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How a finished export ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExportOutcome {
+    Written(PathBuf),
+    Cancelled, // The partial output has been removed
+}
+
+/// An export running on a worker thread, which plays its own copy of the simulation so the
+/// window keeps going undisturbed.
+pub struct ExportJob {
+    frames: usize,
+    written: Arc<AtomicUsize>,
+    cancelled: Arc<AtomicBool>,
+    thread: JoinHandle<Result<ExportOutcome, AppError>>,
+}
+
+impl ExportJob {
+    /// Starts exporting `simulation`, colored like the window with `calibration` and
+    /// `brightness`. Hidden drivers stay hidden. The output is created before this returns,
+    /// so an unwritable path or a missing ffmpeg fails right away.
+    pub fn start(
+        simulation: &Simulation,
+        coordinates: &[LedPoint],
+        calibration: &[LedCalibration],
+        brightness: f32,
+        options: ExportOptions,
+    ) -> Result<ExportJob, AppError> {
+        options.check()?;
+        let output = Output::open(&options)?;
+        let clip = Clip {
+            simulation: simulation.clone(),
+            renderer: FrameRenderer::new(
+                coordinates,
+                options.width,
+                options.height,
+                options.led_size,
+            ),
+            calibration: calibration.to_vec(),
+            brightness,
+            options,
+        };
+
+        let frames = clip.options.frame_count();
+        let written = Arc::new(AtomicUsize::new(0));
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let progress = (Arc::clone(&written), Arc::clone(&cancelled));
+        let thread = thread::Builder::new()
+            .name("export".to_string())
+            .spawn(move || {
+                let (written, cancelled) = progress;
+                clip.export(output, &written, &cancelled)
+            })?;
+        Ok(ExportJob {
+            frames,
+            written,
+            cancelled,
+            thread,
+        })
+    }
+
+    /// Share of the frames written so far, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        self.written.load(Ordering::Relaxed) as f32 / self.frames as f32
+    }
+
+    /// Asks the worker to stop after the current frame.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Waits for the worker and returns how the export ended.
+    pub fn finish(self) -> Result<ExportOutcome, AppError> {
+        self.thread.join().unwrap_or_else(|_| {
+            Err(AppError::Export {
+                reason: "the export thread panicked".to_string(),
+            })
+        })
+    }
+}
+
+// Everything the worker needs to render the clip
+struct Clip {
+    simulation: Simulation,
+    renderer: FrameRenderer,
+    calibration: Vec<LedCalibration>,
+    brightness: f32,
+    options: ExportOptions,
+}
+
+impl Clip {
+    // Writes every frame and finishes the output, or removes it if that didn't work out
+    fn export(
+        mut self,
+        mut output: Output,
+        written: &AtomicUsize,
+        cancelled: &AtomicBool,
+    ) -> Result<ExportOutcome, AppError> {
+        let result = match self.write_frames(&mut output, written, cancelled) {
+            Ok(true) => output
+                .finish()
+                .map(|()| ExportOutcome::Written(self.options.path.clone())),
+            Ok(false) => Ok(ExportOutcome::Cancelled),
+            Err(err) => Err(err),
+        };
+        match &result {
+            Ok(ExportOutcome::Written(path)) => info!("Exported {}", path.display()),
+            Ok(ExportOutcome::Cancelled) => info!("Export cancelled"),
+            Err(_) => {}
+        }
+        if !matches!(result, Ok(ExportOutcome::Written(_))) {
+            output.discard();
+        }
+        result
+    }
+
+    // Returns false when cancelled
+    fn write_frames(
+        &mut self,
+        output: &mut Output,
+        written: &AtomicUsize,
+        cancelled: &AtomicBool,
+    ) -> Result<bool, AppError> {
+        self.simulation.set_speed(self.options.speed);
+        self.simulation.start();
+        self.simulation.seek(self.options.start);
+        let step = Duration::from_secs_f64(1.0 / self.options.fps as f64);
+        for index in 0..self.options.frame_count() {
+            if cancelled.load(Ordering::Relaxed) {
+                return Ok(false);
+            }
+            if index > 0 {
+                self.simulation.tick(step);
+            }
+            let race_time = self.options.race_clock.then(|| self.simulation.race_time());
+            let mut image = self.renderer.render(&self.colors(), race_time);
+            if self.options.labels {
+                let labels: Vec<_> = self.simulation.driver_leds().collect();
+                self.renderer.draw_labels(&mut image, &labels);
+            }
+            output.write(image, &self.options)?;
+            written.store(index + 1, Ordering::Relaxed);
+        }
+        Ok(true)
+    }
+
+    // The current frame as the window shows it: calibrated, then dimmed
+    fn colors(&self) -> Vec<Rgb> {
+        correct_frame(&self.simulation.frame().leds, 1.0, &self.calibration)
+            .into_iter()
+            .map(|color| {
+                color.map_or([0, 0, 0], |color| {
+                    color.map(|channel| (channel as f32 * self.brightness).round() as u8)
+                })
+            })
+            .collect()
+    }
+}
+
+// Where the rendered frames go
+enum Output {
+    Gif {
+        encoder: Option<Encoder<BufWriter<File>>>, // Taken when finished
+        path: PathBuf,
+    },
+    PngSequence {
+        directory: PathBuf,
+        frames: Vec<PathBuf>, // Written so far
+    },
+    Video(Ffmpeg),
+}
+
+impl Output {
+    fn open(options: &ExportOptions) -> Result<Output, AppError> {
+        match options.format {
+            ExportFormat::Gif => {
+                let file = BufWriter::new(File::create(&options.path)?);
+                let mut encoder =
+                    Encoder::new(file, options.width as u16, options.height as u16, &[])
+                        .map_err(gif_error)?;
+                encoder.set_repeat(Repeat::Infinite).map_err(gif_error)?;
+                Ok(Output::Gif {
+                    encoder: Some(encoder),
+                    path: options.path.clone(),
+                })
+            }
+            ExportFormat::PngSequence => {
+                std::fs::create_dir_all(&options.path)?;
+                Ok(Output::PngSequence {
+                    directory: options.path.clone(),
+                    frames: Vec::new(),
+                })
+            }
+            ExportFormat::Video => Ffmpeg::spawn(options).map(Output::Video),
+        }
+    }
+
+    fn write(&mut self, image: RgbaImage, options: &ExportOptions) -> Result<(), AppError> {
+        match self {
+            Output::Gif {
+                encoder: Some(encoder),
+                ..
+            } => {
+                let (width, height) = image.dimensions();
+                let mut pixels = image.into_raw();
+                let mut frame = Frame::from_rgba_speed(
+                    width as u16,
+                    height as u16,
+                    &mut pixels,
+                    GIF_QUANTIZE_SPEED,
+                );
+                // GIF delays are in hundredths of a second
+                frame.delay = (100.0 / options.fps as f64).round() as u16;
+                encoder.write_frame(&frame).map_err(gif_error)
+            }
+            Output::Gif { encoder: None, .. } => Ok(()),
+            Output::PngSequence { directory, frames } => {
+                let path = directory.join(format!("frame_{:06}.png", frames.len()));
+                image
+                    .save_with_format(&path, ImageFormat::Png)
+                    .map_err(|err| AppError::Export {
+                        reason: format!("could not write {}: {}", path.display(), err),
+                    })?;
+                frames.push(path);
+                Ok(())
+            }
+            Output::Video(ffmpeg) => ffmpeg.write(image.as_raw()),
+        }
+    }
+
+    fn finish(&mut self) -> Result<(), AppError> {
+        match self {
+            Output::Gif { encoder, .. } => {
+                if let Some(encoder) = encoder.take() {
+                    encoder.into_inner()?.flush()?;
+                }
+                Ok(())
+            }
+            Output::PngSequence { .. } => Ok(()),
+            Output::Video(ffmpeg) => ffmpeg.finish(),
+        }
+    }
+
+    // Removes what was written; the directory of a PNG sequence stays
+    fn discard(self) {
+        let remove = |path: &PathBuf| {
+            if let Err(err) = std::fs::remove_file(path) {
+                warn!("Could not remove {}: {}", path.display(), err);
+            }
+        };
+        match self {
+            Output::Gif { encoder, path } => {
+                drop(encoder);
+                remove(&path);
+            }
+            Output::PngSequence { frames, .. } => frames.iter().for_each(remove),
+            Output::Video(mut ffmpeg) => {
+                ffmpeg.kill();
+                if ffmpeg.path.exists() {
+                    remove(&ffmpeg.path);
+                }
+            }
+        }
+    }
+}
+
+fn gif_error(err: gif::EncodingError) -> AppError {
+    AppError::Export {
+        reason: format!("could not encode the GIF: {}", err),
+    }
+}
+
+// An ffmpeg process encoding raw RGBA frames from its stdin. Its stderr is collected on a
+// thread of its own, so a chatty ffmpeg can't block on a full pipe while we write frames
+struct Ffmpeg {
+    child: Child,
+    stdin: Option<ChildStdin>, // Closed to end the video
+    stderr: Option<JoinHandle<String>>,
+    path: PathBuf,
+}
+
+impl Ffmpeg {
+    fn spawn(options: &ExportOptions) -> Result<Ffmpeg, AppError> {
+        let mut child = Command::new(FFMPEG)
+            .args(["-y", "-loglevel", "error", "-f", "rawvideo"])
+            .args(["-pixel_format", "rgba"])
+            .arg("-video_size")
+            .arg(format!("{}x{}", options.width, options.height))
+            .arg("-framerate")
+            .arg(options.fps.to_string())
+            .args(["-i", "-", "-pix_fmt", "yuv420p"])
+            .arg(&options.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| AppError::Export {
+                reason: format!("could not run {}: {}", FFMPEG, err),
+            })?;
+        let stdin = child.stdin.take();
+        let stderr = child.stderr.take().map(|mut stderr| {
+            thread::spawn(move || {
+                let mut output = String::new();
+                let _ = stderr.read_to_string(&mut output);
+                output
+            })
+        });
+        Ok(Ffmpeg {
+            child,
+            stdin,
+            stderr,
+            path: options.path.clone(),
+        })
+    }
+
+    fn write(&mut self, pixels: &[u8]) -> Result<(), AppError> {
+        let Some(stdin) = &mut self.stdin else {
+            return Ok(());
+        };
+        match stdin.write_all(pixels) {
+            Ok(()) => Ok(()),
+            // ffmpeg quit early; why is in its stderr
+            Err(err) => Err(self.finish().err().unwrap_or(AppError::Export {
+                reason: format!("could not write to {}: {}", FFMPEG, err),
+            })),
+        }
+    }
+
+    // Closes stdin and waits for the video to be written
+    fn finish(&mut self) -> Result<(), AppError> {
+        drop(self.stdin.take());
+        let status = self.child.wait()?;
+        let stderr = self
+            .stderr
+            .take()
+            .and_then(|thread| thread.join().ok())
+            .unwrap_or_default();
+        if status.success() {
+            return Ok(());
+        }
+        Err(AppError::Export {
+            reason: format!("{} failed ({}): {}", FFMPEG, status, stderr.trim()),
+        })
+    }
+
+    // Stops a running ffmpeg, leaving whatever it had written
+    fn kill(&mut self) {
+        drop(self.stdin.take());
+        if let Ok(None) = self.child.try_wait() {
+            if let Err(err) = self.child.kill() {
+                warn!("Could not stop {}: {}", FFMPEG, err);
+            }
+            let _ = self.child.wait();
+        }
+    }
+}
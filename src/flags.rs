@@ -0,0 +1,247 @@
+use crate::data::RaceControlData;
+use crate::simulation::Rgb;
+use crate::space::LedPoint;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Marshal flag panels: LEDs or spots by the track that show the flag of their marshal sector.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlagPanelConfig {
+    pub panels: Vec<FlagPanel>,
+    pub blue_secs: f64, // A blue flag is shown this long, unless another flag follows sooner
+}
+
+impl Default for FlagPanelConfig {
+    fn default() -> Self {
+        FlagPanelConfig {
+            panels: Vec::new(),
+            blue_secs: 10.0,
+        }
+    }
+}
+
+/// One panel: an LED of the layout kept for it, which the outputs show too, or a spot in
+/// layout coordinates only drawn in the window.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlagPanel {
+    pub sector: u32, // Marshal sector of the race control messages, counted from 1
+    #[serde(default)]
+    pub led: Option<usize>,
+    #[serde(default)]
+    pub x: Option<f64>,
+    #[serde(default)]
+    pub y: Option<f64>,
+}
+
+impl FlagPanel {
+    /// Where the panel is drawn when it has no LED.
+    pub fn spot(&self) -> Option<LedPoint> {
+        self.x.zip(self.y).map(|(x, y)| LedPoint::new(x, y))
+    }
+}
+
+/// The flag a panel shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlagState {
+    Green,
+    Yellow,
+    DoubleYellow,
+    Blue,
+    Red,
+}
+
+impl FlagState {
+    // The state for a race control flag; `None` for flags that aren't shown on panels, like
+    // the chequered flag
+    fn from_flag(flag: &str) -> Option<FlagState> {
+        match flag {
+            "GREEN" | "CLEAR" => Some(FlagState::Green),
+            "YELLOW" => Some(FlagState::Yellow),
+            "DOUBLE YELLOW" => Some(FlagState::DoubleYellow),
+            "BLUE" => Some(FlagState::Blue),
+            "RED" => Some(FlagState::Red),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FlagState::Green => "Green",
+            FlagState::Yellow => "Yellow",
+            FlagState::DoubleYellow => "Double yellow",
+            FlagState::Blue => "Blue",
+            FlagState::Red => "Red",
+        }
+    }
+
+    /// The color of the panel, a double yellow blinking between yellow and off.
+    pub fn color(self, blink_on: bool) -> Option<Rgb> {
+        match self {
+            FlagState::Green => Some([0, 200, 0]),
+            FlagState::Yellow => Some([255, 200, 0]),
+            FlagState::DoubleYellow => blink_on.then_some([255, 200, 0]),
+            FlagState::Blue => Some([0, 80, 255]),
+            FlagState::Red => Some([255, 0, 0]),
+        }
+    }
+}
+
+/// The flag of every marshal sector over the session, from the race control messages. Sector
+/// flags hold until the sector is cleared, a track green or clear clears every sector, and a
+/// red flag covers the whole track until the next track message. Blue flags pass after a while.
+#[derive(Debug, Clone, Default)]
+pub struct FlagTimeline {
+    track: Vec<(DateTime<Utc>, FlagState)>, // Sorted by date
+    sectors: BTreeMap<u32, Vec<(DateTime<Utc>, FlagState)>>, // Sorted by date
+    blue: ChronoDuration,
+}
+
+impl FlagTimeline {
+    /// `messages` must be sorted by date.
+    pub fn new(messages: &[RaceControlData], config: &FlagPanelConfig) -> FlagTimeline {
+        let mut timeline = FlagTimeline {
+            blue: ChronoDuration::milliseconds((config.blue_secs * 1000.0) as i64),
+            ..FlagTimeline::default()
+        };
+        for message in messages {
+            let Some(state) = message.flag.as_deref().and_then(FlagState::from_flag) else {
+                continue;
+            };
+            match (message.scope.as_deref(), message.sector) {
+                (Some("Track"), _) if state != FlagState::Blue => {
+                    timeline.track.push((message.date, state))
+                }
+                (Some("Sector") | None, Some(sector)) => timeline
+                    .sectors
+                    .entry(sector)
+                    .or_default()
+                    .push((message.date, state)),
+                // A driver's blue flag without a sector has no panel to go on
+                _ => {}
+            }
+        }
+        timeline
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.track.is_empty() && self.sectors.is_empty()
+    }
+
+    /// The flag of `sector` at `time`; green before any message.
+    pub fn flag_at(&self, sector: u32, time: DateTime<Utc>) -> FlagState {
+        let track = last_until(&self.track, time);
+        if let Some((_, FlagState::Red)) = track {
+            return FlagState::Red;
+        }
+        let changes = self.sectors.get(&sector).map_or(&[][..], Vec::as_slice);
+        let end = changes.partition_point(|&(date, _)| date <= time);
+        // The latest sector flag still standing; a blue one passes by itself
+        let local = changes[..end]
+            .iter()
+            .rev()
+            .find(|&&(date, state)| state != FlagState::Blue || time < date + self.blue);
+        match (track, local) {
+            (Some(&(track_date, state)), Some(&(date, _))) if track_date >= date => state,
+            (_, Some(&(_, state))) => state,
+            (Some(&(_, state)), None) => state,
+            (None, None) => FlagState::Green,
+        }
+    }
+
+    /// The flags out at `time` other than green: the track's first, then those of the sectors
+    /// that differ from it, by sector. A red flag is the only one out while it lasts.
+    pub fn active_at(&self, time: DateTime<Utc>) -> Vec<(Option<u32>, FlagState)> {
+        let track = last_until(&self.track, time)
+            .map(|&(_, state)| state)
+            .filter(|&state| state != FlagState::Green);
+        if track == Some(FlagState::Red) {
+            return vec![(None, FlagState::Red)];
+        }
+        let mut active: Vec<(Option<u32>, FlagState)> =
+            track.map(|state| (None, state)).into_iter().collect();
+        for &sector in self.sectors.keys() {
+            let state = self.flag_at(sector, time);
+            if state != FlagState::Green && Some(state) != track {
+                active.push((Some(sector), state));
+            }
+        }
+        active
+    }
+}
+
+fn last_until(
+    changes: &[(DateTime<Utc>, FlagState)],
+    time: DateTime<Utc>,
+) -> Option<&(DateTime<Utc>, FlagState)> {
+    changes[..changes.partition_point(|&(date, _)| date <= time)].last()
+}
+
+/// The panels with the flags they show, drawn over the frame as the race clock moves.
+#[derive(Debug, Clone, Default)]
+pub struct FlagPanels {
+    panels: Vec<FlagPanel>,
+    timeline: FlagTimeline,
+}
+
+// A double yellow blinks this many times a second of race time
+const DOUBLE_YELLOW_BLINKS_PER_SEC: i64 = 2;
+
+impl FlagPanels {
+    /// The configured panels with an LED of the layout or a spot; the others are left out
+    /// with a warning.
+    pub fn new(config: &FlagPanelConfig, timeline: FlagTimeline, led_count: usize) -> FlagPanels {
+        let panels = config
+            .panels
+            .iter()
+            .filter(|panel| match (panel.led, panel.spot()) {
+                (Some(led), _) if led >= led_count => {
+                    warn!(
+                        "Ignoring the flag panel of sector {}: the layout has no LED {}",
+                        panel.sector, led
+                    );
+                    false
+                }
+                (None, None) => {
+                    warn!(
+                        "Ignoring the flag panel of sector {}: it needs an led or both x and y",
+                        panel.sector
+                    );
+                    false
+                }
+                _ => true,
+            })
+            .cloned()
+            .collect();
+        FlagPanels { panels, timeline }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.panels.is_empty()
+    }
+
+    /// Each panel with its flag at `time`.
+    pub fn states(&self, time: DateTime<Utc>) -> impl Iterator<Item = (&FlagPanel, FlagState)> {
+        self.panels
+            .iter()
+            .map(move |panel| (panel, self.timeline.flag_at(panel.sector, time)))
+    }
+
+    /// Whether a double yellow shows lit at `time`.
+    pub fn blink_on(time: DateTime<Utc>) -> bool {
+        let half_blinks = time.timestamp_millis() * DOUBLE_YELLOW_BLINKS_PER_SEC * 2 / 1000;
+        half_blinks & 1 == 0
+    }
+
+    /// Lights the LEDs of the panels that have one in their flag's color.
+    pub fn overlay(&self, leds: &mut [Option<Rgb>], time: DateTime<Utc>) {
+        let blink_on = FlagPanels::blink_on(time);
+        for (panel, state) in self.states(time) {
+            if let Some(led) = panel.led.and_then(|index| leds.get_mut(index)) {
+                *led = state.color(blink_on);
+            }
+        }
+    }
+}
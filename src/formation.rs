@@ -0,0 +1,132 @@
+use crate::laps::RaceProgress;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The starting grid shown before lights out, instead of the cars bunched on the LEDs nearest
+/// to where they form up.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormationConfig {
+    pub enabled: bool,
+    pub handover_secs: f64, // After lights out the cars move from their slots onto their LEDs
+    pub row_gap: bool,      // Leave an LED between the rows of two, if the grid fits
+}
+
+impl Default for FormationConfig {
+    fn default() -> Self {
+        FormationConfig {
+            enabled: true,
+            handover_secs: 3.0,
+            row_gap: true,
+        }
+    }
+}
+
+/// When the race starts: the earliest start of a first lap or, without one, the first
+/// "GREEN LIGHT" of race control.
+pub fn lights_out(progress: &RaceProgress) -> Option<DateTime<Utc>> {
+    let first_lap = progress
+        .drivers()
+        .into_iter()
+        .filter_map(|driver_number| progress.lap_start(driver_number, 1))
+        .min();
+    first_lap.or_else(|| {
+        progress
+            .race_control()
+            .iter()
+            .find(|message| message.message.contains("GREEN LIGHT"))
+            .map(|message| message.date)
+    })
+}
+
+/// LEDs behind the start/finish line, LED 0, of the grid slot of `position`. Slots go back two
+/// by two like the grid's staggered columns, with an LED between the rows if `row_gap`.
+pub fn grid_slot(position: u32, led_count: usize, row_gap: bool) -> usize {
+    let index = position.saturating_sub(1) as usize;
+    let behind = if row_gap { index + index / 2 } else { index } + 1;
+    (led_count - behind % led_count) % led_count
+}
+
+/// The field on its grid slots until lights out, then handed over to where the mapping puts
+/// each car.
+#[derive(Debug, Clone)]
+pub struct GridFormation {
+    lights_out: DateTime<Utc>,
+    handover: ChronoDuration,
+    slots: HashMap<u32, usize>, // LED of each car's slot, from their first position
+    led_count: usize,
+}
+
+impl GridFormation {
+    /// Lines the cars up in the order of their first position. `None` without a start or
+    /// positions to go by.
+    pub fn new(
+        progress: &RaceProgress,
+        led_count: usize,
+        config: &FormationConfig,
+    ) -> Option<GridFormation> {
+        let lights_out = lights_out(progress)?;
+        let grid: Vec<(u32, u32)> = progress
+            .drivers()
+            .into_iter()
+            .filter_map(|driver_number| {
+                Some((driver_number, progress.starting_position(driver_number)?))
+            })
+            .collect();
+        if grid.is_empty() || led_count == 0 {
+            return None;
+        }
+        // Gaps between the rows only while the grid takes up less than half the layout
+        let rows = grid.len().div_ceil(2);
+        let row_gap = config.row_gap && grid.len() + rows < led_count / 2;
+        let slots = grid
+            .into_iter()
+            .map(|(driver_number, position)| {
+                (driver_number, grid_slot(position, led_count, row_gap))
+            })
+            .collect();
+        Some(GridFormation {
+            lights_out,
+            handover: ChronoDuration::milliseconds((config.handover_secs * 1000.0) as i64),
+            slots,
+            led_count,
+        })
+    }
+
+    pub fn lights_out(&self) -> DateTime<Utc> {
+        self.lights_out
+    }
+
+    /// Whether cars are shown anywhere but on their mapped LEDs at `date`.
+    pub fn is_active(&self, date: DateTime<Utc>) -> bool {
+        date < self.lights_out + self.handover
+    }
+
+    pub fn slot(&self, driver_number: u32) -> Option<usize> {
+        self.slots.get(&driver_number).copied()
+    }
+
+    /// The LED the driver is shown on at `date` when the mapping puts them on `mapped`: their
+    /// slot before lights out, moving the short way round onto `mapped` during the handover.
+    pub fn led(&self, driver_number: u32, mapped: usize, date: DateTime<Utc>) -> usize {
+        let Some(slot) = self.slot(driver_number) else {
+            return mapped;
+        };
+        if date < self.lights_out {
+            return slot;
+        }
+        if !self.is_active(date) {
+            return mapped;
+        }
+        let share = (date - self.lights_out).num_milliseconds() as f64
+            / self.handover.num_milliseconds() as f64;
+        let count = self.led_count as i64;
+        let mut distance = (mapped as i64 - slot as i64).rem_euclid(count);
+        if distance > count / 2 {
+            distance -= count;
+        }
+        let step = (distance as f64 * share).round() as i64;
+        (slot as i64 + step).rem_euclid(count) as usize
+    }
+}
@@ -0,0 +1,164 @@
+use crate::error::AppError;
+use crate::laps::RaceProgress;
+use crate::mapping::RunRace;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// How the ghost's session is lined up with the one being played.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GhostAlignment {
+    /// Both sessions' first samples at the same time.
+    #[default]
+    SessionStart,
+    /// The start of the ghost's `ghost_lap` with the start of the driver's `lap`.
+    LapStart,
+    /// The first samples `offset_secs` apart.
+    Manual,
+}
+
+impl GhostAlignment {
+    pub const ALL: [GhostAlignment; 3] = [
+        GhostAlignment::SessionStart,
+        GhostAlignment::LapStart,
+        GhostAlignment::Manual,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GhostAlignment::SessionStart => "Session start",
+            GhostAlignment::LapStart => "Lap start",
+            GhostAlignment::Manual => "Manual offset",
+        }
+    }
+}
+
+/// A driver from a second session to show as a ghost.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GhostConfig {
+    pub enabled: bool, // Load the ghost at startup and show it
+    pub session_key: String,
+    pub driver_number: u32,
+    pub alignment: GhostAlignment,
+    pub lap: u32,         // Lap of the played session, for `lap_start`
+    pub ghost_lap: u32,   // Lap of the ghost's session, for `lap_start`
+    pub offset_secs: f64, // How far the ghost runs ahead, for `manual`
+}
+
+impl Default for GhostConfig {
+    fn default() -> Self {
+        GhostConfig {
+            enabled: false,
+            session_key: String::new(),
+            driver_number: 1,
+            alignment: GhostAlignment::SessionStart,
+            lap: 1,
+            ghost_lap: 1,
+            offset_secs: 0.0,
+        }
+    }
+}
+
+/// What the ghost plays: one driver's mapped samples of their session, and its laps.
+#[derive(Debug, Clone)]
+pub struct GhostRun {
+    pub driver_number: u32,
+    pub samples: Vec<RunRace>, // Sorted by date
+    pub progress: RaceProgress,
+}
+
+/// A driver of another session advancing on their own timeline, shifted to line up with the
+/// clock of the played session.
+#[derive(Debug, Clone)]
+pub struct Ghost {
+    run: GhostRun,
+    shift: ChronoDuration, // From a date of the played session to the ghost's
+    index: usize,          // Samples before this index are at or before the last looked-up date
+}
+
+impl Ghost {
+    pub fn new(run: GhostRun) -> Ghost {
+        Ghost {
+            run,
+            shift: ChronoDuration::zero(),
+            index: 0,
+        }
+    }
+
+    pub fn driver_number(&self) -> u32 {
+        self.run.driver_number
+    }
+
+    /// Lines the ghost up with the played session, which started at `start`; lap start
+    /// alignment needs the played session's laps in `progress`.
+    pub fn align(
+        &mut self,
+        config: &GhostConfig,
+        start: DateTime<Utc>,
+        progress: Option<&RaceProgress>,
+    ) -> Result<(), AppError> {
+        let driver_number = self.run.driver_number;
+        let ghost_start = self
+            .run
+            .samples
+            .first()
+            .map(|run_data| run_data.date)
+            .ok_or_else(|| AppError::Ghost {
+                reason: format!("driver {} has no samples", driver_number),
+            })?;
+        let (anchor, ghost_anchor, offset_secs) = match config.alignment {
+            GhostAlignment::SessionStart => (start, ghost_start, 0.0),
+            GhostAlignment::Manual => (start, ghost_start, config.offset_secs),
+            GhostAlignment::LapStart => {
+                let anchor = progress
+                    .and_then(|progress| progress.lap_start(driver_number, config.lap))
+                    .ok_or_else(|| AppError::Ghost {
+                        reason: format!(
+                            "the start of lap {} of driver {} isn't known",
+                            config.lap, driver_number
+                        ),
+                    })?;
+                let ghost_anchor = self
+                    .run
+                    .progress
+                    .lap_start(driver_number, config.ghost_lap)
+                    .ok_or_else(|| AppError::Ghost {
+                        reason: format!(
+                            "the start of the ghost's lap {} isn't known",
+                            config.ghost_lap
+                        ),
+                    })?;
+                (anchor, ghost_anchor, 0.0)
+            }
+        };
+        self.shift = ghost_anchor - anchor
+            + ChronoDuration::milliseconds((offset_secs * 1000.0).round() as i64);
+        self.index = 0;
+        Ok(())
+    }
+
+    /// The ghost's LED at `date` of the played session; `None` before its first sample and after
+    /// its last one. Moving forward walks on from the previous lookup, moving backward searches
+    /// again.
+    pub fn led_at(&mut self, date: DateTime<Utc>) -> Option<usize> {
+        let ghost_date = date + self.shift;
+        let samples = &self.run.samples;
+        if self
+            .index
+            .checked_sub(1)
+            .is_some_and(|last| samples[last].date > ghost_date)
+        {
+            self.index = samples.partition_point(|run_data| run_data.date <= ghost_date);
+        }
+        while self.index < samples.len() && samples[self.index].date <= ghost_date {
+            self.index += 1;
+        }
+        if samples.last().is_some_and(|last| last.date < ghost_date) {
+            return None;
+        }
+        self.index
+            .checked_sub(1)
+            .map(|index| samples[index].led_index)
+    }
+}
@@ -0,0 +1,576 @@
+use crate::gui::race_control::speed_slider;
+use crate::{join_worker, PlotApp, Toast};
+use chrono::Utc;
+use eframe::egui;
+use f1_led_circuit_master_simulation::bundle::{export_bundle, Bundle, BundleConfig};
+use f1_led_circuit_master_simulation::config::Config;
+use f1_led_circuit_master_simulation::driver_info::driver_colors;
+use f1_led_circuit_master_simulation::error::AppError;
+use f1_led_circuit_master_simulation::export::{
+    ffmpeg_available, save_screenshot, ExportFormat, ExportJob, ExportOptions, ExportOutcome,
+    ScreenshotConfig,
+};
+use f1_led_circuit_master_simulation::heatmap::{export_heatmap, Heatmap, HeatmapConfig};
+use f1_led_circuit_master_simulation::lap_chart::{export_lap_chart, LapChart, LapChartConfig};
+use f1_led_circuit_master_simulation::mapping::{layout_length, MAPPING_VERSION};
+use f1_led_circuit_master_simulation::occupancy::{export_occupancy, Occupancy, OccupancyConfig};
+use f1_led_circuit_master_simulation::parquet_export::{ParquetConfig, ParquetJob};
+use f1_led_circuit_master_simulation::render::FrameRenderer;
+use log::error;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// The export window and the exports started from anywhere in the window, with the jobs
+/// writing them.
+pub struct Exports {
+    pub shown: bool,
+    pub options: ExportOptions,
+    pub job: Option<ExportJob>,
+    pub message: Option<String>, // How the last export ended
+    pub screenshot: ScreenshotConfig,
+    pub occupancy: OccupancyConfig,
+    pub occupancy_job: Option<JoinHandle<Result<Occupancy, AppError>>>,
+    pub lap_chart: LapChartConfig,
+    pub lap_chart_job: Option<JoinHandle<Result<LapChart, AppError>>>,
+    pub heatmap: HeatmapConfig,
+    pub heatmap_driver: Option<u32>, // Whose time the heatmap shows; everybody's when `None`
+    pub heatmap_job: Option<JoinHandle<Result<(), AppError>>>,
+    pub parquet: ParquetConfig,
+    pub parquet_job: Option<ParquetJob>,
+    pub bundle: BundleConfig,
+    pub bundle_job: Option<JoinHandle<Result<PathBuf, AppError>>>,
+}
+
+impl Exports {
+    pub fn new(config: &Config, led_size: f32) -> Exports {
+        Exports {
+            shown: false,
+            options: ExportOptions {
+                led_size,
+                ..ExportOptions::default()
+            },
+            job: None,
+            message: None,
+            screenshot: config.screenshot.clone(),
+            occupancy: config.occupancy.clone(),
+            occupancy_job: None,
+            lap_chart: config.lap_chart.clone(),
+            lap_chart_job: None,
+            heatmap: config.heatmap.clone(),
+            heatmap_driver: None,
+            heatmap_job: None,
+            parquet: config.parquet.clone(),
+            parquet_job: None,
+            bundle: config.bundle.clone(),
+            bundle_job: None,
+        }
+    }
+}
+
+impl PlotApp {
+    pub fn export_window(&mut self, ctx: &egui::Context) {
+        let mut show_export = self.exports.shown;
+        egui::Window::new("Export")
+            .open(&mut show_export)
+            .show(ctx, |ui| self.export_ui(ui));
+        self.exports.shown = show_export;
+    }
+
+    // Reports the exports that finished since the last frame
+    pub fn poll_exports(&mut self) {
+        if self
+            .exports
+            .occupancy_job
+            .as_ref()
+            .is_some_and(JoinHandle::is_finished)
+        {
+            self.finish_occupancy_export();
+        }
+
+        if self
+            .exports
+            .lap_chart_job
+            .as_ref()
+            .is_some_and(JoinHandle::is_finished)
+        {
+            self.finish_lap_chart_export();
+        }
+        if self
+            .exports
+            .heatmap_job
+            .as_ref()
+            .is_some_and(JoinHandle::is_finished)
+        {
+            self.finish_heatmap_export();
+        }
+        if self
+            .exports
+            .job
+            .as_ref()
+            .is_some_and(ExportJob::is_finished)
+        {
+            self.finish_export();
+        }
+        if self
+            .exports
+            .parquet_job
+            .as_ref()
+            .is_some_and(ParquetJob::is_finished)
+        {
+            self.finish_parquet_export();
+        }
+        if self
+            .exports
+            .bundle_job
+            .as_ref()
+            .is_some_and(JoinHandle::is_finished)
+        {
+            self.finish_bundle_export();
+        }
+    }
+
+    fn start_export(&mut self) {
+        match ExportJob::start(
+            &self.simulation,
+            &self.view_coordinates,
+            &self.calibration,
+            self.brightness,
+            self.exports.options.clone(),
+        ) {
+            Ok(job) => {
+                self.exports.job = Some(job);
+                self.exports.message = None;
+            }
+            Err(err) => {
+                error!("Could not start the export: {}", err);
+                self.exports.message = Some(err.user_message());
+            }
+        }
+    }
+
+    // Renders the track view as it is shown, zoomed and panned by the camera, at the screen's
+    // pixel density, with the race clock
+    pub fn take_screenshot(&mut self, ctx: &egui::Context) {
+        let scale = ctx.pixels_per_point();
+        let size = self.track_size * scale;
+        let renderer = FrameRenderer::with_camera(
+            &self.view_coordinates,
+            size.x.round() as u32,
+            size.y.round() as u32,
+            self.display.led_size * scale,
+            self.camera.camera(),
+        );
+        let colors = match &*self.shown_frame.lock().unwrap() {
+            Some(frame) => frame.dimmed(),
+            None => Vec::new(),
+        };
+        let race_time = self.simulation.clock_time();
+        let image = renderer.render(&colors, Some(race_time));
+        self.push_toast(
+            match save_screenshot(
+                &image,
+                &self.exports.screenshot.dir,
+                &self.session_name,
+                race_time,
+            ) {
+                Ok(path) => Toast::info(format!("Saved {}", path.display())),
+                Err(err) => {
+                    error!("Could not save the screenshot: {}", err);
+                    Toast::error(err.user_message())
+                }
+            },
+        );
+    }
+
+    pub fn start_occupancy_export(&mut self) {
+        let run_race_data = self.simulation.run_race_data().to_vec();
+        match export_occupancy(
+            run_race_data,
+            self.coordinates.len(),
+            layout_length(&self.coordinates),
+            &self.exports.occupancy,
+            &self.speed,
+        ) {
+            Ok(job) => self.exports.occupancy_job = Some(job),
+            Err(err) => {
+                error!("Could not start the occupancy export: {}", err);
+                self.push_toast(Toast::error(err.user_message()));
+            }
+        }
+    }
+
+    fn finish_occupancy_export(&mut self) {
+        let Some(job) = self.exports.occupancy_job.take() else {
+            return;
+        };
+        self.push_toast(match join_worker(job, "occupancy") {
+            Ok(occupancy) => {
+                let never_lit = occupancy.never_lit();
+                let mut labels: Vec<String> = never_lit
+                    .iter()
+                    .take(10)
+                    .map(|index| format!("U{}", index + 1))
+                    .collect();
+                if never_lit.len() > labels.len() {
+                    labels.push("...".to_string());
+                }
+                let summary = match never_lit.len() {
+                    0 => "every LED was lit".to_string(),
+                    count => format!("{} LEDs never lit: {}", count, labels.join(", ")),
+                };
+                Toast::info(format!(
+                    "Saved {}; {}",
+                    self.exports.occupancy.path.display(),
+                    summary
+                ))
+            }
+            Err(err) => {
+                error!("Occupancy export failed: {}", err);
+                Toast::error(err.user_message())
+            }
+        });
+    }
+
+    pub fn start_lap_chart_export(&mut self) {
+        let Some(progress) = &self.race_progress else {
+            return;
+        };
+        match export_lap_chart(
+            progress,
+            driver_colors(&self.driver_info),
+            &self.exports.lap_chart,
+        ) {
+            Ok(job) => self.exports.lap_chart_job = Some(job),
+            Err(err) => {
+                error!("Could not start the lap chart export: {}", err);
+                self.push_toast(Toast::error(err.user_message()));
+            }
+        }
+    }
+
+    fn finish_lap_chart_export(&mut self) {
+        let Some(job) = self.exports.lap_chart_job.take() else {
+            return;
+        };
+        self.push_toast(match join_worker(job, "lap chart") {
+            Ok(chart) => Toast::info(format!(
+                "Saved the lap chart of {} laps to {} and {}",
+                chart.laps(),
+                self.exports.lap_chart.csv.display(),
+                self.exports.lap_chart.png.display()
+            )),
+            Err(err) => {
+                error!("Lap chart export failed: {}", err);
+                Toast::error(err.user_message())
+            }
+        });
+    }
+
+    // Switches the heatmap on for `heatmap_driver`, or off
+    pub fn show_heatmap(&mut self, shown: bool) {
+        let heatmap = shown.then(|| {
+            Heatmap::new(
+                self.coordinates.len(),
+                self.exports.heatmap_driver,
+                self.exports.heatmap.max_gap_secs,
+            )
+        });
+        self.simulation.set_heatmap(heatmap);
+    }
+
+    pub fn start_heatmap_export(&mut self) {
+        let run_race_data = self.simulation.run_race_data().to_vec();
+        match export_heatmap(
+            run_race_data,
+            self.exports.heatmap_driver,
+            &self.coordinates,
+            &self.exports.heatmap,
+        ) {
+            Ok(job) => self.exports.heatmap_job = Some(job),
+            Err(err) => {
+                error!("Could not start the heatmap export: {}", err);
+                self.push_toast(Toast::error(err.user_message()));
+            }
+        }
+    }
+
+    fn finish_heatmap_export(&mut self) {
+        let Some(job) = self.exports.heatmap_job.take() else {
+            return;
+        };
+        self.push_toast(match join_worker(job, "heatmap") {
+            Ok(()) => Toast::info(format!(
+                "Saved the heatmap to {} and {}",
+                self.exports.heatmap.csv.display(),
+                self.exports.heatmap.png.display()
+            )),
+            Err(err) => {
+                error!("Heatmap export failed: {}", err);
+                Toast::error(err.user_message())
+            }
+        });
+    }
+
+    pub fn start_parquet_export(&mut self) {
+        let run_race_data = self.simulation.run_race_data().to_vec();
+        match ParquetJob::start(run_race_data, self.exports.parquet.path.clone()) {
+            Ok(job) => self.exports.parquet_job = Some(job),
+            Err(err) => {
+                error!("Could not start the Parquet export: {}", err);
+                self.push_toast(Toast::error(err.user_message()));
+            }
+        }
+    }
+
+    fn finish_parquet_export(&mut self) {
+        let Some(job) = self.exports.parquet_job.take() else {
+            return;
+        };
+        let rows = job.rows();
+        self.push_toast(match job.finish() {
+            Ok(path) => Toast::info(format!("Saved {} samples to {}", rows, path.display())),
+            Err(err) => {
+                error!("Parquet export failed: {}", err);
+                Toast::error(err.user_message())
+            }
+        });
+    }
+
+    pub fn start_bundle_export(&mut self) {
+        let bundle = Bundle {
+            created: Utc::now(),
+            mapping_version: MAPPING_VERSION,
+            session: self.session.clone(),
+            coordinates: self.coordinates.clone(),
+            driver_info: self.driver_info.clone(),
+            mapping_options: self
+                .data_source
+                .as_ref()
+                .map(|source| source.mapping.clone())
+                .unwrap_or_default(),
+            run_race_data: self.simulation.run_race_data().to_vec(),
+            mapping_stats: self.mapping_stats.clone(),
+            race_progress: self.race_progress.as_deref().cloned(),
+        };
+        match export_bundle(bundle, &self.exports.bundle.dir, &self.session_name) {
+            Ok(job) => self.exports.bundle_job = Some(job),
+            Err(err) => {
+                error!("Could not start the bundle export: {}", err);
+                self.push_toast(Toast::error(err.user_message()));
+            }
+        }
+    }
+
+    fn finish_bundle_export(&mut self) {
+        let Some(job) = self.exports.bundle_job.take() else {
+            return;
+        };
+        self.push_toast(match join_worker(job, "bundle") {
+            Ok(path) => Toast::info(format!("Saved the session to {}", path.display())),
+            Err(err) => {
+                error!("Bundle export failed: {}", err);
+                Toast::error(err.user_message())
+            }
+        });
+    }
+
+    pub fn heatmap_toggle_ui(&mut self, ui: &mut egui::Ui) {
+        let mut shown = self.simulation.heatmap().is_some();
+        if ui.toggle_value(&mut shown, "HEATMAP").changed() {
+            self.show_heatmap(shown);
+        }
+        if !shown {
+            return;
+        }
+        let previous = self.exports.heatmap_driver;
+        let selected = match self.exports.heatmap_driver {
+            Some(driver_number) => driver_number.to_string(),
+            None => "All drivers".to_string(),
+        };
+        egui::ComboBox::from_id_source("heatmap_driver")
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.exports.heatmap_driver, None, "All drivers");
+                for driver in &self.driver_info {
+                    ui.selectable_value(
+                        &mut self.exports.heatmap_driver,
+                        Some(driver.number),
+                        format!("{}: {}", driver.number, driver.name),
+                    );
+                }
+            });
+        if self.exports.heatmap_driver != previous {
+            self.show_heatmap(true);
+        }
+    }
+
+    fn finish_export(&mut self) {
+        let Some(job) = self.exports.job.take() else {
+            return;
+        };
+        self.exports.message = Some(match job.finish() {
+            Ok(ExportOutcome::Written(path)) => format!("Saved {}", path.display()),
+            Ok(ExportOutcome::Cancelled) => "Export cancelled".to_string(),
+            Err(err) => {
+                error!("Export failed: {}", err);
+                err.user_message()
+            }
+        });
+    }
+
+    fn export_ui(&mut self, ui: &mut egui::Ui) {
+        let options = &mut self.exports.options;
+        ui.add_enabled_ui(self.exports.job.is_none(), |ui| {
+            egui::Grid::new("export_options").show(ui, |ui| {
+                ui.label("Format");
+                let previous_format = options.format;
+                egui::ComboBox::from_id_source("export_format")
+                    .selected_text(options.format.label())
+                    .show_ui(ui, |ui| {
+                        for format in ExportFormat::ALL {
+                            let available = format != ExportFormat::Video || ffmpeg_available();
+                            ui.add_enabled_ui(available, |ui| {
+                                ui.selectable_value(&mut options.format, format, format.label())
+                                    .on_disabled_hover_text("ffmpeg was not found on the PATH");
+                            });
+                        }
+                    });
+                if options.format != previous_format {
+                    options.path.set_extension(options.format.extension());
+                }
+                ui.end_row();
+
+                ui.label("Start (s)");
+                duration_field(ui, &mut options.start, 0.0..=f64::MAX);
+                ui.end_row();
+
+                ui.label("Length (s)");
+                duration_field(ui, &mut options.length, 0.1..=600.0);
+                ui.end_row();
+
+                ui.label("Speed");
+                ui.add(speed_slider(&mut options.speed, self.speed_range.clone()));
+                ui.end_row();
+
+                ui.label("Resolution");
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut options.width).clamp_range(16..=3840));
+                    ui.label("x");
+                    ui.add(egui::DragValue::new(&mut options.height).clamp_range(16..=2160));
+                });
+                ui.end_row();
+
+                ui.label("FPS");
+                ui.add(egui::DragValue::new(&mut options.fps).clamp_range(1..=50));
+                ui.end_row();
+
+                ui.label("LED size (px)");
+                ui.add(egui::Slider::new(&mut options.led_size, 1.0..=40.0));
+                ui.end_row();
+
+                ui.label(match options.format {
+                    ExportFormat::PngSequence => "Directory",
+                    _ => "File",
+                });
+                let mut path = options.path.display().to_string();
+                if ui.text_edit_singleline(&mut path).changed() {
+                    options.path = path.into();
+                }
+                ui.end_row();
+            });
+            ui.checkbox(&mut options.race_clock, "Race clock");
+            ui.checkbox(&mut options.labels, "Driver numbers");
+        });
+
+        ui.separator();
+        match &self.exports.job {
+            Some(job) => {
+                ui.add(egui::ProgressBar::new(job.progress()).show_percentage());
+                if ui.button("CANCEL").clicked() {
+                    job.cancel();
+                }
+            }
+            None => {
+                if ui.button("EXPORT").clicked() {
+                    self.start_export();
+                }
+            }
+        }
+        if let Some(message) = &self.exports.message {
+            ui.label(message);
+        }
+
+        ui.separator();
+        let available = self.race_progress.is_some();
+        let lap_chart = ui
+            .add_enabled(
+                available && self.exports.lap_chart_job.is_none(),
+                egui::Button::new("EXPORT LAP CHART"),
+            )
+            .on_disabled_hover_text(if available {
+                "Exporting..."
+            } else {
+                "No laps or race positions were fetched"
+            });
+        if lap_chart.clicked() {
+            self.start_lap_chart_export();
+        }
+
+        // A recording has no driver data to add up
+        let available = !self.simulation.run_race_data().is_empty();
+        if ui
+            .add_enabled(
+                available && self.exports.heatmap_job.is_none(),
+                egui::Button::new("EXPORT HEATMAP"),
+            )
+            .on_hover_text("The whole race, for the heatmap's driver")
+            .clicked()
+        {
+            self.start_heatmap_export();
+        }
+        match &self.exports.parquet_job {
+            Some(job) => {
+                ui.add(egui::ProgressBar::new(job.progress()).text("Writing Parquet..."));
+            }
+            None => {
+                if ui
+                    .add_enabled(available, egui::Button::new("EXPORT PARQUET"))
+                    .on_hover_text(format!(
+                        "Every mapped sample, to {}",
+                        self.exports.parquet.path.display()
+                    ))
+                    .clicked()
+                {
+                    self.start_parquet_export();
+                }
+            }
+        }
+        if ui
+            .add_enabled(
+                available && self.exports.bundle_job.is_none(),
+                egui::Button::new("EXPORT BUNDLE"),
+            )
+            .on_hover_text(format!(
+                "The session with its layout, roster and laps, to {} for --bundle",
+                self.exports.bundle.dir.display()
+            ))
+            .clicked()
+        {
+            self.start_bundle_export();
+        }
+    }
+}
+
+// A duration edited as seconds
+fn duration_field(ui: &mut egui::Ui, duration: &mut Duration, range: RangeInclusive<f64>) {
+    let mut seconds = duration.as_secs_f64();
+    let field = egui::DragValue::new(&mut seconds)
+        .clamp_range(range)
+        .speed(0.1)
+        .max_decimals(1);
+    if ui.add(field).changed() {
+        *duration = Duration::from_secs_f64(seconds);
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{join_worker, load_ghost_run, PlotApp};
+use eframe::egui;
+use f1_led_circuit_master_simulation::config::Config;
+use f1_led_circuit_master_simulation::error::AppError;
+use f1_led_circuit_master_simulation::ghost::{Ghost, GhostAlignment, GhostConfig, GhostRun};
+use log::error;
+use std::thread::JoinHandle;
+
+/// The ghost window: another session's car, loaded in the background and drawn over the
+/// board once aligned to the session played.
+pub struct GhostCar {
+    pub shown: bool,
+    pub config: GhostConfig,
+    pub run: Option<Ghost>,
+    pub job: Option<JoinHandle<Result<GhostRun, AppError>>>,
+    pub message: Option<String>, // Why the ghost couldn't be loaded or aligned
+}
+
+impl GhostCar {
+    pub fn new(config: &Config) -> GhostCar {
+        GhostCar {
+            shown: false,
+            config: config.ghost.clone(),
+            run: None,
+            job: None,
+            message: None,
+        }
+    }
+}
+
+impl PlotApp {
+    pub fn ghost_window(&mut self, ctx: &egui::Context) {
+        if self.ghost.job.as_ref().is_some_and(JoinHandle::is_finished) {
+            self.finish_ghost_load();
+        }
+        let mut show_ghost = self.ghost.shown;
+        egui::Window::new("Ghost")
+            .open(&mut show_ghost)
+            .show(ctx, |ui| self.ghost_ui(ui));
+        self.ghost.shown = show_ghost;
+    }
+
+    pub fn start_ghost_load(&mut self) {
+        let Some(source) = self.data_source.clone() else {
+            return;
+        };
+        let session_key = self.ghost.config.session_key.trim().to_string();
+        let driver_number = self.ghost.config.driver_number;
+        let spawned = std::thread::Builder::new()
+            .name("ghost".to_string())
+            .spawn(move || load_ghost_run(&source, &session_key, driver_number));
+        match spawned {
+            Ok(job) => {
+                self.ghost.job = Some(job);
+                self.ghost.message = None;
+            }
+            Err(err) => self.ghost.message = Some(AppError::from(err).user_message()),
+        }
+    }
+
+    fn finish_ghost_load(&mut self) {
+        let Some(job) = self.ghost.job.take() else {
+            return;
+        };
+        match join_worker(job, "ghost") {
+            Ok(run) => {
+                self.ghost.run = Some(Ghost::new(run));
+                self.ghost.config.enabled = true;
+                self.align_ghost();
+            }
+            Err(err) => {
+                error!("Could not load the ghost: {}", err);
+                self.ghost.message = Some(err.user_message());
+            }
+        }
+    }
+
+    pub fn align_ghost(&mut self) {
+        let (Some(ghost), Some(first)) =
+            (&mut self.ghost.run, self.simulation.run_race_data().first())
+        else {
+            return;
+        };
+        let aligned = ghost.align(
+            &self.ghost.config,
+            first.date,
+            self.race_progress.as_deref(),
+        );
+        self.ghost.message = aligned.err().map(|err| err.user_message());
+    }
+
+    fn ghost_ui(&mut self, ui: &mut egui::Ui) {
+        let config = &mut self.ghost.config;
+        let loading = self.ghost.job.is_some();
+        ui.add_enabled_ui(!loading, |ui| {
+            egui::Grid::new("ghost_source").show(ui, |ui| {
+                ui.label("Session");
+                ui.text_edit_singleline(&mut config.session_key);
+                ui.end_row();
+
+                ui.label("Driver");
+                let selected = self
+                    .driver_info
+                    .iter()
+                    .find(|driver| driver.number == config.driver_number)
+                    .map_or(config.driver_number.to_string(), |driver| {
+                        format!("{}: {}", driver.number, driver.name)
+                    });
+                egui::ComboBox::from_id_source("ghost_driver")
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        for driver in &self.driver_info {
+                            ui.selectable_value(
+                                &mut config.driver_number,
+                                driver.number,
+                                format!("{}: {}", driver.number, driver.name),
+                            );
+                        }
+                    });
+                ui.end_row();
+            });
+        });
+        let can_load = !loading && !config.session_key.trim().is_empty();
+        if ui
+            .add_enabled(
+                can_load,
+                egui::Button::new(if loading { "LOADING..." } else { "LOAD" }),
+            )
+            .clicked()
+        {
+            self.start_ghost_load();
+        }
+
+        ui.separator();
+        let config = &mut self.ghost.config;
+        let previous = (
+            config.alignment,
+            config.lap,
+            config.ghost_lap,
+            config.offset_secs,
+        );
+        egui::Grid::new("ghost_alignment").show(ui, |ui| {
+            ui.label("Align");
+            egui::ComboBox::from_id_source("ghost_alignment")
+                .selected_text(config.alignment.label())
+                .show_ui(ui, |ui| {
+                    for alignment in GhostAlignment::ALL {
+                        ui.selectable_value(&mut config.alignment, alignment, alignment.label());
+                    }
+                });
+            ui.end_row();
+
+            match config.alignment {
+                GhostAlignment::SessionStart => {}
+                GhostAlignment::LapStart => {
+                    ui.label("Lap");
+                    ui.add(egui::DragValue::new(&mut config.lap).clamp_range(1..=100));
+                    ui.end_row();
+                    ui.label("Ghost lap");
+                    ui.add(egui::DragValue::new(&mut config.ghost_lap).clamp_range(1..=100));
+                    ui.end_row();
+                }
+                GhostAlignment::Manual => {
+                    ui.label("Offset (s)");
+                    ui.add(egui::DragValue::new(&mut config.offset_secs).speed(0.1));
+                    ui.end_row();
+                }
+            }
+        });
+        ui.add_enabled(
+            self.ghost.run.is_some(),
+            egui::Checkbox::new(&mut config.enabled, "Show ghost"),
+        );
+        let changed = previous
+            != (
+                config.alignment,
+                config.lap,
+                config.ghost_lap,
+                config.offset_secs,
+            );
+        if changed {
+            self.align_ghost();
+        }
+        if let Some(message) = &self.ghost.message {
+            ui.label(message);
+        }
+    }
+}
@@ -0,0 +1,11 @@
+// The window's panels, each with the state only it uses
+pub mod exports;
+pub mod ghost;
+pub mod outputs;
+pub mod playlist;
+pub mod prefs;
+pub mod race_control;
+pub mod settings;
+pub mod split;
+pub mod telemetry;
+pub mod timing;
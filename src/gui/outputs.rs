@@ -0,0 +1,300 @@
+use crate::{led_frame, open_output, PlotApp, Toast, LED_OUTPUTS};
+use eframe::egui;
+use f1_led_circuit_master_simulation::config::Config;
+use f1_led_circuit_master_simulation::simulation::Rgb;
+use f1_led_circuit_master_simulation::sink::OutputFrame;
+use f1_led_circuit_master_simulation::status::{self, Health, Status};
+use f1_led_circuit_master_simulation::test_pattern::{
+    PatternPlayer, TestPattern, TestPatternConfig,
+};
+use f1_led_circuit_master_simulation::wled::WledStatusHandle;
+use log::info;
+use std::time::{Duration, Instant};
+
+// How often the top bar reads the connection status, and the color of a healthy connection
+const STATUS_REFRESH: Duration = Duration::from_millis(250);
+const STATUS_ACTIVE: egui::Color32 = egui::Color32::from_rgb(0, 200, 80);
+
+/// The state of the LED outputs as the top bar shows it, and the patterns it lights the board
+/// with to check it.
+pub struct OutputSettings {
+    pub wled_status: Option<WledStatusHandle>,
+    pub reopen_config: Config, // To reopen a failed output
+    pub status: Status,        // Connection states as last read
+    pub status_read: Instant,
+    pub night_override: bool,   // Full brightness despite the night schedule
+    pub calibration_mode: bool, // Light every LED white to measure the board
+    pub calibration_level: f32, // White level used in calibration mode
+    pub test_pattern: Option<PatternPlayer>, // Shown instead of the race while set
+    pub test_patterns: TestPatternConfig,
+}
+
+impl OutputSettings {
+    pub fn new(config: &Config) -> OutputSettings {
+        OutputSettings {
+            wled_status: None,
+            reopen_config: config.clone(),
+            status: status::snapshot(),
+            status_read: Instant::now(),
+            night_override: false,
+            calibration_mode: false,
+            calibration_level: 1.0,
+            test_pattern: None,
+            test_patterns: config.test_patterns.clone(),
+        }
+    }
+}
+
+impl PlotApp {
+    pub fn brightness_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("BRIGHTNESS");
+        ui.add(egui::Slider::new(&mut self.brightness, 0.0..=1.0));
+        if let Some(level) = self.outputs.night_level() {
+            let mut dimming = !self.output_settings.night_override;
+            let toggle = ui
+                .toggle_value(&mut dimming, "NIGHT")
+                .on_hover_text(format!(
+                    "Scheduled dimming, now at {:.0}%; switch off for full brightness",
+                    level * 100.0
+                ));
+            if toggle.changed() {
+                self.output_settings.night_override = !dimming;
+                self.outputs
+                    .set_night_override(self.output_settings.night_override);
+            }
+        }
+    }
+
+    // Calibration mode and the test patterns, which take the board over from the race
+    pub fn board_check_ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.output_settings.calibration_mode, "CALIBRATE");
+        if self.output_settings.calibration_mode {
+            ui.add(egui::Slider::new(
+                &mut self.output_settings.calibration_level,
+                0.0..=1.0,
+            ));
+        }
+        let mut testing = self.output_settings.test_pattern.is_some();
+        if ui
+            .toggle_value(&mut testing, "TEST")
+            .on_hover_text("Test patterns to check the board's wiring")
+            .changed()
+        {
+            self.output_settings.test_pattern = testing.then(|| {
+                PatternPlayer::new(
+                    TestPattern::default(),
+                    self.coordinates.len(),
+                    &self.output_settings.test_patterns,
+                )
+            });
+        }
+    }
+
+    pub fn test_pattern_window(&mut self, ctx: &egui::Context) {
+        if self.output_settings.test_pattern.is_some() {
+            let mut open = true;
+            egui::Window::new("Test patterns")
+                .open(&mut open)
+                .show(ctx, |ui| self.test_pattern_ui(ui));
+            if !open {
+                self.output_settings.test_pattern = None;
+            }
+        }
+    }
+
+    // The calibrated color of an LED before brightness: the simulation frame, or white in
+    // calibration mode
+    fn led_color(&self, index: usize) -> Option<egui::Color32> {
+        if self.output_settings.calibration_mode {
+            let level = (self.output_settings.calibration_level * 255.0).round() as u8;
+            let white = egui::Color32::from_rgb(level, level, level);
+            return Some(self.apply_calibration(index, white));
+        }
+        self.simulation.frame().leds[index]
+            .or_else(|| self.pedal_trail.get(index).copied().flatten())
+            .map(|[r, g, b]| self.apply_calibration(index, egui::Color32::from_rgb(r, g, b)))
+    }
+
+    pub fn output_frame(&self) -> OutputFrame {
+        // Raw colors, to check the channels of the board
+        if let Some(player) = &self.output_settings.test_pattern {
+            return led_frame(&self.simulation, player.frame(), self.brightness);
+        }
+        let mut leds: Vec<Rgb> = (0..self.view_coordinates.len())
+            .map(|index| {
+                self.led_color(index)
+                    .map_or([0, 0, 0], |color| [color.r(), color.g(), color.b()])
+            })
+            .collect();
+        self.led_mask.apply(&mut leds);
+        // The calibration level is absolute, so the brightness slider doesn't apply to it
+        let brightness = if self.output_settings.calibration_mode {
+            1.0
+        } else {
+            self.brightness
+        };
+        led_frame(&self.simulation, leds, brightness)
+    }
+
+    // WLED's state, then dots for the API, the cache and every output; a failed output's dot
+    // offers to reconnect
+    pub fn status_ui(&mut self, ui: &mut egui::Ui) {
+        if let Some(wled_status) = &self.output_settings.wled_status {
+            ui.separator();
+            ui.label(format!("WLED: {}", wled_status.get().label()));
+        }
+        if self.output_settings.status_read.elapsed() >= STATUS_REFRESH {
+            self.output_settings.status = status::snapshot();
+            self.output_settings.status_read = Instant::now();
+        }
+        let status = &self.output_settings.status;
+        let sinks: Vec<_> = status
+            .sinks
+            .iter()
+            .filter(|(name, _)| name.as_str() != "gui")
+            .collect();
+        if status.api.is_none() && status.cache.is_none() && sinks.is_empty() {
+            return;
+        }
+        ui.separator();
+
+        let dot = |ui: &egui::Ui, health: Health, label: &str| {
+            let color = match health {
+                Health::Active => STATUS_ACTIVE,
+                Health::Retrying => ui.visuals().warn_fg_color,
+                Health::Failed => ui.visuals().error_fg_color,
+            };
+            let mut text = egui::text::LayoutJob::default();
+            text.append(
+                "● ",
+                0.0,
+                egui::TextFormat::simple(egui::FontId::default(), color),
+            );
+            let plain =
+                egui::TextFormat::simple(egui::FontId::default(), ui.visuals().text_color());
+            text.append(label, 0.0, plain);
+            text
+        };
+        if let Some(api) = &status.api {
+            let label = match status.last_api_success {
+                Some(success) => format!("API {}", format_age(success.elapsed())),
+                None => "API".to_string(),
+            };
+            let hover = match &api.detail {
+                Some(error) => format!("Last request failed: {}", error),
+                None => "Last request succeeded".to_string(),
+            };
+            ui.label(dot(ui, api.health, &label)).on_hover_text(hover);
+        }
+        if let Some(cache) = &status.cache {
+            let hover = format!(
+                "{} {} ago",
+                cache.detail.as_deref().unwrap_or_default(),
+                format_age(cache.updated.elapsed())
+            );
+            ui.label(dot(ui, cache.health, "CACHE"))
+                .on_hover_text(hover);
+        }
+        let mut reconnect = None;
+        for (name, link) in sinks {
+            let text = dot(ui, link.health, name);
+            let hover = link.detail.as_deref().unwrap_or("connected");
+            if link.health == Health::Failed && LED_OUTPUTS.contains(&name.as_str()) {
+                ui.menu_button(text, |ui| {
+                    ui.label(hover);
+                    if ui.button("Reconnect").clicked() {
+                        reconnect = Some(name.clone());
+                        ui.close_menu();
+                    }
+                })
+                .response
+                .on_hover_text(hover);
+            } else {
+                ui.label(text).on_hover_text(hover);
+            }
+        }
+        if let Some(name) = reconnect {
+            self.reconnect_output(&name);
+        }
+        // Ages move on without anything else to repaint for
+        ui.ctx().request_repaint_after(Duration::from_secs(1));
+    }
+
+    // Replaces a stopped output with a freshly opened one
+    fn reconnect_output(&mut self, name: &str) {
+        if let Some(id) = self.outputs.find(name) {
+            self.outputs.remove(id);
+        }
+        info!("Reconnecting the {} output", name);
+        let registered = open_output(&self.output_settings.reopen_config, &self.layout, name)
+            .and_then(|output| {
+                let Some(output) = output else {
+                    return Ok(());
+                };
+                if output.wled_status.is_some() {
+                    self.output_settings.wled_status = output.wled_status;
+                }
+                if output.race_events.is_some() {
+                    self.controls.race_events = output.race_events;
+                }
+                self.outputs
+                    .register(output.sink, output.options)
+                    .map(|_| ())
+            });
+        if let Err(err) = registered {
+            // Keeps the dot, so there's something to click for another try
+            status::set_sink(name, Health::Failed, Some(err.to_string()));
+            self.push_toast(Toast::error(err.user_message()));
+        }
+        self.output_settings.status = status::snapshot();
+    }
+
+    fn test_pattern_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(player) = &mut self.output_settings.test_pattern else {
+            return;
+        };
+        for pattern in TestPattern::ALL {
+            if ui
+                .radio(player.pattern() == pattern, pattern.label())
+                .clicked()
+            {
+                player.set_pattern(pattern);
+            }
+        }
+        ui.separator();
+        let mut step_secs = player.step_secs();
+        let step = ui
+            .add(
+                egui::Slider::new(&mut step_secs, 0.05..=2.0)
+                    .logarithmic(true)
+                    .suffix(" s")
+                    .text("STEP"),
+            )
+            .on_hover_text("How long the chase stays on an LED, and the length of a blink");
+        if step.changed() {
+            player.set_step_secs(step_secs);
+        }
+        ui.separator();
+        match player.active_led() {
+            Some(index) => ui.heading(format!("U{} (index {})", index + 1, index)),
+            None => ui.label("Every LED"),
+        };
+    }
+
+    fn apply_calibration(&self, index: usize, color: egui::Color32) -> egui::Color32 {
+        self.calibration
+            .get(index)
+            .copied()
+            .unwrap_or_default()
+            .apply(color)
+    }
+}
+
+// How long ago something happened, like "12s" or "5m"
+fn format_age(age: Duration) -> String {
+    match age.as_secs() {
+        seconds @ 0..=59 => format!("{}s", seconds),
+        seconds @ 60..=3599 => format!("{}m", seconds / 60),
+        seconds => format!("{}h", seconds / 3600),
+    }
+}
@@ -0,0 +1,392 @@
+use crate::{
+    join_worker, load_session, PlotApp, ReloadJob, SessionLoad, Toast, EXPORT_REPAINT_INTERVAL,
+};
+use eframe::egui;
+use f1_led_circuit_master_simulation::config::{Config, SessionConfig};
+use f1_led_circuit_master_simulation::error::AppError;
+use f1_led_circuit_master_simulation::playlist::{session_title, Playlist};
+use f1_led_circuit_master_simulation::session_data::LoadedSession;
+use f1_led_circuit_master_simulation::simulation::PlaybackState;
+use log::{error, info, warn};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The playlist window and the sessions played one after the other, each fetched while the
+/// one before plays.
+pub struct SessionQueue {
+    pub shown: bool,
+    pub playlist: Playlist,
+    key: String, // The session key being typed to add to the playlist
+    title_card_secs: f64,
+    prefetch: Option<Prefetch>,        // The playlist entry to play next
+    pub title_card: Option<TitleCard>, // Shown until the next entry plays
+}
+
+// A playlist entry fetched ahead of playing it
+struct Prefetch {
+    index: usize,
+    session: SessionConfig, // As it was when the fetch started, to notice the entry changing
+    state: PrefetchState,
+}
+
+enum PrefetchState {
+    Loading(ReloadJob),
+    Ready(Box<LoadedSession>),
+}
+
+// The name of the playlist entry about to play, over the board
+pub struct TitleCard {
+    index: usize,
+    until: Instant, // Stays up longer while the entry is still loading
+}
+
+impl SessionQueue {
+    pub fn new(config: &Config) -> SessionQueue {
+        SessionQueue {
+            shown: false,
+            playlist: Playlist::new(config.playlist.sessions.clone(), &config.session.key),
+            key: String::new(),
+            title_card_secs: config.playlist.title_card_secs,
+            prefetch: None,
+            title_card: None,
+        }
+    }
+
+    // Whether the fetch ahead has finished; `None` when nothing is being fetched
+    pub fn prefetch_finished(&self) -> Option<bool> {
+        match &self.prefetch {
+            Some(Prefetch {
+                state: PrefetchState::Loading(job),
+                ..
+            }) => Some(job.handle.is_finished()),
+            _ => None,
+        }
+    }
+}
+
+impl PlotApp {
+    pub fn playlist_window(&mut self, ctx: &egui::Context) {
+        let mut show_playlist = self.queue.shown;
+        egui::Window::new("Playlist")
+            .open(&mut show_playlist)
+            .show(ctx, |ui| self.playlist_ui(ui));
+        self.queue.shown = show_playlist;
+    }
+
+    pub fn cancel_prefetch(&mut self) {
+        if let Some(Prefetch {
+            state: PrefetchState::Loading(job),
+            ..
+        }) = self.queue.prefetch.take()
+        {
+            job.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    // Fetches a playlist entry in the background, dropping the one fetched before
+    fn start_prefetch(&mut self, index: usize) {
+        self.cancel_prefetch();
+        let (Some(source), Some(session)) =
+            (self.data_source.clone(), self.queue.playlist.get(index))
+        else {
+            return;
+        };
+        let session = session.clone();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let job_session = session.clone();
+        let job_cancelled = Arc::clone(&cancelled);
+        // Nobody is there to confirm a long window between two sessions
+        let spawned = std::thread::Builder::new()
+            .name("prefetch".to_string())
+            .spawn(move || load_session(&source, &job_session, &job_cancelled, true));
+        match spawned {
+            Ok(handle) => {
+                info!("Fetching playlist session {} ahead", session.key);
+                self.queue.prefetch = Some(Prefetch {
+                    index,
+                    session,
+                    state: PrefetchState::Loading(ReloadJob { handle, cancelled }),
+                });
+            }
+            Err(err) => {
+                let err = AppError::from(err);
+                error!("Could not start fetching session {}: {}", session.key, err);
+                self.skip_playlist_entry(index, &err.user_message());
+            }
+        }
+    }
+
+    // Takes the result of the fetch ahead once it's done; an entry that didn't load is skipped
+    fn poll_prefetch(&mut self) {
+        let finished = matches!(
+            &self.queue.prefetch,
+            Some(Prefetch { state: PrefetchState::Loading(job), .. }) if job.handle.is_finished()
+        );
+        if !finished {
+            return;
+        }
+        let Some(Prefetch {
+            index,
+            session,
+            state: PrefetchState::Loading(job),
+        }) = self.queue.prefetch.take()
+        else {
+            return;
+        };
+        match join_worker(job.handle, "prefetch") {
+            Ok(SessionLoad::Loaded(loaded)) => {
+                self.queue.prefetch = Some(Prefetch {
+                    index,
+                    session,
+                    state: PrefetchState::Ready(loaded),
+                });
+            }
+            Ok(SessionLoad::Cancelled) => {}
+            Ok(SessionLoad::BadWindow(problem)) => {
+                self.skip_playlist_entry(index, &problem.message());
+            }
+            Err(err) => {
+                error!("Could not load playlist session {}: {}", session.key, err);
+                self.skip_playlist_entry(index, &err.user_message());
+            }
+        }
+    }
+
+    fn skip_playlist_entry(&mut self, index: usize, reason: &str) {
+        let Some(session) = self.queue.playlist.get(index) else {
+            return;
+        };
+        warn!("Skipping playlist session {}: {}", session.key, reason);
+        self.push_toast(Toast::warning(format!(
+            "Skipping {}: {}",
+            session_title(session),
+            reason
+        )));
+        self.queue.playlist.skip(index);
+    }
+
+    // Fetches the next playlist entry while the current one plays, puts up its title card
+    // when playback finishes and plays it once the card has been up long enough
+    pub fn update_playlist(&mut self, ctx: &egui::Context) {
+        if self.queue.playlist.is_empty() || self.data_source.is_none() {
+            return;
+        }
+        self.poll_prefetch();
+        // An entry edited since it was fetched is fetched again
+        if self.queue.prefetch.as_ref().is_some_and(|prefetch| {
+            self.queue
+                .playlist
+                .get(prefetch.index)
+                .is_none_or(|session| session.key != prefetch.session.key)
+        }) {
+            self.cancel_prefetch();
+        }
+        if let Some(card) = &mut self.queue.title_card {
+            if self.queue.playlist.is_skipped(card.index) {
+                match self.queue.playlist.following(card.index) {
+                    Some(index) => card.index = index,
+                    None => self.queue.title_card = None,
+                }
+            }
+        }
+        if self.queue.title_card.is_none() && self.simulation.state() == PlaybackState::Finished {
+            if let Some(index) = self.queue.playlist.next() {
+                let secs = Duration::from_secs_f64(self.queue.title_card_secs.max(0.0));
+                self.queue.title_card = Some(TitleCard {
+                    index,
+                    until: Instant::now() + secs,
+                });
+            }
+        }
+
+        // Between sessions, rather than while one is being reloaded from the settings
+        let wanted = match &self.queue.title_card {
+            Some(card) => Some(card.index),
+            None if self.reload_job.is_none() => self.queue.playlist.next(),
+            None => None,
+        };
+        match wanted {
+            Some(index)
+                if self
+                    .queue
+                    .prefetch
+                    .as_ref()
+                    .is_none_or(|p| p.index != index) =>
+            {
+                self.start_prefetch(index);
+            }
+            _ => {}
+        }
+
+        let Some(card) = &self.queue.title_card else {
+            return;
+        };
+        let ready = matches!(
+            &self.queue.prefetch,
+            Some(Prefetch { index, state: PrefetchState::Ready(_), .. }) if *index == card.index
+        );
+        let remaining = card.until.saturating_duration_since(Instant::now());
+        if !ready || !remaining.is_zero() {
+            ctx.request_repaint_after(remaining.max(EXPORT_REPAINT_INTERVAL));
+            return;
+        }
+        self.queue.title_card = None;
+        if let Some(Prefetch {
+            index,
+            session,
+            state: PrefetchState::Ready(loaded),
+        }) = self.queue.prefetch.take()
+        {
+            info!("Playing playlist session {}", session.key);
+            if let Some(job) = self.reload_job.take() {
+                job.cancelled.store(true, Ordering::Relaxed);
+            }
+            self.banner = None;
+            self.settings.window_problem = None;
+            self.session_name = session.label();
+            self.session = session;
+            self.queue.playlist.play(index);
+            self.show_loaded(loaded);
+            self.simulation.start();
+        }
+    }
+
+    // Moves to another playlist entry through its title card, at once if it's already loaded
+    fn jump_in_playlist(&mut self, index: usize) {
+        self.queue.title_card = Some(TitleCard {
+            index,
+            until: Instant::now(),
+        });
+    }
+
+    pub fn playlist_buttons_ui(&mut self, ui: &mut egui::Ui) {
+        let previous = self.queue.playlist.previous();
+        let next = self.queue.playlist.next();
+        if ui
+            .add_enabled(previous.is_some(), egui::Button::new("⏮ PREV"))
+            .on_hover_text("The previous session of the playlist")
+            .clicked()
+        {
+            self.jump_in_playlist(previous.unwrap_or_default());
+        }
+        if ui
+            .add_enabled(next.is_some(), egui::Button::new("NEXT ⏭"))
+            .on_hover_text("The next session of the playlist")
+            .clicked()
+        {
+            self.jump_in_playlist(next.unwrap_or_default());
+        }
+    }
+
+    pub fn title_card_ui(&self, ctx: &egui::Context) {
+        let Some(card) = &self.queue.title_card else {
+            return;
+        };
+        let Some(session) = self.queue.playlist.get(card.index) else {
+            return;
+        };
+        let loading = !matches!(
+            &self.queue.prefetch,
+            Some(Prefetch { index, state: PrefetchState::Ready(_), .. }) if *index == card.index
+        );
+        egui::Area::new(egui::Id::new("title_card"))
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.label("UP NEXT");
+                        ui.heading(session_title(session));
+                        if loading {
+                            ui.horizontal(|ui| {
+                                ui.spinner();
+                                ui.label("Loading");
+                            });
+                        }
+                    });
+                });
+            });
+    }
+
+    fn playlist_ui(&mut self, ui: &mut egui::Ui) {
+        if self.data_source.is_none() {
+            ui.label("Playlists need session data, not a recording");
+            return;
+        }
+        let mut removed = None;
+        let mut raised = None;
+        let mut jump = None;
+        egui::Grid::new("playlist").striped(true).show(ui, |ui| {
+            for (index, session) in self.queue.playlist.sessions().iter().enumerate() {
+                let playing = self.queue.playlist.position() == Some(index);
+                let title = egui::RichText::new(format!(
+                    "{} {}",
+                    if playing { "▶" } else { " " },
+                    session_title(session)
+                ));
+                let title = if self.queue.playlist.is_skipped(index) {
+                    title.strikethrough().weak()
+                } else {
+                    title
+                };
+                if ui
+                    .selectable_label(playing, title)
+                    .on_hover_text(format!("Session {}; click to play it", session.key))
+                    .clicked()
+                {
+                    jump = Some(index);
+                }
+                let fetched = matches!(
+                    &self.queue.prefetch,
+                    Some(Prefetch { index: fetched, state: PrefetchState::Ready(_), .. })
+                        if *fetched == index
+                );
+                ui.label(if fetched { "Fetched" } else { "" });
+                if ui.small_button("⏶").clicked() {
+                    raised = Some(index);
+                }
+                if ui.small_button("✖").clicked() {
+                    removed = Some(index);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(index) = raised {
+            self.queue.playlist.move_up(index);
+        }
+        if let Some(index) = removed {
+            self.queue.playlist.remove(index);
+        }
+        if let Some(index) = jump {
+            self.jump_in_playlist(index);
+        }
+
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.queue.key)
+                    .hint_text("Session key")
+                    .desired_width(80.0),
+            );
+            let key = self.queue.key.trim().to_string();
+            if ui
+                .add_enabled(!key.is_empty(), egui::Button::new("ADD"))
+                .clicked()
+            {
+                self.queue.playlist.push(SessionConfig {
+                    key,
+                    fetch_laps: self.session.fetch_laps,
+                    ..SessionConfig::default()
+                });
+                self.queue.key.clear();
+            }
+            if ui
+                .button("ADD CURRENT")
+                .on_hover_text("The session playing, with its time window and drivers")
+                .clicked()
+            {
+                self.queue.playlist.push(self.session.clone());
+                self.queue.playlist.set_playing(&self.session.key);
+            }
+        });
+    }
+}
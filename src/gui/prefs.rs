@@ -0,0 +1,134 @@
+use crate::PlotApp;
+use eframe::egui;
+use f1_led_circuit_master_simulation::led_style::LedStyle;
+use f1_led_circuit_master_simulation::prefs::{LastSession, Theme, UiPrefs};
+use f1_led_circuit_master_simulation::recorder::layout_hash;
+use f1_led_circuit_master_simulation::team_view::LedView;
+use f1_led_circuit_master_simulation::viewport::Bounds;
+
+/// How the board is drawn in the window, as set from the top bar.
+pub struct DisplayPrefs {
+    pub theme: Theme,
+    pub led_size: f32, // Side length of an LED square in points
+    pub led_style: LedStyle,
+    pub glow_radius: f32, // Reach of the glow beyond a lit LED, in LED sizes
+    pub elevation_shading: bool, // Unlit LEDs shaded by elevation, when the session has it
+    pub elevation_shades: Option<Vec<f32>>, // Each LED's height from 0 to 1; none when flat
+}
+
+impl PlotApp {
+    // The UI state to remember for the next run
+    pub fn ui_prefs(&self) -> UiPrefs {
+        let mut layout_rotations = self.layout_rotations.clone();
+        layout_rotations.insert(layout_hash(&self.coordinates), self.view_transform.rotation);
+        UiPrefs {
+            speed: self.simulation.speed(),
+            brightness: self.brightness,
+            theme: self.display.theme,
+            led_size: self.display.led_size,
+            session_hidden_drivers: self.hidden_drivers.clone(),
+            legend_order: self.legend_order,
+            color_scheme: self.simulation.color_scheme(),
+            led_view: self.simulation.led_view(),
+            layout_rotations,
+            time_offsets: self.time_offsets.clone(),
+            speed_segments: self.speed_segments.clone(),
+            auto_slow: self.simulation.speed_plan().is_auto(),
+            volume: self.audio.volume(),
+            muted: self.audio.is_muted(),
+            last_session: Some(LastSession {
+                key: self.session.key.clone(),
+                window: self.session.window(),
+            }),
+        }
+    }
+
+    pub fn volume_ui(&mut self, ui: &mut egui::Ui) {
+        if self.audio.is_enabled() {
+            ui.label("VOLUME");
+            let mut volume = self.audio.volume();
+            if ui.add(egui::Slider::new(&mut volume, 0.0..=1.0)).changed() {
+                self.audio.set_volume(volume);
+            }
+            let mut muted = self.audio.is_muted();
+            if ui.toggle_value(&mut muted, "MUTE").changed() {
+                self.audio.set_muted(muted);
+            }
+            ui.separator();
+        }
+    }
+
+    pub fn led_display_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("LED SIZE");
+        ui.add(egui::Slider::new(&mut self.display.led_size, 5.0..=40.0));
+        let mut glow = self.display.led_style == LedStyle::Glow;
+        if ui.toggle_value(&mut glow, "GLOW").changed() {
+            self.display.led_style = if glow {
+                LedStyle::Glow
+            } else {
+                LedStyle::Squares
+            };
+        }
+        let elevation = ui
+            .add_enabled(
+                self.display.elevation_shades.is_some(),
+                egui::SelectableLabel::new(self.display.elevation_shading, "ELEVATION"),
+            )
+            .on_hover_text("Shade the unlit LEDs lighter the higher the track is there")
+            .on_disabled_hover_text("This session's locations have no elevation");
+        if elevation.clicked() {
+            self.display.elevation_shading = !self.display.elevation_shading;
+        }
+        let mut teams = self.simulation.led_view() == LedView::Team;
+        if ui
+            .toggle_value(&mut teams, "TEAMS")
+            .on_hover_text("Color the LEDs by team, brighter where both of its cars are")
+            .changed()
+        {
+            self.simulation.set_led_view(if teams {
+                LedView::Team
+            } else {
+                LedView::Driver
+            });
+        }
+    }
+
+    // The rotation and flips of the track view, then the theme
+    pub fn view_ui(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        let previous_transform = self.view_transform;
+        ui.add(
+            egui::Slider::new(&mut self.view_transform.rotation, 0.0..=360.0)
+                .step_by(1.0)
+                .suffix("°")
+                .text("ROTATION"),
+        );
+        ui.checkbox(&mut self.view_transform.flip_horizontal, "FLIP H");
+        ui.checkbox(&mut self.view_transform.flip_vertical, "FLIP V");
+        if self.view_transform != previous_transform {
+            self.view_coordinates = self.layout.view_coordinates(&self.view_transform);
+            self.bounds =
+                Bounds::from_coordinates(&self.view_coordinates[self.layout.track().leds.clone()]);
+            self.camera.reset(&self.bounds);
+        }
+        ui.separator();
+
+        let previous_theme = self.display.theme;
+        egui::ComboBox::from_label("THEME")
+            .selected_text(self.display.theme.label())
+            .show_ui(ui, |ui| {
+                for theme in Theme::ALL {
+                    ui.selectable_value(&mut self.display.theme, theme, theme.label());
+                }
+            });
+        if self.display.theme != previous_theme {
+            apply_theme(ctx, self.display.theme);
+        }
+    }
+}
+
+pub fn apply_theme(ctx: &egui::Context, theme: Theme) {
+    ctx.set_visuals(match theme {
+        Theme::Dark => egui::Visuals::dark(),
+        Theme::Light => egui::Visuals::light(),
+    });
+}
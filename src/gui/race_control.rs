@@ -0,0 +1,553 @@
+use crate::{PlotApp, Toast};
+use chrono::Local;
+use eframe::egui;
+use f1_led_circuit_master_simulation::config::StopConfirmation;
+use f1_led_circuit_master_simulation::markers::timeline_x;
+use f1_led_circuit_master_simulation::render::format_duration;
+use f1_led_circuit_master_simulation::schedule::{
+    parse_start_time, ScheduledStart, LATE_START_TOLERANCE,
+};
+use f1_led_circuit_master_simulation::simulation::PlaybackState;
+use f1_led_circuit_master_simulation::speed_plan::{
+    SlowEvent, SpeedSegment, AUTO_SLOW_SECS, AUTO_SLOW_SPEED,
+};
+use log::{info, warn};
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+
+// How long an armed two-stage STOP button waits for the second click
+const STOP_ARM_WINDOW: Duration = Duration::from_secs(3);
+
+// Playback speeds one click away, shown when they're within the configured range
+const SPEED_PRESETS: [f64; 6] = [0.5, 1.0, 2.0, 5.0, 10.0, 30.0];
+
+// The skip buttons and the race seconds each moves the clock by
+const SKIP_BUTTONS: [(&str, f64); 4] = [
+    ("⏪ 60s", -60.0),
+    ("◀ 10s", -10.0),
+    ("10s ▶", 10.0),
+    ("60s ⏩", 60.0),
+];
+
+// A held skip button skips again after this long, and then at this interval
+const SKIP_REPEAT_DELAY: Duration = Duration::from_millis(500);
+
+const SKIP_REPEAT_INTERVAL: Duration = Duration::from_millis(200);
+
+// Height of the timeline under the track, and how far from a tick on it still hovers it
+const TIMELINE_HEIGHT: f32 = 18.0;
+
+const MARKER_HOVER_DISTANCE: f32 = 3.0;
+
+// One click of the time offset buttons, in seconds
+const TIME_OFFSET_STEP: f64 = 0.5;
+
+// Race seconds a new speed segment covers
+const SPEED_SEGMENT_SECS: f64 = 60.0;
+
+/// The playback controls of the top bar and the speed plan window, and what they're in the
+/// middle of.
+pub struct RaceControls {
+    pub scheduled_start: Option<ScheduledStart>, // Playback armed to start at a wall-clock time
+    pub start_at: String,                        // The start time being typed
+    pub start_at_error: Option<String>,          // Why the start time typed can't be armed
+    pub stop_confirmation: StopConfirmation,
+    pub stop_armed: Option<Instant>, // When a two-stage STOP was clicked the first time
+    pub skip_held: Option<(f64, Instant)>, // The skip button held down and when it skips next
+    pub confirm_stop: bool,          // The reset popup is open
+    pub show_speed_plan: bool,
+}
+
+impl RaceControls {
+    pub fn new(stop_confirmation: StopConfirmation) -> RaceControls {
+        RaceControls {
+            scheduled_start: None,
+            start_at: String::new(),
+            start_at_error: None,
+            stop_confirmation,
+            stop_armed: None,
+            skip_held: None,
+            confirm_stop: false,
+            show_speed_plan: false,
+        }
+    }
+}
+
+impl PlotApp {
+    // The clock, then the buttons and sliders that start, stop and steer playback
+    pub fn race_control_ui(&mut self, ui: &mut egui::Ui) {
+        let race_time = self.simulation.race_time();
+        let duration = self.simulation.duration();
+        // The speed played shows next to the one set while the speed plan changes it
+        let speed = self.simulation.speed();
+        let effective = self.simulation.effective_speed();
+        let speed = if speed_label(effective) == speed_label(speed) {
+            speed_label(speed)
+        } else {
+            format!("{} (set {})", speed_label(effective), speed_label(speed))
+        };
+        ui.label(format!(
+            "Race Time: {} / {} at {}",
+            format_duration(self.simulation.clock_time()),
+            format_duration(duration),
+            speed
+        ))
+        .on_hover_text(format!(
+            "{} remaining",
+            format_duration(duration - race_time)
+        ));
+        ui.separator();
+
+        // A follower's leader has the say over playback
+        let following = self.controls.is_following();
+        if following {
+            match self.controls.synced_to() {
+                Some(leader) => ui.strong(format!("SYNCED TO {}", leader.ip())),
+                None => ui.label("WAITING FOR SYNC LEADER"),
+            };
+        }
+        ui.add_enabled_ui(!following, |ui| {
+            if ui.button("START").clicked() {
+                self.simulation.start();
+            }
+            self.scheduled_start_ui(ui);
+            let paused = self.simulation.state() == PlaybackState::Paused;
+            if ui.button(if paused { "RESUME" } else { "PAUSE" }).clicked() {
+                self.simulation.set_paused(!paused);
+            }
+            let reversed = self.simulation.is_reversed();
+            if ui
+                .add_enabled(
+                    self.simulation.is_running(),
+                    egui::SelectableLabel::new(reversed, "REVERSE"),
+                )
+                .on_hover_text("Play backwards at the set speed, down to the start")
+                .clicked()
+            {
+                self.simulation.set_reversed(!reversed);
+            }
+            self.stop_button_ui(ui);
+            self.skip_buttons_ui(ui);
+            if !self.queue.playlist.is_empty() && self.data_source.is_some() {
+                self.playlist_buttons_ui(ui);
+            }
+
+            self.speed_ui(ui);
+            ui.separator();
+
+            self.time_offset_ui(ui);
+        });
+    }
+
+    pub fn speed_plan_window(&mut self, ctx: &egui::Context) {
+        let mut show_speed_plan = self.race_control.show_speed_plan;
+        egui::Window::new("Speed plan")
+            .open(&mut show_speed_plan)
+            .show(ctx, |ui| self.speed_plan_ui(ui));
+        self.race_control.show_speed_plan = show_speed_plan;
+    }
+
+    // STOP asks first as configured, but only when there's progress to lose; the playback
+    // carries on until it's confirmed
+    pub fn stop_button_ui(&mut self, ui: &mut egui::Ui) {
+        let armed = self
+            .race_control
+            .stop_armed
+            .filter(|since| since.elapsed() < STOP_ARM_WINDOW);
+        self.race_control.stop_armed = armed;
+        let label = if armed.is_some() {
+            "CONFIRM STOP"
+        } else {
+            "STOP"
+        };
+        if !ui.button(label).clicked() {
+            if let Some(since) = armed {
+                ui.ctx()
+                    .request_repaint_after(STOP_ARM_WINDOW.saturating_sub(since.elapsed()));
+            }
+            return;
+        }
+        let started = self.simulation.state() != PlaybackState::Stopped;
+        match self.race_control.stop_confirmation {
+            StopConfirmation::Dialog if started => self.race_control.confirm_stop = true,
+            StopConfirmation::TwoStage if started && armed.is_none() => {
+                self.race_control.stop_armed = Some(Instant::now());
+                ui.ctx().request_repaint_after(STOP_ARM_WINDOW);
+            }
+            _ => {
+                self.race_control.stop_armed = None;
+                self.simulation.reset();
+            }
+        }
+    }
+
+    // Each skip button skips once when pressed and again and again while held
+    pub fn skip_buttons_ui(&mut self, ui: &mut egui::Ui) {
+        let started = self.simulation.state() != PlaybackState::Stopped;
+        let mut held = None;
+        ui.add_enabled_ui(started, |ui| {
+            for (label, secs) in SKIP_BUTTONS {
+                let response = ui.button(label).on_hover_text("Hold to keep skipping");
+                if response.is_pointer_button_down_on() {
+                    held = Some(secs);
+                }
+            }
+        });
+        let now = Instant::now();
+        self.race_control.skip_held = match (held, self.race_control.skip_held) {
+            (Some(secs), Some((held_secs, next))) if secs == held_secs => {
+                if now < next {
+                    Some((secs, next))
+                } else {
+                    self.simulation.skip(secs);
+                    Some((secs, now + SKIP_REPEAT_INTERVAL))
+                }
+            }
+            (Some(secs), _) => {
+                self.simulation.skip(secs);
+                Some((secs, now + SKIP_REPEAT_DELAY))
+            }
+            (None, _) => None,
+        };
+        if let Some((_, next)) = self.race_control.skip_held {
+            ui.ctx()
+                .request_repaint_after(next.saturating_duration_since(now));
+        }
+    }
+
+    pub fn confirm_stop_ui(&mut self, ctx: &egui::Context) {
+        if !self.race_control.confirm_stop {
+            return;
+        }
+        egui::Window::new("Reset simulation?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label("This clears current progress.");
+                ui.horizontal(|ui| {
+                    if ui.button("RESET").clicked() {
+                        self.simulation.reset();
+                        self.race_control.confirm_stop = false;
+                    }
+                    if ui.button("CANCEL").clicked() {
+                        self.race_control.confirm_stop = false;
+                    }
+                });
+            });
+    }
+
+    // Arms playback to start at a wall-clock time, e.g. together with a broadcast recording,
+    // and counts down to it. The SYNC offset still applies and can be nudged afterwards
+    pub fn scheduled_start_ui(&mut self, ui: &mut egui::Ui) {
+        if let Some(start) = self.race_control.scheduled_start {
+            let remaining = start.remaining(Local::now()).as_secs_f64().ceil();
+            ui.label(format!("STARTS IN {}", format_duration(remaining)))
+                .on_hover_text(format!(
+                    "Playback starts at 1x at {}",
+                    start.at().format("%Y-%m-%d %H:%M:%S")
+                ));
+            if ui.button("CANCEL").clicked() {
+                self.race_control.scheduled_start = None;
+            }
+            return;
+        }
+
+        let field = ui
+            .add(
+                egui::TextEdit::singleline(&mut self.race_control.start_at)
+                    .hint_text("15:00")
+                    .desired_width(70.0),
+            )
+            .on_hover_text(
+                "Local time to start at, today like 15:00 or on a date like 2024-05-26 15:00",
+            );
+        let entered = field.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+        if ui.button("ARM").clicked() || entered {
+            match parse_start_time(&self.race_control.start_at, Local::now().date_naive()) {
+                Ok(at) => {
+                    self.race_control.scheduled_start = Some(ScheduledStart::new(at));
+                    self.race_control.start_at_error = None;
+                }
+                Err(err) => self.race_control.start_at_error = Some(err),
+            }
+        }
+        if let Some(err) = &self.race_control.start_at_error {
+            ui.colored_label(ui.visuals().error_fg_color, "⚠")
+                .on_hover_text(err);
+        }
+    }
+
+    // Starts the armed playback at 1x, `lateness` after the time it was armed for. A time
+    // that had passed by more than a moment starts now, with a warning
+    pub fn start_on_schedule(&mut self, lateness: Duration) {
+        self.race_control.scheduled_start = None;
+        self.simulation.set_speed(1.0);
+        self.simulation.start();
+        if lateness > LATE_START_TOLERANCE {
+            let message = format!(
+                "The start time passed {} ago; playback started now",
+                format_duration(lateness.as_secs_f64())
+            );
+            warn!("{}", message);
+            self.push_toast(Toast::warning(message));
+        } else {
+            info!("Playback started on schedule");
+        }
+    }
+
+    // The speed presets with the active one highlighted, then the slider. Only the multiplier
+    // changes, so the clock carries on from where it is.
+    pub fn speed_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("PLAYBACK SPEED");
+        let current = self.simulation.speed();
+        for preset in SPEED_PRESETS {
+            if !self.speed_range.contains(&preset) {
+                continue;
+            }
+            let active = (current - preset).abs() < f64::EPSILON;
+            if ui.selectable_label(active, speed_label(preset)).clicked() {
+                self.simulation.set_speed(preset);
+            }
+        }
+        let mut speed = current;
+        if ui
+            .add(speed_slider(&mut speed, self.speed_range.clone()))
+            .changed()
+        {
+            self.simulation.set_speed(speed);
+        }
+    }
+
+    // Segments of the race played at their own speed, and the auto slowdown around events
+    fn speed_plan_ui(&mut self, ui: &mut egui::Ui) {
+        let plan = self.simulation.speed_plan();
+        let mut auto = plan.is_auto();
+        let events = [SlowEvent::Overtake, SlowEvent::Retirement]
+            .map(|kind| format!("{} {}", plan.event_count(kind), kind.label()));
+        let mut segments = plan.segments().to_vec();
+        if ui
+            .checkbox(
+                &mut auto,
+                format!(
+                    "AUTO: {} for {}s around events",
+                    speed_label(AUTO_SLOW_SPEED),
+                    AUTO_SLOW_SECS
+                ),
+            )
+            .on_hover_text(format!("Known events: {}", events.join(", ")))
+            .changed()
+        {
+            self.simulation.speed_plan_mut().set_auto(auto);
+        }
+
+        let mut removed = None;
+        egui::Grid::new("speed_segments")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("From (s)");
+                ui.label("To (s)");
+                ui.label("Speed");
+                ui.end_row();
+                for (index, segment) in segments.iter_mut().enumerate() {
+                    ui.add(
+                        egui::DragValue::new(&mut segment.start)
+                            .clamp_range(0.0..=segment.end)
+                            .max_decimals(1),
+                    )
+                    .on_hover_text(format_duration(segment.start));
+                    ui.add(
+                        egui::DragValue::new(&mut segment.end)
+                            .clamp_range(segment.start..=f64::MAX)
+                            .max_decimals(1),
+                    )
+                    .on_hover_text(format_duration(segment.end));
+                    ui.add(speed_slider(&mut segment.speed, self.speed_range.clone()));
+                    if ui.small_button("✖").clicked() {
+                        removed = Some(index);
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(index) = removed {
+            segments.remove(index);
+        }
+        if ui
+            .button("ADD HERE")
+            .on_hover_text("Play the next minute of the race at the current speed")
+            .clicked()
+        {
+            let start = self.simulation.race_time().max(0.0);
+            segments.push(SpeedSegment {
+                start,
+                end: start + SPEED_SEGMENT_SECS,
+                speed: self.simulation.speed(),
+            });
+        }
+
+        if segments != self.simulation.speed_plan().segments() {
+            if segments.is_empty() {
+                self.speed_segments.remove(&self.session.key);
+            } else {
+                self.speed_segments
+                    .insert(self.session.key.clone(), segments.clone());
+            }
+            self.simulation.speed_plan_mut().set_segments(segments);
+        }
+    }
+
+    // Nudges the data against the clock, e.g. to line the board up with a broadcast recording.
+    // The offset shows while it's set; clicking it goes back to none
+    pub fn time_offset_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("SYNC");
+        let offset = self.simulation.time_offset();
+        let mut changed = None;
+        if ui
+            .small_button("-")
+            .on_hover_text("Show the data half a second later")
+            .clicked()
+        {
+            changed = Some(offset - TIME_OFFSET_STEP);
+        }
+        if offset != 0.0
+            && ui
+                .small_button(format_time_offset(offset))
+                .on_hover_text("How far the data runs ahead of the clock; click to reset")
+                .clicked()
+        {
+            changed = Some(0.0);
+        }
+        if ui
+            .small_button("+")
+            .on_hover_text("Show the data half a second earlier")
+            .clicked()
+        {
+            changed = Some(offset + TIME_OFFSET_STEP);
+        }
+        if let Some(offset) = changed {
+            self.simulation.set_time_offset(offset);
+            if offset == 0.0 {
+                self.time_offsets.remove(&self.session.key);
+            } else {
+                self.time_offsets.insert(self.session.key.clone(), offset);
+            }
+        }
+    }
+
+    // The offset remembered for the session being shown
+    pub fn time_offset(&self) -> f64 {
+        self.time_offsets
+            .get(&self.session.key)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    // The scrub bar under the track: the part played, the markers and the playhead. Clicking
+    // or dragging seeks there; clicking a marker seeks to a little before its event
+    pub fn timeline_ui(&mut self, ui: &mut egui::Ui) {
+        let (rect, response) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width(), TIMELINE_HEIGHT),
+            egui::Sense::click_and_drag(),
+        );
+        let duration = self.simulation.duration();
+        let offset = self.simulation.time_offset();
+        let x = |clock_time: f64| timeline_x(clock_time, duration, rect.left(), rect.right());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+        let playhead = x(self.simulation.clock_time());
+        painter.rect_filled(
+            egui::Rect::from_x_y_ranges(rect.left()..=playhead, rect.y_range()),
+            2.0,
+            ui.visuals().selection.bg_fill.gamma_multiply(0.5),
+        );
+
+        // Markers under the pointer, topmost last; bands fill the lower half so the ticks over
+        // them stay visible
+        let pointer = response.hover_pos().map(|pos| pos.x);
+        let mut hovered: Vec<(f64, &str)> = Vec::new();
+        if self.marker_config.enabled {
+            for marker in &self.markers {
+                let [r, g, b] = marker.kind.color();
+                let color = egui::Color32::from_rgb(r, g, b);
+                let start = x(marker.start - offset);
+                let span = if marker.end > marker.start {
+                    let end = x(marker.end - offset).max(start + 1.0);
+                    painter.rect_filled(
+                        egui::Rect::from_x_y_ranges(start..=end, rect.center().y..=rect.bottom()),
+                        0.0,
+                        color.gamma_multiply(0.7),
+                    );
+                    start..=end
+                } else {
+                    painter.line_segment(
+                        [
+                            egui::pos2(start, rect.top()),
+                            egui::pos2(start, rect.bottom()),
+                        ],
+                        egui::Stroke::new(2.0, color),
+                    );
+                    start - MARKER_HOVER_DISTANCE..=start + MARKER_HOVER_DISTANCE
+                };
+                if pointer.is_some_and(|pointer| span.contains(&pointer)) {
+                    hovered.push((marker.start - offset, &marker.label));
+                }
+            }
+        }
+        painter.line_segment(
+            [
+                egui::pos2(playhead, rect.top()),
+                egui::pos2(playhead, rect.bottom()),
+            ],
+            egui::Stroke::new(2.0, ui.visuals().strong_text_color()),
+        );
+
+        let target = match (hovered.last(), response.interact_pointer_pos()) {
+            (Some(&(clock_time, _)), _) if response.clicked() => {
+                Some(clock_time - self.marker_config.lead_in_secs)
+            }
+            (_, Some(pos)) if response.clicked() || response.dragged() => {
+                Some((pos.x - rect.left()) as f64 / rect.width().max(1.0) as f64 * duration)
+            }
+            _ => None,
+        };
+        let labels: Vec<&str> = hovered.iter().map(|&(_, label)| label).collect();
+        let hover = labels.join("\n");
+        if !hover.is_empty() {
+            response.on_hover_text(hover);
+        }
+        // A follower's leader has the say over the clock
+        if let (Some(clock_time), false) = (target, self.controls.is_following()) {
+            if self.simulation.state() == PlaybackState::Stopped {
+                self.simulation.start();
+            }
+            self.simulation
+                .seek(Duration::from_secs_f64(clock_time.clamp(0.0, duration)));
+        }
+    }
+}
+
+// Fine-grained speeds, spread evenly from the slowest to the fastest multiple
+pub fn speed_slider(speed: &mut f64, range: RangeInclusive<f64>) -> egui::Slider<'_> {
+    egui::Slider::new(speed, range)
+        .logarithmic(true)
+        .max_decimals(2)
+        .suffix("x")
+}
+
+// A speed multiplier like "0.5x" or "2x"
+pub fn speed_label(speed: f64) -> String {
+    format!("{}x", (speed * 100.0).round() / 100.0)
+}
+
+// A time offset like "+0:02.5" or "-1:30.0"
+pub fn format_time_offset(offset: f64) -> String {
+    let sign = if offset < 0.0 { '-' } else { '+' };
+    let tenths = (offset.abs() * 10.0).round() as u64;
+    format!(
+        "{}{}:{:02}.{}",
+        sign,
+        tenths / 600,
+        tenths / 10 % 60,
+        tenths % 10
+    )
+}
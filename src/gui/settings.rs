@@ -0,0 +1,211 @@
+use crate::{grid_formation, PlotApp};
+use eframe::egui;
+use f1_led_circuit_master_simulation::color::to_hex;
+use f1_led_circuit_master_simulation::config::StopConfirmation;
+use f1_led_circuit_master_simulation::driver_info::driver_numbers;
+use f1_led_circuit_master_simulation::settings::{SessionForm, SessionFormErrors, WindowProblem};
+
+/// The settings window: the session to load and how, checked before it's loaded.
+#[derive(Default)]
+pub struct SessionSettings {
+    pub shown: bool,
+    pub form: SessionForm,
+    pub errors: SessionFormErrors,
+    pub window_problem: Option<WindowProblem>, // Why the form's time window wasn't loaded
+}
+
+impl PlotApp {
+    pub fn settings_window(&mut self, ctx: &egui::Context) {
+        let mut show_settings = self.settings.shown;
+        egui::Window::new("Settings")
+            .open(&mut show_settings)
+            .show(ctx, |ui| self.settings_ui(ui));
+        self.settings.shown &= show_settings; // Applying closes the window too
+    }
+
+    // Opens the settings window on the current session's values, or closes it
+    pub fn toggle_settings(&mut self) {
+        if !self.settings.shown {
+            self.settings.form =
+                SessionForm::new(&self.session, &driver_numbers(&self.driver_info));
+            self.settings.errors = SessionFormErrors::default();
+        }
+        self.settings.shown = !self.settings.shown;
+    }
+
+    fn settings_ui(&mut self, ui: &mut egui::Ui) {
+        let form = &mut self.settings.form;
+        let errors = &self.settings.errors;
+        egui::Grid::new("session_settings").show(ui, |ui| {
+            ui.label("Session");
+            ui.text_edit_singleline(&mut form.key);
+            field_error(ui, &errors.key);
+            ui.end_row();
+
+            ui.label("Start");
+            ui.add(egui::TextEdit::singleline(&mut form.start_time).hint_text("Session start"));
+            field_error(ui, &errors.start_time);
+            ui.end_row();
+
+            ui.label("End");
+            ui.add(egui::TextEdit::singleline(&mut form.end_time).hint_text("Session end"));
+            field_error(ui, &errors.end_time);
+            ui.end_row();
+
+            ui.label("Drivers");
+            egui::ScrollArea::vertical()
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for driver in &self.driver_info {
+                        let mut selected = form.drivers.contains(&driver.number);
+                        let label = format!("{}: {}", driver.number, driver.name);
+                        if ui.checkbox(&mut selected, label).changed() {
+                            if selected {
+                                form.drivers.insert(driver.number);
+                            } else {
+                                form.drivers.remove(&driver.number);
+                            }
+                        }
+                    }
+                });
+            field_error(ui, &errors.drivers);
+            ui.end_row();
+        });
+        if let Some(problem) = self.settings.window_problem.clone() {
+            ui.colored_label(ui.visuals().error_fg_color, problem.message());
+            let mut long_window_ok = false;
+            ui.horizontal(|ui| match &problem {
+                WindowProblem::OutsideSession { session, .. } => {
+                    if ui.button("USE SESSION BOUNDS").clicked() {
+                        self.settings.form.use_session_bounds(session);
+                        self.settings.window_problem = None;
+                    }
+                }
+                WindowProblem::TooLong { .. } => {
+                    long_window_ok = ui.button("FETCH ANYWAY").clicked();
+                }
+                WindowProblem::Reversed => {}
+            });
+            if long_window_ok {
+                self.reload_session(true);
+                return;
+            }
+        }
+        if ui.button("APPLY & RELOAD").clicked() {
+            self.reload_session(false);
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("STOP");
+            egui::ComboBox::from_id_source("stop_confirmation")
+                .selected_text(self.race_control.stop_confirmation.label())
+                .show_ui(ui, |ui| {
+                    for confirmation in StopConfirmation::ALL {
+                        ui.selectable_value(
+                            &mut self.race_control.stop_confirmation,
+                            confirmation,
+                            confirmation.label(),
+                        );
+                    }
+                });
+        });
+        ui.checkbox(
+            &mut self.pause_when_unfocused,
+            "Pause when the window loses focus",
+        )
+        .on_hover_text("Not while frames also go to other outputs");
+        let keep_last_led = &mut self.retirements.keep_last_led;
+        if ui
+            .checkbox(
+                keep_last_led,
+                "Keep retired drivers and ones without recent data on their last LED",
+            )
+            .changed()
+        {
+            self.simulation.set_show_retired(*keep_last_led);
+        }
+        if ui
+            .checkbox(
+                &mut self.blue_flags.enabled,
+                "Pulse cars about to be lapped blue",
+            )
+            .changed()
+        {
+            self.simulation.set_blue_flags(&self.blue_flags);
+        }
+        if ui
+            .checkbox(
+                &mut self.formation.enabled,
+                "Show the starting grid before lights out",
+            )
+            .changed()
+        {
+            let progress = self
+                .race_progress
+                .as_deref()
+                .filter(|_| self.best_laps.is_empty());
+            let led_count = self.coordinates.len();
+            self.simulation.set_grid_formation(grid_formation(
+                progress,
+                led_count,
+                &self.formation,
+            ));
+        }
+        if ui
+            .checkbox(
+                &mut self.led_decay.enabled,
+                "Fade LEDs out as drivers leave them",
+            )
+            .changed()
+        {
+            self.simulation.set_led_decay(&self.led_decay);
+        }
+        if ui
+            .checkbox(
+                &mut self.led_dwell.enabled,
+                "Light the LEDs fast cars pass over between frames",
+            )
+            .changed()
+        {
+            self.simulation.set_led_dwell(&self.led_dwell);
+        }
+        ui.checkbox(
+            &mut self.pedals.enabled,
+            "Show the throttle and brake of the driver focused in the legend",
+        )
+        .on_hover_text("Fetches their car data the first time they're focused");
+        ui.add_enabled(
+            self.pedals.enabled,
+            egui::Checkbox::new(
+                &mut self.pedals.trail,
+                "Tint the LEDs behind them green under throttle and red braking",
+            ),
+        );
+
+        if !self.color_overrides.is_empty() {
+            ui.separator();
+            ui.label("Colors set in [colors] of the config file:");
+            for (driver_number, &color) in &self.color_overrides {
+                ui.horizontal(|ui| {
+                    let (rect, _) =
+                        ui.allocate_exact_size(egui::vec2(10.0, 10.0), egui::Sense::hover());
+                    ui.painter().rect_filled(rect, 0.0, color);
+                    ui.label(format!("{} {}", driver_number, to_hex(color)));
+                });
+            }
+            ui.label(egui::RichText::new("Remove them there to get the team colors back").weak());
+        }
+    }
+}
+
+// What's wrong with a settings field, next to it
+fn field_error(ui: &mut egui::Ui, error: &Option<String>) {
+    match error {
+        Some(error) => {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+        None => {
+            ui.label("");
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use crate::gui::race_control::speed_slider;
+use crate::{join_worker, load_session, PlotApp, SessionLoad};
+use eframe::egui;
+use f1_led_circuit_master_simulation::config::{Config, SessionConfig};
+use f1_led_circuit_master_simulation::driver_info::{
+    apply_color_overrides, driver_colors, driver_teams,
+};
+use f1_led_circuit_master_simulation::error::AppError;
+use f1_led_circuit_master_simulation::led_style::led_shapes;
+use f1_led_circuit_master_simulation::simulation::{PlaybackState, Rgb, Simulation};
+use f1_led_circuit_master_simulation::split::{SplitConfig, SplitPane};
+use f1_led_circuit_master_simulation::viewport::{segment_regions, TrackViewport};
+use log::{error, info};
+use std::sync::atomic::AtomicBool;
+use std::thread::JoinHandle;
+
+/// The split screen window: a second session loaded in the background and shown next to
+/// the one played.
+pub struct SplitScreen {
+    pub shown: bool,
+    pub config: SplitConfig,
+    pub pane: Option<SplitPane>, // A second session shown next to the played one
+    pub job: Option<(String, JoinHandle<Result<SessionLoad, AppError>>)>, // For this session
+    pub message: Option<String>, // Why the second session couldn't be loaded
+}
+
+impl SplitScreen {
+    pub fn new(config: &Config) -> SplitScreen {
+        SplitScreen {
+            shown: false,
+            config: config.split.clone(),
+            pane: None,
+            job: None,
+            message: None,
+        }
+    }
+}
+
+impl PlotApp {
+    pub fn split_window(&mut self, ctx: &egui::Context) {
+        if self
+            .split
+            .job
+            .as_ref()
+            .is_some_and(|(_, job)| job.is_finished())
+        {
+            self.finish_split_load();
+        }
+        let mut show_split = self.split.shown;
+        egui::Window::new("Split screen")
+            .open(&mut show_split)
+            .show(ctx, |ui| self.split_ui(ui));
+        self.split.shown = show_split;
+    }
+
+    // Picks the second session of a split board, and whether the transport acts on both
+    // sessions or each has its own
+    fn split_ui(&mut self, ui: &mut egui::Ui) {
+        let loading = self.split.job.is_some();
+        ui.add_enabled_ui(!loading, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Session");
+                ui.text_edit_singleline(&mut self.split.config.session_key);
+            });
+        });
+        ui.horizontal(|ui| {
+            let can_load = !loading && !self.split.config.session_key.trim().is_empty();
+            if ui
+                .add_enabled(
+                    can_load,
+                    egui::Button::new(if loading { "LOADING..." } else { "LOAD" }),
+                )
+                .clicked()
+            {
+                self.start_split_load();
+            }
+            if ui
+                .add_enabled(self.split.pane.is_some(), egui::Button::new("CLOSE"))
+                .on_hover_text("Show the played session alone again")
+                .clicked()
+            {
+                self.split.pane = None;
+            }
+        });
+        ui.checkbox(&mut self.split.config.ganged, "Gang the transport controls")
+            .on_hover_text("Start, pause and speed act on both sessions");
+
+        if let Some(pane) = &mut self.split.pane {
+            ui.separator();
+            let simulation = &mut pane.simulation;
+            ui.label(format!(
+                "Right: session {}, {} records",
+                pane.session_key,
+                simulation.record_count()
+            ));
+            // Ganged, the played session's controls drive this one every frame
+            ui.add_enabled_ui(!self.split.config.ganged, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("START").clicked() {
+                        simulation.start();
+                    }
+                    let paused = simulation.state() == PlaybackState::Paused;
+                    if ui
+                        .add_enabled(
+                            simulation.is_running(),
+                            egui::Button::new(if paused { "RESUME" } else { "PAUSE" }),
+                        )
+                        .clicked()
+                    {
+                        simulation.set_paused(!paused);
+                    }
+                    let mut speed = simulation.speed();
+                    if ui
+                        .add(speed_slider(&mut speed, self.speed_range.clone()))
+                        .changed()
+                    {
+                        simulation.set_speed(speed);
+                    }
+                });
+            });
+        }
+        if let Some(message) = &self.split.message {
+            ui.label(message);
+        }
+    }
+
+    pub fn start_split_load(&mut self) {
+        let Some(source) = self.data_source.clone() else {
+            return;
+        };
+        let session = SessionConfig {
+            key: self.split.config.session_key.trim().to_string(),
+            ..SessionConfig::default()
+        };
+        let session_key = session.key.clone();
+        let spawned = std::thread::Builder::new()
+            .name("split".to_string())
+            .spawn(move || load_session(&source, &session, &AtomicBool::new(false), true));
+        match spawned {
+            Ok(job) => {
+                self.split.job = Some((session_key, job));
+                self.split.message = None;
+            }
+            Err(err) => self.split.message = Some(AppError::from(err).user_message()),
+        }
+    }
+
+    fn finish_split_load(&mut self) {
+        let Some((session_key, job)) = self.split.job.take() else {
+            return;
+        };
+        match join_worker(job, "split") {
+            Ok(SessionLoad::Loaded(loaded)) => {
+                let mut driver_info = loaded.driver_info;
+                apply_color_overrides(&mut driver_info, &self.color_overrides);
+                let mut simulation = Simulation::new(
+                    loaded.run_race_data,
+                    self.coordinates.len(),
+                    driver_colors(&driver_info),
+                );
+                simulation.set_driver_teams(driver_teams(&driver_info));
+                simulation.set_speed(self.simulation.speed());
+                info!(
+                    "Showing session {} next to session {}: {} records",
+                    session_key,
+                    self.session.key,
+                    simulation.record_count()
+                );
+                self.split.pane = Some(SplitPane {
+                    session_key,
+                    simulation,
+                    driver_info,
+                });
+            }
+            Ok(SessionLoad::Cancelled) => {}
+            Ok(SessionLoad::BadWindow(problem)) => self.split.message = Some(problem.message()),
+            Err(err) => {
+                error!("Could not load session {}: {}", session_key, err);
+                self.split.message = Some(err.user_message());
+            }
+        }
+    }
+
+    // The second session of a split board in its pane, with its own title. It has no
+    // overlays; the legend and the windows are about the played session
+    pub fn split_pane_ui(
+        &self,
+        painter: &egui::Painter,
+        pane: &SplitPane,
+        rect: egui::Rect,
+        clip: egui::Rect,
+    ) {
+        let (track_rect, segment_rects) = segment_regions(rect, self.segment_bounds.len());
+        let viewport = TrackViewport::in_rect(self.bounds, track_rect, 30.0);
+        let positions = self.led_positions(&viewport, &segment_rects);
+        let brightness = match &*self.shown_frame.lock().unwrap() {
+            Some(frame) => frame.brightness,
+            None => 1.0,
+        };
+        let colors: Vec<Rgb> = pane
+            .simulation
+            .frame()
+            .leds
+            .iter()
+            .map(|color| {
+                color
+                    .unwrap_or_default()
+                    .map(|channel| (channel as f32 * brightness).round() as u8)
+            })
+            .collect();
+        painter.line_segment(
+            [rect.left_top(), rect.left_bottom()],
+            egui::Stroke::new(1.0, egui::Color32::GRAY),
+        );
+        painter.extend(led_shapes(
+            self.display.led_style,
+            &positions,
+            &colors,
+            &[],
+            self.display.led_size,
+            self.display.glow_radius,
+            clip,
+        ));
+        self.pane_title(painter, rect, &pane.session_key, &pane.simulation);
+        self.segment_titles(painter, &segment_rects);
+    }
+}
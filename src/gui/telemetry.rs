@@ -0,0 +1,292 @@
+use crate::PlotApp;
+use chrono::Duration as ChronoDuration;
+use eframe::egui;
+use f1_led_circuit_master_simulation::simulation::PlaybackState;
+use f1_led_circuit_master_simulation::telemetry_chart::{
+    visible_points, TelemetrySeries, MAX_PLOT_POINTS,
+};
+use f1_led_circuit_master_simulation::timeline::{time_deltas, LedCrossings};
+
+// Race seconds the telemetry charts show behind and ahead of the cursor while playing
+const TELEMETRY_BEHIND_SECS: f64 = 60.0;
+const TELEMETRY_AHEAD_SECS: f64 = 5.0;
+const TELEMETRY_SPEED_HEIGHT: f32 = 160.0;
+const TELEMETRY_ROW_HEIGHT: f32 = 80.0;
+
+/// The delta and telemetry windows, and the series they chart.
+pub struct TelemetryCharts {
+    pub show_delta: bool,
+    pub delta_drivers: (u32, u32), // How far the first is behind the second
+    pub delta: Vec<[f64; 2]>,      // Race time and delta in seconds, by race time
+    pub delta_for: Option<(u32, u32)>, // The drivers `delta` was worked out for
+    pub show_telemetry: bool,
+    pub telemetry: TelemetrySeries,         // The focused driver's
+    pub telemetry_for: Option<(u32, bool)>, // Whose `telemetry` is, and whether with car data
+}
+
+impl TelemetryCharts {
+    pub fn new(delta_drivers: (u32, u32)) -> TelemetryCharts {
+        TelemetryCharts {
+            show_delta: false,
+            delta_drivers,
+            delta: Vec::new(),
+            delta_for: None,
+            show_telemetry: false,
+            telemetry: TelemetrySeries::default(),
+            telemetry_for: None,
+        }
+    }
+}
+
+impl PlotApp {
+    pub fn telemetry_windows(&mut self, ctx: &egui::Context) {
+        let mut show_delta = self.charts.show_delta;
+        egui::Window::new("Delta")
+            .open(&mut show_delta)
+            .show(ctx, |ui| self.delta_ui(ui));
+        self.charts.show_delta = show_delta;
+
+        let mut show_telemetry = self.charts.show_telemetry;
+        egui::Window::new("Telemetry")
+            .open(&mut show_telemetry)
+            .show(ctx, |ui| self.telemetry_ui(ui));
+        self.charts.show_telemetry = show_telemetry;
+    }
+
+    // Works the delta out again when other drivers were picked
+    fn update_delta(&mut self) {
+        if self.charts.delta_for == Some(self.charts.delta_drivers) {
+            return;
+        }
+        let timelines = self.simulation.timelines();
+        let led_count = self.coordinates.len();
+        let (first, second) = self.charts.delta_drivers;
+        self.charts.delta = match self.simulation.run_race_data().first() {
+            Some(start) => time_deltas(
+                &LedCrossings::new(timelines.timeline(first), led_count),
+                &LedCrossings::new(timelines.timeline(second), led_count),
+                led_count,
+            )
+            .into_iter()
+            .map(|(date, delta)| {
+                [
+                    (date - start.date).num_milliseconds() as f64 / 1000.0,
+                    delta,
+                ]
+            })
+            .collect(),
+            None => Vec::new(),
+        };
+        self.charts.delta_for = Some(self.charts.delta_drivers);
+    }
+
+    fn delta_ui(&mut self, ui: &mut egui::Ui) {
+        let driver_label = |driver_number: u32| {
+            self.driver_info
+                .iter()
+                .find(|driver| driver.number == driver_number)
+                .map_or(driver_number.to_string(), |driver| {
+                    format!("{}: {}", driver.number, driver.name)
+                })
+        };
+        let (first, second) = &mut self.charts.delta_drivers;
+        ui.horizontal(|ui| {
+            for (id, driver_number) in [("delta_first", first), ("delta_second", second)] {
+                egui::ComboBox::from_id_source(id)
+                    .selected_text(driver_label(*driver_number))
+                    .show_ui(ui, |ui| {
+                        for driver in &self.driver_info {
+                            ui.selectable_value(
+                                driver_number,
+                                driver.number,
+                                format!("{}: {}", driver.number, driver.name),
+                            );
+                        }
+                    });
+                if id == "delta_first" {
+                    ui.label("behind");
+                }
+            }
+        });
+        self.update_delta();
+
+        // Only what the race clock has reached
+        let shown = self
+            .charts
+            .delta
+            .partition_point(|point| point[0] <= self.simulation.race_time());
+        let name = format!(
+            "{} behind {} (s)",
+            self.charts.delta_drivers.0, self.charts.delta_drivers.1
+        );
+        egui_plot::Plot::new("delta_plot")
+            .height(200.0)
+            .show(ui, |plot_ui| {
+                plot_ui.line(
+                    egui_plot::Line::new(egui_plot::PlotPoints::new(
+                        self.charts.delta[..shown].to_vec(),
+                    ))
+                    .name(name),
+                );
+            });
+    }
+
+    // Builds the focused driver's series again when the focus moves or their car data arrives
+    fn update_telemetry(&mut self, driver_number: u32) {
+        let car_data = self.car_data.get(&driver_number);
+        let key = (driver_number, car_data.is_some());
+        if self.charts.telemetry_for == Some(key) {
+            return;
+        }
+        self.charts.telemetry = match self.simulation.run_race_data().first() {
+            Some(start) => {
+                let mut series = TelemetrySeries::new(
+                    self.simulation.timelines(),
+                    driver_number,
+                    start.date,
+                    &self.speed,
+                );
+                if let Some(car_data) = car_data {
+                    series.add_car_data(car_data, start.date, &self.speed);
+                }
+                series
+            }
+            None => TelemetrySeries::default(),
+        };
+        self.charts.telemetry_for = Some(key);
+    }
+
+    fn telemetry_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(driver_number) = self.focused_driver else {
+            ui.label("Focus a driver to chart their telemetry");
+            return;
+        };
+        self.update_telemetry(driver_number);
+
+        let race_time = self.simulation.race_time();
+        let following = self.simulation.state() == PlaybackState::Playing;
+        let unit = self.speed.unit.label();
+        let top_speed = self
+            .charts
+            .telemetry
+            .top_speed()
+            .unwrap_or(self.speed.max_speed);
+        let series = &self.charts.telemetry;
+        let mut clicked = telemetry_plot(
+            ui,
+            "telemetry_speed",
+            TELEMETRY_SPEED_HEIGHT,
+            &[
+                (
+                    format!("Estimated speed ({})", unit),
+                    series.estimated_speed.as_slice(),
+                ),
+                (format!("Speed ({})", unit), series.speed.as_slice()),
+            ],
+            [0.0, top_speed * 1.05],
+            race_time,
+            following,
+        );
+        if series.has_car_data() {
+            for (id, name, values, y_range) in [
+                (
+                    "telemetry_throttle",
+                    "Throttle (%)",
+                    &series.throttle,
+                    [0.0, 105.0],
+                ),
+                ("telemetry_gear", "Gear", &series.gear, [0.0, 9.0]),
+            ] {
+                let at = telemetry_plot(
+                    ui,
+                    id,
+                    TELEMETRY_ROW_HEIGHT,
+                    &[(name.to_string(), values.as_slice())],
+                    y_range,
+                    race_time,
+                    following,
+                );
+                clicked = clicked.or(at);
+            }
+        } else if self.pedal_job.is_some() {
+            ui.label("Loading car data...");
+        } else {
+            ui.label("No car data");
+        }
+
+        // A follower's leader has the say over the clock
+        let date = self
+            .simulation
+            .run_race_data()
+            .first()
+            .zip(clicked)
+            .map(|(start, time)| {
+                start.date + ChronoDuration::milliseconds((time.max(0.0) * 1000.0) as i64)
+            });
+        if let (Some(date), false) = (date, self.controls.is_following()) {
+            self.simulation.seek_to_date(date);
+        }
+    }
+}
+
+// One of the telemetry charts, its time axis shared with the others. While playing it follows
+// the cursor; paused, dragging or scrolling pans it in time and zooming spans more or less of it.
+// Returns the race time clicked, if any.
+fn telemetry_plot(
+    ui: &mut egui::Ui,
+    id: &str,
+    height: f32,
+    lines: &[(String, &[[f64; 2]])],
+    y_range: [f64; 2],
+    race_time: f64,
+    following: bool,
+) -> Option<f64> {
+    let response = egui_plot::Plot::new(id)
+        .height(height)
+        .link_axis("telemetry_axis", true, false)
+        .link_cursor("telemetry_cursor", true, false)
+        .allow_drag([true, false])
+        .allow_zoom([true, false])
+        .allow_scroll(false)
+        .include_y(y_range[0])
+        .include_y(y_range[1])
+        .legend(egui_plot::Legend::default())
+        .show(ui, |plot_ui| {
+            let (min, max) = if following {
+                let (min, max) = (
+                    race_time - TELEMETRY_BEHIND_SECS,
+                    race_time + TELEMETRY_AHEAD_SECS,
+                );
+                plot_ui.set_plot_bounds(egui_plot::PlotBounds::from_min_max(
+                    [min, y_range[0]],
+                    [max, y_range[1]],
+                ));
+                (min, max)
+            } else {
+                let bounds = plot_ui.plot_bounds();
+                let (mut min, mut max) = (bounds.min()[0], bounds.max()[0]);
+                // The plot's own scrolling would pan the y axis too, so the wheel pans time here
+                let scroll = plot_ui.ctx().input(|input| input.scroll_delta);
+                let pixels = if scroll.x != 0.0 { scroll.x } else { scroll.y };
+                if plot_ui.response().hovered() && pixels != 0.0 {
+                    let shift = -pixels as f64 / plot_ui.transform().dpos_dvalue_x();
+                    (min, max) = (min + shift, max + shift);
+                    plot_ui.set_plot_bounds(egui_plot::PlotBounds::from_min_max(
+                        [min, bounds.min()[1]],
+                        [max, bounds.max()[1]],
+                    ));
+                }
+                (min, max)
+            };
+            for (name, series) in lines {
+                let points = visible_points(series, min, max, MAX_PLOT_POINTS);
+                plot_ui.line(egui_plot::Line::new(egui_plot::PlotPoints::new(points)).name(name));
+            }
+            plot_ui.vline(egui_plot::VLine::new(race_time));
+        });
+    response
+        .response
+        .interact_pointer_pos()
+        .filter(|_| response.response.clicked())
+        .map(|pos| response.transform.value_from_position(pos).x)
+}
@@ -0,0 +1,337 @@
+use crate::PlotApp;
+use chrono::{DateTime, Utc};
+use eframe::egui;
+use f1_led_circuit_master_simulation::color_scheme::compound_color;
+use f1_led_circuit_master_simulation::lap_table::{format_lap_time, lap_rows};
+use f1_led_circuit_master_simulation::matrix::LedGrid;
+use f1_led_circuit_master_simulation::sectors::SectorTimes;
+use f1_led_circuit_master_simulation::strategy::StrategyChart;
+
+// Sector times like TV graphics: the fastest of everybody, and a driver's own best
+const SECTOR_OVERALL_BEST: egui::Color32 = egui::Color32::from_rgb(170, 70, 255);
+const SECTOR_PERSONAL_BEST: egui::Color32 = egui::Color32::from_rgb(0, 200, 80);
+
+// Rows of the strategy chart, and the driver codes left of them
+const STRATEGY_ROW_HEIGHT: f32 = 14.0;
+const STRATEGY_LABEL_WIDTH: f32 = 36.0;
+
+/// The timing windows: sector times, the lap table, the strategy chart and the matrix preview.
+pub struct TimingTables {
+    pub show_sectors: bool, // The sector times window
+    pub sector_times: SectorTimes,
+    pub sector_driver: u32,           // Whose times the sector window shows
+    pub show_laps: bool,              // The lap table window
+    pub lap_driver: u32,              // Whose laps the lap table shows
+    pub show_strategy: bool,          // The strategy chart window
+    pub strategy_no_spoilers: bool,   // Only stints the clock has reached are charted
+    pub show_matrix: bool,            // The matrix preview window
+    pub matrix_grid: Option<LedGrid>, // The layout on the configured matrix
+}
+
+impl PlotApp {
+    pub fn timing_windows(&mut self, ctx: &egui::Context, race_date: Option<DateTime<Utc>>) {
+        let mut show_sectors = self.timing.show_sectors;
+        egui::Window::new("Sector times")
+            .open(&mut show_sectors)
+            .show(ctx, |ui| self.sectors_ui(ui, race_date));
+        self.timing.show_sectors = show_sectors;
+
+        let mut show_laps = self.timing.show_laps;
+        egui::Window::new("Laps")
+            .open(&mut show_laps)
+            .show(ctx, |ui| self.laps_ui(ui, race_date));
+        self.timing.show_laps = show_laps;
+
+        let mut show_strategy = self.timing.show_strategy;
+        egui::Window::new("Strategy")
+            .open(&mut show_strategy)
+            .show(ctx, |ui| self.strategy_ui(ui, race_date));
+        self.timing.show_strategy = show_strategy;
+
+        let mut show_matrix = self.timing.show_matrix;
+        egui::Window::new("Matrix preview")
+            .open(&mut show_matrix)
+            .show(ctx, |ui| self.matrix_ui(ui));
+        self.timing.show_matrix = show_matrix;
+    }
+
+    // The frame as the configured matrix shows it, next to the true layout in the main view
+    fn matrix_ui(&self, ui: &mut egui::Ui) {
+        let Some(grid) = &self.timing.matrix_grid else {
+            return;
+        };
+        let colors = match &*self.shown_frame.lock().unwrap() {
+            Some(frame) => frame.dimmed(),
+            None => Vec::new(),
+        };
+        let pixels = grid.map(&colors);
+
+        let cell = (ui.available_width() / grid.width() as f32).clamp(4.0, 20.0);
+        let size = egui::vec2(cell * grid.width() as f32, cell * grid.height() as f32);
+        let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, egui::Color32::from_gray(20));
+        for row in 0..grid.height() {
+            for column in 0..grid.width() {
+                let [r, g, b] = pixels[grid.pixel(column, row)];
+                let min = rect.min + egui::vec2(column as f32 * cell, row as f32 * cell);
+                painter.rect_filled(
+                    egui::Rect::from_min_size(min, egui::vec2(cell - 1.0, cell - 1.0)),
+                    0.0,
+                    egui::Color32::from_rgb(r, g, b),
+                );
+            }
+        }
+
+        let mut used: Vec<(usize, usize)> = grid.cells().to_vec();
+        used.sort();
+        used.dedup();
+        ui.label(format!(
+            "{}x{}: {} LEDs on {} cells",
+            grid.width(),
+            grid.height(),
+            grid.cells().len(),
+            used.len()
+        ));
+    }
+
+    fn sectors_ui(&mut self, ui: &mut egui::Ui, race_date: Option<DateTime<Utc>>) {
+        let selected = self
+            .driver_info
+            .iter()
+            .find(|driver| driver.number == self.timing.sector_driver)
+            .map_or(self.timing.sector_driver.to_string(), |driver| {
+                format!("{}: {}", driver.number, driver.name)
+            });
+        egui::ComboBox::from_id_source("sector_driver")
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                for driver in &self.driver_info {
+                    ui.selectable_value(
+                        &mut self.timing.sector_driver,
+                        driver.number,
+                        format!("{}: {}", driver.number, driver.name),
+                    );
+                }
+            });
+
+        let color_of = |seconds: f64, personal_best: f64, overall_best: Option<f64>| {
+            if overall_best.is_some_and(|best| seconds <= best) {
+                Some(SECTOR_OVERALL_BEST)
+            } else if seconds <= personal_best {
+                Some(SECTOR_PERSONAL_BEST)
+            } else {
+                None
+            }
+        };
+        let times = &self.timing.sector_times;
+        // Nothing is known before the race starts
+        let date = race_date.unwrap_or(DateTime::<Utc>::MIN_UTC);
+        egui::Grid::new("sector_times")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("");
+                ui.label("Last");
+                ui.label("Best");
+                ui.end_row();
+                for sector in 1..=times.sectors() {
+                    ui.label(format!("S{}", sector));
+                    let overall_best = times.overall_best(sector, date).map(|time| time.seconds);
+                    match (
+                        times.last(self.timing.sector_driver, sector, date),
+                        times.personal_best(self.timing.sector_driver, sector, date),
+                    ) {
+                        (Some(last), Some(best)) => {
+                            for seconds in [last.seconds, best.seconds] {
+                                let text = egui::RichText::new(format!("{:.3}", seconds));
+                                ui.label(match color_of(seconds, best.seconds, overall_best) {
+                                    Some(color) => text.color(color),
+                                    None => text,
+                                });
+                            }
+                        }
+                        _ => {
+                            ui.label("-");
+                            ui.label("-");
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+    }
+
+    // The completed laps of a driver as the clock passes them; clicking one jumps to its start
+    fn laps_ui(&mut self, ui: &mut egui::Ui, race_date: Option<DateTime<Utc>>) {
+        let selected = self
+            .driver_info
+            .iter()
+            .find(|driver| driver.number == self.timing.lap_driver)
+            .map_or(self.timing.lap_driver.to_string(), |driver| {
+                format!("{}: {}", driver.number, driver.name)
+            });
+        egui::ComboBox::from_id_source("lap_driver")
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                for driver in &self.driver_info {
+                    ui.selectable_value(
+                        &mut self.timing.lap_driver,
+                        driver.number,
+                        format!("{}: {}", driver.number, driver.name),
+                    );
+                }
+            });
+
+        let Some(progress) = &self.race_progress else {
+            ui.label("No lap data for this session");
+            return;
+        };
+        // Nothing is completed before the race starts
+        let date = race_date.unwrap_or(DateTime::<Utc>::MIN_UTC);
+        let rows = lap_rows(progress, self.timing.lap_driver, date);
+        let mut clicked = None;
+        egui::ScrollArea::vertical()
+            .max_height(300.0)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                egui::Grid::new("lap_times").striped(true).show(ui, |ui| {
+                    for heading in ["Lap", "Time", "S1", "S2", "S3"] {
+                        ui.strong(heading);
+                    }
+                    ui.end_row();
+                    for row in &rows {
+                        let lap = match row.pit_out {
+                            true => format!("{} OUT", row.lap_number),
+                            false => row.lap_number.to_string(),
+                        };
+                        let time = egui::RichText::new(format_lap_time(row.lap_time));
+                        let time = match row.personal_best {
+                            true => time.color(SECTOR_PERSONAL_BEST),
+                            false => time,
+                        };
+                        let cells = [egui::RichText::new(lap), time]
+                            .into_iter()
+                            .chain(row.sectors.map(|sector| format_lap_time(sector).into()));
+                        let mut row_clicked = false;
+                        for cell in cells {
+                            row_clicked |= ui
+                                .add(egui::Label::new(cell).sense(egui::Sense::click()))
+                                .on_hover_text("Jump to the start of this lap")
+                                .clicked();
+                        }
+                        if row_clicked {
+                            clicked = row.start;
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        if let (Some(start), false) = (clicked, self.controls.is_following()) {
+            self.simulation.seek_to_date(start);
+        }
+    }
+
+    // Every driver's stints by compound and pit stops along the laps, with the clock as a
+    // playhead; a click seeks there
+    fn strategy_ui(&mut self, ui: &mut egui::Ui, race_date: Option<DateTime<Utc>>) {
+        ui.checkbox(&mut self.timing.strategy_no_spoilers, "No spoilers")
+            .on_hover_text("Only show the stints and pit stops the clock has reached");
+        // The best laps run on clocks of their own
+        let chart = match self.race_progress.as_deref() {
+            Some(progress) if self.best_laps.is_empty() => StrategyChart::new(progress),
+            _ => StrategyChart::default(),
+        };
+        if chart.span().is_none() {
+            ui.label("No lap data for this session");
+            return;
+        }
+
+        let (rect, response) = ui.allocate_exact_size(
+            egui::vec2(
+                ui.available_width(),
+                chart.rows().len() as f32 * STRATEGY_ROW_HEIGHT,
+            ),
+            egui::Sense::click(),
+        );
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+        let bars = egui::Rect::from_x_y_ranges(
+            rect.left() + STRATEGY_LABEL_WIDTH..=rect.right(),
+            rect.y_range(),
+        );
+        let x = |date: DateTime<Utc>| {
+            bars.left() + bars.width() * chart.share(date).unwrap_or_default() as f32
+        };
+        let font = egui::FontId::monospace(STRATEGY_ROW_HEIGHT * 0.7);
+        let text_color = ui.visuals().text_color();
+        // Nothing has happened before the race starts
+        let revealed = race_date.unwrap_or(DateTime::<Utc>::MIN_UTC);
+        for (index, row) in chart.rows().iter().enumerate() {
+            let top = rect.top() + index as f32 * STRATEGY_ROW_HEIGHT;
+            let middle = top + STRATEGY_ROW_HEIGHT / 2.0;
+            let code = self
+                .driver_info
+                .iter()
+                .find(|driver| driver.number == row.driver_number)
+                .map_or(row.driver_number.to_string(), |driver| driver.code.clone());
+            painter.text(
+                egui::pos2(rect.left() + 2.0, middle),
+                egui::Align2::LEFT_CENTER,
+                code,
+                font.clone(),
+                text_color,
+            );
+            if row.stints.is_empty() {
+                painter.text(
+                    egui::pos2(bars.left() + 4.0, middle),
+                    egui::Align2::LEFT_CENTER,
+                    "no stint data",
+                    font.clone(),
+                    ui.visuals().weak_text_color(),
+                );
+                continue;
+            }
+            let row = match self.timing.strategy_no_spoilers {
+                true => row.until(revealed),
+                false => row.clone(),
+            };
+            for stint in &row.stints {
+                let start = x(stint.start);
+                painter.rect_filled(
+                    egui::Rect::from_x_y_ranges(
+                        start..=x(stint.end).max(start + 1.0),
+                        top + 2.0..=top + STRATEGY_ROW_HEIGHT - 2.0,
+                    ),
+                    2.0,
+                    compound_color(stint.compound.as_deref()),
+                );
+            }
+            for &pit_stop in &row.pit_stops {
+                painter.line_segment(
+                    [
+                        egui::pos2(x(pit_stop), top),
+                        egui::pos2(x(pit_stop), top + STRATEGY_ROW_HEIGHT),
+                    ],
+                    egui::Stroke::new(2.0, text_color),
+                );
+            }
+        }
+        if let Some(date) = race_date {
+            painter.line_segment(
+                [
+                    egui::pos2(x(date), rect.top()),
+                    egui::pos2(x(date), rect.bottom()),
+                ],
+                egui::Stroke::new(2.0, ui.visuals().strong_text_color()),
+            );
+        }
+
+        let clicked = response
+            .interact_pointer_pos()
+            .filter(|_| response.clicked())
+            .and_then(|pos| chart.date_at(((pos.x - bars.left()) / bars.width().max(1.0)) as f64));
+        // A follower's leader has the say over the clock
+        if let (Some(date), false) = (clicked, self.controls.is_following()) {
+            self.simulation.seek_to_date(date);
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use crate::error::AppError;
+use crate::mapping::RunRace;
+use crate::render::FrameRenderer;
+use crate::simulation::Rgb;
+use crate::space::LedPoint;
+use chrono::{DateTime, Utc};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::thread::{self, JoinHandle};
+
+const COLD: Rgb = [0, 0, 255];
+const HOT: Rgb = [255, 0, 0];
+
+/// Settings of the heatmap mode and its export.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeatmapConfig {
+    pub max_gap_secs: f64, // Longer gaps between a driver's samples count only this long
+    pub csv: PathBuf,
+    pub png: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    pub led_size: f32,
+}
+
+impl Default for HeatmapConfig {
+    fn default() -> Self {
+        HeatmapConfig {
+            max_gap_secs: 5.0,
+            csv: PathBuf::from("heatmap.csv"),
+            png: PathBuf::from("heatmap.png"),
+            width: 1600,
+            height: 900,
+            led_size: 8.0,
+        }
+    }
+}
+
+/// Seconds spent on each LED up to the playback point, by every driver or by one of them,
+/// added up record by record as the clock moves. Like the occupancy, the time from each sample
+/// to the driver's next one counts for the LED of the earlier sample.
+#[derive(Debug, Clone)]
+pub struct Heatmap {
+    driver: Option<u32>, // Only this driver's time, or everybody's
+    max_gap_secs: f64,
+    seconds: Vec<f64>,                          // Per LED
+    last: HashMap<u32, (usize, DateTime<Utc>)>, // Each driver's LED and date of their last record
+    applied: usize,                             // Records before this index are counted
+}
+
+impl Heatmap {
+    pub fn new(led_count: usize, driver: Option<u32>, max_gap_secs: f64) -> Heatmap {
+        Heatmap {
+            driver,
+            max_gap_secs,
+            seconds: vec![0.0; led_count],
+            last: HashMap::new(),
+            applied: 0,
+        }
+    }
+
+    pub fn driver(&self) -> Option<u32> {
+        self.driver
+    }
+
+    /// Seconds spent on the LED so far, zero for unknown LEDs.
+    pub fn seconds(&self, led_index: usize) -> f64 {
+        self.seconds.get(led_index).copied().unwrap_or(0.0)
+    }
+
+    /// Starts over from the first record.
+    pub fn reset(&mut self) {
+        self.seconds.fill(0.0);
+        self.last.clear();
+        self.applied = 0;
+    }
+
+    /// Counts the records of `run_race_data`, which must be sorted by date, up to `index`.
+    /// Going backwards starts over, so the map always shows the time up to the clock.
+    pub fn advance(&mut self, run_race_data: &[RunRace], index: usize) {
+        let index = index.min(run_race_data.len());
+        if index < self.applied {
+            self.reset();
+        }
+        for run_data in &run_race_data[self.applied..index] {
+            if self
+                .driver
+                .is_some_and(|driver_number| driver_number != run_data.driver_number)
+            {
+                continue;
+            }
+            let previous = self
+                .last
+                .insert(run_data.driver_number, (run_data.led_index, run_data.date));
+            if let Some((led_index, since)) = previous {
+                let gap = (run_data.date - since).num_milliseconds() as f64 / 1000.0;
+                if let Some(seconds) = self.seconds.get_mut(led_index) {
+                    *seconds += gap.min(self.max_gap_secs);
+                }
+            }
+        }
+        self.applied = index;
+    }
+
+    /// Each LED on a blue to red scale from no time to the busiest LED's; LEDs nobody spent
+    /// time on are off.
+    pub fn colors(&self) -> Vec<Option<Rgb>> {
+        let busiest = self.seconds.iter().copied().fold(0.0, f64::max);
+        self.seconds
+            .iter()
+            .map(|&seconds| (seconds > 0.0).then(|| gradient(seconds / busiest)))
+            .collect()
+    }
+
+    /// One row per LED, labelled like the layout (`U1`, `U2`, ...), with its seconds and their
+    /// share of the busiest LED's.
+    pub fn write_csv(&self, writer: impl Write) -> Result<(), AppError> {
+        let csv_error = |err: csv::Error| AppError::Export {
+            reason: format!("could not write the heatmap CSV: {}", err),
+        };
+        let busiest = self.seconds.iter().copied().fold(0.0, f64::max);
+        let mut writer = csv::Writer::from_writer(writer);
+        writer
+            .write_record(["led", "seconds", "share"])
+            .map_err(csv_error)?;
+        for (led_index, &seconds) in self.seconds.iter().enumerate() {
+            let share = if busiest > 0.0 {
+                seconds / busiest
+            } else {
+                0.0
+            };
+            writer
+                .write_record([
+                    format!("U{}", led_index + 1),
+                    format!("{:.3}", seconds),
+                    format!("{:.3}", share),
+                ])
+                .map_err(csv_error)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+// Linear from blue at 0 to red at 1
+fn gradient(share: f64) -> Rgb {
+    let share = share.clamp(0.0, 1.0);
+    let mix = |cold: u8, hot: u8| (cold as f64 + (hot as f64 - cold as f64) * share).round() as u8;
+    [
+        mix(COLD[0], HOT[0]),
+        mix(COLD[1], HOT[1]),
+        mix(COLD[2], HOT[2]),
+    ]
+}
+
+/// Adds up the whole race for `driver`, or everybody, and writes the heatmap CSV and a PNG of
+/// the track in its colors to the configured files on a worker thread.
+pub fn export_heatmap(
+    run_race_data: Vec<RunRace>,
+    driver: Option<u32>,
+    coordinates: &[LedPoint],
+    config: &HeatmapConfig,
+) -> Result<JoinHandle<Result<(), AppError>>, AppError> {
+    let renderer = FrameRenderer::new(coordinates, config.width, config.height, config.led_size);
+    let led_count = coordinates.len();
+    let config = config.clone();
+    let thread = thread::Builder::new()
+        .name("heatmap".to_string())
+        .spawn(move || {
+            let mut heatmap = Heatmap::new(led_count, driver, config.max_gap_secs);
+            heatmap.advance(&run_race_data, run_race_data.len());
+            heatmap.write_csv(std::fs::File::create(&config.csv)?)?;
+            let leds: Vec<Rgb> = heatmap
+                .colors()
+                .into_iter()
+                .map(|color| color.unwrap_or([0, 0, 0]))
+                .collect();
+            renderer
+                .render(&leds, None)
+                .save(&config.png)
+                .map_err(|err| AppError::Export {
+                    reason: format!("could not write {}: {}", config.png.display(), err),
+                })?;
+            info!(
+                "Wrote the heatmap to {} and {}",
+                config.csv.display(),
+                config.png.display()
+            );
+            Ok(())
+        })?;
+    Ok(thread)
+}
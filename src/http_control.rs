@@ -0,0 +1,211 @@
+use crate::control::{ControlConfig, PlaybackCommand};
+use crate::error::AppError;
+use crate::simulation::PlaybackState;
+use crate::sink::{LedSink, OutputFrame};
+use axum::extract::{Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use tokio::sync::watch;
+
+/// What `GET /status` reports.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ControlStatus {
+    pub race_time: f64, // Seconds
+    pub state: PlaybackState,
+    pub speed: f64,
+    pub records: usize, // Mapped records, or recorded frames when playing a recording
+    pub leds: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct SpeedRequest {
+    value: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct SeekRequest {
+    seconds: f64,
+}
+
+#[derive(Clone)]
+struct ControlState {
+    commands: Sender<PlaybackCommand>,
+    status: Arc<Mutex<ControlStatus>>,
+    token: Option<Arc<str>>,
+}
+
+/// Serves the HTTP control API: `POST /start`, `/stop`, `/pause`, `/speed {"value"}` and
+/// `/seek {"seconds"}` queue a command for the simulation's thread, and `GET /status` reports
+/// the state of the latest frame, which reaches the server as an output.
+pub struct ControlServer {
+    runtime: Option<Runtime>,
+    address: SocketAddr,
+    status: Arc<Mutex<ControlStatus>>,
+    stop: watch::Sender<bool>,
+}
+
+impl ControlServer {
+    /// Starts listening; `records` and `leds` describe the loaded data for the status.
+    pub fn open(
+        config: &ControlConfig,
+        commands: Sender<PlaybackCommand>,
+        records: usize,
+        leds: usize,
+    ) -> Result<ControlServer, AppError> {
+        ControlServer::open_with_routes(config, commands, records, leds, Router::new())
+    }
+
+    /// Like `open`, also serving `routes`, e.g. the metrics, behind the same token.
+    pub fn open_with_routes(
+        config: &ControlConfig,
+        commands: Sender<PlaybackCommand>,
+        records: usize,
+        leds: usize,
+        routes: Router,
+    ) -> Result<ControlServer, AppError> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("http-control")
+            .enable_all()
+            .build()?;
+        let listener = runtime
+            .block_on(tokio::net::TcpListener::bind((
+                config.bind.as_str(),
+                config.port,
+            )))
+            .map_err(|err| AppError::Config {
+                reason: format!(
+                    "could not serve the control API on {}:{}: {}",
+                    config.bind, config.port, err
+                ),
+            })?;
+        let address = listener.local_addr()?;
+
+        let status = Arc::new(Mutex::new(ControlStatus {
+            race_time: 0.0,
+            state: PlaybackState::Stopped,
+            speed: 1.0,
+            records,
+            leds,
+        }));
+        let state = ControlState {
+            commands,
+            status: Arc::clone(&status),
+            token: config.token.as_deref().map(Arc::from),
+        };
+        let router = Router::new()
+            .route("/start", post(start))
+            .route("/stop", post(stop))
+            .route("/pause", post(pause))
+            .route("/speed", post(speed))
+            .route("/seek", post(seek))
+            .route("/status", get(status_handler))
+            .with_state(state.clone())
+            .merge(routes)
+            .route_layer(middleware::from_fn_with_state(state, authorize));
+
+        let (stop, mut stopped) = watch::channel(false);
+        runtime.spawn(async move {
+            let server = axum::serve(listener, router).with_graceful_shutdown(async move {
+                let _ = stopped.changed().await;
+            });
+            if let Err(err) = server.await {
+                warn!("The control API stopped: {}", err);
+            }
+        });
+        info!("Serving the control API on {}", address);
+
+        Ok(ControlServer {
+            runtime: Some(runtime),
+            address,
+            status,
+            stop,
+        })
+    }
+
+    /// The address the server listens on, with the actual port when the config asked for 0.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+}
+
+impl LedSink for ControlServer {
+    fn name(&self) -> &str {
+        "http-control"
+    }
+
+    fn submit(&mut self, frame: &OutputFrame) -> Result<(), AppError> {
+        let mut status = self.status.lock().unwrap();
+        status.race_time = frame.timestamp.as_secs_f64();
+        status.state = frame.state;
+        status.speed = frame.speed;
+        Ok(())
+    }
+
+    fn shutdown(&mut self) {
+        let _ = self.stop.send(true);
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_timeout(Duration::from_secs(1));
+        }
+    }
+}
+
+async fn authorize(State(state): State<ControlState>, request: Request, next: Next) -> Response {
+    if let Some(token) = &state.token {
+        let authorized = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|given| given == &**token);
+        if !authorized {
+            return StatusCode::UNAUTHORIZED.into_response();
+        }
+    }
+    next.run(request).await
+}
+
+// Commands are applied on the simulation's next tick, hence 202
+fn queue(state: &ControlState, command: PlaybackCommand) -> StatusCode {
+    match state.commands.send(command) {
+        Ok(()) => StatusCode::ACCEPTED,
+        Err(_) => StatusCode::SERVICE_UNAVAILABLE, // The simulation has shut down
+    }
+}
+
+async fn start(State(state): State<ControlState>) -> StatusCode {
+    queue(&state, PlaybackCommand::Start)
+}
+
+async fn stop(State(state): State<ControlState>) -> StatusCode {
+    queue(&state, PlaybackCommand::Stop)
+}
+
+async fn pause(State(state): State<ControlState>) -> StatusCode {
+    queue(&state, PlaybackCommand::Pause)
+}
+
+async fn speed(State(state): State<ControlState>, Json(request): Json<SpeedRequest>) -> StatusCode {
+    queue(&state, PlaybackCommand::SetSpeed(request.value))
+}
+
+async fn seek(State(state): State<ControlState>, Json(request): Json<SeekRequest>) -> StatusCode {
+    match Duration::try_from_secs_f64(request.seconds) {
+        Ok(race_time) => queue(&state, PlaybackCommand::Seek(race_time)),
+        Err(_) => StatusCode::BAD_REQUEST,
+    }
+}
+
+async fn status_handler(State(state): State<ControlState>) -> Json<ControlStatus> {
+    Json(state.status.lock().unwrap().clone())
+}
@@ -0,0 +1,175 @@
+use crate::control::PlaybackCommand;
+use serde::{Deserialize, Serialize};
+
+/// Gamepad controls, read when the `gamepad` feature is built in: A starts or pauses, B stops
+/// and rewinds, and the bumpers change the speed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadConfig {
+    pub enabled: bool,
+    pub speed_step: f64, // Speed change per bumper press
+}
+
+impl Default for GamepadConfig {
+    fn default() -> Self {
+        GamepadConfig {
+            enabled: false,
+            speed_step: 1.0,
+        }
+    }
+}
+
+/// Push buttons on Raspberry Pi GPIO pins, read when the `gpio` feature is built in. Each
+/// button connects its pin to ground; the internal pull-up keeps it high otherwise.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GpioConfig {
+    pub enabled: bool,
+    pub debounce_ms: u64, // A press counts once the pin has been low this long
+    pub buttons: Vec<GpioButton>,
+}
+
+impl Default for GpioConfig {
+    fn default() -> Self {
+        GpioConfig {
+            enabled: false,
+            debounce_ms: 30,
+            buttons: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GpioButton {
+    pub pin: u8, // BCM pin number
+    pub command: PlaybackCommand,
+}
+
+#[cfg(feature = "gamepad")]
+pub use gamepad::Gamepad;
+
+#[cfg(feature = "gamepad")]
+mod gamepad {
+    use super::GamepadConfig;
+    use crate::control::PlaybackCommand;
+    use crate::error::AppError;
+    use gilrs::{Button, EventType, Gilrs};
+    use log::info;
+
+    /// Every connected gamepad, polled from the thread that owns the simulation.
+    pub struct Gamepad {
+        gilrs: Gilrs,
+        speed_step: f64,
+    }
+
+    impl Gamepad {
+        pub fn open(config: &GamepadConfig) -> Result<Gamepad, AppError> {
+            let gilrs = Gilrs::new().map_err(|err| AppError::Input {
+                reason: format!("could not open the gamepads: {}", err),
+            })?;
+            info!("Listening for gamepad buttons");
+            Ok(Gamepad {
+                gilrs,
+                speed_step: config.speed_step,
+            })
+        }
+
+        /// The commands of the buttons pressed since the last poll.
+        pub fn poll(&mut self) -> Vec<PlaybackCommand> {
+            let mut commands = Vec::new();
+            while let Some(event) = self.gilrs.next_event() {
+                let EventType::ButtonPressed(button, _) = event.event else {
+                    continue;
+                };
+                commands.extend(match button {
+                    Button::South => Some(PlaybackCommand::TogglePause),
+                    Button::East => Some(PlaybackCommand::Stop),
+                    Button::LeftTrigger => Some(PlaybackCommand::AdjustSpeed(-self.speed_step)),
+                    Button::RightTrigger => Some(PlaybackCommand::AdjustSpeed(self.speed_step)),
+                    _ => None,
+                });
+            }
+            commands
+        }
+    }
+}
+
+#[cfg(feature = "gpio")]
+pub use gpio::watch_buttons;
+
+#[cfg(feature = "gpio")]
+mod gpio {
+    use super::GpioConfig;
+    use crate::control::PlaybackCommand;
+    use crate::error::AppError;
+    use log::{debug, info};
+    use rppal::gpio::Gpio;
+    use std::sync::mpsc::Sender;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    // Often enough that the shortest press is seen, rarely enough to cost nothing
+    const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+    /// Watches the configured buttons on a thread of their own and sends the command of each
+    /// press. The thread ends with the process or once `commands` has no receiver.
+    pub fn watch_buttons(
+        config: &GpioConfig,
+        commands: Sender<PlaybackCommand>,
+    ) -> Result<(), AppError> {
+        let gpio_error = |err: rppal::gpio::Error| AppError::Input {
+            reason: format!("GPIO: {}", err),
+        };
+        let gpio = Gpio::new().map_err(gpio_error)?;
+        let mut buttons = Vec::new();
+        for button in &config.buttons {
+            let pin = gpio
+                .get(button.pin)
+                .map_err(gpio_error)?
+                .into_input_pullup();
+            buttons.push((pin, button.command, Debouncer::default()));
+        }
+        let debounce = Duration::from_millis(config.debounce_ms);
+        info!("Watching {} GPIO buttons", buttons.len());
+
+        thread::Builder::new()
+            .name("gpio-buttons".to_string())
+            .spawn(move || loop {
+                let now = Instant::now();
+                for (pin, command, debouncer) in &mut buttons {
+                    if debouncer.update(pin.is_low(), now, debounce) {
+                        debug!("GPIO button pressed: {:?}", command);
+                        if commands.send(*command).is_err() {
+                            return;
+                        }
+                    }
+                }
+                thread::sleep(POLL_INTERVAL);
+            })?;
+        Ok(())
+    }
+
+    // One button's level, which has to hold for the debounce time before it counts
+    #[derive(Default)]
+    struct Debouncer {
+        pressed: bool,
+        changing_since: Option<Instant>, // When the raw level started to differ from `pressed`
+    }
+
+    impl Debouncer {
+        // Returns whether this reading completes a press
+        fn update(&mut self, low: bool, now: Instant, debounce: Duration) -> bool {
+            if low == self.pressed {
+                self.changing_since = None;
+                return false;
+            }
+            let since = *self.changing_since.get_or_insert(now);
+            if now - since < debounce {
+                return false;
+            }
+            self.pressed = low;
+            self.changing_since = None;
+            low
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use crate::error::AppError;
+use crate::laps::RaceProgress;
+use crate::render::{draw_line, draw_text, text_size, BACKGROUND, TEXT_COLOR};
+use crate::simulation::Rgb;
+use image::{Rgba, RgbaImage};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::thread::{self, JoinHandle};
+
+const GRID_COLOR: Rgba<u8> = Rgba([60, 60, 60, 255]);
+const LABEL_COLOR: Rgba<u8> = Rgba([160, 160, 160, 255]);
+const DEFAULT_LINE_COLOR: Rgb = [255, 255, 255];
+const LAP_LABEL_EVERY: u32 = 5;
+
+/// Settings of the lap chart export.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LapChartConfig {
+    pub csv: PathBuf,
+    pub png: PathBuf,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for LapChartConfig {
+    fn default() -> Self {
+        LapChartConfig {
+            csv: PathBuf::from("lap_chart.csv"),
+            png: PathBuf::from("lap_chart.png"),
+            width: 1600,
+            height: 900,
+        }
+    }
+}
+
+/// Every driver's race position at the end of each lap they completed, lap 0 being the grid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LapChart {
+    drivers: Vec<u32>,                // Sorted by number
+    positions: Vec<Vec<Option<u32>>>, // Per driver in the order of `drivers`, per lap from 0
+}
+
+impl LapChart {
+    pub fn new(progress: &RaceProgress) -> LapChart {
+        let drivers = progress.drivers();
+        let positions = drivers
+            .iter()
+            .map(|&driver_number| {
+                let mut by_lap = vec![progress.starting_position(driver_number)];
+                // A retired driver's line stops at the last lap they completed
+                for lap in progress.driver_laps(driver_number) {
+                    let Some(end) = progress.lap_end(driver_number, lap.lap_number) else {
+                        break;
+                    };
+                    // Laps missing in between have no position; a repeated lap replaces the
+                    // position instead of cutting off the laps after it
+                    let index = lap.lap_number as usize;
+                    if by_lap.len() <= index {
+                        by_lap.resize(index + 1, None);
+                    }
+                    by_lap[index] = progress.position_at(driver_number, end);
+                }
+                by_lap
+            })
+            .collect();
+        LapChart { drivers, positions }
+    }
+
+    pub fn drivers(&self) -> &[u32] {
+        &self.drivers
+    }
+
+    /// Number of laps the leader completed.
+    pub fn laps(&self) -> u32 {
+        self.positions
+            .iter()
+            .map(|by_lap| by_lap.len().saturating_sub(1) as u32)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The driver's position at the end of `lap`, `None` past their last completed lap.
+    pub fn position(&self, driver_number: u32, lap: u32) -> Option<u32> {
+        let column = self.drivers.binary_search(&driver_number).ok()?;
+        *self.positions[column].get(lap as usize)?
+    }
+
+    /// One row per lap with a column of positions per driver, empty where there's none.
+    pub fn write_csv(&self, writer: impl Write) -> Result<(), AppError> {
+        let csv_error = |err: csv::Error| AppError::Export {
+            reason: format!("could not write the lap chart CSV: {}", err),
+        };
+        let mut writer = csv::Writer::from_writer(writer);
+        let mut header = vec!["lap".to_string()];
+        header.extend(self.drivers.iter().map(u32::to_string));
+        writer.write_record(&header).map_err(csv_error)?;
+
+        for lap in 0..=self.laps() {
+            let mut record = vec![lap.to_string()];
+            record.extend(self.drivers.iter().map(|&driver_number| {
+                self.position(driver_number, lap)
+                    .map_or(String::new(), |position| position.to_string())
+            }));
+            writer.write_record(&record).map_err(csv_error)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Draws position (leader on top) against lap, one line per driver in their color, with the
+    /// driver's number where the line ends.
+    pub fn render(&self, width: u32, height: u32, colors: &HashMap<u32, Rgb>) -> RgbaImage {
+        let mut image = RgbaImage::from_pixel(width, height, BACKGROUND);
+        let scale = (height / 300).max(1);
+        let slots = self
+            .positions
+            .iter()
+            .flatten()
+            .flatten()
+            .copied()
+            .max()
+            .unwrap_or(1)
+            .max(self.drivers.len() as u32)
+            .max(1);
+        let laps = self.laps().max(1);
+
+        let (label_width, label_height) = text_size(&slots.to_string(), scale);
+        let left = (label_width + 6 * scale) as f32;
+        let right = width as f32 - (text_size("00", scale).0 + 6 * scale) as f32;
+        let top = (label_height + 4 * scale) as f32;
+        let bottom = height as f32 - (label_height + 6 * scale) as f32;
+        let x_of = |lap: u32| left + (right - left) * lap as f32 / laps as f32;
+        // A single slot, or a position of 0, goes on the top line
+        let y_of = |position: u32| {
+            top + (bottom - top) * position.saturating_sub(1) as f32 / (slots - 1).max(1) as f32
+        };
+
+        for position in 1..=slots {
+            let y = y_of(position);
+            draw_line(&mut image, (left, y), (right, y), 1, GRID_COLOR);
+            let text = position.to_string();
+            let text_width = text_size(&text, scale).0;
+            draw_text(
+                &mut image,
+                (left as u32).saturating_sub(text_width + 3 * scale) as i64,
+                (y - (label_height / 2) as f32) as i64,
+                scale,
+                &text,
+                LABEL_COLOR,
+            );
+        }
+        for lap in (0..=laps).step_by(LAP_LABEL_EVERY as usize) {
+            let x = x_of(lap);
+            draw_line(&mut image, (x, top), (x, bottom), 1, GRID_COLOR);
+            let text = lap.to_string();
+            let text_width = text_size(&text, scale).0;
+            draw_text(
+                &mut image,
+                (x - (text_width / 2) as f32) as i64,
+                (bottom + 3.0 * scale as f32) as i64,
+                scale,
+                &text,
+                LABEL_COLOR,
+            );
+        }
+
+        for (column, &driver_number) in self.drivers.iter().enumerate() {
+            let [r, g, b] = colors
+                .get(&driver_number)
+                .copied()
+                .unwrap_or(DEFAULT_LINE_COLOR);
+            let color = Rgba([r, g, b, 255]);
+            let points: Vec<(f32, f32)> = self.positions[column]
+                .iter()
+                .enumerate()
+                .filter_map(|(lap, position)| Some((x_of(lap as u32), y_of((*position)?))))
+                .collect();
+            for pair in points.windows(2) {
+                draw_line(&mut image, pair[0], pair[1], 2 * scale, color);
+            }
+            if let Some(&(x, y)) = points.last() {
+                draw_text(
+                    &mut image,
+                    (x + 3.0 * scale as f32) as i64,
+                    (y - (label_height / 2) as f32) as i64,
+                    scale,
+                    &driver_number.to_string(),
+                    TEXT_COLOR,
+                );
+            }
+        }
+        image
+    }
+}
+
+/// Writes the lap chart CSV and PNG to the configured files on a worker thread, which returns
+/// the chart for a summary.
+pub fn export_lap_chart(
+    progress: &RaceProgress,
+    colors: HashMap<u32, Rgb>,
+    config: &LapChartConfig,
+) -> Result<JoinHandle<Result<LapChart, AppError>>, AppError> {
+    let chart = LapChart::new(progress);
+    let config = config.clone();
+    let thread = thread::Builder::new()
+        .name("lap-chart".to_string())
+        .spawn(move || {
+            chart.write_csv(std::fs::File::create(&config.csv)?)?;
+            chart
+                .render(config.width, config.height, &colors)
+                .save(&config.png)
+                .map_err(|err| AppError::Export {
+                    reason: format!("could not write {}: {}", config.png.display(), err),
+                })?;
+            info!(
+                "Wrote the lap chart of {} laps to {} and {}",
+                chart.laps(),
+                config.csv.display(),
+                config.png.display()
+            );
+            Ok(chart)
+        })?;
+    Ok(thread)
+}
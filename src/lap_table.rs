@@ -0,0 +1,60 @@
+use crate::laps::RaceProgress;
+use chrono::{DateTime, Utc};
+
+/// A completed lap of one driver, as shown in the lap table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LapRow {
+    pub lap_number: u32,
+    pub start: Option<DateTime<Utc>>,
+    pub lap_time: Option<f64>,     // Seconds
+    pub sectors: [Option<f64>; 3], // Seconds, often missing on in and out laps
+    pub personal_best: bool,       // Faster than any of the driver's earlier laps
+    pub pit_out: bool,
+}
+
+/// The laps `driver_number` had completed at `time`, in order. A lap counts as a personal
+/// best when it was the driver's fastest so far as it was set, so there's one at every
+/// improvement.
+pub fn lap_rows(progress: &RaceProgress, driver_number: u32, time: DateTime<Utc>) -> Vec<LapRow> {
+    let mut best = f64::INFINITY;
+    progress
+        .driver_laps(driver_number)
+        .iter()
+        .take_while(|lap| {
+            progress
+                .lap_end(driver_number, lap.lap_number)
+                .is_some_and(|end| end <= time)
+        })
+        .map(|lap| {
+            let personal_best = lap.lap_duration.is_some_and(|duration| duration < best);
+            if let Some(duration) = lap.lap_duration {
+                best = best.min(duration);
+            }
+            LapRow {
+                lap_number: lap.lap_number,
+                start: lap.date_start,
+                lap_time: lap.lap_duration,
+                sectors: [
+                    lap.duration_sector_1,
+                    lap.duration_sector_2,
+                    lap.duration_sector_3,
+                ],
+                personal_best,
+                pit_out: lap.is_pit_out_lap,
+            }
+        })
+        .collect()
+}
+
+/// A lap or sector time like "1:13.847", or "23.456" under a minute; "-" when unknown.
+pub fn format_lap_time(seconds: Option<f64>) -> String {
+    let Some(seconds) = seconds.filter(|seconds| seconds.is_finite() && *seconds >= 0.0) else {
+        return "-".to_string();
+    };
+    // Rounded once, so 59.9996 becomes 1:00.000 rather than 0:60.000
+    let millis = (seconds * 1000.0).round() as u64;
+    match millis / 60_000 {
+        0 => format!("{}.{:03}", millis / 1000, millis % 1000),
+        minutes => format!("{}:{:02}.{:03}", minutes, millis / 1000 % 60, millis % 1000),
+    }
+}
@@ -0,0 +1,256 @@
+use crate::data::{IntervalData, LapData, PositionData, RaceControlData, StintData};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// The laps, race positions, gaps, tyre stints and race control messages of a session, for what
+/// the location samples can't tell.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RaceProgress {
+    laps: Vec<LapData>,                 // Sorted by driver, then lap number
+    positions: Vec<PositionData>,       // Sorted by driver, then date
+    intervals: Vec<IntervalData>,       // Sorted by driver, then date
+    stints: Vec<StintData>,             // Sorted by driver, then stint number
+    race_control: Vec<RaceControlData>, // Sorted by date
+}
+
+impl RaceProgress {
+    pub fn new(mut laps: Vec<LapData>, mut positions: Vec<PositionData>) -> RaceProgress {
+        laps.sort_by_key(|lap| (lap.driver_number, lap.lap_number));
+        positions.sort_by_key(|position| (position.driver_number, position.date));
+        RaceProgress {
+            laps,
+            positions,
+            intervals: Vec::new(),
+            stints: Vec::new(),
+            race_control: Vec::new(),
+        }
+    }
+
+    /// The progress with the gaps to the leader, which only races have.
+    pub fn with_intervals(mut self, mut intervals: Vec<IntervalData>) -> RaceProgress {
+        intervals.sort_by_key(|interval| (interval.driver_number, interval.date));
+        self.intervals = intervals;
+        self
+    }
+
+    pub fn has_intervals(&self) -> bool {
+        !self.intervals.is_empty()
+    }
+
+    /// The progress with the tyre stints.
+    pub fn with_stints(mut self, mut stints: Vec<StintData>) -> RaceProgress {
+        stints.sort_by_key(|stint| (stint.driver_number, stint.stint_number));
+        self.stints = stints;
+        self
+    }
+
+    pub fn has_stints(&self) -> bool {
+        !self.stints.is_empty()
+    }
+
+    /// The progress with the race control messages, which carry the flags.
+    pub fn with_race_control(mut self, mut messages: Vec<RaceControlData>) -> RaceProgress {
+        messages.sort_by_key(|message| message.date);
+        self.race_control = messages;
+        self
+    }
+
+    /// The race control messages by date.
+    pub fn race_control(&self) -> &[RaceControlData] {
+        &self.race_control
+    }
+
+    pub fn has_laps(&self) -> bool {
+        !self.laps.is_empty()
+    }
+
+    pub fn has_positions(&self) -> bool {
+        !self.positions.is_empty()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.laps.is_empty() && self.positions.is_empty()
+    }
+
+    /// Every driver with laps or positions, by number.
+    pub fn drivers(&self) -> Vec<u32> {
+        let mut drivers: Vec<u32> = self
+            .laps
+            .iter()
+            .map(|lap| lap.driver_number)
+            .chain(self.positions.iter().map(|position| position.driver_number))
+            .collect();
+        drivers.sort();
+        drivers.dedup();
+        drivers
+    }
+
+    /// The driver's laps by lap number.
+    pub fn driver_laps(&self, driver_number: u32) -> &[LapData] {
+        let start = self
+            .laps
+            .partition_point(|lap| lap.driver_number < driver_number);
+        let end = self
+            .laps
+            .partition_point(|lap| lap.driver_number <= driver_number);
+        &self.laps[start..end]
+    }
+
+    /// The driver's tyre stints by stint number.
+    pub fn driver_stints(&self, driver_number: u32) -> &[StintData] {
+        let start = self
+            .stints
+            .partition_point(|stint| stint.driver_number < driver_number);
+        let end = self
+            .stints
+            .partition_point(|stint| stint.driver_number <= driver_number);
+        &self.stints[start..end]
+    }
+
+    /// The driver's position changes by date.
+    pub fn driver_positions(&self, driver_number: u32) -> &[PositionData] {
+        let start = self
+            .positions
+            .partition_point(|position| position.driver_number < driver_number);
+        let end = self
+            .positions
+            .partition_point(|position| position.driver_number <= driver_number);
+        &self.positions[start..end]
+    }
+
+    /// When the driver started `lap_number`, if it's known.
+    pub fn lap_start(&self, driver_number: u32, lap_number: u32) -> Option<DateTime<Utc>> {
+        self.driver_laps(driver_number)
+            .iter()
+            .find(|lap| lap.lap_number == lap_number)?
+            .date_start
+    }
+
+    /// When the driver completed `lap_number`: the start of their next lap, or else the start
+    /// of the lap plus its duration. `None` for a lap they didn't complete.
+    pub fn lap_end(&self, driver_number: u32, lap_number: u32) -> Option<DateTime<Utc>> {
+        let laps = self.driver_laps(driver_number);
+        let index = laps
+            .binary_search_by_key(&lap_number, |lap| lap.lap_number)
+            .ok()?;
+        let next_start = laps
+            .get(index + 1)
+            .filter(|next| next.lap_number == lap_number + 1)
+            .and_then(|next| next.date_start);
+        next_start.or_else(|| {
+            let lap = &laps[index];
+            let duration =
+                ChronoDuration::milliseconds((lap.lap_duration? * 1000.0).round() as i64);
+            Some(lap.date_start? + duration)
+        })
+    }
+
+    /// Number of laps the driver had completed at `time`.
+    pub fn laps_completed(&self, driver_number: u32, time: DateTime<Utc>) -> u32 {
+        self.driver_laps(driver_number)
+            .iter()
+            .take_while(|lap| {
+                self.lap_end(driver_number, lap.lap_number)
+                    .is_some_and(|end| end <= time)
+            })
+            .last()
+            .map_or(0, |lap| lap.lap_number)
+    }
+
+    /// Laps the driver had covered at `time`: the completed ones plus the share of the current
+    /// lap's duration that has passed, or of the previous lap's while it isn't known. `None`
+    /// until the start of one of their laps is known.
+    pub fn laps_covered(&self, driver_number: u32, time: DateTime<Utc>) -> Option<f64> {
+        let laps = self.driver_laps(driver_number);
+        let index = laps
+            .iter()
+            .rposition(|lap| lap.date_start.is_some_and(|start| start <= time))?;
+        let lap = &laps[index];
+        let elapsed = (time - lap.date_start?).num_milliseconds() as f64 / 1000.0;
+        let duration = lap.lap_duration.or_else(|| {
+            index
+                .checked_sub(1)
+                .and_then(|previous| laps[previous].lap_duration)
+        });
+        let share = match duration {
+            Some(duration) if duration > 0.0 => (elapsed / duration).min(1.0),
+            _ => 0.0,
+        };
+        Some(lap.lap_number.saturating_sub(1) as f64 + share)
+    }
+
+    /// The driver's race position at `time`, once one is known.
+    pub fn position_at(&self, driver_number: u32, time: DateTime<Utc>) -> Option<u32> {
+        let positions = self.driver_positions(driver_number);
+        let end = positions.partition_point(|position| position.date <= time);
+        end.checked_sub(1).map(|index| positions[index].position)
+    }
+
+    /// Number of position changes of any driver at or before `time`; it only grows when the
+    /// order may have changed.
+    pub fn position_changes_until(&self, time: DateTime<Utc>) -> usize {
+        self.drivers()
+            .into_iter()
+            .map(|driver_number| {
+                self.driver_positions(driver_number)
+                    .partition_point(|position| position.date <= time)
+            })
+            .sum()
+    }
+
+    /// Number of intervals of any driver at or before `time`; it only grows when a gap may have
+    /// changed.
+    pub fn intervals_until(&self, time: DateTime<Utc>) -> usize {
+        let mut count = 0;
+        let mut rest = &self.intervals[..];
+        while let Some(first) = rest.first() {
+            let driver_number = first.driver_number;
+            let driver_end =
+                rest.partition_point(|interval| interval.driver_number == driver_number);
+            count += rest[..driver_end].partition_point(|interval| interval.date <= time);
+            rest = &rest[driver_end..];
+        }
+        count
+    }
+
+    /// The driver's last known gap to the leader at `time`; `None` while they're lapped.
+    pub fn gap_to_leader(&self, driver_number: u32, time: DateTime<Utc>) -> Option<f64> {
+        let start = self
+            .intervals
+            .partition_point(|interval| interval.driver_number < driver_number);
+        let intervals = &self.intervals[start..];
+        let end = intervals.partition_point(|interval| {
+            interval.driver_number == driver_number && interval.date <= time
+        });
+        intervals[..end].last()?.gap_to_leader
+    }
+
+    /// The tyre compound the driver was on at `time`: that of the stint covering the lap they
+    /// were on, or of the last stint started before it while the laps of the stints are
+    /// incomplete.
+    pub fn compound_at(&self, driver_number: u32, time: DateTime<Utc>) -> Option<&str> {
+        let lap = self.laps_completed(driver_number, time) + 1;
+        let stints = self.driver_stints(driver_number);
+        stints
+            .iter()
+            .find(|stint| {
+                stint.lap_start.is_some_and(|first| first <= lap)
+                    && stint.lap_end.is_none_or(|last| lap <= last)
+            })
+            .or_else(|| {
+                stints
+                    .iter()
+                    .rev()
+                    .find(|stint| stint.lap_start.is_some_and(|first| first <= lap))
+            })?
+            .compound
+            .as_deref()
+    }
+
+    /// The driver's first known position, which is their grid slot.
+    pub fn starting_position(&self, driver_number: u32) -> Option<u32> {
+        self.driver_positions(driver_number)
+            .first()
+            .map(|position| position.position)
+    }
+}
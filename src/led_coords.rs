@@ -0,0 +1,134 @@
+use crate::error::AppError;
+use crate::space::LedPoint;
+use crate::viewport::centroid;
+
+/// Board orientation: the rotation is applied first, then the flips, all around the layout
+/// centroid.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LayoutTransform {
+    pub rotation: f64, // Clockwise, in degrees
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+}
+
+impl LayoutTransform {
+    pub fn apply(&self, point: LedPoint, center: LedPoint) -> LedPoint {
+        let (dx, dy) = (point.x - center.x, point.y - center.y);
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let (dx, dy) = (dx * cos + dy * sin, dy * cos - dx * sin);
+        let dx = if self.flip_horizontal { -dx } else { dx };
+        let dy = if self.flip_vertical { -dy } else { dy };
+        LedPoint::new(center.x + dx, center.y + dy)
+    }
+
+    pub fn transform_coordinates(&self, coordinates: &[LedPoint]) -> Vec<LedPoint> {
+        let center = centroid(coordinates);
+        coordinates
+            .iter()
+            .map(|&coord| self.apply(coord, center))
+            .collect()
+    }
+}
+
+/// The Zandvoort layout; the comment on each entry is the LED's label on the board.
+#[rustfmt::skip]
+pub fn read_coordinates() -> Result<Vec<LedPoint>, AppError> {
+    Ok(vec![
+        LedPoint { x: 6413.0, y: 33.0 }, // U1
+        LedPoint { x: 6007.0, y: 197.0 }, // U2
+        LedPoint { x: 5652.0, y: 444.0 }, // U3
+        LedPoint { x: 5431.0, y: 822.0 }, // U4
+        LedPoint { x: 5727.0, y: 1143.0 }, // U5
+        LedPoint { x: 6141.0, y: 1268.0 }, // U6
+        LedPoint { x: 6567.0, y: 1355.0 }, // U7
+        LedPoint { x: 6975.0, y: 1482.0 }, // U8
+        LedPoint { x: 7328.0, y: 1738.0 }, // U9
+        LedPoint { x: 7369.0, y: 2173.0 }, // U10
+        LedPoint { x: 7024.0, y: 2448.0 }, // U11
+        LedPoint { x: 6592.0, y: 2505.0 }, // U12
+        LedPoint { x: 6159.0, y: 2530.0 }, // U13
+        LedPoint { x: 5725.0, y: 2525.0 }, // U14
+        LedPoint { x: 5288.0, y: 2489.0 }, // U15
+        LedPoint { x: 4857.0, y: 2434.0 }, // U16
+        LedPoint { x: 4429.0, y: 2356.0 }, // U17
+        LedPoint { x: 4004.0, y: 2249.0 }, // U18
+        LedPoint { x: 3592.0, y: 2122.0 }, // U19
+        LedPoint { x: 3181.0, y: 1977.0 }, // U20
+        LedPoint { x: 2779.0, y: 1812.0 }, // U21
+        LedPoint { x: 2387.0, y: 1624.0 }, // U22
+        LedPoint { x: 1988.0, y: 1453.0 }, // U23
+        LedPoint { x: 1703.0, y: 1779.0 }, // U24
+        LedPoint { x: 1271.0, y: 1738.0 }, // U25
+        LedPoint { x: 1189.0, y: 1314.0 }, // U26
+        LedPoint { x: 1257.0, y: 884.0 }, // U27
+        LedPoint { x: 1333.0, y: 454.0 }, // U28
+        LedPoint { x: 1409.0, y: 25.0 }, // U29
+        LedPoint { x: 1485.0, y: -405.0 }, // U30
+        LedPoint { x: 1558.0, y: -835.0 }, // U31
+        LedPoint { x: 1537.0, y: -1267.0 }, // U32
+        LedPoint { x: 1208.0, y: -1555.0 }, // U33
+        LedPoint { x: 779.0, y: -1606.0 }, // U34
+        LedPoint { x: 344.0, y: -1604.0 }, // U35
+        LedPoint { x: -88.0, y: -1539.0 }, // U36
+        LedPoint { x: -482.0, y: -1346.0 }, // U37
+        LedPoint { x: -785.0, y: -1038.0 }, // U38
+        LedPoint { x: -966.0, y: -644.0 }, // U39
+        LedPoint { x: -1015.0, y: -206.0 }, // U40
+        LedPoint { x: -923.0, y: 231.0 }, // U41
+        LedPoint { x: -762.0, y: 650.0 }, // U42
+        LedPoint { x: -591.0, y: 1078.0 }, // U43
+        LedPoint { x: -423.0, y: 1497.0 }, // U44
+        LedPoint { x: -254.0, y: 1915.0 }, // U45
+        LedPoint { x: -86.0, y: 2329.0 }, // U46
+        LedPoint { x: 83.0, y: 2744.0 }, // U47
+        LedPoint { x: 251.0, y: 3158.0 }, // U48
+        LedPoint { x: 416.0, y: 3574.0 }, // U49
+        LedPoint { x: 588.0, y: 3990.0 }, // U50
+        LedPoint { x: 755.0, y: 4396.0 }, // U51
+        LedPoint { x: 920.0, y: 4804.0 }, // U52
+        LedPoint { x: 1086.0, y: 5212.0 }, // U53
+        LedPoint { x: 1250.0, y: 5615.0 }, // U54
+        LedPoint { x: 1418.0, y: 6017.0 }, // U55
+        LedPoint { x: 1583.0, y: 6419.0 }, // U56
+        LedPoint { x: 1909.0, y: 6702.0 }, // U57
+        LedPoint { x: 2306.0, y: 6512.0 }, // U58
+        LedPoint { x: 2319.0, y: 6071.0 }, // U59
+        LedPoint { x: 2152.0, y: 5660.0 }, // U60
+        LedPoint { x: 1988.0, y: 5255.0 }, // U61
+        LedPoint { x: 1853.0, y: 4836.0 }, // U62
+        LedPoint { x: 1784.0, y: 4407.0 }, // U63
+        LedPoint { x: 1779.0, y: 3971.0 }, // U64
+        LedPoint { x: 1605.0, y: 3569.0 }, // U65
+        LedPoint { x: 1211.0, y: 3375.0 }, // U66
+        LedPoint { x: 811.0, y: 3188.0 }, // U67
+        LedPoint { x: 710.0, y: 2755.0 }, // U68
+        LedPoint { x: 1116.0, y: 2595.0 }, // U69
+        LedPoint { x: 1529.0, y: 2717.0 }, // U70
+        LedPoint { x: 1947.0, y: 2848.0 }, // U71
+        LedPoint { x: 2371.0, y: 2946.0 }, // U72
+        LedPoint { x: 2806.0, y: 2989.0 }, // U73
+        LedPoint { x: 3239.0, y: 2946.0 }, // U74
+        LedPoint { x: 3665.0, y: 2864.0 }, // U75
+        LedPoint { x: 4092.0, y: 2791.0 }, // U76
+        LedPoint { x: 4523.0, y: 2772.0 }, // U77
+        LedPoint { x: 4945.0, y: 2886.0 }, // U78
+        LedPoint { x: 5331.0, y: 3087.0 }, // U79
+        LedPoint { x: 5703.0, y: 3315.0 }, // U80
+        LedPoint { x: 6105.0, y: 3484.0 }, // U81
+        LedPoint { x: 6538.0, y: 3545.0 }, // U82
+        LedPoint { x: 6969.0, y: 3536.0 }, // U83
+        LedPoint { x: 7402.0, y: 3511.0 }, // U84
+        LedPoint { x: 7831.0, y: 3476.0 }, // U85
+        LedPoint { x: 8241.0, y: 3335.0 }, // U86
+        LedPoint { x: 8549.0, y: 3025.0 }, // U87
+        LedPoint { x: 8703.0, y: 2612.0 }, // U88
+        LedPoint { x: 8662.0, y: 2173.0 }, // U89
+        LedPoint { x: 8451.0, y: 1785.0 }, // U90
+        LedPoint { x: 8203.0, y: 1426.0 }, // U91
+        LedPoint { x: 7973.0, y: 1053.0 }, // U92
+        LedPoint { x: 7777.0, y: 664.0 }, // U93
+        LedPoint { x: 7581.0, y: 275.0 }, // U94
+        LedPoint { x: 7274.0, y: -35.0 }, // U95
+        LedPoint { x: 6839.0, y: -46.0 }, // U96
+    ])
+}
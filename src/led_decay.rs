@@ -0,0 +1,81 @@
+use crate::simulation::Rgb;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// LEDs fading out after the driver on them moves on, instead of going off at once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LedDecayConfig {
+    pub enabled: bool,
+    pub fade_ms: u64, // Race time the fade takes, 100 to 500
+}
+
+impl Default for LedDecayConfig {
+    fn default() -> Self {
+        LedDecayConfig {
+            enabled: false,
+            fade_ms: 300,
+        }
+    }
+}
+
+impl LedDecayConfig {
+    /// The fade in seconds, within the bounds it's allowed.
+    pub fn fade_secs(&self) -> f64 {
+        self.fade_ms.clamp(100, 500) as f64 / 1000.0
+    }
+}
+
+/// The LEDs drivers left, each fading from the color it had to off unless a driver comes
+/// back onto it.
+#[derive(Debug, Clone, Default)]
+pub struct LedDecay {
+    fade_secs: Option<f64>,              // None while off
+    occupied: Vec<Option<Rgb>>,          // The frame before, by LED
+    vacated: HashMap<usize, (Rgb, f64)>, // The color and race time an LED was left at
+}
+
+impl LedDecay {
+    pub fn new(config: &LedDecayConfig) -> LedDecay {
+        LedDecay {
+            fade_secs: config.enabled.then(|| config.fade_secs()),
+            ..LedDecay::default()
+        }
+    }
+
+    /// Whether an LED is still fading, so frames change without records being played.
+    pub fn is_active(&self) -> bool {
+        !self.vacated.is_empty()
+    }
+
+    /// Forgets the LEDs lit and fading, e.g. after a jump the fades wouldn't make sense across.
+    pub fn reset(&mut self) {
+        self.occupied.clear();
+        self.vacated.clear();
+    }
+
+    /// Takes the lit LEDs of a frame at `race_time` as occupied and lights the ones left
+    /// before, dimmed by how far they've faded.
+    pub fn apply(&mut self, leds: &mut [Option<Rgb>], race_time: f64) {
+        let Some(fade_secs) = self.fade_secs else {
+            return;
+        };
+        for (index, (before, now)) in self.occupied.iter().zip(leds.iter()).enumerate() {
+            if let (Some(color), None) = (before, now) {
+                self.vacated.insert(index, (*color, race_time));
+            }
+        }
+        self.vacated.retain(|&index, &mut (_, since)| {
+            let age = race_time - since;
+            leds.get(index).is_some_and(Option::is_none) && (0.0..fade_secs).contains(&age)
+        });
+        self.occupied.clear();
+        self.occupied.extend_from_slice(leds);
+        for (&index, &(color, since)) in &self.vacated {
+            let level = 1.0 - (race_time - since) / fade_secs;
+            if let Some(led) = leds.get_mut(index) {
+                *led = Some(color.map(|channel| (channel as f64 * level).round() as u8));
+            }
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+/// LEDs a car passes over between two frames lighting up behind it, so at high speeds it
+/// doesn't look like it skips parts of the track.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LedDwellConfig {
+    pub enabled: bool,
+    pub min_ms: u64,     // Wall time each passed LED stays lit, whatever the speed
+    pub max_skip: usize, // Longer jumps, like through the pit lane, aren't filled in
+}
+
+impl Default for LedDwellConfig {
+    fn default() -> Self {
+        LedDwellConfig {
+            enabled: false,
+            min_ms: 40,
+            max_skip: 20,
+        }
+    }
+}
+
+/// The LEDs each driver passed over and until when, in wall time, each stays lit. The LEDs
+/// of a skip go off in track order, the one furthest behind the car first.
+#[derive(Debug, Clone, Default)]
+pub struct LedDwell {
+    config: Option<LedDwellConfig>, // None while off
+    wall_time: Duration,
+    step: Duration, // Wall time of the last step
+    queues: HashMap<u32, VecDeque<(usize, Duration)>>,
+}
+
+impl LedDwell {
+    pub fn new(config: &LedDwellConfig) -> LedDwell {
+        LedDwell {
+            config: config.enabled.then(|| config.clone()),
+            ..LedDwell::default()
+        }
+    }
+
+    /// Whether an LED is lit for a passed car, so frames change without records being played.
+    pub fn is_active(&self) -> bool {
+        !self.queues.is_empty()
+    }
+
+    /// Forgets every passed LED, e.g. after a jump or going backwards, which nobody drove.
+    pub fn reset(&mut self) {
+        self.queues.clear();
+    }
+
+    /// Moves the wall clock on by a step and puts out the LEDs whose time is up.
+    pub fn advance(&mut self, dt: Duration) {
+        self.wall_time += dt;
+        self.step = dt;
+        let now = self.wall_time;
+        self.queues.retain(|_, queue| {
+            queue.retain(|&(_, until)| until > now);
+            !queue.is_empty()
+        });
+    }
+
+    /// A driver moved from LED `from` to `to` of `led_count` LEDs in driving order. Moving
+    /// forwards past LEDs, by way of the line too, lights them; moving backwards or further
+    /// than `max_skip` forgets the driver's.
+    pub fn moved(&mut self, driver_number: u32, from: usize, to: usize, led_count: usize) {
+        let Some(config) = &self.config else {
+            return;
+        };
+        if led_count == 0 || from == to {
+            return;
+        }
+        let ahead = (to + led_count - from) % led_count;
+        if ahead > led_count / 2 || ahead > config.max_skip + 1 {
+            self.queues.remove(&driver_number);
+            return;
+        }
+        let min = Duration::from_millis(config.min_ms);
+        let queue = self.queues.entry(driver_number).or_default();
+        for passed in 1..ahead {
+            // Passed in order during the last step, so each goes off that much later
+            let until = self.wall_time + min + self.step.mul_f64(passed as f64 / ahead as f64);
+            queue.push_back(((from + passed) % led_count, until));
+        }
+        if queue.is_empty() {
+            self.queues.remove(&driver_number);
+        }
+    }
+
+    /// The lit LEDs with the driver each is lit for.
+    pub fn leds(&self) -> impl Iterator<Item = (u32, usize)> + '_ {
+        self.queues.iter().flat_map(|(&driver_number, queue)| {
+            queue
+                .iter()
+                .map(move |&(led_index, _)| (driver_number, led_index))
+        })
+    }
+}
@@ -0,0 +1,203 @@
+use crate::error::AppError;
+use crate::simulation::Rgb;
+use crate::space::LedPoint;
+use eframe::egui;
+use log::warn;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Optional tables of broken LEDs, checked in this order.
+pub const MASK_FILES: [&str; 2] = ["led_mask.csv", "led_mask.json"];
+
+#[derive(Debug, Deserialize)]
+struct MaskEntry {
+    led: String,   // LED label as used in the layout, e.g. "U12"
+    fault: String, // "dead", or the broken channels, e.g. "b" for a stuck blue
+}
+
+/// What's wrong with a physical LED.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LedFault {
+    /// Doesn't light at all; what it would show goes to the nearest healthy LED.
+    Dead,
+    /// Red, green and blue, true for each channel that's broken and kept off.
+    Channels([bool; 3]),
+}
+
+impl std::str::FromStr for LedFault {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<LedFault, String> {
+        let value = value.trim().to_ascii_lowercase();
+        if value == "dead" {
+            return Ok(LedFault::Dead);
+        }
+        let mut channels = [false; 3];
+        for channel in value.chars() {
+            match channel {
+                'r' => channels[0] = true,
+                'g' => channels[1] = true,
+                'b' => channels[2] = true,
+                _ => {
+                    return Err(format!(
+                        "{:?} is neither \"dead\" nor channels like \"rb\"",
+                        value
+                    ))
+                }
+            }
+        }
+        match channels {
+            [false, false, false] => Err("no broken channel given".to_string()),
+            [true, true, true] => Ok(LedFault::Dead),
+            _ => Ok(LedFault::Channels(channels)),
+        }
+    }
+}
+
+/// The broken LEDs of a board, with the healthy LED each dead one hands its color to.
+#[derive(Debug, Clone, Default)]
+pub struct LedMask {
+    faults: Vec<Option<LedFault>>, // Per LED
+    reroute: Vec<usize>,           // Per LED: the LED showing its color, itself unless dead
+}
+
+impl LedMask {
+    /// Works out the nearest healthy neighbor of every dead LED in `coordinates`. A board
+    /// without a single healthy LED has nowhere to send anything, so its colors stay put.
+    pub fn new(coordinates: &[LedPoint], faults: Vec<Option<LedFault>>) -> LedMask {
+        let dead = |index: usize| faults.get(index) == Some(&Some(LedFault::Dead));
+        let reroute = (0..coordinates.len())
+            .map(|index| {
+                if !dead(index) {
+                    return index;
+                }
+                let here = &coordinates[index];
+                (0..coordinates.len())
+                    .filter(|&other| !dead(other))
+                    .min_by(|&a, &b| {
+                        let distance = |other: LedPoint| other.distance(*here);
+                        distance(coordinates[a]).total_cmp(&distance(coordinates[b]))
+                    })
+                    .unwrap_or(index)
+            })
+            .collect();
+        LedMask { faults, reroute }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.faults.iter().all(Option::is_none)
+    }
+
+    pub fn fault(&self, index: usize) -> Option<LedFault> {
+        self.faults.get(index).copied().flatten()
+    }
+
+    /// The LED showing the color of LED `index`.
+    pub fn rerouted(&self, index: usize) -> usize {
+        self.reroute.get(index).copied().unwrap_or(index)
+    }
+
+    /// Adjusts a frame for the broken LEDs, where black is off: a dead LED's color moves to
+    /// its neighbor unless that's lit already, and broken channels are switched off.
+    pub fn apply(&self, leds: &mut [Rgb]) {
+        for index in 0..leds.len() {
+            match self.fault(index) {
+                Some(LedFault::Dead) => {
+                    let color = std::mem::take(&mut leds[index]);
+                    let target = self.rerouted(index);
+                    if let Some(neighbor) = leds.get_mut(target) {
+                        if *neighbor == [0, 0, 0] && target != index {
+                            *neighbor = color;
+                        }
+                    }
+                }
+                Some(LedFault::Channels(broken)) => {
+                    for (channel, broken) in leds[index].iter_mut().zip(broken) {
+                        if broken {
+                            *channel = 0;
+                        }
+                    }
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+/// Reads a CSV or JSON table of broken LEDs for a layout; LEDs without an entry are healthy.
+pub fn read_mask(path: &Path, coordinates: &[LedPoint]) -> Result<LedMask, AppError> {
+    let decode_error = |err: &dyn std::fmt::Display| AppError::Decode {
+        context: format!("LED mask file {}: {}", path.display(), err),
+    };
+    let entries: Vec<MaskEntry> = if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_reader(std::fs::File::open(path)?).map_err(|err| decode_error(&err))?
+    } else {
+        csv::Reader::from_path(path)
+            .map_err(|err| decode_error(&err))?
+            .deserialize()
+            .collect::<Result<_, _>>()
+            .map_err(|err| decode_error(&err))?
+    };
+
+    let mut faults = vec![None; coordinates.len()];
+    for entry in entries {
+        let index = match entry
+            .led
+            .trim()
+            .strip_prefix('U')
+            .and_then(|number| number.parse::<usize>().ok())
+        {
+            Some(number) if (1..=coordinates.len()).contains(&number) => number - 1,
+            _ => {
+                warn!("Ignoring the mask of unknown LED {:?}", entry.led);
+                continue;
+            }
+        };
+        faults[index] = Some(
+            entry
+                .fault
+                .parse()
+                .map_err(|err: String| decode_error(&format!("LED {}: {}", entry.led, err)))?,
+        );
+    }
+
+    Ok(LedMask::new(coordinates, faults))
+}
+
+/// Cross-hatching over the masked LEDs at `positions`, the top left corner of each: grey for
+/// dead LEDs, and in the color of the broken channels for the others.
+pub fn hatch_shapes(mask: &LedMask, positions: &[egui::Pos2], led_size: f32) -> Vec<egui::Shape> {
+    let mut shapes = Vec::new();
+    for (index, &corner) in positions.iter().enumerate() {
+        let color = match mask.fault(index) {
+            None => continue,
+            Some(LedFault::Dead) => egui::Color32::GRAY,
+            Some(LedFault::Channels([r, g, b])) => {
+                let on = |broken: bool| if broken { 255 } else { 0 };
+                egui::Color32::from_rgb(on(r), on(g), on(b))
+            }
+        };
+        let stroke = egui::Stroke::new(1.0, color);
+        let point = |x: f32, y: f32| corner + egui::vec2(x, y);
+        // Lines across the square at a quarter, half and three quarters of each diagonal
+        for step in 1..=3 {
+            let c = led_size * step as f32 / 2.0; // x + y = c
+            let d = c - led_size; // x - y = d
+            shapes.push(egui::Shape::line_segment(
+                [
+                    point((c - led_size).max(0.0), c.min(led_size)),
+                    point(c.min(led_size), (c - led_size).max(0.0)),
+                ],
+                stroke,
+            ));
+            shapes.push(egui::Shape::line_segment(
+                [
+                    point(d.max(0.0), (-d).max(0.0)),
+                    point((led_size + d).min(led_size), (led_size - d).min(led_size)),
+                ],
+                stroke,
+            ));
+        }
+    }
+    shapes
+}
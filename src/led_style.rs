@@ -0,0 +1,140 @@
+use crate::simulation::Rgb;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+// Strength of the halos around a lit LED, innermost first
+const HALO_ALPHAS: [f32; 3] = [0.35, 0.2, 0.1];
+// Unlit LEDs in the glow style, as a share of the LED size
+const DOT_SHARE: f32 = 0.2;
+const DOT_COLOR: egui::Color32 = egui::Color32::from_gray(40);
+// Grey of unlit LEDs shaded by elevation, from the lowest to the highest
+const SHADE_LOW: f32 = 15.0;
+const SHADE_HIGH: f32 = 90.0;
+// How much the halos of the highest LEDs reach further than those of the lowest
+const SHADE_GLOW: f32 = 0.3;
+
+/// Spread of the LEDs' elevations, in the units of the locations, below which a session is
+/// taken as flat.
+pub const MIN_ELEVATION_RANGE: f64 = 1.0;
+
+/// How the window draws the LEDs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LedStyle {
+    /// Squares, black when off.
+    #[default]
+    Squares,
+    /// Round LEDs with a halo when lit and small dim dots when off.
+    Glow,
+}
+
+/// The shapes for LEDs at `positions`, the top left corner of each, with their `colors`;
+/// black is off and LEDs without a color are too. In the glow style the halos come first so
+/// the cores are drawn over every halo, and the halos add up where they overlap.
+/// `glow_radius` is how far the outermost halo reaches beyond the LED, in LED sizes. LEDs
+/// whose shapes would fall entirely outside `clip` are left out. `shades`, each LED's height
+/// from 0 to 1 as `elevation_shades` has them, light unlit LEDs the higher they are and
+/// stretch the halos slightly; LEDs past their end aren't shaded.
+pub fn led_shapes(
+    style: LedStyle,
+    positions: &[egui::Pos2],
+    colors: &[Rgb],
+    shades: &[f32],
+    led_size: f32,
+    glow_radius: f32,
+    clip: egui::Rect,
+) -> Vec<egui::Shape> {
+    let visible = |position: egui::Pos2, reach: f32| {
+        clip.intersects(
+            egui::Rect::from_min_size(position, egui::vec2(led_size, led_size)).expand(reach),
+        )
+    };
+    let color_of = |index: usize| {
+        colors
+            .get(index)
+            .map_or(egui::Color32::BLACK, |&[r, g, b]| {
+                egui::Color32::from_rgb(r, g, b)
+            })
+    };
+    let unlit_color = |index: usize, unshaded: egui::Color32| {
+        shades.get(index).map_or(unshaded, |&shade| {
+            egui::Color32::from_gray((SHADE_LOW + (SHADE_HIGH - SHADE_LOW) * shade) as u8)
+        })
+    };
+    match style {
+        LedStyle::Squares => positions
+            .iter()
+            .enumerate()
+            .filter(|&(_, &position)| visible(position, 0.0))
+            .map(|(index, &position)| {
+                let rect = egui::Rect::from_min_size(position, egui::vec2(led_size, led_size));
+                let mut color = color_of(index);
+                if color == egui::Color32::BLACK {
+                    color = unlit_color(index, color);
+                }
+                egui::Shape::rect_filled(rect, egui::Rounding::ZERO, color)
+            })
+            .collect(),
+        LedStyle::Glow => {
+            let core = led_size / 2.0;
+            let max_reach = glow_radius * (1.0 + SHADE_GLOW / 2.0) * led_size;
+            let lit: Vec<(egui::Pos2, egui::Color32, f32)> = positions
+                .iter()
+                .enumerate()
+                .filter(|&(_, &position)| visible(position, max_reach))
+                .map(|(index, &position)| {
+                    let scale = shades
+                        .get(index)
+                        .map_or(1.0, |&shade| 1.0 + SHADE_GLOW * (shade - 0.5));
+                    (position + egui::vec2(core, core), color_of(index), scale)
+                })
+                .filter(|&(_, color, _)| color != egui::Color32::BLACK)
+                .collect();
+
+            let mut shapes = Vec::with_capacity(positions.len() + lit.len() * 4);
+            for &(center, color, scale) in &lit {
+                for (ring, alpha) in HALO_ALPHAS.iter().enumerate() {
+                    let reach = (ring + 1) as f32 / HALO_ALPHAS.len() as f32;
+                    let radius = core + reach * glow_radius * scale * led_size;
+                    shapes.push(egui::Shape::circle_filled(
+                        center,
+                        radius,
+                        color.gamma_multiply(*alpha).additive(),
+                    ));
+                }
+            }
+            for (index, &position) in positions.iter().enumerate() {
+                if color_of(index) == egui::Color32::BLACK && visible(position, 0.0) {
+                    let center = position + egui::vec2(core, core);
+                    shapes.push(egui::Shape::circle_filled(
+                        center,
+                        led_size * DOT_SHARE,
+                        unlit_color(index, DOT_COLOR),
+                    ));
+                }
+            }
+            for &(center, color, _) in &lit {
+                shapes.push(egui::Shape::circle_filled(center, core, color));
+            }
+            shapes
+        }
+    }
+}
+
+/// Each LED's mean elevation from the mapping scaled from 0 at the lowest to 1 at the highest;
+/// LEDs no sample with an elevation was snapped to sit halfway. `None` when the session has no
+/// elevations or they spread less than `MIN_ELEVATION_RANGE`, rather than a uniform tint.
+pub fn elevation_shades(led_elevation: &[Option<f64>]) -> Option<Vec<f32>> {
+    let known = led_elevation.iter().flatten();
+    let low = known.clone().copied().reduce(f64::min)?;
+    let high = known.copied().reduce(f64::max)?;
+    if high - low < MIN_ELEVATION_RANGE {
+        return None;
+    }
+    Some(
+        led_elevation
+            .iter()
+            .map(|elevation| elevation.map_or(0.5, |z| ((z - low) / (high - low)) as f32))
+            .collect(),
+    )
+}
@@ -0,0 +1,103 @@
+// Without either there's nothing to fetch the API with
+#[cfg(not(any(feature = "native", feature = "web")))]
+compile_error!("build with the native feature, or with the web one for the browser");
+
+pub mod atomic_file;
+pub mod audio;
+pub mod battles;
+pub mod blue_flags;
+pub mod bundle;
+pub mod cache;
+pub mod calibration;
+pub mod camera;
+#[cfg(feature = "native")]
+pub mod cli;
+pub mod color;
+pub mod color_scheme;
+pub mod config;
+pub mod control;
+pub mod coverage;
+pub mod csv_export;
+pub mod data;
+pub mod dmx;
+pub mod driver_info;
+#[cfg(feature = "native")]
+pub mod enttec;
+pub mod error;
+pub mod export;
+pub mod flags;
+pub mod formation;
+pub mod ghost;
+pub mod heatmap;
+#[cfg(feature = "http-control")]
+pub mod http_control;
+pub mod input;
+pub mod lap_chart;
+pub mod lap_table;
+pub mod laps;
+pub mod led_coords;
+pub mod led_decay;
+pub mod led_dwell;
+pub mod led_mask;
+pub mod led_style;
+pub mod mapping;
+pub mod markers;
+pub mod matrix;
+pub mod metrics;
+#[cfg(feature = "native")]
+pub mod mqtt;
+pub mod night;
+pub mod notices;
+pub mod occupancy;
+pub mod osc;
+pub mod overtakes;
+#[cfg(feature = "native")]
+pub mod parquet_export;
+pub mod pedals;
+pub mod pixel_map;
+pub mod playback;
+pub mod playlist;
+pub mod position_deltas;
+#[cfg(feature = "native")]
+pub mod preflight;
+pub mod prefs;
+pub mod qualifying;
+pub mod race_events;
+pub mod recorder;
+pub mod render;
+pub mod retirements;
+pub mod rivals;
+pub mod schedule;
+pub mod sectors;
+pub mod segments;
+#[cfg(feature = "native")]
+pub mod session_data;
+pub mod settings;
+pub mod simulation;
+pub mod sink;
+pub mod snapshot;
+pub mod space;
+pub mod speed_plan;
+pub mod split;
+pub mod status;
+pub mod strategy;
+pub mod summary;
+pub mod sync;
+pub mod team_view;
+pub mod telemetry_chart;
+pub mod test_pattern;
+pub mod timeline;
+pub mod track_progress;
+pub mod trains;
+#[cfg(feature = "native")]
+pub mod tui;
+pub mod viewport;
+pub mod watchdog;
+#[cfg(feature = "web")]
+pub mod web;
+#[cfg(feature = "native")]
+pub mod websocket;
+#[cfg(feature = "native")]
+pub mod wled;
+#[cfg(feature = "ws2812")]
+pub mod ws2812;
@@ -1,574 +1,3895 @@
-use chrono::{DateTime, Utc};
+mod gui;
+
+use axum::Router;
+use chrono::{DateTime, Local, Utc};
 use eframe::{egui, App, Frame};
-use reqwest::Client;
-use serde::de::{self, Deserializer};
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::error::Error as StdError;
+use f1_led_circuit_master_simulation::audio::AudioPlayer;
+use f1_led_circuit_master_simulation::battles::BattleDetector;
+use f1_led_circuit_master_simulation::blue_flags::BlueFlagConfig;
+use f1_led_circuit_master_simulation::bundle::{Bundle, BUNDLE_EXTENSION};
+use f1_led_circuit_master_simulation::cache::mapping_cache_key;
+use f1_led_circuit_master_simulation::calibration::{
+    correct_frame, read_calibration, LedCalibration, CALIBRATION_FILES,
+};
+use f1_led_circuit_master_simulation::camera::{FollowCamera, MAX_ZOOM};
+use f1_led_circuit_master_simulation::cli::{CliArgs, Command, DataFormat};
+use f1_led_circuit_master_simulation::color_scheme::ColorSchemeKind;
+use f1_led_circuit_master_simulation::config::{
+    ApiConfig, CalibrationConfig, Config, SessionConfig, DEFAULT_CONFIG_FILE,
+};
+use f1_led_circuit_master_simulation::control::PlaybackCommand;
+use f1_led_circuit_master_simulation::csv_export::write_csv;
+use f1_led_circuit_master_simulation::data::{fetch_session, CarData, TimeWindow};
+use f1_led_circuit_master_simulation::dmx::DmxSink;
+use f1_led_circuit_master_simulation::driver_info::{
+    apply_color_overrides, color_overrides, driver_colors, driver_numbers, driver_teams,
+    legend_order, season_roster, DriverInfo, RosterConfig, DEFAULT_SEASON,
+};
+use f1_led_circuit_master_simulation::enttec::EnttecSink;
+use f1_led_circuit_master_simulation::error::AppError;
+use f1_led_circuit_master_simulation::export::{ExportFormat, ExportJob, ExportOptions};
+use f1_led_circuit_master_simulation::flags::{FlagPanelConfig, FlagPanels, FlagTimeline};
+use f1_led_circuit_master_simulation::formation::{FormationConfig, GridFormation};
+use f1_led_circuit_master_simulation::ghost::GhostRun;
+#[cfg(feature = "http-control")]
+use f1_led_circuit_master_simulation::http_control::ControlServer;
+#[cfg(feature = "gpio")]
+use f1_led_circuit_master_simulation::input::watch_buttons;
+#[cfg(feature = "gamepad")]
+use f1_led_circuit_master_simulation::input::Gamepad;
+use f1_led_circuit_master_simulation::lap_table::format_lap_time;
+use f1_led_circuit_master_simulation::laps::RaceProgress;
+use f1_led_circuit_master_simulation::led_coords::{read_coordinates, LayoutTransform};
+use f1_led_circuit_master_simulation::led_decay::LedDecayConfig;
+use f1_led_circuit_master_simulation::led_dwell::LedDwellConfig;
+use f1_led_circuit_master_simulation::led_mask::{hatch_shapes, read_mask, LedMask, MASK_FILES};
+use f1_led_circuit_master_simulation::led_style::{elevation_shades, led_shapes};
+use f1_led_circuit_master_simulation::mapping::{
+    layout_length, MappingOptions, MappingStats, RunRace,
+};
+use f1_led_circuit_master_simulation::markers::{event_markers, Marker, MarkerConfig};
+use f1_led_circuit_master_simulation::matrix::{LedGrid, MatrixSink};
+#[cfg(feature = "metrics")]
+use f1_led_circuit_master_simulation::metrics::{self, MetricsServer};
+use f1_led_circuit_master_simulation::mqtt::MqttPublisher;
+use f1_led_circuit_master_simulation::night::NightDimmer;
+use f1_led_circuit_master_simulation::notices;
+use f1_led_circuit_master_simulation::osc::OscSink;
+use f1_led_circuit_master_simulation::overtakes::{detect_overtakes, OvertakeConfig};
+use f1_led_circuit_master_simulation::parquet_export::ParquetJob;
+use f1_led_circuit_master_simulation::pedals::{
+    tint_trail, PedalConfig, PedalTrace, BRAKE_COLOR, THROTTLE_COLOR,
+};
+use f1_led_circuit_master_simulation::position_deltas::{format_position_delta, PositionDeltas};
+use f1_led_circuit_master_simulation::preflight::{
+    check_bundle, check_layout, check_session_data, CheckReport,
+};
+use f1_led_circuit_master_simulation::prefs::{LegendOrder, UiPrefs};
+use f1_led_circuit_master_simulation::qualifying::{
+    best_laps, garage_segments, overlay_best_laps, BestLap, QualifyingConfig, QualifyingMode,
+};
+use f1_led_circuit_master_simulation::race_events::RaceEvent;
+use f1_led_circuit_master_simulation::recorder::{
+    layout_hash, FrameRecorder, RecorderConfig, Recording,
+};
+use f1_led_circuit_master_simulation::render::format_duration;
+use f1_led_circuit_master_simulation::retirements::{RetirementConfig, Retirements};
+use f1_led_circuit_master_simulation::rivals::{GapSource, Rival, RivalTracker};
+use f1_led_circuit_master_simulation::schedule::{ScheduledStart, LATE_START_TOLERANCE};
+use f1_led_circuit_master_simulation::sectors::{SectorConfig, SectorTimes};
+use f1_led_circuit_master_simulation::segments::{ChainSink, Layout};
+use f1_led_circuit_master_simulation::session_data::{
+    load_race_progress, load_session_data, prepare_car_data, prepare_race_data,
+    prepare_race_progress, prepare_session_roster, session_bundle, session_drivers,
+    session_season_roster, DataSource, LoadedSession,
+};
+use f1_led_circuit_master_simulation::settings::{
+    check_window, SessionForm, SessionFormErrors, WindowProblem,
+};
+use f1_led_circuit_master_simulation::simulation::{PlaybackState, Rgb, Simulation};
+use f1_led_circuit_master_simulation::sink::{
+    install_panic_blanking, FrameDispatcher, GuiSink, HardwareSink, LedSink, OutputFrame, SinkId,
+    SinkOptions, SHUTDOWN_TIMEOUT,
+};
+use f1_led_circuit_master_simulation::snapshot::{DatasetIds, SnapshotSettings, StateSnapshot};
+use f1_led_circuit_master_simulation::space::LedPoint;
+use f1_led_circuit_master_simulation::speed_plan::SpeedSegment;
+use f1_led_circuit_master_simulation::split::gang;
+use f1_led_circuit_master_simulation::status::{self};
+use f1_led_circuit_master_simulation::summary::{leader_laps, race_summary, SummaryLog};
+use f1_led_circuit_master_simulation::sync::{
+    data_hash, SyncFollower, SyncLeader, SyncMessage, SyncRole,
+};
+use f1_led_circuit_master_simulation::test_pattern::PatternPlayer;
+use f1_led_circuit_master_simulation::timeline::{estimated_laps, DriverDistances, SpeedConfig};
+use f1_led_circuit_master_simulation::trains::TrainDetector;
+use f1_led_circuit_master_simulation::tui::{self, KeyAction, TerminalSession, TrackView};
+use f1_led_circuit_master_simulation::viewport::{
+    centroid, segment_regions, split_panes, Bounds, TrackViewport,
+};
+use f1_led_circuit_master_simulation::watchdog::{DiagnosticDump, Watchdog, WatchdogConfig};
+use f1_led_circuit_master_simulation::websocket::WebSocketServer;
+use f1_led_circuit_master_simulation::wled::{WledSink, WledStatusHandle};
+#[cfg(feature = "ws2812")]
+use f1_led_circuit_master_simulation::ws2812::Ws2812Strip;
+use gui::exports::Exports;
+use gui::ghost::GhostCar;
+use gui::outputs::OutputSettings;
+use gui::playlist::SessionQueue;
+use gui::prefs::{apply_theme, DisplayPrefs};
+use gui::race_control::RaceControls;
+use gui::settings::SessionSettings;
+use gui::split::SplitScreen;
+use gui::telemetry::TelemetryCharts;
+use gui::timing::TimingTables;
+use log::{debug, error, info, trace, warn};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::net::SocketAddr;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::result::Result;
-use std::time::Instant;
-use tokio;
-
-#[derive(Debug, Serialize, Deserialize)]
-struct LocationData {
-    x: f64,
-    y: f64,
-    #[serde(deserialize_with = "deserialize_datetime")]
-    date: DateTime<Utc>,
-    driver_number: u32,
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+// Key of the persisted `UiPrefs` in eframe's storage
+const UI_PREFS_KEY: &str = "ui_prefs";
+
+// Repaint rates: fast enough to follow the data while playing, slow while waiting to start
+const PLAYING_REPAINT_INTERVAL: Duration = Duration::from_millis(33);
+const IDLE_REPAINT_INTERVAL: Duration = Duration::from_millis(500);
+// While stopped or paused with a gamepad, buttons or the control API to listen to
+const INPUT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+// While an export runs, to move its progress bar
+const EXPORT_REPAINT_INTERVAL: Duration = Duration::from_millis(100);
+// How often the export command logs how far it got
+const EXPORT_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+// How often closing the window checks whether the background jobs are done
+const JOB_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// Pulses per second of the outline around battling cars
+const BATTLE_PULSE_HZ: f64 = 1.5;
+
+// The glow along the LEDs a DRS train covers, as a share of white
+const TRAIN_GLOW: f32 = 0.12;
+
+// How far the track view zooms per point scrolled
+const ZOOM_PER_POINT: f64 = 0.005;
+
+// The legend switches to three-letter codes on its own when it's narrower than this
+const LEGEND_COMPACT_WIDTH: f32 = 140.0;
+
+// The throttle and brake bars in the legend
+const PEDAL_BAR_WIDTH: f32 = 10.0;
+const PEDAL_BAR_HEIGHT: f32 = 40.0;
+
+// How long a toast stays up, unless it's a warning
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+// Older toasts make room beyond this many
+const MAX_TOASTS: usize = 6;
+
+// The outputs `register_outputs` starts, by sink name
+const LED_OUTPUTS: [&str; 7] = [
+    "dmx",
+    "enttec",
+    "wled",
+    "mqtt",
+    "osc",
+    "websocket",
+    "ws2812",
+];
+
+// Where --tui logs to, as the terminal is taken
+const TUI_LOG_FILE: &str = "f1-led-circuit.log";
+// Wall time between two frames of the headless loop (30 Hz); the clock steps by `FIXED_STEP`
+const HEADLESS_TICK: Duration = Duration::from_micros(33_333);
+
+struct PlotApp {
+    coordinates: Vec<LedPoint>,
+    layout: Layout, // The segments of `coordinates` and their places on the chain
+    view_coordinates: Vec<LedPoint>, // `coordinates` as drawn, the view transform on the track
+    view_transform: LayoutTransform,
+    layout_rotations: BTreeMap<u64, f64>, // Rotation of each layout shown, by layout hash
+    time_offsets: BTreeMap<String, f64>,  // Time offset of each session shown, by session key
+    speed_segments: BTreeMap<String, Vec<SpeedSegment>>, // Speed plan of each session shown
+    hidden_drivers: BTreeMap<String, Vec<u32>>, // Unticked in the legend, by session key
+    bounds: Bounds,                       // Bounding box of the track in `view_coordinates`
+    segment_bounds: Vec<Bounds>,          // Of every other segment, in the layout's order
+    camera: FollowCamera,                 // Zoom and pan of the track view
+    simulation: Simulation,
+    last_update: Instant, // Wall clock of the previous frame, to advance the simulation
+    speed_range: RangeInclusive<f64>, // Range offered by the playback speed slider
+    driver_info: Vec<DriverInfo>,
+    legend_order: LegendOrder,
+    legend_rows: Vec<u32>, // Driver numbers in legend order
+    legend_sorted_for: Option<(LegendOrder, usize)>, // The order and position changes of `legend_rows`
+    legend_compact: bool,                            // Three-letter codes instead of full names
+    display: DisplayPrefs,
+    brightness: f32,                  // Global brightness applied to every LED
+    calibration: Vec<LedCalibration>, // Per-LED correction applied after brightness
+    led_mask: LedMask,                // Broken LEDs of the board, applied after calibration
+    mapping_stats: MappingStats,
+    show_diagnostics: bool,
+    watchdog: Watchdog,
+    watchdog_config: WatchdogConfig,              // Where dumps go
+    stall_warning: Option<String>, // What stalled and where its dump went, until dismissed
+    outputs: FrameDispatcher,      // Receives a frame per update, the window itself included
+    shown_frame: Arc<Mutex<Option<OutputFrame>>>, // Latest frame handed to the window's sink
+    recorder: RecorderConfig,
+    recording: Option<SinkId>, // The recorder sink while recording
+    controls: Controls,        // Commands from outside the window, applied on each update
+    audio: AudioPlayer,        // Plays the race events of each update
+    session_name: String,      // For screenshot file names
+    track_size: egui::Vec2,    // Size of the track view in points, for screenshots
+    toasts: Vec<Toast>,        // Oldest first
+    banner: Option<String>,    // Why there's no session to show, until another one loads
+    race_progress: Option<Arc<RaceProgress>>, // Laps and positions, when fetched
+    speed: SpeedConfig,
+    battles: BattleDetector,
+    trains: TrainDetector,
+    position_deltas: Option<PositionDeltas>, // Places gained since the start; none for best laps
+    focused_driver: Option<u32>, // Clicked in the legend; their nearest rivals are marked
+    rivals: RivalTracker,
+    data_source: Option<DataSource>, // Where sessions come from; none when playing a recording
+    charts: TelemetryCharts,
+    race_control: RaceControls,
+    output_settings: OutputSettings,
+    settings: SessionSettings,
+    exports: Exports,
+    queue: SessionQueue,
+    ghost: GhostCar,
+    split: SplitScreen,
+    timing: TimingTables,
+    distances: DriverDistances, // Of the session played, for the diagnostics
+    sectors: SectorConfig,
+    overtakes: OvertakeConfig,
+    flag_panels: FlagPanelConfig,
+    summary_log: Option<SummaryLog>, // The race summary once per lap, when configured
+    formation: FormationConfig,
+    retirements: RetirementConfig,
+    blue_flags: BlueFlagConfig,
+    led_decay: LedDecayConfig,
+    led_dwell: LedDwellConfig,
+    pedals: PedalConfig,
+    pedal_traces: BTreeMap<u32, PedalTrace>, // Car data matched to the records, by driver
+    car_data: BTreeMap<u32, Vec<CarData>>,   // As loaded with the traces, by driver
+    pedal_job: Option<(u32, JoinHandle<Result<CarDataLoad, AppError>>)>, // And whose it is
+    pedal_trail: Vec<Option<Rgb>>,           // The focused driver's trail, by LED
+    qualifying: QualifyingConfig,
+    best_laps: Vec<BestLap>, // Played instead of the session in the best laps mode, fastest first
+    marker_config: MarkerConfig,
+    markers: Vec<Marker>,   // On the timeline, of the session shown
+    session: SessionConfig, // What's playing, or loading
+    reload_job: Option<ReloadJob>,
+    color_overrides: BTreeMap<u32, egui::Color32>, // Driver colors from the config file
+    pause_when_unfocused: bool,
+    focus_resume_delay: Duration,
+    paused_for_focus: bool, // Paused by losing focus rather than by hand
+    focus_resume_at: Option<Instant>, // When playback goes on, since focus came back
 }
 
-#[derive(Debug, Deserialize)]
-struct LedCoordinate {
-    x_led: f64,
-    y_led: f64,
+// A session being fetched and mapped in the background
+struct ReloadJob {
+    handle: JoinHandle<Result<SessionLoad, AppError>>,
+    cancelled: Arc<AtomicBool>,
 }
 
-#[derive(Debug)]
-struct RunRace {
-    date: DateTime<Utc>,
-    driver_number: u32,
-    x_led: f64,
-    y_led: f64,
+// A driver's car data as fetched, and matched to their records
+struct CarDataLoad {
+    trace: PedalTrace,
+    samples: Vec<CarData>,
 }
 
-#[derive(Debug)]
-struct DriverInfo {
-    number: u32,
-    name: &'static str,
-    team: &'static str,
-    color: egui::Color32,
+// How loading a session in the background ended, short of an error
+enum SessionLoad {
+    Loaded(Box<LoadedSession>),
+    Cancelled,
+    BadWindow(WindowProblem), // Nothing was fetched
 }
 
-struct PlotApp {
-    coordinates: Vec<LedCoordinate>,
-    run_race_data: Vec<RunRace>,
-    start_time: Instant,
-    race_time: f64, // Elapsed race time in seconds
-    race_started: bool,
-    driver_info: Vec<DriverInfo>,
-    current_index: usize,
-    led_states: HashMap<(i64, i64), egui::Color32>, // Tracks the current state of the LEDs
-    last_positions: HashMap<u32, (i64, i64)>,       // Last known positions of each driver
-    speed: i32,                                     // Playback speed multiplier
+// How a toast is colored and whether it goes away by itself
+#[derive(Clone, Copy, PartialEq)]
+enum ToastLevel {
+    Info,
+    Warning, // Stays until it's dismissed
+    Error,
+}
+
+// A short message in the corner of the window, stacked above the ones shown before
+struct Toast {
+    text: String,
+    level: ToastLevel,
+    shown_at: Instant,
+}
+
+impl Toast {
+    fn new(text: String, level: ToastLevel) -> Toast {
+        Toast {
+            text,
+            level,
+            shown_at: Instant::now(),
+        }
+    }
+
+    fn info(text: String) -> Toast {
+        Toast::new(text, ToastLevel::Info)
+    }
+
+    fn warning(text: String) -> Toast {
+        Toast::new(text, ToastLevel::Warning)
+    }
+
+    fn error(text: String) -> Toast {
+        Toast::new(text, ToastLevel::Error)
+    }
+
+    // How long until it goes away by itself; zero once it should be gone
+    fn remaining(&self) -> Option<Duration> {
+        (self.level != ToastLevel::Warning)
+            .then(|| TOAST_DURATION.saturating_sub(self.shown_at.elapsed()))
+    }
 }
 
 impl PlotApp {
     fn new(
-        coordinates: Vec<LedCoordinate>,
-        run_race_data: Vec<RunRace>,
+        layout: Layout,
+        mut simulation: Simulation,
         driver_info: Vec<DriverInfo>,
+        calibration: Vec<LedCalibration>,
+        mapping_stats: MappingStats,
+        config: &Config,
+        prefs: Option<UiPrefs>,
     ) -> PlotApp {
+        // Settings from the config file or command line win over the remembered UI state
+        let prefs = prefs.unwrap_or_default();
+        let coordinates = layout.coordinates().to_vec();
+        if !config.is_set("playback.speed") {
+            simulation.set_speed(
+                prefs
+                    .speed
+                    .clamp(config.playback.min_speed, config.playback.max_speed),
+            );
+        }
+        let hidden_drivers = prefs.session_hidden_drivers;
+        for &driver_number in hidden_drivers
+            .get(&config.session.key)
+            .into_iter()
+            .flatten()
+        {
+            simulation.set_driver_hidden(driver_number, true);
+        }
+        let led_size = if config.is_set("display.led_size") {
+            config.display.led_size
+        } else {
+            prefs.led_size
+        };
+        let brightness = if config.is_set("display.brightness") {
+            config.display.brightness
+        } else {
+            prefs.brightness.clamp(0.0, 1.0)
+        };
+        let sector_times =
+            SectorTimes::new(simulation.timelines(), coordinates.len(), &config.sectors);
+        let distances = DriverDistances::new(simulation.timelines(), &config.speed);
+        let matrix_grid = LedGrid::new(&coordinates, &config.matrix).ok();
+        let delta_drivers = (
+            driver_info.first().map_or(0, |driver| driver.number),
+            driver_info.get(1).map_or(0, |driver| driver.number),
+        );
+        let view_transform = LayoutTransform {
+            rotation: prefs
+                .layout_rotations
+                .get(&layout_hash(&coordinates))
+                .copied()
+                .unwrap_or_default(),
+            ..LayoutTransform::default()
+        };
+        let view_coordinates = layout.view_coordinates(&view_transform);
+        let bounds = Bounds::from_coordinates(&view_coordinates[layout.track().leds.clone()]);
+        let segment_bounds = layout.segments()[1..]
+            .iter()
+            .map(|segment| Bounds::from_coordinates(&view_coordinates[segment.leds.clone()]))
+            .collect();
+        if let Some(&offset) = prefs.time_offsets.get(&config.session.key) {
+            simulation.set_time_offset(offset);
+        }
+        if let Some(segments) = prefs.speed_segments.get(&config.session.key) {
+            simulation.speed_plan_mut().set_segments(segments.clone());
+        }
+        simulation.speed_plan_mut().set_auto(prefs.auto_slow);
+        simulation.set_color_scheme(prefs.color_scheme);
+        simulation.set_led_view(prefs.led_view);
+        let mut audio = AudioPlayer::open(&config.audio);
+        if !config.is_set("audio.volume") {
+            audio.set_volume(prefs.volume);
+        }
+        if !config.is_set("audio.muted") {
+            audio.set_muted(prefs.muted);
+        }
+
         PlotApp {
+            bounds,
+            segment_bounds,
+            camera: FollowCamera::new(&bounds),
+            view_coordinates,
+            view_transform,
+            layout_rotations: prefs.layout_rotations,
+            time_offsets: prefs.time_offsets,
+            speed_segments: prefs.speed_segments,
+            hidden_drivers,
             coordinates,
-            run_race_data,
-            start_time: Instant::now(),
-            race_time: 0.0,
-            race_started: false,
+            layout,
+            simulation,
+            last_update: Instant::now(),
+            speed_range: config.playback.min_speed..=config.playback.max_speed,
             driver_info,
-            current_index: 0,
-            led_states: HashMap::new(), // Initialize empty LED state tracking
-            last_positions: HashMap::new(), // Initialize empty last positions hashmap
-            speed: 1,
+            legend_order: prefs.legend_order,
+            legend_rows: Vec::new(),
+            legend_sorted_for: None,
+            legend_compact: false,
+            display: DisplayPrefs {
+                theme: prefs.theme,
+                led_size,
+                led_style: config.display.style,
+                glow_radius: config.display.glow_radius,
+                elevation_shading: config.display.elevation_shading,
+                elevation_shades: elevation_shades(&mapping_stats.led_elevation),
+            },
+            brightness,
+            calibration,
+            led_mask: LedMask::default(),
+            mapping_stats,
+            show_diagnostics: false,
+            watchdog: Watchdog::new(&config.watchdog),
+            watchdog_config: config.watchdog.clone(),
+            stall_warning: None,
+            outputs: FrameDispatcher::new(),
+            shown_frame: Arc::new(Mutex::new(None)),
+            recorder: config.recorder.clone(),
+            recording: None,
+            controls: Controls::default(),
+            audio,
+            session_name: config.session.label(),
+            track_size: egui::Vec2::ZERO,
+            toasts: Vec::new(),
+            banner: None,
+            race_progress: None,
+            speed: config.speed.clone(),
+            battles: BattleDetector::new(&config.battles),
+            trains: TrainDetector::new(&config.trains),
+            position_deltas: None,
+            focused_driver: None,
+            rivals: RivalTracker::default(),
+            data_source: None,
+            charts: TelemetryCharts::new(delta_drivers),
+            race_control: RaceControls::new(config.playback.stop_confirmation),
+            output_settings: OutputSettings::new(config),
+            settings: SessionSettings::default(),
+            exports: Exports::new(config, led_size),
+            queue: SessionQueue::new(config),
+            ghost: GhostCar::new(config),
+            split: SplitScreen::new(config),
+            timing: TimingTables {
+                show_sectors: false,
+                sector_times,
+                sector_driver: delta_drivers.0,
+                show_laps: false,
+                lap_driver: delta_drivers.0,
+                show_strategy: false,
+                strategy_no_spoilers: false,
+                show_matrix: false,
+                matrix_grid,
+            },
+            distances,
+            sectors: config.sectors.clone(),
+            overtakes: config.overtakes.clone(),
+            flag_panels: config.flag_panels.clone(),
+            summary_log: config.summary.log.clone().map(SummaryLog::new),
+            formation: config.formation.clone(),
+            retirements: config.retirements.clone(),
+            blue_flags: config.blue_flags.clone(),
+            led_decay: config.led_decay.clone(),
+            led_dwell: config.led_dwell.clone(),
+            pedals: config.pedals.clone(),
+            pedal_traces: BTreeMap::new(),
+            car_data: BTreeMap::new(),
+            pedal_job: None,
+            pedal_trail: Vec::new(),
+            qualifying: config.qualifying.clone(),
+            best_laps: Vec::new(),
+            marker_config: config.markers.clone(),
+            markers: Vec::new(),
+            session: config.session.clone(),
+            reload_job: None,
+            color_overrides: BTreeMap::new(),
+            pause_when_unfocused: config.playback.pause_when_unfocused,
+            focus_resume_delay: Duration::from_secs_f64(
+                config.playback.focus_resume_delay_secs.max(0.0),
+            ),
+            paused_for_focus: false,
+            focus_resume_at: None,
         }
     }
 
-    fn reset(&mut self) {
-        self.start_time = Instant::now();
-        self.race_time = 0.0;
-        self.race_started = false;
-        self.current_index = 0;
-        self.led_states.clear(); // Reset LED states
-        self.last_positions.clear(); // Reset last positions
-    }
-
-    fn update_race(&mut self) {
-        if self.race_started {
-            let elapsed = self.start_time.elapsed().as_secs_f64();
-            self.race_time = elapsed * self.speed as f64;
-
-            let mut next_index = self.current_index;
-            while next_index < self.run_race_data.len() {
-                let run_data = &self.run_race_data[next_index];
-                let race_data_time =
-                    (run_data.date - self.run_race_data[0].date).num_milliseconds() as f64 / 1000.0;
-                if race_data_time <= self.race_time {
-                    next_index += 1;
-                } else {
-                    break;
-                }
+    fn is_off_track(&self, driver_number: u32) -> bool {
+        match (
+            self.mapping_stats.off_track_since.get(&driver_number),
+            self.simulation.run_race_data().first(),
+        ) {
+            (Some(since), Some(first)) => {
+                (*since - first.date).num_milliseconds() as f64 / 1000.0
+                    <= self.simulation.race_time()
             }
+            _ => false,
+        }
+    }
 
-            self.current_index = next_index;
-            self.update_led_states();
+    fn toggle_recording(&mut self) {
+        match self.recording.take() {
+            Some(id) => self.outputs.remove(id),
+            None => match start_recording(&self.recorder, &self.coordinates, &mut self.outputs) {
+                Ok(id) => self.recording = Some(id),
+                Err(err) => error!("Could not start recording: {}", err),
+            },
         }
     }
 
-    fn update_led_states(&mut self) {
-        self.led_states.clear();
+    // The summary of the race as far as it's played, as copied to the clipboard
+    fn race_summary(&self) -> String {
+        let progress = self.race_progress.as_deref();
+        let flags = progress.map_or_else(FlagTimeline::default, |progress| {
+            FlagTimeline::new(progress.race_control(), &self.flag_panels)
+        });
+        race_summary(
+            &self.session.label(),
+            self.simulation.race_time(),
+            self.simulation.race_date(),
+            progress,
+            &self.driver_info,
+            &flags,
+        )
+    }
 
-        for run_data in &self.run_race_data[..self.current_index] {
-            let coord_key = (
-                Self::scale_f64(run_data.x_led, 1_000_000),
-                Self::scale_f64(run_data.y_led, 1_000_000),
+    // Appends the summary to the log once the leader completes a lap. A log that can't be
+    // written is given up on rather than failing every lap
+    fn log_summary(&mut self, race_date: Option<DateTime<Utc>>) {
+        let (Some(summary_log), Some(date), Some(progress)) = (
+            &mut self.summary_log,
+            race_date,
+            self.race_progress.as_deref(),
+        ) else {
+            return;
+        };
+        if !summary_log.lap_completed(leader_laps(progress, date)) {
+            return;
+        }
+        let summary = self.race_summary();
+        let Some(summary_log) = &self.summary_log else {
+            return;
+        };
+        if let Err(err) = summary_log.append(&summary) {
+            error!(
+                "Could not log the race summary to {}: {}",
+                summary_log.path().display(),
+                err
             );
-
-            println!("Driver {} moved to LED position {:?}", run_data.driver_number, coord_key);
-
-            // Update the last known position of the driver
-            self.last_positions
-                .insert(run_data.driver_number, coord_key);
+            self.push_toast(Toast::error(err.user_message()));
+            self.summary_log = None;
         }
+    }
 
-        // Update the LED states for all known positions
-        for (&driver_number, &position) in &self.last_positions {
-            let color = self
-                .driver_info
-                .iter()
-                .find(|&driver| driver.number == driver_number)
-                .map_or(egui::Color32::WHITE, |driver| driver.color);
-            println!(
-                "LED at position {:?} set to color {:?} for driver {}",
-                position, color, driver_number
-            );
-            self.led_states.insert(position, color);
+    fn push_toast(&mut self, toast: Toast) {
+        self.toasts.push(toast);
+        if self.toasts.len() > MAX_TOASTS {
+            self.toasts.remove(0);
         }
     }
 
-    fn scale_f64(value: f64, scale: i64) -> i64 {
-        (value * scale as f64) as i64
+    // Picks up the problems reported elsewhere and draws the toasts still up, newest at the
+    // bottom
+    fn show_toasts(&mut self, ctx: &egui::Context) {
+        for message in notices::take() {
+            self.push_toast(Toast::warning(message));
+        }
+        self.toasts
+            .retain(|toast| toast.remaining() != Some(Duration::ZERO));
+        if self.toasts.is_empty() {
+            return;
+        }
+
+        let mut dismissed = None;
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10.0, -10.0))
+            .show(ctx, |ui| {
+                for (index, toast) in self.toasts.iter().enumerate() {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            let text = egui::RichText::new(&toast.text);
+                            ui.label(match toast.level {
+                                ToastLevel::Info => text,
+                                ToastLevel::Warning => text.color(ui.visuals().warn_fg_color),
+                                ToastLevel::Error => text.color(ui.visuals().error_fg_color),
+                            });
+                            if ui.small_button("✖").clicked() {
+                                dismissed = Some(index);
+                            }
+                        });
+                    });
+                }
+            });
+        if let Some(index) = dismissed {
+            self.toasts.remove(index);
+        }
+        if let Some(next) = self.toasts.iter().filter_map(Toast::remaining).min() {
+            ctx.request_repaint_after(next);
+        }
     }
-}
 
-impl App for PlotApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
-        self.update_race();
+    // The lines of the diagnostics window by section, shown as they're copied
+    fn diagnostics_sections(&self) -> Vec<Vec<String>> {
+        let stats = &self.mapping_stats;
+        let pipeline = &stats.pipeline;
+        let run_race_data = self.simulation.run_race_data();
 
-        let painter = ctx.layer_painter(egui::LayerId::new(
-            egui::Order::Background,
-            egui::Id::new("layer"),
+        let mut samples = vec!["Location samples fetched:".to_string()];
+        for (driver_number, count) in &pipeline.fetched_per_driver {
+            samples.push(format!("  Driver {}: {}", driver_number, count));
+        }
+        samples.push(format!(
+            "Dropped at (0, 0): {}",
+            pipeline.zero_coordinate_samples
         ));
+        samples.push(format!(
+            "Interpolated across dropouts: {}",
+            pipeline.synthetic_samples
+        ));
+        samples.push(format!(
+            "Dropped by downsampling: {}",
+            pipeline.downsampled_samples
+        ));
+        samples.push(match pipeline.align_ms {
+            Some(interval_ms) => format!(
+                "Resampled onto a {} ms grid: {} interpolated samples",
+                interval_ms, pipeline.aligned_samples
+            ),
+            None => "Resampled onto a grid: no, as received".to_string(),
+        });
+        samples.push(format!(
+            "Dropped as off track: {} (max snap distance {:.0})",
+            stats.dropped_samples, stats.max_snap_distance
+        ));
+        let mut dropped: Vec<_> = stats.dropped_per_driver.iter().collect();
+        dropped.sort();
+        for (driver_number, count) in dropped {
+            samples.push(format!("  Driver {}: {}", driver_number, count));
+        }
+        let elevations: Vec<f64> = stats.led_elevation.iter().flatten().copied().collect();
+        samples.push(
+            match (
+                elevations.iter().copied().reduce(f64::min),
+                elevations.iter().copied().reduce(f64::max),
+            ) {
+                (Some(low), Some(high)) => format!(
+                    "Elevation: {:.0} to {:.0} over {} of {} LEDs{}",
+                    low,
+                    high,
+                    elevations.len(),
+                    stats.led_elevation.len(),
+                    if self.display.elevation_shades.is_none() {
+                        ", too flat to shade"
+                    } else {
+                        ""
+                    }
+                ),
+                _ => "Elevation: none in the samples".to_string(),
+            },
+        );
 
-        let (min_x, max_x) = self
-            .coordinates
-            .iter()
-            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), coord| {
-                (min.min(coord.x_led), max.max(coord.x_led))
-            });
-        let (min_y, max_y) = self
-            .coordinates
-            .iter()
-            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), coord| {
-                (min.min(coord.y_led), max.max(coord.y_led))
-            });
-
-        let width = max_x - min_x;
-        let height = max_y - min_y;
+        let memory = std::mem::size_of_val(run_race_data) as f64 / (1024.0 * 1024.0);
+        let mut records = vec![
+            format!(
+                "Records: {} mapped, {} after collapsing {} repeated positions",
+                pipeline.mapped_samples,
+                run_race_data.len(),
+                stats.collapsed_samples
+            ),
+            format!("Memory: about {:.1} MiB", memory),
+            format!("Window: {}", format_window(&pipeline.window)),
+            format!(
+                "Fetch: {:.2}s, mapping: {:.2}s",
+                pipeline.fetch_time.as_secs_f64(),
+                pipeline.mapping_time.as_secs_f64()
+            ),
+            format!(
+                "Current index: {} of {}",
+                self.simulation.current_index(),
+                run_race_data.len()
+            ),
+        ];
+        // Header names only; their values and the token may be secrets
+        if let Some(source) = &self.data_source {
+            records.push(format!("API: {}", source.api.describe()));
+        }
 
-        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
-            ui.horizontal(|ui| {
-                ui.separator();
-                ui.label(format!(
-                    "Race Time: {:02}:{:02}:{:05.2}",
-                    (self.race_time / 3600.0).floor() as u32, // hours
-                    ((self.race_time % 3600.0) / 60.0).floor() as u32, // minutes
-                    self.race_time % 60.0                     // seconds with milliseconds
+        let mut outputs = vec!["Output frames produced / sent / skipped / dropped:".to_string()];
+        for (name, stats) in self.outputs.stats() {
+            outputs.push(format!(
+                "  {}: {} / {} / {} / {}",
+                name, stats.produced, stats.sent, stats.skipped, stats.dropped
+            ));
+        }
+        let mut positions = vec!["Track position (from the start/finish LED):".to_string()];
+        for progress in self.simulation.track_progress().drivers() {
+            positions.push(format!(
+                "  Driver {}: {:.1}%, {} laps",
+                progress.driver_number, progress.percent, progress.laps
+            ));
+        }
+        let mut distances =
+            vec!["Distance travelled (estimated from the raw locations):".to_string()];
+        if let Some(date) = self.simulation.race_date() {
+            let layout_length = layout_length(&self.coordinates);
+            let mut drivers: Vec<u32> = self.simulation.timelines().drivers().collect();
+            drivers.sort();
+            for driver_number in drivers {
+                let Some(meters) = self.distances.at(driver_number, date) else {
+                    continue;
+                };
+                let laps = estimated_laps(meters, layout_length, &self.speed)
+                    .map_or(String::new(), |laps| format!(", about {:.1} laps", laps));
+                distances.push(format!(
+                    "  Driver {}: {:.2} km{}",
+                    driver_number,
+                    meters / 1000.0,
+                    laps
                 ));
-                ui.separator();
-
-                if ui.button("START").clicked() {
-                    self.race_started = true;
-                    self.start_time = Instant::now();
-                    self.current_index = 0;
-                    self.led_states.clear(); // Clear LED states when race starts
-                }
-                if ui.button("STOP").clicked() {
-                    self.reset();
-                }
+            }
+        }
+        vec![samples, records, positions, distances, outputs]
+    }
 
-                ui.label("PLAYBACK SPEED");
-                ui.add(egui::Slider::new(&mut self.speed, 1..=5));
+    // The red bar under the top panel while no session could be loaded
+    fn show_banner(&mut self, ctx: &egui::Context) {
+        let Some(message) = &self.banner else {
+            return;
+        };
+        let mut retry = false;
+        let fill = ctx.style().visuals.error_fg_color;
+        egui::TopBottomPanel::top("error_banner")
+            .frame(egui::Frame::default().fill(fill).inner_margin(6.0))
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new(message).color(egui::Color32::WHITE));
+                    // A recording has no session to fetch again
+                    if self.data_source.is_some() {
+                        retry = ui.button("RETRY").clicked();
+                    }
+                });
             });
-        });
-
-        egui::SidePanel::right("legend_panel").show(ctx, |ui| {
-            ui.vertical(|ui| {
-                let style = ui.style_mut();
-                style
-                    .text_styles
-                    .get_mut(&egui::TextStyle::Body)
-                    .unwrap()
-                    .size = 8.0; // Set the font size to 8.0 (or any other size you prefer)
+        if retry {
+            self.settings.form =
+                SessionForm::new(&self.session, &driver_numbers(&self.driver_info));
+            self.reload_session(false);
+        }
+    }
 
-                for driver in &self.driver_info {
-                    ui.horizontal(|ui| {
-                        ui.label(format!(
-                            "{}: {} ({})",
-                            driver.number, driver.name, driver.team
-                        ));
-                        ui.painter().rect_filled(
-                            egui::Rect::from_min_size(ui.cursor().min, egui::vec2(5.0, 5.0)),
-                            0.0,
-                            driver.color,
-                        );
-                        ui.add_space(5.0); // Space between legend items
-                    });
-                }
+    // The yellow bar under the top panel after a stall, until it's dismissed
+    fn show_stall_warning(&mut self, ctx: &egui::Context) {
+        let Some(message) = &self.stall_warning else {
+            return;
+        };
+        let mut dismissed = false;
+        let fill = ctx.style().visuals.warn_fg_color;
+        egui::TopBottomPanel::top("stall_banner")
+            .frame(egui::Frame::default().fill(fill).inner_margin(6.0))
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new(message).color(egui::Color32::BLACK));
+                    dismissed = ui.small_button("✖").clicked();
+                });
             });
-        });
-
-        egui::CentralPanel::default().show(ctx, |ui| {
-            for coord in &self.coordinates {
-                let norm_x = ((coord.x_led - min_x) / width) as f32 * (ui.available_width() - 60.0); // Adjust for left/right margin
-                let norm_y = (ui.available_height() - 60.0)
-                    - (((coord.y_led - min_y) / height) as f32 * (ui.available_height() - 60.0)); // Adjust for top/bottom margin
+        if dismissed {
+            self.stall_warning = None;
+        }
+    }
 
-                painter.rect_filled(
-                    egui::Rect::from_min_size(
-                        egui::pos2(norm_x + 30.0, norm_y + 30.0), // Adjust position to include margins
-                        egui::vec2(20.0, 20.0),
-                    ),
-                    egui::Rounding::same(0.0),
-                    egui::Color32::BLACK,
-                );
+    // Writes a diagnostic dump, with the errors and background jobs only the window knows of
+    fn write_diagnostics(&self, reason: &str) -> Result<PathBuf, AppError> {
+        let mut dump =
+            DiagnosticDump::new(reason, &self.simulation, &self.outputs, &status::snapshot());
+        if let Some(message) = &self.banner {
+            dump.error("session", message);
+        }
+        if let Some(message) = &self.ghost.message {
+            dump.error("ghost", message);
+        }
+        for (name, finished) in self.jobs() {
+            if let Some(finished) = finished {
+                dump.thread(name, !finished);
             }
+        }
+        dump.write(&self.watchdog_config.dir)
+    }
 
-            for ((x, y), color) in &self.led_states {
-                let norm_x = ((*x as f64 / 1_000_000.0 - min_x) / width) as f32
-                    * (ui.available_width() - 60.0); // Adjust for left/right margin
-                let norm_y = (ui.available_height() - 60.0)
-                    - (((*y as f64 / 1_000_000.0 - min_y) / height) as f32
-                        * (ui.available_height() - 60.0)); // Adjust for top/bottom margin
+    // Each background job and whether it has finished; `None` when it isn't running
+    fn jobs(&self) -> [(&'static str, Option<bool>); 11] {
+        [
+            (
+                "reload",
+                self.reload_job.as_ref().map(|job| job.handle.is_finished()),
+            ),
+            ("prefetch", self.queue.prefetch_finished()),
+            (
+                "ghost",
+                self.ghost.job.as_ref().map(JoinHandle::is_finished),
+            ),
+            (
+                "car data",
+                self.pedal_job.as_ref().map(|(_, job)| job.is_finished()),
+            ),
+            (
+                "split",
+                self.split.job.as_ref().map(|(_, job)| job.is_finished()),
+            ),
+            (
+                "export",
+                self.exports.job.as_ref().map(ExportJob::is_finished),
+            ),
+            (
+                "occupancy",
+                self.exports
+                    .occupancy_job
+                    .as_ref()
+                    .map(JoinHandle::is_finished),
+            ),
+            (
+                "lap chart",
+                self.exports
+                    .lap_chart_job
+                    .as_ref()
+                    .map(JoinHandle::is_finished),
+            ),
+            (
+                "heatmap",
+                self.exports
+                    .heatmap_job
+                    .as_ref()
+                    .map(JoinHandle::is_finished),
+            ),
+            (
+                "parquet",
+                self.exports
+                    .parquet_job
+                    .as_ref()
+                    .map(ParquetJob::is_finished),
+            ),
+            (
+                "bundle",
+                self.exports
+                    .bundle_job
+                    .as_ref()
+                    .map(JoinHandle::is_finished),
+            ),
+        ]
+    }
 
-                painter.rect_filled(
-                    egui::Rect::from_min_size(
-                        egui::pos2(norm_x + 30.0, norm_y + 30.0), // Adjust position to include margins
-                        egui::vec2(20.0, 20.0),
-                    ),
-                    egui::Rounding::same(0.0),
-                    *color,
-                );
+    // Turns the outputs off, which also finishes the recording, and gives the background jobs
+    // a moment to finish writing their files. Loads and exports are cancelled rather than
+    // waited out; a job still running after that is left behind
+    fn shut_down(&mut self) {
+        self.outputs.blank_and_shutdown(SHUTDOWN_TIMEOUT);
+        if let Some(job) = &self.reload_job {
+            job.cancelled.store(true, Ordering::Relaxed);
+        }
+        self.cancel_prefetch();
+        if let Some(job) = &self.exports.job {
+            job.cancel();
+        }
+        let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+        let running = |app: &PlotApp| {
+            app.jobs()
+                .iter()
+                .any(|(_, finished)| *finished == Some(false))
+        };
+        while running(self) && Instant::now() < deadline {
+            std::thread::sleep(JOB_POLL_INTERVAL);
+        }
+        for (name, finished) in self.jobs() {
+            if finished == Some(false) {
+                warn!("Closing with the {} job still running", name);
             }
-        });
+        }
+    }
 
-        ctx.request_repaint(); // Request the GUI to repaint
+    // The playback state and settings with the ids of the data played, next to the dumps
+    fn write_snapshot(&self) -> Result<PathBuf, AppError> {
+        let mapping = self
+            .data_source
+            .as_ref()
+            .map_or_else(MappingOptions::default, |source| source.mapping.clone());
+        let cache_key = mapping_cache_key(
+            &self.session.key,
+            &session_drivers(&self.session, &self.driver_info),
+            &self.session.window(),
+            &self.coordinates,
+            &mapping,
+        );
+        let dataset = DatasetIds::new(
+            &self.session,
+            &self.coordinates,
+            cache_key,
+            self.simulation.run_race_data(),
+        );
+        let settings = SnapshotSettings {
+            focused_driver: self.focused_driver,
+            brightness: self.brightness,
+            mapping,
+            qualifying: self.qualifying.clone(),
+        };
+        StateSnapshot::new(&self.simulation, dataset, settings).write(&self.watchdog_config.dir)
     }
-}
 
-fn main() -> Result<(), Box<dyn StdError>> {
-    let coordinates = read_coordinates()?; // Unwrap the result here
+    // Works out the timeline's markers again, for a session just shown. The best laps have
+    // none, as nothing dated lines up with them
+    fn update_markers(&mut self) {
+        self.markers = match (
+            self.race_progress.as_deref(),
+            self.simulation.run_race_data().first(),
+        ) {
+            (Some(progress), Some(first)) if self.best_laps.is_empty() => {
+                event_markers(progress, self.simulation.overtakes().all(), first.date)
+            }
+            _ => Vec::new(),
+        };
+    }
 
-    // Initialize the runtime for async execution
-    let runtime = tokio::runtime::Runtime::new()?;
-    let raw_data = runtime.block_on(fetch_data())?;
+    // Pauses playback while another window has focus and plays on a little after it's back,
+    // unless the frames also go to outputs that are there to keep running in the background
+    fn follow_focus(&mut self, ctx: &egui::Context, now: Instant) {
+        let applies = self.pause_when_unfocused
+            && !self.controls.is_following()
+            && self
+                .outputs
+                .names()
+                .all(|name| matches!(name, "gui" | "recorder"));
+        if applies && !ctx.input(|input| input.focused) {
+            self.focus_resume_at = None;
+            if self.simulation.state() == PlaybackState::Playing {
+                self.simulation.set_paused(true);
+                self.paused_for_focus = true;
+            }
+            return;
+        }
+        if !self.paused_for_focus {
+            return;
+        }
+        // Resumed or stopped by hand in the meantime
+        if self.simulation.state() != PlaybackState::Paused {
+            self.paused_for_focus = false;
+            self.focus_resume_at = None;
+            return;
+        }
+        let resume_at = *self
+            .focus_resume_at
+            .get_or_insert(now + self.focus_resume_delay);
+        if !applies || now >= resume_at {
+            self.simulation.set_paused(false);
+            self.paused_for_focus = false;
+            self.focus_resume_at = None;
+        } else {
+            ctx.request_repaint_after(resume_at - now);
+        }
+    }
 
-    let run_race_data = generate_run_race_data(&raw_data, &coordinates);
+    // Sorts the legend again when the order was changed or, when it's by position, once a
+    // position changed, so rows don't move every frame
+    fn update_legend_rows(&mut self, race_date: Option<DateTime<Utc>>) {
+        let progress = self.race_progress.as_deref();
+        let changes = match (self.legend_order, progress, race_date) {
+            (LegendOrder::Position, Some(progress), Some(date)) => {
+                progress.position_changes_until(date)
+            }
+            _ => 0,
+        };
+        if self.legend_sorted_for == Some((self.legend_order, changes)) {
+            return;
+        }
+        self.legend_rows = legend_order(&self.driver_info, self.legend_order, |driver_number| {
+            let progress = progress?;
+            race_date
+                .and_then(|date| progress.position_at(driver_number, date))
+                .or_else(|| progress.starting_position(driver_number))
+        });
+        self.legend_sorted_for = Some((self.legend_order, changes));
+    }
 
-    let driver_info = vec![
-        DriverInfo {
-            number: 1,
-            name: "Max Verstappen",
-            team: "Red Bull",
-            color: egui::Color32::from_rgb(30, 65, 255),
-        },
-        DriverInfo {
-            number: 2,
-            name: "Logan Sargeant",
-            team: "Williams",
-            color: egui::Color32::from_rgb(0, 82, 255),
-        },
-        DriverInfo {
-            number: 4,
-            name: "Lando Norris",
-            team: "McLaren",
-            color: egui::Color32::from_rgb(255, 135, 0),
-        },
-        DriverInfo {
-            number: 10,
-            name: "Pierre Gasly",
-            team: "Alpine",
-            color: egui::Color32::from_rgb(2, 144, 240),
-        },
-        DriverInfo {
-            number: 11,
-            name: "Sergio Perez",
-            team: "Red Bull",
-            color: egui::Color32::from_rgb(30, 65, 255),
-        },
-        DriverInfo {
-            number: 14,
-            name: "Fernando Alonso",
-            team: "Aston Martin",
-            color: egui::Color32::from_rgb(0, 110, 120),
-        },
-        DriverInfo {
-            number: 16,
-            name: "Charles Leclerc",
-            team: "Ferrari",
-            color: egui::Color32::from_rgb(220, 0, 0),
-        },
-        DriverInfo {
-            number: 18,
-            name: "Lance Stroll",
-            team: "Aston Martin",
-            color: egui::Color32::from_rgb(0, 110, 120),
-        },
-        DriverInfo {
-            number: 20,
-            name: "Kevin Magnussen",
-            team: "Haas",
-            color: egui::Color32::from_rgb(160, 207, 205),
-        },
-        DriverInfo {
-            number: 22,
-            name: "Yuki Tsunoda",
-            team: "AlphaTauri",
-            color: egui::Color32::from_rgb(60, 130, 200),
-        },
-        DriverInfo {
-            number: 23,
-            name: "Alex Albon",
-            team: "Williams",
-            color: egui::Color32::from_rgb(0, 82, 255),
-        },
-        DriverInfo {
-            number: 24,
-            name: "Zhou Guanyu",
-            team: "Stake F1",
-            color: egui::Color32::from_rgb(165, 160, 155),
-        },
-        DriverInfo {
-            number: 27,
-            name: "Nico Hulkenberg",
-            team: "Haas",
-            color: egui::Color32::from_rgb(160, 207, 205),
-        },
-        DriverInfo {
-            number: 31,
-            name: "Esteban Ocon",
-            team: "Alpine",
-            color: egui::Color32::from_rgb(2, 144, 240),
-        },
-        DriverInfo {
-            number: 40,
-            name: "Liam Lawson",
-            team: "AlphaTauri",
-            color: egui::Color32::from_rgb(60, 130, 200),
-        },
-        DriverInfo {
-            number: 44,
-            name: "Lewis Hamilton",
-            team: "Mercedes",
-            color: egui::Color32::from_rgb(0, 210, 190),
-        },
-        DriverInfo {
-            number: 55,
-            name: "Carlos Sainz",
-            team: "Ferrari",
-            color: egui::Color32::from_rgb(220, 0, 0),
-        },
-        DriverInfo {
-            number: 63,
-            name: "George Russell",
-            team: "Mercedes",
-            color: egui::Color32::from_rgb(0, 210, 190),
-        },
-        DriverInfo {
-            number: 77,
-            name: "Valtteri Bottas",
-            team: "Stake F1",
-            color: egui::Color32::from_rgb(165, 160, 155),
-        },
-        DriverInfo {
-            number: 81,
-            name: "Oscar Piastri",
-            team: "McLaren",
-            color: egui::Color32::from_rgb(255, 135, 0),
-        },
-    ];
+    // Keeps the drivers hidden in this session for when it's shown again. Numbers go to other
+    // drivers in other sessions, so each session has its own
+    fn remember_hidden_drivers(&mut self) {
+        let mut hidden: Vec<u32> = self.simulation.hidden_drivers().collect();
+        hidden.sort();
+        if hidden.is_empty() {
+            self.hidden_drivers.remove(&self.session.key);
+        } else {
+            self.hidden_drivers.insert(self.session.key.clone(), hidden);
+        }
+    }
 
-    let app = PlotApp::new(coordinates, run_race_data, driver_info);
+    // Checks the settings form, then stops playback, drops the current data and fetches the
+    // session it describes in the background, replacing any reload still running
+    // Loads the session in the settings form; `long_window_ok` once a long window is confirmed
+    fn reload_session(&mut self, long_window_ok: bool) {
+        let roster = driver_numbers(&self.driver_info);
+        let session = match self.settings.form.apply(&self.session, &roster) {
+            Ok(session) => session,
+            Err(errors) => {
+                self.settings.errors = errors;
+                return;
+            }
+        };
+        self.settings.errors = SessionFormErrors::default();
+        self.settings.window_problem = None;
+        let Some(source) = self.data_source.clone() else {
+            return;
+        };
+        // A fetch can't be interrupted, but the thread stops after it and nobody waits for it
+        if let Some(job) = self.reload_job.take() {
+            job.cancelled.store(true, Ordering::Relaxed);
+        }
 
-    let native_options = eframe::NativeOptions::default();
-    eframe::run_native(
-        "F1-LED-CIRCUIT SIMULATION",
-        native_options,
-        Box::new(|_cc| Box::new(app)),
-    )?;
+        self.simulation.reset();
+        self.show_session(Vec::new(), MappingStats::default(), None);
 
-    Ok(())
-}
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let job_session = session.clone();
+        let job_cancelled = Arc::clone(&cancelled);
+        let spawned = std::thread::Builder::new()
+            .name("session".to_string())
+            .spawn(move || load_session(&source, &job_session, &job_cancelled, long_window_ok));
+        match spawned {
+            Ok(handle) => {
+                info!("Reloading session {}", session.key);
+                self.reload_job = Some(ReloadJob { handle, cancelled });
+                self.queue.playlist.set_playing(&session.key);
+                self.queue.title_card = None;
+                self.banner = None;
+                self.session_name = session.label();
+                self.session = session;
+                self.settings.shown = false;
+            }
+            Err(err) => {
+                let err = AppError::from(err);
+                error!("Could not start loading the session: {}", err);
+                self.push_toast(Toast::error(err.user_message()));
+            }
+        }
+    }
 
-async fn fetch_data() -> Result<Vec<LocationData>, Box<dyn StdError>> {
-    let session_key = "9149";
-    let driver_numbers = vec![
-        1, 2, 4, 10, 11, 14, 16, 18, 20, 22, 23, 24, 27, 31, 40, 44, 55, 63, 77, 81,
-    ];
+    fn finish_reload(&mut self) {
+        let Some(job) = self.reload_job.take() else {
+            return;
+        };
+        match join_worker(job.handle, "session") {
+            Ok(SessionLoad::Loaded(loaded)) => self.show_loaded(loaded),
+            Ok(SessionLoad::Cancelled) => {}
+            // The form still holds the window, to fix it there
+            Ok(SessionLoad::BadWindow(problem)) => {
+                warn!(
+                    "Not loading session {}: {}",
+                    self.session.key,
+                    problem.message()
+                );
+                self.settings.window_problem = Some(problem);
+                self.settings.shown = true;
+            }
+            Err(err) => {
+                error!("Could not load session {}: {}", self.session.key, err);
+                self.banner = Some(err.user_message());
+            }
+        }
+    }
 
-    let client = Client::new();
-    let mut all_data: Vec<LocationData> = Vec::new();
+    // Shows a session loaded in the background with its own roster
+    fn show_loaded(&mut self, loaded: Box<LoadedSession>) {
+        let records = loaded.run_race_data.len();
+        let mut driver_info = loaded.driver_info;
+        apply_color_overrides(&mut driver_info, &self.color_overrides);
+        self.driver_info = driver_info;
+        if !loaded.made_up_drivers.is_empty() {
+            self.push_toast(Toast::warning(made_up_drivers_message(
+                &loaded.made_up_drivers,
+            )));
+        }
+        self.show_session(
+            loaded.run_race_data,
+            loaded.mapping_stats,
+            loaded.race_progress,
+        );
+        self.push_toast(Toast::info(format!(
+            "Loaded session {}: {} records",
+            self.session.key, records
+        )));
+    }
 
-    for driver_number in driver_numbers {
-        let url = format!(
-            "https://api.openf1.org/v1/location?session_key={}&driver_number={}",
-            session_key, driver_number
+    // Plays new race data from the start with everything derived from it worked out again;
+    // the speed and the hidden drivers stay
+    fn show_session(
+        &mut self,
+        run_race_data: Vec<RunRace>,
+        mapping_stats: MappingStats,
+        race_progress: Option<RaceProgress>,
+    ) {
+        let led_count = self.coordinates.len();
+        let race_progress = race_progress.map(Arc::new);
+        let mut simulation =
+            Simulation::new(run_race_data, led_count, driver_colors(&self.driver_info));
+        self.best_laps = apply_qualifying_mode(
+            &mut simulation,
+            race_progress.as_deref(),
+            &self.qualifying,
+            &self.driver_info,
         );
-        let resp = client.get(&url).send().await?;
-        if resp.status().is_success() {
-            let data: Vec<LocationData> = resp.json().await?;
-            all_data.extend(data.into_iter().filter(|d| d.x != 0.0 && d.y != 0.0));
-        } else {
-            eprintln!(
-                "Failed to fetch data for driver {}: HTTP {}",
-                driver_number,
-                resp.status()
+        let garage_segments = simulation.speed_plan().segments().to_vec();
+        // The best laps run on clocks of their own, which nothing dated in the session lines up with
+        let session_progress = race_progress
+            .as_deref()
+            .filter(|_| self.best_laps.is_empty());
+        simulation.set_race_progress(race_progress.clone().filter(|_| self.best_laps.is_empty()));
+        simulation.set_color_scheme(self.simulation.color_scheme());
+        simulation.set_led_view(self.simulation.led_view());
+        simulation.set_driver_teams(driver_teams(&self.driver_info));
+        simulation.set_speed(self.simulation.speed());
+        simulation.set_time_offset(self.time_offset());
+        let segments = self.speed_segments.get(&self.session.key).cloned();
+        simulation
+            .speed_plan_mut()
+            .set_segments(segments.unwrap_or(garage_segments));
+        simulation
+            .speed_plan_mut()
+            .set_auto(self.simulation.speed_plan().is_auto());
+        for &driver_number in self
+            .hidden_drivers
+            .get(&self.session.key)
+            .into_iter()
+            .flatten()
+        {
+            simulation.set_driver_hidden(driver_number, true);
+        }
+        if let (Some(progress), true) = (session_progress, self.overtakes.enabled) {
+            let overtakes = detect_overtakes(
+                progress,
+                simulation.timelines(),
+                &self.overtakes,
+                self.speed.meters_per_unit,
             );
+            simulation.set_overtakes(overtakes, self.overtakes.history);
+        }
+        if let Some(progress) = session_progress {
+            let timeline = FlagTimeline::new(progress.race_control(), &self.flag_panels);
+            simulation.set_flag_panels(FlagPanels::new(&self.flag_panels, timeline, led_count));
+        }
+        simulation.set_grid_formation(grid_formation(session_progress, led_count, &self.formation));
+        if self.best_laps.is_empty() {
+            let retirements = Retirements::new(simulation.timelines(), &self.retirements);
+            simulation.set_retirements(retirements);
+        }
+        simulation.set_show_retired(self.retirements.keep_last_led);
+        simulation.set_stale_secs(self.retirements.stale_secs);
+        simulation.set_blue_flags(&self.blue_flags);
+        simulation.set_led_decay(&self.led_decay);
+        simulation.set_led_dwell(&self.led_dwell);
+        if let Some(source) = &self.data_source {
+            simulation.set_positioning(source.mapping.positioning, &self.coordinates);
         }
+        let heatmap = self.simulation.heatmap().is_some();
+        self.simulation = simulation;
+        self.controls
+            .set_session(&self.session.key, &self.simulation);
+        self.show_heatmap(heatmap);
+
+        self.timing.sector_times =
+            SectorTimes::new(self.simulation.timelines(), led_count, &self.sectors);
+        self.distances = DriverDistances::new(self.simulation.timelines(), &self.speed);
+        // The grid is taken here once, so seeking doesn't move it
+        self.position_deltas = session_progress.map(PositionDeltas::new);
+        self.race_progress = race_progress;
+        if let Some(summary_log) = &mut self.summary_log {
+            summary_log.reset();
+        }
+        self.update_markers();
+        self.legend_sorted_for = None;
+        self.display.elevation_shades = elevation_shades(&mapping_stats.led_elevation);
+        self.mapping_stats = mapping_stats;
+        self.charts.delta_for = None;
+        self.charts.telemetry_for = None;
+        self.battles.clear();
+        self.trains.clear();
+        self.rivals.clear();
+        self.align_ghost();
+        // The traces were matched to the records played before; a load under way is let go
+        self.pedal_traces.clear();
+        self.car_data.clear();
+        self.pedal_job = None;
     }
 
-    // Sort the data by the date field
-    all_data.sort_by_key(|d| d.date);
-    Ok(all_data)
-}
-
-fn read_coordinates() -> Result<Vec<LedCoordinate>, Box<dyn StdError>> {
-    Ok(vec![
-        LedCoordinate { x_led: 6413.0, y_led: 33.0 }, // U1
-        LedCoordinate { x_led: 6007.0, y_led: 197.0 }, // U2
-        LedCoordinate { x_led: 5652.0, y_led: 444.0 }, // U3
-        LedCoordinate { x_led: 5431.0, y_led: 822.0 }, // U4
-        LedCoordinate { x_led: 5727.0, y_led: 1143.0 }, // U5
-        LedCoordinate { x_led: 6141.0, y_led: 1268.0 }, // U6
-        LedCoordinate { x_led: 6567.0, y_led: 1355.0 }, // U7
-        LedCoordinate { x_led: 6975.0, y_led: 1482.0 }, // U8
-        LedCoordinate { x_led: 7328.0, y_led: 1738.0 }, // U9
-        LedCoordinate { x_led: 7369.0, y_led: 2173.0 }, // U10
-        LedCoordinate { x_led: 7024.0, y_led: 2448.0 }, // U11
-        LedCoordinate { x_led: 6592.0, y_led: 2505.0 }, // U12
-        LedCoordinate { x_led: 6159.0, y_led: 2530.0 }, // U13
-        LedCoordinate { x_led: 5725.0, y_led: 2525.0 }, // U14
-        LedCoordinate { x_led: 5288.0, y_led: 2489.0 }, // U15
-        LedCoordinate { x_led: 4857.0, y_led: 2434.0 }, // U16
-        LedCoordinate { x_led: 4429.0, y_led: 2356.0 }, // U17
-        LedCoordinate { x_led: 4004.0, y_led: 2249.0 }, // U18
-        LedCoordinate { x_led: 3592.0, y_led: 2122.0 }, // U19
-        LedCoordinate { x_led: 3181.0, y_led: 1977.0 }, // U20
-        LedCoordinate { x_led: 2779.0, y_led: 1812.0 }, // U21
-        LedCoordinate { x_led: 2387.0, y_led: 1624.0 }, // U22
-        LedCoordinate { x_led: 1988.0, y_led: 1453.0 }, // U23
-        LedCoordinate { x_led: 1703.0, y_led: 1779.0 }, // U24
-        LedCoordinate { x_led: 1271.0, y_led: 1738.0 }, // U25
-        LedCoordinate { x_led: 1189.0, y_led: 1314.0 }, // U26
-        LedCoordinate { x_led: 1257.0, y_led: 884.0 }, // U27
-        LedCoordinate { x_led: 1333.0, y_led: 454.0 }, // U28
-        LedCoordinate { x_led: 1409.0, y_led: 25.0 }, // U29
-        LedCoordinate { x_led: 1485.0, y_led: -405.0 }, // U30
-        LedCoordinate { x_led: 1558.0, y_led: -835.0 }, // U31
-        LedCoordinate { x_led: 1537.0, y_led: -1267.0 }, // U32
-        LedCoordinate { x_led: 1208.0, y_led: -1555.0 }, // U33
-        LedCoordinate { x_led: 779.0, y_led: -1606.0 }, // U34
-        LedCoordinate { x_led: 344.0, y_led: -1604.0 }, // U35
-        LedCoordinate { x_led: -88.0, y_led: -1539.0 }, // U36
-        LedCoordinate { x_led: -482.0, y_led: -1346.0 }, // U37
-        LedCoordinate { x_led: -785.0, y_led: -1038.0 }, // U38
-        LedCoordinate { x_led: -966.0, y_led: -644.0 }, // U39
-        LedCoordinate { x_led: -1015.0, y_led: -206.0 }, // U40
-        LedCoordinate { x_led: -923.0, y_led: 231.0 }, // U41
-        LedCoordinate { x_led: -762.0, y_led: 650.0 }, // U42
-        LedCoordinate { x_led: -591.0, y_led: 1078.0 }, // U43
-        LedCoordinate { x_led: -423.0, y_led: 1497.0 }, // U44
-        LedCoordinate { x_led: -254.0, y_led: 1915.0 }, // U45
-        LedCoordinate { x_led: -86.0, y_led: 2329.0 }, // U46
-        LedCoordinate { x_led: 83.0, y_led: 2744.0 }, // U47
-        LedCoordinate { x_led: 251.0, y_led: 3158.0 }, // U48
-        LedCoordinate { x_led: 416.0, y_led: 3574.0 }, // U49
-        LedCoordinate { x_led: 588.0, y_led: 3990.0 }, // U50
-        LedCoordinate { x_led: 755.0, y_led: 4396.0 }, // U51
-        LedCoordinate { x_led: 920.0, y_led: 4804.0 }, // U52
-        LedCoordinate { x_led: 1086.0, y_led: 5212.0 }, // U53
-        LedCoordinate { x_led: 1250.0, y_led: 5615.0 }, // U54
-        LedCoordinate { x_led: 1418.0, y_led: 6017.0 }, // U55
-        LedCoordinate { x_led: 1583.0, y_led: 6419.0 }, // U56
-        LedCoordinate { x_led: 1909.0, y_led: 6702.0 }, // U57
-        LedCoordinate { x_led: 2306.0, y_led: 6512.0 }, // U58
-        LedCoordinate { x_led: 2319.0, y_led: 6071.0 }, // U59
-        LedCoordinate { x_led: 2152.0, y_led: 5660.0 }, // U60
-        LedCoordinate { x_led: 1988.0, y_led: 5255.0 }, // U61
-        LedCoordinate { x_led: 1853.0, y_led: 4836.0 }, // U62
-        LedCoordinate { x_led: 1784.0, y_led: 4407.0 }, // U63
-        LedCoordinate { x_led: 1779.0, y_led: 3971.0 }, // U64
-        LedCoordinate { x_led: 1605.0, y_led: 3569.0 }, // U65
-        LedCoordinate { x_led: 1211.0, y_led: 3375.0 }, // U66
-        LedCoordinate { x_led: 811.0, y_led: 3188.0 }, // U67
-        LedCoordinate { x_led: 710.0, y_led: 2755.0 }, // U68
-        LedCoordinate { x_led: 1116.0, y_led: 2595.0 }, // U69
-        LedCoordinate { x_led: 1529.0, y_led: 2717.0 }, // U70
-        LedCoordinate { x_led: 1947.0, y_led: 2848.0 }, // U71
-        LedCoordinate { x_led: 2371.0, y_led: 2946.0 }, // U72
-        LedCoordinate { x_led: 2806.0, y_led: 2989.0 }, // U73
-        LedCoordinate { x_led: 3239.0, y_led: 2946.0 }, // U74
-        LedCoordinate { x_led: 3665.0, y_led: 2864.0 }, // U75
-        LedCoordinate { x_led: 4092.0, y_led: 2791.0 }, // U76
-        LedCoordinate { x_led: 4523.0, y_led: 2772.0 }, // U77
-        LedCoordinate { x_led: 4945.0, y_led: 2886.0 }, // U78
-        LedCoordinate { x_led: 5331.0, y_led: 3087.0 }, // U79
-        LedCoordinate { x_led: 5703.0, y_led: 3315.0 }, // U80
-        LedCoordinate { x_led: 6105.0, y_led: 3484.0 }, // U81
-        LedCoordinate { x_led: 6538.0, y_led: 3545.0 }, // U82
-        LedCoordinate { x_led: 6969.0, y_led: 3536.0 }, // U83
-        LedCoordinate { x_led: 7402.0, y_led: 3511.0 }, // U84
-        LedCoordinate { x_led: 7831.0, y_led: 3476.0 }, // U85
-        LedCoordinate { x_led: 8241.0, y_led: 3335.0 }, // U86
-        LedCoordinate { x_led: 8549.0, y_led: 3025.0 }, // U87
-        LedCoordinate { x_led: 8703.0, y_led: 2612.0 }, // U88
-        LedCoordinate { x_led: 8662.0, y_led: 2173.0 }, // U89
-        LedCoordinate { x_led: 8451.0, y_led: 1785.0 }, // U90
-        LedCoordinate { x_led: 8203.0, y_led: 1426.0 }, // U91
-        LedCoordinate { x_led: 7973.0, y_led: 1053.0 }, // U92
-        LedCoordinate { x_led: 7777.0, y_led: 664.0 }, // U93
-        LedCoordinate { x_led: 7581.0, y_led: 275.0 }, // U94
-        LedCoordinate { x_led: 7274.0, y_led: -35.0 }, // U95
-        LedCoordinate { x_led: 6839.0, y_led: -46.0 }, // U96
-    ])
-}
-
-fn generate_run_race_data(
-    raw_data: &[LocationData],
-    coordinates: &[LedCoordinate],
-) -> Vec<RunRace> {
-    raw_data
-        .iter()
-        .map(|data| {
-            let (nearest_coord, _distance) = coordinates
-                .iter()
-                .map(|coord| {
-                    let distance =
-                        ((data.x - coord.x_led).powi(2) + (data.y - coord.y_led).powi(2)).sqrt();
-                    (coord, distance)
-                })
-                .min_by(|(_, dist_a), (_, dist_b)| {
-                    dist_a
-                        .partial_cmp(dist_b)
-                        .unwrap_or(std::cmp::Ordering::Equal)
+    // Loads the focused driver's car data the first time the pedal overlay or the telemetry
+    // chart needs it, and works out the LEDs their trail tints
+    fn update_pedals(&mut self) {
+        if self
+            .pedal_job
+            .as_ref()
+            .is_some_and(|(_, job)| job.is_finished())
+        {
+            self.finish_pedal_load();
+        }
+        self.pedal_trail.clear();
+        let Some(driver_number) = self
+            .focused_driver
+            .filter(|_| self.pedals.enabled || self.charts.show_telemetry)
+        else {
+            return;
+        };
+        if !self.pedal_traces.contains_key(&driver_number) && self.pedal_job.is_none() {
+            self.start_pedal_load(driver_number);
+        }
+        let date = self
+            .simulation
+            .race_date()
+            .filter(|_| self.simulation.is_running());
+        let (Some(trace), Some(date), true) = (
+            self.pedal_traces.get(&driver_number),
+            date,
+            self.pedals.enabled && self.pedals.trail,
+        ) else {
+            return;
+        };
+        let trail = trace.trail(date, self.pedals.trail_leds, &self.pedals);
+        self.pedal_trail.resize(self.coordinates.len(), None);
+        tint_trail(&mut self.pedal_trail, &trail);
+    }
+
+    fn start_pedal_load(&mut self, driver_number: u32) {
+        // A recording has no car data to fetch
+        let Some(source) = self.data_source.clone() else {
+            self.pedal_traces
+                .insert(driver_number, PedalTrace::default());
+            return;
+        };
+        let session_key = self.session.key.clone();
+        let window = self.mapping_stats.pipeline.window;
+        let records: Vec<RunRace> = self
+            .simulation
+            .run_race_data()
+            .iter()
+            .filter(|record| record.driver_number == driver_number)
+            .cloned()
+            .collect();
+        let led_count = self.coordinates.len();
+        let spawned = std::thread::Builder::new()
+            .name("car-data".to_string())
+            .spawn(move || {
+                let car_data = prepare_car_data(
+                    &source.api,
+                    &session_key,
+                    driver_number,
+                    &window,
+                    &source.cache_dir,
+                )?;
+                let trace = PedalTrace::new(driver_number, &records, &car_data, led_count);
+                Ok(CarDataLoad {
+                    trace,
+                    samples: car_data,
                 })
-                .unwrap();
+            });
+        match spawned {
+            Ok(job) => self.pedal_job = Some((driver_number, job)),
+            Err(err) => {
+                self.pedal_traces
+                    .insert(driver_number, PedalTrace::default());
+                self.push_toast(Toast::error(AppError::from(err).user_message()));
+            }
+        }
+    }
 
-            RunRace {
-                date: data.date,
-                driver_number: data.driver_number,
-                x_led: nearest_coord.x_led,
-                y_led: nearest_coord.y_led,
+    fn finish_pedal_load(&mut self) {
+        let Some((driver_number, job)) = self.pedal_job.take() else {
+            return;
+        };
+        let (trace, car_data) = match join_worker(job, "car data") {
+            Ok(CarDataLoad { trace, samples }) => {
+                if trace.is_empty() && samples.is_empty() {
+                    self.push_toast(Toast::info(format!(
+                        "No car data for driver {}",
+                        driver_number
+                    )));
+                }
+                (trace, samples)
             }
-        })
-        .collect()
+            // Not tried again for this driver until the session is loaded again
+            Err(err) => {
+                error!(
+                    "Could not load the car data of driver {}: {}",
+                    driver_number, err
+                );
+                self.push_toast(Toast::error(err.user_message()));
+                (PedalTrace::default(), Vec::new())
+            }
+        };
+        self.pedal_traces.insert(driver_number, trace);
+        self.car_data.insert(driver_number, car_data);
+    }
+
+    // The focused driver's throttle and brake as two bars filling up from the bottom
+    fn pedal_bars_ui(
+        &self,
+        ui: &mut egui::Ui,
+        driver_number: u32,
+        race_date: Option<DateTime<Utc>>,
+    ) {
+        let Some(trace) = self.pedal_traces.get(&driver_number) else {
+            if self.pedal_job.is_some() {
+                ui.label("Loading car data...");
+            }
+            return;
+        };
+        if trace.is_empty() {
+            ui.label("No car data");
+            return;
+        }
+        let sample = race_date.and_then(|date| trace.at(date));
+        let throttle = sample.map_or(0.0, |sample| sample.throttle);
+        let brake = if sample.is_some_and(|sample| sample.braking) {
+            100.0
+        } else {
+            0.0
+        };
+        ui.horizontal(|ui| {
+            for (label, percent, [r, g, b]) in [
+                ("THROTTLE", throttle, THROTTLE_COLOR),
+                ("BRAKE", brake, BRAKE_COLOR),
+            ] {
+                let (rect, response) = ui.allocate_exact_size(
+                    egui::vec2(PEDAL_BAR_WIDTH, PEDAL_BAR_HEIGHT),
+                    egui::Sense::hover(),
+                );
+                let level = (percent / 100.0).clamp(0.0, 1.0) as f32;
+                let filled = egui::Rect::from_min_max(
+                    egui::pos2(rect.min.x, rect.max.y - rect.height() * level),
+                    rect.max,
+                );
+                let painter = ui.painter();
+                painter.rect_filled(filled, 0.0, egui::Color32::from_rgb(r, g, b));
+                painter.rect_stroke(rect, 0.0, ui.visuals().widgets.noninteractive.bg_stroke);
+                response.on_hover_text(format!("{} {:.0}%", label, percent));
+            }
+        });
+    }
+
+    // Eases the view toward the focused driver's LED while following, and back out to the
+    // whole track once their data stops
+    fn update_camera(&mut self, dt: Duration) {
+        let date = self.simulation.race_date();
+        let target = self
+            .focused_driver
+            .filter(|&driver_number| {
+                !self.simulation.is_stale(driver_number)
+                    && !date.is_some_and(|date| {
+                        self.simulation
+                            .retirements()
+                            .is_retired(driver_number, date)
+                    })
+            })
+            .and_then(|driver_number| {
+                self.simulation
+                    .driver_leds()
+                    .find(|&(number, _)| number == driver_number)
+            })
+            .and_then(|(_, led_index)| self.view_coordinates.get(led_index).copied());
+        self.camera.update(target, &self.bounds, dt.as_secs_f64());
+    }
+
+    // Screen position of every LED: the track's through `viewport`, each other segment's
+    // fitted into its own box of `boxes`
+    fn led_positions(&self, viewport: &TrackViewport, boxes: &[egui::Rect]) -> Vec<egui::Pos2> {
+        let mut positions: Vec<egui::Pos2> = self
+            .view_coordinates
+            .iter()
+            .map(|coord| viewport.to_screen(*coord))
+            .collect();
+        let others = self.layout.segments()[1..].iter().zip(&self.segment_bounds);
+        for ((segment, bounds), rect) in others.zip(boxes) {
+            let viewport = TrackViewport::in_rect(*bounds, *rect, 10.0);
+            for led_index in segment.leds.clone() {
+                positions[led_index] = viewport.to_screen(self.view_coordinates[led_index]);
+            }
+        }
+        positions
+    }
+
+    // The name of each segment other than the track atop its box
+    fn segment_titles(&self, painter: &egui::Painter, boxes: &[egui::Rect]) {
+        for (segment, rect) in self.layout.segments()[1..].iter().zip(boxes) {
+            painter.line_segment(
+                [rect.left_top(), rect.right_top()],
+                egui::Stroke::new(1.0, egui::Color32::DARK_GRAY),
+            );
+            painter.text(
+                rect.left_top() + egui::vec2(8.0, 8.0),
+                egui::Align2::LEFT_TOP,
+                &segment.name,
+                egui::FontId::proportional(12.0),
+                egui::Color32::GRAY,
+            );
+        }
+    }
+
+    // Which session a pane of a split board shows, and how far into it the pane is
+    fn pane_title(
+        &self,
+        painter: &egui::Painter,
+        rect: egui::Rect,
+        session_key: &str,
+        simulation: &Simulation,
+    ) {
+        painter.text(
+            rect.left_top() + egui::vec2(8.0, 8.0),
+            egui::Align2::LEFT_TOP,
+            format!(
+                "Session {} · {}",
+                session_key,
+                format_duration(simulation.race_time())
+            ),
+            egui::FontId::proportional(14.0),
+            egui::Color32::GRAY,
+        );
+    }
 }
 
-fn deserialize_datetime<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let s: String = Deserialize::deserialize(deserializer)?;
-    DateTime::parse_from_rfc3339(&s)
-        .map_err(de::Error::custom)
-        .map(|dt| dt.with_timezone(&Utc))
+impl App for PlotApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
+        if ctx.input(|input| input.viewport().close_requested()) {
+            self.shut_down();
+            return;
+        }
+        if let Some(warning) = self.controls.apply(&mut self.simulation, &self.speed_range) {
+            self.push_toast(Toast::warning(warning));
+        }
+
+        // The clock keeps running in calibration mode; only the display is overridden
+        let now = Instant::now();
+        let due = self
+            .race_control
+            .scheduled_start
+            .and_then(|start| start.lateness(Local::now()));
+        if let Some(lateness) = due {
+            self.start_on_schedule(lateness);
+            // On time, the clock counts from the instant armed rather than from this frame
+            if lateness <= LATE_START_TOLERANCE {
+                self.last_update = now.checked_sub(lateness).unwrap_or(now);
+            }
+        }
+        self.follow_focus(ctx, now);
+        self.simulation.advance(now - self.last_update);
+        if let Some(pane) = &mut self.split.pane {
+            if self.split.config.ganged {
+                gang(&self.simulation, &mut pane.simulation);
+            }
+            pane.simulation.advance(now - self.last_update);
+        }
+        let events = self.simulation.take_race_events();
+        self.audio.play(&events);
+        self.controls.publish(&events);
+        if let Err(err) = self.controls.broadcast(&self.simulation) {
+            self.push_toast(Toast::error(err.user_message()));
+        }
+        if let Some(player) = &mut self.output_settings.test_pattern {
+            player.tick(now - self.last_update);
+        }
+        self.update_camera(now - self.last_update);
+        self.last_update = now;
+        self.update_pedals();
+        self.outputs.dispatch(self.output_frame());
+        // A failed output stops by itself; the others keep going
+        if let Some(err) = self.outputs.take_error() {
+            self.push_toast(Toast::error(err.user_message()));
+        }
+        let playing = self.simulation.state() == PlaybackState::Playing;
+        let clock_time = playing.then(|| self.simulation.clock_time());
+        if let Some(stall) = self.watchdog.check(clock_time, &self.outputs.queues(), now) {
+            let reason = stall.describe(self.watchdog_config.stall_secs);
+            warn!("{}", reason);
+            self.stall_warning = Some(match self.write_diagnostics(&reason) {
+                Ok(path) => format!("{}. Diagnostics written to {}", reason, path.display()),
+                Err(err) => {
+                    error!("Could not write the diagnostics: {}", err);
+                    format!("{}. The diagnostics could not be written", reason)
+                }
+            });
+        }
+
+        let race_date = self
+            .simulation
+            .race_date()
+            .filter(|_| self.simulation.is_running());
+        match race_date {
+            Some(date) => {
+                self.battles.update(
+                    self.simulation.timelines(),
+                    date,
+                    self.race_progress.as_deref(),
+                    &self.speed,
+                );
+            }
+            None => self.battles.clear(),
+        }
+        match (race_date, self.race_progress.as_deref()) {
+            (Some(date), Some(progress)) => {
+                self.trains.update(progress, date);
+            }
+            _ => self.trains.clear(),
+        }
+        self.log_summary(race_date);
+        if let (Some(deltas), Some(date), Some(progress)) = (
+            &mut self.position_deltas,
+            race_date,
+            self.race_progress.as_deref(),
+        ) {
+            deltas.update(
+                progress,
+                self.simulation.retirements(),
+                date,
+                Instant::now(),
+            );
+        }
+        let rivals = self.rivals.update(
+            self.simulation.track_progress(),
+            self.focused_driver,
+            self.race_progress.as_deref(),
+            race_date,
+        );
+
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Background,
+            egui::Id::new("layer"),
+        ));
+
+        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.separator();
+                self.race_control_ui(ui);
+                ui.separator();
+
+                self.brightness_ui(ui);
+                ui.separator();
+
+                self.volume_ui(ui);
+
+                self.led_display_ui(ui);
+                ui.separator();
+
+                self.board_check_ui(ui);
+                ui.separator();
+
+                self.view_ui(ui, ctx);
+                ui.separator();
+
+                ui.toggle_value(&mut self.show_diagnostics, "DIAGNOSTICS");
+
+                let mut recording = self.recording.is_some();
+                if ui.checkbox(&mut recording, "RECORD").changed() {
+                    self.toggle_recording();
+                }
+                ui.toggle_value(&mut self.exports.shown, "EXPORT");
+                if self.data_source.is_some() {
+                    ui.toggle_value(&mut self.ghost.shown, "GHOST");
+                    ui.toggle_value(&mut self.split.shown, "SPLIT");
+                    ui.toggle_value(&mut self.queue.shown, "PLAYLIST");
+                }
+                ui.toggle_value(&mut self.charts.show_delta, "DELTA");
+                ui.toggle_value(&mut self.charts.show_telemetry, "TELEMETRY");
+                if !self.controls.is_following() {
+                    ui.toggle_value(&mut self.race_control.show_speed_plan, "SPEED PLAN");
+                }
+                ui.toggle_value(&mut self.timing.show_sectors, "SECTORS");
+                if self.race_progress.is_some() {
+                    ui.toggle_value(&mut self.timing.show_laps, "LAPS");
+                    ui.toggle_value(&mut self.timing.show_strategy, "STRATEGY");
+                }
+                if self.timing.matrix_grid.is_some() {
+                    ui.toggle_value(&mut self.timing.show_matrix, "MATRIX");
+                }
+                if !self.simulation.run_race_data().is_empty() {
+                    self.heatmap_toggle_ui(ui);
+                }
+                if self.data_source.is_some() {
+                    let gear = ui.button("⚙").on_hover_text("Session settings");
+                    if gear.clicked() {
+                        self.toggle_settings();
+                    }
+                }
+                let copy = ui
+                    .button("📋")
+                    .on_hover_text("Copy a text summary of the race");
+                if copy.clicked() {
+                    let text = self.race_summary();
+                    ui.output_mut(|output| output.copied_text = text);
+                    self.push_toast(Toast::info("Copied the race summary".to_string()));
+                }
+                let camera = ui.button("📷").on_hover_text("Save a screenshot (F12)");
+                if camera.clicked() || ctx.input(|input| input.key_pressed(egui::Key::F12)) {
+                    self.take_screenshot(ctx);
+                }
+
+                self.status_ui(ui);
+            });
+        });
+
+        self.show_banner(ctx);
+        self.show_stall_warning(ctx);
+        self.confirm_stop_ui(ctx);
+
+        let mut export_occupancy_clicked = false;
+        let mut dump_clicked = false;
+        let mut snapshot_clicked = false;
+        let sections = if self.show_diagnostics {
+            self.diagnostics_sections()
+        } else {
+            Vec::new()
+        };
+        egui::Window::new("Diagnostics")
+            .open(&mut self.show_diagnostics)
+            .show(ctx, |ui| {
+                for (index, section) in sections.iter().enumerate() {
+                    if index > 0 {
+                        ui.separator();
+                    }
+                    for line in section {
+                        ui.label(line);
+                    }
+                }
+
+                ui.separator();
+                if ui.button("COPY").on_hover_text("Copy as text").clicked() {
+                    let text = sections
+                        .iter()
+                        .map(|section| section.join("\n"))
+                        .collect::<Vec<_>>()
+                        .join("\n\n");
+                    ui.output_mut(|output| output.copied_text = text);
+                }
+                dump_clicked = ui
+                    .button("DUMP DIAGNOSTICS NOW")
+                    .on_hover_text(
+                        "Write the playback state, outputs, errors and threads to a file",
+                    )
+                    .clicked();
+                // A recording can't be loaded again from the cache
+                snapshot_clicked = ui
+                    .add_enabled(
+                        self.data_source.is_some(),
+                        egui::Button::new("SNAPSHOT STATE"),
+                    )
+                    .on_hover_text(
+                        "Write the position, settings and frame to a file that --load-snapshot restores",
+                    )
+                    .clicked();
+                ui.separator();
+                // A recording has no driver data to count
+                let idle =
+                    self.exports.occupancy_job.is_none() && !self.simulation.run_race_data().is_empty();
+                export_occupancy_clicked = ui
+                    .add_enabled(idle, egui::Button::new("EXPORT OCCUPANCY"))
+                    .clicked();
+            });
+        if export_occupancy_clicked {
+            self.start_occupancy_export();
+        }
+        if snapshot_clicked {
+            let toast = match self.write_snapshot() {
+                Ok(path) => Toast::info(format!("Saved {}", path.display())),
+                Err(err) => {
+                    error!("Could not write the state snapshot: {}", err);
+                    Toast::error(err.user_message())
+                }
+            };
+            self.push_toast(toast);
+        }
+        if dump_clicked {
+            let toast = match self.write_diagnostics("Requested from the diagnostics window") {
+                Ok(path) => Toast::info(format!("Saved {}", path.display())),
+                Err(err) => {
+                    error!("Could not write the diagnostics: {}", err);
+                    Toast::error(err.user_message())
+                }
+            };
+            self.push_toast(toast);
+        }
+        self.poll_exports();
+        self.test_pattern_window(ctx);
+
+        self.export_window(ctx);
+
+        self.ghost_window(ctx);
+
+        self.split_window(ctx);
+
+        if self
+            .reload_job
+            .as_ref()
+            .is_some_and(|job| job.handle.is_finished())
+        {
+            self.finish_reload();
+        }
+        self.update_playlist(ctx);
+        self.playlist_window(ctx);
+        self.settings_window(ctx);
+
+        self.timing_windows(ctx, race_date);
+
+        self.telemetry_windows(ctx);
+
+        self.speed_plan_window(ctx);
+
+        egui::SidePanel::right("legend_panel").show(ctx, |ui| {
+            ui.vertical(|ui| {
+                let style = ui.style_mut();
+                style
+                    .text_styles
+                    .get_mut(&egui::TextStyle::Body)
+                    .unwrap()
+                    .size = 8.0; // Set the font size to 8.0 (or any other size you prefer)
+
+                egui::ComboBox::from_id_source("legend_order")
+                    .selected_text(format!("Sort: {}", self.legend_order.label()))
+                    .show_ui(ui, |ui| {
+                        for order in LegendOrder::ALL {
+                            ui.selectable_value(&mut self.legend_order, order, order.label());
+                        }
+                    });
+                let mut color_scheme = self.simulation.color_scheme();
+                let context = self.simulation.scheme_context();
+                egui::ComboBox::from_id_source("color_scheme")
+                    .selected_text(format!("Colors: {}", color_scheme.label()))
+                    .show_ui(ui, |ui| {
+                        for scheme in ColorSchemeKind::ALL {
+                            let missing = scheme.missing(&context);
+                            let response = ui
+                                .add_enabled(
+                                    missing.is_none(),
+                                    egui::SelectableLabel::new(
+                                        color_scheme == scheme,
+                                        scheme.label(),
+                                    ),
+                                )
+                                .on_disabled_hover_text(format!(
+                                    "Needs {}, which weren't fetched for this session",
+                                    missing.map_or("", |data| data.label())
+                                ));
+                            if response.clicked() {
+                                color_scheme = scheme;
+                            }
+                        }
+                    });
+                if color_scheme != self.simulation.color_scheme() {
+                    self.simulation.set_color_scheme(color_scheme);
+                }
+                ui.toggle_value(&mut self.legend_compact, "CODES")
+                    .on_hover_text("Show three-letter codes instead of full names");
+                let compact = self.legend_compact || ui.available_width() < LEGEND_COMPACT_WIDTH;
+                self.update_legend_rows(race_date);
+
+                let mut toggled = Vec::new();
+                let mut focus_clicked = None;
+                for &driver_number in &self.legend_rows {
+                    let Some(driver) = self
+                        .driver_info
+                        .iter()
+                        .find(|driver| driver.number == driver_number)
+                    else {
+                        continue;
+                    };
+                    ui.horizontal(|ui| {
+                        let mut visible = !self.simulation.is_driver_hidden(driver.number);
+                        if ui.checkbox(&mut visible, "").changed() {
+                            toggled.push(driver.number);
+                        }
+                        let full_name =
+                            format!("{}: {} ({})", driver.number, driver.name, driver.team);
+                        let mut text = if compact {
+                            driver.code.to_string()
+                        } else {
+                            full_name.clone()
+                        };
+                        let speed = race_date.and_then(|date| {
+                            self.simulation
+                                .timelines()
+                                .speed_at(driver.number, date, &self.speed)
+                        });
+                        if let (Some(speed), false) = (speed, compact) {
+                            text.push_str(&format!(" {:.0} {}", speed, self.speed.unit.label()));
+                        }
+                        let delta = match (&self.position_deltas, self.race_progress.as_deref()) {
+                            (Some(deltas), Some(progress)) => race_date.and_then(|date| {
+                                deltas.delta(
+                                    progress,
+                                    self.simulation.retirements(),
+                                    driver.number,
+                                    date,
+                                )
+                            }),
+                            _ => None,
+                        };
+                        if let Some(delta) = delta {
+                            text.push_str(&format!(" {}", format_position_delta(delta)));
+                        }
+                        if let Some(best_lap) = self
+                            .best_laps
+                            .iter()
+                            .find(|best_lap| best_lap.driver_number == driver.number)
+                        {
+                            text.push_str(&format!(
+                                " {}",
+                                format_lap_time(Some(best_lap.lap_duration))
+                            ));
+                        }
+                        let retired = race_date.is_some_and(|date| {
+                            self.simulation
+                                .retirements()
+                                .is_retired(driver.number, date)
+                        });
+                        // Compact rows are only greyed out, the tooltip says why
+                        let status = if retired {
+                            Some("DNF")
+                        } else if self.simulation.is_stale(driver.number) {
+                            Some("NO DATA")
+                        } else if self.is_off_track(driver.number) {
+                            Some("OFF TRACK")
+                        } else {
+                            None
+                        };
+                        let (mut label, mut hover) = match status {
+                            Some(status) if compact => (
+                                egui::RichText::new(text).weak(),
+                                format!("{} {}", full_name, status),
+                            ),
+                            Some(status) => (
+                                egui::RichText::new(format!("{} {}", text, status)).weak(),
+                                format!("{} {}", full_name, status),
+                            ),
+                            None => (egui::RichText::new(text), full_name),
+                        };
+                        if let Some(progress) =
+                            self.simulation.track_progress().driver(driver.number)
+                        {
+                            hover.push_str(&format!(
+                                "\n{:.1}% round the lap, {} laps",
+                                progress.percent, progress.laps
+                            ));
+                        }
+                        let focused = self.focused_driver == Some(driver.number);
+                        if focused {
+                            label = label.underline();
+                        }
+                        // Green when the driver just gained a place, red when they lost one
+                        if let Some((gained, strength)) = self
+                            .position_deltas
+                            .as_ref()
+                            .and_then(|deltas| deltas.flash(driver.number, Instant::now()))
+                        {
+                            let color = if gained {
+                                egui::Color32::GREEN
+                            } else {
+                                egui::Color32::RED
+                            };
+                            label = label.background_color(color.gamma_multiply(strength * 0.5));
+                        }
+                        hover.push_str(if focused {
+                            "\nClick to stop following"
+                        } else {
+                            "\nClick to mark the nearest cars"
+                        });
+                        let response = ui
+                            .add(egui::Label::new(label).sense(egui::Sense::click()))
+                            .on_hover_text(hover);
+                        if response.clicked() {
+                            focus_clicked = Some(driver.number);
+                        }
+                        let [r, g, b] = self.simulation.driver_color(driver.number);
+                        ui.painter().rect_filled(
+                            egui::Rect::from_min_size(ui.cursor().min, egui::vec2(5.0, 5.0)),
+                            0.0,
+                            egui::Color32::from_rgb(r, g, b),
+                        );
+                        ui.add_space(5.0); // Space between legend items
+                    });
+                }
+
+                for driver_number in toggled {
+                    let hidden = self.simulation.is_driver_hidden(driver_number);
+                    self.simulation.set_driver_hidden(driver_number, !hidden);
+                    self.remember_hidden_drivers();
+                }
+                if let Some(driver_number) = focus_clicked {
+                    self.focused_driver =
+                        (self.focused_driver != Some(driver_number)).then_some(driver_number);
+                }
+
+                if let Some(focused) = self.focused_driver {
+                    ui.separator();
+                    ui.label(format!("RIVALS OF {}", focused));
+                    for (direction, rival) in [("Ahead", rivals.ahead), ("Behind", rivals.behind)] {
+                        if let Some(rival) = rival {
+                            ui.label(format!("{}: {}", direction, format_rival(&rival)));
+                        }
+                    }
+                    if self.pedals.enabled {
+                        self.pedal_bars_ui(ui, focused, race_date);
+                    }
+                    ui.horizontal(|ui| {
+                        let mut following = self.camera.is_following();
+                        if ui
+                            .toggle_value(&mut following, "FOLLOW")
+                            .on_hover_text("Keep the view on them; dragging the view stops it")
+                            .changed()
+                        {
+                            self.camera.set_following(following);
+                        }
+                        ui.add(
+                            egui::DragValue::new(&mut self.camera.follow_zoom)
+                                .clamp_range(1.0..=MAX_ZOOM)
+                                .speed(0.1)
+                                .suffix("x"),
+                        )
+                        .on_hover_text("How far in to zoom while following");
+                    });
+                }
+
+                if !self.battles.battles().is_empty() {
+                    ui.separator();
+                    ui.label("BATTLES");
+                    for battle in self.battles.battles() {
+                        let (first, second) = battle.drivers;
+                        let gap = match battle.gap_secs {
+                            Some(gap_secs) => format!(" ({:.1}s)", gap_secs),
+                            None => String::new(),
+                        };
+                        ui.label(format!(
+                            "{} - {}: {:.0} m{}",
+                            first, second, battle.distance_m, gap
+                        ));
+                    }
+                }
+
+                if !self.trains.trains().is_empty() {
+                    ui.separator();
+                    ui.label("TRAINS");
+                    for train in self.trains.trains() {
+                        let leader = train.drivers[0];
+                        let leader = self
+                            .driver_info
+                            .iter()
+                            .find(|driver| driver.number == leader)
+                            .map_or(leader.to_string(), |driver| driver.name.clone());
+                        ui.label(format!(
+                            "P{}–P{}, {} cars, led by {}",
+                            train.first_position,
+                            train.last_position,
+                            train.drivers.len(),
+                            leader
+                        ));
+                    }
+                }
+
+                if !self.simulation.lapped().is_empty() {
+                    ui.separator();
+                    ui.label("BLUE FLAGS");
+                    let lapped: Vec<String> = self
+                        .simulation
+                        .lapped()
+                        .iter()
+                        .map(u32::to_string)
+                        .collect();
+                    ui.label(format!("About to be lapped: {}", lapped.join(", ")))
+                        .on_hover_text("Their LEDs pulse blue while the leader closes in");
+                }
+
+                let mut overtakes = self.simulation.overtakes().recent().take(5).peekable();
+                if overtakes.peek().is_some() {
+                    ui.separator();
+                    ui.label("OVERTAKES");
+                    for overtake in overtakes {
+                        ui.label(format!(
+                            "{} passed {} for P{}",
+                            overtake.driver_number, overtake.overtaken, overtake.position
+                        ));
+                    }
+                }
+            });
+        });
+
+        egui::TopBottomPanel::bottom("timeline").show(ctx, |ui| self.timeline_ui(ui));
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            if self.reload_job.is_some() {
+                ui.vertical_centered(|ui| {
+                    ui.spinner();
+                    ui.label(format!("Loading session {}...", self.session.key));
+                    // Until the fetch starts, or when the data comes from the cache, it's unknown
+                    if let Some((_, window)) = self
+                        .output_settings
+                        .status
+                        .fetching
+                        .as_ref()
+                        .filter(|(key, _)| *key == self.session.key)
+                    {
+                        ui.label(format!("Fetching {}", format_window(window)));
+                    }
+                });
+                return;
+            }
+            // E.g. every sample was off track; the board still shows, all dark
+            if self.simulation.record_count() == 0 {
+                ui.vertical_centered(|ui| {
+                    ui.label(format!("No data loaded for session {}", self.session.key));
+                });
+            }
+            self.track_size = ui.available_size();
+            let board = egui::Rect::from_min_size(egui::Pos2::ZERO, self.track_size);
+            let panes = split_panes(board, if self.split.pane.is_some() { 2 } else { 1 });
+            let (track_rect, segment_rects) = segment_regions(panes[0], self.segment_bounds.len());
+            let viewport = TrackViewport::in_rect(self.bounds, track_rect, 30.0)
+                .with_camera(self.camera.camera());
+            // Dragging pans, the wheel zooms and a double click shows the whole track again
+            let response = ui.interact(
+                panes[0],
+                ui.id().with("track_view"),
+                egui::Sense::click_and_drag(),
+            );
+            if response.dragged() {
+                let (dx, dy) = viewport.layout_delta(response.drag_delta());
+                self.camera.pan(-dx, -dy);
+            }
+            if response.hovered() {
+                let scrolled = ctx.input(|input| input.scroll_delta.y);
+                if scrolled != 0.0 {
+                    self.camera
+                        .zoom_by((scrolled as f64 * ZOOM_PER_POINT).exp());
+                }
+            }
+            if response.double_clicked() {
+                self.camera.reset(&self.bounds);
+            }
+            // Zoomed in, the played session stays out of the other pane
+            let painter = if self.split.pane.is_some() {
+                painter.with_clip_rect(panes[0])
+            } else {
+                painter.clone()
+            };
+
+            let positions = self.led_positions(&viewport, &segment_rects);
+            self.segment_titles(&painter, &segment_rects);
+            let colors = match &*self.shown_frame.lock().unwrap() {
+                Some(frame) => frame.dimmed(),
+                None => Vec::new(),
+            };
+            // A faint glow under the LEDs along each DRS train
+            let driver_leds: HashMap<u32, usize> = self.simulation.driver_leds().collect();
+            let glow = egui::Color32::WHITE.gamma_multiply(TRAIN_GLOW);
+            for train in self.trains.trains() {
+                let leds = train.leds(
+                    |driver_number| driver_leds.get(&driver_number).copied(),
+                    positions.len(),
+                );
+                for led_index in leds {
+                    let center = positions[led_index]
+                        + egui::vec2(self.display.led_size, self.display.led_size) / 2.0;
+                    painter.circle_filled(center, self.display.led_size, glow);
+                }
+            }
+            let shades = match &self.display.elevation_shades {
+                Some(shades) if self.display.elevation_shading => shades.as_slice(),
+                _ => &[],
+            };
+            painter.extend(led_shapes(
+                self.display.led_style,
+                &positions,
+                &colors,
+                shades,
+                self.display.led_size,
+                self.display.glow_radius,
+                ui.clip_rect(),
+            ));
+            painter.extend(hatch_shapes(
+                &self.led_mask,
+                &positions,
+                self.display.led_size,
+            ));
+
+            // A white outline pulsing around each battling car
+            let phase = ctx.input(|input| input.time) * BATTLE_PULSE_HZ * std::f64::consts::TAU;
+            let outline = egui::Color32::WHITE.gamma_multiply(0.6 + 0.4 * phase.sin() as f32);
+            for (driver_number, led_index) in self.simulation.driver_leds() {
+                if !self.battles.is_battling(driver_number) {
+                    continue;
+                }
+                let Some(&position) = positions.get(led_index) else {
+                    continue;
+                };
+                painter.rect_stroke(
+                    egui::Rect::from_min_size(
+                        position,
+                        egui::vec2(self.display.led_size, self.display.led_size),
+                    )
+                    .expand(2.0),
+                    egui::Rounding::same(0.0),
+                    egui::Stroke::new(2.0, outline),
+                );
+            }
+
+            // A thin line from the focused car to each nearest rival, which gets a ring
+            let led_center = |led_index: usize| {
+                let min = *positions.get(led_index)?;
+                Some(min + egui::vec2(self.display.led_size, self.display.led_size) / 2.0)
+            };
+            let focused_led = self
+                .focused_driver
+                .and_then(|driver_number| self.simulation.track_progress().led(driver_number));
+            if let Some(from) = focused_led.and_then(led_center) {
+                let stroke = egui::Stroke::new(1.0, egui::Color32::WHITE.gamma_multiply(0.7));
+                for rival in [rivals.ahead, rivals.behind].into_iter().flatten() {
+                    let Some(to) = self
+                        .simulation
+                        .track_progress()
+                        .led(rival.driver_number)
+                        .and_then(led_center)
+                    else {
+                        continue;
+                    };
+                    painter.line_segment([from, to], stroke);
+                    painter.circle_stroke(to, self.display.led_size, stroke);
+                }
+            }
+
+            // The ghost as an outline in its driver's color at half strength
+            let ghost_led = match (&mut self.ghost.run, race_date) {
+                (Some(ghost), Some(date)) if self.ghost.config.enabled => ghost.led_at(date),
+                _ => None,
+            };
+            if let (Some(ghost), Some(&position)) = (
+                &self.ghost.run,
+                ghost_led.and_then(|index| positions.get(index)),
+            ) {
+                let color = self
+                    .driver_info
+                    .iter()
+                    .find(|driver| driver.number == ghost.driver_number())
+                    .map_or(egui::Color32::WHITE, |driver| driver.color);
+                painter.rect_stroke(
+                    egui::Rect::from_min_size(
+                        position,
+                        egui::vec2(self.display.led_size, self.display.led_size),
+                    )
+                    .shrink(1.0),
+                    egui::Rounding::same(0.0),
+                    egui::Stroke::new(2.0, color.gamma_multiply(0.5)),
+                );
+            }
+
+            // Flag panels without an LED of their own, as spots by the track
+            if let Some(date) = self
+                .simulation
+                .race_date()
+                .filter(|_| self.simulation.is_running())
+            {
+                let center = centroid(&self.coordinates);
+                let blink_on = FlagPanels::blink_on(date);
+                for (panel, state) in self.simulation.flag_panels().states(date) {
+                    let (Some(spot), None) = (panel.spot(), panel.led) else {
+                        continue;
+                    };
+                    let spot = viewport.to_screen(self.view_transform.apply(spot, center))
+                        + egui::vec2(self.display.led_size, self.display.led_size) / 2.0;
+                    if let Some([r, g, b]) = state.color(blink_on) {
+                        painter.circle_filled(
+                            spot,
+                            self.display.led_size,
+                            egui::Color32::from_rgb(r, g, b),
+                        );
+                    }
+                    painter.circle_stroke(
+                        spot,
+                        self.display.led_size,
+                        egui::Stroke::new(1.0, egui::Color32::GRAY),
+                    );
+                }
+            }
+
+            if let (Some(pane), Some(&rect)) = (&self.split.pane, panes.get(1)) {
+                self.split_pane_ui(&painter, pane, rect, ui.clip_rect());
+                self.pane_title(&painter, panes[0], &self.session.key, &self.simulation);
+            }
+        });
+
+        // Input repaints immediately anyway; these only drive the clock and pick up outside
+        // commands. A finished race shows a static board, so without outside controls it needs
+        // no repaints at all
+        if !self.output_settings.calibration_mode {
+            match self.simulation.state() {
+                PlaybackState::Playing => ctx.request_repaint_after(PLAYING_REPAINT_INTERVAL),
+                _ if self.controls.is_active() => ctx.request_repaint_after(INPUT_POLL_INTERVAL),
+                PlaybackState::Stopped | PlaybackState::Paused => {
+                    ctx.request_repaint_after(IDLE_REPAINT_INTERVAL)
+                }
+                PlaybackState::Finished => {}
+            }
+        }
+        if !self.battles.battles().is_empty() || self.output_settings.test_pattern.is_some() {
+            ctx.request_repaint_after(PLAYING_REPAINT_INTERVAL); // Keeps the outlines pulsing
+        }
+        if self.camera.is_following() {
+            ctx.request_repaint_after(PLAYING_REPAINT_INTERVAL); // Eases on while paused too
+        }
+        if let Some(start) = self.race_control.scheduled_start {
+            // Next time the countdown changes, which is the start itself in its last second
+            let remaining = start.remaining(Local::now()).as_secs_f64();
+            ctx.request_repaint_after(Duration::from_secs_f64(remaining.fract()));
+        }
+        if self.exports.job.is_some()
+            || self.exports.occupancy_job.is_some()
+            || self.exports.lap_chart_job.is_some()
+            || self.ghost.job.is_some()
+            || self.split.job.is_some()
+            || self.exports.heatmap_job.is_some()
+            || self.exports.parquet_job.is_some()
+            || self.exports.bundle_job.is_some()
+            || self.reload_job.is_some()
+            || self.queue.prefetch_finished().is_some()
+        {
+            ctx.request_repaint_after(EXPORT_REPAINT_INTERVAL);
+        }
+        self.title_card_ui(ctx);
+        self.show_toasts(ctx);
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, UI_PREFS_KEY, &self.ui_prefs());
+    }
+}
+
+// Waits for a finished worker thread, turning a panic into an export error
+fn join_worker<T>(job: JoinHandle<Result<T, AppError>>, name: &str) -> Result<T, AppError> {
+    job.join().unwrap_or_else(|_| {
+        Err(AppError::Export {
+            reason: format!("the {} thread panicked", name),
+        })
+    })
+}
+
+// A time window like "2023-08-27 12:59–15:31 UTC"; open ends show as "…"
+fn format_window(window: &TimeWindow) -> String {
+    let time = |time: Option<DateTime<Utc>>, format: &str| {
+        time.map_or("…".to_string(), |time| time.format(format).to_string())
+    };
+    let same_day = matches!((window.start, window.end), (Some(start), Some(end)) if start.date_naive() == end.date_naive());
+    format!(
+        "{}–{} UTC",
+        time(window.start, "%Y-%m-%d %H:%M"),
+        time(
+            window.end,
+            if same_day { "%H:%M" } else { "%Y-%m-%d %H:%M" }
+        )
+    )
+}
+
+// A rival and the gap to them, like "44 1.2s"; an estimated gap is marked with a "~", and
+// without a lap time to estimate it from the LEDs between the cars are given instead
+fn format_rival(rival: &Rival) -> String {
+    match (rival.gap_secs, rival.source) {
+        (Some(gap_secs), GapSource::Intervals) => {
+            format!("{} {:.1}s", rival.driver_number, gap_secs)
+        }
+        (Some(gap_secs), GapSource::Estimate) => {
+            format!("{} ~{:.1}s", rival.driver_number, gap_secs)
+        }
+        (None, _) => format!("{} {} LEDs", rival.driver_number, rival.leds),
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match CliArgs::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        // --help, which prints to stdout
+        Err(err) if !err.use_stderr() => {
+            let _ = err.print();
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            let _ = err.print();
+            let reason = err.kind().to_string();
+            return ExitCode::from(AppError::Config { reason }.exit_code());
+        }
+    };
+
+    // --verbose raises the default level to debug; RUST_LOG still takes precedence
+    let mut logger = env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or(if args.verbose { "debug" } else { "info" }),
+    );
+    // The terminal view takes the terminal, so the log goes to a file instead
+    if args.tui {
+        match File::create(TUI_LOG_FILE) {
+            Ok(file) => {
+                logger.target(env_logger::Target::Pipe(Box::new(file)));
+            }
+            Err(err) => eprintln!("Could not create {}, logging here: {}", TUI_LOG_FILE, err),
+        }
+    }
+    logger.init();
+    install_panic_blanking();
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            error!("{}", err);
+            eprintln!("{}", err.user_message());
+            ExitCode::from(err.exit_code())
+        }
+    }
+}
+
+// Loads the config file and applies the command line overrides on top
+fn load_config(args: &CliArgs) -> Result<Config, AppError> {
+    let mut config = Config::load(args.config.as_deref())?;
+    if let Some(session_key) = &args.session_key {
+        config.session.key = session_key.clone();
+    }
+    if let Some(speed) = args.speed {
+        config.playback.speed = speed;
+        config.mark_set("playback.speed");
+    }
+    if let Some(brightness) = args.brightness {
+        config.display.brightness = brightness;
+        config.mark_set("display.brightness");
+    }
+    if let Some(cache_dir) = &args.cache_dir {
+        config.cache.dir = cache_dir.clone();
+    }
+    if let Some(path) = &args.record {
+        config.recorder.enabled = true;
+        config.recorder.path = path.clone();
+    }
+
+    if config.playback.min_speed <= 0.0 {
+        return Err(AppError::Config {
+            reason: format!(
+                "playback.min_speed {} must be above 0",
+                config.playback.min_speed
+            ),
+        });
+    }
+    if config.playback.min_speed > config.playback.max_speed {
+        return Err(AppError::Config {
+            reason: format!(
+                "playback.min_speed {} is above playback.max_speed {}",
+                config.playback.min_speed, config.playback.max_speed
+            ),
+        });
+    }
+    config.playback.speed = config
+        .playback
+        .speed
+        .clamp(config.playback.min_speed, config.playback.max_speed);
+    config.display.brightness = config.display.brightness.clamp(0.0, 1.0);
+    config.display.night_brightness = config.display.night_brightness.clamp(0.0, 1.0);
+    Ok(config)
+}
+
+fn run(args: &CliArgs) -> Result<(), AppError> {
+    if args.check {
+        return run_check(args);
+    }
+    let mut config = load_config(args)?;
+    let color_overrides = color_overrides(&config.colors)?;
+    // A snapshot brings its session and settings along, so the same data comes from the cache
+    let snapshot = match &args.load_snapshot {
+        Some(path) => {
+            let snapshot = StateSnapshot::read(path)?;
+            config.session = snapshot.dataset.session.clone();
+            config.mapping = snapshot.settings.mapping.clone();
+            config.qualifying = snapshot.settings.qualifying.clone();
+            config.display.brightness = snapshot.settings.brightness;
+            Some(snapshot)
+        }
+        None => None,
+    };
+
+    let layout = Layout::new(&config.layout, read_coordinates()?)?;
+    let coordinates = layout.coordinates().to_vec();
+    if coordinates.is_empty() {
+        return Err(AppError::LayoutInvalid {
+            reason: "the layout has no LEDs".to_string(),
+        });
+    }
+    // A bundle brings its session along, which is then what's shown and exported
+    let mut bundle = match &args.bundle {
+        Some(path) => {
+            let bundle = open_bundle(path, &layout)?;
+            config.session = bundle.session.clone();
+            config.mapping = bundle.mapping_options.clone();
+            Some(bundle)
+        }
+        None => None,
+    };
+    // Neither a bundle nor a snapshot brings the pit segments along
+    config.mapping.pit_leds = layout.pit_leds();
+    let session_name = match &args.play {
+        Some(path) => path
+            .file_stem()
+            .map_or("replay".into(), |stem| stem.to_string_lossy().into_owned()),
+        None => config.session.label(),
+    };
+    // A window missing the session would fetch nothing; a long one was configured on purpose
+    let window_problem = match &args.play {
+        Some(_) => None,
+        None if args.test_pattern.is_some() || bundle.is_some() => None,
+        None => match check_session_window(&config.api, &config.session) {
+            Err(WindowProblem::TooLong { hours }) => {
+                warn!("Fetching {:.1} hours of data as configured", hours);
+                None
+            }
+            result => result.err(),
+        },
+    };
+    // Ghosts and reloads fetch other sessions too
+    let source = DataSource {
+        api: config.api.clone(),
+        mapping: config.mapping.clone(),
+        cache_dir: config.cache.dir.clone(),
+        coordinates: coordinates.clone(),
+        roster: config.roster.clone(),
+    };
+    if let (Some(problem), false) = (&window_problem, args.command == Command::Simulate) {
+        return Err(AppError::Config {
+            reason: problem.message(),
+        });
+    }
+    let output = args.output.as_deref();
+    match args.command {
+        Command::Simulate => {}
+        Command::Fetch => return run_fetch(&config, &source),
+        Command::Map => return run_map(&config, &source, bundle, &color_overrides, output),
+        Command::Export(format) => {
+            return run_export(&config, &source, bundle, &color_overrides, format, output)
+        }
+    }
+
+    let mut setup_error = None;
+    let (mut simulation, driver_info, mapping_stats, made_up_drivers) =
+        match (&args.play, &mut bundle) {
+            (Some(path), _) => (
+                load_replay(path, &coordinates)?,
+                Vec::new(),
+                MappingStats::default(),
+                Vec::new(),
+            ),
+            (None, Some(bundle)) => {
+                let simulation = Simulation::new(
+                    std::mem::take(&mut bundle.run_race_data),
+                    coordinates.len(),
+                    driver_colors(&bundle.driver_info),
+                );
+                (
+                    simulation,
+                    bundle.driver_info.clone(),
+                    std::mem::take(&mut bundle.mapping_stats),
+                    Vec::new(),
+                )
+            }
+            (None, None) if args.test_pattern.is_some() => {
+                let driver_info = roster(&config.roster, &color_overrides);
+                let simulation =
+                    Simulation::new(Vec::new(), coordinates.len(), driver_colors(&driver_info));
+                (simulation, driver_info, MappingStats::default(), Vec::new())
+            }
+            (None, None) if window_problem.is_some() => {
+                let err = AppError::Config {
+                    reason: window_problem
+                        .as_ref()
+                        .map_or(String::new(), WindowProblem::message),
+                };
+                if args.headless {
+                    return Err(err);
+                }
+                let driver_info = roster(&config.roster, &color_overrides);
+                let simulation =
+                    Simulation::new(Vec::new(), coordinates.len(), driver_colors(&driver_info));
+                setup_error = Some(err.user_message());
+                (simulation, driver_info, MappingStats::default(), Vec::new())
+            }
+            (None, None) => match prepare_simulation(&config, &coordinates, &color_overrides) {
+                Ok(prepared) => prepared,
+                // The window shows why and can try again; without one there's nothing to play
+                Err(err) if !args.headless => {
+                    error!("Could not load session {}: {}", config.session.key, err);
+                    let driver_info = roster(&config.roster, &color_overrides);
+                    let simulation =
+                        Simulation::new(Vec::new(), coordinates.len(), driver_colors(&driver_info));
+                    setup_error = Some(err.user_message());
+                    (simulation, driver_info, MappingStats::default(), Vec::new())
+                }
+                Err(err) => return Err(err),
+            },
+        };
+
+    // A recording holds calibrated colors already
+    let calibration = match &args.play {
+        Some(_) => vec![LedCalibration::default(); coordinates.len()],
+        None => load_calibration(&config.calibration, coordinates.len())?,
+    };
+
+    let mask_file = match &args.play {
+        Some(_) => None,
+        None => config
+            .calibration
+            .mask_file
+            .as_deref()
+            .or_else(|| MASK_FILES.iter().map(Path::new).find(|path| path.exists())),
+    };
+    let led_mask = match mask_file {
+        Some(path) => {
+            let mask = read_mask(path, &coordinates)?;
+            info!("Masking broken LEDs from {}", path.display());
+            mask
+        }
+        None => LedMask::default(),
+    };
+
+    simulation.set_speed(config.playback.speed);
+
+    // A recording has nothing to do with other sessions
+    let data_source = args.play.is_none().then_some(source);
+
+    // Laps and positions feed the lap chart and the overtake animations; a recording has neither
+    let race_progress = match bundle {
+        Some(bundle) => bundle.race_progress,
+        None => data_source
+            .as_ref()
+            .filter(|_| setup_error.is_none() && args.test_pattern.is_none())
+            .and_then(|source| {
+                load_race_progress(
+                    source,
+                    &config.session,
+                    &session_drivers(&config.session, &driver_info),
+                )
+            }),
+    };
+    let best_laps = apply_qualifying_mode(
+        &mut simulation,
+        race_progress.as_ref(),
+        &config.qualifying,
+        &driver_info,
+    );
+    simulation.set_speed(config.playback.speed);
+    // The best laps run on clocks of their own, which nothing dated in the session lines up with
+    let session_progress = race_progress.as_ref().filter(|_| best_laps.is_empty());
+    if let (Some(progress), true) = (session_progress, config.overtakes.enabled) {
+        let overtakes = detect_overtakes(
+            progress,
+            simulation.timelines(),
+            &config.overtakes,
+            config.speed.meters_per_unit,
+        );
+        info!("Found {} overtakes", overtakes.len());
+        simulation.set_overtakes(overtakes, config.overtakes.history);
+    }
+    if let Some(progress) = session_progress {
+        let timeline = FlagTimeline::new(progress.race_control(), &config.flag_panels);
+        let flag_panels = FlagPanels::new(&config.flag_panels, timeline, coordinates.len());
+        simulation.set_flag_panels(flag_panels);
+    }
+    simulation.set_grid_formation(grid_formation(
+        session_progress,
+        coordinates.len(),
+        &config.formation,
+    ));
+    let race_progress = race_progress.map(Arc::new);
+    simulation.set_race_progress(race_progress.clone().filter(|_| best_laps.is_empty()));
+    if best_laps.is_empty() {
+        let retirements = Retirements::new(simulation.timelines(), &config.retirements);
+        simulation.set_retirements(retirements);
+    }
+    simulation.set_show_retired(config.retirements.keep_last_led);
+    simulation.set_stale_secs(config.retirements.stale_secs);
+    simulation.set_blue_flags(&config.blue_flags);
+    simulation.set_led_decay(&config.led_decay);
+    simulation.set_led_dwell(&config.led_dwell);
+    simulation.set_positioning(config.mapping.positioning, &coordinates);
+    simulation.set_driver_teams(driver_teams(&driver_info));
+    let snapshot_warning = snapshot.as_ref().and_then(|snapshot| {
+        let mismatches = snapshot.mismatches(&coordinates, simulation.run_race_data());
+        (!mismatches.is_empty()).then(|| {
+            format!(
+                "The snapshot was taken of other data: {} changed since, so it may not show the same frame",
+                mismatches.join(" and ")
+            )
+        })
+    });
+    if let Some(warning) = &snapshot_warning {
+        warn!("{}", warning);
+    }
+
+    let mut outputs = FrameDispatcher::new();
+    if let Some(schedule) = config.display.night_schedule() {
+        info!(
+            "Dimming to {:.0}% from {} to {}",
+            schedule.brightness * 100.0,
+            schedule.start.format("%H:%M"),
+            schedule.end.format("%H:%M")
+        );
+        outputs.set_night_dimmer(Some(NightDimmer::new(schedule)));
+    }
+    let (wled_status, race_events) = register_outputs(&config, &layout, &mut outputs)?;
+    let recording = if config.recorder.enabled {
+        Some(start_recording(
+            &config.recorder,
+            &coordinates,
+            &mut outputs,
+        )?)
+    } else {
+        None
+    };
+
+    let mut controls = start_controls(&config, &simulation, coordinates.len(), &mut outputs)?;
+    controls.race_events = race_events;
+
+    if let (true, Some(pattern)) = (args.headless, args.test_pattern) {
+        let player = PatternPlayer::new(pattern, coordinates.len(), &config.test_patterns);
+        return run_test_pattern(player, config.display.brightness, outputs);
+    }
+    // Without a window there's nobody to resume it, so it plays on from where it was taken
+    if let (true, Some(snapshot)) = (args.headless, &snapshot) {
+        snapshot.restore(&mut simulation);
+        simulation.set_paused(false);
+    }
+    if args.tui {
+        return run_tui(
+            &mut simulation,
+            &config,
+            &coordinates,
+            &calibration,
+            &led_mask,
+            outputs,
+            controls,
+        );
+    }
+    if args.headless {
+        return run_headless(
+            &mut simulation,
+            &config,
+            &calibration,
+            &led_mask,
+            args.start_at.map(ScheduledStart::new),
+            outputs,
+            controls,
+        );
+    }
+
+    let test_pattern = args.test_pattern;
+    let start_at = args.start_at.map(ScheduledStart::new);
+    let native_options = eframe::NativeOptions {
+        persist_window: true, // Restore the window size and position of the last run
+        ..Default::default()
+    };
+    eframe::run_native(
+        "F1-LED-CIRCUIT SIMULATION",
+        native_options,
+        Box::new(move |cc| {
+            // Missing or unreadable stored state just means starting from the defaults
+            let prefs = cc
+                .storage
+                .and_then(|storage| eframe::get_value::<UiPrefs>(storage, UI_PREFS_KEY));
+            let last_window = prefs
+                .as_ref()
+                .and_then(|prefs| prefs.session_window(&config));
+            let mut app = PlotApp::new(
+                layout,
+                simulation,
+                driver_info,
+                calibration,
+                mapping_stats,
+                &config,
+                prefs,
+            );
+            let gui = GuiSink::new(cc.egui_ctx.clone(), Arc::clone(&app.shown_frame));
+            if let Err(err) = outputs.register(Box::new(gui), SinkOptions::default()) {
+                error!("Could not start the window's frame sink: {}", err);
+            }
+            app.outputs = outputs;
+            app.output_settings.wled_status = wled_status;
+            app.recording = recording;
+            app.controls = controls;
+            app.session_name = session_name;
+            app.race_progress = race_progress;
+            app.best_laps = best_laps;
+            app.update_markers();
+            app.data_source = data_source;
+            app.color_overrides = color_overrides;
+            app.led_mask = led_mask;
+            app.race_control.scheduled_start = start_at;
+            app.banner = setup_error;
+            if let Some(snapshot) = &snapshot {
+                snapshot.restore(&mut app.simulation);
+                app.focused_driver = snapshot.settings.focused_driver;
+            }
+            if let Some(warning) = snapshot_warning {
+                app.push_toast(Toast::warning(warning));
+            }
+            if !made_up_drivers.is_empty() {
+                app.push_toast(Toast::warning(made_up_drivers_message(&made_up_drivers)));
+            }
+            if window_problem.is_some() {
+                app.settings.form =
+                    SessionForm::new(&app.session, &driver_numbers(&app.driver_info));
+                app.settings.window_problem = window_problem;
+                app.settings.shown = true;
+            }
+            // Goes back to the part of the session the last run played
+            if let Some(window) = last_window.filter(|_| {
+                app.data_source.is_some()
+                    && snapshot.is_none()
+                    && test_pattern.is_none()
+                    && app.settings.window_problem.is_none()
+            }) {
+                let session = SessionConfig {
+                    start_time: window.start,
+                    end_time: window.end,
+                    ..app.session.clone()
+                };
+                app.settings.form = SessionForm::new(&session, &driver_numbers(&app.driver_info));
+                app.reload_session(false);
+            }
+            app.output_settings.test_pattern = test_pattern.map(|pattern| {
+                PatternPlayer::new(
+                    pattern,
+                    app.coordinates.len(),
+                    &app.output_settings.test_patterns,
+                )
+            });
+            if app.ghost.config.enabled && app.data_source.is_some() && test_pattern.is_none() {
+                app.start_ghost_load();
+            }
+            if app.split.config.enabled && app.data_source.is_some() && test_pattern.is_none() {
+                app.start_split_load();
+            }
+            apply_theme(&cc.egui_ctx, app.display.theme);
+            Box::new(app)
+        }),
+    )
+    .map_err(|err| AppError::Gui {
+        reason: err.to_string(),
+    })?;
+
+    Ok(())
+}
+
+// The configured season's roster with the colors from the config file, for when there's no
+// session to go by
+fn roster(
+    config: &RosterConfig,
+    color_overrides: &BTreeMap<u32, egui::Color32>,
+) -> Vec<DriverInfo> {
+    let mut driver_info = season_roster(config.season.unwrap_or(DEFAULT_SEASON), config);
+    apply_color_overrides(&mut driver_info, color_overrides);
+    driver_info
+}
+
+// Fetches or loads the mapped race data of the configured session
+fn prepare_simulation(
+    config: &Config,
+    coordinates: &[LedPoint],
+    color_overrides: &BTreeMap<u32, egui::Color32>,
+) -> Result<(Simulation, Vec<DriverInfo>, MappingStats, Vec<u32>), AppError> {
+    let roster = session_season_roster(&config.api, &config.roster, &config.session);
+    let drivers = session_drivers(&config.session, &roster);
+
+    let (run_race_data, mapping_stats) = prepare_race_data(
+        &config.api,
+        &config.session.key,
+        &drivers,
+        &config.session.window(),
+        coordinates,
+        &config.mapping,
+        &config.cache.dir,
+    )?;
+    let (mut driver_info, made_up_drivers) = prepare_session_roster(
+        &config.api,
+        &config.session.key,
+        &config.cache.dir,
+        roster,
+        &run_race_data,
+    );
+    apply_color_overrides(&mut driver_info, color_overrides);
+    let simulation = Simulation::new(
+        run_race_data,
+        coordinates.len(),
+        driver_colors(&driver_info),
+    );
+    Ok((simulation, driver_info, mapping_stats, made_up_drivers))
+}
+
+// A warning naming the drivers shown with placeholder names and colors
+fn made_up_drivers_message(numbers: &[u32]) -> String {
+    let numbers: Vec<String> = numbers.iter().map(u32::to_string).collect();
+    format!(
+        "Drivers {} aren't on the roster or in the session's driver list; they're shown as \"Driver <number>\" in a made-up color",
+        numbers.join(", ")
+    )
+}
+
+// The starting grid to show before lights out. Only races start from a grid, and only they
+// have gaps to the leader
+fn grid_formation(
+    progress: Option<&RaceProgress>,
+    led_count: usize,
+    config: &FormationConfig,
+) -> Option<GridFormation> {
+    let progress = progress.filter(|progress| config.enabled && progress.has_intervals())?;
+    GridFormation::new(progress, led_count, config)
+}
+
+// Plays a freshly loaded session as the qualifying mode says: its best laps instead of the
+// session, which are returned, or with the garage time sped through
+fn apply_qualifying_mode(
+    simulation: &mut Simulation,
+    progress: Option<&RaceProgress>,
+    config: &QualifyingConfig,
+    driver_info: &[DriverInfo],
+) -> Vec<BestLap> {
+    let Some(progress) = progress else {
+        if config.mode != QualifyingMode::Off {
+            warn!("Playing the whole session: the qualifying mode needs the laps");
+        }
+        return Vec::new();
+    };
+    match config.mode {
+        QualifyingMode::Off => Vec::new(),
+        QualifyingMode::BestLaps => {
+            let best_laps = best_laps(progress);
+            if best_laps.is_empty() {
+                warn!("Playing the whole session: it has no flying laps");
+                return best_laps;
+            }
+            info!("Playing the best laps of {} drivers", best_laps.len());
+            let overlaid = overlay_best_laps(simulation.run_race_data(), &best_laps);
+            let led_count = simulation.frame().leds.len();
+            *simulation = Simulation::new(overlaid, led_count, driver_colors(driver_info));
+            best_laps
+        }
+        QualifyingMode::SkipGarage => {
+            let run_race_data = simulation.run_race_data();
+            if let (Some(first), Some(last)) = (run_race_data.first(), run_race_data.last()) {
+                let segments =
+                    garage_segments(progress, first.date, last.date, config.garage_speed);
+                info!("Skipping {} stretches of garage time", segments.len());
+                simulation.speed_plan_mut().set_segments(segments);
+            }
+            Vec::new()
+        }
+    }
+}
+
+// Fetches or loads the mapped race data and the laps of a session to reload; `None` when the
+// reload was cancelled in between
+fn load_session(
+    source: &DataSource,
+    session: &SessionConfig,
+    cancelled: &AtomicBool,
+    long_window_ok: bool,
+) -> Result<SessionLoad, AppError> {
+    match check_session_window(&source.api, session) {
+        Err(WindowProblem::TooLong { .. }) if long_window_ok => {}
+        Err(problem) => return Ok(SessionLoad::BadWindow(problem)),
+        Ok(()) => {}
+    }
+    let roster = session_season_roster(&source.api, &source.roster, session);
+    let driver_numbers = session_drivers(session, &roster);
+    let (run_race_data, mapping_stats) = prepare_race_data(
+        &source.api,
+        &session.key,
+        &driver_numbers,
+        &session.window(),
+        &source.coordinates,
+        &source.mapping,
+        &source.cache_dir,
+    )?;
+    if cancelled.load(Ordering::Relaxed) {
+        return Ok(SessionLoad::Cancelled);
+    }
+    let (driver_info, made_up_drivers) = prepare_session_roster(
+        &source.api,
+        &session.key,
+        &source.cache_dir,
+        roster,
+        &run_race_data,
+    );
+    let race_progress = load_race_progress(source, session, &driver_numbers);
+    if cancelled.load(Ordering::Relaxed) {
+        return Ok(SessionLoad::Cancelled);
+    }
+    Ok(SessionLoad::Loaded(Box::new(LoadedSession {
+        run_race_data,
+        mapping_stats,
+        race_progress,
+        driver_info,
+        made_up_drivers,
+    })))
+}
+
+// Checks the session's time window before anything is fetched for it. When the session ran is
+// only asked for if the window has a bound, and not getting it skips that part of the check
+fn check_session_window(api: &ApiConfig, session: &SessionConfig) -> Result<(), WindowProblem> {
+    let window = session.window();
+    let info = if window.start.is_some() || window.end.is_some() {
+        let fetched = tokio::runtime::Runtime::new()
+            .map_err(AppError::from)
+            .and_then(|runtime| runtime.block_on(fetch_session(api, &session.key)));
+        fetched.unwrap_or_else(|err| {
+            warn!(
+                "Could not check the time window against session {}: {}",
+                session.key, err
+            );
+            None
+        })
+    } else {
+        None
+    };
+    check_window(session, info.as_ref())
+}
+
+// Fetches or loads the ghost driver's mapped samples and laps from their session
+fn load_ghost_run(
+    source: &DataSource,
+    session_key: &str,
+    driver_number: u32,
+) -> Result<GhostRun, AppError> {
+    let (samples, _) = prepare_race_data(
+        &source.api,
+        session_key,
+        &[driver_number],
+        &TimeWindow::default(),
+        &source.coordinates,
+        &source.mapping,
+        &source.cache_dir,
+    )?;
+    // The laps only matter for lap start alignment
+    let progress = prepare_race_progress(
+        &source.api,
+        session_key,
+        &[driver_number],
+        &source.cache_dir,
+    )
+    .unwrap_or_else(|err| {
+        warn!("Could not fetch the ghost's laps: {}", err);
+        RaceProgress::default()
+    });
+    Ok(GhostRun {
+        driver_number,
+        samples,
+        progress,
+    })
+}
+
+// Opens a session bundle to play instead of fetching the session. Records mapped onto another
+// layout, or by another version of the mapping, are mapped onto this one again
+fn open_bundle(path: &Path, layout: &Layout) -> Result<Bundle, AppError> {
+    let mut bundle = Bundle::open(path)?;
+    bundle.mapping_options.pit_leds = layout.pit_leds();
+    if bundle.map_onto(layout.coordinates())? {
+        warn!(
+            "{} was mapped for another layout or mapping version; mapped it again",
+            path.display()
+        );
+    }
+    info!(
+        "Loaded {} records of session {} from {}",
+        bundle.run_race_data.len(),
+        bundle.session.key,
+        path.display()
+    );
+    Ok(bundle)
+}
+
+// Loads a recording to play instead of the race; it must have one LED per layout LED, and a
+// different layout only earns a warning
+fn load_replay(path: &Path, coordinates: &[LedPoint]) -> Result<Simulation, AppError> {
+    let recording = Recording::read(BufReader::new(File::open(path)?))?;
+    let header = recording.header;
+    if header.led_count as usize != coordinates.len() {
+        return Err(AppError::LayoutInvalid {
+            reason: format!(
+                "{} was recorded for {} LEDs, the layout has {}",
+                path.display(),
+                header.led_count,
+                coordinates.len()
+            ),
+        });
+    }
+    if header.layout_hash != layout_hash(coordinates) {
+        warn!(
+            "{} was recorded for a different layout with the same LED count; playing it anyway",
+            path.display()
+        );
+    }
+    info!(
+        "Loaded {} recorded frames covering {:.1}s from {}",
+        recording.len(),
+        recording.duration().as_secs_f64(),
+        path.display()
+    );
+    Ok(Simulation::from_recording(recording))
+}
+
+// Registers a sink for every enabled output; returns the WLED status for display and the
+// publisher of the race events
+fn register_outputs(
+    config: &Config,
+    layout: &Layout,
+    outputs: &mut FrameDispatcher,
+) -> Result<(Option<WledStatusHandle>, Option<MqttPublisher>), AppError> {
+    let mut wled_status = None;
+    let mut race_events = None;
+    for name in LED_OUTPUTS {
+        if let Some(output) = open_output(config, layout, name)? {
+            wled_status = wled_status.or(output.wled_status);
+            race_events = race_events.or(output.race_events);
+            outputs.register(output.sink, output.options)?;
+        }
+    }
+    #[cfg(not(feature = "ws2812"))]
+    if config.ws2812.enabled {
+        warn!("Ignoring the ws2812 output: this build lacks the ws2812 feature");
+    }
+    Ok((wled_status, race_events))
+}
+
+// An opened output, ready to register
+struct Output {
+    sink: Box<dyn LedSink>,
+    options: SinkOptions,
+    wled_status: Option<WledStatusHandle>,
+    race_events: Option<MqttPublisher>,
+}
+
+// Opens the output called `name` when it's enabled, also to reconnect it after a failure
+fn open_output(config: &Config, layout: &Layout, name: &str) -> Result<Option<Output>, AppError> {
+    let coordinates = layout.coordinates();
+    // On a matrix the LED outputs drive one pixel per grid cell, else the pixels of the chain
+    let grid = if config.matrix.enabled {
+        Some(LedGrid::new(coordinates, &config.matrix)?)
+    } else {
+        None
+    };
+    let led_count = grid
+        .as_ref()
+        .map_or(layout.chain_length(), LedGrid::pixel_count);
+    let pixels = |sink: Box<dyn LedSink>| -> Box<dyn LedSink> {
+        let sink: Box<dyn LedSink> = match &grid {
+            Some(grid) => Box::new(MatrixSink::new(sink, grid.clone())),
+            None if layout.is_chained() => Box::new(ChainSink::new(sink, layout.clone())),
+            None => sink,
+        };
+        let output = config.output(name);
+        if output.is_identity() {
+            sink
+        } else {
+            Box::new(HardwareSink::new(sink, &output, coordinates))
+        }
+    };
+    let output = |sink: Box<dyn LedSink>, options: SinkOptions| {
+        Some(Output {
+            sink,
+            options,
+            wled_status: None,
+            race_events: None,
+        })
+    };
+    Ok(match name {
+        "dmx" if config.dmx.enabled => output(
+            pixels(Box::new(DmxSink::open(&config.dmx)?)),
+            SinkOptions::hardware(config.dmx.fps),
+        ),
+        "enttec" if config.enttec.enabled => output(
+            pixels(Box::new(EnttecSink::open(&config.enttec, led_count)?)),
+            SinkOptions::hardware(config.enttec.fps),
+        ),
+        "wled" if config.wled.enabled => {
+            let wled = WledSink::open(&config.wled)?;
+            Some(Output {
+                wled_status: Some(wled.status()),
+                sink: pixels(Box::new(wled)),
+                options: SinkOptions::hardware(config.wled.max_fps),
+                race_events: None,
+            })
+        }
+        "mqtt" if config.mqtt.enabled => {
+            let mqtt = MqttPublisher::connect(&config.mqtt)?;
+            Some(Output {
+                race_events: Some(mqtt.clone()),
+                sink: Box::new(mqtt),
+                options: SinkOptions::hardware(config.mqtt.max_fps),
+                wled_status: None,
+            })
+        }
+        "osc" if config.osc.enabled => output(
+            Box::new(OscSink::open(&config.osc)?),
+            SinkOptions::hardware(config.osc.max_fps),
+        ),
+        // Browsers want the race time of every frame, so unchanged frames are sent too
+        "websocket" if config.websocket.enabled => output(
+            Box::new(WebSocketServer::open(&config.websocket, coordinates)?),
+            SinkOptions {
+                max_fps: Some(config.websocket.max_fps),
+                skip_unchanged: false,
+            },
+        ),
+        // Dropping the strip blanks it, also when a panic unwinds through its thread
+        #[cfg(feature = "ws2812")]
+        "ws2812" if config.ws2812.enabled => output(
+            pixels(Box::new(Ws2812Strip::open(&config.ws2812, led_count)?)),
+            SinkOptions::hardware(config.ws2812.max_fps),
+        ),
+        _ => None,
+    })
+}
+
+// Playback commands from outside the window: the control API and GPIO buttons send theirs over
+// a channel, gamepads are polled. A sync follower plays along with its leader instead
+#[derive(Default)]
+struct Controls {
+    commands: Option<Receiver<PlaybackCommand>>,
+    #[cfg(feature = "gamepad")]
+    gamepad: Option<Gamepad>,
+    sync: Option<PlaybackSync>,
+    #[cfg(feature = "metrics")]
+    _metrics: Option<MetricsServer>, // Serves the metrics unless the control API does
+    race_events: Option<MqttPublisher>, // Publishes the race events of each update
+}
+
+// Keeps the instances in a sync group on the same race state
+enum PlaybackSync {
+    Leader {
+        leader: SyncLeader,
+        session: String,
+        data_hash: u64,
+    },
+    Follower(SyncFollower),
+}
+
+impl Controls {
+    // Whether commands may arrive without any input to the window
+    fn is_active(&self) -> bool {
+        #[cfg(feature = "gamepad")]
+        if self.gamepad.is_some() {
+            return true;
+        }
+        self.commands.is_some() || self.sync.is_some()
+    }
+
+    fn is_following(&self) -> bool {
+        matches!(self.sync, Some(PlaybackSync::Follower(_)))
+    }
+
+    // The leader followed, `None` while there's none to follow
+    fn synced_to(&self) -> Option<SocketAddr> {
+        match &self.sync {
+            Some(PlaybackSync::Follower(follower)) => follower.leader(Instant::now()),
+            _ => None,
+        }
+    }
+
+    // Applies the commands that came in. A follower ignores them and follows its leader; the
+    // first time that plays other race data, the warning is returned
+    fn apply(
+        &mut self,
+        simulation: &mut Simulation,
+        speeds: &RangeInclusive<f64>,
+    ) -> Option<String> {
+        let following = self.is_following();
+        #[cfg(feature = "gamepad")]
+        if let Some(gamepad) = &mut self.gamepad {
+            for command in gamepad.poll() {
+                if !following {
+                    command.apply(simulation, speeds);
+                }
+            }
+        }
+        if let Some(commands) = &self.commands {
+            for command in commands.try_iter() {
+                if !following {
+                    command.apply(simulation, speeds);
+                }
+            }
+        }
+        match &mut self.sync {
+            Some(PlaybackSync::Follower(follower)) => {
+                let now = Instant::now();
+                let warning = follower.receive(now);
+                follower.follow(simulation, now);
+                warning
+            }
+            _ => None,
+        }
+    }
+
+    // Publishes race events taken from the simulation, when an output wants them
+    fn publish(&self, events: &[RaceEvent]) {
+        if let Some(publisher) = &self.race_events {
+            for event in events {
+                publisher.publish_event(event);
+            }
+        }
+    }
+
+    // Sends the state to the followers when leading. A leader that fails stops leading
+    fn broadcast(&mut self, simulation: &Simulation) -> Result<(), AppError> {
+        let Some(PlaybackSync::Leader {
+            leader,
+            session,
+            data_hash,
+        }) = &mut self.sync
+        else {
+            return Ok(());
+        };
+        let message = SyncMessage::new(session, *data_hash, simulation);
+        if let Err(err) = leader.update(message, Instant::now()) {
+            self.sync = None;
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    // The session played changed, e.g. after loading another one in the window
+    fn set_session(&mut self, session_key: &str, simulation: &Simulation) {
+        match &mut self.sync {
+            Some(PlaybackSync::Leader {
+                session, data_hash, ..
+            }) => {
+                *session = session_key.to_string();
+                *data_hash = self::data_hash(simulation.run_race_data());
+            }
+            Some(PlaybackSync::Follower(follower)) => {
+                follower.set_data_hash(data_hash(simulation.run_race_data()));
+            }
+            None => {}
+        }
+    }
+}
+
+// Starts every enabled source of playback commands
+fn start_controls(
+    config: &Config,
+    simulation: &Simulation,
+    led_count: usize,
+    outputs: &mut FrameDispatcher,
+) -> Result<Controls, AppError> {
+    let (sender, commands) = mpsc::channel();
+    let metrics = metrics_routes(config)?;
+    let api = start_control_api(
+        config,
+        &sender,
+        simulation,
+        led_count,
+        outputs,
+        metrics.clone().unwrap_or_default(),
+    )?;
+    let buttons = watch_gpio_buttons(config, &sender)?;
+    #[cfg(not(feature = "gamepad"))]
+    if config.gamepad.enabled {
+        warn!("Ignoring the gamepad: this build lacks the gamepad feature");
+    }
+    let sync = match config.sync.role {
+        SyncRole::Off => None,
+        SyncRole::Leader => Some(PlaybackSync::Leader {
+            leader: SyncLeader::open(&config.sync)?,
+            session: config.session.key.clone(),
+            data_hash: data_hash(simulation.run_race_data()),
+        }),
+        SyncRole::Follower => Some(PlaybackSync::Follower(SyncFollower::open(
+            &config.sync,
+            data_hash(simulation.run_race_data()),
+        )?)),
+    };
+    Ok(Controls {
+        commands: (api || buttons).then_some(commands),
+        sync,
+        #[cfg(feature = "gamepad")]
+        gamepad: match config.gamepad.enabled {
+            true => Some(Gamepad::open(&config.gamepad)?),
+            false => None,
+        },
+        #[cfg(feature = "metrics")]
+        _metrics: match metrics {
+            Some(routes) if !api => Some(MetricsServer::open(&config.metrics, routes)?),
+            _ => None,
+        },
+        race_events: None,
+    })
+}
+
+// Starts recording metrics when they're enabled; the routes serving them still need a server
+#[cfg(feature = "metrics")]
+fn metrics_routes(config: &Config) -> Result<Option<Router>, AppError> {
+    if !config.metrics.enabled {
+        return Ok(None);
+    }
+    Ok(Some(metrics::routes(metrics::install()?)))
+}
+
+#[cfg(not(feature = "metrics"))]
+fn metrics_routes(config: &Config) -> Result<Option<Router>, AppError> {
+    if config.metrics.enabled {
+        warn!("Ignoring the metrics: this build lacks the metrics feature");
+    }
+    Ok(None)
+}
+
+// Starts the control API when it's enabled; returns whether it did
+#[cfg(feature = "http-control")]
+fn start_control_api(
+    config: &Config,
+    commands: &Sender<PlaybackCommand>,
+    simulation: &Simulation,
+    led_count: usize,
+    outputs: &mut FrameDispatcher,
+    routes: Router,
+) -> Result<bool, AppError> {
+    if !config.control.enabled {
+        return Ok(false);
+    }
+    let server = ControlServer::open_with_routes(
+        &config.control,
+        commands.clone(),
+        simulation.record_count(),
+        led_count,
+        routes,
+    )?;
+    // The server reports the playback state of the frames it gets
+    outputs.register(Box::new(server), SinkOptions::default())?;
+    Ok(true)
+}
+
+#[cfg(not(feature = "http-control"))]
+fn start_control_api(
+    config: &Config,
+    _commands: &Sender<PlaybackCommand>,
+    _simulation: &Simulation,
+    _led_count: usize,
+    _outputs: &mut FrameDispatcher,
+    _routes: Router,
+) -> Result<bool, AppError> {
+    if config.control.enabled {
+        warn!("Ignoring the control API: this build lacks the http-control feature");
+    }
+    Ok(false)
+}
+
+// Starts watching the GPIO buttons when they're enabled; returns whether it did
+#[cfg(feature = "gpio")]
+fn watch_gpio_buttons(
+    config: &Config,
+    commands: &Sender<PlaybackCommand>,
+) -> Result<bool, AppError> {
+    if !config.gpio.enabled {
+        return Ok(false);
+    }
+    watch_buttons(&config.gpio, commands.clone())?;
+    Ok(true)
+}
+
+#[cfg(not(feature = "gpio"))]
+fn watch_gpio_buttons(
+    config: &Config,
+    _commands: &Sender<PlaybackCommand>,
+) -> Result<bool, AppError> {
+    if config.gpio.enabled {
+        warn!("Ignoring the GPIO buttons: this build lacks the gpio feature");
+    }
+    Ok(false)
+}
+
+// Records every frame, changed or not, at the recording's tick rate
+fn start_recording(
+    config: &RecorderConfig,
+    coordinates: &[LedPoint],
+    outputs: &mut FrameDispatcher,
+) -> Result<SinkId, AppError> {
+    let options = SinkOptions {
+        max_fps: Some(config.fps),
+        skip_unchanged: false,
+    };
+    outputs.register(Box::new(FrameRecorder::new(config, coordinates)?), options)
+}
+
+// The frame for the outputs without a window: calibrated and masked, with unlit LEDs black
+fn headless_frame(
+    simulation: &Simulation,
+    config: &Config,
+    calibration: &[LedCalibration],
+    led_mask: &LedMask,
+) -> OutputFrame {
+    let mut leds: Vec<Rgb> = correct_frame(&simulation.frame().leds, 1.0, calibration)
+        .into_iter()
+        .map(|color| color.unwrap_or([0, 0, 0]))
+        .collect();
+    led_mask.apply(&mut leds);
+    led_frame(simulation, leds, config.display.brightness)
+}
+
+fn led_frame(simulation: &Simulation, leds: Vec<Rgb>, brightness: f32) -> OutputFrame {
+    OutputFrame {
+        leds,
+        brightness,
+        timestamp: Duration::from_secs_f64(simulation.race_time()),
+        state: simulation.state(),
+        speed: simulation.effective_speed(),
+        drivers: simulation.track_progress().drivers(),
+    }
+}
+
+// The --check mode: runs every check on its own, so one failing doesn't hide the others, and
+// prints the report. Without a usable config the rest is checked against the defaults
+fn run_check(args: &CliArgs) -> Result<(), AppError> {
+    let mut report = CheckReport::default();
+    let config = match load_config(args) {
+        Ok(config) => {
+            let path = args
+                .config
+                .as_deref()
+                .or_else(|| Some(Path::new(DEFAULT_CONFIG_FILE)).filter(|path| path.exists()));
+            let found = path.map_or("none, using the defaults".to_string(), |path| {
+                format!("read {}", path.display())
+            });
+            report.record("config", Ok(found));
+            config
+        }
+        Err(err) => {
+            report.record("config", Err(err));
+            Config::default()
+        }
+    };
+    report.record(
+        "colors",
+        color_overrides(&config.colors).map(|colors| format!("{} overrides", colors.len())),
+    );
+
+    let layout = match read_coordinates().and_then(|builtin| Layout::new(&config.layout, builtin)) {
+        Ok(layout) => {
+            check_layout(&mut report, layout.coordinates());
+            layout
+        }
+        Err(err) => {
+            report.record("layout", Err(err));
+            Layout::single(Vec::new())
+        }
+    };
+    let coordinates = layout.coordinates().to_vec();
+    let calibration = load_calibration(&config.calibration, coordinates.len());
+    report.record(
+        "calibration",
+        calibration.map(|leds| format!("{} LEDs", leds.len())),
+    );
+    let mask_file = config
+        .calibration
+        .mask_file
+        .as_deref()
+        .or_else(|| MASK_FILES.iter().map(Path::new).find(|path| path.exists()));
+    if let Some(path) = mask_file {
+        let mask = read_mask(path, &coordinates);
+        report.record("mask", mask.map(|_| format!("read {}", path.display())));
+    }
+
+    // A bundle stands in for fetching the session
+    match &args.bundle {
+        Some(path) => check_bundle(&mut report, path, &coordinates),
+        None => {
+            let source = DataSource {
+                api: config.api.clone(),
+                mapping: MappingOptions {
+                    pit_leds: layout.pit_leds(),
+                    ..config.mapping.clone()
+                },
+                cache_dir: config.cache.dir.clone(),
+                coordinates: coordinates.clone(),
+                roster: config.roster.clone(),
+            };
+            check_session_data(&mut report, &source, &config.session);
+        }
+    }
+
+    let mut enabled = 0;
+    for name in LED_OUTPUTS {
+        let result = match open_output(&config, &layout, name) {
+            Ok(Some(_)) => Ok("opened".to_string()),
+            Ok(None) => continue,
+            Err(err) => Err(err),
+        };
+        report.record(format!("output {}", name), result);
+        enabled += 1;
+    }
+    if enabled == 0 {
+        report.record("outputs", Ok("none enabled".to_string()));
+    }
+
+    println!("{}", report);
+    report.outcome()
+}
+
+// The LED calibration from the configured file, or else from the first of the usual files there
+// is; without either every LED is left as it is
+fn load_calibration(
+    config: &CalibrationConfig,
+    led_count: usize,
+) -> Result<Vec<LedCalibration>, AppError> {
+    let file = config.file.as_deref().or_else(|| {
+        CALIBRATION_FILES
+            .iter()
+            .map(Path::new)
+            .find(|path| path.exists())
+    });
+    match file {
+        Some(path) => read_calibration(path, led_count),
+        None => Ok(vec![LedCalibration::default(); led_count]),
+    }
+}
+
+// The fetch command: gets the session's records, driver list and laps into the cache, so
+// playing it later needs no network
+fn run_fetch(config: &Config, source: &DataSource) -> Result<(), AppError> {
+    let loaded = load_session_data(source, &config.session)?;
+    info!(
+        "Cached {} records of {} drivers{} for session {} in {}",
+        loaded.run_race_data.len(),
+        loaded.driver_info.len(),
+        if loaded.race_progress.is_some() {
+            " with their laps"
+        } else {
+            ""
+        },
+        config.session.key,
+        source.cache_dir.display()
+    );
+    Ok(())
+}
+
+// The map command: maps the session, or the bundle given, onto the layout and writes it as a
+// bundle to play without fetching
+fn run_map(
+    config: &Config,
+    source: &DataSource,
+    bundle: Option<Bundle>,
+    color_overrides: &BTreeMap<u32, egui::Color32>,
+    output: Option<&Path>,
+) -> Result<(), AppError> {
+    // An opened bundle has been mapped onto the layout already
+    let bundle = match bundle {
+        Some(bundle) => bundle,
+        None => {
+            let mut loaded = load_session_data(source, &config.session)?;
+            apply_color_overrides(&mut loaded.driver_info, color_overrides);
+            session_bundle(source, &config.session, loaded)
+        }
+    };
+    let path = output.map_or_else(
+        || {
+            config
+                .bundle
+                .dir
+                .join(format!("{}.{}", config.session.label(), BUNDLE_EXTENSION))
+        },
+        Path::to_path_buf,
+    );
+    bundle.save(&path)?;
+    info!(
+        "Wrote {} records of session {} to {}",
+        bundle.run_race_data.len(),
+        bundle.session.key,
+        path.display()
+    );
+    Ok(())
+}
+
+// The export command: maps the session like for playback, or takes the bundle given, and
+// writes the samples to a file or renders a clip of them
+fn run_export(
+    config: &Config,
+    source: &DataSource,
+    bundle: Option<Bundle>,
+    color_overrides: &BTreeMap<u32, egui::Color32>,
+    format: DataFormat,
+    output: Option<&Path>,
+) -> Result<(), AppError> {
+    let session = &config.session;
+    let (run_race_data, mut driver_info) = match bundle {
+        Some(bundle) => (bundle.run_race_data, bundle.driver_info),
+        None => {
+            let roster = session_season_roster(&source.api, &source.roster, session);
+            let (run_race_data, _) = prepare_race_data(
+                &source.api,
+                &session.key,
+                &session_drivers(session, &roster),
+                &session.window(),
+                &source.coordinates,
+                &source.mapping,
+                &source.cache_dir,
+            )?;
+            // Only the clip shows who's who
+            let driver_info = match format {
+                DataFormat::Gif => {
+                    let cache_dir = &source.cache_dir;
+                    prepare_session_roster(
+                        &source.api,
+                        &session.key,
+                        cache_dir,
+                        roster,
+                        &run_race_data,
+                    )
+                    .0
+                }
+                DataFormat::Parquet | DataFormat::Csv => roster,
+            };
+            (run_race_data, driver_info)
+        }
+    };
+    apply_color_overrides(&mut driver_info, color_overrides);
+    let default_path =
+        |extension: &str| PathBuf::from(format!("{}.{}", session.label(), extension));
+
+    match format {
+        DataFormat::Parquet => {
+            let path = output.map_or_else(|| config.parquet.path.clone(), Path::to_path_buf);
+            let job = ParquetJob::start(run_race_data, path)?;
+            while !job.is_finished() {
+                std::thread::sleep(EXPORT_REPORT_INTERVAL);
+                info!(
+                    "Written {:.0}% of {} rows",
+                    job.progress() * 100.0,
+                    job.rows()
+                );
+            }
+            job.finish().map(|_| ())
+        }
+        DataFormat::Csv => {
+            let path = output.map_or_else(|| default_path("csv"), Path::to_path_buf);
+            let file = File::create(&path).map_err(|err| AppError::Export {
+                reason: format!("could not create {}: {}", path.display(), err),
+            })?;
+            write_csv(&run_race_data, BufWriter::new(file))?;
+            info!("Wrote {} rows to {}", run_race_data.len(), path.display());
+            Ok(())
+        }
+        DataFormat::Gif => {
+            let path = output.map_or_else(|| default_path("gif"), Path::to_path_buf);
+            let led_count = source.coordinates.len();
+            let simulation = Simulation::new(run_race_data, led_count, driver_colors(&driver_info));
+            let options = ExportOptions {
+                format: ExportFormat::Gif,
+                speed: config.playback.speed,
+                led_size: config.display.led_size,
+                path,
+                ..ExportOptions::default()
+            };
+            let job = ExportJob::start(
+                &simulation,
+                &source.coordinates,
+                &load_calibration(&config.calibration, led_count)?,
+                config.display.brightness,
+                options,
+            )?;
+            while !job.is_finished() {
+                std::thread::sleep(EXPORT_REPORT_INTERVAL);
+                info!("Rendered {:.0}% of the clip", job.progress() * 100.0);
+            }
+            job.finish().map(|_| ())
+        }
+    }
+}
+
+// Shows a test pattern on the outputs until the program is stopped, logging the LED it's on
+fn run_test_pattern(
+    mut player: PatternPlayer,
+    brightness: f32,
+    outputs: FrameDispatcher,
+) -> Result<(), AppError> {
+    info!(
+        "Showing the {} test pattern; stop with Ctrl+C",
+        player.pattern().name()
+    );
+    let started = Instant::now();
+    let mut next_tick = started;
+    let mut active = None;
+    loop {
+        if player.active_led() != active {
+            active = player.active_led();
+            if let Some(index) = active {
+                info!("LED U{} (index {})", index + 1, index);
+            }
+        }
+        outputs.dispatch(OutputFrame {
+            leds: player.frame(),
+            brightness,
+            timestamp: started.elapsed(),
+            state: PlaybackState::Playing,
+            speed: 1.0,
+            drivers: Vec::new(),
+        });
+        if let Some(err) = outputs.take_error() {
+            return Err(err);
+        }
+
+        next_tick += HEADLESS_TICK;
+        std::thread::sleep(next_tick.saturating_duration_since(Instant::now()));
+        player.tick(HEADLESS_TICK);
+    }
+}
+
+// Plays the whole race without a window, ticking the simulation at a fixed rate and feeding
+// any configured hardware output
+fn run_headless(
+    simulation: &mut Simulation,
+    config: &Config,
+    calibration: &[LedCalibration],
+    led_mask: &LedMask,
+    start_at: Option<ScheduledStart>,
+    mut outputs: FrameDispatcher,
+    mut controls: Controls,
+) -> Result<(), AppError> {
+    let speeds = config.playback.min_speed..=config.playback.max_speed;
+    if let Some(start) = start_at {
+        info!(
+            "Waiting to start at {}",
+            start.at().format("%Y-%m-%d %H:%M:%S")
+        );
+        // Checked against the clock every second, so a suspend in between doesn't delay it
+        let lateness = loop {
+            let now = Local::now();
+            if let Some(lateness) = start.lateness(now) {
+                break lateness;
+            }
+            std::thread::sleep(start.remaining(now).min(Duration::from_secs(1)));
+        };
+        if lateness > LATE_START_TOLERANCE {
+            warn!(
+                "The start time passed {} ago; starting now",
+                format_duration(lateness.as_secs_f64())
+            );
+        }
+        simulation.set_speed(1.0);
+    }
+    info!(
+        "Playing {} records headless at {}x speed",
+        simulation.record_count(),
+        simulation.speed()
+    );
+    let audio = AudioPlayer::open(&config.audio);
+    let mut watchdog = Watchdog::new(&config.watchdog);
+    // Already running when restored from a snapshot
+    if !simulation.is_running() {
+        simulation.start();
+    }
+
+    let mut next_tick = Instant::now();
+    let mut next_report = 0.0;
+    while !simulation.is_finished() {
+        controls.apply(simulation, &speeds);
+        let lit = simulation.advance(HEADLESS_TICK).lit().count();
+        let events = simulation.take_race_events();
+        audio.play(&events);
+        controls.publish(&events);
+        if let Err(err) = controls.broadcast(simulation) {
+            warn!("Stopped leading the playback sync: {}", err);
+        }
+        if simulation.race_time() >= next_report {
+            debug!("Race time {:.1}s: {} LEDs lit", simulation.race_time(), lit);
+            next_report += 1.0;
+        }
+
+        let frame = headless_frame(simulation, config, calibration, led_mask);
+        trace!("Frame: {:?}", frame.leds);
+        outputs.dispatch(frame);
+        if let Some(err) = outputs.take_error() {
+            return Err(err);
+        }
+        let clock_time =
+            (simulation.state() == PlaybackState::Playing).then(|| simulation.clock_time());
+        if let Some(stall) = watchdog.check(clock_time, &outputs.queues(), Instant::now()) {
+            let reason = stall.describe(config.watchdog.stall_secs);
+            let dump = DiagnosticDump::new(&reason, simulation, &outputs, &status::snapshot());
+            match dump.write(&config.watchdog.dir) {
+                Ok(path) => warn!("{}; diagnostics written to {}", reason, path.display()),
+                Err(err) => warn!("{}; could not write the diagnostics: {}", reason, err),
+            }
+        }
+
+        next_tick += HEADLESS_TICK;
+        std::thread::sleep(next_tick.saturating_duration_since(Instant::now()));
+    }
+    // The LEDs go dark rather than staying on the final frame
+    outputs.blank_and_shutdown(SHUTDOWN_TIMEOUT);
+    if let Some(err) = outputs.take_error() {
+        return Err(err);
+    }
+    info!(
+        "Headless playback finished after {:.1}s of race time",
+        simulation.race_time()
+    );
+    Ok(())
+}
+
+// Plays the race like `run_headless`, drawing the track in the terminal until q is pressed
+fn run_tui(
+    simulation: &mut Simulation,
+    config: &Config,
+    coordinates: &[LedPoint],
+    calibration: &[LedCalibration],
+    led_mask: &LedMask,
+    mut outputs: FrameDispatcher,
+    mut controls: Controls,
+) -> Result<(), AppError> {
+    let speeds = config.playback.min_speed..=config.playback.max_speed;
+    let audio = AudioPlayer::open(&config.audio);
+    let mut view = TrackView::new(coordinates, tui::supports_truecolor());
+    let mut terminal = TerminalSession::open()?;
+    info!(
+        "Playing {} records in the terminal at {}x speed",
+        simulation.record_count(),
+        simulation.speed()
+    );
+    if !simulation.is_running() {
+        simulation.start();
+    }
+
+    let mut next_tick = Instant::now();
+    'playing: loop {
+        while crossterm::event::poll(Duration::ZERO)? {
+            // A resize needs nothing but the next draw, which lays the track out again
+            let crossterm::event::Event::Key(key) = crossterm::event::read()? else {
+                continue;
+            };
+            match tui::key_action(key) {
+                Some(KeyAction::Playback(command)) => command.apply(simulation, &speeds),
+                Some(KeyAction::Quit) => break 'playing,
+                None => {}
+            }
+        }
+        controls.apply(simulation, &speeds);
+        simulation.advance(HEADLESS_TICK);
+        let events = simulation.take_race_events();
+        audio.play(&events);
+        controls.publish(&events);
+        if let Err(err) = controls.broadcast(simulation) {
+            warn!("Stopped leading the playback sync: {}", err);
+        }
+        outputs.dispatch(headless_frame(simulation, config, calibration, led_mask));
+        if let Some(err) = outputs.take_error() {
+            return Err(err);
+        }
+        terminal.draw(&mut view, simulation)?;
+
+        next_tick += HEADLESS_TICK;
+        std::thread::sleep(next_tick.saturating_duration_since(Instant::now()));
+    }
+    drop(terminal);
+    outputs.blank_and_shutdown(SHUTDOWN_TIMEOUT);
+    if let Some(err) = outputs.take_error() {
+        return Err(err);
+    }
+    info!(
+        "Left the terminal view after {:.1}s of race time",
+        simulation.race_time()
+    );
+    Ok(())
 }
@@ -0,0 +1,925 @@
+use crate::data::{LocationData, PipelineStats};
+use crate::error::AppError;
+use crate::space::{LedPoint, TelemetryPoint};
+use chrono::{DateTime, Duration, Utc};
+use log::warn;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+
+/// A location sample snapped to an LED.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRace {
+    pub date: DateTime<Utc>,
+    pub driver_number: u32,
+    pub led_index: usize,      // Index into the LED coordinate list
+    pub point: TelemetryPoint, // Location before snapping
+}
+
+/// Bumped whenever a change to the mapping would put the same samples on other LEDs, so session
+/// bundles mapped by another version are mapped again.
+pub const MAPPING_VERSION: u16 = 1;
+
+/// Samples farther than this many median LED spacings from every LED are dropped as off track.
+pub const SNAP_DISTANCE_FACTOR: f64 = 1.5;
+
+/// How a driver's raw positions are smoothed before they're snapped to LEDs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Smoothing {
+    #[default]
+    Off,
+    /// The mean of `smoothing_window` samples centred on each one.
+    MovingAverage,
+    /// Each position drawn `smoothing_alpha` of the way from the smoothed one before it.
+    Exponential,
+}
+
+/// How a car lights the board.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LedPositioning {
+    /// The one LED it's mapped to.
+    #[default]
+    Nearest,
+    /// Its LED and the neighbour it's closer to, shared as `blend_weights` says, so it moves
+    /// smoothly between them. Repeated positions are kept, as the share changes between them.
+    Blended,
+}
+
+/// Tunables for the mapping stage.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MappingOptions {
+    pub snap_distance_factor: f64,
+    pub collapse_duplicates: bool, // Drop records that don't move their driver to a new LED
+    pub downsample_ms: Option<u64>, // Keep at most one sample per driver this often; all when unset
+    pub fill_gaps: bool,           // Interpolate across dropouts of `gap_min_ms` to `gap_max_ms`
+    pub gap_min_ms: u64,
+    pub gap_max_ms: u64, // Longer gaps are left to the retirement handling
+    pub sample_interval_ms: u64, // Spacing of the interpolated samples
+    pub align_ms: Option<u64>, // Resample every driver onto a common grid this often; off when unset
+    pub smoothing: Smoothing,  // Of the raw positions, against jitter between neighbouring LEDs
+    pub smoothing_window: usize, // Samples averaged by the moving average
+    pub smoothing_alpha: f64,  // Share of each new sample in the exponential smoothing
+    pub hysteresis_margin: Option<f64>, // Another LED must be this much closer before a driver moves to it
+    pub hysteresis_ratio: Option<f64>, // Or this many times closer, e.g. 1.5; both when both are set
+    pub positioning: LedPositioning,
+    #[serde(skip)]
+    pub pit_leds: Vec<Range<usize>>, // Of the layout's pit segments, which it fills in
+}
+
+impl Default for MappingOptions {
+    fn default() -> Self {
+        MappingOptions {
+            snap_distance_factor: SNAP_DISTANCE_FACTOR,
+            collapse_duplicates: true,
+            downsample_ms: None,
+            fill_gaps: false,
+            gap_min_ms: 1000,
+            gap_max_ms: 5000,
+            sample_interval_ms: 270, // OpenF1 sends about 3.7 samples a second
+            align_ms: None,
+            smoothing: Smoothing::Off,
+            smoothing_window: 5,
+            smoothing_alpha: 0.3,
+            hysteresis_margin: None,
+            hysteresis_ratio: None,
+            positioning: LedPositioning::Nearest,
+            pit_leds: Vec::new(),
+        }
+    }
+}
+
+/// Counters collected while mapping samples to LEDs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MappingStats {
+    pub max_snap_distance: f64,
+    pub dropped_samples: usize,
+    pub dropped_per_driver: HashMap<u32, usize>,
+    pub off_track_since: HashMap<u32, DateTime<Utc>>, // Start of a driver's final off-track stretch
+    pub collapsed_samples: usize,
+    pub pipeline: PipelineStats, // From fetching the samples this mapping started from
+    pub led_elevation: Vec<Option<f64>>, // Mean z of the samples snapped to each LED, by LED
+}
+
+/// Median distance between neighbouring LEDs of the closed layout.
+pub fn median_led_spacing(coordinates: &[LedPoint]) -> f64 {
+    if coordinates.len() < 2 {
+        return 0.0;
+    }
+
+    // The layout is a closed loop, so the last LED neighbours the first one
+    let mut spacings: Vec<f64> = coordinates
+        .iter()
+        .zip(coordinates.iter().cycle().skip(1))
+        .map(|(a, b)| a.distance(*b))
+        .collect();
+    spacings.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    spacings[spacings.len() / 2]
+}
+
+/// Length of the closed layout, from LED to LED in order and back to the first.
+pub fn layout_length(coordinates: &[LedPoint]) -> f64 {
+    if coordinates.len() < 2 {
+        return 0.0;
+    }
+    coordinates
+        .iter()
+        .zip(coordinates.iter().cycle().skip(1))
+        .map(|(a, b)| a.distance(*b))
+        .sum()
+}
+
+// Telemetry is roughly in decimeters, so nearest-LED candidates are shared per 0.5 m cell
+const NEAREST_CACHE_CELL_SIZE: f64 = 5.0;
+const NEAREST_CACHE_CAPACITY: usize = 4096;
+
+/// The LEDs bucketed on a uniform grid of about one LED per cell, so finding the nearest one
+/// only looks at the cells around a point rather than at every LED. It finds the same LED as
+/// `nearest_led`, ties going to the lower index.
+#[derive(Debug, Clone)]
+pub struct NearestLedIndex {
+    points: Vec<LedPoint>,
+    origin: LedPoint, // Corner of the first cell
+    cell_size: f64,
+    columns: usize,
+    rows: usize,
+    cells: Vec<Vec<usize>>, // LED indexes in each cell, row by row
+}
+
+impl NearestLedIndex {
+    pub fn new(coordinates: &[LedPoint]) -> Self {
+        let points = coordinates.to_vec();
+        let (mut min_x, mut min_y) = (f64::INFINITY, f64::INFINITY);
+        let (mut max_x, mut max_y) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for point in &points {
+            (min_x, max_x) = (min_x.min(point.x), max_x.max(point.x));
+            (min_y, max_y) = (min_y.min(point.y), max_y.max(point.y));
+        }
+        if points.is_empty() {
+            (min_x, min_y, max_x, max_y) = (0.0, 0.0, 0.0, 0.0);
+        }
+        let (width, height) = (max_x - min_x, max_y - min_y);
+        let count = points.len().max(1) as f64;
+        // A straight line of LEDs has no area, a single LED not even a length
+        let cell_size = [
+            (width * height / count).sqrt(),
+            width.max(height) / count,
+            1.0,
+        ]
+        .into_iter()
+        .find(|size| *size > 0.0 && size.is_finite())
+        .unwrap_or(1.0);
+        let columns = (width / cell_size).floor() as usize + 1;
+        let rows = (height / cell_size).floor() as usize + 1;
+
+        let mut index = NearestLedIndex {
+            points,
+            origin: LedPoint::new(min_x, min_y),
+            cell_size,
+            columns,
+            rows,
+            cells: vec![Vec::new(); columns * rows],
+        };
+        for (led, &point) in index.points.iter().enumerate() {
+            let (column, row) = index.cell_of(point);
+            index.cells[row * columns + column].push(led);
+        }
+        index
+    }
+
+    // The cell a point falls in, or the nearest cell on the edge for one outside the grid
+    fn cell_of(&self, point: LedPoint) -> (usize, usize) {
+        let along = |value: f64, origin: f64, count: usize| {
+            (((value - origin) / self.cell_size).floor().max(0.0) as usize).min(count - 1)
+        };
+        (
+            along(point.x, self.origin.x, self.columns),
+            along(point.y, self.origin.y, self.rows),
+        )
+    }
+
+    /// Index of and distance to the LED closest to `point`, like `nearest_led`.
+    pub fn nearest(&self, point: LedPoint) -> (usize, f64) {
+        if self.points.is_empty() {
+            return (0, f64::INFINITY);
+        }
+        let (column, row) = self.cell_of(point);
+        let (column, row) = (column as isize, row as isize);
+        let mut best: Option<(f64, usize)> = None;
+        let visit = |cell_column: isize, cell_row: isize, best: &mut Option<(f64, usize)>| {
+            if cell_column < 0
+                || cell_row < 0
+                || cell_column >= self.columns as isize
+                || cell_row >= self.rows as isize
+            {
+                return;
+            }
+            let cell = cell_row as usize * self.columns + cell_column as usize;
+            for &led in &self.cells[cell] {
+                let distance = point.distance(self.points[led]);
+                if best.is_none_or(|(best_distance, best_led)| {
+                    distance < best_distance || (distance == best_distance && led < best_led)
+                }) {
+                    *best = Some((distance, led));
+                }
+            }
+        };
+        // Ring by ring outwards; LEDs beyond ring `ring` are at least `ring` cells away
+        for ring in 0..=self.columns.max(self.rows) as isize {
+            for offset in -ring..=ring {
+                visit(column + offset, row - ring, &mut best);
+                if ring > 0 {
+                    visit(column + offset, row + ring, &mut best);
+                }
+            }
+            for offset in -ring + 1..ring {
+                visit(column - ring, row + offset, &mut best);
+                visit(column + ring, row + offset, &mut best);
+            }
+            if best.is_some_and(|(distance, _)| distance < ring as f64 * self.cell_size) {
+                break;
+            }
+        }
+        best.map_or((0, f64::INFINITY), |(distance, led)| (led, distance))
+    }
+
+    /// Indexes of the LEDs at most `radius` from `point`, in ascending order.
+    pub fn within(&self, point: LedPoint, radius: f64) -> Vec<usize> {
+        if self.points.is_empty() || radius.is_nan() {
+            return Vec::new();
+        }
+        let span = |value: f64, origin: f64, count: usize| {
+            let cell = |value: f64| {
+                (((value - origin) / self.cell_size).floor().max(0.0) as usize).min(count - 1)
+            };
+            cell(value - radius)..=cell(value + radius)
+        };
+        let mut leds: Vec<usize> = span(point.y, self.origin.y, self.rows)
+            .flat_map(|row| {
+                span(point.x, self.origin.x, self.columns)
+                    .flat_map(move |column| &self.cells[row * self.columns + column])
+            })
+            .copied()
+            .filter(|&led| point.distance(self.points[led]) <= radius)
+            .collect();
+        leds.sort_unstable();
+        leds
+    }
+}
+
+/// Memoizes nearest-LED queries per cell of a grid over telemetry positions. The first query
+/// in a cell collects the LEDs that can be nearest to any point of it; every query then measures
+/// just those from its own point, so it gets the same answer as `NearestLedIndex::nearest`.
+pub struct NearestLedCache<'a> {
+    index: &'a NearestLedIndex,
+    cache: HashMap<(i64, i64), Vec<usize>>, // Candidate LEDs of each cell, by index
+}
+
+impl<'a> NearestLedCache<'a> {
+    pub fn new(index: &'a NearestLedIndex) -> Self {
+        NearestLedCache {
+            index,
+            cache: HashMap::new(),
+        }
+    }
+
+    pub fn nearest(&mut self, point: TelemetryPoint) -> (usize, f64) {
+        let cell = (
+            (point.x / NEAREST_CACHE_CELL_SIZE).floor() as i64,
+            (point.y / NEAREST_CACHE_CELL_SIZE).floor() as i64,
+        );
+        if !self.cache.contains_key(&cell) {
+            // Simple bound: start over instead of tracking recency
+            if self.cache.len() >= NEAREST_CACHE_CAPACITY {
+                self.cache.clear();
+            }
+            self.cache.insert(cell, self.candidates(cell));
+        }
+
+        let point = to_led_space(point);
+        let mut best: Option<(f64, usize)> = None;
+        for &led in &self.cache[&cell] {
+            let distance = point.distance(self.index.points[led]);
+            // Candidates are in index order, so ties keep the lower index
+            if best.is_none_or(|(best_distance, _)| distance < best_distance) {
+                best = Some((distance, led));
+            }
+        }
+        best.map_or((0, f64::INFINITY), |(distance, led)| (led, distance))
+    }
+
+    // Every point of the cell is within half a diagonal of its center, and so within that plus
+    // the center's nearest distance of some LED. Its nearest LED is therefore no farther than
+    // a whole diagonal plus that distance from the center.
+    fn candidates(&self, cell: (i64, i64)) -> Vec<usize> {
+        let center = to_led_space(TelemetryPoint::new(
+            (cell.0 as f64 + 0.5) * NEAREST_CACHE_CELL_SIZE,
+            (cell.1 as f64 + 0.5) * NEAREST_CACHE_CELL_SIZE,
+        ));
+        let (_, distance) = self.index.nearest(center);
+        let reach = distance + NEAREST_CACHE_CELL_SIZE * std::f64::consts::SQRT_2;
+        // Leeway for rounding, so an LED exactly at the bound isn't missed
+        self.index.within(center, reach * (1.0 + 1e-9) + 1e-9)
+    }
+}
+
+// Where a telemetry position lies on the board. Layouts are drawn in the telemetry's units,
+// so it's the same numbers; this is the one place the two spaces meet.
+fn to_led_space(point: TelemetryPoint) -> LedPoint {
+    LedPoint::new(point.x, point.y)
+}
+
+/// The LEDs a car at `point` on `led_index` lights and the share of each, adding up to 1: its
+/// LED and whichever neighbour along the strip is closer, each by the inverse of its distance.
+/// So a car 30% of the way from one LED to the next lights them 70% and 30%. Just its LED
+/// when it sits on it or there's no other.
+pub fn blend_weights(
+    coordinates: &[LedPoint],
+    led_index: usize,
+    point: TelemetryPoint,
+) -> Vec<(usize, f32)> {
+    let count = coordinates.len();
+    let point = to_led_space(point);
+    let to_led = coordinates
+        .get(led_index)
+        .map_or(0.0, |&led| point.distance(led));
+    if count < 2 || to_led == 0.0 {
+        return vec![(led_index, 1.0)];
+    }
+    let (neighbour, to_neighbour) = [(led_index + count - 1) % count, (led_index + 1) % count]
+        .into_iter()
+        .map(|index| (index, point.distance(coordinates[index])))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or_default();
+    let share = (to_neighbour / (to_led + to_neighbour)) as f32;
+    vec![(led_index, share), (neighbour, 1.0 - share)]
+}
+
+/// Index of and distance to the LED closest to `point`; an infinite distance when there are
+/// no LEDs, so the sample is off track.
+pub fn nearest_led(coordinates: &[LedPoint], point: LedPoint) -> (usize, f64) {
+    coordinates
+        .iter()
+        .enumerate()
+        .map(|(index, coord)| (index, point.distance(*coord)))
+        .min_by(|(_, dist_a), (_, dist_b)| {
+            dist_a
+                .partial_cmp(dist_b)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .unwrap_or((0, f64::INFINITY))
+}
+
+/// Datasets at least this large are snapped to LEDs on all cores; smaller ones aren't worth
+/// the thread pool overhead.
+pub const PARALLEL_MAPPING_THRESHOLD: usize = 20_000;
+
+/// The nearest LED index and distance of every sample, in input order. The parallel path gives
+/// each worker its own `NearestLedCache`; since cached lookups are exact, both paths return
+/// identical results.
+pub fn snap_to_leds(
+    raw_data: &[LocationData],
+    coordinates: &[LedPoint],
+    parallel: bool,
+) -> Vec<(usize, f64)> {
+    snap_to_index(raw_data, &NearestLedIndex::new(coordinates), parallel)
+}
+
+/// Like `snap_to_leds`, with an index built before.
+pub fn snap_to_index(
+    raw_data: &[LocationData],
+    index: &NearestLedIndex,
+    parallel: bool,
+) -> Vec<(usize, f64)> {
+    if parallel {
+        raw_data
+            .par_iter()
+            .map_init(
+                || NearestLedCache::new(index),
+                |nearest_cache, data| nearest_cache.nearest(data.point),
+            )
+            .collect()
+    } else {
+        let mut nearest_cache = NearestLedCache::new(index);
+        raw_data
+            .iter()
+            .map(|data| nearest_cache.nearest(data.point))
+            .collect()
+    }
+}
+
+/// Snaps every sample to its nearest LED, dropping samples farther than `max_snap_distance`.
+pub fn generate_run_race_data(
+    raw_data: &[LocationData],
+    coordinates: &[LedPoint],
+    max_snap_distance: f64,
+) -> Result<(Vec<RunRace>, MappingStats), AppError> {
+    let mut mapper = LedMapper::new(coordinates, max_snap_distance)?;
+    let run_race_data = mapper.map(raw_data);
+    Ok((run_race_data, mapper.finish()))
+}
+
+/// Snaps samples to their nearest LEDs a batch at a time, e.g. a driver's samples, so each
+/// batch can be dropped before the next one is mapped. The stats add up over the batches.
+pub struct LedMapper {
+    index: NearestLedIndex,
+    stats: MappingStats,
+    hysteresis_margin: Option<f64>,
+    hysteresis_ratio: Option<f64>,
+    assigned: HashMap<u32, usize>, // The LED each driver is on, as of their last sample on track
+    elevation: Vec<(f64, usize)>,  // Sum and count of the z snapped to each LED, by LED
+    pits: Vec<(Range<usize>, f64)>, // LEDs of each pit segment and its own max snap distance
+    track: Option<(NearestLedIndex, Vec<usize>)>, // The other LEDs, with their layout indices
+}
+
+impl LedMapper {
+    /// Fails on a layout without LEDs, as every sample would be off track.
+    pub fn new(coordinates: &[LedPoint], max_snap_distance: f64) -> Result<Self, AppError> {
+        if coordinates.is_empty() {
+            return Err(AppError::LayoutInvalid {
+                reason: "there are no LEDs to map the location samples onto".to_string(),
+            });
+        }
+        Ok(LedMapper {
+            index: NearestLedIndex::new(coordinates),
+            stats: MappingStats {
+                max_snap_distance,
+                ..MappingStats::default()
+            },
+            hysteresis_margin: None,
+            hysteresis_ratio: None,
+            assigned: HashMap::new(),
+            elevation: vec![(0.0, 0); coordinates.len()],
+            pits: Vec::new(),
+            track: None,
+        })
+    }
+
+    /// Sets the LEDs of the pit segments. A sample nearest a pit LED only goes on it within
+    /// that segment's own snap distance, its median spacing times `snap_distance_factor`, so
+    /// cars on the straight next to the pit lane stay on the track; the others go on the
+    /// nearest track LED.
+    pub fn set_pit_leds(&mut self, pit_leds: &[Range<usize>], snap_distance_factor: f64) {
+        let points = &self.index.points;
+        let leds =
+            |range: &Range<usize>| range.start.min(points.len())..range.end.min(points.len());
+        self.pits = pit_leds
+            .iter()
+            .map(|range| {
+                let spacing = median_led_spacing(&points[leds(range)]);
+                (leds(range), spacing * snap_distance_factor)
+            })
+            .collect();
+        let track: Vec<usize> = (0..points.len())
+            .filter(|led| !self.pits.iter().any(|(leds, _)| leds.contains(led)))
+            .collect();
+        self.track = (!self.pits.is_empty() && !track.is_empty()).then(|| {
+            let coordinates: Vec<LedPoint> = track.iter().map(|&led| points[led]).collect();
+            (NearestLedIndex::new(&coordinates), track)
+        });
+    }
+
+    // The LED a sample nearest `nearest` goes on, its distance to it and how far it may be:
+    // the nearest track LED instead of a pit LED beyond its segment's snap distance
+    fn route(&self, point: TelemetryPoint, nearest: usize, distance: f64) -> (usize, f64, f64) {
+        let max_snap_distance = self.stats.max_snap_distance;
+        let Some((index, track)) = &self.track else {
+            return (nearest, distance, max_snap_distance);
+        };
+        match self.pits.iter().find(|(leds, _)| leds.contains(&nearest)) {
+            Some(&(_, max_distance)) if distance <= max_distance => {
+                (nearest, distance, max_distance)
+            }
+            Some(_) => {
+                let (led, distance) = index.nearest(to_led_space(point));
+                (track[led], distance, max_snap_distance)
+            }
+            None => (nearest, distance, max_snap_distance),
+        }
+    }
+
+    /// Keeps a driver on their LED until another one is `margin` closer to them, `ratio` times
+    /// closer, or both when both are set, so a car between two LEDs doesn't flap between them.
+    /// Off when neither is set.
+    pub fn set_hysteresis(&mut self, margin: Option<f64>, ratio: Option<f64>) {
+        self.hysteresis_margin = margin;
+        self.hysteresis_ratio = ratio;
+    }
+
+    // The LED a driver at `point` is shown on when `nearest` is the closest one: the one
+    // they're on unless the hysteresis lets them move
+    fn assign(&mut self, driver_number: u32, point: TelemetryPoint, nearest: usize) -> usize {
+        if self.hysteresis_margin.is_none() && self.hysteresis_ratio.is_none() {
+            return nearest;
+        }
+        let led = match self.assigned.get(&driver_number) {
+            Some(&current) if current != nearest => {
+                let point = to_led_space(point);
+                let to_current = point.distance(self.index.points[current]);
+                let to_nearest = point.distance(self.index.points[nearest]);
+                let margin = self
+                    .hysteresis_margin
+                    .is_none_or(|margin| to_current - to_nearest >= margin);
+                let ratio = self
+                    .hysteresis_ratio
+                    .is_none_or(|ratio| to_current >= to_nearest * ratio);
+                if margin && ratio {
+                    nearest
+                } else {
+                    current
+                }
+            }
+            _ => nearest,
+        };
+        self.assigned.insert(driver_number, led);
+        led
+    }
+
+    /// Maps a batch of samples sorted by date, dropping those farther than the max snap
+    /// distance as off track. A driver's batches must come in date order.
+    pub fn map(&mut self, samples: &[LocationData]) -> Vec<RunRace> {
+        // The snapping is independent per sample; the stats and the hysteresis depend on sample
+        // order, so they're worked out in a sequential pass afterwards
+        let snapped = snap_to_index(
+            samples,
+            &self.index,
+            samples.len() >= PARALLEL_MAPPING_THRESHOLD,
+        );
+        let mut records = Vec::with_capacity(samples.len());
+        for (data, (nearest_index, distance)) in samples.iter().zip(snapped) {
+            let (nearest_index, distance, max_snap_distance) =
+                self.route(data.point, nearest_index, distance);
+            let stats = &mut self.stats;
+            if distance > max_snap_distance {
+                if !stats.dropped_per_driver.contains_key(&data.driver_number) {
+                    warn!(
+                        "Driver {} has samples {:.0} from the nearest LED, dropping them as off track",
+                        data.driver_number, distance
+                    );
+                }
+                stats.dropped_samples += 1;
+                *stats
+                    .dropped_per_driver
+                    .entry(data.driver_number)
+                    .or_insert(0) += 1;
+                stats
+                    .off_track_since
+                    .entry(data.driver_number)
+                    .or_insert(data.date);
+                continue;
+            }
+
+            // Back on track, so any earlier off-track stretch wasn't the final one
+            stats.off_track_since.remove(&data.driver_number);
+            // Where the car is, not where the hysteresis shows it
+            if let Some(z) = data.z {
+                let (sum, count) = &mut self.elevation[nearest_index];
+                *sum += z;
+                *count += 1;
+            }
+            records.push(RunRace {
+                date: data.date,
+                driver_number: data.driver_number,
+                led_index: self.assign(data.driver_number, data.point, nearest_index),
+                point: data.point,
+            });
+        }
+        records
+    }
+
+    pub fn finish(mut self) -> MappingStats {
+        self.stats.led_elevation = self
+            .elevation
+            .iter()
+            .map(|&(sum, count)| (count > 0).then(|| sum / count as f64))
+            .collect();
+        self.stats
+    }
+}
+
+/// The mapping stage, a driver at a time: fills gaps, downsamples and aligns a driver's samples
+/// as `options` say, snaps them to LEDs and collapses repeated positions, then drops the
+/// samples before going on to the next driver. So besides the records kept so far, only one
+/// driver's samples and records are held at once. Each driver's samples must be sorted by
+/// date; the records come out sorted by date, ties in the order of `per_driver`. The counts
+/// of the passes go to `pipeline`. Fails on a layout without LEDs.
+pub fn map_drivers(
+    per_driver: Vec<(u32, Vec<LocationData>)>,
+    coordinates: &[LedPoint],
+    options: &MappingOptions,
+    pipeline: &mut PipelineStats,
+) -> Result<(Vec<RunRace>, MappingStats), AppError> {
+    let millis = |ms: u64| Duration::milliseconds(ms as i64);
+    // Every driver is aligned onto the same grid, from the earliest sample of any of them
+    let origin = per_driver
+        .iter()
+        .filter_map(|(_, samples)| samples.first())
+        .map(|sample| sample.date)
+        .min();
+    let max_snap_distance = median_led_spacing(coordinates) * options.snap_distance_factor;
+    let mut mapper = LedMapper::new(coordinates, max_snap_distance)?;
+    mapper.set_hysteresis(options.hysteresis_margin, options.hysteresis_ratio);
+    mapper.set_pit_leds(&options.pit_leds, options.snap_distance_factor);
+    let mut run_race_data = Vec::new();
+    let mut collapsed = 0;
+    for (_, mut samples) in per_driver {
+        smooth(
+            &mut samples,
+            options.smoothing,
+            options.smoothing_window,
+            options.smoothing_alpha,
+            millis(options.gap_max_ms),
+        );
+        if options.fill_gaps {
+            pipeline.synthetic_samples += fill_gaps(
+                &mut samples,
+                millis(options.gap_min_ms),
+                millis(options.gap_max_ms),
+                millis(options.sample_interval_ms),
+            );
+        }
+        if let Some(interval_ms) = options.downsample_ms {
+            pipeline.downsampled_samples += downsample(&mut samples, millis(interval_ms));
+        }
+        if let (Some(interval_ms), Some(origin)) = (options.align_ms, origin) {
+            samples = align_to_grid(
+                &samples,
+                origin,
+                millis(interval_ms),
+                millis(options.gap_max_ms),
+            );
+            pipeline.aligned_samples += samples.len();
+        }
+
+        let mut records = mapper.map(&samples);
+        drop(samples);
+        pipeline.mapped_samples += records.len();
+        // Aligned data keeps a record per driver and grid step, blended data every position
+        if options.collapse_duplicates
+            && options.align_ms.is_none()
+            && options.positioning == LedPositioning::Nearest
+        {
+            collapsed += collapse_duplicate_positions(&mut records);
+        }
+        run_race_data.append(&mut records);
+    }
+    pipeline.align_ms = options.align_ms;
+
+    // Stable, so drivers keep their order within a date
+    run_race_data.sort_by_key(|run_data| run_data.date);
+    let mut stats = mapper.finish();
+    stats.collapsed_samples = collapsed;
+    Ok((run_race_data, stats))
+}
+
+/// Smooths each driver's positions as `smoothing` says, over `window` samples or with `alpha`.
+/// Gaps longer than `max_gap`, like a red flag, start afresh, so a car isn't dragged across the
+/// infield towards where it shows up again. Dates are left exactly as they are. The samples must
+/// be sorted by date.
+pub fn smooth(
+    samples: &mut [LocationData],
+    smoothing: Smoothing,
+    window: usize,
+    alpha: f64,
+    max_gap: Duration,
+) {
+    if smoothing == Smoothing::Off {
+        return;
+    }
+    let mut by_driver: BTreeMap<u32, Vec<usize>> = BTreeMap::new();
+    for (index, sample) in samples.iter().enumerate() {
+        by_driver
+            .entry(sample.driver_number)
+            .or_default()
+            .push(index);
+    }
+    for indexes in by_driver.into_values() {
+        // Stretches without a gap longer than `max_gap`, smoothed on their own
+        let mut segment_start = 0;
+        for end in 1..=indexes.len() {
+            let gap = indexes
+                .get(end)
+                .map(|&next| samples[next].date - samples[indexes[end - 1]].date);
+            if gap.is_some_and(|gap| gap <= max_gap) {
+                continue;
+            }
+            let segment = &indexes[segment_start..end];
+            let points: Vec<TelemetryPoint> =
+                segment.iter().map(|&index| samples[index].point).collect();
+            let smoothed = match smoothing {
+                Smoothing::Off => points,
+                Smoothing::MovingAverage => moving_average(&points, window),
+                Smoothing::Exponential => exponential(&points, alpha),
+            };
+            for (&index, point) in segment.iter().zip(smoothed) {
+                samples[index].point = point;
+            }
+            segment_start = end;
+        }
+    }
+}
+
+// The mean of the `window` points centred on each, fewer at the ends
+fn moving_average(points: &[TelemetryPoint], window: usize) -> Vec<TelemetryPoint> {
+    let before = window.max(1).saturating_sub(1) / 2;
+    let after = window.max(1) - 1 - before;
+    (0..points.len())
+        .map(|index| {
+            let around =
+                &points[index.saturating_sub(before)..(index + after + 1).min(points.len())];
+            let count = around.len() as f64;
+            TelemetryPoint::new(
+                around.iter().map(|point| point.x).sum::<f64>() / count,
+                around.iter().map(|point| point.y).sum::<f64>() / count,
+            )
+        })
+        .collect()
+}
+
+fn exponential(points: &[TelemetryPoint], alpha: f64) -> Vec<TelemetryPoint> {
+    let alpha = alpha.clamp(0.0, 1.0);
+    let mut smoothed: Vec<TelemetryPoint> = Vec::with_capacity(points.len());
+    for &point in points {
+        let next = match smoothed.last() {
+            Some(last) => last.lerp(point, alpha),
+            None => point,
+        };
+        smoothed.push(next);
+    }
+    smoothed
+}
+
+/// Fills the gaps between consecutive samples of a driver that last from `min_gap` to
+/// `max_gap` with samples every `interval`, interpolated linearly between the two and marked
+/// synthetic. The samples stay sorted by date. Returns the number of samples added.
+pub fn fill_gaps(
+    samples: &mut Vec<LocationData>,
+    min_gap: Duration,
+    max_gap: Duration,
+    interval: Duration,
+) -> usize {
+    let interval_ms = interval.num_milliseconds().max(1);
+    let mut previous: HashMap<u32, usize> = HashMap::new();
+    let mut synthetic = Vec::new();
+    for (index, sample) in samples.iter().enumerate() {
+        if let Some(&before) = previous.get(&sample.driver_number) {
+            let from = &samples[before];
+            let gap = sample.date - from.date;
+            if gap >= min_gap && gap <= max_gap {
+                let gap_ms = gap.num_milliseconds();
+                for offset in (interval_ms..gap_ms).step_by(interval_ms as usize) {
+                    let share = offset as f64 / gap_ms as f64;
+                    synthetic.push(LocationData {
+                        point: from.point.lerp(sample.point, share),
+                        z: lerp_z(from.z, sample.z, share),
+                        date: from.date + Duration::milliseconds(offset),
+                        driver_number: sample.driver_number,
+                        synthetic: true,
+                    });
+                }
+            }
+        }
+        previous.insert(sample.driver_number, index);
+    }
+
+    let added = synthetic.len();
+    samples.extend(synthetic);
+    samples.sort_by_key(|sample| sample.date);
+    added
+}
+
+/// Resamples every driver onto one grid of `interval` steps from `origin`, so all drivers
+/// have a sample at the same instants. Each grid sample is interpolated between the
+/// driver's samples around it and marked synthetic; there are none before a driver's first
+/// sample, after its last, or within gaps longer than `max_gap`. Sorted by date, then driver.
+pub fn align_to_grid(
+    samples: &[LocationData],
+    origin: DateTime<Utc>,
+    interval: Duration,
+    max_gap: Duration,
+) -> Vec<LocationData> {
+    let interval_ms = interval.num_milliseconds().max(1);
+    let mut by_driver: BTreeMap<u32, Vec<&LocationData>> = BTreeMap::new();
+    for sample in samples {
+        by_driver
+            .entry(sample.driver_number)
+            .or_default()
+            .push(sample);
+    }
+
+    let mut aligned = Vec::new();
+    for (driver_number, driver_samples) in by_driver {
+        let first = driver_samples[0].date;
+        let last = driver_samples[driver_samples.len() - 1].date;
+        // The first grid step at or after the driver's first sample
+        let offset = (first - origin).num_milliseconds();
+        let mut step = (offset + interval_ms - 1).div_euclid(interval_ms);
+        let mut before = 0;
+        loop {
+            let date = origin + Duration::milliseconds(step * interval_ms);
+            if date > last {
+                break;
+            }
+            while driver_samples[before + 1..]
+                .first()
+                .is_some_and(|next| next.date <= date)
+            {
+                before += 1;
+            }
+            let from = driver_samples[before];
+            let point = match driver_samples.get(before + 1) {
+                _ if from.date == date => Some((from.point, from.z)),
+                Some(to) if to.date - from.date <= max_gap => {
+                    let share = (date - from.date).num_milliseconds() as f64
+                        / (to.date - from.date).num_milliseconds() as f64;
+                    Some((
+                        from.point.lerp(to.point, share),
+                        lerp_z(from.z, to.z, share),
+                    ))
+                }
+                _ => None,
+            };
+            if let Some((point, z)) = point {
+                aligned.push(LocationData {
+                    point,
+                    z,
+                    date,
+                    driver_number,
+                    synthetic: true,
+                });
+            }
+            step += 1;
+        }
+    }
+    // Stable, so drivers stay in order within each step
+    aligned.sort_by_key(|sample| sample.date);
+    aligned
+}
+
+// The elevation part way between two samples; unknown unless both have one
+fn lerp_z(from: Option<f64>, to: Option<f64>, share: f64) -> Option<f64> {
+    let (from, to) = (from?, to?);
+    Some(from + (to - from) * share)
+}
+
+/// Keeps at most one sample per driver and `interval`, counted from the driver's first sample:
+/// the one closest to each interval boundary, so the kept samples stay on time. A driver's
+/// first and last samples are always kept. Returns the number of samples removed.
+pub fn downsample(samples: &mut Vec<LocationData>, interval: Duration) -> usize {
+    let interval = interval.num_milliseconds().max(1);
+    let mut first_index: HashMap<u32, usize> = HashMap::new();
+    let mut last_index: HashMap<u32, usize> = HashMap::new();
+    for (index, sample) in samples.iter().enumerate() {
+        first_index.entry(sample.driver_number).or_insert(index);
+        last_index.insert(sample.driver_number, index);
+    }
+
+    // Index of the sample kept for each driver and boundary, with its distance to the boundary
+    let mut closest: HashMap<(u32, i64), (usize, i64)> = HashMap::new();
+    for (index, sample) in samples.iter().enumerate() {
+        let first = &samples[first_index[&sample.driver_number]];
+        let offset = (sample.date - first.date).num_milliseconds();
+        let boundary = (offset + interval / 2).div_euclid(interval);
+        let distance = (offset - boundary * interval).abs();
+        let is_last = last_index[&sample.driver_number] == index;
+        closest
+            .entry((sample.driver_number, boundary))
+            .and_modify(|kept| {
+                if is_last || distance < kept.1 {
+                    *kept = (index, distance);
+                }
+            })
+            .or_insert((index, distance));
+    }
+
+    // A first sample sits right on its boundary, so only the driver's last sample can take it
+    let mut keep = vec![false; samples.len()];
+    for index in closest
+        .into_values()
+        .map(|(index, _)| index)
+        .chain(first_index.into_values())
+    {
+        keep[index] = true;
+    }
+    let before = samples.len();
+    let mut index = 0;
+    samples.retain(|_| {
+        index += 1;
+        keep[index - 1]
+    });
+    before - samples.len()
+}
+
+/// Drops every record that maps its driver to the same LED as the driver's previous record,
+/// keeping the first arrival so timing is preserved. Returns the number of records removed.
+pub fn collapse_duplicate_positions(run_race_data: &mut Vec<RunRace>) -> usize {
+    let before = run_race_data.len();
+    let mut last_led: HashMap<u32, usize> = HashMap::new();
+    run_race_data.retain(|run_data| {
+        last_led.insert(run_data.driver_number, run_data.led_index) != Some(run_data.led_index)
+    });
+    before - run_race_data.len()
+}
@@ -0,0 +1,196 @@
+use crate::laps::RaceProgress;
+use crate::overtakes::Overtake;
+use crate::race_events::pit_exits;
+use crate::simulation::Rgb;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The event markers on the timeline under the track.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MarkerConfig {
+    pub enabled: bool,
+    pub lead_in_secs: f64, // Clicking a marker seeks this long before its event
+}
+
+impl Default for MarkerConfig {
+    fn default() -> Self {
+        MarkerConfig {
+            enabled: true,
+            lead_in_secs: 5.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkerKind {
+    PitStop,
+    Overtake,
+    Yellow, // Yellow flags and safety car periods
+    Red,
+}
+
+impl MarkerKind {
+    pub fn color(self) -> Rgb {
+        match self {
+            MarkerKind::PitStop => [255, 255, 255],
+            MarkerKind::Overtake => [0, 200, 80],
+            MarkerKind::Yellow => [255, 200, 0],
+            MarkerKind::Red => [230, 0, 0],
+        }
+    }
+}
+
+/// An event on the timeline: a tick, or a band while `end` is after `start`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Marker {
+    pub kind: MarkerKind,
+    pub start: f64, // Race seconds from the first record
+    pub end: f64,   // Infinite for a band still open when the messages end
+    pub label: String,
+}
+
+impl Marker {
+    fn tick(kind: MarkerKind, start: f64, label: String) -> Marker {
+        Marker {
+            kind,
+            start,
+            end: start,
+            label,
+        }
+    }
+}
+
+/// The markers of the pit stops, the overtakes and the flag and safety car periods in the race
+/// control messages, positioned from `first_record`, bands first so ticks are drawn over them.
+pub fn event_markers(
+    progress: &RaceProgress,
+    overtakes: &[Overtake],
+    first_record: DateTime<Utc>,
+) -> Vec<Marker> {
+    let secs = |date: DateTime<Utc>| (date - first_record).num_milliseconds() as f64 / 1000.0;
+    let mut markers = flag_bands(progress, secs);
+    markers.extend(
+        pit_exits(progress)
+            .into_iter()
+            .map(|(date, driver_number)| {
+                Marker::tick(
+                    MarkerKind::PitStop,
+                    secs(date),
+                    format!("{} left the pits", driver_number),
+                )
+            }),
+    );
+    markers.extend(overtakes.iter().map(|overtake| {
+        Marker::tick(
+            MarkerKind::Overtake,
+            secs(overtake.date),
+            format!(
+                "{} passed {} for P{}",
+                overtake.driver_number, overtake.overtaken, overtake.position
+            ),
+        )
+    }));
+    markers
+}
+
+// Red flags until the track goes green again, safety cars until they come in and sector
+// yellows until their sector or the whole track is cleared
+fn flag_bands(progress: &RaceProgress, secs: impl Fn(DateTime<Utc>) -> f64) -> Vec<Marker> {
+    let mut bands = Vec::new();
+    let mut red: Option<f64> = None;
+    let mut safety_car: Option<(f64, &str)> = None;
+    let mut yellows: BTreeMap<u32, f64> = BTreeMap::new();
+    for message in progress.race_control() {
+        let time = secs(message.date);
+        let flag = message.flag.as_deref().unwrap_or_default();
+        let track = message.scope.as_deref() == Some("Track");
+        if message.category == "SafetyCar" {
+            let text = message.message.to_uppercase();
+            if text.contains("DEPLOYED") && safety_car.is_none() {
+                let label = if text.contains("VIRTUAL") {
+                    "Virtual safety car"
+                } else {
+                    "Safety car"
+                };
+                safety_car = Some((time, label));
+            } else if text.contains("IN THIS LAP") || text.contains("ENDING") {
+                if let Some((start, label)) = safety_car.take() {
+                    bands.push(band(MarkerKind::Yellow, start, time, label.to_string()));
+                }
+            }
+            continue;
+        }
+        match (flag, track, message.sector) {
+            ("RED", true, _) => {
+                red.get_or_insert(time);
+            }
+            ("GREEN" | "CLEAR", true, _) => {
+                if let Some(start) = red.take() {
+                    bands.push(band(MarkerKind::Red, start, time, "Red flag".to_string()));
+                }
+                for (sector, start) in std::mem::take(&mut yellows) {
+                    bands.push(yellow_band(sector, start, time));
+                }
+            }
+            ("YELLOW" | "DOUBLE YELLOW", false, Some(sector)) => {
+                yellows.entry(sector).or_insert(time);
+            }
+            ("GREEN" | "CLEAR", false, Some(sector)) => {
+                if let Some(start) = yellows.remove(&sector) {
+                    bands.push(yellow_band(sector, start, time));
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = red {
+        bands.push(band(
+            MarkerKind::Red,
+            start,
+            f64::INFINITY,
+            "Red flag".to_string(),
+        ));
+    }
+    if let Some((start, label)) = safety_car {
+        bands.push(band(
+            MarkerKind::Yellow,
+            start,
+            f64::INFINITY,
+            label.to_string(),
+        ));
+    }
+    for (sector, start) in yellows {
+        bands.push(yellow_band(sector, start, f64::INFINITY));
+    }
+    bands.sort_by(|a, b| a.start.total_cmp(&b.start));
+    bands
+}
+
+fn band(kind: MarkerKind, start: f64, end: f64, label: String) -> Marker {
+    Marker {
+        kind,
+        start,
+        end,
+        label,
+    }
+}
+
+fn yellow_band(sector: u32, start: f64, end: f64) -> Marker {
+    band(
+        MarkerKind::Yellow,
+        start,
+        end,
+        format!("Yellow flag in sector {}", sector),
+    )
+}
+
+/// Where `secs` of a timeline `duration` long falls between `left` and `right`, clamped to them.
+pub fn timeline_x(secs: f64, duration: f64, left: f32, right: f32) -> f32 {
+    if duration <= 0.0 {
+        return left;
+    }
+    let share = (secs / duration).clamp(0.0, 1.0) as f32;
+    left + share * (right - left)
+}
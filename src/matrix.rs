@@ -0,0 +1,151 @@
+use crate::error::AppError;
+use crate::simulation::Rgb;
+use crate::sink::{LedSink, OutputFrame};
+use crate::space::LedPoint;
+use crate::viewport::Bounds;
+use serde::{Deserialize, Serialize};
+
+/// An off-the-shelf LED matrix instead of a strip shaped like the circuit; the hardware outputs
+/// then get one pixel per grid cell.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MatrixConfig {
+    pub enabled: bool,
+    pub width: usize,
+    pub height: usize,
+    pub serpentine: bool, // Every other row runs right to left, as wired on most matrices
+}
+
+impl Default for MatrixConfig {
+    fn default() -> Self {
+        MatrixConfig {
+            enabled: false,
+            width: 16,
+            height: 16,
+            serpentine: false,
+        }
+    }
+}
+
+/// The layout projected onto a grid: every LED goes to the nearest cell after stretching the
+/// layout's bounding box over the grid. LEDs landing on the same cell share it.
+#[derive(Debug, Clone)]
+pub struct LedGrid {
+    cells: Vec<(usize, usize)>, // Column and row of each LED; row 0 is the top
+    width: usize,
+    height: usize,
+    serpentine: bool,
+}
+
+impl LedGrid {
+    pub fn new(coordinates: &[LedPoint], config: &MatrixConfig) -> Result<LedGrid, AppError> {
+        if config.width == 0 || config.height == 0 {
+            return Err(AppError::Config {
+                reason: format!(
+                    "matrix.width and matrix.height must be above 0, not {}x{}",
+                    config.width, config.height
+                ),
+            });
+        }
+
+        // Larger y is further up, like in the window
+        let bounds = Bounds::from_coordinates(coordinates);
+        let nearest = |share: f64, cells: usize| {
+            let share = if share.is_finite() { share } else { 0.5 };
+            (share * (cells - 1) as f64).round() as usize
+        };
+        let cells = coordinates
+            .iter()
+            .map(|coord| {
+                (
+                    nearest((coord.x - bounds.min_x) / bounds.width(), config.width),
+                    nearest((bounds.max_y - coord.y) / bounds.height(), config.height),
+                )
+            })
+            .collect();
+        Ok(LedGrid {
+            cells,
+            width: config.width,
+            height: config.height,
+            serpentine: config.serpentine,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixel_count(&self) -> usize {
+        self.width * self.height
+    }
+
+    /// Column and row of each LED.
+    pub fn cells(&self) -> &[(usize, usize)] {
+        &self.cells
+    }
+
+    /// Index of a cell in the pixel buffer.
+    pub fn pixel(&self, column: usize, row: usize) -> usize {
+        let column = if self.serpentine && row & 1 == 1 {
+            self.width - 1 - column
+        } else {
+            column
+        };
+        row * self.width + column
+    }
+
+    /// The pixel buffer for a frame of layout colors, row by row from the top. A cell shared
+    /// by several LEDs shows the brightest of them; cells without any LED are black.
+    pub fn map(&self, leds: &[Rgb]) -> Vec<Rgb> {
+        let brightness = |color: &Rgb| color.iter().map(|&channel| channel as u32).sum::<u32>();
+        let mut pixels = vec![[0, 0, 0]; self.pixel_count()];
+        for (&(column, row), color) in self.cells.iter().zip(leds) {
+            let pixel = &mut pixels[self.pixel(column, row)];
+            if brightness(color) > brightness(pixel) {
+                *pixel = *color;
+            }
+        }
+        pixels
+    }
+}
+
+/// Feeds another sink the grid's pixel buffer instead of the layout's LEDs.
+pub struct MatrixSink {
+    inner: Box<dyn LedSink>,
+    grid: LedGrid,
+}
+
+impl MatrixSink {
+    pub fn new(inner: Box<dyn LedSink>, grid: LedGrid) -> MatrixSink {
+        MatrixSink { inner, grid }
+    }
+}
+
+impl LedSink for MatrixSink {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn start(&mut self) -> Result<(), AppError> {
+        self.inner.start()
+    }
+
+    fn submit(&mut self, frame: &OutputFrame) -> Result<(), AppError> {
+        self.inner.submit(&OutputFrame {
+            leds: self.grid.map(&frame.leds),
+            brightness: frame.brightness,
+            timestamp: frame.timestamp,
+            state: frame.state,
+            speed: frame.speed,
+            drivers: frame.drivers.clone(),
+        })
+    }
+
+    fn shutdown(&mut self) {
+        self.inner.shutdown();
+    }
+}
@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[cfg(feature = "metrics")]
+pub use exporter::{install, routes, MetricsServer};
+
+pub const FRAMES_RENDERED: &str = "f1_frames_rendered_total";
+pub const TICK_DURATION: &str = "f1_tick_duration_seconds";
+pub const SINK_FRAMES_SENT: &str = "f1_sink_frames_sent_total";
+pub const SINK_FRAMES_SKIPPED: &str = "f1_sink_frames_skipped_total";
+pub const API_REQUESTS: &str = "f1_api_requests_total";
+pub const API_FAILURES: &str = "f1_api_failures_total";
+pub const UPTIME: &str = "f1_uptime_seconds";
+
+/// Settings of the Prometheus `/metrics` endpoint, served when the `metrics` feature is built
+/// in. With the control API running it's served there instead, behind the same token.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+    pub bind: String,
+    pub port: u16,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        MetricsConfig {
+            enabled: false,
+            bind: "127.0.0.1".to_string(),
+            port: 9464,
+        }
+    }
+}
+
+/// A frame went out to the outputs; its rate is the frames rendered per second.
+pub fn frame_rendered() {
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!(FRAMES_RENDERED).increment(1);
+}
+
+/// The simulation ticked, taking `duration`.
+pub fn tick(duration: Duration) {
+    #[cfg(feature = "metrics")]
+    ::metrics::histogram!(TICK_DURATION).record(duration.as_secs_f64());
+    #[cfg(not(feature = "metrics"))]
+    let _ = duration;
+}
+
+/// The sink called `sink` sent a frame, or skipped one equal to the last it sent.
+pub fn sink_frame(sink: &str, sent: bool) {
+    #[cfg(feature = "metrics")]
+    {
+        let name = if sent {
+            SINK_FRAMES_SENT
+        } else {
+            SINK_FRAMES_SKIPPED
+        };
+        ::metrics::counter!(name, "sink" => sink.to_string()).increment(1);
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = (sink, sent);
+}
+
+/// A request to the API was answered successfully, or failed.
+pub fn api_request(ok: bool) {
+    #[cfg(feature = "metrics")]
+    {
+        ::metrics::counter!(API_REQUESTS).increment(1);
+        if !ok {
+            ::metrics::counter!(API_FAILURES).increment(1);
+        }
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = ok;
+}
+
+// The exporter half, only built with the `metrics` feature; without it the functions above
+// record nothing
+#[cfg(feature = "metrics")]
+mod exporter {
+    use super::{MetricsConfig, TICK_DURATION, UPTIME};
+    use crate::error::AppError;
+    use axum::routing::get;
+    use axum::Router;
+    use log::{info, warn};
+    use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder, PrometheusHandle};
+    use std::net::SocketAddr;
+    use std::sync::{Mutex, OnceLock};
+    use std::time::{Duration, Instant};
+    use tokio::runtime::Runtime;
+    use tokio::sync::watch;
+
+    // A tick normally takes well under a millisecond; the top buckets catch seeks
+    const TICK_BUCKETS: [f64; 9] = [
+        0.0001, 0.0002, 0.0005, 0.001, 0.002, 0.005, 0.01, 0.05, 0.25,
+    ];
+    // Histograms keep every sample until they're scraped or this comes round
+    const UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+    static STARTED: OnceLock<Instant> = OnceLock::new();
+
+    /// Starts recording the metrics, once per process; later calls get the same handle.
+    pub fn install() -> Result<PrometheusHandle, AppError> {
+        static HANDLE: Mutex<Option<PrometheusHandle>> = Mutex::new(None);
+        let mut installed = HANDLE.lock().unwrap();
+        if let Some(handle) = &*installed {
+            return Ok(handle.clone());
+        }
+        let error = |err: BuildError| AppError::Config {
+            reason: format!("could not start recording metrics: {}", err),
+        };
+        let handle = PrometheusBuilder::new()
+            .set_buckets_for_metric(Matcher::Full(TICK_DURATION.to_string()), &TICK_BUCKETS)
+            .map_err(error)?
+            .install_recorder()
+            .map_err(error)?;
+        STARTED.get_or_init(Instant::now);
+        let upkeep = handle.clone();
+        std::thread::Builder::new()
+            .name("metrics-upkeep".to_string())
+            .spawn(move || loop {
+                std::thread::sleep(UPKEEP_INTERVAL);
+                upkeep.run_upkeep();
+            })?;
+        *installed = Some(handle.clone());
+        Ok(handle)
+    }
+
+    /// `GET /metrics` in the Prometheus text format, for a server to serve.
+    pub fn routes(handle: PrometheusHandle) -> Router {
+        Router::new().route(
+            "/metrics",
+            get(move || {
+                let handle = handle.clone();
+                async move {
+                    let uptime = STARTED
+                        .get()
+                        .map_or(0.0, |started| started.elapsed().as_secs_f64());
+                    ::metrics::gauge!(UPTIME).set(uptime);
+                    handle.render()
+                }
+            }),
+        )
+    }
+
+    /// Serves the metrics on their own, when there's no control API to serve them.
+    pub struct MetricsServer {
+        runtime: Option<Runtime>,
+        address: SocketAddr,
+        stop: watch::Sender<bool>,
+    }
+
+    impl MetricsServer {
+        pub fn open(config: &MetricsConfig, routes: Router) -> Result<MetricsServer, AppError> {
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(1)
+                .thread_name("metrics")
+                .enable_all()
+                .build()?;
+            let listener = runtime
+                .block_on(tokio::net::TcpListener::bind((
+                    config.bind.as_str(),
+                    config.port,
+                )))
+                .map_err(|err| AppError::Config {
+                    reason: format!(
+                        "could not serve the metrics on {}:{}: {}",
+                        config.bind, config.port, err
+                    ),
+                })?;
+            let address = listener.local_addr()?;
+
+            let (stop, mut stopped) = watch::channel(false);
+            runtime.spawn(async move {
+                let server = axum::serve(listener, routes).with_graceful_shutdown(async move {
+                    let _ = stopped.changed().await;
+                });
+                if let Err(err) = server.await {
+                    warn!("The metrics endpoint stopped: {}", err);
+                }
+            });
+            info!("Serving metrics on http://{}/metrics", address);
+
+            Ok(MetricsServer {
+                runtime: Some(runtime),
+                address,
+                stop,
+            })
+        }
+
+        /// The address the server listens on, with the actual port when the config asked for 0.
+        pub fn address(&self) -> SocketAddr {
+            self.address
+        }
+    }
+
+    impl Drop for MetricsServer {
+        fn drop(&mut self) {
+            let _ = self.stop.send(true);
+            if let Some(runtime) = self.runtime.take() {
+                runtime.shutdown_timeout(Duration::from_secs(1));
+            }
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use crate::error::AppError;
+use crate::simulation::{PlaybackState, Rgb};
+use crate::sink::{LedSink, OutputFrame};
+use crate::track_progress::DriverProgress;
+use log::{debug, info, warn};
+use rumqttc::{Client, MqttOptions, QoS};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::thread;
+use std::time::Duration;
+
+// Publishes waiting for the event loop beyond this many are dropped instead of queued
+const REQUEST_CAPACITY: usize = 16;
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MqttConfig {
+    pub enabled: bool,
+    pub broker_url: String, // mqtt://host:port; TLS brokers aren't supported
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub qos: u8, // 0, 1 or 2
+    pub frame_topic: String,
+    pub state_topic: String,
+    pub events_topic: String,
+    pub max_fps: f64, // Frame publish rate; state changes and events are always sent
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        MqttConfig {
+            enabled: false,
+            broker_url: "mqtt://localhost:1883".to_string(),
+            client_id: "f1-led-circuit".to_string(),
+            username: None,
+            password: None,
+            qos: 0,
+            frame_topic: "f1led/frame".to_string(),
+            state_topic: "f1led/state".to_string(),
+            events_topic: "f1led/events".to_string(),
+            max_fps: 10.0,
+        }
+    }
+}
+
+/// Publishes frames, playback state and race events to an MQTT broker. The connection runs on
+/// its own thread; publishing never blocks, and drops messages while the broker is behind.
+/// Clones share the connection, so race events can be published next to the frame sink.
+#[derive(Clone)]
+pub struct MqttPublisher {
+    client: Client,
+    config: MqttConfig,
+    qos: QoS,
+    last_state: Option<(PlaybackState, f64, Vec<DriverProgress>)>,
+}
+
+impl MqttPublisher {
+    pub fn connect(config: &MqttConfig) -> Result<MqttPublisher, AppError> {
+        let (host, port) = parse_broker_url(&config.broker_url)?;
+        let qos = match config.qos {
+            0 => QoS::AtMostOnce,
+            1 => QoS::AtLeastOnce,
+            2 => QoS::ExactlyOnce,
+            other => {
+                return Err(AppError::Config {
+                    reason: format!("mqtt.qos must be 0, 1 or 2, got {}", other),
+                })
+            }
+        };
+
+        let mut options = MqttOptions::new(&config.client_id, host, port);
+        options.set_keep_alive(KEEP_ALIVE);
+        if let Some(username) = &config.username {
+            options.set_credentials(username, config.password.clone().unwrap_or_default());
+        }
+
+        let (client, mut connection) = Client::new(options, REQUEST_CAPACITY);
+        let broker_url = config.broker_url.clone();
+        thread::Builder::new()
+            .name("mqtt".to_string())
+            .spawn(move || {
+                // Iterating drives the connection and reconnects; it ends once the client is gone
+                for event in connection.iter() {
+                    if let Err(err) = event {
+                        warn!("MQTT connection to {} failed: {}", broker_url, err);
+                        thread::sleep(RECONNECT_DELAY);
+                    }
+                }
+            })?;
+        info!("Publishing to MQTT broker {}", config.broker_url);
+
+        Ok(MqttPublisher {
+            client,
+            config: config.clone(),
+            qos,
+            last_state: None,
+        })
+    }
+
+    /// Publishes the frame as a JSON array of `[r, g, b]`.
+    pub fn publish_frame(&self, leds: &[Rgb]) {
+        self.publish(&self.config.frame_topic, false, json!(leds));
+    }
+
+    /// Publishes the playback state when it, the speed or a driver's progress round the lap
+    /// changed since the last call. The message is retained, so late subscribers see the
+    /// current state.
+    pub fn publish_state(
+        &mut self,
+        state: PlaybackState,
+        speed: f64,
+        race_time: f64,
+        drivers: &[DriverProgress],
+    ) {
+        let unchanged = self
+            .last_state
+            .as_ref()
+            .is_some_and(|last| (last.0, last.1, last.2.as_slice()) == (state, speed, drivers));
+        if unchanged {
+            return;
+        }
+        self.last_state = Some((state, speed, drivers.to_vec()));
+        let payload = json!({
+            "state": state,
+            "speed": speed,
+            "race_time": race_time,
+            "drivers": drivers,
+        });
+        self.publish(&self.config.state_topic, true, payload);
+    }
+
+    /// Publishes a race event such as a pit stop or flag.
+    pub fn publish_event(&self, event: &impl Serialize) {
+        match serde_json::to_value(event) {
+            Ok(payload) => self.publish(&self.config.events_topic, false, payload),
+            Err(err) => warn!("Could not encode an MQTT event: {}", err),
+        }
+    }
+
+    fn publish(&self, topic: &str, retain: bool, payload: serde_json::Value) {
+        if let Err(err) = self
+            .client
+            .try_publish(topic, self.qos, retain, payload.to_string())
+        {
+            debug!("Dropping MQTT message on {}: {}", topic, err);
+        }
+    }
+}
+
+impl LedSink for MqttPublisher {
+    fn name(&self) -> &str {
+        "mqtt"
+    }
+
+    fn submit(&mut self, frame: &OutputFrame) -> Result<(), AppError> {
+        self.publish_frame(&frame.dimmed());
+        self.publish_state(
+            frame.state,
+            frame.speed,
+            frame.timestamp.as_secs_f64(),
+            &frame.drivers,
+        );
+        Ok(())
+    }
+}
+
+fn parse_broker_url(url: &str) -> Result<(String, u16), AppError> {
+    let invalid = || AppError::Config {
+        reason: format!(
+            "mqtt.broker_url must look like mqtt://host:1883, got {}",
+            url
+        ),
+    };
+    let address = url
+        .strip_prefix("mqtt://")
+        .or_else(|| url.strip_prefix("tcp://"))
+        .unwrap_or(url)
+        .trim_end_matches('/');
+    match address.rsplit_once(':') {
+        Some((host, port)) => Ok((host.to_string(), port.parse().map_err(|_| invalid())?)),
+        None if !address.is_empty() => Ok((address.to_string(), 1883)),
+        None => Err(invalid()),
+    }
+}
@@ -0,0 +1,94 @@
+use chrono::{NaiveTime, Timelike};
+use std::time::Duration;
+use web_time::Instant;
+
+/// How long the brightness takes to go from the day to the night level, and back.
+pub const NIGHT_RAMP: Duration = Duration::from_secs(60);
+
+const DAY_SECS: f64 = 24.0 * 60.0 * 60.0;
+
+/// Hours of the day, in local time, during which every output is dimmed. The night may run
+/// past midnight, e.g. from 22:00 to 07:00.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NightSchedule {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub brightness: f32, // Scales the global brightness during the night
+}
+
+impl NightSchedule {
+    /// Whether `time` is during the night; the start is, the end isn't. A night starting
+    /// when it ends never comes.
+    pub fn is_night(&self, time: NaiveTime) -> bool {
+        seconds_between(self.start, time) < seconds_between(self.start, self.end)
+    }
+}
+
+// Seconds from `from` until `to` comes round next, from 0 up to a day
+fn seconds_between(from: NaiveTime, to: NaiveTime) -> f64 {
+    let seconds =
+        |time: NaiveTime| time.num_seconds_from_midnight() as f64 + time.nanosecond() as f64 / 1e9;
+    (seconds(to) - seconds(from)).rem_euclid(DAY_SECS)
+}
+
+/// Works out the factor for the global brightness of each frame from a night schedule,
+/// ramping between the day and night levels instead of jumping. While overridden, the outputs
+/// go back to full brightness, ramped too.
+#[derive(Debug, Clone)]
+pub struct NightDimmer {
+    schedule: NightSchedule,
+    overridden: bool,
+    level: Option<(f32, Instant)>, // The last factor and when it was worked out
+}
+
+impl NightDimmer {
+    pub fn new(schedule: NightSchedule) -> NightDimmer {
+        NightDimmer {
+            schedule,
+            overridden: false,
+            level: None,
+        }
+    }
+
+    pub fn schedule(&self) -> &NightSchedule {
+        &self.schedule
+    }
+
+    pub fn is_overridden(&self) -> bool {
+        self.overridden
+    }
+
+    pub fn set_overridden(&mut self, overridden: bool) {
+        self.overridden = overridden;
+    }
+
+    /// The last factor worked out, if any.
+    pub fn last_level(&self) -> Option<f32> {
+        self.level.map(|(level, _)| level)
+    }
+
+    /// The factor at local `time` of day, as of `now`. It moves towards the schedule's level
+    /// by a whole swing per `NIGHT_RAMP`; the first call starts at the level right away.
+    pub fn level(&mut self, time: NaiveTime, now: Instant) -> f32 {
+        let target = if !self.overridden && self.schedule.is_night(time) {
+            self.schedule.brightness
+        } else {
+            1.0
+        };
+        let level = match self.level {
+            None => target,
+            Some((level, updated)) => {
+                let elapsed = now.saturating_duration_since(updated).as_secs_f32();
+                let step =
+                    (1.0 - self.schedule.brightness).abs() * elapsed / NIGHT_RAMP.as_secs_f32();
+                if level < target {
+                    (level + step).min(target)
+                } else {
+                    (level - step).max(target)
+                }
+            }
+        };
+        self.level = Some((level, now));
+        level
+    }
+}
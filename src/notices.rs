@@ -0,0 +1,30 @@
+use crate::error::AppError;
+use log::warn;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+// Older notices are dropped beyond this many, e.g. when there's no window to take them
+const MAX_QUEUED: usize = 50;
+
+static QUEUE: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Logs a problem the app carries on after and queues its user message for the window, from
+/// whichever thread ran into it.
+pub fn report(err: &AppError) {
+    warn!("{}", err);
+    let mut queue = QUEUE.lock().unwrap();
+    queue.push_back(err.user_message());
+    while queue.len() > MAX_QUEUED {
+        queue.pop_front();
+    }
+}
+
+/// Takes the messages queued since the last call, oldest first.
+pub fn take() -> Vec<String> {
+    QUEUE.lock().unwrap().drain(..).collect()
+}
+
+/// How many messages wait for the window to take them.
+pub fn queued() -> usize {
+    QUEUE.lock().unwrap().len()
+}
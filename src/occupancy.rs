@@ -0,0 +1,184 @@
+use crate::error::AppError;
+use crate::mapping::RunRace;
+use crate::timeline::{estimated_laps, DriverDistances, DriverTimelines, SpeedConfig};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::thread::{self, JoinHandle};
+
+/// Settings of the LED occupancy export.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OccupancyConfig {
+    pub path: PathBuf,
+    pub max_gap_secs: f64, // Longer gaps between a driver's samples count only this long
+}
+
+impl Default for OccupancyConfig {
+    fn default() -> Self {
+        OccupancyConfig {
+            path: PathBuf::from("occupancy.csv"),
+            max_gap_secs: 5.0,
+        }
+    }
+}
+
+/// How many seconds each driver spent on each LED: the time from each sample to the driver's
+/// next one counts for the LED of the earlier sample.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Occupancy {
+    drivers: Vec<u32>,                  // Sorted by number
+    seconds: Vec<Vec<f64>>,             // Per LED, per driver in the order of `drivers`
+    sampled: Vec<Vec<bool>>,            // Per LED, whether a sample put each driver on it
+    distances: Vec<(f64, Option<f64>)>, // Meters and laps per driver, when set
+}
+
+impl Occupancy {
+    /// Walks every driver's timeline; `run_race_data` must be sorted by date and only refer
+    /// to LEDs below `led_count`.
+    pub fn compute(run_race_data: &[RunRace], led_count: usize, max_gap_secs: f64) -> Occupancy {
+        let timelines = DriverTimelines::new(run_race_data);
+        let mut drivers: Vec<u32> = timelines.drivers().collect();
+        drivers.sort();
+
+        let mut seconds = vec![vec![0.0; drivers.len()]; led_count];
+        let mut sampled = vec![vec![false; drivers.len()]; led_count];
+        for (column, &driver_number) in drivers.iter().enumerate() {
+            for run_data in timelines.timeline(driver_number) {
+                sampled[run_data.led_index][column] = true;
+            }
+            for pair in timelines.timeline(driver_number).windows(2) {
+                let gap = (pair[1].date - pair[0].date).num_milliseconds() as f64 / 1000.0;
+                seconds[pair[0].led_index][column] += gap.min(max_gap_secs);
+            }
+        }
+        Occupancy {
+            drivers,
+            seconds,
+            sampled,
+            distances: Vec::new(),
+        }
+    }
+
+    /// Adds how far each driver went over the whole session, and the laps that make round a
+    /// layout `layout_length` long, to the written file.
+    pub fn set_distances(
+        &mut self,
+        distances: &DriverDistances,
+        layout_length: f64,
+        config: &SpeedConfig,
+    ) {
+        self.distances = self
+            .drivers
+            .iter()
+            .map(|&driver_number| {
+                let meters = distances.total(driver_number).unwrap_or_default();
+                (meters, estimated_laps(meters, layout_length, config))
+            })
+            .collect();
+    }
+
+    pub fn drivers(&self) -> &[u32] {
+        &self.drivers
+    }
+
+    /// Seconds `driver_number` spent on the LED, zero for unknown drivers and LEDs.
+    pub fn seconds(&self, led_index: usize, driver_number: u32) -> f64 {
+        match (
+            self.seconds.get(led_index),
+            self.drivers.binary_search(&driver_number),
+        ) {
+            (Some(row), Ok(column)) => row[column],
+            _ => 0.0,
+        }
+    }
+
+    /// The LEDs no sample ever put a driver on.
+    pub fn never_lit(&self) -> Vec<usize> {
+        (0..self.sampled.len())
+            .filter(|&led_index| !self.sampled[led_index].contains(&true))
+            .collect()
+    }
+
+    /// One row per LED, labelled like the layout (`U1`, `U2`, ...), with a column of seconds
+    /// per driver and the total, then a `never_lit` row counting the LEDs each driver and no
+    /// driver at all was ever on. With the distances set, rows of each driver's meters and
+    /// estimated laps follow.
+    pub fn write_csv(&self, writer: impl Write) -> Result<(), AppError> {
+        let csv_error = |err: csv::Error| AppError::Export {
+            reason: format!("could not write the occupancy CSV: {}", err),
+        };
+        let mut writer = csv::Writer::from_writer(writer);
+        let mut header = vec!["led".to_string()];
+        header.extend(self.drivers.iter().map(u32::to_string));
+        header.push("total".to_string());
+        writer.write_record(&header).map_err(csv_error)?;
+
+        for (led_index, row) in self.seconds.iter().enumerate() {
+            let mut record = vec![format!("U{}", led_index + 1)];
+            record.extend(row.iter().map(|seconds| format!("{:.3}", seconds)));
+            record.push(format!("{:.3}", row.iter().sum::<f64>()));
+            writer.write_record(&record).map_err(csv_error)?;
+        }
+        let mut record = vec!["never_lit".to_string()];
+        record.extend((0..self.drivers.len()).map(|column| {
+            let never_lit = self.sampled.iter().filter(|row| !row[column]).count();
+            never_lit.to_string()
+        }));
+        record.push(self.never_lit().len().to_string());
+        writer.write_record(&record).map_err(csv_error)?;
+        if !self.distances.is_empty() {
+            let mut record = vec!["distance_m".to_string()];
+            record.extend(
+                self.distances
+                    .iter()
+                    .map(|(meters, _)| format!("{:.0}", meters)),
+            );
+            let total: f64 = self.distances.iter().map(|(meters, _)| meters).sum();
+            record.push(format!("{:.0}", total));
+            writer.write_record(&record).map_err(csv_error)?;
+
+            // Laps don't add up to anything across drivers
+            let mut record = vec!["laps".to_string()];
+            record.extend(
+                self.distances
+                    .iter()
+                    .map(|(_, laps)| laps.map_or(String::new(), |laps| format!("{:.1}", laps))),
+            );
+            record.push(String::new());
+            writer.write_record(&record).map_err(csv_error)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Computes the occupancy and the distances, for a layout `layout_length` long, and writes them
+/// to the configured file on a worker thread, which returns them for a summary.
+pub fn export_occupancy(
+    run_race_data: Vec<RunRace>,
+    led_count: usize,
+    layout_length: f64,
+    config: &OccupancyConfig,
+    speed: &SpeedConfig,
+) -> Result<JoinHandle<Result<Occupancy, AppError>>, AppError> {
+    let config = config.clone();
+    let speed = speed.clone();
+    let thread = thread::Builder::new()
+        .name("occupancy".to_string())
+        .spawn(move || {
+            let mut occupancy = Occupancy::compute(&run_race_data, led_count, config.max_gap_secs);
+            let distances = DriverDistances::new(&DriverTimelines::new(&run_race_data), &speed);
+            occupancy.set_distances(&distances, layout_length, &speed);
+            occupancy.write_csv(std::fs::File::create(&config.path)?)?;
+            info!(
+                "Wrote the occupancy of {} LEDs to {}; {} were never lit",
+                led_count,
+                config.path.display(),
+                occupancy.never_lit().len()
+            );
+            Ok(occupancy)
+        })?;
+    Ok(thread)
+}
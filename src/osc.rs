@@ -0,0 +1,144 @@
+use crate::error::AppError;
+use crate::simulation::Rgb;
+use crate::sink::{LedSink, OutputFrame, DEFAULT_MAX_FPS};
+use log::info;
+use rosc::{encoder, OscBundle, OscMessage, OscPacket, OscType};
+use serde::{Deserialize, Serialize};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+/// How LED colors are laid out in OSC messages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OscLedMode {
+    /// One `{prefix}/led/{index}` message per LED with red, green and blue floats in 0-1.
+    #[default]
+    PerLed,
+    /// A single `{prefix}/leds` message with all pixels as an RGB byte blob.
+    Blob,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OscConfig {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub prefix: String, // Address prefix of every message
+    pub mode: OscLedMode,
+    pub max_fps: f64,
+}
+
+impl Default for OscConfig {
+    fn default() -> Self {
+        OscConfig {
+            enabled: false,
+            host: "127.0.0.1".to_string(),
+            port: 9000,
+            prefix: "/f1led".to_string(),
+            mode: OscLedMode::default(),
+            max_fps: DEFAULT_MAX_FPS,
+        }
+    }
+}
+
+/// Sends each frame as one OSC bundle over UDP, together with the race time and speed so
+/// shows can be synchronized to the replay.
+pub struct OscSink {
+    socket: UdpSocket,
+    target: SocketAddr,
+    prefix: String,
+    mode: OscLedMode,
+}
+
+impl OscSink {
+    pub fn open(config: &OscConfig) -> Result<OscSink, AppError> {
+        let target = (config.host.as_str(), config.port)
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+            .ok_or_else(|| AppError::Config {
+                reason: format!("could not resolve the OSC host {}", config.host),
+            })?;
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        info!("Sending OSC to {}", target);
+
+        Ok(OscSink {
+            socket,
+            target,
+            prefix: config.prefix.trim_end_matches('/').to_string(),
+            mode: config.mode,
+        })
+    }
+
+    /// Sends a frame.
+    pub fn show(&mut self, leds: &[Rgb], race_time: f64, speed: f64) -> Result<(), AppError> {
+        let packet = frame_bundle(&self.prefix, self.mode, leds, race_time, speed);
+        let bytes = encoder::encode(&packet).map_err(|err| AppError::Output {
+            reason: format!("could not encode an OSC bundle: {}", err),
+        })?;
+        self.socket
+            .send_to(&bytes, self.target)
+            .map_err(|err| AppError::Output {
+                reason: format!("could not send OSC to {}: {}", self.target, err),
+            })?;
+
+        Ok(())
+    }
+}
+
+impl LedSink for OscSink {
+    fn name(&self) -> &str {
+        "osc"
+    }
+
+    fn submit(&mut self, frame: &OutputFrame) -> Result<(), AppError> {
+        self.show(&frame.dimmed(), frame.timestamp.as_secs_f64(), frame.speed)
+    }
+}
+
+/// The OSC bundle for one frame.
+pub fn frame_bundle(
+    prefix: &str,
+    mode: OscLedMode,
+    leds: &[Rgb],
+    race_time: f64,
+    speed: f64,
+) -> OscPacket {
+    let message = |addr: String, args: Vec<OscType>| OscPacket::Message(OscMessage { addr, args });
+
+    let mut content = vec![
+        message(
+            format!("{}/racetime", prefix),
+            vec![OscType::Float(race_time as f32)],
+        ),
+        message(
+            format!("{}/speed", prefix),
+            vec![OscType::Float(speed as f32)],
+        ),
+    ];
+    match mode {
+        OscLedMode::PerLed => {
+            content.extend(leds.iter().enumerate().map(|(index, color)| {
+                message(
+                    format!("{}/led/{}", prefix, index),
+                    color
+                        .iter()
+                        .map(|&channel| OscType::Float(channel as f32 / 255.0))
+                        .collect(),
+                )
+            }));
+        }
+        OscLedMode::Blob => {
+            let pixels = leds.concat();
+            content.push(message(
+                format!("{}/leds", prefix),
+                vec![OscType::Blob(pixels)],
+            ));
+        }
+    }
+
+    OscPacket::Bundle(OscBundle {
+        timetag: (0, 1).into(), // Immediately
+        content,
+    })
+}
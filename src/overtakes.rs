@@ -0,0 +1,208 @@
+use crate::laps::RaceProgress;
+use crate::simulation::Rgb;
+use crate::timeline::DriverTimelines;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+// An overtake blinks its LED white and then in the overtaker's color this many times...
+const BLINKS: i64 = 3;
+// ... over this much race time
+const ANIMATION_MILLIS: i64 = 1000;
+// Position changes of two drivers this close together in time are the same pass
+const SWAP_TOLERANCE_MILLIS: i64 = 2000;
+const WHITE: Rgb = [255, 255, 255];
+
+/// Which position changes are shown as overtakes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OvertakeConfig {
+    pub enabled: bool,
+    pub max_distance_m: f64, // Swaps of cars further apart, e.g. through a pit stop, aren't passes
+    pub history: usize,      // Overtakes remembered for the legend
+}
+
+impl Default for OvertakeConfig {
+    fn default() -> Self {
+        OvertakeConfig {
+            enabled: true,
+            max_distance_m: 100.0,
+            history: 50,
+        }
+    }
+}
+
+/// A pass on track: `driver_number` took `position` from `overtaken` on the LED at `led_index`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Overtake {
+    pub date: DateTime<Utc>,
+    pub driver_number: u32,
+    pub overtaken: u32,
+    pub position: u32,
+    pub led_index: usize,
+}
+
+/// Finds the overtakes in the race positions: a driver gaining the position another one lost
+/// at about the same time while the two were close together on track. Sorted by date.
+pub fn detect_overtakes(
+    progress: &RaceProgress,
+    timelines: &DriverTimelines,
+    config: &OvertakeConfig,
+    meters_per_unit: f64,
+) -> Vec<Overtake> {
+    // (date, driver, from, to) of every position change
+    let mut changes: Vec<(DateTime<Utc>, u32, u32, u32)> = Vec::new();
+    for driver_number in progress.drivers() {
+        for pair in progress.driver_positions(driver_number).windows(2) {
+            if pair[0].position != pair[1].position {
+                changes.push((
+                    pair[1].date,
+                    driver_number,
+                    pair[0].position,
+                    pair[1].position,
+                ));
+            }
+        }
+    }
+    changes.sort_by_key(|&(date, driver_number, _, _)| (date, driver_number));
+
+    let tolerance = ChronoDuration::milliseconds(SWAP_TOLERANCE_MILLIS);
+    let mut overtakes = Vec::new();
+    for &(date, driver_number, from, to) in &changes {
+        if to >= from {
+            continue;
+        }
+        let overtaken = changes
+            .iter()
+            .find(|&&(other_date, other, other_from, other_to)| {
+                other != driver_number
+                    && other_from == to
+                    && other_to > other_from
+                    && (other_date - date).abs() <= tolerance
+            });
+        let Some(&(_, overtaken, _, _)) = overtaken else {
+            continue;
+        };
+        let (Some(overtaker_at), Some(overtaken_at)) = (
+            timelines.position_at(driver_number, date),
+            timelines.position_at(overtaken, date),
+        ) else {
+            continue;
+        };
+        let distance_m = overtaker_at.point.distance(overtaken_at.point) * meters_per_unit;
+        if distance_m <= config.max_distance_m {
+            overtakes.push(Overtake {
+                date,
+                driver_number,
+                overtaken,
+                position: to,
+                led_index: overtaker_at.led_index,
+            });
+        }
+    }
+    overtakes
+}
+
+/// Plays the overtakes as the race clock passes them. Only overtakes the clock moves forward
+/// over are played, so seeking backwards doesn't replay the ones already shown.
+#[derive(Debug, Clone, Default)]
+pub struct OvertakeAnimations {
+    overtakes: Vec<Overtake>, // Sorted by date
+    next: usize,              // First overtake the clock hasn't passed
+    active: Vec<Overtake>,
+    recent: VecDeque<Overtake>, // The last played ones, newest first
+    history: usize,
+    clock: Option<DateTime<Utc>>,
+}
+
+impl OvertakeAnimations {
+    /// `overtakes` must be sorted by date; the last `history` played ones are kept.
+    pub fn new(overtakes: Vec<Overtake>, history: usize) -> OvertakeAnimations {
+        OvertakeAnimations {
+            overtakes,
+            history,
+            ..OvertakeAnimations::default()
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.active.is_empty()
+    }
+
+    /// Every overtake, played or not, by date.
+    pub fn all(&self) -> &[Overtake] {
+        &self.overtakes
+    }
+
+    /// The last played overtakes, newest first.
+    pub fn recent(&self) -> impl Iterator<Item = &Overtake> + '_ {
+        self.recent.iter()
+    }
+
+    /// Drops every animation and the history, for a restart.
+    pub fn reset(&mut self) {
+        self.next = 0;
+        self.active.clear();
+        self.recent.clear();
+        self.clock = None;
+    }
+
+    /// Moves to `date`: starts the overtakes passed since the last call and ends the finished
+    /// animations. Going backwards only ends the running animations. Returns the overtakes
+    /// whose animation started and is still running, so a jump far ahead returns none.
+    pub fn advance(&mut self, date: DateTime<Utc>) -> Vec<Overtake> {
+        let next = self
+            .overtakes
+            .partition_point(|overtake| overtake.date <= date);
+        let length = ChronoDuration::milliseconds(ANIMATION_MILLIS);
+        let mut started = Vec::new();
+        if self.clock.is_some_and(|clock| date < clock) {
+            self.active.clear();
+        } else {
+            for overtake in &self.overtakes[self.next..next] {
+                self.active.push(overtake.clone());
+                self.recent.push_front(overtake.clone());
+                if date < overtake.date + length {
+                    started.push(overtake.clone());
+                }
+            }
+            self.recent.truncate(self.history);
+        }
+        self.next = next;
+        self.clock = Some(date);
+
+        self.active.retain(|overtake| date < overtake.date + length);
+        started
+    }
+
+    /// Draws the running animations of shown drivers over `leds`: each blinks white, then in
+    /// the overtaker's color.
+    pub fn overlay(
+        &self,
+        leds: &mut [Option<Rgb>],
+        driver_colors: &HashMap<u32, Rgb>,
+        hidden_drivers: &HashSet<u32>,
+    ) {
+        let Some(clock) = self.clock else {
+            return;
+        };
+        for overtake in &self.active {
+            if hidden_drivers.contains(&overtake.driver_number) {
+                continue;
+            }
+            let elapsed = (clock - overtake.date).num_milliseconds();
+            let half_blinks = elapsed * BLINKS * 2 / ANIMATION_MILLIS;
+            let color = if half_blinks & 1 == 0 {
+                WHITE
+            } else {
+                driver_colors
+                    .get(&overtake.driver_number)
+                    .copied()
+                    .unwrap_or(WHITE)
+            };
+            if let Some(led) = leds.get_mut(overtake.led_index) {
+                *led = Some(color);
+            }
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use crate::error::AppError;
+use crate::mapping::RunRace;
+use arrow::array::{ArrayRef, Float64Array, TimestampMicrosecondArray, UInt32Array};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use log::{info, warn};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// Rows per row group of the written file; each group is encoded and written in one go.
+pub const ROW_GROUP_ROWS: usize = 65_536;
+
+/// Settings of the Parquet export of the mapped race data.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParquetConfig {
+    pub path: PathBuf,
+}
+
+impl Default for ParquetConfig {
+    fn default() -> Self {
+        ParquetConfig {
+            path: PathBuf::from("race.parquet"),
+        }
+    }
+}
+
+/// Columns of the file: the sample time in microseconds UTC, the driver, the LED they were
+/// mapped to and the location before snapping.
+pub fn race_data_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new(
+            "timestamp",
+            DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+            false,
+        ),
+        Field::new("driver_number", DataType::UInt32, false),
+        Field::new("led_index", DataType::UInt32, false),
+        Field::new("x", DataType::Float64, false),
+        Field::new("y", DataType::Float64, false),
+    ]))
+}
+
+/// Writes `run_race_data` to `writer` as zstd compressed Parquet, a row group at a time.
+/// `written` counts the rows written so far.
+pub fn write_parquet<W: Write + Send>(
+    run_race_data: &[RunRace],
+    writer: W,
+    written: &AtomicUsize,
+) -> Result<(), AppError> {
+    let schema = race_data_schema();
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .set_max_row_group_size(ROW_GROUP_ROWS)
+        .build();
+    let mut writer = ArrowWriter::try_new(writer, Arc::clone(&schema), Some(properties))
+        .map_err(parquet_error)?;
+    for rows in run_race_data.chunks(ROW_GROUP_ROWS) {
+        writer
+            .write(&record_batch(&schema, rows)?)
+            .map_err(parquet_error)?;
+        writer.flush().map_err(parquet_error)?; // Ends the row group
+        written.fetch_add(rows.len(), Ordering::Relaxed);
+    }
+    writer.close().map_err(parquet_error)?;
+    Ok(())
+}
+
+fn record_batch(schema: &SchemaRef, rows: &[RunRace]) -> Result<RecordBatch, AppError> {
+    let timestamps: TimestampMicrosecondArray = rows
+        .iter()
+        .map(|row| Some(row.date.timestamp_micros()))
+        .collect();
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(timestamps.with_timezone("UTC")),
+        Arc::new(UInt32Array::from_iter_values(
+            rows.iter().map(|row| row.driver_number),
+        )),
+        Arc::new(UInt32Array::from_iter_values(
+            rows.iter().map(|row| row.led_index as u32),
+        )),
+        Arc::new(Float64Array::from_iter_values(
+            rows.iter().map(|row| row.point.x),
+        )),
+        Arc::new(Float64Array::from_iter_values(
+            rows.iter().map(|row| row.point.y),
+        )),
+    ];
+    RecordBatch::try_new(Arc::clone(schema), columns).map_err(|err| AppError::Export {
+        reason: format!("could not build the Parquet rows: {}", err),
+    })
+}
+
+fn parquet_error(err: parquet::errors::ParquetError) -> AppError {
+    AppError::Export {
+        reason: format!("could not write the Parquet file: {}", err),
+    }
+}
+
+/// A Parquet export running on a worker thread.
+pub struct ParquetJob {
+    rows: usize,
+    written: Arc<AtomicUsize>,
+    thread: JoinHandle<Result<PathBuf, AppError>>,
+}
+
+impl ParquetJob {
+    /// Starts writing `run_race_data` to `path`. The file is created before this returns, so
+    /// an unwritable path fails right away; a failed export removes it again.
+    pub fn start(run_race_data: Vec<RunRace>, path: PathBuf) -> Result<ParquetJob, AppError> {
+        let file = File::create(&path).map_err(|err| AppError::Export {
+            reason: format!("could not create {}: {}", path.display(), err),
+        })?;
+        let rows = run_race_data.len();
+        let written = Arc::new(AtomicUsize::new(0));
+        let progress = Arc::clone(&written);
+        let thread = thread::Builder::new()
+            .name("parquet".to_string())
+            .spawn(move || {
+                if let Err(err) = write_parquet(&run_race_data, file, &progress) {
+                    if let Err(err) = std::fs::remove_file(&path) {
+                        warn!("Could not remove {}: {}", path.display(), err);
+                    }
+                    return Err(err);
+                }
+                info!("Wrote {} rows to {}", rows, path.display());
+                Ok(path)
+            })?;
+        Ok(ParquetJob {
+            rows,
+            written,
+            thread,
+        })
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Share of the rows written so far, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        match self.rows {
+            0 => 1.0,
+            rows => self.written.load(Ordering::Relaxed) as f32 / rows as f32,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Waits for the worker and returns the path of the written file.
+    pub fn finish(self) -> Result<PathBuf, AppError> {
+        self.thread.join().unwrap_or_else(|_| {
+            Err(AppError::Export {
+                reason: "the Parquet thread panicked".to_string(),
+            })
+        })
+    }
+}
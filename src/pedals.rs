@@ -0,0 +1,158 @@
+use crate::data::CarData;
+use crate::mapping::RunRace;
+use crate::simulation::Rgb;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Trail LEDs driven under throttle.
+pub const THROTTLE_COLOR: Rgb = [0, 200, 0];
+/// Trail LEDs driven braking.
+pub const BRAKE_COLOR: Rgb = [220, 0, 0];
+
+// A car data sample further from a record than this is a dropout, not the pedals there
+const MAX_SAMPLE_AGE_MS: i64 = 1000;
+// A longer jump between two records, like through the pit lane, isn't filled in
+const MAX_FILL: usize = 10;
+
+/// Throttle and brake of the focused driver, from their car data: bars next to the legend and,
+/// with `trail`, the LEDs behind them tinted by what they were doing there.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PedalConfig {
+    pub enabled: bool,
+    pub trail: bool,
+    pub trail_leds: usize,
+    pub min_throttle: f64, // Percent from which an LED counts as driven under throttle
+}
+
+impl Default for PedalConfig {
+    fn default() -> Self {
+        PedalConfig {
+            enabled: false,
+            trail: true,
+            trail_leds: 40,
+            min_throttle: 20.0,
+        }
+    }
+}
+
+/// The pedals as a driver entered an LED.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PedalSample {
+    pub date: DateTime<Utc>,
+    pub led_index: usize,
+    pub throttle: f64, // Percent
+    pub braking: bool,
+}
+
+/// A driver's records matched to their car data once, so frames only look samples up.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PedalTrace {
+    samples: Vec<PedalSample>, // By date
+}
+
+impl PedalTrace {
+    /// Matches each record of `driver_number` to the car data sample at or just before it.
+    /// Records without a recent sample are left out; the LEDs a record jumped over share its
+    /// sample. Both inputs are sorted by date.
+    pub fn new(
+        driver_number: u32,
+        run_race_data: &[RunRace],
+        car_data: &[CarData],
+        led_count: usize,
+    ) -> PedalTrace {
+        let max_age = ChronoDuration::milliseconds(MAX_SAMPLE_AGE_MS);
+        let mut samples = Vec::new();
+        let mut next = 0;
+        let mut previous_led: Option<usize> = None;
+        for record in run_race_data
+            .iter()
+            .filter(|record| record.driver_number == driver_number)
+        {
+            while next < car_data.len() && car_data[next].date <= record.date {
+                next += 1;
+            }
+            let Some(sample) = next.checked_sub(1).map(|index| &car_data[index]) else {
+                continue;
+            };
+            if record.date - sample.date > max_age {
+                previous_led = None;
+                continue;
+            }
+            let step = match previous_led {
+                Some(previous) if led_count > 0 => {
+                    (record.led_index + led_count - previous) % led_count
+                }
+                _ => 0,
+            };
+            let jumped = if step <= MAX_FILL { step } else { 0 };
+            for behind in (0..jumped.max(1)).rev() {
+                samples.push(PedalSample {
+                    date: record.date,
+                    led_index: (record.led_index + led_count - behind) % led_count.max(1),
+                    throttle: sample.throttle.unwrap_or(0.0),
+                    braking: sample.brake.is_some_and(|brake| brake > 0.0),
+                });
+            }
+            previous_led = Some(record.led_index);
+        }
+        PedalTrace { samples }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// The pedals at `date`, from the last record before it.
+    pub fn at(&self, date: DateTime<Utc>) -> Option<&PedalSample> {
+        let end = self.samples.partition_point(|sample| sample.date <= date);
+        end.checked_sub(1).map(|index| &self.samples[index])
+    }
+
+    /// The colors of the `length` LEDs the driver passed last by `date`, newest first, without
+    /// the one they're on: green under throttle, red braking, none coasting.
+    pub fn trail(
+        &self,
+        date: DateTime<Utc>,
+        length: usize,
+        config: &PedalConfig,
+    ) -> Vec<(usize, Option<Rgb>)> {
+        let end = self.samples.partition_point(|sample| sample.date <= date);
+        let mut passed = self.samples[..end].iter().rev();
+        let Some(current) = passed.next() else {
+            return Vec::new();
+        };
+        let mut trail: Vec<(usize, Option<Rgb>)> = Vec::with_capacity(length);
+        for sample in passed {
+            if trail.len() == length {
+                break;
+            }
+            // Only the latest pass of an LED counts
+            if sample.led_index == current.led_index
+                || trail.iter().any(|&(index, _)| index == sample.led_index)
+            {
+                continue;
+            }
+            let color = if sample.braking {
+                Some(BRAKE_COLOR)
+            } else if sample.throttle >= config.min_throttle {
+                Some(THROTTLE_COLOR)
+            } else {
+                None
+            };
+            trail.push((sample.led_index, color));
+        }
+        trail
+    }
+}
+
+/// Lights the unlit LEDs of `trail`, fading towards its end; cars on them stay as they are.
+pub fn tint_trail(leds: &mut [Option<Rgb>], trail: &[(usize, Option<Rgb>)]) {
+    for (position, &(index, color)) in trail.iter().enumerate() {
+        let (Some(color), Some(led @ None)) = (color, leds.get_mut(index)) else {
+            continue;
+        };
+        let level = 1.0 - position as f64 / trail.len() as f64;
+        *led = Some(color.map(|channel| (channel as f64 * level).round() as u8));
+    }
+}
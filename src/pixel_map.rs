@@ -0,0 +1,71 @@
+use crate::error::AppError;
+use crate::simulation::Rgb;
+
+/// Maps layout LED indices to pixel indices of a physical strip.
+#[derive(Debug, Clone)]
+pub struct PixelMap {
+    pixels: Vec<usize>, // Strip pixel of each layout LED
+    pixel_count: usize,
+}
+
+impl PixelMap {
+    /// LED `i` drives pixel `offset + order[i]`, or `offset + i` without an order table. The
+    /// strip may be longer than the layout; its extra pixels stay dark.
+    pub fn new(
+        led_count: usize,
+        order: Option<&[usize]>,
+        offset: usize,
+        pixel_count: usize,
+    ) -> Result<PixelMap, AppError> {
+        let pixels: Vec<usize> = match order {
+            Some(order) if order.len() != led_count => {
+                return Err(AppError::LayoutInvalid {
+                    reason: format!(
+                        "the pixel order has {} entries for {} LEDs",
+                        order.len(),
+                        led_count
+                    ),
+                })
+            }
+            Some(order) => order.iter().map(|pixel| offset + pixel).collect(),
+            None => (offset..offset + led_count).collect(),
+        };
+
+        if let Some(&pixel) = pixels.iter().find(|&&pixel| pixel >= pixel_count) {
+            return Err(AppError::LayoutInvalid {
+                reason: format!(
+                    "pixel {} is past the end of the {}-pixel strip",
+                    pixel, pixel_count
+                ),
+            });
+        }
+
+        Ok(PixelMap {
+            pixels,
+            pixel_count,
+        })
+    }
+
+    pub fn pixel_count(&self) -> usize {
+        self.pixel_count
+    }
+
+    /// Strip colors for a frame of layout colors; unmapped pixels are black.
+    pub fn map(&self, leds: &[Rgb]) -> Vec<Rgb> {
+        let mut strip = vec![[0, 0, 0]; self.pixel_count];
+        for (&pixel, color) in self.pixels.iter().zip(leds) {
+            strip[pixel] = *color;
+        }
+        strip
+    }
+}
+
+/// Lookup table for `gamma` correction, so linear app colors look right on LEDs whose
+/// perceived brightness isn't linear in the duty cycle.
+pub fn gamma_table(gamma: f32) -> [u8; 256] {
+    let mut table = [0; 256];
+    for (value, entry) in table.iter_mut().enumerate() {
+        *entry = ((value as f32 / 255.0).powf(gamma) * 255.0).round() as u8;
+    }
+    table
+}
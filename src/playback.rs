@@ -0,0 +1,115 @@
+use crate::mapping::RunRace;
+use std::time::Duration;
+
+// Jumps further than this many records use a binary search instead of the linear walk
+const SEEK_WALK_LIMIT: usize = 256;
+
+/// The playback clock and the index of the next record to apply.
+#[derive(Debug, Clone)]
+pub struct Playback {
+    pub race_time: f64, // Elapsed race time in seconds
+    pub race_started: bool,
+    pub paused: bool,         // The clock stands still until resumed
+    pub current_index: usize, // Records before this index have been played
+    pub speed: f64,           // Playback speed multiplier
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Playback {
+            race_time: 0.0,
+            race_started: false,
+            paused: false,
+            current_index: 0,
+            speed: 1.0,
+        }
+    }
+}
+
+impl Playback {
+    /// Starts playing from the first record.
+    pub fn start(&mut self) {
+        self.race_started = true;
+        self.paused = false;
+        self.race_time = 0.0;
+        self.current_index = 0;
+    }
+
+    /// Stops playback and rewinds to the beginning.
+    pub fn reset(&mut self) {
+        self.race_time = 0.0;
+        self.race_started = false;
+        self.paused = false;
+        self.current_index = 0;
+    }
+
+    /// Advances the clock by `dt` of real time scaled by the speed; returns whether any records
+    /// were played, i.e. whether the LED states need rebuilding.
+    pub fn update(&mut self, dt: Duration, run_race_data: &[RunRace]) -> bool {
+        self.update_at(dt, self.speed, run_race_data)
+    }
+
+    /// Like `update`, at `speed` instead of the set speed.
+    pub fn update_at(&mut self, dt: Duration, speed: f64, run_race_data: &[RunRace]) -> bool {
+        if !self.race_started || self.paused {
+            return false;
+        }
+
+        let previous_index = self.current_index;
+        self.advance_to(self.race_time + dt.as_secs_f64() * speed, run_race_data);
+        self.current_index != previous_index
+    }
+
+    /// Whether every record has been played.
+    pub fn is_finished(&self, run_race_data: &[RunRace]) -> bool {
+        self.race_started && self.current_index >= run_race_data.len()
+    }
+
+    /// Moves the clock to `race_time` and the index past every record at or before it.
+    pub fn advance_to(&mut self, race_time: f64, run_race_data: &[RunRace]) {
+        self.race_time = race_time;
+
+        let rewound = self.current_index > 0
+            && record_time(run_race_data, self.current_index - 1) > self.race_time;
+        if rewound {
+            // Before the clock's start not even the first record has been played
+            self.current_index = if race_time < 0.0 {
+                0
+            } else {
+                seek_index_for_time(run_race_data, Duration::from_secs_f64(race_time))
+            };
+            return;
+        }
+
+        // Normal frame advances only move a few records, so walk forward from the current index
+        let walk_end = (self.current_index + SEEK_WALK_LIMIT).min(run_race_data.len());
+        let mut next_index = self.current_index;
+        while next_index < walk_end && record_time(run_race_data, next_index) <= self.race_time {
+            next_index += 1;
+        }
+
+        if next_index == walk_end
+            && walk_end < run_race_data.len()
+            && record_time(run_race_data, walk_end) <= self.race_time
+        {
+            next_index =
+                seek_index_for_time(run_race_data, Duration::from_secs_f64(race_time.max(0.0)));
+        }
+
+        self.current_index = next_index;
+    }
+}
+
+/// Seconds between the first record and the record at `index`.
+fn record_time(run_race_data: &[RunRace], index: usize) -> f64 {
+    (run_race_data[index].date - run_race_data[0].date).num_milliseconds() as f64 / 1000.0
+}
+
+/// Index of the first record later than `race_time` after the first record, so every record
+/// before it has been played. `run_race_data` must be sorted by date.
+pub fn seek_index_for_time(run_race_data: &[RunRace], race_time: Duration) -> usize {
+    let race_time = race_time.as_secs_f64();
+    run_race_data.partition_point(|run_data| {
+        (run_data.date - run_race_data[0].date).num_milliseconds() as f64 / 1000.0 <= race_time
+    })
+}
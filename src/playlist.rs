@@ -0,0 +1,137 @@
+use crate::config::SessionConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Sessions played one after another, each fetched while the one before plays.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlaylistConfig {
+    pub sessions: Vec<SessionConfig>,
+    pub title_card_secs: f64, // How long the next session's name shows between two sessions
+}
+
+impl Default for PlaylistConfig {
+    fn default() -> Self {
+        PlaylistConfig {
+            sessions: Vec::new(),
+            title_card_secs: 5.0,
+        }
+    }
+}
+
+/// The name of a session on its title card: the one configured, or its key.
+pub fn session_title(session: &SessionConfig) -> String {
+    match &session.name {
+        Some(name) => name.clone(),
+        None => format!("Session {}", session.key),
+    }
+}
+
+/// The sessions to play in order, where playback is in them and the ones that failed to load.
+#[derive(Debug, Clone, Default)]
+pub struct Playlist {
+    sessions: Vec<SessionConfig>,
+    position: Option<usize>, // The entry playing; none while something off the list plays
+    skipped: BTreeSet<usize>, // Failed to load, so moving through the list passes them
+}
+
+impl Playlist {
+    /// A playlist positioned at the first entry for the session `playing`, if there is one.
+    pub fn new(sessions: Vec<SessionConfig>, playing: &str) -> Playlist {
+        let mut playlist = Playlist {
+            sessions,
+            ..Playlist::default()
+        };
+        playlist.set_playing(playing);
+        playlist
+    }
+
+    pub fn sessions(&self) -> &[SessionConfig] {
+        &self.sessions
+    }
+
+    pub fn get(&self, index: usize) -> Option<&SessionConfig> {
+        self.sessions.get(index)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    pub fn position(&self) -> Option<usize> {
+        self.position
+    }
+
+    pub fn is_skipped(&self, index: usize) -> bool {
+        self.skipped.contains(&index)
+    }
+
+    /// The entry after the one playing, or the first one while playing something else.
+    pub fn next(&self) -> Option<usize> {
+        match self.position {
+            Some(position) => self.following(position),
+            None => (0..self.sessions.len()).find(|index| !self.is_skipped(*index)),
+        }
+    }
+
+    /// The entry before the one playing.
+    pub fn previous(&self) -> Option<usize> {
+        let position = self.position?;
+        (0..position).rev().find(|index| !self.is_skipped(*index))
+    }
+
+    /// The first entry after `index` that hasn't been skipped.
+    pub fn following(&self, index: usize) -> Option<usize> {
+        (index + 1..self.sessions.len()).find(|index| !self.is_skipped(*index))
+    }
+
+    /// Moves playback to the entry, which counts as loaded again if it was skipped before.
+    pub fn play(&mut self, index: usize) {
+        if index < self.sessions.len() {
+            self.position = Some(index);
+            self.skipped.remove(&index);
+        }
+    }
+
+    /// Points at the first entry for the session loaded some other way, or at none.
+    pub fn set_playing(&mut self, key: &str) {
+        self.position = self.sessions.iter().position(|session| session.key == key);
+    }
+
+    /// Passes over the entry from now on, after it failed to load.
+    pub fn skip(&mut self, index: usize) {
+        self.skipped.insert(index);
+    }
+
+    pub fn push(&mut self, session: SessionConfig) {
+        self.sessions.push(session);
+    }
+
+    /// Takes the entry out; the ones skipped get another chance, as the positions shift.
+    pub fn remove(&mut self, index: usize) {
+        if index >= self.sessions.len() {
+            return;
+        }
+        self.sessions.remove(index);
+        self.skipped.clear();
+        self.position = match self.position {
+            Some(position) if position == index => None,
+            Some(position) if position > index => Some(position - 1),
+            position => position,
+        };
+    }
+
+    /// Swaps the entry with the one before it.
+    pub fn move_up(&mut self, index: usize) {
+        if index == 0 || index >= self.sessions.len() {
+            return;
+        }
+        self.sessions.swap(index - 1, index);
+        self.skipped.clear();
+        self.position = match self.position {
+            Some(position) if position == index => Some(index - 1),
+            Some(position) if position == index - 1 => Some(index),
+            position => position,
+        };
+    }
+}
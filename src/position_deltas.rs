@@ -0,0 +1,123 @@
+use crate::laps::RaceProgress;
+use crate::retirements::Retirements;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use std::collections::HashMap;
+use std::time::Duration;
+use web_time::Instant;
+
+/// How long a leaderboard row flashes after its driver gained or lost a place.
+pub const FLASH_DURATION: Duration = Duration::from_millis(800);
+
+// A longer step of the race clock between two updates is a seek, whose changes don't flash
+const MAX_LIVE_STEP_SECS: i64 = 10;
+
+/// Places gained as shown in the leaderboard: "▲3", "▼2", or "–" for none.
+pub fn format_position_delta(delta: i32) -> String {
+    match delta {
+        0 => "–".to_string(),
+        gained if gained > 0 => format!("▲{}", gained),
+        lost => format!("▼{}", -lost),
+    }
+}
+
+/// Places gained or lost against the starting grid, and the leaderboard rows flashing after a
+/// position changed while playing.
+#[derive(Debug, Clone, Default)]
+pub struct PositionDeltas {
+    grid: HashMap<u32, u32>, // Starting positions, taken once when the data loads
+    positions: HashMap<u32, u32>, // Positions at the last update
+    date: Option<DateTime<Utc>>, // Race date of the last update
+    flashes: HashMap<u32, (bool, Instant)>, // Whether the driver gained, and since when
+}
+
+impl PositionDeltas {
+    /// Takes the grid from each driver's first known position.
+    pub fn new(progress: &RaceProgress) -> PositionDeltas {
+        let grid = progress
+            .drivers()
+            .into_iter()
+            .filter_map(|driver_number| {
+                Some((driver_number, progress.starting_position(driver_number)?))
+            })
+            .collect();
+        PositionDeltas::with_grid(grid)
+    }
+
+    /// Against a grid known otherwise, e.g. the official starting grid, by driver number.
+    pub fn with_grid(grid: HashMap<u32, u32>) -> PositionDeltas {
+        PositionDeltas {
+            grid,
+            ..PositionDeltas::default()
+        }
+    }
+
+    pub fn starting_position(&self, driver_number: u32) -> Option<u32> {
+        self.grid.get(&driver_number).copied()
+    }
+
+    /// Places the driver gained since the start by `date`, negative when they lost some. A
+    /// retired driver keeps the position they retired in.
+    pub fn delta(
+        &self,
+        progress: &RaceProgress,
+        retirements: &Retirements,
+        driver_number: u32,
+        date: DateTime<Utc>,
+    ) -> Option<i32> {
+        let start = self.starting_position(driver_number)?;
+        let position = classified_position(progress, retirements, driver_number, date)?;
+        Some(start as i32 - position as i32)
+    }
+
+    /// Notes the positions at `date` and flashes the rows of the drivers whose position changed
+    /// since the last update. After a seek the positions are only noted.
+    pub fn update(
+        &mut self,
+        progress: &RaceProgress,
+        retirements: &Retirements,
+        date: DateTime<Utc>,
+        now: Instant,
+    ) {
+        let live = self.date.is_some_and(|last| {
+            last <= date && date - last <= ChronoDuration::seconds(MAX_LIVE_STEP_SECS)
+        });
+        for driver_number in progress.drivers() {
+            let Some(position) = classified_position(progress, retirements, driver_number, date)
+            else {
+                self.positions.remove(&driver_number);
+                continue;
+            };
+            match self.positions.insert(driver_number, position) {
+                Some(previous) if live && previous != position => {
+                    self.flashes
+                        .insert(driver_number, (position < previous, now));
+                }
+                _ => {}
+            }
+        }
+        self.flashes
+            .retain(|_, (_, since)| now.duration_since(*since) < FLASH_DURATION);
+        self.date = Some(date);
+    }
+
+    /// The driver's row flash at `now`: whether they gained the place, and how strong the flash
+    /// still is, fading from 1 to 0.
+    pub fn flash(&self, driver_number: u32, now: Instant) -> Option<(bool, f32)> {
+        let &(gained, since) = self.flashes.get(&driver_number)?;
+        let age = now.duration_since(since).as_secs_f32() / FLASH_DURATION.as_secs_f32();
+        (age < 1.0).then_some((gained, 1.0 - age))
+    }
+}
+
+// The driver's position at `date`, or when they retired if that was before
+fn classified_position(
+    progress: &RaceProgress,
+    retirements: &Retirements,
+    driver_number: u32,
+    date: DateTime<Utc>,
+) -> Option<u32> {
+    let date = retirements
+        .since(driver_number)
+        .map_or(date, |retired| retired.min(date));
+    progress.position_at(driver_number, date)
+}
@@ -0,0 +1,239 @@
+use crate::bundle::Bundle;
+use crate::cache::{
+    check_drivers, check_mapping, check_progress, drivers_cache_key, mapping_cache_key,
+    progress_cache_key,
+};
+use crate::config::SessionConfig;
+use crate::data::{fetch_session, probe_endpoint, Endpoint, SessionInfo, TimeWindow};
+use crate::driver_info::{season_roster, DEFAULT_SEASON};
+use crate::error::AppError;
+use crate::session_data::{session_drivers, DataSource};
+use crate::space::LedPoint;
+use chrono::{DateTime, Datelike, Duration, Utc};
+use std::fmt;
+use std::path::Path;
+
+/// How much of the session's location samples the location probe asks for.
+pub const LOCATION_PROBE_SECS: i64 = 10;
+
+/// One item of the `--check` report.
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: String,
+    pub result: Result<String, String>, // What was found, or why the check failed
+}
+
+/// Every check `--check` ran, in order. No check depends on another having passed.
+#[derive(Debug, Default)]
+pub struct CheckReport {
+    pub checks: Vec<Check>,
+}
+
+impl CheckReport {
+    pub fn record(&mut self, name: impl Into<String>, result: Result<String, AppError>) {
+        self.checks.push(Check {
+            name: name.into(),
+            result: result.map_err(|err| err.to_string()),
+        });
+    }
+
+    pub fn failed(&self) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.result.is_err())
+            .count()
+    }
+
+    /// `CheckFailed` when any check failed.
+    pub fn outcome(&self) -> Result<(), AppError> {
+        match self.failed() {
+            0 => Ok(()),
+            failed => Err(AppError::CheckFailed {
+                failed,
+                total: self.checks.len(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for CheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            match &check.result {
+                Ok(found) => writeln!(f, "PASS  {}: {}", check.name, found)?,
+                Err(reason) => writeln!(f, "FAIL  {}: {}", check.name, reason)?,
+            }
+        }
+        match self.failed() {
+            0 => write!(f, "All {} checks passed", self.checks.len()),
+            failed => write!(f, "{} of {} checks failed", failed, self.checks.len()),
+        }
+    }
+}
+
+/// Checks the layout has LEDs, each at a finite position and no two in the same spot.
+pub fn check_layout(report: &mut CheckReport, coordinates: &[LedPoint]) {
+    report.record("layout", describe_layout(coordinates));
+}
+
+fn describe_layout(coordinates: &[LedPoint]) -> Result<String, AppError> {
+    let invalid = |reason: String| Err(AppError::LayoutInvalid { reason });
+    if coordinates.is_empty() {
+        return invalid("the layout has no LEDs".to_string());
+    }
+    if let Some(index) = coordinates
+        .iter()
+        .position(|led| !led.x.is_finite() || !led.y.is_finite())
+    {
+        return invalid(format!("LED {} has no finite position", index));
+    }
+    let mut sorted: Vec<(usize, LedPoint)> = coordinates.iter().copied().enumerate().collect();
+    sorted.sort_by(|(_, a), (_, b)| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    if let Some(pair) = sorted.windows(2).find(|pair| pair[0].1 == pair[1].1) {
+        let (first, second) = (pair[0].0.min(pair[1].0), pair[0].0.max(pair[1].0));
+        return invalid(format!(
+            "LEDs {} and {} are in the same spot",
+            first, second
+        ));
+    }
+    Ok(format!("{} LEDs", coordinates.len()))
+}
+
+/// Checks the bundle reads back whole, and whether it has to be mapped onto the layout.
+pub fn check_bundle(report: &mut CheckReport, path: &Path, coordinates: &[LedPoint]) {
+    let result = Bundle::open(path).map(|bundle| {
+        format!(
+            "{} records of session {}{}",
+            bundle.run_race_data.len(),
+            bundle.session.key,
+            if bundle.needs_mapping(coordinates) {
+                ", to be mapped onto this layout"
+            } else {
+                ""
+            }
+        )
+    });
+    report.record(format!("bundle {}", path.display()), result);
+}
+
+/// Checks what the session is fetched from and cached in: when it ran, a single request to
+/// every endpoint it's read from and whatever of it is cached.
+pub fn check_session_data(report: &mut CheckReport, source: &DataSource, session: &SessionConfig) {
+    let info = check_session(report, source, session);
+    // The season, and so the drivers, as the session would be loaded
+    let start = session.start_time.or(info.map(|info| info.date_start));
+    let season = source
+        .roster
+        .season
+        .or(start.map(|start| start.year() as u32));
+    let roster = season_roster(season.unwrap_or(DEFAULT_SEASON), &source.roster);
+    let driver_numbers = session_drivers(session, &roster);
+
+    match driver_numbers.first() {
+        Some(&driver_number) => check_endpoints(report, source, session, driver_number, start),
+        None => report.record(
+            "API",
+            Err(AppError::Config {
+                reason: "session.drivers is empty".to_string(),
+            }),
+        ),
+    }
+    check_cache(report, source, session, &driver_numbers);
+}
+
+fn check_session(
+    report: &mut CheckReport,
+    source: &DataSource,
+    session: &SessionConfig,
+) -> Option<SessionInfo> {
+    let fetched = tokio::runtime::Runtime::new()
+        .map_err(AppError::from)
+        .and_then(|runtime| runtime.block_on(fetch_session(&source.api, &session.key)));
+    let (info, result) = match fetched {
+        Ok(Some(info)) => (
+            Some(info.clone()),
+            Ok(format!(
+                "ran from {} to {}",
+                info.date_start.format("%Y-%m-%d %H:%M"),
+                info.date_end.format("%H:%M UTC")
+            )),
+        ),
+        Ok(None) => (
+            None,
+            Err(AppError::Config {
+                reason: format!("the API doesn't know session {}", session.key),
+            }),
+        ),
+        Err(err) => (None, Err(err)),
+    };
+    report.record(format!("session {}", session.key), result);
+    info
+}
+
+fn check_endpoints(
+    report: &mut CheckReport,
+    source: &DataSource,
+    session: &SessionConfig,
+    driver_number: u32,
+    start: Option<DateTime<Utc>>,
+) {
+    let window = match start {
+        Some(start) => TimeWindow {
+            start: Some(start),
+            end: Some(start + Duration::seconds(LOCATION_PROBE_SECS)),
+        },
+        None => session.window(),
+    };
+    // The session check has asked the sessions endpoint already
+    for endpoint in Endpoint::ALL {
+        if endpoint == Endpoint::Sessions || (endpoint.is_progress() && !session.fetch_laps) {
+            continue;
+        }
+        let result = tokio::runtime::Runtime::new()
+            .map_err(AppError::from)
+            .and_then(|runtime| {
+                runtime.block_on(probe_endpoint(
+                    &source.api,
+                    endpoint,
+                    &session.key,
+                    driver_number,
+                    &window,
+                ))
+            });
+        let found = result.map(|rows| format!("{} rows for driver {}", rows, driver_number));
+        report.record(format!("API {}", endpoint.name()), found);
+    }
+}
+
+fn check_cache(
+    report: &mut CheckReport,
+    source: &DataSource,
+    session: &SessionConfig,
+    driver_numbers: &[u32],
+) {
+    let dir = &source.cache_dir;
+    let found = |result: Result<bool, AppError>| {
+        result.map(|cached| {
+            if cached {
+                "reads back"
+            } else {
+                "not cached yet"
+            }
+            .to_string()
+        })
+    };
+    let key = mapping_cache_key(
+        &session.key,
+        driver_numbers,
+        &session.window(),
+        &source.coordinates,
+        &source.mapping,
+    );
+    report.record("cache mapping", found(check_mapping(dir, key)));
+    let key = drivers_cache_key(&session.key);
+    report.record("cache drivers", found(check_drivers(dir, key)));
+    if session.fetch_laps {
+        let key = progress_cache_key(&session.key, driver_numbers);
+        report.record("cache laps", found(check_progress(dir, key)));
+    }
+}
@@ -0,0 +1,116 @@
+use crate::color_scheme::ColorSchemeKind;
+use crate::config::Config;
+use crate::data::TimeWindow;
+use crate::speed_plan::SpeedSegment;
+use crate::team_view::LedView;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Color scheme of the window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 2] = [Theme::Dark, Theme::Light];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Theme::Dark => "Dark",
+            Theme::Light => "Light",
+        }
+    }
+}
+
+/// How the legend rows are ordered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LegendOrder {
+    #[default]
+    Number,
+    Name,
+    Team,
+    /// Current race position, when positions are loaded; drivers without one go last.
+    Position,
+}
+
+impl LegendOrder {
+    pub const ALL: [LegendOrder; 4] = [
+        LegendOrder::Number,
+        LegendOrder::Name,
+        LegendOrder::Team,
+        LegendOrder::Position,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LegendOrder::Number => "Number",
+            LegendOrder::Name => "Name",
+            LegendOrder::Team => "Team",
+            LegendOrder::Position => "Position",
+        }
+    }
+}
+
+/// The session the last run played, and which part of it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LastSession {
+    pub key: String,
+    pub window: TimeWindow,
+}
+
+/// UI state remembered between runs. Window size and position are persisted by eframe itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiPrefs {
+    pub speed: f64,
+    pub brightness: f32,
+    pub theme: Theme,
+    pub led_size: f32,
+    pub session_hidden_drivers: BTreeMap<String, Vec<u32>>, // Unticked in the legend, by session key
+    pub legend_order: LegendOrder,
+    pub color_scheme: ColorSchemeKind,
+    pub led_view: LedView,
+    pub layout_rotations: BTreeMap<u64, f64>, // Degrees by layout hash
+    pub time_offsets: BTreeMap<String, f64>,  // Seconds the data runs ahead, by session key
+    pub speed_segments: BTreeMap<String, Vec<SpeedSegment>>, // Speed plan, by session key
+    pub auto_slow: bool,                      // Slow down around overtakes and retirements
+    pub volume: f32,                          // Of the audio cues
+    pub muted: bool,
+    pub last_session: Option<LastSession>,
+}
+
+impl Default for UiPrefs {
+    fn default() -> Self {
+        UiPrefs {
+            speed: 1.0,
+            brightness: 1.0,
+            theme: Theme::default(),
+            led_size: 20.0,
+            session_hidden_drivers: BTreeMap::new(),
+            legend_order: LegendOrder::default(),
+            color_scheme: ColorSchemeKind::default(),
+            led_view: LedView::default(),
+            layout_rotations: BTreeMap::new(),
+            time_offsets: BTreeMap::new(),
+            speed_segments: BTreeMap::new(),
+            auto_slow: false,
+            volume: 0.8,
+            muted: false,
+            last_session: None,
+        }
+    }
+}
+
+impl UiPrefs {
+    /// The window last played of the session `config` plays, when it differs from the configured
+    /// one; none when the config file or command line sets either end of the window.
+    pub fn session_window(&self, config: &Config) -> Option<TimeWindow> {
+        let last = self.last_session.as_ref()?;
+        let window_set = config.is_set("session.start_time") || config.is_set("session.end_time");
+        (!window_set && last.key == config.session.key && last.window != config.session.window())
+            .then_some(last.window)
+    }
+}
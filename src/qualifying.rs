@@ -0,0 +1,190 @@
+use crate::data::LapData;
+use crate::laps::RaceProgress;
+use crate::mapping::RunRace;
+use crate::speed_plan::SpeedSegment;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// How a qualifying session is played.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QualifyingMode {
+    /// The whole session as it ran.
+    #[default]
+    Off,
+    /// Every driver's best flying lap at once, each started together.
+    BestLaps,
+    /// The whole session, fast while nobody is out on track.
+    SkipGarage,
+}
+
+impl QualifyingMode {
+    pub const ALL: [QualifyingMode; 3] = [
+        QualifyingMode::Off,
+        QualifyingMode::BestLaps,
+        QualifyingMode::SkipGarage,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            QualifyingMode::Off => "Whole session",
+            QualifyingMode::BestLaps => "Best laps overlaid",
+            QualifyingMode::SkipGarage => "Skip garage time",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QualifyingConfig {
+    pub mode: QualifyingMode,
+    pub garage_speed: f64, // Speed while every car is in the pits, for `skip_garage`
+}
+
+impl Default for QualifyingConfig {
+    fn default() -> Self {
+        QualifyingConfig {
+            mode: QualifyingMode::Off,
+            garage_speed: 20.0,
+        }
+    }
+}
+
+/// A driver's fastest flying lap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BestLap {
+    pub driver_number: u32,
+    pub lap_number: u32,
+    pub date_start: DateTime<Utc>,
+    pub lap_duration: f64, // Seconds
+}
+
+impl BestLap {
+    pub fn date_end(&self) -> DateTime<Utc> {
+        self.date_start + ChronoDuration::milliseconds((self.lap_duration * 1000.0).round() as i64)
+    }
+}
+
+/// The driver's laps with a time that are neither out laps nor in laps, an in lap being the
+/// one before an out lap.
+pub fn flying_laps(progress: &RaceProgress, driver_number: u32) -> Vec<&LapData> {
+    let laps = progress.driver_laps(driver_number);
+    laps.iter()
+        .enumerate()
+        .filter(|&(index, lap)| {
+            let in_lap = laps
+                .get(index + 1)
+                .is_some_and(|next| next.lap_number == lap.lap_number + 1 && next.is_pit_out_lap);
+            !lap.is_pit_out_lap
+                && !in_lap
+                && lap.date_start.is_some()
+                && lap.lap_duration.is_some_and(|duration| duration > 0.0)
+        })
+        .map(|(_, lap)| lap)
+        .collect()
+}
+
+/// Each driver's fastest flying lap, fastest first.
+pub fn best_laps(progress: &RaceProgress) -> Vec<BestLap> {
+    let mut best: Vec<BestLap> = progress
+        .drivers()
+        .into_iter()
+        .filter_map(|driver_number| {
+            flying_laps(progress, driver_number)
+                .into_iter()
+                .filter_map(|lap| {
+                    Some(BestLap {
+                        driver_number,
+                        lap_number: lap.lap_number,
+                        date_start: lap.date_start?,
+                        lap_duration: lap.lap_duration?,
+                    })
+                })
+                .min_by(|a, b| a.lap_duration.total_cmp(&b.lap_duration))
+        })
+        .collect();
+    best.sort_by(|a, b| a.lap_duration.total_cmp(&b.lap_duration));
+    best
+}
+
+/// The records of each best lap moved onto a clock of its own, so all the laps start at the
+/// same time: that of the earliest one. Other records are left out.
+pub fn overlay_best_laps(run_race_data: &[RunRace], best_laps: &[BestLap]) -> Vec<RunRace> {
+    let Some(base) = best_laps.iter().map(|lap| lap.date_start).min() else {
+        return Vec::new();
+    };
+    let mut overlaid: Vec<RunRace> = best_laps
+        .iter()
+        .flat_map(|lap| {
+            let (start, end) = (lap.date_start, lap.date_end());
+            run_race_data
+                .iter()
+                .filter(move |run_data| {
+                    run_data.driver_number == lap.driver_number
+                        && (start..=end).contains(&run_data.date)
+                })
+                .map(move |run_data| RunRace {
+                    date: base + (run_data.date - start),
+                    ..run_data.clone()
+                })
+        })
+        .collect();
+    overlaid.sort_by_key(|run_data| run_data.date);
+    overlaid
+}
+
+/// Segments at `speed` covering the stretches between `first_record` and `last_record` while
+/// no car is out on track. A car is out from an out lap's start to the end of the lap before
+/// its next out lap, or of its last lap.
+pub fn garage_segments(
+    progress: &RaceProgress,
+    first_record: DateTime<Utc>,
+    last_record: DateTime<Utc>,
+    speed: f64,
+) -> Vec<SpeedSegment> {
+    let mut runs: Vec<(DateTime<Utc>, DateTime<Utc>)> = Vec::new();
+    for driver_number in progress.drivers() {
+        let laps = progress.driver_laps(driver_number);
+        let mut run_start = None;
+        for (index, lap) in laps.iter().enumerate() {
+            if run_start.is_none() || lap.is_pit_out_lap {
+                run_start = lap.date_start.or(run_start);
+            }
+            let run_ends = laps.get(index + 1).is_none_or(|next| next.is_pit_out_lap);
+            if let (true, Some(start)) = (run_ends, run_start) {
+                // Not up to the next lap's start, which is after the time in the garage
+                let end = lap
+                    .date_start
+                    .zip(lap.lap_duration)
+                    .map(|(date, duration)| {
+                        date + ChronoDuration::milliseconds((duration * 1000.0).round() as i64)
+                    });
+                runs.push((start, end.unwrap_or(start).max(start)));
+                run_start = None;
+            }
+        }
+    }
+    runs.sort();
+
+    let secs = |date: DateTime<Utc>| (date - first_record).num_milliseconds() as f64 / 1000.0;
+    let mut segments = Vec::new();
+    let mut idle_from = first_record;
+    for (start, end) in runs {
+        if start > idle_from {
+            segments.push(SpeedSegment {
+                start: secs(idle_from),
+                end: secs(start),
+                speed,
+            });
+        }
+        idle_from = idle_from.max(end);
+    }
+    if last_record > idle_from {
+        segments.push(SpeedSegment {
+            start: secs(idle_from),
+            end: secs(last_record),
+            speed,
+        });
+    }
+    segments
+}
@@ -0,0 +1,87 @@
+use crate::laps::RaceProgress;
+use crate::overtakes::Overtake;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::Serialize;
+
+// Events further behind the clock than this when it passes them, e.g. after a seek, are
+// skipped rather than played late
+const STALE_MILLIS: i64 = 1000;
+
+/// Something happening in the race as the clock passes it, for outputs besides the LEDs such
+/// as sound or MQTT, where it's a JSON object named by its `event` field.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RaceEvent {
+    /// The clock started from the beginning.
+    Start,
+    /// An overtake animation started.
+    Overtake(Overtake),
+    /// A driver left the pit lane.
+    PitStop {
+        driver_number: u32,
+        date: DateTime<Utc>,
+    },
+}
+
+/// When each driver left the pit lane: the start of their pit out laps, sorted by date.
+pub fn pit_exits(progress: &RaceProgress) -> Vec<(DateTime<Utc>, u32)> {
+    let mut exits: Vec<(DateTime<Utc>, u32)> = progress
+        .drivers()
+        .into_iter()
+        .flat_map(|driver_number| {
+            progress
+                .driver_laps(driver_number)
+                .iter()
+                .filter(|lap| lap.is_pit_out_lap)
+                .filter_map(move |lap| Some((lap.date_start?, driver_number)))
+        })
+        .collect();
+    exits.sort();
+    exits
+}
+
+/// Plays the pit exits as the race clock passes them. Like the overtakes, only exits the clock
+/// moves forward over are played, and those it jumps far past are skipped.
+#[derive(Debug, Clone, Default)]
+pub struct PitStops {
+    exits: Vec<(DateTime<Utc>, u32)>, // Sorted by date
+    next: usize,                      // First exit the clock hasn't passed
+    clock: Option<DateTime<Utc>>,
+}
+
+impl PitStops {
+    /// `exits` must be sorted by date, as `pit_exits` returns them.
+    pub fn new(exits: Vec<(DateTime<Utc>, u32)>) -> PitStops {
+        PitStops {
+            exits,
+            ..PitStops::default()
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.next = 0;
+        self.clock = None;
+    }
+
+    /// Moves to `date` and returns the pit stops passed since the last call.
+    pub fn advance(&mut self, date: DateTime<Utc>) -> Vec<RaceEvent> {
+        let next = self.exits.partition_point(|&(exit, _)| exit <= date);
+        let passed = if self.clock.is_some_and(|clock| date < clock) {
+            &[][..]
+        } else {
+            &self.exits[self.next..next]
+        };
+        let stale = date - ChronoDuration::milliseconds(STALE_MILLIS);
+        let events = passed
+            .iter()
+            .filter(|&&(exit, _)| exit > stale)
+            .map(|&(date, driver_number)| RaceEvent::PitStop {
+                driver_number,
+                date,
+            })
+            .collect();
+        self.next = next;
+        self.clock = Some(date);
+        events
+    }
+}
@@ -0,0 +1,408 @@
+use crate::atomic_file::temporary_path;
+use crate::error::AppError;
+use crate::simulation::Rgb;
+use crate::sink::{LedSink, OutputFrame, DEFAULT_MAX_FPS};
+use crate::space::LedPoint;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Version of the `.ledrec` format, written to the header; bump it on any change to the layout
+/// below. A recording replays a race without the API, e.g. on a microcontroller.
+///
+/// The file is little endian throughout: a header, then one record per frame that changed any
+/// LED.
+///
+/// | Field       | Type    | Notes                                       |
+/// |-------------|---------|---------------------------------------------|
+/// | magic       | 4 bytes | `LREC`                                      |
+/// | version     | u16     | Readers reject versions they don't know     |
+/// | led_count   | u16     |                                             |
+/// | layout_hash | u64     | `layout_hash` of the LED coordinates        |
+/// | tick_rate   | u16     | Frames per second the recording was made at |
+///
+/// Each frame record is the race time as u32 milliseconds, a u16 count of changed LEDs, and for
+/// each changed LED its u16 index and red, green and blue bytes. Records never go back in time.
+/// LEDs keep their color until a later record changes it; before the first record every LED is
+/// black.
+pub const RECORDING_VERSION: u16 = 1;
+
+const MAGIC: &[u8; 4] = b"LREC";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecorderConfig {
+    pub enabled: bool, // Record from the start; the window can also toggle recording
+    pub path: PathBuf,
+    pub fps: f64,
+}
+
+impl Default for RecorderConfig {
+    fn default() -> Self {
+        RecorderConfig {
+            enabled: false,
+            path: PathBuf::from("race.ledrec"),
+            fps: DEFAULT_MAX_FPS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordingHeader {
+    pub version: u16,
+    pub led_count: u16,
+    pub layout_hash: u64,
+    pub tick_rate: u16,
+}
+
+/// One frame of a recording with every LED's color.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedFrame {
+    pub time: Duration, // Race time, to the millisecond
+    pub leds: Vec<Rgb>,
+}
+
+/// FNV-1a over the coordinate bits, so players on other platforms can check that a recording
+/// was made for their layout.
+pub fn layout_hash(coordinates: &[LedPoint]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for coord in coordinates {
+        for byte in coord
+            .x
+            .to_le_bytes()
+            .into_iter()
+            .chain(coord.y.to_le_bytes())
+        {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// Writes the header and delta-encoded frames of a recording.
+pub struct RecordingWriter<W: Write> {
+    writer: W,
+    previous: Vec<Rgb>, // LED colors as of the last written record
+    last_millis: u32,   // Race time of the last written record
+}
+
+impl<W: Write> RecordingWriter<W> {
+    pub fn new(mut writer: W, header: RecordingHeader) -> Result<RecordingWriter<W>, AppError> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&header.version.to_le_bytes())?;
+        writer.write_all(&header.led_count.to_le_bytes())?;
+        writer.write_all(&header.layout_hash.to_le_bytes())?;
+        writer.write_all(&header.tick_rate.to_le_bytes())?;
+        Ok(RecordingWriter {
+            writer,
+            previous: vec![[0, 0, 0]; header.led_count as usize],
+            last_millis: 0,
+        })
+    }
+
+    /// Race time of the last record written, zero before the first.
+    pub fn last_time(&self) -> Duration {
+        Duration::from_millis(self.last_millis as u64)
+    }
+
+    /// Records the LEDs that differ from the previous frame; nothing when none do. A frame
+    /// from before the last record is an error, as records can't go back in time.
+    pub fn write_frame(&mut self, time: Duration, leds: &[Rgb]) -> Result<(), AppError> {
+        let millis = u32::try_from(time.as_millis()).unwrap_or(u32::MAX);
+        if millis < self.last_millis {
+            return Err(AppError::Output {
+                reason: format!(
+                    "recorded frame at {}ms comes after one at {}ms",
+                    millis, self.last_millis
+                ),
+            });
+        }
+        let changed: Vec<(u16, Rgb)> = leds
+            .iter()
+            .zip(&self.previous)
+            .enumerate()
+            .filter(|(_, (color, previous))| color != previous)
+            .map(|(index, (&color, _))| (index as u16, color))
+            .collect();
+        if changed.is_empty() {
+            return Ok(());
+        }
+
+        let mut record = Vec::with_capacity(6 + changed.len() * 5);
+        record.extend_from_slice(&millis.to_le_bytes());
+        record.extend_from_slice(&(changed.len() as u16).to_le_bytes());
+        for (index, color) in changed {
+            record.extend_from_slice(&index.to_le_bytes());
+            record.extend_from_slice(&color);
+            self.previous[index as usize] = color;
+        }
+        self.writer.write_all(&record)?;
+        self.last_millis = millis;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), AppError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+// Seeking restores the nearest of these snapshots, taken every this many records, and plays the
+// records after it
+const KEYFRAME_INTERVAL: usize = 256;
+
+/// A recording loaded for playback: the records as read, an index of where each starts and its
+/// race time, and the full LED state every `KEYFRAME_INTERVAL` records for seeking.
+#[derive(Clone)]
+pub struct Recording {
+    pub header: RecordingHeader,
+    records: Vec<u8>,
+    index: Vec<(usize, Duration)>, // Offset in `records` and race time of each record
+    keyframes: Vec<Vec<Rgb>>,      // LED state after `i * KEYFRAME_INTERVAL` records
+}
+
+impl Recording {
+    /// Reads and checks a whole recording. A truncated last record, as left by a crash, is
+    /// dropped with a warning.
+    pub fn read(mut reader: impl Read) -> Result<Recording, AppError> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a .ledrec file"));
+        }
+        let version = read_u16(&mut reader)?;
+        if version != RECORDING_VERSION {
+            return Err(invalid(&format!("unsupported version {}", version)));
+        }
+        let header = RecordingHeader {
+            version,
+            led_count: read_u16(&mut reader)?,
+            layout_hash: u64::from_le_bytes(read_array(&mut reader)?),
+            tick_rate: read_u16(&mut reader)?,
+        };
+
+        let mut records = Vec::new();
+        reader.read_to_end(&mut records)?;
+        let mut recording = Recording {
+            header,
+            records,
+            index: Vec::new(),
+            keyframes: Vec::new(),
+        };
+        let mut leds = vec![[0, 0, 0]; header.led_count as usize];
+        recording.keyframes.push(leds.clone());
+        let mut offset = 0;
+        while offset < recording.records.len() {
+            let Some((time, end)) = recording.check_record(offset)? else {
+                warn!(
+                    "Ignoring the truncated last record of the recording, at {} bytes",
+                    offset
+                );
+                recording.records.truncate(offset);
+                break;
+            };
+            if let Some(&(_, previous)) = recording.index.last() {
+                if time < previous {
+                    return Err(invalid(&format!(
+                        "the record at {} bytes goes back from {}ms to {}ms",
+                        offset,
+                        previous.as_millis(),
+                        time.as_millis()
+                    )));
+                }
+            }
+            recording.index.push((offset, time));
+            recording.apply(recording.index.len() - 1, &mut leds);
+            if recording.index.len() == recording.keyframes.len() * KEYFRAME_INTERVAL {
+                recording.keyframes.push(leds.clone());
+            }
+            offset = end;
+        }
+        Ok(recording)
+    }
+
+    /// Number of records, i.e. of frames that changed something.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Race time of a record.
+    pub fn time(&self, record: usize) -> Duration {
+        self.index[record].1
+    }
+
+    /// Race time of the last record.
+    pub fn duration(&self) -> Duration {
+        self.index.last().map_or(Duration::ZERO, |&(_, time)| time)
+    }
+
+    /// Number of records at or before `time`.
+    pub fn records_until(&self, time: Duration) -> usize {
+        self.index
+            .partition_point(|&(_, record_time)| record_time <= time)
+    }
+
+    /// Brings `leds` from the state after `from` records to the state after `to`, replaying
+    /// from a keyframe when that's shorter or `to` is behind.
+    pub fn advance(&self, leds: &mut [Rgb], from: usize, to: usize) {
+        let to = to.min(self.len());
+        let from = if to < from || to - from > KEYFRAME_INTERVAL {
+            let keyframe = to / KEYFRAME_INTERVAL;
+            leds.copy_from_slice(&self.keyframes[keyframe]);
+            keyframe * KEYFRAME_INTERVAL
+        } else {
+            from
+        };
+        for record in from..to {
+            self.apply(record, leds);
+        }
+    }
+
+    // Sets the LEDs a record changes; records are checked while reading
+    fn apply(&self, record: usize, leds: &mut [Rgb]) {
+        let offset = self.index[record].0;
+        let count = u16::from_le_bytes([self.records[offset + 4], self.records[offset + 5]]);
+        for change in self.records[offset + 6..]
+            .chunks_exact(5)
+            .take(count as usize)
+        {
+            let index = u16::from_le_bytes([change[0], change[1]]) as usize;
+            leds[index] = [change[2], change[3], change[4]];
+        }
+    }
+
+    // The time and end of the record at `offset`, or `None` when the data stops inside it
+    fn check_record(&self, offset: usize) -> Result<Option<(Duration, usize)>, AppError> {
+        let Some(fixed) = self.records.get(offset..offset + 6) else {
+            return Ok(None);
+        };
+        let millis = u32::from_le_bytes([fixed[0], fixed[1], fixed[2], fixed[3]]);
+        let count = u16::from_le_bytes([fixed[4], fixed[5]]) as usize;
+        let end = offset + 6 + count * 5;
+        let Some(changes) = self.records.get(offset + 6..end) else {
+            return Ok(None);
+        };
+        for change in changes.chunks_exact(5) {
+            let index = u16::from_le_bytes([change[0], change[1]]);
+            if index >= self.header.led_count {
+                return Err(invalid(&format!("LED {} is out of range", index)));
+            }
+        }
+        Ok(Some((Duration::from_millis(millis as u64), end)))
+    }
+}
+
+/// Reads a whole recording, expanding each record into a full frame.
+pub fn read_recording(
+    reader: impl Read,
+) -> Result<(RecordingHeader, Vec<RecordedFrame>), AppError> {
+    let recording = Recording::read(reader)?;
+    let mut leds = vec![[0, 0, 0]; recording.header.led_count as usize];
+    let frames = (0..recording.len())
+        .map(|record| {
+            recording.apply(record, &mut leds);
+            RecordedFrame {
+                time: recording.time(record),
+                leds: leds.clone(),
+            }
+        })
+        .collect();
+    Ok((recording.header, frames))
+}
+
+fn invalid(reason: &str) -> AppError {
+    AppError::Decode {
+        context: format!("LED recording: {}", reason),
+    }
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> std::io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_u16(reader: &mut impl Read) -> std::io::Result<u16> {
+    read_array(reader).map(u16::from_le_bytes)
+}
+
+/// Records the frames it receives, with brightness applied, to a `.ledrec` file. The file is
+/// written under a temporary name and only takes its own once the recording is finished.
+pub struct FrameRecorder {
+    path: PathBuf,
+    header: RecordingHeader,
+    writer: Option<RecordingWriter<BufWriter<File>>>,
+}
+
+impl FrameRecorder {
+    pub fn new(
+        config: &RecorderConfig,
+        coordinates: &[LedPoint],
+    ) -> Result<FrameRecorder, AppError> {
+        let led_count = u16::try_from(coordinates.len()).map_err(|_| AppError::Config {
+            reason: format!(
+                "recordings hold at most {} LEDs, the layout has {}",
+                u16::MAX,
+                coordinates.len()
+            ),
+        })?;
+        Ok(FrameRecorder {
+            path: config.path.clone(),
+            header: RecordingHeader {
+                version: RECORDING_VERSION,
+                led_count,
+                layout_hash: layout_hash(coordinates),
+                tick_rate: config.fps.round().clamp(1.0, u16::MAX as f64) as u16,
+            },
+            writer: None,
+        })
+    }
+}
+
+impl LedSink for FrameRecorder {
+    fn name(&self) -> &str {
+        "recorder"
+    }
+
+    fn start(&mut self) -> Result<(), AppError> {
+        let file = BufWriter::new(File::create(temporary_path(&self.path))?);
+        self.writer = Some(RecordingWriter::new(file, self.header)?);
+        info!("Recording frames to {}", self.path.display());
+        Ok(())
+    }
+
+    // After seeking back, frames are left out until the clock passes the last one recorded
+    fn submit(&mut self, frame: &OutputFrame) -> Result<(), AppError> {
+        match &mut self.writer {
+            Some(writer) if frame.timestamp >= writer.last_time() => {
+                writer.write_frame(frame.timestamp, &frame.dimmed())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn shutdown(&mut self) {
+        if let Some(mut writer) = self.writer.take() {
+            let finished = writer.flush().and_then(|()| {
+                std::fs::rename(temporary_path(&self.path), &self.path)?;
+                Ok(())
+            });
+            match finished {
+                Ok(()) => info!("Saved the recording {}", self.path.display()),
+                Err(err) => warn!("Could not finish {}: {}", self.path.display(), err),
+            }
+        }
+    }
+
+    fn blank_on_shutdown(&self) -> bool {
+        false
+    }
+}
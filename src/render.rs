@@ -0,0 +1,246 @@
+use crate::simulation::Rgb;
+use crate::space::LedPoint;
+use crate::viewport::{Bounds, Camera, TrackViewport};
+use eframe::egui;
+use image::{Rgba, RgbaImage};
+
+// Same margin and colors as the window with the dark theme
+const MARGIN: f32 = 30.0;
+const UNLIT: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+/// Background of rendered images, like the window's with the dark theme.
+pub const BACKGROUND: Rgba<u8> = Rgba([27, 27, 27, 255]);
+/// Color of the race clock and driver numbers.
+pub const TEXT_COLOR: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+// 3x5 pixel glyphs of the race clock and labels, one row per byte with the leftmost pixel in bit 2
+const GLYPH_WIDTH: u32 = 3;
+const GLYPH_HEIGHT: u32 = 5;
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+const COLON: [u8; 5] = [0b000, 0b010, 0b000, 0b010, 0b000];
+const POINT: [u8; 5] = [0b000, 0b000, 0b000, 0b000, 0b010];
+
+/// Draws the track into images of a fixed size, the way the window paints it but without one,
+/// for exports.
+pub struct FrameRenderer {
+    corners: Vec<(i64, i64)>, // Top left pixel of each LED square
+    led_size: u32,
+    width: u32,
+    height: u32,
+}
+
+impl FrameRenderer {
+    /// `led_size` is the side length of an LED square in pixels.
+    pub fn new(coordinates: &[LedPoint], width: u32, height: u32, led_size: f32) -> Self {
+        Self::framed(coordinates, width, height, led_size, None)
+    }
+
+    /// Like `new`, but zoomed and panned as `camera` shows the track in the window.
+    pub fn with_camera(
+        coordinates: &[LedPoint],
+        width: u32,
+        height: u32,
+        led_size: f32,
+        camera: Camera,
+    ) -> Self {
+        Self::framed(coordinates, width, height, led_size, Some(camera))
+    }
+
+    fn framed(
+        coordinates: &[LedPoint],
+        width: u32,
+        height: u32,
+        led_size: f32,
+        camera: Option<Camera>,
+    ) -> Self {
+        let mut viewport = TrackViewport::new(
+            Bounds::from_coordinates(coordinates),
+            egui::vec2(width as f32, height as f32),
+            MARGIN,
+        );
+        if let Some(camera) = camera {
+            viewport = viewport.with_camera(camera);
+        }
+        let corners = coordinates
+            .iter()
+            .map(|coord| {
+                let corner = viewport.to_screen(*coord);
+                (corner.x.round() as i64, corner.y.round() as i64)
+            })
+            .collect();
+        FrameRenderer {
+            corners,
+            led_size: led_size.round().max(1.0) as u32,
+            width,
+            height,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// One frame: every LED as a black square, lit in its color from `leds`, and the race time
+    /// in seconds in the top left corner when given.
+    pub fn render(&self, leds: &[Rgb], race_time: Option<f64>) -> RgbaImage {
+        let mut image = RgbaImage::from_pixel(self.width, self.height, BACKGROUND);
+        for (index, &(x, y)) in self.corners.iter().enumerate() {
+            let color = match leds.get(index) {
+                Some(&[r, g, b]) => Rgba([r, g, b, 255]),
+                None => UNLIT,
+            };
+            fill_rect(&mut image, x, y, self.led_size, self.led_size, color);
+        }
+        if let Some(race_time) = race_time {
+            self.draw_clock(&mut image, race_time);
+        }
+        image
+    }
+
+    /// Writes each driver's number to the right of their LED, given as `(driver, LED index)`.
+    pub fn draw_labels(&self, image: &mut RgbaImage, labels: &[(u32, usize)]) {
+        let scale = (self.height / 360).max(1);
+        for &(driver_number, index) in labels {
+            if let Some(&(x, y)) = self.corners.get(index) {
+                let x = x + self.led_size as i64 + scale as i64;
+                draw_text(image, x, y, scale, &driver_number.to_string(), TEXT_COLOR);
+            }
+        }
+    }
+
+    // Scaled with the image height, so the clock reads the same at every resolution
+    fn draw_clock(&self, image: &mut RgbaImage, race_time: f64) {
+        let scale = (self.height / 180).max(1);
+        let corner = MARGIN as i64 / 2;
+        draw_text(
+            image,
+            corner,
+            corner,
+            scale,
+            &format_race_time(race_time),
+            TEXT_COLOR,
+        );
+    }
+}
+
+/// Size in pixels of `text` drawn with `draw_text`.
+pub fn text_size(text: &str, scale: u32) -> (u32, u32) {
+    let characters = text.chars().count() as u32;
+    (
+        (characters * (GLYPH_WIDTH + 1)).saturating_sub(1) * scale,
+        GLYPH_HEIGHT * scale,
+    )
+}
+
+/// Draws digits, colons and points with built-in glyphs from the top left corner at `x`, `y`,
+/// `scale` pixels per glyph pixel; anything else is skipped.
+pub fn draw_text(
+    image: &mut RgbaImage,
+    mut x: i64,
+    y: i64,
+    scale: u32,
+    text: &str,
+    color: Rgba<u8>,
+) {
+    for character in text.chars() {
+        let glyph = match character {
+            ':' => &COLON,
+            '.' => &POINT,
+            digit => match digit.to_digit(10) {
+                Some(digit) => &DIGITS[digit as usize],
+                None => continue,
+            },
+        };
+        for (row, bits) in glyph.iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                    fill_rect(
+                        image,
+                        x + (column * scale) as i64,
+                        y + (row as u32 * scale) as i64,
+                        scale,
+                        scale,
+                        color,
+                    );
+                }
+            }
+        }
+        x += ((GLYPH_WIDTH + 1) * scale) as i64;
+    }
+}
+
+/// Race time as the window shows it, `HH:MM:SS.ss`.
+pub fn format_race_time(race_time: f64) -> String {
+    format!(
+        "{:02}:{:02}:{:05.2}",
+        (race_time / 3600.0).floor() as u32,          // hours
+        ((race_time % 3600.0) / 60.0).floor() as u32, // minutes
+        race_time % 60.0                              // seconds with milliseconds
+    )
+}
+
+/// A duration in whole seconds, `HH:MM:SS`; the hours go past 99 rather than wrap.
+pub fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.max(0.0) as u64;
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Draws a straight line `width` pixels thick, clipped to the image.
+pub fn draw_line(
+    image: &mut RgbaImage,
+    from: (f32, f32),
+    to: (f32, f32),
+    width: u32,
+    color: Rgba<u8>,
+) {
+    let steps = (to.0 - from.0)
+        .abs()
+        .max((to.1 - from.1).abs())
+        .ceil()
+        .max(1.0) as u32;
+    let offset = width as f32 / 2.0;
+    for step in 0..=steps {
+        let t = step as f32 / steps as f32;
+        let x = from.0 + (to.0 - from.0) * t - offset;
+        let y = from.1 + (to.1 - from.1) * t - offset;
+        fill_rect(
+            image,
+            x.round() as i64,
+            y.round() as i64,
+            width,
+            width,
+            color,
+        );
+    }
+}
+
+// Fills a rectangle, clipped to the image
+fn fill_rect(image: &mut RgbaImage, x: i64, y: i64, width: u32, height: u32, color: Rgba<u8>) {
+    let x_range = x.max(0)..(x + width as i64).min(image.width() as i64);
+    let y_range = y.max(0)..(y + height as i64).min(image.height() as i64);
+    for py in y_range {
+        for px in x_range.clone() {
+            image.put_pixel(px as u32, py as u32, color);
+        }
+    }
+}
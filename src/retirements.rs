@@ -0,0 +1,81 @@
+use crate::timeline::DriverTimelines;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// When a driver counts as retired, and what happens to their LED then.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetirementConfig {
+    pub inactivity_secs: f64, // A driver whose samples stop this long before the data ends retired
+    pub keep_last_led: bool,  // Keep retired and stale drivers lit on their last LED
+    pub stale_secs: f64,      // A driver's LED fades once their last sample is this old; 0 never
+}
+
+impl Default for RetirementConfig {
+    fn default() -> Self {
+        RetirementConfig {
+            inactivity_secs: 60.0,
+            keep_last_led: false,
+            stale_secs: 20.0,
+        }
+    }
+}
+
+/// Seconds a stale driver's LED takes to fade out.
+pub const STALE_FADE_SECS: f64 = 1.0;
+
+/// How bright a driver's LED is with their last sample `age` seconds before the clock: full
+/// until `stale_secs`, then fading out over `STALE_FADE_SECS`.
+pub fn stale_alpha(age: f64, stale_secs: f64) -> f32 {
+    (1.0 - (age - stale_secs) / STALE_FADE_SECS).clamp(0.0, 1.0) as f32
+}
+
+/// The drivers whose location stream ends early, and from when they count as retired.
+#[derive(Debug, Clone, Default)]
+pub struct Retirements {
+    since: HashMap<u32, DateTime<Utc>>, // Last sample plus the inactivity threshold
+}
+
+impl Retirements {
+    /// Finds the drivers whose last sample is more than `config.inactivity_secs` before the
+    /// last sample of anyone. They retire that long after their last sample, so a car that is
+    /// only stopped for a while, e.g. under a red flag, never does.
+    pub fn new(timelines: &DriverTimelines, config: &RetirementConfig) -> Retirements {
+        let inactivity = ChronoDuration::milliseconds((config.inactivity_secs * 1000.0) as i64);
+        let last_samples: HashMap<u32, DateTime<Utc>> = timelines
+            .drivers()
+            .filter_map(|driver_number| {
+                let last = timelines.timeline(driver_number).last()?;
+                Some((driver_number, last.date))
+            })
+            .collect();
+        let Some(&end) = last_samples.values().max() else {
+            return Retirements::default();
+        };
+
+        let since = last_samples
+            .into_iter()
+            .filter(|&(_, last)| end - last > inactivity)
+            .map(|(driver_number, last)| (driver_number, last + inactivity))
+            .collect();
+        Retirements { since }
+    }
+
+    /// Whether the driver has retired by `date`; rewinding before it brings them back.
+    pub fn is_retired(&self, driver_number: u32, date: DateTime<Utc>) -> bool {
+        self.since
+            .get(&driver_number)
+            .is_some_and(|since| *since <= date)
+    }
+
+    /// When the driver retires, if they do.
+    pub fn since(&self, driver_number: u32) -> Option<DateTime<Utc>> {
+        self.since.get(&driver_number).copied()
+    }
+
+    /// The dates drivers retire, in no particular order.
+    pub fn dates(&self) -> impl Iterator<Item = DateTime<Utc>> + '_ {
+        self.since.values().copied()
+    }
+}
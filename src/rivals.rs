@@ -0,0 +1,135 @@
+use crate::laps::RaceProgress;
+use crate::track_progress::TrackProgress;
+use chrono::{DateTime, Utc};
+
+/// Where a rival's gap comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GapSource {
+    Intervals, // The OpenF1 gaps to the leader
+    Estimate,  // The LEDs between the cars at the focused driver's last lap time
+}
+
+/// The car closest to the focused driver in one direction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rival {
+    pub driver_number: u32,
+    pub leds: usize,           // LEDs between the two cars along the track
+    pub gap_secs: Option<f64>, // Unknown for an estimate before a lap time is known
+    pub source: GapSource,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NearestRivals {
+    pub ahead: Option<Rival>,
+    pub behind: Option<Rival>,
+}
+
+/// The cars closest ahead of and behind `focused` on the LEDs, counting round the start/finish
+/// line, so the last car on the lap has the first one ahead of it. A car on the same LED counts
+/// as ahead. The gaps come from the gaps to the leader at `time` when both cars have one, and
+/// are estimated from the LEDs between them otherwise.
+pub fn nearest_rivals(
+    track: &TrackProgress,
+    focused: u32,
+    progress: Option<&RaceProgress>,
+    time: Option<DateTime<Utc>>,
+) -> NearestRivals {
+    let led_count = track.led_count();
+    let Some(own) = track.led(focused).filter(|_| led_count > 0) else {
+        return NearestRivals::default();
+    };
+    let others: Vec<(u32, usize)> = track
+        .leds()
+        .filter(|&(driver_number, _)| driver_number != focused)
+        .collect();
+    let nearest = |distance: &dyn Fn(usize) -> usize, skip_same_led: bool| {
+        others
+            .iter()
+            .map(|&(driver_number, led)| (distance(led), driver_number))
+            .filter(|&(leds, _)| !(skip_same_led && leds == 0))
+            .min()
+            .map(|(leds, driver_number)| {
+                let (gap_secs, source) = gap(track, focused, driver_number, leds, progress, time);
+                Rival {
+                    driver_number,
+                    leds,
+                    gap_secs,
+                    source,
+                }
+            })
+    };
+    NearestRivals {
+        ahead: nearest(&|led| (led + led_count - own) % led_count, false),
+        behind: nearest(&|led| (own + led_count - led) % led_count, true),
+    }
+}
+
+fn gap(
+    track: &TrackProgress,
+    focused: u32,
+    rival: u32,
+    leds: usize,
+    progress: Option<&RaceProgress>,
+    time: Option<DateTime<Utc>>,
+) -> (Option<f64>, GapSource) {
+    let (Some(progress), Some(time)) = (progress, time) else {
+        return (None, GapSource::Estimate);
+    };
+    if let (Some(own), Some(other)) = (
+        progress.gap_to_leader(focused, time),
+        progress.gap_to_leader(rival, time),
+    ) {
+        return (Some((own - other).abs()), GapSource::Intervals);
+    }
+    // The last lap the driver completed before `time` stands for the whole lap
+    let lap_secs = progress
+        .driver_laps(focused)
+        .iter()
+        .take_while(|lap| {
+            progress
+                .lap_end(focused, lap.lap_number)
+                .is_some_and(|end| end <= time)
+        })
+        .filter_map(|lap| lap.lap_duration)
+        .last();
+    let share = leds as f64 / track.led_count() as f64;
+    (
+        lap_secs.map(|lap_secs| share * lap_secs),
+        GapSource::Estimate,
+    )
+}
+
+/// Keeps the focused driver's rivals, worked out again only when the focus changes or a
+/// driver moves to another LED.
+#[derive(Debug, Clone, Default)]
+pub struct RivalTracker {
+    worked_out_for: Option<(u32, u64)>, // Focused driver and track progress version
+    rivals: NearestRivals,
+}
+
+impl RivalTracker {
+    pub fn update(
+        &mut self,
+        track: &TrackProgress,
+        focused: Option<u32>,
+        progress: Option<&RaceProgress>,
+        time: Option<DateTime<Utc>>,
+    ) -> NearestRivals {
+        let Some(focused) = focused else {
+            self.clear();
+            return self.rivals;
+        };
+        let key = (focused, track.version());
+        if self.worked_out_for != Some(key) {
+            self.rivals = nearest_rivals(track, focused, progress, time);
+            self.worked_out_for = Some(key);
+        }
+        self.rivals
+    }
+
+    /// Forgets the rivals, e.g. when the positions come from another session.
+    pub fn clear(&mut self) {
+        self.worked_out_for = None;
+        self.rivals = NearestRivals::default();
+    }
+}
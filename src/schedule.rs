@@ -0,0 +1,71 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use std::time::Duration;
+
+/// A start this much after its time still counts as on time; later ones come with a warning.
+pub const LATE_START_TOLERANCE: Duration = Duration::from_secs(1);
+
+const TIME_FORMATS: [&str; 2] = ["%H:%M", "%H:%M:%S"];
+const DATE_TIME_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%dT%H:%M:%S",
+];
+
+/// Parses when playback should start, in local time: "15:00" or "15:00:30" on `today`, or a
+/// full date like "2024-05-26 15:00". RFC 3339 with an offset is taken as it is.
+pub fn parse_start_time(value: &str, today: NaiveDate) -> Result<DateTime<Local>, String> {
+    let value = value.trim();
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(date_time.with_timezone(&Local));
+    }
+    let time = TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(value, format).ok());
+    let naive = match time {
+        Some(time) => today.and_time(time),
+        None => DATE_TIME_FORMATS
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+            .ok_or_else(|| {
+                format!(
+                    "{:?} is neither a time like 15:00 nor a date like 2024-05-26 15:00",
+                    value
+                )
+            })?,
+    };
+    // The hour skipped when the clocks go forward never comes; of the one repeated when they
+    // go back, the first is meant
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(|| format!("{} doesn't exist in local time", naive))
+}
+
+/// Playback armed to start at a wall-clock time. It's checked against the clock each time
+/// rather than timed once, so an app started hours ahead, or a computer that slept in
+/// between, still starts on the second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduledStart {
+    at: DateTime<Local>,
+}
+
+impl ScheduledStart {
+    pub fn new(at: DateTime<Local>) -> ScheduledStart {
+        ScheduledStart { at }
+    }
+
+    pub fn at(&self) -> DateTime<Local> {
+        self.at
+    }
+
+    /// Time left until the start at `now`, zero once it's due.
+    pub fn remaining(&self, now: DateTime<Local>) -> Duration {
+        (self.at - now).to_std().unwrap_or_default()
+    }
+
+    /// How late a start at `now` is, `None` while it isn't due yet.
+    pub fn lateness(&self, now: DateTime<Local>) -> Option<Duration> {
+        (now >= self.at).then(|| (now - self.at).to_std().unwrap_or_default())
+    }
+}
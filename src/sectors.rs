@@ -0,0 +1,155 @@
+use crate::timeline::{DriverTimelines, LedCrossings};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Where the sectors of the track begin.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SectorConfig {
+    pub starts: Vec<usize>, // First LED of each sector in driving order; empty splits the track in three
+}
+
+impl SectorConfig {
+    /// The first LED of each sector on a track of `led_count` LEDs, ascending.
+    pub fn boundaries(&self, led_count: usize) -> Vec<usize> {
+        let mut starts: Vec<usize> = if self.starts.is_empty() {
+            (0..3).map(|sector| sector * led_count / 3).collect()
+        } else {
+            self.starts
+                .iter()
+                .copied()
+                .filter(|&led_index| led_index < led_count)
+                .collect()
+        };
+        starts.sort();
+        starts.dedup();
+        starts
+    }
+}
+
+/// How long a driver took through one sector of one lap.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SectorTime {
+    pub lap: u32,    // Counted from 1, the lap the driver's data starts on
+    pub sector: u32, // Counted from 1
+    pub seconds: f64,
+    pub end: DateTime<Utc>, // When the driver left the sector; the time isn't known before
+}
+
+/// A driver's time through each sector they completed, from the dates they first reached its
+/// first LED and the next sector's. Sorted by `end`.
+pub fn sector_times(
+    crossings: &LedCrossings,
+    boundaries: &[usize],
+    led_count: usize,
+) -> Vec<SectorTime> {
+    let Some(&first) = boundaries.first() else {
+        return Vec::new();
+    };
+    // Positions moved so that laps begin on multiples of `led_count`
+    let shifted = |position: usize| position + led_count - first;
+    let first_lap = shifted(crossings.start()) / led_count;
+    let mut entries = Vec::new(); // (lap, sector, date) of every sector start reached, in order
+    for lap in first_lap.. {
+        if lap * led_count >= shifted(crossings.end()) {
+            break;
+        }
+        for (sector, &led_index) in boundaries.iter().enumerate() {
+            let Some(position) = (lap * led_count + led_index).checked_sub(led_count) else {
+                continue;
+            };
+            if let Some(date) = crossings.at(position) {
+                entries.push(((lap - first_lap + 1) as u32, sector as u32 + 1, date));
+            }
+        }
+    }
+    // The reached positions have no gaps, so neither have the entries
+    entries
+        .windows(2)
+        .map(|pair| SectorTime {
+            lap: pair[0].0,
+            sector: pair[0].1,
+            seconds: (pair[1].2 - pair[0].2).num_milliseconds() as f64 / 1000.0,
+            end: pair[1].2,
+        })
+        .collect()
+}
+
+/// Every driver's sector times, revealed as the clock passes the end of each.
+#[derive(Debug, Clone, Default)]
+pub struct SectorTimes {
+    by_driver: HashMap<u32, Vec<SectorTime>>,
+    sectors: u32,
+}
+
+impl SectorTimes {
+    pub fn new(
+        timelines: &DriverTimelines,
+        led_count: usize,
+        config: &SectorConfig,
+    ) -> SectorTimes {
+        let boundaries = config.boundaries(led_count);
+        let by_driver = timelines
+            .drivers()
+            .map(|driver_number| {
+                let crossings = LedCrossings::new(timelines.timeline(driver_number), led_count);
+                (
+                    driver_number,
+                    sector_times(&crossings, &boundaries, led_count),
+                )
+            })
+            .collect();
+        SectorTimes {
+            by_driver,
+            sectors: boundaries.len() as u32,
+        }
+    }
+
+    /// Number of sectors per lap.
+    pub fn sectors(&self) -> u32 {
+        self.sectors
+    }
+
+    // The driver's times through `sector` that ended by `date`
+    fn known(
+        &self,
+        driver_number: u32,
+        sector: u32,
+        date: DateTime<Utc>,
+    ) -> impl Iterator<Item = &SectorTime> + '_ {
+        let times = self
+            .by_driver
+            .get(&driver_number)
+            .map_or(&[][..], Vec::as_slice);
+        let known = times.partition_point(|time| time.end <= date);
+        times[..known]
+            .iter()
+            .filter(move |time| time.sector == sector)
+    }
+
+    /// The driver's latest time through `sector` as of `date`.
+    pub fn last(&self, driver_number: u32, sector: u32, date: DateTime<Utc>) -> Option<SectorTime> {
+        self.known(driver_number, sector, date).last().copied()
+    }
+
+    /// The driver's fastest time through `sector` as of `date`.
+    pub fn personal_best(
+        &self,
+        driver_number: u32,
+        sector: u32,
+        date: DateTime<Utc>,
+    ) -> Option<SectorTime> {
+        self.known(driver_number, sector, date)
+            .min_by(|a, b| a.seconds.total_cmp(&b.seconds))
+            .copied()
+    }
+
+    /// Anybody's fastest time through `sector` as of `date`.
+    pub fn overall_best(&self, sector: u32, date: DateTime<Utc>) -> Option<SectorTime> {
+        self.by_driver
+            .keys()
+            .filter_map(|&driver_number| self.personal_best(driver_number, sector, date))
+            .min_by(|a, b| a.seconds.total_cmp(&b.seconds))
+    }
+}
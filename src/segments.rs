@@ -0,0 +1,276 @@
+use crate::error::AppError;
+use crate::led_coords::LayoutTransform;
+use crate::simulation::Rgb;
+use crate::sink::{LedSink, OutputFrame};
+use crate::space::LedPoint;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// What a segment's LEDs show: the circuit itself, or the pit lane, which only gets the
+/// samples close enough to its own LEDs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SegmentRole {
+    #[default]
+    Track,
+    Pit,
+}
+
+/// One board of a daisy chain.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SegmentConfig {
+    pub name: String,
+    pub role: SegmentRole,
+    pub coordinates: Option<Vec<[f64; 2]>>, // In the layout's units; the built-in circuit when unset
+    pub chain_offset: usize,                // Pixel of the chain its first LED is
+    pub rotation: f64,                      // How the board is drawn, clockwise in degrees
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+}
+
+/// The boards making up the layout; without any, the built-in circuit is the whole chain.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LayoutConfig {
+    pub segments: Vec<SegmentConfig>,
+}
+
+/// A segment of the layout: its LEDs in the layout and where they sit on the chain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub name: String,
+    pub role: SegmentRole,
+    pub leds: Range<usize>, // Indices in the layout's coordinates
+    pub chain_offset: usize,
+    pub transform: LayoutTransform,
+}
+
+impl Segment {
+    /// The pixels of the chain the segment drives.
+    pub fn chain(&self) -> Range<usize> {
+        self.chain_offset..self.chain_offset + self.leds.len()
+    }
+}
+
+/// The coordinates of every segment merged into one layout, the track segment first and the
+/// others in the configured order, with where each segment's LEDs go on the chain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    coordinates: Vec<LedPoint>,
+    segments: Vec<Segment>,
+}
+
+impl Layout {
+    /// A single board driven from the first pixel.
+    pub fn single(coordinates: Vec<LedPoint>) -> Layout {
+        let segments = vec![Segment {
+            name: "track".to_string(),
+            role: SegmentRole::Track,
+            leds: 0..coordinates.len(),
+            chain_offset: 0,
+            transform: LayoutTransform::default(),
+        }];
+        Layout {
+            coordinates,
+            segments,
+        }
+    }
+
+    /// The layout `config` describes, `builtin` standing in for a track segment without
+    /// coordinates. There must be exactly one track segment, every segment needs a name of its
+    /// own and LEDs, and no two segments may drive the same pixels.
+    pub fn new(config: &LayoutConfig, builtin: Vec<LedPoint>) -> Result<Layout, AppError> {
+        let invalid = |reason: String| AppError::LayoutInvalid { reason };
+        if config.segments.is_empty() {
+            return Ok(Layout::single(builtin));
+        }
+        let tracks = config
+            .segments
+            .iter()
+            .filter(|segment| segment.role == SegmentRole::Track)
+            .count();
+        if tracks != 1 {
+            return Err(invalid(format!(
+                "the layout needs exactly one track segment, not {}",
+                tracks
+            )));
+        }
+        let mut names = HashSet::new();
+        for segment in &config.segments {
+            if segment.name.is_empty() {
+                return Err(invalid("every layout segment needs a name".to_string()));
+            }
+            if !names.insert(segment.name.as_str()) {
+                return Err(invalid(format!(
+                    "there are several layout segments called {}",
+                    segment.name
+                )));
+            }
+        }
+
+        let mut ordered: Vec<&SegmentConfig> = config.segments.iter().collect();
+        ordered.sort_by_key(|segment| segment.role != SegmentRole::Track);
+        let mut coordinates = Vec::new();
+        let mut segments = Vec::new();
+        for segment in ordered {
+            let points = match (&segment.coordinates, segment.role) {
+                (Some(points), _) => points.iter().map(|&[x, y]| LedPoint::new(x, y)).collect(),
+                (None, SegmentRole::Track) => builtin.clone(),
+                (None, SegmentRole::Pit) => {
+                    return Err(invalid(format!(
+                        "layout segment {} needs coordinates",
+                        segment.name
+                    )))
+                }
+            };
+            if points.is_empty() {
+                return Err(invalid(format!(
+                    "layout segment {} has no LEDs",
+                    segment.name
+                )));
+            }
+            let start = coordinates.len();
+            coordinates.extend(points);
+            segments.push(Segment {
+                name: segment.name.clone(),
+                role: segment.role,
+                leds: start..coordinates.len(),
+                chain_offset: segment.chain_offset,
+                transform: LayoutTransform {
+                    rotation: segment.rotation,
+                    flip_horizontal: segment.flip_horizontal,
+                    flip_vertical: segment.flip_vertical,
+                },
+            });
+        }
+
+        let mut on_chain: Vec<&Segment> = segments.iter().collect();
+        on_chain.sort_by_key(|segment| segment.chain_offset);
+        for pair in on_chain.windows(2) {
+            let (first, second) = (pair[0].chain(), pair[1].chain());
+            if first.end > second.start {
+                return Err(invalid(format!(
+                    "layout segments {} (pixels {}-{}) and {} (pixels {}-{}) overlap on the chain",
+                    pair[0].name,
+                    first.start,
+                    first.end - 1,
+                    pair[1].name,
+                    second.start,
+                    second.end - 1
+                )));
+            }
+        }
+        Ok(Layout {
+            coordinates,
+            segments,
+        })
+    }
+
+    /// Every LED of every segment.
+    pub fn coordinates(&self) -> &[LedPoint] {
+        &self.coordinates
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// The track segment, which comes first.
+    pub fn track(&self) -> &Segment {
+        &self.segments[0]
+    }
+
+    /// The LEDs of the pit segments, for the mapping.
+    pub fn pit_leds(&self) -> Vec<Range<usize>> {
+        self.segments
+            .iter()
+            .filter(|segment| segment.role == SegmentRole::Pit)
+            .map(|segment| segment.leds.clone())
+            .collect()
+    }
+
+    /// Whether the chain differs from the layout's LED order, so the outputs need their frames
+    /// rearranged.
+    pub fn is_chained(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|segment| segment.chain_offset != segment.leds.start)
+    }
+
+    /// Pixels on the chain, up to the end of the last segment.
+    pub fn chain_length(&self) -> usize {
+        self.segments
+            .iter()
+            .map(|segment| segment.chain().end)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The chain's pixels for a frame of layout colors: each segment's colors at its offset,
+    /// with the pixels between segments black.
+    pub fn chain_frame(&self, leds: &[Rgb]) -> Vec<Rgb> {
+        let mut pixels = vec![[0, 0, 0]; self.chain_length()];
+        for segment in &self.segments {
+            let end = segment.leds.end.min(leds.len());
+            let colors = leds.get(segment.leds.start..end).unwrap_or_default();
+            pixels[segment.chain_offset..segment.chain_offset + colors.len()]
+                .copy_from_slice(colors);
+        }
+        pixels
+    }
+
+    /// The coordinates as drawn: every segment turned by its own transform, the track then
+    /// also by `view`.
+    pub fn view_coordinates(&self, view: &LayoutTransform) -> Vec<LedPoint> {
+        let mut coordinates = Vec::with_capacity(self.coordinates.len());
+        for segment in &self.segments {
+            let turned = segment
+                .transform
+                .transform_coordinates(&self.coordinates[segment.leds.clone()]);
+            match segment.role {
+                SegmentRole::Track => coordinates.extend(view.transform_coordinates(&turned)),
+                SegmentRole::Pit => coordinates.extend(turned),
+            }
+        }
+        coordinates
+    }
+}
+
+/// Feeds another sink the chain's pixels instead of the layout's LEDs.
+pub struct ChainSink {
+    inner: Box<dyn LedSink>,
+    layout: Layout,
+}
+
+impl ChainSink {
+    pub fn new(inner: Box<dyn LedSink>, layout: Layout) -> ChainSink {
+        ChainSink { inner, layout }
+    }
+}
+
+impl LedSink for ChainSink {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn start(&mut self) -> Result<(), AppError> {
+        self.inner.start()
+    }
+
+    fn submit(&mut self, frame: &OutputFrame) -> Result<(), AppError> {
+        self.inner.submit(&OutputFrame {
+            leds: self.layout.chain_frame(&frame.leds),
+            brightness: frame.brightness,
+            timestamp: frame.timestamp,
+            state: frame.state,
+            speed: frame.speed,
+            drivers: frame.drivers.clone(),
+        })
+    }
+
+    fn shutdown(&mut self) {
+        self.inner.shutdown();
+    }
+}
@@ -0,0 +1,349 @@
+use crate::bundle::Bundle;
+use crate::cache::{
+    car_data_cache_key, drivers_cache_key, load_car_data, load_drivers, load_mapping,
+    load_progress, mapping_cache_key, progress_cache_key, store_car_data, store_drivers,
+    store_mapping, store_progress,
+};
+use crate::config::{ApiConfig, SessionConfig};
+use crate::data::{
+    fetch_car_data, fetch_driver_data_cached, fetch_drivers, fetch_intervals, fetch_laps,
+    fetch_positions, fetch_race_control, fetch_session, fetch_stints, CarData, TimeWindow,
+};
+use crate::driver_info::{
+    driver_numbers, season_roster, session_roster, DriverInfo, RosterConfig, DEFAULT_SEASON,
+};
+use crate::error::AppError;
+use crate::laps::RaceProgress;
+use crate::mapping::{map_drivers, MappingOptions, MappingStats, RunRace, MAPPING_VERSION};
+use crate::notices;
+use crate::space::LedPoint;
+use chrono::{Datelike, Utc};
+use log::{info, warn};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// What fetching and mapping a session needs.
+#[derive(Debug, Clone)]
+pub struct DataSource {
+    pub api: ApiConfig,
+    pub mapping: MappingOptions,
+    pub cache_dir: PathBuf,
+    pub coordinates: Vec<LedPoint>,
+    pub roster: RosterConfig,
+}
+
+/// The data of a loaded session.
+pub struct LoadedSession {
+    pub run_race_data: Vec<RunRace>,
+    pub mapping_stats: MappingStats,
+    pub race_progress: Option<RaceProgress>,
+    pub driver_info: Vec<DriverInfo>, // The roster as it was in the session
+    pub made_up_drivers: Vec<u32>, // In the data but neither on the roster nor the session's list
+}
+
+/// Fetches or loads everything shown of a session: its mapped records, the roster as it was in
+/// the session and, when the session asks for them, the laps and positions. The records, the
+/// driver list and the laps are cached, so loading the session again fetches none of them.
+pub fn load_session_data(
+    source: &DataSource,
+    session: &SessionConfig,
+) -> Result<LoadedSession, AppError> {
+    let roster = session_season_roster(&source.api, &source.roster, session);
+    let driver_numbers = session_drivers(session, &roster);
+    let (run_race_data, mapping_stats) = prepare_race_data(
+        &source.api,
+        &session.key,
+        &driver_numbers,
+        &session.window(),
+        &source.coordinates,
+        &source.mapping,
+        &source.cache_dir,
+    )?;
+    let (driver_info, made_up_drivers) = prepare_session_roster(
+        &source.api,
+        &session.key,
+        &source.cache_dir,
+        roster,
+        &run_race_data,
+    );
+    let race_progress = load_race_progress(source, session, &driver_numbers);
+    Ok(LoadedSession {
+        run_race_data,
+        mapping_stats,
+        race_progress,
+        driver_info,
+        made_up_drivers,
+    })
+}
+
+/// A bundle of `session` as loaded from `source`, mapped onto the source's layout.
+pub fn session_bundle(
+    source: &DataSource,
+    session: &SessionConfig,
+    loaded: LoadedSession,
+) -> Bundle {
+    Bundle {
+        created: Utc::now(),
+        mapping_version: MAPPING_VERSION,
+        session: session.clone(),
+        coordinates: source.coordinates.clone(),
+        driver_info: loaded.driver_info,
+        mapping_options: source.mapping.clone(),
+        run_race_data: loaded.run_race_data,
+        mapping_stats: loaded.mapping_stats,
+        race_progress: loaded.race_progress,
+    }
+}
+
+/// The roster of the season the session ran in, unless the config names one. When it ran comes
+/// from its configured start or else from the API; the default season's without either
+pub fn session_season_roster(
+    api: &ApiConfig,
+    config: &RosterConfig,
+    session: &SessionConfig,
+) -> Vec<DriverInfo> {
+    let season = config.season.or_else(|| {
+        if let Some(start) = session.start_time {
+            return Some(start.year() as u32);
+        }
+        let fetched = tokio::runtime::Runtime::new()
+            .map_err(AppError::from)
+            .and_then(|runtime| runtime.block_on(fetch_session(api, &session.key)));
+        match fetched {
+            Ok(info) => info.map(|info| info.date_start.year() as u32),
+            Err(err) => {
+                warn!(
+                    "Could not find out when session {} ran: {}; using the {} roster",
+                    session.key, err, DEFAULT_SEASON
+                );
+                None
+            }
+        }
+    });
+    season_roster(season.unwrap_or(DEFAULT_SEASON), config)
+}
+
+/// The roster as it was in the session, from the session's driver list in the cache or else
+/// fetched. Without the list the season's roster stays as it is and drivers it lacks are made up
+pub fn prepare_session_roster(
+    api: &ApiConfig,
+    session_key: &str,
+    cache_dir: &Path,
+    roster: Vec<DriverInfo>,
+    run_race_data: &[RunRace],
+) -> (Vec<DriverInfo>, Vec<u32>) {
+    let cache_key = drivers_cache_key(session_key);
+    let listed = load_drivers(cache_dir, cache_key).unwrap_or_else(|| {
+        let fetched = tokio::runtime::Runtime::new()
+            .map_err(AppError::from)
+            .and_then(|runtime| runtime.block_on(fetch_drivers(api, session_key)));
+        match fetched {
+            Ok(listed) => {
+                if !listed.is_empty() {
+                    if let Err(err) = store_drivers(cache_dir, cache_key, &listed) {
+                        notices::report(&AppError::Cache {
+                            reason: format!("could not write the driver list cache: {}", err),
+                        });
+                    }
+                }
+                listed
+            }
+            Err(err) => {
+                warn!(
+                    "Could not get the drivers of session {}: {}; using the roster",
+                    session_key, err
+                );
+                Vec::new()
+            }
+        }
+    });
+    let (roster, made_up) = session_roster(
+        roster,
+        &listed,
+        run_race_data.iter().map(|run| run.driver_number),
+    );
+    for number in &made_up {
+        warn!("Driver {} isn't known; showing a placeholder", number);
+    }
+    (roster, made_up)
+}
+
+/// The session's drivers, or else the whole roster
+pub fn session_drivers(session: &SessionConfig, driver_info: &[DriverInfo]) -> Vec<u32> {
+    session
+        .drivers
+        .clone()
+        .unwrap_or_else(|| driver_numbers(driver_info))
+}
+
+/// Loads or fetches the laps and race positions of the session. Without them only the lap chart
+/// is unavailable, so failing to get them is just a warning
+pub fn load_race_progress(
+    source: &DataSource,
+    session: &SessionConfig,
+    driver_numbers: &[u32],
+) -> Option<RaceProgress> {
+    if !session.fetch_laps {
+        return None;
+    }
+    let session_key = &session.key;
+    match prepare_race_progress(&source.api, session_key, driver_numbers, &source.cache_dir) {
+        Ok(progress) if progress.is_empty() => {
+            warn!("No laps or positions for session {}", session_key);
+            None
+        }
+        Ok(progress) => Some(progress),
+        Err(err) => {
+            notices::report(&err);
+            None
+        }
+    }
+}
+
+/// Loads the laps and race positions from the cache, or fetches them and refreshes the cache
+pub fn prepare_race_progress(
+    api: &ApiConfig,
+    session_key: &str,
+    driver_numbers: &[u32],
+    cache_dir: &Path,
+) -> Result<RaceProgress, AppError> {
+    let cache_key = progress_cache_key(session_key, driver_numbers);
+    if let Some(cached) = load_progress(cache_dir, cache_key) {
+        info!(
+            "Using cached laps and positions for session {}",
+            session_key
+        );
+        return Ok(cached);
+    }
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let progress = runtime.block_on(async {
+        let laps = fetch_laps(api, session_key, driver_numbers).await?;
+        let positions = fetch_positions(api, session_key, driver_numbers).await?;
+        let intervals = fetch_intervals(api, session_key, driver_numbers).await?;
+        let stints = fetch_stints(api, session_key, driver_numbers).await?;
+        let race_control = fetch_race_control(api, session_key).await?;
+        Ok::<_, AppError>(
+            RaceProgress::new(laps, positions)
+                .with_intervals(intervals)
+                .with_stints(stints)
+                .with_race_control(race_control),
+        )
+    })?;
+    if !progress.is_empty() {
+        if let Err(err) = store_progress(cache_dir, cache_key, &progress) {
+            notices::report(&AppError::Cache {
+                reason: format!("could not write the laps and positions cache: {}", err),
+            });
+        }
+    }
+    Ok(progress)
+}
+
+/// Loads a driver's car data within `window` from the cache, or fetches it and refreshes the
+/// cache
+pub fn prepare_car_data(
+    api: &ApiConfig,
+    session_key: &str,
+    driver_number: u32,
+    window: &TimeWindow,
+    cache_dir: &Path,
+) -> Result<Vec<CarData>, AppError> {
+    let cache_key = car_data_cache_key(session_key, driver_number, window);
+    if let Some(cached) = load_car_data(cache_dir, cache_key) {
+        info!(
+            "Using cached car data of driver {} for session {}",
+            driver_number, session_key
+        );
+        return Ok(cached);
+    }
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let samples = runtime.block_on(fetch_car_data(api, session_key, driver_number, window))?;
+    if !samples.is_empty() {
+        if let Err(err) = store_car_data(cache_dir, cache_key, &samples) {
+            notices::report(&AppError::Cache {
+                reason: format!("could not write the car data cache: {}", err),
+            });
+        }
+    }
+    Ok(samples)
+}
+
+/// Loads the mapped data from the cache, or fetches and maps it and refreshes the cache
+pub fn prepare_race_data(
+    api: &ApiConfig,
+    session_key: &str,
+    driver_numbers: &[u32],
+    window: &TimeWindow,
+    coordinates: &[LedPoint],
+    mapping_options: &MappingOptions,
+    cache_dir: &Path,
+) -> Result<(Vec<RunRace>, MappingStats), AppError> {
+    let cache_key = mapping_cache_key(
+        session_key,
+        driver_numbers,
+        window,
+        coordinates,
+        mapping_options,
+    );
+    if let Some(cached) = load_mapping(cache_dir, cache_key) {
+        info!("Using cached mapped data for session {}", session_key);
+        return Ok(cached);
+    }
+
+    // Initialize the runtime for async execution
+    let runtime = tokio::runtime::Runtime::new()?;
+    let (per_driver, mut pipeline) = runtime.block_on(fetch_driver_data_cached(
+        api,
+        session_key,
+        driver_numbers,
+        window,
+        cache_dir,
+    ))?;
+    let mapping_started = Instant::now();
+    let (run_race_data, mut mapping_stats) =
+        map_drivers(per_driver, coordinates, mapping_options, &mut pipeline)?;
+    if mapping_options.fill_gaps {
+        info!(
+            "Interpolated {} samples across short dropouts",
+            pipeline.synthetic_samples
+        );
+    }
+    if let Some(interval_ms) = mapping_options.downsample_ms {
+        info!(
+            "Downsampled to one sample per driver every {} ms, dropping {} samples",
+            interval_ms, pipeline.downsampled_samples
+        );
+    }
+    if let Some(interval_ms) = mapping_options.align_ms {
+        info!(
+            "Resampled every driver onto a {} ms grid: {} samples",
+            interval_ms, pipeline.aligned_samples
+        );
+    }
+    info!(
+        "Mapped {} samples to {} LEDs ({} dropped as off track)",
+        pipeline.mapped_samples,
+        coordinates.len(),
+        mapping_stats.dropped_samples
+    );
+    if mapping_stats.collapsed_samples > 0 {
+        info!(
+            "Collapsed repeated LED positions: {} -> {} records ({:.1}x smaller)",
+            pipeline.mapped_samples,
+            run_race_data.len(),
+            pipeline.mapped_samples as f64 / run_race_data.len().max(1) as f64
+        );
+    }
+    pipeline.mapping_time = mapping_started.elapsed();
+    mapping_stats.pipeline = pipeline;
+
+    if let Err(err) = store_mapping(cache_dir, cache_key, &run_race_data, &mapping_stats) {
+        notices::report(&AppError::Cache {
+            reason: format!("could not write the mapping cache: {}", err),
+        });
+    }
+
+    Ok((run_race_data, mapping_stats))
+}
@@ -0,0 +1,188 @@
+use crate::config::SessionConfig;
+use crate::data::{SessionInfo, TimeWindow};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::BTreeSet;
+
+/// Windows longer than this many hours mean a huge fetch, so they're confirmed first.
+pub const LONG_WINDOW_HOURS: i64 = 6;
+
+/// The session settings as edited in the settings window, before they're checked.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionForm {
+    pub key: String,
+    pub start_time: String, // RFC 3339, empty for the start of the session
+    pub end_time: String,   // RFC 3339, empty for the end of the session
+    pub drivers: BTreeSet<u32>,
+}
+
+/// What's wrong with each field of a session form, shown next to it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionFormErrors {
+    pub key: Option<String>,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub drivers: Option<String>,
+}
+
+impl SessionFormErrors {
+    pub fn is_empty(&self) -> bool {
+        *self == SessionFormErrors::default()
+    }
+}
+
+impl SessionForm {
+    /// The form for `session`; without a driver list every driver of `roster` is selected.
+    pub fn new(session: &SessionConfig, roster: &[u32]) -> SessionForm {
+        let format =
+            |time: Option<DateTime<Utc>>| time.map_or(String::new(), |time| time.to_rfc3339());
+        SessionForm {
+            key: session.key.clone(),
+            start_time: format(session.start_time),
+            end_time: format(session.end_time),
+            drivers: match &session.drivers {
+                Some(drivers) => drivers.iter().copied().collect(),
+                None => roster.iter().copied().collect(),
+            },
+        }
+    }
+
+    /// Sets the window to the whole session.
+    pub fn use_session_bounds(&mut self, info: &SessionInfo) {
+        self.start_time = info.date_start.to_rfc3339();
+        self.end_time = info.date_end.to_rfc3339();
+    }
+
+    /// `session` with the form's values, or what's wrong with them. Selecting the whole
+    /// `roster` means no driver list, and a new key drops the session's name.
+    pub fn apply(
+        &self,
+        session: &SessionConfig,
+        roster: &[u32],
+    ) -> Result<SessionConfig, SessionFormErrors> {
+        let mut errors = SessionFormErrors::default();
+        let key = self.key.trim();
+        if key.is_empty() {
+            errors.key = Some("Enter a session key".to_string());
+        }
+        let start_time = parse_time(&self.start_time).unwrap_or_else(|err| {
+            errors.start_time = Some(err);
+            None
+        });
+        let end_time = parse_time(&self.end_time).unwrap_or_else(|err| {
+            errors.end_time = Some(err);
+            None
+        });
+        if let (Some(start), Some(end)) = (start_time, end_time) {
+            if end <= start {
+                errors.end_time = Some("Must be after the start".to_string());
+            }
+        }
+        if self.drivers.is_empty() {
+            errors.drivers = Some("Select at least one driver".to_string());
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let whole_roster = roster.iter().all(|driver| self.drivers.contains(driver))
+            && self.drivers.len() == roster.len();
+        Ok(SessionConfig {
+            key: key.to_string(),
+            name: session.name.clone().filter(|_| key == session.key),
+            start_time,
+            end_time,
+            drivers: (!whole_roster).then(|| self.drivers.iter().copied().collect()),
+            ..session.clone()
+        })
+    }
+}
+
+// An empty field is no limit
+fn parse_time(text: &str) -> Result<Option<DateTime<Utc>>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    DateTime::parse_from_rfc3339(text)
+        .map(|time| Some(time.with_timezone(&Utc)))
+        .map_err(|_| "Expected a time like 2023-08-27T13:00:00Z".to_string())
+}
+
+/// What's wrong with a session's time window.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WindowProblem {
+    /// The start isn't before the end.
+    Reversed,
+    /// The window misses the session entirely.
+    OutsideSession {
+        key: String,
+        window: TimeWindow,
+        session: SessionInfo,
+    },
+    /// Longer than `LONG_WINDOW_HOURS`; fine once confirmed.
+    TooLong { hours: f64 },
+}
+
+impl WindowProblem {
+    pub fn message(&self) -> String {
+        let time = |time: Option<DateTime<Utc>>| {
+            time.map_or("…".to_string(), |time| {
+                time.format("%Y-%m-%d %H:%M").to_string()
+            })
+        };
+        match self {
+            WindowProblem::Reversed => "the start time must be before the end time".to_string(),
+            WindowProblem::OutsideSession {
+                key,
+                window,
+                session,
+            } => format!(
+                "window {}–{} is outside session {} which ran {}–{}",
+                time(window.start),
+                time(window.end),
+                key,
+                time(Some(session.date_start)),
+                session.date_end.format("%H:%M")
+            ),
+            WindowProblem::TooLong { hours } => format!(
+                "the window spans {:.1} hours, which means fetching a lot of data",
+                hours
+            ),
+        }
+    }
+}
+
+/// Checks the time window of `session`, against when it ran if that's known. A window that's
+/// only too long comes last, so it's only reported once the window is otherwise fine.
+pub fn check_window(
+    session: &SessionConfig,
+    info: Option<&SessionInfo>,
+) -> Result<(), WindowProblem> {
+    let window = session.window();
+    if let (Some(start), Some(end)) = (window.start, window.end) {
+        if start >= end {
+            return Err(WindowProblem::Reversed);
+        }
+    }
+    let start = window.start.or(info.map(|info| info.date_start));
+    let end = window.end.or(info.map(|info| info.date_end));
+    if let Some(info) = info {
+        let misses = start.is_some_and(|start| start >= info.date_end)
+            || end.is_some_and(|end| end <= info.date_start);
+        if misses {
+            return Err(WindowProblem::OutsideSession {
+                key: session.key.clone(),
+                window,
+                session: info.clone(),
+            });
+        }
+    }
+    if let (Some(start), Some(end)) = (start, end) {
+        if end - start > Duration::hours(LONG_WINDOW_HOURS) {
+            return Err(WindowProblem::TooLong {
+                hours: (end - start).num_minutes() as f64 / 60.0,
+            });
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,964 @@
+use crate::blue_flags::{about_to_be_lapped, blue_tint, BlueFlagConfig};
+use crate::color_scheme::{ColorSchemeKind, SchemeContext};
+use crate::flags::FlagPanels;
+use crate::formation::GridFormation;
+use crate::heatmap::Heatmap;
+use crate::laps::RaceProgress;
+use crate::led_decay::{LedDecay, LedDecayConfig};
+use crate::led_dwell::{LedDwell, LedDwellConfig};
+use crate::mapping::{blend_weights, LedPositioning, RunRace};
+use crate::metrics;
+use crate::overtakes::{Overtake, OvertakeAnimations};
+use crate::playback::Playback;
+use crate::race_events::{pit_exits, PitStops, RaceEvent};
+use crate::recorder::Recording;
+use crate::retirements::{stale_alpha, Retirements};
+use crate::space::{LedPoint, TelemetryPoint};
+use crate::speed_plan::{SlowEvent, SpeedPlan};
+use crate::team_view::{team_leds, LedView};
+use crate::timeline::DriverTimelines;
+use crate::track_progress::TrackProgress;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use log::trace;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use web_time::Instant;
+
+/// Real time the clock moves by in one step of `Simulation::advance`; the race time moves this
+/// times the speed.
+pub const FIXED_STEP: Duration = Duration::from_millis(50);
+
+// Steps one `advance` catches up on at most; time behind beyond that is dropped, so a long
+// hitch can't leave every later call further behind
+const MAX_CATCH_UP_STEPS: u32 = 10;
+
+/// An LED color as plain red, green and blue channels.
+pub type Rgb = [u8; 3];
+
+/// The color of every LED at one instant, indexed like the LED coordinates; `None` is off.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LedFrame {
+    pub leds: Vec<Option<Rgb>>,
+}
+
+impl LedFrame {
+    /// The lit LEDs with their index.
+    pub fn lit(&self) -> impl Iterator<Item = (usize, Rgb)> + '_ {
+        self.leds
+            .iter()
+            .enumerate()
+            .filter_map(|(index, color)| color.map(|color| (index, color)))
+    }
+}
+
+/// Coarse playback state, for status displays and outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaybackState {
+    Stopped,
+    Playing,
+    Paused,
+    Finished,
+}
+
+/// The race replay without any display: the clock, the played records and the resulting frame.
+/// It plays either mapped race data or a recording of the frames of an earlier run.
+#[derive(Clone)]
+pub struct Simulation {
+    run_race_data: Vec<RunRace>,
+    timelines: DriverTimelines,
+    driver_colors: HashMap<u32, Rgb>, // Drivers without a color are shown white
+    color_scheme: ColorSchemeKind,
+    led_view: LedView,
+    driver_teams: HashMap<u32, String>, // Drivers without a team make up one of their own
+    race_progress: Option<Arc<RaceProgress>>, // What the color schemes go by besides the roster
+    hidden_drivers: HashSet<u32>,
+    playback: Playback,
+    reversed: bool,                         // The clock runs backwards down to 0
+    last_positions: HashMap<u32, Position>, // Last known position of each driver
+    applied_index: usize,                   // Records before this index are in `last_positions`
+    frame: LedFrame,
+    replay: Option<Replay>, // Played instead of `run_race_data` when set
+    overtakes: OvertakeAnimations,
+    pit_stops: PitStops,
+    flag_panels: FlagPanels,
+    grid_formation: Option<GridFormation>, // The starting grid shown before lights out
+    events: Vec<RaceEvent>,                // Passed by the clock since they were last taken
+    heatmap: Option<Heatmap>,              // Shown instead of the drivers when set
+    retirements: Retirements,
+    show_retired: bool, // Retired and stale drivers stay lit on their last LED
+    stale_secs: Option<f64>, // Age of a driver's last sample from which their LED fades
+    stale: HashMap<u32, f32>, // Brightness of the stale drivers as of the last frame
+    blue_flags: Option<usize>, // LEDs the leader closes to before a lapped car pulses blue
+    lapped: Vec<u32>,   // Cars about to be lapped as of the last frame
+    led_decay: LedDecay,
+    led_dwell: LedDwell,
+    time_offset: f64, // Seconds the data runs ahead of the clock, to line up with a broadcast
+    blend: Option<Vec<LedPoint>>, // The LED coordinates, when cars are shared between two LEDs
+    speed_plan: SpeedPlan,
+    track_progress: TrackProgress, // Follows the played records
+    unstepped: Duration,           // Real time passed to `advance` not stepped yet
+}
+
+// A recording being played and the LED state after its first `applied` records
+#[derive(Clone)]
+struct Replay {
+    recording: Recording,
+    leds: Vec<Rgb>,
+    applied: usize,
+}
+
+impl Simulation {
+    /// `run_race_data` must be sorted by date and only refer to LEDs below `led_count`.
+    pub fn new(
+        run_race_data: Vec<RunRace>,
+        led_count: usize,
+        driver_colors: HashMap<u32, Rgb>,
+    ) -> Simulation {
+        Simulation {
+            timelines: DriverTimelines::new(&run_race_data),
+            run_race_data,
+            driver_colors,
+            color_scheme: ColorSchemeKind::default(),
+            led_view: LedView::default(),
+            driver_teams: HashMap::new(),
+            race_progress: None,
+            hidden_drivers: HashSet::new(),
+            playback: Playback::default(),
+            reversed: false,
+            last_positions: HashMap::new(),
+            applied_index: 0,
+            frame: LedFrame {
+                leds: vec![None; led_count],
+            },
+            replay: None,
+            overtakes: OvertakeAnimations::default(),
+            pit_stops: PitStops::default(),
+            flag_panels: FlagPanels::default(),
+            grid_formation: None,
+            events: Vec::new(),
+            heatmap: None,
+            retirements: Retirements::default(),
+            show_retired: false,
+            stale_secs: None,
+            stale: HashMap::new(),
+            blue_flags: None,
+            lapped: Vec::new(),
+            led_decay: LedDecay::default(),
+            led_dwell: LedDwell::default(),
+            time_offset: 0.0,
+            blend: None,
+            speed_plan: SpeedPlan::default(),
+            track_progress: TrackProgress::new(led_count),
+            unstepped: Duration::ZERO,
+        }
+    }
+
+    /// Plays a recording instead of race data; black LEDs in it count as off.
+    pub fn from_recording(recording: Recording) -> Simulation {
+        let led_count = recording.header.led_count as usize;
+        let mut simulation = Simulation::new(Vec::new(), led_count, HashMap::new());
+        simulation.replay = Some(Replay {
+            recording,
+            leds: vec![[0, 0, 0]; led_count],
+            applied: 0,
+        });
+        simulation
+    }
+
+    /// Sets the overtakes to animate as the clock passes them, sorted by date; the last
+    /// `history` played ones are kept.
+    pub fn set_overtakes(&mut self, overtakes: Vec<Overtake>, history: usize) {
+        let times: Vec<f64> = overtakes
+            .iter()
+            .filter_map(|overtake| self.seconds_at(overtake.date))
+            .collect();
+        self.speed_plan.set_events(SlowEvent::Overtake, times);
+        self.overtakes = OvertakeAnimations::new(overtakes, history);
+    }
+
+    pub fn overtakes(&self) -> &OvertakeAnimations {
+        &self.overtakes
+    }
+
+    /// Sets the marshal flag panels, whose LEDs show their flag once the race is on.
+    pub fn set_flag_panels(&mut self, flag_panels: FlagPanels) {
+        self.flag_panels = flag_panels;
+        self.render();
+    }
+
+    pub fn flag_panels(&self) -> &FlagPanels {
+        &self.flag_panels
+    }
+
+    /// Shows the cars on their grid slots until lights out, or where the mapping puts them
+    /// all along with `None`.
+    pub fn set_grid_formation(&mut self, formation: Option<GridFormation>) {
+        self.grid_formation = formation;
+        self.render();
+    }
+
+    pub fn grid_formation(&self) -> Option<&GridFormation> {
+        self.grid_formation.as_ref()
+    }
+
+    /// Shows the time spent on each LED instead of the drivers, or the drivers again with
+    /// `None`. The heatmap is brought up to the clock right away.
+    pub fn set_heatmap(&mut self, heatmap: Option<Heatmap>) {
+        self.heatmap = heatmap;
+        self.render();
+    }
+
+    pub fn heatmap(&self) -> Option<&Heatmap> {
+        self.heatmap.as_ref()
+    }
+
+    /// Sets the drivers who retire; their LED goes dark once the clock passes the date they
+    /// do, unless retired drivers are shown.
+    pub fn set_retirements(&mut self, retirements: Retirements) {
+        let times: Vec<f64> = retirements
+            .dates()
+            .filter_map(|date| self.seconds_at(date))
+            .collect();
+        self.speed_plan.set_events(SlowEvent::Retirement, times);
+        self.retirements = retirements;
+        self.render();
+    }
+
+    pub fn retirements(&self) -> &Retirements {
+        &self.retirements
+    }
+
+    /// Keeps retired drivers lit on their last LED.
+    pub fn set_show_retired(&mut self, show_retired: bool) {
+        self.show_retired = show_retired;
+        self.render();
+    }
+
+    /// Fades out the LED of a driver whose last sample is `secs` old by the clock, until a newer
+    /// one is played; 0 or less keeps every driver lit.
+    pub fn set_stale_secs(&mut self, secs: f64) {
+        self.stale_secs = (secs > 0.0).then_some(secs);
+        self.render();
+    }
+
+    /// Whether the driver's LED is fading or faded out for lack of newer samples.
+    pub fn is_stale(&self, driver_number: u32) -> bool {
+        self.stale.contains_key(&driver_number)
+    }
+
+    /// Pulses the cars about to be lapped blue, or stops with a disabled config.
+    pub fn set_blue_flags(&mut self, config: &BlueFlagConfig) {
+        self.blue_flags = config.enabled.then_some(config.leds_behind);
+        self.render();
+    }
+
+    /// Fades the LEDs drivers leave, or switches them off at once with a disabled config.
+    pub fn set_led_decay(&mut self, config: &LedDecayConfig) {
+        self.led_decay = LedDecay::new(config);
+        self.render();
+    }
+
+    /// Lights the LEDs a car passes over between frames, or leaves them dark with a disabled
+    /// config.
+    pub fn set_led_dwell(&mut self, config: &LedDwellConfig) {
+        self.led_dwell = LedDwell::new(config);
+        self.render();
+    }
+
+    /// Lights each car's LED alone, or shares it with the closer neighbour as the mapping's
+    /// `LedPositioning::Blended` has it; `coordinates` are the LEDs the data was mapped onto.
+    pub fn set_positioning(&mut self, positioning: LedPositioning, coordinates: &[LedPoint]) {
+        self.blend = (positioning == LedPositioning::Blended).then(|| coordinates.to_vec());
+        self.render();
+    }
+
+    /// The cars about to be lapped, by number; empty while blue flags are off.
+    pub fn lapped(&self) -> &[u32] {
+        &self.lapped
+    }
+
+    pub fn run_race_data(&self) -> &[RunRace] {
+        &self.run_race_data
+    }
+
+    /// Number of records played so far.
+    pub fn current_index(&self) -> usize {
+        self.playback.current_index
+    }
+
+    /// Number of mapped records, or of recorded frames when playing a recording.
+    pub fn record_count(&self) -> usize {
+        match &self.replay {
+            Some(replay) => replay.recording.len(),
+            None => self.run_race_data.len(),
+        }
+    }
+
+    pub fn frame(&self) -> &LedFrame {
+        &self.frame
+    }
+
+    /// Elapsed race time in seconds, of the data being shown.
+    pub fn race_time(&self) -> f64 {
+        self.playback.race_time
+    }
+
+    /// The clock as shown and sought: the race time less the time offset, 0 until started.
+    pub fn clock_time(&self) -> f64 {
+        if self.playback.race_started {
+            self.playback.race_time - self.time_offset
+        } else {
+            0.0
+        }
+    }
+
+    pub fn time_offset(&self) -> f64 {
+        self.time_offset
+    }
+
+    /// Shows the data `offset` seconds ahead of the clock, or behind it when negative. The clock
+    /// stays where it is and a started replay jumps to the data at the new offset.
+    pub fn set_time_offset(&mut self, offset: f64) -> &LedFrame {
+        let clock = self.clock_time();
+        self.time_offset = offset;
+        if self.playback.race_started {
+            self.seek(Duration::from_secs_f64(clock.max(0.0)));
+        }
+        &self.frame
+    }
+
+    /// Seconds from the first record to the last, or the length of the recording. It grows
+    /// with the race data.
+    pub fn duration(&self) -> f64 {
+        if let Some(replay) = &self.replay {
+            return replay.recording.duration().as_secs_f64();
+        }
+        match (self.run_race_data.first(), self.run_race_data.last()) {
+            (Some(first), Some(last)) => {
+                (last.date - first.date).num_milliseconds() as f64 / 1000.0
+            }
+            _ => 0.0,
+        }
+    }
+
+    // Race seconds of `date`, `None` without race data
+    fn seconds_at(&self, date: DateTime<Utc>) -> Option<f64> {
+        let first = self.run_race_data.first()?;
+        Some((date - first.date).num_milliseconds() as f64 / 1000.0)
+    }
+
+    /// The date the clock has reached, `None` without race data.
+    pub fn race_date(&self) -> Option<DateTime<Utc>> {
+        let first = self.run_race_data.first()?;
+        Some(first.date + ChronoDuration::milliseconds((self.playback.race_time * 1000.0) as i64))
+    }
+
+    pub fn timelines(&self) -> &DriverTimelines {
+        &self.timelines
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.playback.race_started
+    }
+
+    /// Whether every record has been played.
+    pub fn is_finished(&self) -> bool {
+        match &self.replay {
+            Some(replay) => self.playback.race_started && replay.applied >= replay.recording.len(),
+            None => self.playback.is_finished(&self.run_race_data),
+        }
+    }
+
+    pub fn state(&self) -> PlaybackState {
+        if !self.is_running() {
+            PlaybackState::Stopped
+        } else if self.is_finished() {
+            PlaybackState::Finished
+        } else if self.playback.paused {
+            PlaybackState::Paused
+        } else {
+            PlaybackState::Playing
+        }
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.playback.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f64) {
+        self.playback.speed = speed;
+    }
+
+    /// The speed actually played: the set speed, or what the speed plan makes of it.
+    pub fn effective_speed(&self) -> f64 {
+        match self.speed_plan.effective_speed() {
+            Some(speed) if self.speed_plan.is_active() => speed,
+            _ => self.playback.speed,
+        }
+    }
+
+    /// Each driver's progress round the lap and the laps counted as the records played.
+    pub fn track_progress(&self) -> &TrackProgress {
+        &self.track_progress
+    }
+
+    pub fn speed_plan(&self) -> &SpeedPlan {
+        &self.speed_plan
+    }
+
+    pub fn speed_plan_mut(&mut self) -> &mut SpeedPlan {
+        &mut self.speed_plan
+    }
+
+    // The speed to play the next `dt` at, eased along the speed plan
+    fn step_speed(&mut self, dt: Duration) -> f64 {
+        if !self.speed_plan.is_active() {
+            self.speed_plan.reset();
+            return self.playback.speed;
+        }
+        self.speed_plan.advance(
+            self.playback.race_time,
+            self.playback.speed,
+            dt.as_secs_f64(),
+        )
+    }
+
+    /// Starts the clock from 0 with a dark board, at the first record unless there's a time
+    /// offset.
+    pub fn start(&mut self) {
+        self.playback.start();
+        self.reversed = false;
+        self.speed_plan.reset();
+        self.unstepped = Duration::ZERO;
+        self.clear();
+        self.events.push(RaceEvent::Start);
+        if self.time_offset != 0.0 {
+            self.seek(Duration::ZERO);
+        }
+    }
+
+    /// Stops or resumes the clock of a started replay, keeping the board as it is.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.playback.paused = paused && self.playback.race_started;
+    }
+
+    /// Plays a started replay backwards at the set speed, or forwards again. Backwards, the
+    /// speed plan and race events are left out, and reaching the start of the clock pauses
+    /// playback and turns forwards again.
+    pub fn set_reversed(&mut self, reversed: bool) {
+        self.reversed = reversed && self.playback.race_started;
+        self.speed_plan.reset();
+    }
+
+    pub fn is_reversed(&self) -> bool {
+        self.reversed
+    }
+
+    /// Stops playback and rewinds to the beginning with a dark board.
+    pub fn reset(&mut self) {
+        self.playback.reset();
+        self.reversed = false;
+        self.speed_plan.reset();
+        self.clear();
+    }
+
+    /// Advances a running replay by `elapsed` of real time in steps of `FIXED_STEP`, keeping
+    /// the remainder for the next call, and returns the frame of the last step. However the
+    /// elapsed time is split up, the same total plays the same steps and frames, as long as no
+    /// call falls more than `MAX_CATCH_UP_STEPS` steps behind; the time beyond that is dropped
+    /// rather than played later.
+    pub fn advance(&mut self, elapsed: Duration) -> &LedFrame {
+        self.unstepped += elapsed;
+        let mut steps = 0;
+        while self.unstepped >= FIXED_STEP {
+            if steps == MAX_CATCH_UP_STEPS {
+                trace!("Dropping {:?} the clock fell behind", self.unstepped);
+                self.unstepped = Duration::ZERO;
+                break;
+            }
+            self.tick(FIXED_STEP);
+            self.unstepped -= FIXED_STEP;
+            steps += 1;
+        }
+        &self.frame
+    }
+
+    /// Advances a running replay by `dt` of real time in a single step and returns the
+    /// resulting frame.
+    pub fn tick(&mut self, dt: Duration) -> &LedFrame {
+        let started = Instant::now();
+        let playing = self.playback.race_started && !self.playback.paused;
+        self.led_dwell.advance(dt);
+        let speed = if playing && !self.reversed {
+            self.step_speed(dt)
+        } else {
+            self.playback.speed
+        };
+        if playing && self.reversed {
+            self.rewind(dt.as_secs_f64() * speed);
+        } else if self.replay.is_some() {
+            if playing {
+                self.playback.race_time += dt.as_secs_f64() * speed;
+                self.apply_replay();
+            }
+        } else if self.playback.update_at(dt, speed, &self.run_race_data) {
+            self.apply_records(true);
+        } else if self.overtakes.is_active()
+            || self.led_dwell.is_active()
+            || (playing && (self.stale_secs.is_some() || self.led_decay.is_active()))
+        {
+            // Animations and stale drivers fade with the clock, passed LEDs with the wall clock
+            self.render();
+        }
+        metrics::tick(started.elapsed());
+        &self.frame
+    }
+
+    // Moves the clock `secs` backwards as played, stopping at its start
+    fn rewind(&mut self, secs: f64) {
+        let clock_time = self.clock_time() - secs;
+        if clock_time <= 0.0 {
+            self.playback.paused = true;
+            self.reversed = false;
+        }
+        let race_time = clock_time.max(0.0) + self.time_offset;
+        if self.replay.is_some() {
+            self.playback.race_time = race_time;
+            self.apply_replay();
+        } else {
+            self.playback.advance_to(race_time, &self.run_race_data);
+            self.apply_records(true);
+        }
+    }
+
+    /// Moves the data to `date`, starting a replay that isn't running; the clock follows at
+    /// the time offset before it. Dates before the first record go to the beginning.
+    pub fn seek_to_date(&mut self, date: DateTime<Utc>) -> &LedFrame {
+        if !self.playback.race_started {
+            self.start();
+        }
+        let clock_time = self.seconds_at(date).unwrap_or_default() - self.time_offset;
+        self.seek(Duration::from_secs_f64(clock_time.max(0.0)))
+    }
+
+    /// Moves the clock to `clock_time`, forwards or backwards; the data follows at the time
+    /// offset after it.
+    pub fn seek(&mut self, clock_time: Duration) -> &LedFrame {
+        let race_time = clock_time.as_secs_f64() + self.time_offset;
+        self.speed_plan.reset();
+        self.led_decay.reset();
+        self.led_dwell.reset();
+        if self.replay.is_some() {
+            self.playback.race_time = race_time;
+            self.apply_replay();
+        } else {
+            self.playback.advance_to(race_time, &self.run_race_data);
+            self.apply_records(false);
+        }
+        &self.frame
+    }
+
+    /// Moves the clock `secs` forwards, or backwards when negative, but not before the start.
+    /// Before playback starts there's no clock to move.
+    pub fn skip(&mut self, secs: f64) -> &LedFrame {
+        if self.playback.race_started {
+            let clock_time = (self.clock_time() + secs).max(0.0);
+            self.seek(Duration::from_secs_f64(clock_time));
+        }
+        &self.frame
+    }
+
+    /// The LED of each shown driver; none when playing a recording, which has no drivers.
+    pub fn driver_leds(&self) -> impl Iterator<Item = (u32, usize)> + '_ {
+        self.last_positions
+            .iter()
+            .filter(|(driver_number, _)| !self.hidden_drivers.contains(driver_number))
+            .map(|(&driver_number, position)| {
+                (driver_number, self.shown_led(driver_number, position))
+            })
+    }
+
+    /// The LED each driver was last mapped to and the date of that record, hidden drivers
+    /// included.
+    pub fn last_positions(&self) -> impl Iterator<Item = (u32, usize, DateTime<Utc>)> + '_ {
+        self.last_positions
+            .iter()
+            .map(|(&driver_number, position)| (driver_number, position.led_index, position.since))
+    }
+
+    // The LED a driver is shown on: their grid slot before lights out, else where they are
+    fn shown_led(&self, driver_number: u32, position: &Position) -> usize {
+        match (&self.grid_formation, self.race_date()) {
+            (Some(formation), Some(date)) => formation.led(driver_number, position.led_index, date),
+            _ => position.led_index,
+        }
+    }
+
+    // The LEDs a driver lights and the share of each: the LED they're shown on, spread towards
+    // its neighbour when blending, unless that's their grid slot
+    fn shown_shares(&self, driver_number: u32, position: &Position) -> Vec<(usize, f32)> {
+        let led_index = self.shown_led(driver_number, position);
+        match &self.blend {
+            Some(coordinates) if led_index == position.led_index => {
+                blend_weights(coordinates, led_index, position.point)
+            }
+            _ => vec![(led_index, 1.0)],
+        }
+    }
+
+    pub fn is_driver_hidden(&self, driver_number: u32) -> bool {
+        self.hidden_drivers.contains(&driver_number)
+    }
+
+    pub fn hidden_drivers(&self) -> impl Iterator<Item = u32> + '_ {
+        self.hidden_drivers.iter().copied()
+    }
+
+    /// Colors the drivers by `scheme` from the next frame on, which is rebuilt right away. A
+    /// scheme whose data the race progress lacks colors them by team instead.
+    pub fn set_color_scheme(&mut self, scheme: ColorSchemeKind) {
+        self.color_scheme = scheme;
+        self.render();
+    }
+
+    pub fn color_scheme(&self) -> ColorSchemeKind {
+        self.color_scheme
+    }
+
+    /// Shows every driver or every team from the next frame on, which is rebuilt right away.
+    pub fn set_led_view(&mut self, view: LedView) {
+        self.led_view = view;
+        self.render();
+    }
+
+    pub fn led_view(&self) -> LedView {
+        self.led_view
+    }
+
+    /// Sets the team of each driver, which the team view groups the cars by.
+    pub fn set_driver_teams(&mut self, teams: HashMap<u32, String>) {
+        self.driver_teams = teams;
+        self.render();
+    }
+
+    /// Sets the laps, positions and stints the color schemes go by; the pit stops in the laps
+    /// become race events.
+    pub fn set_race_progress(&mut self, progress: Option<Arc<RaceProgress>>) {
+        self.pit_stops = PitStops::new(progress.as_deref().map(pit_exits).unwrap_or_default());
+        self.race_progress = progress;
+        self.render();
+    }
+
+    /// The race events the clock passed since the last call, in order: the start, and the
+    /// overtakes and pit stops as their animations start. Taken every tick by whatever follows
+    /// them, so they stay in step with the LEDs.
+    pub fn take_race_events(&mut self) -> Vec<RaceEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// What the color schemes have to go by at the race time.
+    pub fn scheme_context(&self) -> SchemeContext<'_> {
+        SchemeContext {
+            team_colors: &self.driver_colors,
+            progress: self.race_progress.as_deref(),
+            time: self.race_date(),
+        }
+    }
+
+    /// The color the driver is shown in at the race time.
+    pub fn driver_color(&self, driver_number: u32) -> Rgb {
+        let context = self.scheme_context();
+        let scheme = match self.color_scheme.missing(&context) {
+            Some(_) => ColorSchemeKind::Team,
+            None => self.color_scheme,
+        };
+        let color = scheme.scheme().color_for(driver_number, &context);
+        [color.r(), color.g(), color.b()]
+    }
+
+    /// Hides or shows a driver's LED from the next frame on, which is rebuilt right away.
+    pub fn set_driver_hidden(&mut self, driver_number: u32, hidden: bool) {
+        if hidden {
+            self.hidden_drivers.insert(driver_number);
+        } else {
+            self.hidden_drivers.remove(&driver_number);
+        }
+        self.render();
+    }
+
+    fn clear(&mut self) {
+        self.overtakes.reset();
+        self.pit_stops.reset();
+        self.events.clear();
+        if let Some(heatmap) = &mut self.heatmap {
+            heatmap.reset();
+        }
+        self.last_positions.clear();
+        self.track_progress.reset();
+        self.led_decay.reset();
+        self.led_dwell.reset();
+        self.applied_index = 0;
+        self.frame.leds.fill(None);
+        if let Some(replay) = &mut self.replay {
+            replay.leds.fill([0, 0, 0]);
+            replay.applied = 0;
+        }
+    }
+
+    // Brings the recorded LED state up to the clock and renders the frame
+    fn apply_replay(&mut self) {
+        if let Some(replay) = &mut self.replay {
+            let race_time = Duration::from_secs_f64(self.playback.race_time.max(0.0));
+            let target = replay.recording.records_until(race_time);
+            replay
+                .recording
+                .advance(&mut replay.leds, replay.applied, target);
+            replay.applied = target;
+        }
+        self.render();
+    }
+
+    // Brings `last_positions` up to the playback index and renders the frame. Only records
+    // `played` through, not jumped to, light the LEDs passed in between
+    fn apply_records(&mut self, played: bool) {
+        let current_index = self.playback.current_index;
+
+        // Played backwards, only the drivers of the records gone back over move, each to their
+        // latest sample still played; laps crossed backwards are taken back on the way
+        if current_index < self.applied_index && played {
+            let time = current_index
+                .checked_sub(1)
+                .map(|last_played| self.run_race_data[last_played].date);
+            let moved: HashSet<u32> = self.run_race_data[current_index..self.applied_index]
+                .iter()
+                .map(|run_data| run_data.driver_number)
+                .collect();
+            for driver_number in moved {
+                match time.and_then(|time| self.timelines.position_at(driver_number, time)) {
+                    Some(run_data) => {
+                        self.last_positions
+                            .insert(driver_number, Position::from(run_data));
+                        self.track_progress
+                            .advance(driver_number, run_data.led_index);
+                    }
+                    None => {
+                        self.last_positions.remove(&driver_number);
+                    }
+                }
+            }
+            if current_index == 0 {
+                self.track_progress.reset();
+            }
+            self.led_dwell.reset();
+            self.applied_index = current_index;
+        }
+
+        // Only the records since the last update are new; after a backward jump the positions
+        // come straight from the per-driver timelines instead of replaying the prefix
+        if current_index < self.applied_index {
+            self.last_positions = match current_index.checked_sub(1) {
+                Some(last_played) => {
+                    let time = self.run_race_data[last_played].date;
+                    self.timelines
+                        .drivers()
+                        .filter_map(|driver_number| {
+                            self.timelines
+                                .position_at(driver_number, time)
+                                .map(|run_data| (driver_number, Position::from(run_data)))
+                        })
+                        .collect()
+                }
+                None => HashMap::new(),
+            };
+            // Laps can only be counted along the way
+            self.track_progress.reset();
+            self.led_dwell.reset();
+            for run_data in &self.run_race_data[..current_index] {
+                self.track_progress
+                    .advance(run_data.driver_number, run_data.led_index);
+            }
+            self.applied_index = current_index;
+        }
+
+        for run_data in &self.run_race_data[self.applied_index..current_index] {
+            trace!(
+                "Driver {} moved to LED index {}",
+                run_data.driver_number,
+                run_data.led_index
+            );
+            let previous = self
+                .last_positions
+                .insert(run_data.driver_number, Position::from(run_data));
+            if let (Some(previous), true) = (previous, played) {
+                self.led_dwell.moved(
+                    run_data.driver_number,
+                    previous.led_index,
+                    run_data.led_index,
+                    self.frame.leds.len(),
+                );
+            }
+            self.track_progress
+                .advance(run_data.driver_number, run_data.led_index);
+        }
+        self.applied_index = current_index;
+
+        self.render();
+    }
+
+    // Drivers sharing an LED are laid over each other in the order of their records, each
+    // covering their share of it, so one wholly on the LED shows the color of the latest; ties
+    // go to the higher driver number, so frames don't depend on hash order. A recording shows
+    // as recorded
+    fn render(&mut self) {
+        if let Some(replay) = &self.replay {
+            for (led, &color) in self.frame.leds.iter_mut().zip(&replay.leds) {
+                *led = (color != [0, 0, 0]).then_some(color);
+            }
+            return;
+        }
+        if let Some(heatmap) = &mut self.heatmap {
+            heatmap.advance(&self.run_race_data, self.applied_index);
+            for (led, color) in self.frame.leds.iter_mut().zip(heatmap.colors()) {
+                *led = color;
+            }
+            return;
+        }
+
+        let date = self.race_date();
+        let mut positions: Vec<(&u32, &Position)> = self
+            .last_positions
+            .iter()
+            .filter(|(driver_number, _)| !self.hidden_drivers.contains(driver_number))
+            .filter(|&(&driver_number, _)| {
+                self.show_retired
+                    || !date.is_some_and(|date| self.retirements.is_retired(driver_number, date))
+            })
+            .collect();
+        positions.sort_by_key(|&(&driver_number, position)| (position.since, driver_number));
+
+        let colors: HashMap<u32, Rgb> = self
+            .driver_colors
+            .keys()
+            .chain(self.last_positions.keys())
+            .map(|&driver_number| (driver_number, self.driver_color(driver_number)))
+            .collect();
+        self.lapped = match (self.blue_flags, date) {
+            (Some(leds_behind), Some(_)) if self.playback.race_started => about_to_be_lapped(
+                &self.track_progress,
+                self.race_progress.as_deref(),
+                date,
+                leds_behind,
+            ),
+            _ => Vec::new(),
+        };
+        self.stale.clear();
+        if let (Some(stale_secs), Some(date), false) = (
+            self.stale_secs.filter(|_| self.playback.race_started),
+            date,
+            self.show_retired,
+        ) {
+            for (&driver_number, position) in &positions {
+                let age = (date - position.since).num_milliseconds() as f64 / 1000.0;
+                let alpha = stale_alpha(age, stale_secs);
+                if alpha < 1.0 {
+                    self.stale.insert(driver_number, alpha);
+                }
+            }
+        }
+        let positions = positions
+            .into_iter()
+            .filter(|(driver_number, _)| self.stale.get(driver_number) != Some(&0.0));
+        let leds: Vec<(usize, Rgb, f32)> = match self.led_view {
+            LedView::Driver => positions
+                .flat_map(|(driver_number, position)| {
+                    let mut color = colors[driver_number];
+                    if let Some(date) = date.filter(|_| self.lapped.contains(driver_number)) {
+                        color = blue_tint(color, date);
+                    }
+                    if let Some(&alpha) = self.stale.get(driver_number) {
+                        color = color.map(|channel| (channel as f32 * alpha).round() as u8);
+                    }
+                    self.shown_shares(*driver_number, position)
+                        .into_iter()
+                        .map(move |(led_index, share)| (led_index, color, share))
+                })
+                .collect(),
+            // Purely by team, whatever the color scheme; a car counts on its own LED only
+            LedView::Team => {
+                let cars: Vec<(u32, usize)> = positions
+                    .map(|(&driver_number, position)| {
+                        (driver_number, self.shown_led(driver_number, position))
+                    })
+                    .collect();
+                let led_count = self.frame.leds.len();
+                team_leds(&cars, &self.driver_teams, &self.driver_colors, led_count)
+                    .into_iter()
+                    .enumerate()
+                    .filter_map(|(led_index, color)| color.map(|color| (led_index, color, 1.0)))
+                    .collect()
+            }
+        };
+        self.frame.leds.fill(None);
+        for (led_index, color, share) in leds {
+            let led = &mut self.frame.leds[led_index];
+            let below = led.unwrap_or([0, 0, 0]);
+            let blended = std::array::from_fn(|channel| {
+                (below[channel] as f32 * (1.0 - share) + color[channel] as f32 * share).round()
+                    as u8
+            });
+            *led = (blended != [0, 0, 0]).then_some(blended);
+        }
+        // Cars on an LED go over the ones passed over
+        for (driver_number, led_index) in self.led_dwell.leds() {
+            if let (Some(led @ None), false) = (
+                self.frame.leds.get_mut(led_index),
+                self.hidden_drivers.contains(&driver_number),
+            ) {
+                *led = colors.get(&driver_number).copied();
+            }
+        }
+        if self.playback.race_started {
+            self.led_decay
+                .apply(&mut self.frame.leds, self.playback.race_time);
+        }
+
+        match self.race_date().filter(|_| self.playback.race_started) {
+            Some(date) => {
+                let overtakes = self.overtakes.advance(date);
+                self.events
+                    .extend(overtakes.into_iter().map(RaceEvent::Overtake));
+                self.events.extend(self.pit_stops.advance(date));
+            }
+            None => {
+                self.overtakes.reset();
+                self.pit_stops.reset();
+            }
+        }
+        self.overtakes
+            .overlay(&mut self.frame.leds, &colors, &self.hidden_drivers);
+        if let Some(date) = self.race_date().filter(|_| self.playback.race_started) {
+            self.flag_panels.overlay(&mut self.frame.leds, date);
+        }
+    }
+}
+
+// A driver's LED, where exactly they were and the date of the record that put them there
+#[derive(Debug, Clone, Copy)]
+struct Position {
+    led_index: usize,
+    point: TelemetryPoint,
+    since: DateTime<Utc>,
+}
+
+impl From<&RunRace> for Position {
+    fn from(run_data: &RunRace) -> Self {
+        Position {
+            led_index: run_data.led_index,
+            point: run_data.point,
+            since: run_data.date,
+        }
+    }
+}
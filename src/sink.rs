@@ -0,0 +1,701 @@
+use crate::error::AppError;
+use crate::led_coords::LayoutTransform;
+use crate::mapping::nearest_led;
+use crate::metrics;
+use crate::night::NightDimmer;
+use crate::pixel_map::gamma_table;
+use crate::simulation::{PlaybackState, Rgb};
+use crate::space::LedPoint;
+use crate::status::{self, Health};
+use crate::track_progress::DriverProgress;
+use chrono::Local;
+use eframe::egui;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use web_time::Instant;
+
+/// Default frame rate limit of hardware outputs.
+pub const DEFAULT_MAX_FPS: f64 = 30.0;
+
+// An unchanged frame is still resent this often, for controllers that blank when nothing arrives
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long shutting the outputs down waits for the sinks to send the blank frame and stop.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
+// How long a panic waits for the sinks to pick up the blank frame before it carries on
+const PANIC_BLANK_TIMEOUT: Duration = Duration::from_millis(300);
+
+// How often a shutdown checks whether the sinks are done
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+// The queues of every dispatcher's sinks that are blanked, for the panic hook
+static PANIC_BLANKED: Mutex<Vec<Weak<FrameSlot>>> = Mutex::new(Vec::new());
+
+/// One frame of the output pipeline, produced once per simulation tick.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputFrame {
+    pub leds: Vec<Rgb>,      // Calibrated colors before brightness; off LEDs are black
+    pub brightness: f32,     // Global brightness, applied by each sink
+    pub timestamp: Duration, // Race time the frame shows
+    pub state: PlaybackState, // Playback state and speed at that time
+    pub speed: f64,
+    pub drivers: Vec<DriverProgress>, // Progress round the lap of each driver, by number
+}
+
+impl OutputFrame {
+    /// The LED colors with the global brightness applied.
+    pub fn dimmed(&self) -> Vec<Rgb> {
+        self.leds
+            .iter()
+            .map(|color| color.map(|channel| (channel as f32 * self.brightness).round() as u8))
+            .collect()
+    }
+}
+
+/// A consumer of LED frames: the window, a hardware output or a file.
+///
+/// Every registered sink runs on its own thread, so `submit` may block on I/O without holding
+/// up the simulation or the other sinks. Rate limiting is left to the dispatcher.
+pub trait LedSink: Send {
+    /// Short name for logs and diagnostics.
+    fn name(&self) -> &str;
+
+    /// Called on the sink's thread before the first frame.
+    fn start(&mut self) -> Result<(), AppError> {
+        Ok(())
+    }
+
+    fn submit(&mut self, frame: &OutputFrame) -> Result<(), AppError>;
+
+    /// Called on the sink's thread after the last frame, also when the sink failed.
+    fn shutdown(&mut self) {}
+
+    /// Whether the sink is sent a frame with every LED off when the outputs shut down, so
+    /// lights don't stay on showing the last one. Off for sinks that keep or show frames.
+    fn blank_on_shutdown(&self) -> bool {
+        true
+    }
+}
+
+/// How the dispatcher paces frames to one sink.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SinkOptions {
+    pub max_fps: Option<f64>, // Frames arriving faster are replaced by the newest one
+    pub skip_unchanged: bool, // Skip frames equal to the last one sent, up to a keep-alive
+}
+
+impl SinkOptions {
+    /// Pacing for a physical output: at most `max_fps`, and only changed frames plus a
+    /// keep-alive every 2 seconds.
+    pub fn hardware(max_fps: f64) -> SinkOptions {
+        SinkOptions {
+            max_fps: Some(max_fps),
+            skip_unchanged: true,
+        }
+    }
+
+    fn min_interval(&self) -> Duration {
+        self.max_fps.map_or(Duration::ZERO, |fps| {
+            Duration::from_secs_f64(1.0 / fps.max(0.1))
+        })
+    }
+}
+
+/// Settings of one LED output from its `[outputs.<name>]` table, applied on the way to it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputConfig {
+    pub gamma: f32,    // 1.0 sends the colors as they are
+    pub rotation: f64, // How the board is mounted, clockwise in degrees
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        OutputConfig {
+            gamma: 1.0,
+            rotation: 0.0,
+            flip_horizontal: false,
+            flip_vertical: false,
+        }
+    }
+}
+
+impl OutputConfig {
+    /// Whether frames pass through unchanged, so the output needn't be wrapped.
+    pub fn is_identity(&self) -> bool {
+        *self == OutputConfig::default()
+    }
+
+    /// The board's orientation relative to the layout.
+    pub fn transform(&self) -> LayoutTransform {
+        LayoutTransform {
+            rotation: self.rotation,
+            flip_horizontal: self.flip_horizontal,
+            flip_vertical: self.flip_vertical,
+        }
+    }
+}
+
+/// Feeds another sink frames corrected for the physical output: each LED takes the color of the
+/// layout LED closest to where the board's mounting puts it, then the global brightness and
+/// the output's gamma through a lookup table are applied. The frames it passes on are at full
+/// brightness.
+pub struct HardwareSink {
+    inner: Box<dyn LedSink>,
+    gamma: [u8; 256],
+    sources: Option<Vec<usize>>, // Layout LED shown by each LED; none when mounted as drawn
+}
+
+impl HardwareSink {
+    /// `coordinates` are the layout's, in the order of the frames' LEDs.
+    pub fn new(
+        inner: Box<dyn LedSink>,
+        config: &OutputConfig,
+        coordinates: &[LedPoint],
+    ) -> HardwareSink {
+        let transform = config.transform();
+        let sources = (transform != LayoutTransform::default()).then(|| {
+            transform
+                .transform_coordinates(coordinates)
+                .into_iter()
+                .map(|point| nearest_led(coordinates, point).0)
+                .collect()
+        });
+        HardwareSink {
+            inner,
+            gamma: gamma_table(config.gamma),
+            sources,
+        }
+    }
+
+    /// The colors the output is sent for a frame.
+    pub fn correct(&self, frame: &OutputFrame) -> Vec<Rgb> {
+        let dimmed = frame.dimmed();
+        let leds = match &self.sources {
+            Some(sources) => sources
+                .iter()
+                .map(|&source| dimmed.get(source).copied().unwrap_or_default())
+                .collect(),
+            None => dimmed,
+        };
+        leds.into_iter()
+            .map(|color| color.map(|channel| self.gamma[channel as usize]))
+            .collect()
+    }
+}
+
+impl LedSink for HardwareSink {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn start(&mut self) -> Result<(), AppError> {
+        self.inner.start()
+    }
+
+    fn submit(&mut self, frame: &OutputFrame) -> Result<(), AppError> {
+        self.inner.submit(&OutputFrame {
+            leds: self.correct(frame),
+            brightness: 1.0,
+            timestamp: frame.timestamp,
+            state: frame.state,
+            speed: frame.speed,
+            drivers: frame.drivers.clone(),
+        })
+    }
+
+    fn shutdown(&mut self) {
+        self.inner.shutdown();
+    }
+
+    fn blank_on_shutdown(&self) -> bool {
+        self.inner.blank_on_shutdown()
+    }
+}
+
+/// Delivery counters of one registered sink.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SinkStats {
+    pub produced: u64, // Frames dispatched to the sink
+    pub sent: u64,     // Frames the sink accepted, keep-alives included
+    pub skipped: u64,  // Frames equal to the last one sent
+    pub dropped: u64,  // Frames replaced by a newer one before the sink got to them
+}
+
+/// The queue of one registered sink, to spot one that stopped taking frames.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SinkQueue {
+    pub name: String,
+    pub depth: usize,      // Frames waiting for the sink, at most one
+    pub waiting: Duration, // Since a frame first waited for the sink to take it; zero when none
+    pub alive: bool,       // Its thread is still running
+}
+
+/// Fans frames out to the registered sinks. Each sink has a one-frame queue: a frame it hasn't
+/// picked up yet is replaced by the next one, so a slow sink skips frames instead of falling
+/// behind. A sink whose `start` or `submit` fails is stopped and its error kept for the caller.
+/// A night dimmer, when set, scales the brightness of every frame before it goes out. Dropping
+/// the dispatcher blanks the sinks and shuts them down, like `blank_and_shutdown`.
+#[derive(Default)]
+pub struct FrameDispatcher {
+    workers: Vec<Worker>,
+    next_id: usize,
+    error: Arc<Mutex<Option<AppError>>>,
+    night: Mutex<Option<NightDimmer>>,
+}
+
+/// Identifies a registered sink, for removing it again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SinkId(usize);
+
+struct Worker {
+    id: SinkId,
+    name: String,
+    slot: Arc<FrameSlot>,
+    thread: Option<JoinHandle<()>>,
+    blank: bool, // Sent an all-black frame on shutdown
+}
+
+#[derive(Default)]
+struct FrameSlot {
+    state: Mutex<SlotState>,
+    ready: Condvar,
+    produced: AtomicU64,
+    sent: AtomicU64,
+    skipped: AtomicU64,
+    dropped: AtomicU64,
+}
+
+enum Next {
+    Frame(Arc<OutputFrame>),
+    Idle, // Nothing arrived within the timeout
+    Closed,
+}
+
+#[derive(Default)]
+struct SlotState {
+    pending: Option<Arc<OutputFrame>>,
+    pending_since: Option<Instant>, // Kept when a newer frame replaces the pending one
+    last: Option<Arc<OutputFrame>>, // The newest frame put, for blanking
+    closed: bool,
+}
+
+impl FrameSlot {
+    fn put(&self, frame: Arc<OutputFrame>) {
+        self.put_locked(self.state.lock().unwrap(), frame);
+    }
+
+    fn put_locked(&self, mut state: MutexGuard<SlotState>, frame: Arc<OutputFrame>) {
+        if state.closed {
+            return;
+        }
+        state.last = Some(Arc::clone(&frame));
+        self.produced.fetch_add(1, Ordering::Relaxed);
+        if state.pending.replace(frame).is_some() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        } else {
+            state.pending_since = Some(Instant::now());
+        }
+        self.ready.notify_one();
+    }
+
+    // Waits for the next frame, at most `timeout` if given; `Closed` once closed and drained
+    fn next(&self, timeout: Option<Duration>) -> Next {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(frame) = state.pending.take() {
+                state.pending_since = None;
+                return Next::Frame(frame);
+            }
+            if state.closed {
+                return Next::Closed;
+            }
+            state = match deadline {
+                None => self.ready.wait(state).unwrap(),
+                Some(deadline) => {
+                    let Some(timeout) = deadline.checked_duration_since(Instant::now()) else {
+                        return Next::Idle;
+                    };
+                    self.ready.wait_timeout(state, timeout).unwrap().0
+                }
+            };
+        }
+    }
+
+    // Puts the newest frame again with every LED off; nothing before the first frame, as the
+    // sink hasn't lit anything yet
+    fn blank(&self, state: MutexGuard<SlotState>) {
+        let Some(last) = &state.last else {
+            return;
+        };
+        let frame = OutputFrame {
+            leds: vec![[0, 0, 0]; last.leds.len()],
+            drivers: Vec::new(),
+            ..OutputFrame::clone(last)
+        };
+        self.put_locked(state, Arc::new(frame));
+    }
+
+    // A frame that arrived since the last `next`, without waiting
+    fn newer(&self) -> Option<Arc<OutputFrame>> {
+        let mut state = self.state.lock().unwrap();
+        state.pending_since = None;
+        state.pending.take()
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.ready.notify_one();
+    }
+}
+
+impl FrameDispatcher {
+    pub fn new() -> FrameDispatcher {
+        FrameDispatcher::default()
+    }
+
+    /// Starts `sink` on its own thread; it receives the frames dispatched from now on, paced
+    /// according to `options`.
+    pub fn register(
+        &mut self,
+        sink: Box<dyn LedSink>,
+        options: SinkOptions,
+    ) -> Result<SinkId, AppError> {
+        let name = sink.name().to_string();
+        let blank = sink.blank_on_shutdown();
+        let slot = Arc::new(FrameSlot::default());
+        let thread = thread::Builder::new()
+            .name(format!("sink-{}", name))
+            .spawn({
+                let slot = Arc::clone(&slot);
+                let error = Arc::clone(&self.error);
+                move || run_sink(sink, options, &slot, &error)
+            })?;
+        let id = SinkId(self.next_id);
+        self.next_id += 1;
+        self.workers.push(Worker {
+            id,
+            name,
+            slot: Arc::clone(&slot),
+            thread: Some(thread),
+            blank,
+        });
+        if blank {
+            let mut blanked = PANIC_BLANKED.lock().unwrap();
+            blanked.retain(|slot| slot.strong_count() > 0);
+            blanked.push(Arc::downgrade(&slot));
+        }
+        Ok(id)
+    }
+
+    /// Delivers the frame still queued for the sink, shuts it down and waits for its thread.
+    pub fn remove(&mut self, id: SinkId) {
+        if let Some(position) = self.workers.iter().position(|worker| worker.id == id) {
+            let mut worker = self.workers.remove(position);
+            worker.slot.close();
+            worker.join();
+            status::remove_sink(&worker.name);
+        }
+    }
+
+    /// The sink registered under `name`, also when it has stopped after an error.
+    pub fn find(&self, name: &str) -> Option<SinkId> {
+        self.workers
+            .iter()
+            .find(|worker| worker.name == name)
+            .map(|worker| worker.id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.workers.is_empty()
+    }
+
+    /// The names of the sinks registered, in the order they were.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.workers.iter().map(|worker| worker.name.as_str())
+    }
+
+    /// Dims every frame from now on by `dimmer`, going by the local time; `None` stops that.
+    pub fn set_night_dimmer(&self, dimmer: Option<NightDimmer>) {
+        *self.night.lock().unwrap() = dimmer;
+    }
+
+    /// Suspends the night dimming, or resumes it. Either way the brightness ramps.
+    pub fn set_night_override(&self, overridden: bool) {
+        if let Some(dimmer) = self.night.lock().unwrap().as_mut() {
+            dimmer.set_overridden(overridden);
+        }
+    }
+
+    /// The factor the night dimmer scaled the last frame's brightness by, if there's a dimmer.
+    pub fn night_level(&self) -> Option<f32> {
+        self.night
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|dimmer| dimmer.last_level().unwrap_or(1.0))
+    }
+
+    /// Hands `frame` to every sink without waiting for any of them.
+    pub fn dispatch(&self, mut frame: OutputFrame) {
+        if let Some(dimmer) = self.night.lock().unwrap().as_mut() {
+            frame.brightness *= dimmer.level(Local::now().time(), Instant::now());
+        }
+        let frame = Arc::new(frame);
+        metrics::frame_rendered();
+        for worker in &self.workers {
+            worker.slot.put(Arc::clone(&frame));
+        }
+    }
+
+    /// The first error of a sink that stopped, if one hasn't been taken yet.
+    pub fn take_error(&self) -> Option<AppError> {
+        self.error.lock().unwrap().take()
+    }
+
+    pub fn stats(&self) -> Vec<(&str, SinkStats)> {
+        self.workers
+            .iter()
+            .map(|worker| {
+                let slot = &worker.slot;
+                let stats = SinkStats {
+                    produced: slot.produced.load(Ordering::Relaxed),
+                    sent: slot.sent.load(Ordering::Relaxed),
+                    skipped: slot.skipped.load(Ordering::Relaxed),
+                    dropped: slot.dropped.load(Ordering::Relaxed),
+                };
+                (worker.name.as_str(), stats)
+            })
+            .collect()
+    }
+
+    /// What waits for each sink, and whether its thread still runs.
+    pub fn queues(&self) -> Vec<SinkQueue> {
+        self.workers
+            .iter()
+            .map(|worker| {
+                let state = worker.slot.state.lock().unwrap();
+                SinkQueue {
+                    name: worker.name.clone(),
+                    depth: usize::from(state.pending.is_some()),
+                    waiting: state
+                        .pending_since
+                        .map_or(Duration::ZERO, |since| since.elapsed()),
+                    alive: worker.is_running(),
+                }
+            })
+            .collect()
+    }
+
+    /// Delivers the frames still queued, shuts every sink down and waits for their threads.
+    pub fn shutdown(&mut self) {
+        for worker in &self.workers {
+            worker.slot.close();
+        }
+        for worker in &mut self.workers {
+            worker.join();
+        }
+    }
+
+    /// Hands every sink that lights LEDs the last frame with all of them off, then shuts the
+    /// sinks down like `shutdown`. Waits at most `timeout` in all; a sink still busy by then is
+    /// left to finish on its own thread.
+    pub fn blank_and_shutdown(&mut self, timeout: Duration) {
+        for worker in &self.workers {
+            if worker.blank {
+                worker.slot.blank(worker.slot.state.lock().unwrap());
+            }
+            worker.slot.close();
+        }
+        let deadline = Instant::now() + timeout;
+        for worker in &mut self.workers {
+            while worker.is_running() && Instant::now() < deadline {
+                thread::sleep(SHUTDOWN_POLL_INTERVAL);
+            }
+            if worker.is_running() {
+                warn!("The {} output didn't stop in time; leaving it", worker.name);
+                worker.thread = None;
+            }
+            worker.join();
+        }
+    }
+}
+
+/// Makes a panic on the calling thread, the main one, blank the sinks of every dispatcher before
+/// it's reported as usual, so the LEDs don't freeze on the last frame when the program dies. The
+/// sinks' threads send the blank frame; the panic waits a moment for them to pick it up. Panics
+/// on other threads end only that thread, so they leave the outputs alone.
+pub fn install_panic_blanking() {
+    let main = thread::current().id();
+    let report = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if thread::current().id() == main {
+            blank_on_panic();
+        }
+        report(info);
+    }));
+}
+
+// Never waits on a lock, as the panicking thread may hold it
+fn blank_on_panic() {
+    let slots: Vec<Arc<FrameSlot>> = match PANIC_BLANKED.try_lock() {
+        Ok(blanked) => blanked.iter().filter_map(Weak::upgrade).collect(),
+        Err(_) => return,
+    };
+    for slot in &slots {
+        if let Ok(state) = slot.state.try_lock() {
+            slot.blank(state);
+        }
+    }
+    let deadline = Instant::now() + PANIC_BLANK_TIMEOUT;
+    let waiting = |slot: &Arc<FrameSlot>| {
+        slot.state
+            .try_lock()
+            .map_or(true, |state| state.pending.is_some())
+    };
+    while slots.iter().any(waiting) && Instant::now() < deadline {
+        thread::sleep(SHUTDOWN_POLL_INTERVAL);
+    }
+}
+
+impl Worker {
+    fn is_running(&self) -> bool {
+        self.thread
+            .as_ref()
+            .is_some_and(|thread| !thread.is_finished())
+    }
+
+    fn join(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                warn!("The {} output panicked", self.name);
+            }
+        }
+    }
+}
+
+impl Drop for FrameDispatcher {
+    fn drop(&mut self) {
+        self.blank_and_shutdown(SHUTDOWN_TIMEOUT);
+    }
+}
+
+fn run_sink(
+    mut sink: Box<dyn LedSink>,
+    options: SinkOptions,
+    slot: &FrameSlot,
+    error: &Mutex<Option<AppError>>,
+) {
+    let result = sink.start().and_then(|()| {
+        status::set_sink(sink.name(), Health::Active, None);
+        pace_frames(sink.as_mut(), options, slot)
+    });
+    sink.shutdown();
+
+    if let Err(err) = result {
+        warn!("{}; stopping the {} output", err, sink.name());
+        status::set_sink(sink.name(), Health::Failed, Some(err.to_string()));
+        slot.close();
+        error.lock().unwrap().get_or_insert(err);
+    }
+}
+
+fn pace_frames(
+    sink: &mut dyn LedSink,
+    options: SinkOptions,
+    slot: &FrameSlot,
+) -> Result<(), AppError> {
+    let min_interval = options.min_interval();
+    let mut last_sent: Option<(Instant, Arc<OutputFrame>)> = None;
+    loop {
+        let keep_alive_in = match &last_sent {
+            Some((sent_at, _)) if options.skip_unchanged => {
+                Some(KEEP_ALIVE_INTERVAL.saturating_sub(sent_at.elapsed()))
+            }
+            _ => None,
+        };
+        let frame = match (slot.next(keep_alive_in), &last_sent) {
+            (Next::Closed, _) => return Ok(()),
+            (Next::Idle, Some((_, sent))) => Arc::clone(sent),
+            (Next::Idle, None) => continue,
+            (Next::Frame(frame), None) => frame,
+            (Next::Frame(frame), Some((sent_at, sent))) => {
+                let since = sent_at.elapsed();
+                if options.skip_unchanged
+                    && since < KEEP_ALIVE_INTERVAL
+                    && same_output(sent, &frame)
+                {
+                    slot.skipped.fetch_add(1, Ordering::Relaxed);
+                    metrics::sink_frame(sink.name(), false);
+                    continue;
+                }
+                // Waiting out the rate limit, then sending whatever is newest by then, means the
+                // last frame before a pause is never lost
+                match min_interval.checked_sub(since) {
+                    Some(wait) if !wait.is_zero() => {
+                        thread::sleep(wait);
+                        match slot.newer() {
+                            Some(newer) => {
+                                slot.dropped.fetch_add(1, Ordering::Relaxed);
+                                newer
+                            }
+                            None => frame,
+                        }
+                    }
+                    _ => frame,
+                }
+            }
+        };
+
+        sink.submit(&frame)?;
+        slot.sent.fetch_add(1, Ordering::Relaxed);
+        metrics::sink_frame(sink.name(), true);
+        last_sent = Some((Instant::now(), frame));
+    }
+}
+
+// Whether two frames look the same to a sink; the race time always differs, so it's ignored
+fn same_output(a: &OutputFrame, b: &OutputFrame) -> bool {
+    a.leds == b.leds && a.brightness == b.brightness && a.state == b.state && a.speed == b.speed
+}
+
+/// Hands frames to the window: keeps the newest one for drawing and asks egui to repaint when
+/// it differs from the one shown.
+pub struct GuiSink {
+    ctx: egui::Context,
+    latest: Arc<Mutex<Option<OutputFrame>>>,
+}
+
+impl GuiSink {
+    /// The window draws whatever frame `latest` holds.
+    pub fn new(ctx: egui::Context, latest: Arc<Mutex<Option<OutputFrame>>>) -> GuiSink {
+        GuiSink { ctx, latest }
+    }
+}
+
+impl LedSink for GuiSink {
+    fn name(&self) -> &str {
+        "gui"
+    }
+
+    fn blank_on_shutdown(&self) -> bool {
+        false
+    }
+
+    fn submit(&mut self, frame: &OutputFrame) -> Result<(), AppError> {
+        let mut latest = self.latest.lock().unwrap();
+        if latest.as_ref() != Some(frame) {
+            *latest = Some(frame.clone());
+            self.ctx.request_repaint();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+use crate::color_scheme::ColorSchemeKind;
+use crate::config::SessionConfig;
+use crate::error::AppError;
+use crate::mapping::{MappingOptions, RunRace};
+use crate::qualifying::QualifyingConfig;
+use crate::recorder::layout_hash;
+use crate::simulation::{PlaybackState, Simulation};
+use crate::space::LedPoint;
+use crate::sync::data_hash;
+use crate::team_view::LedView;
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// What a snapshot was taken of. The data itself stays in the cache; these tell whether the
+/// data a snapshot is loaded against is the same.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatasetIds {
+    pub session: SessionConfig,
+    pub layout_hash: String, // Of the LED coordinates, as in recordings
+    pub cache_key: String,   // Of the mapped data in the cache
+    pub data_hash: String,   // Of the mapped records, as compared when syncing
+}
+
+impl DatasetIds {
+    pub fn new(
+        session: &SessionConfig,
+        coordinates: &[LedPoint],
+        cache_key: u64,
+        run_race_data: &[RunRace],
+    ) -> DatasetIds {
+        DatasetIds {
+            session: session.clone(),
+            layout_hash: format!("{:016x}", layout_hash(coordinates)),
+            cache_key: format!("{:016x}", cache_key),
+            data_hash: format!("{:016x}", data_hash(run_race_data)),
+        }
+    }
+}
+
+/// Where playback stood and how the simulation was set to play.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaybackSnapshot {
+    pub state: PlaybackState,
+    pub clock_time: f64,
+    pub race_time: f64,
+    pub race_date: Option<DateTime<Utc>>,
+    pub speed: f64,
+    pub reversed: bool,
+    pub time_offset: f64,
+    pub color_scheme: ColorSchemeKind,
+    pub led_view: LedView,
+    pub hidden_drivers: Vec<u32>,
+}
+
+/// The settings outside the simulation that change what's shown.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotSettings {
+    pub focused_driver: Option<u32>,
+    pub brightness: f32,
+    pub mapping: MappingOptions,
+    pub qualifying: QualifyingConfig,
+}
+
+/// A driver's last LED and the date of the record that put them there.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DriverSnapshot {
+    pub driver_number: u32,
+    pub led_index: usize,
+    pub date: DateTime<Utc>,
+}
+
+/// A lit LED of the frame shown, with its color as `#RRGGBB`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedSnapshot {
+    pub index: usize,
+    pub color: String,
+}
+
+/// The state of a run, written as JSON to attach to a bug report and loaded again with
+/// `--load-snapshot` to land on the same frame. It names the data it was taken of rather than
+/// holding it, so it stays small.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    pub written_at: String,
+    pub dataset: DatasetIds,
+    pub playback: PlaybackSnapshot,
+    pub settings: SnapshotSettings,
+    pub drivers: Vec<DriverSnapshot>, // By driver number
+    pub leds: Vec<LedSnapshot>,       // Lit LEDs only, by index
+}
+
+impl StateSnapshot {
+    pub fn new(
+        simulation: &Simulation,
+        dataset: DatasetIds,
+        settings: SnapshotSettings,
+    ) -> StateSnapshot {
+        let mut hidden_drivers: Vec<u32> = simulation.hidden_drivers().collect();
+        hidden_drivers.sort_unstable();
+        let mut drivers: Vec<DriverSnapshot> = simulation
+            .last_positions()
+            .map(|(driver_number, led_index, date)| DriverSnapshot {
+                driver_number,
+                led_index,
+                date,
+            })
+            .collect();
+        drivers.sort_by_key(|driver| driver.driver_number);
+        let leds = simulation
+            .frame()
+            .lit()
+            .map(|(index, [r, g, b])| LedSnapshot {
+                index,
+                color: format!("#{:02X}{:02X}{:02X}", r, g, b),
+            })
+            .collect();
+        StateSnapshot {
+            written_at: Local::now().to_rfc3339(),
+            dataset,
+            playback: PlaybackSnapshot {
+                state: simulation.state(),
+                clock_time: simulation.clock_time(),
+                race_time: simulation.race_time(),
+                race_date: simulation.race_date().filter(|_| simulation.is_running()),
+                speed: simulation.speed(),
+                reversed: simulation.is_reversed(),
+                time_offset: simulation.time_offset(),
+                color_scheme: simulation.color_scheme(),
+                led_view: simulation.led_view(),
+                hidden_drivers,
+            },
+            settings,
+            drivers,
+            leds,
+        }
+    }
+
+    /// Saves the snapshot in `dir`, which is created if needed, and returns the path of the file.
+    pub fn write(&self, dir: &Path) -> Result<PathBuf, AppError> {
+        std::fs::create_dir_all(dir)?;
+        let name = format!("snapshot_{}.json", Local::now().format("%Y%m%d-%H%M%S-%3f"));
+        let path = dir.join(name);
+        let writer = BufWriter::new(File::create(&path)?);
+        serde_json::to_writer_pretty(writer, self).map_err(std::io::Error::from)?;
+        Ok(path)
+    }
+
+    pub fn read(path: &Path) -> Result<StateSnapshot, AppError> {
+        let reader = BufReader::new(File::open(path)?);
+        serde_json::from_reader(reader).map_err(|err| AppError::Decode {
+            context: format!("state snapshot {}: {}", path.display(), err),
+        })
+    }
+
+    /// What differs between the data the snapshot was taken of and the data loaded, e.g. after
+    /// the cache was refreshed. Empty when the snapshot can be restored as it was.
+    pub fn mismatches(&self, coordinates: &[LedPoint], run_race_data: &[RunRace]) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        if self.dataset.layout_hash != format!("{:016x}", layout_hash(coordinates)) {
+            mismatches.push("the LED layout".to_string());
+        }
+        if self.dataset.data_hash != format!("{:016x}", data_hash(run_race_data)) {
+            mismatches.push("the mapped data".to_string());
+        }
+        mismatches
+    }
+
+    /// Sets `simulation` up as the snapshot has it and moves it to the snapshot's clock time.
+    /// A snapshot taken while playing is restored paused, on the frame it was taken on.
+    pub fn restore(&self, simulation: &mut Simulation) {
+        let playback = &self.playback;
+        let hidden: Vec<u32> = simulation.hidden_drivers().collect();
+        for driver_number in hidden {
+            simulation.set_driver_hidden(driver_number, false);
+        }
+        for &driver_number in &playback.hidden_drivers {
+            simulation.set_driver_hidden(driver_number, true);
+        }
+        simulation.set_color_scheme(playback.color_scheme);
+        simulation.set_led_view(playback.led_view);
+        simulation.set_speed(playback.speed);
+        simulation.set_time_offset(playback.time_offset);
+        if playback.state == PlaybackState::Stopped {
+            simulation.reset();
+            return;
+        }
+        simulation.start();
+        simulation.seek(Duration::from_secs_f64(playback.clock_time.max(0.0)));
+        simulation.set_paused(true);
+        simulation.set_reversed(playback.reversed);
+    }
+}
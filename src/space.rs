@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+/// A position in the telemetry's own units, as the location samples report it.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct TelemetryPoint {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// A position on the LED board, in the layout's units. Only the mapping turns telemetry
+/// positions into these.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct LedPoint {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl TelemetryPoint {
+    pub fn new(x: f64, y: f64) -> TelemetryPoint {
+        TelemetryPoint { x, y }
+    }
+
+    pub fn distance(self, other: TelemetryPoint) -> f64 {
+        (self.x - other.x).hypot(self.y - other.y)
+    }
+
+    /// The point `share` of the way to `other`.
+    pub fn lerp(self, other: TelemetryPoint, share: f64) -> TelemetryPoint {
+        TelemetryPoint::new(
+            self.x + (other.x - self.x) * share,
+            self.y + (other.y - self.y) * share,
+        )
+    }
+}
+
+impl LedPoint {
+    pub fn new(x: f64, y: f64) -> LedPoint {
+        LedPoint { x, y }
+    }
+
+    pub fn distance(self, other: LedPoint) -> f64 {
+        (self.x - other.x).hypot(self.y - other.y)
+    }
+}
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+/// Race seconds around an event that auto mode plays slowly, half before and half after it.
+pub const AUTO_SLOW_SECS: f64 = 30.0;
+/// Speed of auto mode around events.
+pub const AUTO_SLOW_SPEED: f64 = 1.0;
+
+// Real seconds a tenfold change of speed takes. The speed eases on a log scale, so 10x to 1x
+// feels as even as 1x to 0.1x
+const DECADE_RAMP_SECS: f64 = 1.0;
+
+/// A stretch of race time, in seconds from the first record, played at its own speed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SpeedSegment {
+    pub start: f64,
+    pub end: f64,
+    pub speed: f64,
+}
+
+impl SpeedSegment {
+    pub fn contains(&self, race_time: f64) -> bool {
+        (self.start..self.end).contains(&race_time)
+    }
+}
+
+/// What auto mode slows down for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SlowEvent {
+    Overtake,
+    Retirement,
+}
+
+impl SlowEvent {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SlowEvent::Overtake => "overtakes",
+            SlowEvent::Retirement => "retirements",
+        }
+    }
+}
+
+/// Speeds for parts of the race that differ from the chosen one: segments set by hand and, in
+/// auto mode, `AUTO_SLOW_SPEED` around events. The speed played eases towards the planned one
+/// instead of jumping, and slows down ahead of a slower part so it doesn't overshoot it.
+#[derive(Debug, Clone, Default)]
+pub struct SpeedPlan {
+    segments: Vec<SpeedSegment>,
+    events: Vec<(SlowEvent, f64)>, // Race seconds of each event, by kind
+    auto: bool,
+    suspended: bool, // Nothing applies, e.g. while playing at another instance's speed
+    effective: Option<f64>, // The speed played last, while following the plan
+}
+
+impl SpeedPlan {
+    pub fn segments(&self) -> &[SpeedSegment] {
+        &self.segments
+    }
+
+    /// Segments overlapping each other play at the speed of the first one listed.
+    pub fn set_segments(&mut self, segments: Vec<SpeedSegment>) {
+        self.segments = segments;
+    }
+
+    /// Replaces the race times of the events of `kind`.
+    pub fn set_events(&mut self, kind: SlowEvent, times: impl IntoIterator<Item = f64>) {
+        self.events.retain(|&(other, _)| other != kind);
+        self.events
+            .extend(times.into_iter().map(|time| (kind, time)));
+    }
+
+    /// How many events of `kind` auto mode knows about.
+    pub fn event_count(&self, kind: SlowEvent) -> usize {
+        self.events
+            .iter()
+            .filter(|&&(other, _)| other == kind)
+            .count()
+    }
+
+    pub fn is_auto(&self) -> bool {
+        self.auto
+    }
+
+    pub fn set_auto(&mut self, auto: bool) {
+        self.auto = auto;
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    pub fn set_suspended(&mut self, suspended: bool) {
+        self.suspended = suspended;
+    }
+
+    /// Whether anything in the plan changes the speed.
+    pub fn is_active(&self) -> bool {
+        !self.suspended && (!self.segments.is_empty() || (self.auto && !self.events.is_empty()))
+    }
+
+    /// The speed planned at `race_time` when `speed` was chosen: the first segment covering
+    /// it, otherwise the auto slowdown around an event, if it's slower.
+    pub fn planned_speed(&self, race_time: f64, speed: f64) -> f64 {
+        if let Some(segment) = self
+            .segments
+            .iter()
+            .find(|segment| segment.contains(race_time))
+        {
+            return segment.speed;
+        }
+        let near_event = self
+            .events
+            .iter()
+            .any(|&(_, time)| (race_time - time).abs() <= AUTO_SLOW_SECS / 2.0);
+        if self.auto && near_event {
+            speed.min(AUTO_SLOW_SPEED)
+        } else {
+            speed
+        }
+    }
+
+    /// The speed to play the next `dt` real seconds at, from `race_time` with `speed` chosen.
+    /// It eases from the last one towards the planned speed, looking as far ahead as the
+    /// current speed covers while it eases.
+    pub fn advance(&mut self, race_time: f64, speed: f64, dt: f64) -> f64 {
+        let current = self
+            .effective
+            .unwrap_or_else(|| self.planned_speed(race_time, speed));
+        let ahead = race_time + current * DECADE_RAMP_SECS;
+        let target = self
+            .planned_speed(race_time, speed)
+            .min(self.planned_speed(ahead, speed));
+        let (from, to) = (current.max(0.01).log10(), target.max(0.01).log10());
+        let step = dt / DECADE_RAMP_SECS;
+        let next = 10f64.powf(from + (to - from).clamp(-step, step));
+        self.effective = Some(next);
+        next
+    }
+
+    /// The speed played last, `None` before playing with the plan or after a jump.
+    pub fn effective_speed(&self) -> Option<f64> {
+        self.effective
+    }
+
+    /// Forgets the speed played last, e.g. after a jump to another part of the race, so the
+    /// planned speed there applies right away.
+    pub fn reset(&mut self) {
+        self.effective = None;
+    }
+}
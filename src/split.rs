@@ -0,0 +1,53 @@
+use crate::driver_info::DriverInfo;
+use crate::simulation::{PlaybackState, Simulation};
+use serde::{Deserialize, Serialize};
+
+/// A second session shown next to the played one, e.g. the same race a year earlier, on the
+/// same layout.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SplitConfig {
+    pub enabled: bool, // Load the second session at startup and split the board
+    pub session_key: String,
+    pub ganged: bool, // Start, pause and speed act on both sessions
+}
+
+impl Default for SplitConfig {
+    fn default() -> Self {
+        SplitConfig {
+            enabled: false,
+            session_key: String::new(),
+            ganged: true,
+        }
+    }
+}
+
+/// The second pane of a split board: a session with its own roster and clock. It holds all of
+/// the session's records, like the played one, so a split board takes about twice the memory.
+#[derive(Clone)]
+pub struct SplitPane {
+    pub session_key: String,
+    pub simulation: Simulation,
+    pub driver_info: Vec<DriverInfo>, // The roster as it was in the session
+}
+
+/// Has `follower` start, pause and resume with `leader` and play at its speed. Each keeps a
+/// clock of its own, so sessions of different lengths simply end apart.
+pub fn gang(leader: &Simulation, follower: &mut Simulation) {
+    match leader.state() {
+        PlaybackState::Stopped => {
+            if follower.is_running() {
+                follower.reset();
+            }
+        }
+        state => {
+            if !follower.is_running() {
+                follower.start();
+            }
+            follower.set_paused(state == PlaybackState::Paused);
+        }
+    }
+    if follower.speed() != leader.speed() {
+        follower.set_speed(leader.speed());
+    }
+}
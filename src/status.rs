@@ -0,0 +1,99 @@
+use crate::data::TimeWindow;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use web_time::Instant;
+
+static STATUS: Mutex<Status> = Mutex::new(Status {
+    api: None,
+    last_api_success: None,
+    cache: None,
+    sinks: BTreeMap::new(),
+    fetching: None,
+});
+
+/// How a connection is doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    Active,
+    Retrying,
+    Failed,
+}
+
+/// The latest state of one connection; `detail` is the last error, or what it last did.
+#[derive(Debug, Clone)]
+pub struct Link {
+    pub health: Health,
+    pub detail: Option<String>,
+    pub updated: Instant,
+}
+
+impl Link {
+    fn new(health: Health, detail: Option<String>) -> Link {
+        Link {
+            health,
+            detail,
+            updated: Instant::now(),
+        }
+    }
+}
+
+/// What the background tasks last reported about the API, the cache and the outputs. `None`
+/// where there's been no activity yet.
+#[derive(Debug, Clone)]
+pub struct Status {
+    pub api: Option<Link>,
+    pub last_api_success: Option<Instant>,
+    pub cache: Option<Link>,
+    pub sinks: BTreeMap<String, Link>,          // By sink name
+    pub fetching: Option<(String, TimeWindow)>, // Session key and window of the latest location fetch
+}
+
+/// A request to the API succeeded.
+pub fn api_ok() {
+    let mut status = STATUS.lock().unwrap();
+    status.api = Some(Link::new(Health::Active, None));
+    status.last_api_success = Some(Instant::now());
+}
+
+/// A request to the API failed with `error`; `retrying` when it's tried again.
+pub fn api_failed(error: &str, retrying: bool) {
+    let health = if retrying {
+        Health::Retrying
+    } else {
+        Health::Failed
+    };
+    STATUS.lock().unwrap().api = Some(Link::new(health, Some(error.to_string())));
+}
+
+/// Location samples of `session_key` are being fetched for `window`.
+pub fn fetching(session_key: &str, window: &TimeWindow) {
+    STATUS.lock().unwrap().fetching = Some((session_key.to_string(), *window));
+}
+
+/// The cache was read or written, e.g. "loaded the mapping".
+pub fn cache_used(what: &str) {
+    STATUS.lock().unwrap().cache = Some(Link::new(Health::Active, Some(what.to_string())));
+}
+
+pub fn cache_failed(error: &str) {
+    STATUS.lock().unwrap().cache = Some(Link::new(Health::Failed, Some(error.to_string())));
+}
+
+/// The output `name` is now in `health`, with `detail` for the last error.
+pub fn set_sink(name: &str, health: Health, detail: Option<String>) {
+    STATUS
+        .lock()
+        .unwrap()
+        .sinks
+        .insert(name.to_string(), Link::new(health, detail));
+}
+
+/// The output `name` was removed; it's no longer shown.
+pub fn remove_sink(name: &str) {
+    STATUS.lock().unwrap().sinks.remove(name);
+}
+
+/// A copy of the current status, for display.
+pub fn snapshot() -> Status {
+    STATUS.lock().unwrap().clone()
+}
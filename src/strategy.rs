@@ -0,0 +1,129 @@
+use crate::laps::RaceProgress;
+use crate::race_events::pit_exits;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+
+/// One tyre stint of a driver, from the start of its first lap to the end of its last.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StintBar {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub compound: Option<String>,
+}
+
+/// A driver's row of the strategy chart; drivers without stint data have no stints.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrategyRow {
+    pub driver_number: u32,
+    pub stints: Vec<StintBar>,
+    pub pit_stops: Vec<DateTime<Utc>>, // When they left the pit lane
+}
+
+impl StrategyRow {
+    /// The row as far as it had happened at `time`: the stints started by then, up to it, and
+    /// the pit stops made.
+    pub fn until(&self, time: DateTime<Utc>) -> StrategyRow {
+        StrategyRow {
+            driver_number: self.driver_number,
+            stints: self
+                .stints
+                .iter()
+                .filter(|stint| stint.start <= time)
+                .map(|stint| StintBar {
+                    end: stint.end.min(time),
+                    ..stint.clone()
+                })
+                .collect(),
+            pit_stops: self
+                .pit_stops
+                .iter()
+                .copied()
+                .filter(|&pit_stop| pit_stop <= time)
+                .collect(),
+        }
+    }
+}
+
+/// Every driver's tyre stints and pit stops over the time their laps span.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StrategyChart {
+    rows: Vec<StrategyRow>, // By driver number
+    span: Option<(DateTime<Utc>, DateTime<Utc>)>,
+}
+
+impl StrategyChart {
+    /// A row per driver with laps or positions. A stint starts with its first lap; one whose
+    /// last lap isn't known yet runs to the end of the driver's last known lap.
+    pub fn new(progress: &RaceProgress) -> StrategyChart {
+        let exits = pit_exits(progress);
+        let mut span: Option<(DateTime<Utc>, DateTime<Utc>)> = None;
+        let rows = progress
+            .drivers()
+            .into_iter()
+            .map(|driver_number| {
+                let laps = progress.driver_laps(driver_number);
+                let first = laps.iter().find_map(|lap| lap.date_start);
+                let last = laps
+                    .iter()
+                    .rev()
+                    .find_map(|lap| progress.lap_end(driver_number, lap.lap_number));
+                if let (Some(first), Some(last)) = (first, last) {
+                    span = Some(match span {
+                        Some((start, end)) => (start.min(first), end.max(last)),
+                        None => (first, last),
+                    });
+                }
+                let stints = progress
+                    .driver_stints(driver_number)
+                    .iter()
+                    .filter_map(|stint| {
+                        let start = progress.lap_start(driver_number, stint.lap_start?)?;
+                        let end = stint
+                            .lap_end
+                            .and_then(|lap| progress.lap_end(driver_number, lap))
+                            .or(last)
+                            .unwrap_or(start);
+                        Some(StintBar {
+                            start,
+                            end: end.max(start),
+                            compound: stint.compound.clone(),
+                        })
+                    })
+                    .collect();
+                let pit_stops = exits
+                    .iter()
+                    .filter(|&&(_, driver)| driver == driver_number)
+                    .map(|&(date, _)| date)
+                    .collect();
+                StrategyRow {
+                    driver_number,
+                    stints,
+                    pit_stops,
+                }
+            })
+            .collect();
+        StrategyChart { rows, span }
+    }
+
+    pub fn rows(&self) -> &[StrategyRow] {
+        &self.rows
+    }
+
+    /// From the start of the first lap to the end of the last; `None` without lap times.
+    pub fn span(&self) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        self.span
+    }
+
+    /// Where `date` lies along the span, from 0 at its start to 1 at its end.
+    pub fn share(&self, date: DateTime<Utc>) -> Option<f64> {
+        let (start, end) = self.span?;
+        let length = (end - start).num_milliseconds().max(1) as f64;
+        Some(((date - start).num_milliseconds() as f64 / length).clamp(0.0, 1.0))
+    }
+
+    /// The date `share` of the way along the span.
+    pub fn date_at(&self, share: f64) -> Option<DateTime<Utc>> {
+        let (start, end) = self.span?;
+        let millis = (end - start).num_milliseconds() as f64 * share.clamp(0.0, 1.0);
+        Some(start + ChronoDuration::milliseconds(millis.round() as i64))
+    }
+}
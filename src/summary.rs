@@ -0,0 +1,215 @@
+use crate::driver_info::DriverInfo;
+use crate::error::AppError;
+use crate::flags::{FlagState, FlagTimeline};
+use crate::laps::RaceProgress;
+use crate::race_events::pit_exits;
+use crate::render::format_race_time;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Number of drivers in the running order of a summary.
+pub const SUMMARY_POSITIONS: usize = 10;
+
+/// Settings of the race summary; with `log` set, the summary is appended to that file every
+/// time the leader completes a lap.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SummaryConfig {
+    pub log: Option<PathBuf>,
+}
+
+/// A plain text summary of the race at `race_time`, for pasting into a chat: the session, the
+/// race time, the top ten with their gaps to the leader, the pit stops made and the flags out.
+/// Sections whose data isn't loaded are left out, as are the gaps without intervals. `date` is
+/// the race date at `race_time`, without which only the session and the race time are known.
+pub fn race_summary(
+    session: &str,
+    race_time: f64,
+    date: Option<DateTime<Utc>>,
+    progress: Option<&RaceProgress>,
+    drivers: &[DriverInfo],
+    flags: &FlagTimeline,
+) -> String {
+    let mut sections = vec![format!(
+        "Session {}\nRace time {}",
+        session,
+        format_race_time(race_time.max(0.0))
+    )];
+    if let (Some(date), Some(progress)) = (date, progress) {
+        let order = running_order(progress, date);
+        if !order.is_empty() {
+            sections.push(running_order_section(&order, progress, date, drivers));
+        }
+        if progress.has_laps() {
+            sections.push(pit_stop_section(&order, progress, date, drivers));
+        }
+    }
+    if let Some(date) = date.filter(|_| !flags.is_empty()) {
+        sections.push(flag_section(&flags.active_at(date)));
+    }
+    sections.join("\n\n")
+}
+
+/// Laps the leader had completed at `time`: the most of any driver.
+pub fn leader_laps(progress: &RaceProgress, time: DateTime<Utc>) -> u32 {
+    progress
+        .drivers()
+        .into_iter()
+        .map(|driver_number| progress.laps_completed(driver_number, time))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Appends the summary to a file once per lap of the leader.
+#[derive(Debug, Clone)]
+pub struct SummaryLog {
+    path: PathBuf,
+    laps: Option<u32>, // The leader's at the last check
+}
+
+impl SummaryLog {
+    pub fn new(path: PathBuf) -> SummaryLog {
+        SummaryLog { path, laps: None }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Forgets the laps, as for another session.
+    pub fn reset(&mut self) {
+        self.laps = None;
+    }
+
+    /// Whether the leader completed a lap since the last check, `laps` being theirs now. The
+    /// first check and seeking backwards only note the laps.
+    pub fn lap_completed(&mut self, laps: u32) -> bool {
+        let completed = self.laps.is_some_and(|previous| laps > previous);
+        self.laps = Some(laps);
+        completed
+    }
+
+    /// Appends `summary` to the file, which is created if needed, after a blank line.
+    pub fn append(&self, summary: &str) -> Result<(), AppError> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}\n", summary)?;
+        Ok(())
+    }
+}
+
+// The drivers with a race position at `time`, leader first
+fn running_order(progress: &RaceProgress, time: DateTime<Utc>) -> Vec<(u32, u32)> {
+    let mut order: Vec<(u32, u32)> = progress
+        .drivers()
+        .into_iter()
+        .filter_map(|driver_number| {
+            let position = progress.position_at(driver_number, time)?;
+            Some((position, driver_number))
+        })
+        .collect();
+    order.sort();
+    order
+}
+
+fn running_order_section(
+    order: &[(u32, u32)],
+    progress: &RaceProgress,
+    time: DateTime<Utc>,
+    drivers: &[DriverInfo],
+) -> String {
+    order
+        .iter()
+        .take(SUMMARY_POSITIONS)
+        .enumerate()
+        .map(|(index, &(position, driver_number))| {
+            let gap = if !progress.has_intervals() {
+                String::new()
+            } else if index == 0 {
+                "Leader".to_string()
+            } else {
+                progress
+                    .gap_to_leader(driver_number, time)
+                    .map_or(String::new(), |gap| format!("+{:.3}", gap))
+            };
+            let line = format!(
+                "{:>2}  {:<3}  {:<4}  {}",
+                position,
+                driver_code(drivers, driver_number),
+                format!("#{}", driver_number),
+                gap
+            );
+            line.trim_end().to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// The stops of every driver who made one, in the running order and then by number
+fn pit_stop_section(
+    order: &[(u32, u32)],
+    progress: &RaceProgress,
+    time: DateTime<Utc>,
+    drivers: &[DriverInfo],
+) -> String {
+    let mut stops: Vec<(u32, usize)> = Vec::new();
+    for (_, driver_number) in pit_exits(progress)
+        .into_iter()
+        .take_while(|&(date, _)| date <= time)
+    {
+        match stops
+            .iter_mut()
+            .find(|(number, _)| *number == driver_number)
+        {
+            Some((_, count)) => *count += 1,
+            None => stops.push((driver_number, 1)),
+        }
+    }
+    if stops.is_empty() {
+        return "Pit stops: none".to_string();
+    }
+    stops.sort_by_key(|&(driver_number, _)| {
+        let position = order
+            .iter()
+            .find(|&&(_, number)| number == driver_number)
+            .map_or(u32::MAX, |&(position, _)| position);
+        (position, driver_number)
+    });
+    let stops: Vec<String> = stops
+        .iter()
+        .map(|&(driver_number, count)| format!("{} {}", driver_code(drivers, driver_number), count))
+        .collect();
+    format!("Pit stops: {}", stops.join(", "))
+}
+
+fn flag_section(active: &[(Option<u32>, FlagState)]) -> String {
+    if active.is_empty() {
+        return "Flags: green".to_string();
+    }
+    let flags: Vec<String> = active
+        .iter()
+        .map(|&(sector, state)| {
+            let label = state.label().to_lowercase();
+            match sector {
+                Some(sector) => format!("sector {} {}", sector, label),
+                None => format!("track {}", label),
+            }
+        })
+        .collect();
+    format!("Flags: {}", flags.join(", "))
+}
+
+fn driver_code(drivers: &[DriverInfo], driver_number: u32) -> String {
+    drivers
+        .iter()
+        .find(|driver| driver.number == driver_number)
+        .map_or_else(
+            || format!("#{}", driver_number),
+            |driver| driver.code.clone(),
+        )
+}
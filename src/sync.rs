@@ -0,0 +1,267 @@
+use crate::error::AppError;
+use crate::mapping::RunRace;
+use crate::simulation::{PlaybackState, Simulation};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::time::Duration;
+use web_time::Instant;
+
+/// A follower that hasn't heard from its leader for this long stops following.
+pub const LEADER_TIMEOUT: Duration = Duration::from_secs(3);
+
+// Largest message read; a state message is about 150 bytes
+const MAX_MESSAGE_BYTES: usize = 1024;
+
+// A follower evens out a small drift over this many real seconds, but never runs more than
+// `MAX_SLEW` faster or slower than its leader to do it
+const SLEW_SECS: f64 = 2.0;
+const MAX_SLEW: f64 = 0.1;
+
+// While paused there's nothing to slew, so any drift beyond this jumps
+const PAUSED_DRIFT_SECS: f64 = 0.001;
+
+/// Part an instance plays in keeping several boards on the same race state.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncRole {
+    #[default]
+    Off,
+    /// Sends its race time, speed and playback state to the multicast group.
+    Leader,
+    /// Plays along with the leader it hears on the multicast group; its own transport
+    /// controls are locked.
+    Follower,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncConfig {
+    pub role: SyncRole,
+    pub group: Ipv4Addr, // Multicast group the leader sends to and followers join
+    pub port: u16,
+    pub interface: Ipv4Addr, // Address of the network to use, or 0.0.0.0 for the default
+    pub interval_secs: f64,  // How often the leader sends its state, besides on every change
+    pub seek_secs: f64, // Drift in race seconds beyond which a follower jumps instead of slewing
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        SyncConfig {
+            role: SyncRole::Off,
+            group: Ipv4Addr::new(239, 255, 70, 1),
+            port: 47_100,
+            interface: Ipv4Addr::UNSPECIFIED,
+            interval_secs: 0.2,
+            seek_secs: 2.0,
+        }
+    }
+}
+
+/// The leader's state, sent as one JSON datagram.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncMessage {
+    pub session: String,
+    pub data_hash: u64, // Of the race data played, see `data_hash`
+    pub race_time: f64,
+    pub speed: f64, // The speed actually played, speed plan included
+    pub state: PlaybackState,
+}
+
+impl SyncMessage {
+    /// The state of `simulation` to send.
+    pub fn new(session: &str, data_hash: u64, simulation: &Simulation) -> SyncMessage {
+        SyncMessage {
+            session: session.to_string(),
+            data_hash,
+            race_time: simulation.race_time(),
+            speed: simulation.effective_speed(),
+            state: simulation.state(),
+        }
+    }
+}
+
+/// FNV-1a over the date, driver and LED of every record, so instances can tell whether
+/// they play the same data.
+pub fn data_hash(run_race_data: &[RunRace]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for run_data in run_race_data {
+        for byte in run_data
+            .date
+            .timestamp_micros()
+            .to_le_bytes()
+            .into_iter()
+            .chain(run_data.driver_number.to_le_bytes())
+            .chain((run_data.led_index as u64).to_le_bytes())
+        {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// Sends the playback state to the followers: every `interval_secs`, and right away when the
+/// state or speed changes.
+pub struct SyncLeader {
+    socket: UdpSocket,
+    target: SocketAddr,
+    interval: Duration,
+    last: Option<(SyncMessage, Instant)>, // What was sent last, and when
+}
+
+impl SyncLeader {
+    pub fn open(config: &SyncConfig) -> Result<SyncLeader, AppError> {
+        // Sending from the interface's address picks its network
+        let socket = UdpSocket::bind((config.interface, 0))?;
+        let target = SocketAddr::V4(SocketAddrV4::new(config.group, config.port));
+        info!("Leading playback sync on {}", target);
+        Ok(SyncLeader {
+            socket,
+            target,
+            interval: Duration::from_secs_f64(config.interval_secs.max(0.01)),
+            last: None,
+        })
+    }
+
+    /// Sends `message` if it's due at `now`.
+    pub fn update(&mut self, message: SyncMessage, now: Instant) -> Result<(), AppError> {
+        let due = match &self.last {
+            Some((last, sent)) => {
+                now.duration_since(*sent) >= self.interval
+                    || last.state != message.state
+                    || last.speed != message.speed
+                    || last.data_hash != message.data_hash
+            }
+            None => true,
+        };
+        if !due {
+            return Ok(());
+        }
+        let bytes = serde_json::to_vec(&message).map_err(|err| AppError::Output {
+            reason: format!("could not encode the sync state: {}", err),
+        })?;
+        self.socket
+            .send_to(&bytes, self.target)
+            .map_err(|err| AppError::Output {
+                reason: format!("could not send the sync state to {}: {}", self.target, err),
+            })?;
+        self.last = Some((message, now));
+        Ok(())
+    }
+}
+
+/// Listens for a leader on the multicast group and plays along with it.
+pub struct SyncFollower {
+    socket: UdpSocket,
+    data_hash: u64, // Of the race data played here
+    seek_secs: f64,
+    leader: Option<(SyncMessage, Instant, SocketAddr)>, // The last message and when it came
+    mismatched: Option<SocketAddr>, // The leader last warned about for playing other data
+}
+
+impl SyncFollower {
+    pub fn open(config: &SyncConfig, data_hash: u64) -> Result<SyncFollower, AppError> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, config.port))?;
+        socket.join_multicast_v4(&config.group, &config.interface)?;
+        socket.set_nonblocking(true)?;
+        info!(
+            "Following playback sync on {}:{}",
+            config.group, config.port
+        );
+        Ok(SyncFollower {
+            socket,
+            data_hash,
+            seek_secs: config.seek_secs,
+            leader: None,
+            mismatched: None,
+        })
+    }
+
+    /// The race data played here changed, e.g. after loading another session.
+    pub fn set_data_hash(&mut self, data_hash: u64) {
+        self.data_hash = data_hash;
+        self.mismatched = None;
+    }
+
+    /// Reads what the leader sent since the last call. Returns a warning the first time a
+    /// leader turns out to play other data than this instance; it's followed anyway.
+    pub fn receive(&mut self, now: Instant) -> Option<String> {
+        let mut buffer = [0; MAX_MESSAGE_BYTES];
+        let mut warning = None;
+        while let Ok((length, sender)) = self.socket.recv_from(&mut buffer) {
+            let message: SyncMessage = match serde_json::from_slice(&buffer[..length]) {
+                Ok(message) => message,
+                Err(err) => {
+                    warn!("Ignoring a bad sync message from {}: {}", sender, err);
+                    continue;
+                }
+            };
+            if message.data_hash != self.data_hash && self.mismatched != Some(sender) {
+                let text = format!(
+                    "The sync leader {} plays other race data (session {}); the boards may differ",
+                    sender.ip(),
+                    message.session
+                );
+                warn!("{}", text);
+                warning = Some(text);
+                self.mismatched = Some(sender);
+            }
+            self.leader = Some((message, now, sender));
+        }
+        warning
+    }
+
+    /// The leader being followed, `None` before it's heard or once it's gone quiet.
+    pub fn leader(&self, now: Instant) -> Option<SocketAddr> {
+        self.leader
+            .as_ref()
+            .filter(|(_, received, _)| now.duration_since(*received) < LEADER_TIMEOUT)
+            .map(|&(_, _, sender)| sender)
+    }
+
+    /// Brings `simulation` in line with the leader's last message, if it's recent.
+    pub fn follow(&self, simulation: &mut Simulation, now: Instant) {
+        if let Some((message, received, _)) = &self.leader {
+            let age = now.duration_since(*received);
+            if age < LEADER_TIMEOUT {
+                follow(simulation, message, age, self.seek_secs);
+            }
+        }
+    }
+}
+
+/// Matches `simulation` to a leader's `message` received `age` ago: the same playback state,
+/// and the same race time give or take a small drift. A drift up to `seek_secs` is evened out
+/// by running a little faster or slower; a larger one jumps. The follower's own speed plan is
+/// suspended.
+pub fn follow(simulation: &mut Simulation, message: &SyncMessage, age: Duration, seek_secs: f64) {
+    if message.state == PlaybackState::Stopped {
+        if simulation.is_running() {
+            simulation.reset();
+        }
+        return;
+    }
+    if !simulation.is_running() {
+        simulation.start();
+    }
+    // The leader's speed already has its speed plan in it
+    simulation.speed_plan_mut().set_suspended(true);
+    let playing = message.state == PlaybackState::Playing;
+    simulation.set_paused(message.state == PlaybackState::Paused);
+
+    // Where the leader is by now
+    let target = match playing {
+        true => message.race_time + age.as_secs_f64() * message.speed,
+        false => message.race_time,
+    };
+    let drift = target - simulation.race_time();
+    if drift.abs() > seek_secs || (!playing && drift.abs() > PAUSED_DRIFT_SECS) {
+        let clock = target - simulation.time_offset();
+        simulation.seek(Duration::from_secs_f64(clock.max(0.0)));
+        simulation.set_speed(message.speed);
+    } else {
+        let slew = (drift / (message.speed.max(0.01) * SLEW_SECS)).clamp(-MAX_SLEW, MAX_SLEW);
+        simulation.set_speed(message.speed * (1.0 + slew));
+    }
+}
@@ -0,0 +1,80 @@
+use crate::simulation::Rgb;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Brightness of a team's LED with one of its cars on or next to it; with both it's full.
+pub const ONE_CAR_BRIGHTNESS: f32 = 0.4;
+
+/// What the LEDs show: every driver in the color scheme's color, or every team in its color as
+/// bright as how many of its cars are there.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LedView {
+    #[default]
+    Driver,
+    Team,
+}
+
+/// The team view of `cars`, driver number and LED, on `led_count` LEDs. Every LED with a car
+/// on it shows the car's team color, dimmed to `ONE_CAR_BRIGHTNESS` unless a teammate is on
+/// it or an LED next to it. Teams sharing an LED split it, mixing their colors. A team is
+/// colored like its lowest numbered driver; drivers without a team make up one of their own.
+pub fn team_leds(
+    cars: &[(u32, usize)],
+    teams: &HashMap<u32, String>,
+    team_colors: &HashMap<u32, Rgb>,
+    led_count: usize,
+) -> Vec<Option<Rgb>> {
+    let mut by_team: BTreeMap<String, Vec<(u32, usize)>> = BTreeMap::new();
+    for &(driver_number, led_index) in cars {
+        let team = match teams.get(&driver_number) {
+            Some(team) => team.clone(),
+            None => format!("#{}", driver_number),
+        };
+        by_team
+            .entry(team)
+            .or_default()
+            .push((driver_number, led_index));
+    }
+
+    let mut shares: Vec<Vec<[f32; 3]>> = vec![Vec::new(); led_count];
+    for team_cars in by_team.values() {
+        let lead = team_cars
+            .iter()
+            .map(|&(driver_number, _)| driver_number)
+            .min();
+        let [r, g, b] = lead
+            .and_then(|driver_number| team_colors.get(&driver_number))
+            .copied()
+            .unwrap_or([255, 255, 255]);
+        let mut leds: Vec<usize> = team_cars.iter().map(|&(_, led_index)| led_index).collect();
+        leds.sort_unstable();
+        leds.dedup();
+        for &led_index in leds.iter().filter(|&&led_index| led_index < led_count) {
+            let nearby = team_cars
+                .iter()
+                .filter(|&&(_, other)| led_distance(led_index, other, led_count) <= 1)
+                .count();
+            let brightness = if nearby >= 2 { 1.0 } else { ONE_CAR_BRIGHTNESS };
+            shares[led_index].push([r, g, b].map(|channel| channel as f32 * brightness));
+        }
+    }
+
+    shares
+        .into_iter()
+        .map(|colors| {
+            (!colors.is_empty()).then(|| {
+                let count = colors.len() as f32;
+                [0, 1, 2].map(|channel| {
+                    let sum: f32 = colors.iter().map(|color| color[channel]).sum();
+                    (sum / count).round() as u8
+                })
+            })
+        })
+        .collect()
+}
+
+// LEDs between two, either way round the track
+fn led_distance(a: usize, b: usize, led_count: usize) -> usize {
+    let ahead = (b + led_count - a) % led_count;
+    ahead.min(led_count - ahead)
+}
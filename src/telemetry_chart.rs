@@ -0,0 +1,123 @@
+use crate::data::CarData;
+use crate::timeline::{DriverTimelines, SpeedConfig};
+use chrono::{DateTime, Utc};
+
+/// Most points of a series drawn at once; a longer stretch on screen is thinned to this.
+pub const MAX_PLOT_POINTS: usize = 2000;
+
+/// A driver's telemetry as `[race time in seconds, value]` points, each series by time.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TelemetrySeries {
+    pub estimated_speed: Vec<[f64; 2]>, // From the locations, in the configured unit
+    pub speed: Vec<[f64; 2]>,           // From the car data, in the configured unit
+    pub throttle: Vec<[f64; 2]>,        // Percent
+    pub gear: Vec<[f64; 2]>,
+}
+
+impl TelemetrySeries {
+    /// The estimated speed of `driver_number` at each of their records, timed from `start`.
+    pub fn new(
+        timelines: &DriverTimelines,
+        driver_number: u32,
+        start: DateTime<Utc>,
+        config: &SpeedConfig,
+    ) -> TelemetrySeries {
+        let estimated_speed = timelines
+            .timeline(driver_number)
+            .iter()
+            .filter_map(|record| {
+                let speed = timelines.speed_at(driver_number, record.date, config)?;
+                Some([seconds_since(start, record.date), speed])
+            })
+            .collect();
+        TelemetrySeries {
+            estimated_speed,
+            ..TelemetrySeries::default()
+        }
+    }
+
+    /// Adds the speed, throttle and gear of the driver's car data, sorted by date, timed from
+    /// `start`. Samples without a value are left out of that series.
+    pub fn add_car_data(
+        &mut self,
+        car_data: &[CarData],
+        start: DateTime<Utc>,
+        config: &SpeedConfig,
+    ) {
+        for sample in car_data {
+            let time = seconds_since(start, sample.date);
+            if let Some(speed) = sample.speed {
+                let speed = config.unit.from_meters_per_second(speed / 3.6);
+                self.speed.push([time, speed]);
+            }
+            if let Some(throttle) = sample.throttle {
+                self.throttle.push([time, throttle]);
+            }
+            if let Some(gear) = sample.n_gear {
+                self.gear.push([time, gear as f64]);
+            }
+        }
+    }
+
+    pub fn has_car_data(&self) -> bool {
+        !(self.speed.is_empty() && self.throttle.is_empty() && self.gear.is_empty())
+    }
+
+    /// The highest speed of either series, to scale the chart to.
+    pub fn top_speed(&self) -> Option<f64> {
+        self.estimated_speed
+            .iter()
+            .chain(&self.speed)
+            .map(|point| point[1])
+            .reduce(f64::max)
+    }
+}
+
+/// The points of `series` from `min` to `max` seconds, with one more either side so the line
+/// runs to the edges. Past `max_points` they're thinned by keeping the lowest and the highest
+/// of each of `max_points / 2` equal stretches of time, so peaks survive.
+pub fn visible_points(series: &[[f64; 2]], min: f64, max: f64, max_points: usize) -> Vec<[f64; 2]> {
+    let first = series
+        .partition_point(|point| point[0] < min)
+        .saturating_sub(1);
+    let last = (series.partition_point(|point| point[0] <= max) + 1).min(series.len());
+    let shown = &series[first..last.max(first)];
+    let buckets = max_points / 2;
+    if shown.len() <= max_points || buckets == 0 {
+        return shown.to_vec();
+    }
+
+    let (from, to) = (shown[0][0], shown[shown.len() - 1][0]);
+    let width = (to - from).max(f64::EPSILON) / buckets as f64;
+    let mut thinned = Vec::with_capacity(buckets * 2);
+    let mut rest = shown;
+    for bucket in 1..=buckets {
+        let end = if bucket == buckets {
+            rest.len()
+        } else {
+            let until = from + width * bucket as f64;
+            rest.partition_point(|point| point[0] < until)
+        };
+        let (points, after) = rest.split_at(end);
+        rest = after;
+        let lowest = points.iter().min_by(|a, b| a[1].total_cmp(&b[1]));
+        let highest = points.iter().max_by(|a, b| a[1].total_cmp(&b[1]));
+        let (Some(&lowest), Some(&highest)) = (lowest, highest) else {
+            continue;
+        };
+        // In time order, so the line doesn't double back
+        if lowest[0] <= highest[0] {
+            thinned.extend([lowest, highest]);
+        } else {
+            thinned.extend([highest, lowest]);
+        }
+        if lowest == highest {
+            thinned.pop();
+        }
+    }
+    thinned
+}
+
+fn seconds_since(start: DateTime<Utc>, date: DateTime<Utc>) -> f64 {
+    (date - start).num_milliseconds() as f64 / 1000.0
+}
@@ -0,0 +1,207 @@
+use crate::simulation::Rgb;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// Dark steps between the LEDs of the index blink, so one count doesn't run into the next
+const BLINK_PAUSE_STEPS: usize = 3;
+
+/// A pattern for checking a board's wiring without any race data.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestPattern {
+    #[default]
+    Red,
+    Green,
+    Blue,
+    White,
+    /// One LED at a time, in index order.
+    Chase,
+    /// Every hue spread over the LEDs, moving along.
+    Rainbow,
+    /// LED n blinks n times, then the next one.
+    IndexBlink,
+}
+
+impl TestPattern {
+    pub const ALL: [TestPattern; 7] = [
+        TestPattern::Red,
+        TestPattern::Green,
+        TestPattern::Blue,
+        TestPattern::White,
+        TestPattern::Chase,
+        TestPattern::Rainbow,
+        TestPattern::IndexBlink,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TestPattern::Red => "Red",
+            TestPattern::Green => "Green",
+            TestPattern::Blue => "Blue",
+            TestPattern::White => "White",
+            TestPattern::Chase => "Chase",
+            TestPattern::Rainbow => "Rainbow",
+            TestPattern::IndexBlink => "Index blink",
+        }
+    }
+
+    /// Name on the command line, e.g. "index_blink".
+    pub fn name(&self) -> &'static str {
+        match self {
+            TestPattern::Red => "red",
+            TestPattern::Green => "green",
+            TestPattern::Blue => "blue",
+            TestPattern::White => "white",
+            TestPattern::Chase => "chase",
+            TestPattern::Rainbow => "rainbow",
+            TestPattern::IndexBlink => "index_blink",
+        }
+    }
+}
+
+impl std::str::FromStr for TestPattern {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<TestPattern, String> {
+        TestPattern::ALL
+            .into_iter()
+            .find(|pattern| pattern.name() == value)
+            .ok_or_else(|| {
+                let names: Vec<_> = TestPattern::ALL.iter().map(TestPattern::name).collect();
+                format!(
+                    "unknown test pattern {}, expected one of {}",
+                    value,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// Settings of the test patterns.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TestPatternConfig {
+    pub step_secs: f64, // How long the chase stays on an LED, and the length of a blink
+    pub rainbow_secs: f64, // Time the rainbow takes to go round once
+}
+
+impl Default for TestPatternConfig {
+    fn default() -> Self {
+        TestPatternConfig {
+            step_secs: 0.5,
+            rainbow_secs: 5.0,
+        }
+    }
+}
+
+/// Plays a test pattern on a board of `led_count` LEDs.
+#[derive(Debug, Clone)]
+pub struct PatternPlayer {
+    pattern: TestPattern,
+    led_count: usize,
+    step_secs: f64,
+    rainbow_secs: f64,
+    elapsed: f64, // Seconds since the pattern started
+}
+
+impl PatternPlayer {
+    pub fn new(pattern: TestPattern, led_count: usize, config: &TestPatternConfig) -> Self {
+        PatternPlayer {
+            pattern,
+            led_count,
+            step_secs: config.step_secs.max(0.01),
+            rainbow_secs: config.rainbow_secs.max(0.1),
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn pattern(&self) -> TestPattern {
+        self.pattern
+    }
+
+    /// Switches to `pattern`, which starts from the beginning.
+    pub fn set_pattern(&mut self, pattern: TestPattern) {
+        self.pattern = pattern;
+        self.elapsed = 0.0;
+    }
+
+    pub fn step_secs(&self) -> f64 {
+        self.step_secs
+    }
+
+    pub fn set_step_secs(&mut self, step_secs: f64) {
+        self.step_secs = step_secs.max(0.01);
+    }
+
+    pub fn tick(&mut self, elapsed: Duration) {
+        self.elapsed += elapsed.as_secs_f64();
+    }
+
+    // Whole steps since the start
+    fn steps(&self) -> usize {
+        (self.elapsed / self.step_secs) as usize
+    }
+
+    /// The LED the chase or the index blink is on; `None` for the other patterns.
+    pub fn active_led(&self) -> Option<usize> {
+        if self.led_count == 0 {
+            return None;
+        }
+        match self.pattern {
+            TestPattern::Chase => Some(self.steps() % self.led_count),
+            TestPattern::IndexBlink => Some(self.blink().0),
+            _ => None,
+        }
+    }
+
+    // The LED being counted and whether it's lit. LED n (from 1) blinks n times, a step on
+    // and a step off each, followed by a pause
+    fn blink(&self) -> (usize, bool) {
+        let steps_of = |index: usize| 2 * (index + 1) + BLINK_PAUSE_STEPS;
+        let cycle: usize = (0..self.led_count).map(steps_of).sum();
+        let mut step = self.steps() % cycle;
+        for index in 0..self.led_count {
+            if step < steps_of(index) {
+                return (index, step < 2 * (index + 1) && step & 1 == 0);
+            }
+            step -= steps_of(index);
+        }
+        (0, false)
+    }
+
+    /// The colors of every LED right now.
+    pub fn frame(&self) -> Vec<Rgb> {
+        let solid = |color: Rgb| vec![color; self.led_count];
+        match self.pattern {
+            TestPattern::Red => solid([255, 0, 0]),
+            TestPattern::Green => solid([0, 255, 0]),
+            TestPattern::Blue => solid([0, 0, 255]),
+            TestPattern::White => solid([255, 255, 255]),
+            TestPattern::Chase | TestPattern::IndexBlink => {
+                let lit = match self.pattern {
+                    TestPattern::IndexBlink if self.led_count > 0 => Some(self.blink())
+                        .filter(|&(_, on)| on)
+                        .map(|(index, _)| index),
+                    _ => self.active_led(),
+                };
+                let mut leds = solid([0, 0, 0]);
+                if let Some(index) = lit {
+                    leds[index] = [255, 255, 255];
+                }
+                leds
+            }
+            TestPattern::Rainbow => {
+                let turn = self.elapsed / self.rainbow_secs;
+                (0..self.led_count)
+                    .map(|index| {
+                        let hue = (index as f64 / self.led_count as f64 + turn).fract();
+                        let color =
+                            egui::Color32::from(egui::ecolor::Hsva::new(hue as f32, 1.0, 1.0, 1.0));
+                        [color.r(), color.g(), color.b()]
+                    })
+                    .collect()
+            }
+        }
+    }
+}
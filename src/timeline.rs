@@ -0,0 +1,307 @@
+use crate::mapping::RunRace;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Unit of estimated speeds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpeedUnit {
+    #[default]
+    #[serde(rename = "km/h")]
+    KilometersPerHour,
+    #[serde(rename = "mph")]
+    MilesPerHour,
+    #[serde(rename = "m/s")]
+    MetersPerSecond,
+}
+
+impl SpeedUnit {
+    pub fn label(self) -> &'static str {
+        match self {
+            SpeedUnit::KilometersPerHour => "km/h",
+            SpeedUnit::MilesPerHour => "mph",
+            SpeedUnit::MetersPerSecond => "m/s",
+        }
+    }
+
+    pub fn from_meters_per_second(self, speed: f64) -> f64 {
+        match self {
+            SpeedUnit::KilometersPerHour => speed * 3.6,
+            SpeedUnit::MilesPerHour => speed * 3600.0 / 1609.344,
+            SpeedUnit::MetersPerSecond => speed,
+        }
+    }
+}
+
+/// How speeds are estimated from the raw locations of consecutive samples.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpeedConfig {
+    pub unit: SpeedUnit,
+    pub meters_per_unit: f64, // Length of one raw coordinate unit; OpenF1's are about decimeters
+    pub window: usize,        // Gaps between samples averaged
+    pub max_speed: f64,       // In `unit`; faster gaps are glitches and left out
+    pub stale_secs: f64,      // No estimate once the latest sample is this old
+}
+
+impl Default for SpeedConfig {
+    fn default() -> Self {
+        SpeedConfig {
+            unit: SpeedUnit::KilometersPerHour,
+            meters_per_unit: 0.1,
+            window: 4,
+            max_speed: 400.0,
+            stale_secs: 5.0,
+        }
+    }
+}
+
+/// The mapped records split per driver, each sorted by date.
+#[derive(Debug, Clone, Default)]
+pub struct DriverTimelines {
+    timelines: HashMap<u32, Vec<RunRace>>,
+}
+
+impl DriverTimelines {
+    /// Splits `run_race_data`, which must already be sorted by date.
+    pub fn new(run_race_data: &[RunRace]) -> DriverTimelines {
+        let mut timelines: HashMap<u32, Vec<RunRace>> = HashMap::new();
+        for run_data in run_race_data {
+            timelines
+                .entry(run_data.driver_number)
+                .or_default()
+                .push(run_data.clone());
+        }
+        DriverTimelines { timelines }
+    }
+
+    pub fn drivers(&self) -> impl Iterator<Item = u32> + '_ {
+        self.timelines.keys().copied()
+    }
+
+    /// All records of `driver_number`, or an empty slice for an unknown driver.
+    pub fn timeline(&self, driver_number: u32) -> &[RunRace] {
+        self.timelines
+            .get(&driver_number)
+            .map_or(&[], |timeline| timeline.as_slice())
+    }
+
+    /// The driver's latest record at or before `time`, if they have one yet.
+    pub fn position_at(&self, driver_number: u32, time: DateTime<Utc>) -> Option<&RunRace> {
+        let timeline = self.timeline(driver_number);
+        let end = timeline.partition_point(|run_data| run_data.date <= time);
+        end.checked_sub(1).map(|index| &timeline[index])
+    }
+
+    /// The driver's records with `start <= date < end`.
+    pub fn samples_between(
+        &self,
+        driver_number: u32,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> &[RunRace] {
+        let timeline = self.timeline(driver_number);
+        let first = timeline.partition_point(|run_data| run_data.date < start);
+        let last = timeline.partition_point(|run_data| run_data.date < end);
+        &timeline[first..last.max(first)]
+    }
+
+    /// The driver's estimated speed at `time`: the average over their last `config.window`
+    /// gaps between samples, leaving out glitches faster than `config.max_speed`. `None` before
+    /// their second sample and once their latest one is `config.stale_secs` old.
+    pub fn speed_at(
+        &self,
+        driver_number: u32,
+        time: DateTime<Utc>,
+        config: &SpeedConfig,
+    ) -> Option<f64> {
+        let timeline = self.timeline(driver_number);
+        let end = timeline.partition_point(|run_data| run_data.date <= time);
+        let latest = end.checked_sub(1).map(|index| &timeline[index])?;
+        if seconds_between(latest.date, time) > config.stale_secs {
+            return None;
+        }
+
+        let start = end.saturating_sub(config.window + 1);
+        let speeds: Vec<f64> = timeline[start..end]
+            .windows(2)
+            .filter_map(|pair| {
+                let (meters, seconds) = gap(&pair[0], &pair[1], config)?;
+                Some(config.unit.from_meters_per_second(meters / seconds))
+            })
+            .collect();
+        if speeds.is_empty() {
+            return None;
+        }
+        Some(speeds.iter().sum::<f64>() / speeds.len() as f64)
+    }
+
+    /// The LED index of every driver who has a record at or before `time`.
+    pub fn positions_at(&self, time: DateTime<Utc>) -> HashMap<u32, usize> {
+        self.drivers()
+            .filter_map(|driver_number| {
+                self.position_at(driver_number, time)
+                    .map(|run_data| (driver_number, run_data.led_index))
+            })
+            .collect()
+    }
+}
+
+/// How far each driver had gone by each of their samples, in meters, summed over the raw
+/// locations of consecutive samples. Worked out once, so the distance at any point of the
+/// clock is a lookup, whichever way it got there.
+#[derive(Debug, Clone, Default)]
+pub struct DriverDistances {
+    series: HashMap<u32, Vec<(DateTime<Utc>, f64)>>, // Per sample, by date
+}
+
+impl DriverDistances {
+    /// Gaps faster than `config.max_speed` are glitches, like for the speeds, and add nothing.
+    pub fn new(timelines: &DriverTimelines, config: &SpeedConfig) -> DriverDistances {
+        let series = timelines
+            .drivers()
+            .map(|driver_number| {
+                let timeline = timelines.timeline(driver_number);
+                let mut meters = 0.0;
+                let mut distances = Vec::with_capacity(timeline.len());
+                distances.extend(timeline.first().map(|run_data| (run_data.date, 0.0)));
+                for pair in timeline.windows(2) {
+                    meters += gap(&pair[0], &pair[1], config).map_or(0.0, |(meters, _)| meters);
+                    distances.push((pair[1].date, meters));
+                }
+                (driver_number, distances)
+            })
+            .collect();
+        DriverDistances { series }
+    }
+
+    /// Meters the driver had gone by `time`, over their samples up to it; `None` before the
+    /// first.
+    pub fn at(&self, driver_number: u32, time: DateTime<Utc>) -> Option<f64> {
+        let series = self.series.get(&driver_number)?;
+        let end = series.partition_point(|&(date, _)| date <= time);
+        end.checked_sub(1).map(|index| series[index].1)
+    }
+
+    /// Meters the driver went over all their samples.
+    pub fn total(&self, driver_number: u32) -> Option<f64> {
+        self.series
+            .get(&driver_number)?
+            .last()
+            .map(|&(_, meters)| meters)
+    }
+}
+
+/// Roughly how many laps `meters` make round a layout `layout_length` long in its own units,
+/// which are the telemetry's; `None` without a layout.
+pub fn estimated_laps(meters: f64, layout_length: f64, config: &SpeedConfig) -> Option<f64> {
+    let lap_meters = layout_length * config.meters_per_unit;
+    (lap_meters > 0.0).then(|| meters / lap_meters)
+}
+
+// Meters and seconds between two consecutive samples of a driver; `None` for samples no time
+// apart and for glitches faster than `config.max_speed`
+fn gap(a: &RunRace, b: &RunRace, config: &SpeedConfig) -> Option<(f64, f64)> {
+    let seconds = seconds_between(a.date, b.date);
+    if seconds <= 0.0 {
+        return None;
+    }
+    let meters = a.point.distance(b.point) * config.meters_per_unit;
+    let speed = config.unit.from_meters_per_second(meters / seconds);
+    (speed <= config.max_speed).then_some((meters, seconds))
+}
+
+/// When a driver first reached each LED, counting on across laps: position `k` is LED
+/// `k % led_count` on the `k / led_count`th lap since LED 0. LEDs skipped between two samples
+/// get times interpolated between the samples bracketing them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LedCrossings {
+    start: usize,              // Position of the first sample
+    dates: Vec<DateTime<Utc>>, // Per position from `start`
+}
+
+impl LedCrossings {
+    /// Follows one driver's `timeline`. A move more than half the LEDs forward counts as a
+    /// snap back to an earlier LED, which is no progress.
+    pub fn new(timeline: &[RunRace], led_count: usize) -> LedCrossings {
+        let Some(first) = timeline.first() else {
+            return LedCrossings::default();
+        };
+        let mut crossings = LedCrossings {
+            start: first.led_index,
+            dates: vec![first.date],
+        };
+        let mut position = first.led_index;
+        let mut reached = first.date; // Latest sample on the LED at `position`
+        for run_data in &timeline[1..] {
+            let forward = (run_data.led_index + led_count - position % led_count) % led_count;
+            if forward == 0 {
+                reached = run_data.date;
+                continue;
+            }
+            if forward > led_count / 2 {
+                continue;
+            }
+            let millis = (run_data.date - reached).num_milliseconds() as f64;
+            for step in 1..=forward {
+                let share = step as f64 / forward as f64;
+                crossings
+                    .dates
+                    .push(reached + ChronoDuration::milliseconds((millis * share).round() as i64));
+            }
+            position += forward;
+            reached = run_data.date;
+        }
+        crossings
+    }
+
+    /// Position of the driver's first sample.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// When the driver reached `position`, if they did.
+    pub fn at(&self, position: usize) -> Option<DateTime<Utc>> {
+        self.dates.get(position.checked_sub(self.start)?).copied()
+    }
+
+    /// One past the last reached position.
+    pub fn end(&self) -> usize {
+        self.start + self.dates.len()
+    }
+
+    /// Every reached position with its date, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, DateTime<Utc>)> + '_ {
+        (self.start..).zip(self.dates.iter().copied())
+    }
+}
+
+/// How far `a` is behind `b` in seconds at every LED both reached, dated when the later of them
+/// got there, in date order. Both must have started within half a lap of each other, which
+/// tells which lap each of them started on; a lapped driver's delta keeps growing.
+pub fn time_deltas(
+    a: &LedCrossings,
+    b: &LedCrossings,
+    led_count: usize,
+) -> Vec<(DateTime<Utc>, f64)> {
+    let half = (led_count / 2) as i64;
+    let lap_shift = match b.start() as i64 - a.start() as i64 {
+        offset if offset > half => -(led_count as i64),
+        offset if offset < -half => led_count as i64,
+        _ => 0,
+    };
+    let mut deltas: Vec<(DateTime<Utc>, f64)> = a
+        .iter()
+        .filter_map(|(position, a_date)| {
+            let b_position = usize::try_from(position as i64 - lap_shift).ok()?;
+            let b_date = b.at(b_position)?;
+            Some((a_date.max(b_date), seconds_between(b_date, a_date)))
+        })
+        .collect();
+    deltas.sort_by_key(|&(date, _)| date);
+    deltas
+}
+
+fn seconds_between(start: DateTime<Utc>, end: DateTime<Utc>) -> f64 {
+    (end - start).num_milliseconds() as f64 / 1000.0
+}
@@ -0,0 +1,120 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// A driver's progress round the lap, from the LED they're on.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct DriverProgress {
+    pub driver_number: u32,
+    pub percent: f64, // 0 on the first LED, towards 100 on the last
+    pub laps: u32,    // Times across the line from the last LED to the first
+}
+
+/// Follows the drivers round the layout, LED 0 being the start/finish line and the LEDs in
+/// driving order. Each driver's previous LED tells a car crossing the line, e.g. from U96 to
+/// U1, which wraps to 0% and counts a lap, from one jumping back, which doesn't.
+#[derive(Debug, Clone, Default)]
+pub struct TrackProgress {
+    led_count: usize,
+    drivers: HashMap<u32, (usize, u32)>, // LED and laps of each driver
+    behind_line: HashSet<u32>,           // First seen on the second half of the lap, e.g. the grid
+    version: u64,                        // Bumped whenever a driver moves
+}
+
+impl TrackProgress {
+    pub fn new(led_count: usize) -> TrackProgress {
+        TrackProgress {
+            led_count,
+            drivers: HashMap::new(),
+            behind_line: HashSet::new(),
+            version: 0,
+        }
+    }
+
+    pub fn led_count(&self) -> usize {
+        self.led_count
+    }
+
+    /// Changes whenever a driver moves to another LED, so what's worked out from the positions
+    /// only needs redoing then.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Forgets every driver, e.g. before replaying from the start.
+    pub fn reset(&mut self) {
+        self.drivers.clear();
+        self.behind_line.clear();
+        self.version += 1;
+    }
+
+    /// Moves a driver to `led_index`. Going round more than half the track forwards by way of
+    /// the line counts as crossing it; the same backwards takes the lap back again.
+    pub fn advance(&mut self, driver_number: u32, led_index: usize) {
+        let half = self.led_count / 2;
+        if self.led(driver_number) != Some(led_index) {
+            self.version += 1;
+        }
+        if !self.drivers.contains_key(&driver_number) && led_index >= self.led_count - half {
+            self.behind_line.insert(driver_number);
+        }
+        let (led, laps) = self.drivers.entry(driver_number).or_insert((led_index, 0));
+        if led_index + half < *led {
+            *laps += 1;
+        } else if *led + half < led_index {
+            *laps = laps.saturating_sub(1);
+        }
+        *led = led_index;
+    }
+
+    /// Progress of LED `led_index` round the lap.
+    pub fn percent(&self, led_index: usize) -> f64 {
+        match self.led_count {
+            0 => 0.0,
+            led_count => led_index as f64 / led_count as f64 * 100.0,
+        }
+    }
+
+    /// The LED the driver was last seen on.
+    pub fn led(&self, driver_number: u32) -> Option<usize> {
+        self.drivers.get(&driver_number).map(|&(led, _)| led)
+    }
+
+    /// Every driver seen so far and their LED, in no particular order.
+    pub fn leds(&self) -> impl Iterator<Item = (u32, usize)> + '_ {
+        self.drivers
+            .iter()
+            .map(|(&driver_number, &(led, _))| (driver_number, led))
+    }
+
+    pub fn driver(&self, driver_number: u32) -> Option<DriverProgress> {
+        let &(led, laps) = self.drivers.get(&driver_number)?;
+        Some(DriverProgress {
+            driver_number,
+            percent: self.percent(led),
+            laps,
+        })
+    }
+
+    /// Laps the driver has covered since first seen, counting from the line: a car first seen
+    /// on the second half of the lap, like one on the grid, starts a lap down and gets it back
+    /// crossing the line.
+    pub fn laps_covered(&self, driver_number: u32) -> Option<f64> {
+        let &(led, laps) = self.drivers.get(&driver_number)?;
+        let start = if self.behind_line.contains(&driver_number) {
+            -1.0
+        } else {
+            0.0
+        };
+        Some(start + laps as f64 + self.percent(led) / 100.0)
+    }
+
+    /// Every driver seen so far, by number.
+    pub fn drivers(&self) -> Vec<DriverProgress> {
+        let mut drivers: Vec<u32> = self.drivers.keys().copied().collect();
+        drivers.sort();
+        drivers
+            .into_iter()
+            .filter_map(|driver_number| self.driver(driver_number))
+            .collect()
+    }
+}
@@ -0,0 +1,151 @@
+use crate::laps::RaceProgress;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// When cars running nose to tail count as a DRS train.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrainConfig {
+    pub enabled: bool,
+    pub gap_secs: f64,         // A car joins the car ahead this close
+    pub release_gap_secs: f64, // ... and drops off this far behind, so it doesn't flicker
+    pub min_cars: usize,
+}
+
+impl Default for TrainConfig {
+    fn default() -> Self {
+        TrainConfig {
+            enabled: true,
+            gap_secs: 1.0,
+            release_gap_secs: 1.2,
+            min_cars: 3,
+        }
+    }
+}
+
+/// Consecutive cars each close behind the one ahead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Train {
+    pub drivers: Vec<u32>, // In race order, the car leading it first
+    pub first_position: u32,
+    pub last_position: u32,
+}
+
+impl Train {
+    /// The LEDs from the last car of the train forwards to the first, both included. Empty
+    /// when either isn't on the board, or the cars look more than half a lap apart.
+    pub fn leds(&self, led_of: impl Fn(u32) -> Option<usize>, led_count: usize) -> Vec<usize> {
+        let (Some(&first), Some(&last)) = (self.drivers.first(), self.drivers.last()) else {
+            return Vec::new();
+        };
+        let (Some(front), Some(back)) = (led_of(first), led_of(last)) else {
+            return Vec::new();
+        };
+        if led_count == 0 {
+            return Vec::new();
+        }
+        let span = (front + led_count - back) % led_count;
+        if span > led_count / 2 {
+            return Vec::new();
+        }
+        (0..=span).map(|step| (back + step) % led_count).collect()
+    }
+}
+
+/// Finds the trains from the gaps to the leader. Cars are ordered by their gaps rather than by
+/// the position feed, so a car pitting out of a train drops out of it as soon as its gap grows,
+/// and the cars behind it are compared with the car ahead of it.
+#[derive(Debug, Clone, Default)]
+pub struct TrainDetector {
+    config: TrainConfig,
+    index: Option<usize>, // Intervals played when the trains were last worked out
+    linked: HashSet<(u32, u32)>, // Cars within the gap of the car ahead, (ahead, behind)
+    trains: Vec<Train>,
+}
+
+impl TrainDetector {
+    pub fn new(config: &TrainConfig) -> TrainDetector {
+        TrainDetector {
+            config: config.clone(),
+            ..TrainDetector::default()
+        }
+    }
+
+    /// The trains as of the last update, front of the race first.
+    pub fn trains(&self) -> &[Train] {
+        &self.trains
+    }
+
+    /// Forgets every train, e.g. when another session is loaded.
+    pub fn clear(&mut self) {
+        self.index = None;
+        self.linked.clear();
+        self.trains.clear();
+    }
+
+    /// Finds the trains at `time`, only working them out again once another interval has
+    /// come in.
+    pub fn update(&mut self, progress: &RaceProgress, time: DateTime<Utc>) -> &[Train] {
+        if !self.config.enabled {
+            self.clear();
+            return &self.trains;
+        }
+        let index = progress.intervals_until(time);
+        if self.index == Some(index) {
+            return &self.trains;
+        }
+        self.index = Some(index);
+
+        // Lapped cars have no gap, so they can't be in a train; positions count in gap order
+        let mut cars: Vec<(f64, u32)> = progress
+            .drivers()
+            .into_iter()
+            .filter_map(|driver_number| {
+                // The leader's own gap may be left empty
+                let gap = progress.gap_to_leader(driver_number, time).or_else(|| {
+                    (progress.position_at(driver_number, time) == Some(1)).then_some(0.0)
+                })?;
+                Some((gap, driver_number))
+            })
+            .collect();
+        cars.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut linked = HashSet::new();
+        self.trains.clear();
+        let mut train: Vec<u32> = Vec::new();
+        let mut first_position = 0;
+        for (index, pair) in cars.windows(2).enumerate() {
+            let ((ahead_gap, ahead), (behind_gap, behind)) = (pair[0], pair[1]);
+            let limit = if self.linked.contains(&(ahead, behind)) {
+                self.config.release_gap_secs
+            } else {
+                self.config.gap_secs
+            };
+            if behind_gap - ahead_gap <= limit {
+                linked.insert((ahead, behind));
+                if train.is_empty() {
+                    train.push(ahead);
+                    first_position = index as u32 + 1;
+                }
+                train.push(behind);
+            } else {
+                self.finish_train(&mut train, first_position);
+            }
+        }
+        self.finish_train(&mut train, first_position);
+        self.linked = linked;
+        &self.trains
+    }
+
+    fn finish_train(&mut self, train: &mut Vec<u32>, first_position: u32) {
+        if train.len() >= self.config.min_cars.max(2) {
+            self.trains.push(Train {
+                drivers: train.clone(),
+                first_position,
+                last_position: first_position + train.len() as u32 - 1,
+            });
+        }
+        train.clear();
+    }
+}
@@ -0,0 +1,231 @@
+use crate::control::PlaybackCommand;
+use crate::render::format_duration;
+use crate::simulation::{PlaybackState, Rgb, Simulation};
+use crate::space::LedPoint;
+use crate::viewport::Bounds;
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use crossterm::ExecutableCommand;
+use ratatui::backend::CrosstermBackend;
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::Terminal;
+use std::io::{self, Stdout};
+
+// A terminal cell is about twice as tall as it's wide
+const CELL_ASPECT: f64 = 2.0;
+// Speed change per press of + or -
+const SPEED_STEP: f64 = 1.0;
+const LIT: &str = "█";
+const UNLIT: &str = "·";
+const KEYS: &str = "space play/pause  +/- speed  q quit";
+
+// The 16 ANSI colors as xterm shows them, for terminals without true color
+const ANSI_COLORS: [(Color, Rgb); 16] = [
+    (Color::Black, [0, 0, 0]),
+    (Color::Red, [205, 0, 0]),
+    (Color::Green, [0, 205, 0]),
+    (Color::Yellow, [205, 205, 0]),
+    (Color::Blue, [0, 0, 238]),
+    (Color::Magenta, [205, 0, 205]),
+    (Color::Cyan, [0, 205, 205]),
+    (Color::Gray, [229, 229, 229]),
+    (Color::DarkGray, [127, 127, 127]),
+    (Color::LightRed, [255, 0, 0]),
+    (Color::LightGreen, [0, 255, 0]),
+    (Color::LightYellow, [255, 255, 0]),
+    (Color::LightBlue, [92, 92, 255]),
+    (Color::LightMagenta, [255, 0, 255]),
+    (Color::LightCyan, [0, 255, 255]),
+    (Color::White, [255, 255, 255]),
+];
+
+/// Whether the terminal says it shows 24-bit color.
+pub fn supports_truecolor() -> bool {
+    std::env::var("COLORTERM").is_ok_and(|value| value == "truecolor" || value == "24bit")
+}
+
+/// The terminal color for an LED color: the color itself with true color, otherwise the
+/// nearest of the 16 ANSI colors.
+pub fn terminal_color(color: Rgb, truecolor: bool) -> Color {
+    if truecolor {
+        return Color::Rgb(color[0], color[1], color[2]);
+    }
+    let distance = |ansi: &Rgb| -> i32 {
+        ansi.iter()
+            .zip(color)
+            .map(|(&a, b)| (a as i32 - b as i32).pow(2))
+            .sum()
+    };
+    ANSI_COLORS
+        .iter()
+        .min_by_key(|(_, ansi)| distance(ansi))
+        .map_or(Color::White, |&(ansi, _)| ansi)
+}
+
+/// The cell of each LED when the layout is scaled into `area`, keeping its proportions and
+/// centered. Larger y values go higher up; along an axis the layout doesn't span, LEDs go in
+/// the middle.
+pub fn layout_cells(coordinates: &[LedPoint], bounds: &Bounds, area: Rect) -> Vec<(u16, u16)> {
+    let columns = area.width.saturating_sub(1) as f64;
+    let rows = area.height.saturating_sub(1) as f64;
+    // Layout units per column; a row is CELL_ASPECT times as many
+    let scale = [
+        bounds.width() / columns,
+        bounds.height() / (rows * CELL_ASPECT),
+    ]
+    .into_iter()
+    .filter(|scale| scale.is_finite() && *scale > 0.0)
+    .fold(0.0, f64::max);
+    let center = bounds.center();
+    coordinates
+        .iter()
+        .map(|coord| {
+            let (column, row) = if scale > 0.0 {
+                (
+                    columns / 2.0 + (coord.x - center.x) / scale,
+                    rows / 2.0 - (coord.y - center.y) / (scale * CELL_ASPECT),
+                )
+            } else {
+                (columns / 2.0, rows / 2.0)
+            };
+            (
+                area.x + column.round().clamp(0.0, columns) as u16,
+                area.y + row.round().clamp(0.0, rows) as u16,
+            )
+        })
+        .collect()
+}
+
+/// What a key press in the terminal does.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyAction {
+    Playback(PlaybackCommand),
+    Quit,
+}
+
+/// Space plays and pauses, + and - change the speed, q, Esc and Ctrl+C quit.
+pub fn key_action(key: KeyEvent) -> Option<KeyAction> {
+    if key.kind != KeyEventKind::Press {
+        return None;
+    }
+    match key.code {
+        KeyCode::Char(' ') => Some(KeyAction::Playback(PlaybackCommand::TogglePause)),
+        KeyCode::Char('+') | KeyCode::Char('=') => Some(KeyAction::Playback(
+            PlaybackCommand::AdjustSpeed(SPEED_STEP),
+        )),
+        KeyCode::Char('-') => Some(KeyAction::Playback(PlaybackCommand::AdjustSpeed(
+            -SPEED_STEP,
+        ))),
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            Some(KeyAction::Quit)
+        }
+        KeyCode::Char('q') | KeyCode::Esc => Some(KeyAction::Quit),
+        _ => None,
+    }
+}
+
+/// The track drawn into the terminal: every LED a dim dot, lit ones a block in their color,
+/// and a status line at the bottom.
+pub struct TrackView {
+    coordinates: Vec<LedPoint>,
+    bounds: Bounds,
+    truecolor: bool,
+    cells: Vec<(u16, u16)>,     // Of each LED, for `laid_out_for`
+    laid_out_for: Option<Rect>, // The track area last drawn into; laid out again on a resize
+}
+
+impl TrackView {
+    pub fn new(coordinates: &[LedPoint], truecolor: bool) -> TrackView {
+        TrackView {
+            coordinates: coordinates.to_vec(),
+            bounds: Bounds::from_coordinates(coordinates),
+            truecolor,
+            cells: Vec::new(),
+            laid_out_for: None,
+        }
+    }
+
+    pub fn render(&mut self, simulation: &Simulation, area: Rect, buffer: &mut Buffer) {
+        if area.height == 0 {
+            return;
+        }
+        let track = Rect {
+            height: area.height - 1,
+            ..area
+        };
+        if self.laid_out_for != Some(track) {
+            self.cells = layout_cells(&self.coordinates, &self.bounds, track);
+            self.laid_out_for = Some(track);
+        }
+        if track.height > 0 {
+            let unlit = Style::default().fg(Color::DarkGray);
+            for &(x, y) in &self.cells {
+                buffer.get_mut(x, y).set_symbol(UNLIT).set_style(unlit);
+            }
+            for (index, color) in simulation.frame().lit() {
+                if let Some(&(x, y)) = self.cells.get(index) {
+                    let style = Style::default().fg(terminal_color(color, self.truecolor));
+                    buffer.get_mut(x, y).set_symbol(LIT).set_style(style);
+                }
+            }
+        }
+
+        let state = match simulation.state() {
+            PlaybackState::Stopped => "STOPPED",
+            PlaybackState::Playing => "PLAYING",
+            PlaybackState::Paused => "PAUSED",
+            PlaybackState::Finished => "FINISHED",
+        };
+        let status = format!(
+            " {}  {} / {}  {}x   {}",
+            state,
+            format_duration(simulation.clock_time()),
+            format_duration(simulation.duration()),
+            (simulation.effective_speed() * 100.0).round() / 100.0,
+            KEYS
+        );
+        buffer.set_stringn(
+            area.x,
+            area.bottom() - 1,
+            status,
+            area.width as usize,
+            Style::default().add_modifier(Modifier::REVERSED),
+        );
+    }
+}
+
+/// The terminal in raw mode on the alternate screen, put back as it was when dropped.
+pub struct TerminalSession {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+}
+
+impl TerminalSession {
+    pub fn open() -> io::Result<TerminalSession> {
+        enable_raw_mode()?;
+        io::stdout().execute(EnterAlternateScreen)?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+        terminal.hide_cursor()?;
+        Ok(TerminalSession { terminal })
+    }
+
+    /// Draws the view over the whole terminal, at its current size.
+    pub fn draw(&mut self, view: &mut TrackView, simulation: &Simulation) -> io::Result<()> {
+        self.terminal.draw(|frame| {
+            let area = frame.size();
+            view.render(simulation, area, frame.buffer_mut());
+        })?;
+        Ok(())
+    }
+}
+
+impl Drop for TerminalSession {
+    fn drop(&mut self) {
+        let _ = self.terminal.show_cursor();
+        let _ = io::stdout().execute(LeaveAlternateScreen);
+        let _ = disable_raw_mode();
+    }
+}
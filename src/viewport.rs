@@ -0,0 +1,233 @@
+use crate::space::LedPoint;
+use eframe::egui;
+use log::warn;
+
+/// Axis-aligned bounding box of a set of LED coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min_x: f64,
+    pub max_x: f64,
+    pub min_y: f64,
+    pub max_y: f64,
+}
+
+impl Bounds {
+    /// The box around the coordinates; an empty box at the origin when there are none. A
+    /// layout that doesn't span an axis gets a warning, as it's drawn as a line or a point.
+    pub fn from_coordinates(coordinates: &[LedPoint]) -> Bounds {
+        let bounds = Bounds::around(coordinates);
+        if coordinates.len() > 1 {
+            for axis in bounds.flat_axes() {
+                warn!(
+                    "All {} LEDs have the same {} coordinate, so they're drawn in the middle along it",
+                    coordinates.len(),
+                    axis
+                );
+            }
+        }
+        bounds
+    }
+
+    fn around(coordinates: &[LedPoint]) -> Bounds {
+        if coordinates.is_empty() {
+            return Bounds {
+                min_x: 0.0,
+                max_x: 0.0,
+                min_y: 0.0,
+                max_y: 0.0,
+            };
+        }
+        coordinates.iter().fold(
+            Bounds {
+                min_x: f64::INFINITY,
+                max_x: f64::NEG_INFINITY,
+                min_y: f64::INFINITY,
+                max_y: f64::NEG_INFINITY,
+            },
+            |bounds, coord| Bounds {
+                min_x: bounds.min_x.min(coord.x),
+                max_x: bounds.max_x.max(coord.x),
+                min_y: bounds.min_y.min(coord.y),
+                max_y: bounds.max_y.max(coord.y),
+            },
+        )
+    }
+
+    pub fn width(&self) -> f64 {
+        self.max_x - self.min_x
+    }
+
+    pub fn height(&self) -> f64 {
+        self.max_y - self.min_y
+    }
+
+    /// The axes, "x" and "y", the box has no extent along.
+    pub fn flat_axes(&self) -> Vec<&'static str> {
+        [("x", self.width()), ("y", self.height())]
+            .into_iter()
+            .filter(|&(_, extent)| !(extent > 0.0 && extent.is_finite()))
+            .map(|(axis, _)| axis)
+            .collect()
+    }
+
+    pub fn center(&self) -> LedPoint {
+        LedPoint::new(
+            (self.min_x + self.max_x) / 2.0,
+            (self.min_y + self.max_y) / 2.0,
+        )
+    }
+}
+
+/// Mean position of a set of LED coordinates; the origin when there are none.
+pub fn centroid(coordinates: &[LedPoint]) -> LedPoint {
+    if coordinates.is_empty() {
+        return LedPoint::default();
+    }
+    let (sum_x, sum_y) = coordinates
+        .iter()
+        .fold((0.0, 0.0), |(x, y), coord| (x + coord.x, y + coord.y));
+    let count = coordinates.len() as f64;
+    LedPoint::new(sum_x / count, sum_y / count)
+}
+
+/// Zoom and pan of the track view: zoom 1 fits the whole layout, larger zooms in around
+/// `center`, in layout coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    pub center: LedPoint,
+    pub zoom: f64,
+}
+
+impl Camera {
+    /// The whole layout.
+    pub fn full(bounds: &Bounds) -> Camera {
+        Camera {
+            center: bounds.center(),
+            zoom: 1.0,
+        }
+    }
+}
+
+/// Maps layout coordinates onto a screen area, keeping a margin on every side.
+#[derive(Debug, Clone, Copy)]
+pub struct TrackViewport {
+    bounds: Bounds,
+    origin: egui::Pos2, // Top left corner of the area on screen
+    size: egui::Vec2,
+    margin: f32,
+    camera: Option<Camera>, // The whole layout without one
+}
+
+impl TrackViewport {
+    pub fn new(bounds: Bounds, size: egui::Vec2, margin: f32) -> TrackViewport {
+        TrackViewport {
+            bounds,
+            origin: egui::Pos2::ZERO,
+            size,
+            margin,
+            camera: None,
+        }
+    }
+
+    /// Zoomed and panned as the camera says.
+    pub fn with_camera(self, camera: Camera) -> TrackViewport {
+        TrackViewport {
+            camera: Some(camera),
+            ..self
+        }
+    }
+
+    /// Maps onto `rect` rather than an area at the top left of the screen, e.g. one pane of a
+    /// split board.
+    pub fn in_rect(bounds: Bounds, rect: egui::Rect, margin: f32) -> TrackViewport {
+        TrackViewport {
+            origin: rect.min,
+            ..TrackViewport::new(bounds, rect.size(), margin)
+        }
+    }
+
+    /// Screen position of a point on the board; larger y values are drawn higher up. Along an axis
+    /// the bounds don't span, e.g. with a single LED, points go in the middle.
+    pub fn to_screen(&self, point: LedPoint) -> egui::Pos2 {
+        let point = match self.camera {
+            Some(camera) => {
+                let home = self.bounds.center();
+                LedPoint::new(
+                    home.x + (point.x - camera.center.x) * camera.zoom,
+                    home.y + (point.y - camera.center.y) * camera.zoom,
+                )
+            }
+            None => point,
+        };
+        let usable_width = self.size.x - 2.0 * self.margin;
+        let usable_height = self.size.y - 2.0 * self.margin;
+        let norm_x = share(point.x, self.bounds.min_x, self.bounds.width()) * usable_width;
+        let norm_y =
+            usable_height - share(point.y, self.bounds.min_y, self.bounds.height()) * usable_height;
+        egui::pos2(
+            self.origin.x + norm_x + self.margin,
+            self.origin.y + norm_y + self.margin,
+        )
+    }
+
+    /// How far in layout coordinates a move of `delta` on screen goes; none along an axis the
+    /// bounds don't span.
+    pub fn layout_delta(&self, delta: egui::Vec2) -> (f64, f64) {
+        let zoom = self.camera.map_or(1.0, |camera| camera.zoom);
+        let along = |delta: f32, usable: f32, extent: f64| {
+            if extent > 0.0 && extent.is_finite() && usable > 0.0 {
+                delta as f64 / usable as f64 * extent / zoom
+            } else {
+                0.0
+            }
+        };
+        (
+            along(
+                delta.x,
+                self.size.x - 2.0 * self.margin,
+                self.bounds.width(),
+            ),
+            // Larger y values are drawn higher up
+            -along(
+                delta.y,
+                self.size.y - 2.0 * self.margin,
+                self.bounds.height(),
+            ),
+        )
+    }
+}
+
+/// Splits `rect` into `count` panes of equal width side by side, left to right.
+pub fn split_panes(rect: egui::Rect, count: usize) -> Vec<egui::Rect> {
+    let width = rect.width() / count.max(1) as f32;
+    (0..count.max(1))
+        .map(|index| {
+            let left = rect.min.x + width * index as f32;
+            egui::Rect::from_min_max(
+                egui::pos2(left, rect.min.y),
+                egui::pos2(left + width, rect.max.y),
+            )
+        })
+        .collect()
+}
+
+/// Splits `rect` between the segments of a layout: the track keeps the top, and the `others`
+/// get boxes of equal width side by side in a strip along the bottom, a quarter of the height.
+pub fn segment_regions(rect: egui::Rect, others: usize) -> (egui::Rect, Vec<egui::Rect>) {
+    if others == 0 {
+        return (rect, Vec::new());
+    }
+    let strip_top = rect.max.y - rect.height() / 4.0;
+    let track = egui::Rect::from_min_max(rect.min, egui::pos2(rect.max.x, strip_top));
+    let strip = egui::Rect::from_min_max(egui::pos2(rect.min.x, strip_top), rect.max);
+    (track, split_panes(strip, others))
+}
+
+// How far `value` is along an extent starting at `min`, one half when there's no extent
+fn share(value: f64, min: f64, extent: f64) -> f32 {
+    if extent > 0.0 && extent.is_finite() {
+        ((value - min) / extent) as f32
+    } else {
+        0.5
+    }
+}
@@ -0,0 +1,244 @@
+use crate::error::AppError;
+use crate::notices;
+use crate::simulation::{PlaybackState, Simulation};
+use crate::sink::{FrameDispatcher, SinkQueue};
+use crate::status::{Health, Status};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use web_time::Instant;
+
+/// Settings of the stall watchdog: when the clock or an output stops moving for `stall_secs`,
+/// a diagnostic dump is written to `dir`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchdogConfig {
+    pub enabled: bool,
+    pub stall_secs: f64,
+    pub dir: PathBuf,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        WatchdogConfig {
+            enabled: true,
+            stall_secs: 10.0,
+            dir: PathBuf::from("diagnostics"),
+        }
+    }
+}
+
+/// What stopped moving.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stall {
+    Clock { clock_time: f64 }, // Playing, but the clock stayed here
+    Sink { name: String },     // A frame has waited for the output all along
+}
+
+impl Stall {
+    pub fn describe(&self, stall_secs: f64) -> String {
+        match self {
+            Stall::Clock { clock_time } => format!(
+                "The clock has been stuck at {:.1}s for {:.0}s while playing",
+                clock_time, stall_secs
+            ),
+            Stall::Sink { name } => format!(
+                "The {} output hasn't taken a frame for {:.0}s",
+                name, stall_secs
+            ),
+        }
+    }
+}
+
+/// Notices a clock that doesn't advance while playing, or an output that doesn't take its
+/// frames. Checked once per frame; a stall is reported once, and again only after things moved.
+#[derive(Debug, Clone)]
+pub struct Watchdog {
+    enabled: bool,
+    stall_after: Duration,
+    clock: Option<(f64, Instant)>, // The clock time last seen and since when it stayed there
+    stalled: bool,
+}
+
+impl Watchdog {
+    pub fn new(config: &WatchdogConfig) -> Watchdog {
+        Watchdog {
+            enabled: config.enabled,
+            stall_after: Duration::from_secs_f64(config.stall_secs.max(0.1)),
+            clock: None,
+            stalled: false,
+        }
+    }
+
+    /// Looks at `clock_time`, given while playing, and the sinks' queues at `now`.
+    pub fn check(
+        &mut self,
+        clock_time: Option<f64>,
+        sinks: &[SinkQueue],
+        now: Instant,
+    ) -> Option<Stall> {
+        if !self.enabled {
+            return None;
+        }
+        self.clock = clock_time.map(|time| match self.clock {
+            Some((last, since)) if last == time => (last, since),
+            _ => (time, now),
+        });
+        let stall = match self.clock {
+            Some((clock_time, since)) if now.duration_since(since) >= self.stall_after => {
+                Some(Stall::Clock { clock_time })
+            }
+            // A sink that failed has stopped for good; that's reported as an error instead
+            _ => sinks
+                .iter()
+                .find(|sink| sink.alive && sink.waiting >= self.stall_after)
+                .map(|sink| Stall::Sink {
+                    name: sink.name.clone(),
+                }),
+        };
+        let first = stall.is_some() && !self.stalled;
+        self.stalled = stall.is_some();
+        stall.filter(|_| first)
+    }
+}
+
+/// The playback state in a diagnostic dump.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaybackDump {
+    pub state: PlaybackState,
+    pub clock_time: f64,
+    pub race_time: f64,
+    pub speed: f64,
+    pub effective_speed: f64,
+    pub reversed: bool,
+    pub current_index: usize,
+    pub record_count: usize,
+}
+
+/// One output in a diagnostic dump: its queue and delivery counters.
+#[derive(Debug, Clone, Serialize)]
+pub struct SinkDump {
+    pub name: String,
+    pub depth: usize,
+    pub waiting_secs: f64,
+    pub alive: bool,
+    pub produced: u64,
+    pub sent: u64,
+    pub skipped: u64,
+    pub dropped: u64,
+}
+
+/// Everything that helps tell why the app looks frozen, written as JSON to attach to a bug
+/// report.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticDump {
+    pub reason: String,
+    pub written_at: String,
+    pub playback: PlaybackDump,
+    pub sinks: Vec<SinkDump>,
+    pub notices_queued: usize, // Problems waiting for the window to show them
+    pub fetching: Option<String>, // The latest location fetch
+    pub errors: BTreeMap<String, String>, // The last error of each subsystem that has one
+    pub threads: BTreeMap<String, bool>, // Whether each background thread still runs
+}
+
+impl DiagnosticDump {
+    /// The state of the simulation and the outputs, with the errors `status` holds.
+    pub fn new(
+        reason: &str,
+        simulation: &Simulation,
+        outputs: &FrameDispatcher,
+        status: &Status,
+    ) -> DiagnosticDump {
+        let queues = outputs.queues();
+        let sinks = queues
+            .iter()
+            .zip(outputs.stats())
+            .map(|(queue, (_, stats))| SinkDump {
+                name: queue.name.clone(),
+                depth: queue.depth,
+                waiting_secs: queue.waiting.as_secs_f64(),
+                alive: queue.alive,
+                produced: stats.produced,
+                sent: stats.sent,
+                skipped: stats.skipped,
+                dropped: stats.dropped,
+            })
+            .collect();
+        let mut errors = BTreeMap::new();
+        let links = status
+            .api
+            .iter()
+            .map(|api| ("api".to_string(), api))
+            .chain(
+                status
+                    .cache
+                    .iter()
+                    .map(|cache| ("cache".to_string(), cache)),
+            )
+            .chain(
+                status
+                    .sinks
+                    .iter()
+                    .map(|(name, link)| (format!("sink {}", name), link)),
+            );
+        for (subsystem, link) in links {
+            if let (Health::Retrying | Health::Failed, Some(detail)) = (link.health, &link.detail) {
+                errors.insert(subsystem, detail.clone());
+            }
+        }
+        let threads = queues
+            .iter()
+            .map(|queue| (format!("sink-{}", queue.name), queue.alive))
+            .collect();
+        DiagnosticDump {
+            reason: reason.to_string(),
+            written_at: Local::now().to_rfc3339(),
+            playback: PlaybackDump {
+                state: simulation.state(),
+                clock_time: simulation.clock_time(),
+                race_time: simulation.race_time(),
+                speed: simulation.speed(),
+                effective_speed: simulation.effective_speed(),
+                reversed: simulation.is_reversed(),
+                current_index: simulation.current_index(),
+                record_count: simulation.record_count(),
+            },
+            sinks,
+            notices_queued: notices::queued(),
+            fetching: status
+                .fetching
+                .as_ref()
+                .map(|(session_key, window)| format!("session {} {:?}", session_key, window)),
+            errors,
+            threads,
+        }
+    }
+
+    /// Notes the last error of a subsystem the status doesn't track.
+    pub fn error(&mut self, subsystem: &str, error: &str) {
+        self.errors.insert(subsystem.to_string(), error.to_string());
+    }
+
+    /// Notes whether a background thread still runs.
+    pub fn thread(&mut self, name: &str, alive: bool) {
+        self.threads.insert(name.to_string(), alive);
+    }
+
+    /// Saves the dump in `dir`, which is created if needed, and returns the path of the file.
+    pub fn write(&self, dir: &Path) -> Result<PathBuf, AppError> {
+        std::fs::create_dir_all(dir)?;
+        let name = format!(
+            "diagnostics_{}.json",
+            Local::now().format("%Y%m%d-%H%M%S-%3f")
+        );
+        let path = dir.join(name);
+        let writer = BufWriter::new(File::create(&path)?);
+        serde_json::to_writer_pretty(writer, self).map_err(std::io::Error::from)?;
+        Ok(path)
+    }
+}
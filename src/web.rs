@@ -0,0 +1,333 @@
+use crate::bundle::Bundle;
+use crate::config::{ApiConfig, DisplayConfig, PlaybackConfig, SessionConfig};
+use crate::data::fetch_driver_data;
+use crate::driver_info::{
+    driver_colors, driver_numbers, driver_teams, get_driver_info_for_year, DEFAULT_SEASON,
+};
+use crate::error::AppError;
+use crate::led_coords::read_coordinates;
+use crate::led_style::{elevation_shades, led_shapes};
+use crate::mapping::{map_drivers, MappingOptions, MAPPING_VERSION};
+use crate::render::format_race_time;
+use crate::simulation::{PlaybackState, Rgb, Simulation};
+use crate::space::LedPoint;
+use crate::viewport::{Bounds, TrackViewport};
+use chrono::Utc;
+use eframe::egui;
+use std::cell::RefCell;
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+use web_time::Instant;
+
+/// The bundle played when the page's query string names no session, relative to the page.
+/// `trunk serve` copies the `web` directory next to it.
+pub const DEFAULT_BUNDLE: &str = "web/zandvoort.f1led";
+
+// Fast enough to follow the data while playing
+const PLAYING_REPAINT_INTERVAL: Duration = Duration::from_millis(33);
+
+/// Where the browser viewer gets its session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebSource {
+    /// A `.f1led` bundle served with the page, at a URL relative to it.
+    Bundle(String),
+    /// The session with this key, fetched from the API and mapped in the browser.
+    Api(String),
+}
+
+impl WebSource {
+    /// The source a page's query string asks for: `?session=<key>` fetches that session from
+    /// the API and `?bundle=<url>` plays a bundle; without either, the one at `DEFAULT_BUNDLE`.
+    pub fn from_query(query: &str) -> WebSource {
+        let mut source = WebSource::Bundle(DEFAULT_BUNDLE.to_string());
+        for pair in query.trim_start_matches('?').split('&') {
+            match pair.split_once('=') {
+                Some(("session", key)) if !key.is_empty() => {
+                    return WebSource::Api(key.to_string())
+                }
+                Some(("bundle", url)) if !url.is_empty() => {
+                    source = WebSource::Bundle(url.to_string());
+                }
+                _ => {}
+            }
+        }
+        source
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            WebSource::Bundle(url) => format!("the bundle {}", url),
+            WebSource::Api(key) => format!("session {} from the API", key),
+        }
+    }
+}
+
+/// Fetches the session `source` names, with bundle URLs taken relative to `page_url`. A
+/// session from the API is mapped onto the Zandvoort layout with the default options, as a
+/// bundle made from it would be.
+pub async fn load_session(source: &WebSource, page_url: &str) -> Result<Bundle, AppError> {
+    match source {
+        WebSource::Bundle(url) => load_bundle(url, page_url).await,
+        WebSource::Api(key) => load_from_api(key).await,
+    }
+}
+
+async fn load_bundle(url: &str, page_url: &str) -> Result<Bundle, AppError> {
+    let resolved = reqwest::Url::parse(page_url)
+        .and_then(|page| page.join(url))
+        .map_err(|err| AppError::Config {
+            reason: format!("bundle URL {}: {}", url, err),
+        })?;
+    let network = |source| AppError::Network {
+        url: resolved.to_string(),
+        source,
+    };
+    let bytes = reqwest::get(resolved.clone())
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(network)?
+        .bytes()
+        .await
+        .map_err(network)?;
+    let mut bundle = Bundle::read(&bytes[..])?;
+    // Made by another build, the records are mapped again onto the bundle's own layout
+    let coordinates = bundle.coordinates.clone();
+    bundle.map_onto(&coordinates)?;
+    Ok(bundle)
+}
+
+async fn load_from_api(key: &str) -> Result<Bundle, AppError> {
+    let api = ApiConfig::default();
+    let session = SessionConfig {
+        key: key.to_string(),
+        ..SessionConfig::default()
+    };
+    let coordinates = read_coordinates()?;
+    let driver_info = get_driver_info_for_year(DEFAULT_SEASON);
+    let (per_driver, mut pipeline) =
+        fetch_driver_data(&api, key, &driver_numbers(&driver_info), &session.window()).await?;
+    let mapping_options = MappingOptions::default();
+    let (run_race_data, mut mapping_stats) =
+        map_drivers(per_driver, &coordinates, &mapping_options, &mut pipeline)?;
+    mapping_stats.pipeline = pipeline;
+    Ok(Bundle {
+        created: Utc::now(),
+        mapping_version: MAPPING_VERSION,
+        session,
+        coordinates,
+        driver_info,
+        mapping_options,
+        run_race_data,
+        mapping_stats,
+        race_progress: None,
+    })
+}
+
+/// Plays a session in the browser: the layout with its LEDs, play and pause, the speed and a
+/// slider through the race. Nothing goes to hardware; the page is the only output.
+pub struct WebViewer {
+    source: WebSource,
+    loaded: Rc<RefCell<Option<Result<Bundle, AppError>>>>, // Filled in once the load ends
+    session: Option<WebSession>,
+    error: Option<String>, // Why the session couldn't be loaded
+    display: DisplayConfig,
+    speed: f64, // To start playing at
+    speed_range: RangeInclusive<f64>,
+    last_update: Instant,
+}
+
+// A loaded session and its playback
+struct WebSession {
+    label: String,
+    coordinates: Vec<LedPoint>,
+    bounds: Bounds,
+    shades: Vec<f32>, // Of the unlit LEDs by elevation; empty when not shaded
+    simulation: Simulation,
+}
+
+impl WebSession {
+    fn new(bundle: Bundle, display: &DisplayConfig, speed: f64) -> WebSession {
+        let led_count = bundle.coordinates.len();
+        let mut simulation = Simulation::new(
+            bundle.run_race_data,
+            led_count,
+            driver_colors(&bundle.driver_info),
+        );
+        simulation.set_driver_teams(driver_teams(&bundle.driver_info));
+        simulation.set_race_progress(bundle.race_progress.map(Arc::new));
+        simulation.set_speed(speed);
+        simulation.start();
+        WebSession {
+            label: bundle.session.label(),
+            bounds: Bounds::from_coordinates(&bundle.coordinates),
+            shades: elevation_shades(&bundle.mapping_stats.led_elevation)
+                .filter(|_| display.elevation_shading)
+                .unwrap_or_default(),
+            coordinates: bundle.coordinates,
+            simulation,
+        }
+    }
+}
+
+impl WebViewer {
+    /// Starts loading the session of `source` in the background, with URLs relative to
+    /// `page_url`; the viewer shows it once it's there.
+    pub fn new(ctx: &egui::Context, source: WebSource, page_url: String) -> WebViewer {
+        let loaded = Rc::new(RefCell::new(None));
+        wasm_bindgen_futures::spawn_local({
+            let loaded = Rc::clone(&loaded);
+            let source = source.clone();
+            let ctx = ctx.clone();
+            async move {
+                let result = load_session(&source, &page_url).await;
+                *loaded.borrow_mut() = Some(result);
+                ctx.request_repaint();
+            }
+        });
+        let playback = PlaybackConfig::default();
+        WebViewer {
+            source,
+            loaded,
+            session: None,
+            error: None,
+            display: DisplayConfig::default(),
+            speed: playback.speed,
+            speed_range: playback.min_speed..=playback.max_speed,
+            last_update: Instant::now(),
+        }
+    }
+
+    // Why the session couldn't be loaded; the default bundle isn't in a fresh checkout, so its
+    // absence says how to make it
+    fn load_error(&self, err: &AppError) -> String {
+        let not_found = matches!(err, AppError::Network { source, .. }
+            if source.status() == Some(reqwest::StatusCode::NOT_FOUND));
+        if not_found && self.source == WebSource::Bundle(DEFAULT_BUNDLE.to_string()) {
+            format!(
+                "the page serves no bundle there. Make one with `cargo run -- map --output {}` \
+                 and serve the page again, or open it with ?session=<key> to fetch a session \
+                 from the API or ?bundle=<url> to play another bundle",
+                DEFAULT_BUNDLE
+            )
+        } else {
+            err.user_message()
+        }
+    }
+
+    fn controls_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(session) = &mut self.session else {
+            return;
+        };
+        let simulation = &mut session.simulation;
+        ui.horizontal(|ui| {
+            ui.strong(format!("Session {}", session.label));
+            let playing = simulation.state() == PlaybackState::Playing;
+            if ui.button(if playing { "PAUSE" } else { "PLAY" }).clicked() {
+                if simulation.is_running() {
+                    simulation.set_paused(playing);
+                } else {
+                    simulation.start();
+                }
+            }
+            let mut speed = simulation.speed();
+            let slider = egui::Slider::new(&mut speed, self.speed_range.clone())
+                .logarithmic(true)
+                .max_decimals(2)
+                .suffix("x");
+            if ui.add(slider).changed() {
+                simulation.set_speed(speed);
+            }
+            ui.label(format_race_time(simulation.race_time().max(0.0)));
+        });
+        let duration = simulation.duration();
+        let mut clock_time = simulation.clock_time().clamp(0.0, duration);
+        let seeker = egui::Slider::new(&mut clock_time, 0.0..=duration)
+            .show_value(false)
+            .text("Race");
+        if ui.add(seeker).changed() {
+            if !simulation.is_running() {
+                simulation.start();
+                simulation.set_paused(true);
+            }
+            simulation.seek(Duration::from_secs_f64(clock_time));
+        }
+    }
+
+    fn track_ui(&self, ui: &mut egui::Ui) {
+        let Some(session) = &self.session else {
+            ui.centered_and_justified(|ui| match &self.error {
+                Some(error) => ui.label(format!(
+                    "Could not load {}: {}",
+                    self.source.describe(),
+                    error
+                )),
+                None => ui.label(format!("Loading {}…", self.source.describe())),
+            });
+            return;
+        };
+        let rect = ui.available_rect_before_wrap();
+        let viewport = TrackViewport::in_rect(session.bounds, rect, 30.0);
+        let positions: Vec<egui::Pos2> = session
+            .coordinates
+            .iter()
+            .map(|coord| viewport.to_screen(*coord))
+            .collect();
+        let colors: Vec<Rgb> = session
+            .simulation
+            .frame()
+            .leds
+            .iter()
+            .map(|color| {
+                color
+                    .unwrap_or_default()
+                    .map(|channel| (channel as f32 * self.display.brightness).round() as u8)
+            })
+            .collect();
+        ui.painter().extend(led_shapes(
+            self.display.style,
+            &positions,
+            &colors,
+            &session.shades,
+            self.display.led_size,
+            self.display.glow_radius,
+            ui.clip_rect(),
+        ));
+    }
+}
+
+impl eframe::App for WebViewer {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if let Some(result) = self.loaded.borrow_mut().take() {
+            match result {
+                Ok(bundle) => {
+                    self.session = Some(WebSession::new(bundle, &self.display, self.speed));
+                    self.last_update = Instant::now();
+                }
+                Err(err) => {
+                    log::error!("{}", err);
+                    self.error = Some(self.load_error(&err));
+                }
+            }
+        }
+        let now = Instant::now();
+        if let Some(session) = &mut self.session {
+            session.simulation.advance(now - self.last_update);
+        }
+        self.last_update = now;
+
+        egui::TopBottomPanel::top("controls").show(ctx, |ui| self.controls_ui(ui));
+        egui::CentralPanel::default()
+            .frame(egui::Frame::none().fill(egui::Color32::BLACK))
+            .show(ctx, |ui| self.track_ui(ui));
+
+        let playing = self
+            .session
+            .as_ref()
+            .is_some_and(|session| session.simulation.state() == PlaybackState::Playing);
+        if playing {
+            ctx.request_repaint_after(PLAYING_REPAINT_INTERVAL);
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use crate::error::AppError;
+use crate::simulation::{PlaybackState, Rgb};
+use crate::sink::{LedSink, OutputFrame, DEFAULT_MAX_FPS};
+use crate::space::LedPoint;
+use crate::track_progress::DriverProgress;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::{Html, Response};
+use axum::routing::get;
+use axum::Router;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc, watch};
+
+// Messages a client may fall behind by before it's dropped, about two seconds at 30 fps
+const CLIENT_BACKLOG: usize = 64;
+
+// How long shutting down waits for clients to get their close message
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
+// Test page served at `/`, drawing the LEDs it receives from `/ws`
+const PAGE: &str = include_str!("websocket.html");
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebSocketConfig {
+    pub enabled: bool,
+    pub bind: String, // Address to listen on; the default accepts other devices on the network
+    pub port: u16,
+    pub max_fps: f64,
+}
+
+impl Default for WebSocketConfig {
+    fn default() -> Self {
+        WebSocketConfig {
+            enabled: false,
+            bind: "0.0.0.0".to_string(),
+            port: 8765,
+            max_fps: DEFAULT_MAX_FPS,
+        }
+    }
+}
+
+/// A message to the browser, as JSON with a `type` field. A client gets the layout, the full
+/// frame and the leaderboard on connecting, then the changes of every frame and the
+/// leaderboard whenever its order changes.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ClientMessage<'a> {
+    Layout {
+        leds: Vec<[f64; 2]>, // x and y of each LED
+    },
+    Frame {
+        time: f64, // Race time in seconds
+        state: PlaybackState,
+        speed: f64,
+        leds: &'a [Rgb],
+        drivers: &'a [DriverProgress], // Progress round the lap
+    },
+    Changes {
+        time: f64,
+        state: PlaybackState,
+        speed: f64,
+        changes: Vec<(usize, Rgb)>, // Index and new color of each LED that changed
+        drivers: &'a [DriverProgress],
+    },
+    Leaderboard {
+        time: f64,
+        drivers: Vec<u32>, // Driver numbers, leader first
+    },
+}
+
+/// The running order of the drivers: most laps covered first, ties by number.
+pub fn running_order(drivers: &[DriverProgress]) -> Vec<u32> {
+    let mut order: Vec<&DriverProgress> = drivers.iter().collect();
+    order.sort_by(|a, b| {
+        let covered = |driver: &DriverProgress| driver.laps as f64 + driver.percent / 100.0;
+        covered(b)
+            .total_cmp(&covered(a))
+            .then(a.driver_number.cmp(&b.driver_number))
+    });
+    order
+        .into_iter()
+        .map(|driver| driver.driver_number)
+        .collect()
+}
+
+/// Serves the frames to browsers over WebSocket at `/ws`, with a test page at `/`.
+pub struct WebSocketServer {
+    runtime: Option<Runtime>,
+    address: SocketAddr,
+    frames: broadcast::Sender<String>,
+    snapshot: Arc<Mutex<String>>, // The latest full frame message, for new clients
+    leaderboard: Arc<Mutex<String>>, // The latest leaderboard message, for new clients
+    leds: Vec<Rgb>,               // Colors of the latest frame, brightness applied
+    order: Vec<u32>,              // Running order of the latest leaderboard
+    stop: watch::Sender<bool>,
+    disconnected: mpsc::Receiver<()>, // Closes once the server and every client are gone
+}
+
+// What the server and its client tasks share
+struct Shared {
+    frames: broadcast::Sender<String>,
+    snapshot: Arc<Mutex<String>>,
+    leaderboard: Arc<Mutex<String>>,
+    layout: String,
+    stop: watch::Receiver<bool>,
+    _connected: mpsc::Sender<()>, // Never sent on; dropped with the last task
+}
+
+impl WebSocketServer {
+    pub fn open(
+        config: &WebSocketConfig,
+        coordinates: &[LedPoint],
+    ) -> Result<WebSocketServer, AppError> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("websocket")
+            .enable_all()
+            .build()?;
+        let listener = runtime
+            .block_on(tokio::net::TcpListener::bind((
+                config.bind.as_str(),
+                config.port,
+            )))
+            .map_err(|err| AppError::Output {
+                reason: format!(
+                    "could not listen for WebSocket clients on {}:{}: {}",
+                    config.bind, config.port, err
+                ),
+            })?;
+        let address = listener.local_addr()?;
+
+        let leds = vec![[0, 0, 0]; coordinates.len()];
+        let layout = ClientMessage::Layout {
+            leds: coordinates.iter().map(|coord| [coord.x, coord.y]).collect(),
+        };
+        let snapshot = ClientMessage::Frame {
+            time: 0.0,
+            state: PlaybackState::Stopped,
+            speed: 1.0,
+            leds: &leds,
+            drivers: &[],
+        };
+        let (frames, _) = broadcast::channel(CLIENT_BACKLOG);
+        let (stop, stop_receiver) = watch::channel(false);
+        let (connected, disconnected) = mpsc::channel(1);
+        let leaderboard = ClientMessage::Leaderboard {
+            time: 0.0,
+            drivers: Vec::new(),
+        };
+        let snapshot = Arc::new(Mutex::new(to_json(&snapshot)?));
+        let leaderboard = Arc::new(Mutex::new(to_json(&leaderboard)?));
+        let shared = Arc::new(Shared {
+            frames: frames.clone(),
+            snapshot: Arc::clone(&snapshot),
+            leaderboard: Arc::clone(&leaderboard),
+            layout: to_json(&layout)?,
+            stop: stop_receiver.clone(),
+            _connected: connected,
+        });
+
+        let router = Router::new()
+            .route("/", get(|| async { Html(PAGE) }))
+            .route("/ws", get(upgrade))
+            .with_state(shared);
+        let mut server_stop = stop_receiver;
+        runtime.spawn(async move {
+            let server = axum::serve(listener, router).with_graceful_shutdown(async move {
+                let _ = server_stop.changed().await;
+            });
+            if let Err(err) = server.await {
+                warn!("The WebSocket server stopped: {}", err);
+            }
+        });
+        info!("Serving frames to WebSocket clients on {}", address);
+
+        Ok(WebSocketServer {
+            runtime: Some(runtime),
+            address,
+            frames,
+            snapshot,
+            leaderboard,
+            leds,
+            order: Vec::new(),
+            stop,
+            disconnected,
+        })
+    }
+
+    /// The address the server listens on, with the actual port when the config asked for 0.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+}
+
+impl LedSink for WebSocketServer {
+    fn name(&self) -> &str {
+        "websocket"
+    }
+
+    fn submit(&mut self, frame: &OutputFrame) -> Result<(), AppError> {
+        let leds = frame.dimmed();
+        let time = frame.timestamp.as_secs_f64();
+        let changes = leds
+            .iter()
+            .zip(&self.leds)
+            .enumerate()
+            .filter(|(_, (color, previous))| color != previous)
+            .map(|(index, (&color, _))| (index, color))
+            .collect();
+        let changes = ClientMessage::Changes {
+            time,
+            state: frame.state,
+            speed: frame.speed,
+            changes,
+            drivers: &frame.drivers,
+        };
+        let snapshot = ClientMessage::Frame {
+            time,
+            state: frame.state,
+            speed: frame.speed,
+            leds: &leds,
+            drivers: &frame.drivers,
+        };
+        *self.snapshot.lock().unwrap() = to_json(&snapshot)?;
+        // Fails only while no client is connected
+        let _ = self.frames.send(to_json(&changes)?);
+        self.leds = leds;
+
+        let order = running_order(&frame.drivers);
+        if order != self.order {
+            let leaderboard = to_json(&ClientMessage::Leaderboard {
+                time,
+                drivers: order.clone(),
+            })?;
+            *self.leaderboard.lock().unwrap() = leaderboard.clone();
+            let _ = self.frames.send(leaderboard);
+            self.order = order;
+        }
+        Ok(())
+    }
+
+    fn shutdown(&mut self) {
+        let _ = self.stop.send(true);
+        if let Some(runtime) = self.runtime.take() {
+            let disconnected = &mut self.disconnected;
+            runtime.block_on(async {
+                let _ = tokio::time::timeout(SHUTDOWN_TIMEOUT, disconnected.recv()).await;
+            });
+            runtime.shutdown_timeout(SHUTDOWN_TIMEOUT);
+            info!("Stopped the WebSocket server");
+        }
+    }
+}
+
+fn to_json(message: &ClientMessage) -> Result<String, AppError> {
+    serde_json::to_string(message).map_err(|err| AppError::Output {
+        reason: format!("could not encode a WebSocket message: {}", err),
+    })
+}
+
+async fn upgrade(ws: WebSocketUpgrade, State(shared): State<Arc<Shared>>) -> Response {
+    ws.on_upgrade(|socket| serve_client(socket, shared))
+}
+
+// Sends the layout, current frame and leaderboard, then every change until the client leaves,
+// falls too far behind or the server stops
+async fn serve_client(mut socket: WebSocket, shared: Arc<Shared>) {
+    // Subscribing before taking the snapshot means no change falls between the two
+    let mut frames = shared.frames.subscribe();
+    let mut stop = shared.stop.clone();
+    let snapshot = shared.snapshot.lock().unwrap().clone();
+    let leaderboard = shared.leaderboard.lock().unwrap().clone();
+    for message in [shared.layout.clone(), snapshot, leaderboard] {
+        if socket.send(Message::Text(message)).await.is_err() {
+            return;
+        }
+    }
+    debug!("WebSocket client connected");
+
+    loop {
+        tokio::select! {
+            frame = frames.recv() => match frame {
+                Ok(message) => {
+                    if socket.send(Message::Text(message)).await.is_err() {
+                        return;
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    debug!("Dropping a WebSocket client {} messages behind", missed);
+                    break;
+                }
+                Err(RecvError::Closed) => break,
+            },
+            incoming = socket.recv() => match incoming {
+                Some(Ok(_)) => {} // Clients have nothing to say
+                _ => return,
+            },
+            _ = stop.changed() => break,
+        }
+    }
+    let _ = socket.send(Message::Close(None)).await;
+}
@@ -0,0 +1,275 @@
+use crate::error::AppError;
+use crate::simulation::Rgb;
+use crate::sink::{LedSink, OutputFrame, DEFAULT_MAX_FPS};
+use crate::status::{set_sink, Health};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::net::UdpSocket;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Default port of WLED's UDP realtime protocols.
+pub const WLED_UDP_PORT: u16 = 21324;
+
+// UDP realtime protocol ids and limits
+const DRGB: u8 = 2;
+const DNRGB: u8 = 4;
+const DRGB_MAX_LEDS: usize = 490;
+const DNRGB_MAX_LEDS: usize = 489;
+const REALTIME_TIMEOUT_SECS: u8 = 2; // WLED returns to its own effects after this long
+                                     // LEDs per JSON state request, which WLED has to fit in a fixed buffer
+const JSON_MAX_LEDS: usize = 256;
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(2);
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// How frames reach the WLED controller.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WledProtocol {
+    /// `POST /json/state` with the per-LED `"i"` segment syntax.
+    #[default]
+    Json,
+    /// DRGB/DNRGB realtime packets, for lower latency.
+    Udp,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WledConfig {
+    pub enabled: bool,
+    pub host: String, // IP or hostname of the controller
+    pub protocol: WledProtocol,
+    pub udp_port: u16,
+    pub max_fps: f64,
+}
+
+impl Default for WledConfig {
+    fn default() -> Self {
+        WledConfig {
+            enabled: false,
+            host: "wled.local".to_string(),
+            protocol: WledProtocol::default(),
+            udp_port: WLED_UDP_PORT,
+            max_fps: DEFAULT_MAX_FPS,
+        }
+    }
+}
+
+/// Connection state of a `WledSink`, for display.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WledStatus {
+    Connecting,
+    Connected,
+    Unreachable { retry_in: Duration },
+}
+
+impl WledStatus {
+    pub fn label(&self) -> String {
+        match self {
+            WledStatus::Connecting => "connecting".to_string(),
+            WledStatus::Connected => "connected".to_string(),
+            WledStatus::Unreachable { retry_in } => {
+                format!("unreachable, retrying in {:.1}s", retry_in.as_secs_f32())
+            }
+        }
+    }
+}
+
+/// Shared view of a `WledSink`'s connection state, still readable once the sink has been
+/// handed to its output thread.
+#[derive(Clone)]
+pub struct WledStatusHandle(Arc<Mutex<WledStatus>>);
+
+impl WledStatusHandle {
+    pub fn get(&self) -> WledStatus {
+        *self.0.lock().unwrap()
+    }
+}
+
+/// Streams frames to a WLED controller. While the controller is unreachable, frames are
+/// skipped until the next retry, with the delay doubling after each failure.
+pub struct WledSink {
+    config: WledConfig,
+    status: Arc<Mutex<WledStatus>>,
+    client: reqwest::blocking::Client,
+    socket: UdpSocket,
+    backoff: Duration,
+    retry_at: Option<Instant>,
+    sent_brightness: Option<u8>, // Master brightness the controller last acknowledged
+    last_health_check: Option<Instant>, // UDP has no replies, so the JSON API is polled instead
+}
+
+impl WledSink {
+    pub fn open(config: &WledConfig) -> Result<WledSink, AppError> {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(HTTP_TIMEOUT)
+            .build()
+            .map_err(|source| AppError::Network {
+                url: config.host.clone(),
+                source,
+            })?;
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        info!("Sending frames to WLED at {}", config.host);
+        Ok(WledSink {
+            config: config.clone(),
+            status: Arc::new(Mutex::new(WledStatus::Connecting)),
+            client,
+            socket,
+            backoff: INITIAL_BACKOFF,
+            retry_at: None,
+            sent_brightness: None,
+            last_health_check: None,
+        })
+    }
+
+    pub fn status(&self) -> WledStatusHandle {
+        WledStatusHandle(Arc::clone(&self.status))
+    }
+
+    /// Sends a frame, unless a retry is pending. `leds` should not have the app brightness
+    /// applied, since it's sent as WLED's master brightness instead.
+    pub fn show(&mut self, leds: &[Rgb], brightness: f32) {
+        let now = Instant::now();
+        if self.retry_at.is_some_and(|retry_at| now < retry_at) {
+            return;
+        }
+
+        let brightness = (brightness.clamp(0.0, 1.0) * 255.0).round() as u8;
+        match self.send(leds, brightness) {
+            Ok(()) => {
+                if self.set_status(WledStatus::Connected) {
+                    info!("WLED at {} is connected", self.config.host);
+                }
+                self.backoff = INITIAL_BACKOFF;
+                self.retry_at = None;
+            }
+            Err(err) => {
+                warn!("{}; retrying in {:?}", err, self.backoff);
+                self.set_status(WledStatus::Unreachable {
+                    retry_in: self.backoff,
+                });
+                self.sent_brightness = None;
+                self.last_health_check = None;
+                self.retry_at = Some(now + self.backoff);
+                self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+
+    // Returns whether the status changed
+    fn set_status(&self, status: WledStatus) -> bool {
+        let mut current = self.status.lock().unwrap();
+        let changed = *current != status;
+        *current = status;
+        if changed {
+            let health = match status {
+                WledStatus::Unreachable { .. } => Health::Retrying,
+                WledStatus::Connecting | WledStatus::Connected => Health::Active,
+            };
+            set_sink(self.name(), health, Some(status.label()));
+        }
+        changed
+    }
+
+    fn send(&mut self, leds: &[Rgb], brightness: u8) -> Result<(), AppError> {
+        match self.config.protocol {
+            WledProtocol::Json => {
+                for (chunk, colors) in json_colors(leds).into_iter().enumerate() {
+                    let state = if chunk == 0 {
+                        json!({ "on": true, "bri": brightness, "seg": { "i": colors } })
+                    } else {
+                        json!({ "seg": { "i": colors } })
+                    };
+                    self.post_state(state)?;
+                }
+                Ok(())
+            }
+            WledProtocol::Udp => {
+                let health_check_due = match self.last_health_check {
+                    Some(last) => last.elapsed() >= HEALTH_CHECK_INTERVAL,
+                    None => true,
+                };
+                if self.sent_brightness != Some(brightness) || health_check_due {
+                    self.post_state(json!({ "on": true, "bri": brightness }))?;
+                    self.sent_brightness = Some(brightness);
+                    self.last_health_check = Some(Instant::now());
+                }
+                for packet in realtime_packets(leds) {
+                    self.socket
+                        .send_to(&packet, (self.config.host.as_str(), self.config.udp_port))?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn post_state(&self, state: serde_json::Value) -> Result<(), AppError> {
+        let url = format!("http://{}/json/state", self.config.host);
+        let response = self
+            .client
+            .post(&url)
+            .json(&state)
+            .send()
+            .map_err(|source| AppError::Network {
+                url: url.clone(),
+                source,
+            })?;
+        response
+            .error_for_status()
+            .map_err(|source| AppError::Network { url, source })?;
+        Ok(())
+    }
+}
+
+impl LedSink for WledSink {
+    fn name(&self) -> &str {
+        "wled"
+    }
+
+    // WLED applies the brightness itself as its master brightness
+    fn submit(&mut self, frame: &OutputFrame) -> Result<(), AppError> {
+        self.show(&frame.leds, frame.brightness);
+        Ok(())
+    }
+}
+
+/// The `i` arrays of the JSON state requests for a frame: the index of the first LED, then
+/// the hex colors from there on, in chunks WLED can take in one request.
+pub fn json_colors(leds: &[Rgb]) -> Vec<Vec<serde_json::Value>> {
+    leds.chunks(JSON_MAX_LEDS)
+        .enumerate()
+        .map(|(chunk, colors)| {
+            std::iter::once(json!(chunk * JSON_MAX_LEDS))
+                .chain(
+                    colors
+                        .iter()
+                        .map(|[r, g, b]| json!(format!("{:02X}{:02X}{:02X}", r, g, b))),
+                )
+                .collect()
+        })
+        .collect()
+}
+
+/// UDP realtime packets for a frame: one DRGB packet, or DNRGB chunks for long strips.
+pub fn realtime_packets(leds: &[Rgb]) -> Vec<Vec<u8>> {
+    if leds.len() <= DRGB_MAX_LEDS {
+        let mut packet = vec![DRGB, REALTIME_TIMEOUT_SECS];
+        packet.extend(leds.iter().flatten());
+        return vec![packet];
+    }
+
+    leds.chunks(DNRGB_MAX_LEDS)
+        .enumerate()
+        .map(|(chunk, colors)| {
+            let start = (chunk * DNRGB_MAX_LEDS) as u16;
+            let mut packet = vec![DNRGB, REALTIME_TIMEOUT_SECS];
+            packet.extend_from_slice(&start.to_be_bytes());
+            packet.extend(colors.iter().flatten());
+            packet
+        })
+        .collect()
+}
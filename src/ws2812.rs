@@ -0,0 +1,103 @@
+use crate::config::Ws2812Config;
+use crate::error::AppError;
+use crate::pixel_map::PixelMap;
+use crate::simulation::Rgb;
+use crate::sink::{LedSink, OutputFrame};
+use log::{info, warn};
+use rppal::spi::{Bus, Mode, SlaveSelect, Spi};
+
+// The strip's 800 kHz signal is produced with three SPI bits per data bit: 0b100 for a 0 and
+// 0b110 for a 1
+const SPI_CLOCK_HZ: u32 = 2_400_000;
+
+// Low time after a frame that latches it; 300 µs covers newer WS2812B revisions
+const RESET_BYTES: usize = 90;
+
+/// A WS2812 strip on the Raspberry Pi's SPI0 MOSI pin (GPIO 10). The strip is blanked when
+/// this is dropped, including while unwinding from a panic.
+pub struct Ws2812Strip {
+    spi: Spi,
+    pixel_map: PixelMap,
+}
+
+impl Ws2812Strip {
+    pub fn open(config: &Ws2812Config, led_count: usize) -> Result<Ws2812Strip, AppError> {
+        let pixel_map = PixelMap::new(
+            led_count,
+            config.pixel_order.as_deref(),
+            config.pixel_offset,
+            config.pixel_count.unwrap_or(led_count),
+        )?;
+        let spi =
+            Spi::new(Bus::Spi0, SlaveSelect::Ss0, SPI_CLOCK_HZ, Mode::Mode0).map_err(|err| {
+                AppError::Output {
+                    reason: format!("could not open SPI0 for the WS2812 strip: {}", err),
+                }
+            })?;
+        info!(
+            "Driving a {}-pixel WS2812 strip on SPI0",
+            pixel_map.pixel_count()
+        );
+
+        Ok(Ws2812Strip { spi, pixel_map })
+    }
+
+    /// Sends a frame of layout colors.
+    pub fn show(&mut self, leds: &[Rgb]) -> Result<(), AppError> {
+        let strip = self.pixel_map.map(leds);
+        self.write(&strip)
+    }
+
+    /// Turns every pixel off.
+    pub fn blank(&mut self) -> Result<(), AppError> {
+        self.write(&vec![[0, 0, 0]; self.pixel_map.pixel_count()])
+    }
+
+    fn write(&mut self, strip: &[Rgb]) -> Result<(), AppError> {
+        let mut buffer = Vec::with_capacity(strip.len() * 9 + RESET_BYTES);
+        for &[r, g, b] in strip {
+            // WS2812 expects green first
+            for channel in [g, r, b] {
+                encode_byte(channel, &mut buffer);
+            }
+        }
+        buffer.resize(buffer.len() + RESET_BYTES, 0);
+
+        self.spi.write(&buffer).map_err(|err| AppError::Output {
+            reason: format!("could not write to the WS2812 strip: {}", err),
+        })?;
+        Ok(())
+    }
+}
+
+impl LedSink for Ws2812Strip {
+    fn name(&self) -> &str {
+        "ws2812"
+    }
+
+    fn submit(&mut self, frame: &OutputFrame) -> Result<(), AppError> {
+        self.show(&frame.dimmed())
+    }
+}
+
+impl Drop for Ws2812Strip {
+    fn drop(&mut self) {
+        if let Err(err) = self.blank() {
+            warn!("{}", err);
+        }
+    }
+}
+
+// Expands each data bit, most significant first, into three SPI bits
+fn encode_byte(value: u8, buffer: &mut Vec<u8>) {
+    let mut bits: u32 = 0;
+    for bit in (0..8).rev() {
+        bits = (bits << 3)
+            | if value & (1 << bit) != 0 {
+                0b110
+            } else {
+                0b100
+            };
+    }
+    buffer.extend_from_slice(&bits.to_be_bytes()[1..]);
+}
@@ -0,0 +1,119 @@
+#![cfg(feature = "native")]
+
+use f1_led_circuit_master_simulation::config::{ApiConfig, Secret, API_TOKEN_ENV};
+use f1_led_circuit_master_simulation::data::fetch_session;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, Once};
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const PROXY_KEY: &str = "proxy-k3y";
+const CONFIG_TOKEN: &str = "t0ken-from-config";
+const ENV_TOKEN: &str = "t0ken-from-env";
+
+// Everything logged while the tests run, at every level and from every crate
+static LOGGED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+struct Capture;
+
+impl log::Log for Capture {
+    fn enabled(&self, _: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        let line = format!("{} {}", record.target(), record.args());
+        LOGGED.lock().unwrap().push(line);
+    }
+
+    fn flush(&self) {}
+}
+
+fn capture_log() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        log::set_logger(&Capture).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+    });
+}
+
+fn api(server: &MockServer) -> ApiConfig {
+    ApiConfig {
+        base_url: server.uri(),
+        timeout_secs: 5,
+        retries: 2,
+        retry_delay_ms: 1,
+        headers: BTreeMap::from([("x-api-key".to_string(), Secret::new(PROXY_KEY))]),
+        bearer_token: Some(Secret::new(CONFIG_TOKEN)),
+        ..ApiConfig::default()
+    }
+}
+
+// Fails the first request with a 500, then answers; requests without the headers get a 404
+async fn mock_sessions(server: &MockServer, token: &str) {
+    let authorization = format!("Bearer {}", token);
+    Mock::given(method("GET"))
+        .and(path("/sessions"))
+        .and(header("x-api-key", PROXY_KEY))
+        .and(header("authorization", authorization.as_str()))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/sessions"))
+        .and(header("x-api-key", PROXY_KEY))
+        .and(header("authorization", authorization.as_str()))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn sends_the_headers_on_every_attempt_without_logging_them() {
+    capture_log();
+    std::env::remove_var(API_TOKEN_ENV);
+    let server = MockServer::start().await;
+    mock_sessions(&server, CONFIG_TOKEN).await;
+
+    assert_eq!(fetch_session(&api(&server), "9149").await.unwrap(), None);
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 2);
+    for request in &requests {
+        assert_eq!(request.headers.get("x-api-key").unwrap(), PROXY_KEY);
+    }
+
+    // The token from the environment wins over the config's
+    std::env::set_var(API_TOKEN_ENV, ENV_TOKEN);
+    let server = MockServer::start().await;
+    mock_sessions(&server, ENV_TOKEN).await;
+    let result = fetch_session(&api(&server), "9149").await;
+    std::env::remove_var(API_TOKEN_ENV);
+    assert_eq!(result.unwrap(), None);
+
+    let logged = LOGGED.lock().unwrap();
+    assert!(logged.iter().any(|line| line.contains("retrying")));
+    for secret in [PROXY_KEY, CONFIG_TOKEN, ENV_TOKEN] {
+        assert!(
+            logged.iter().all(|line| !line.contains(secret)),
+            "{} was logged",
+            secret
+        );
+    }
+}
+
+#[test]
+fn redacts_the_secrets_when_printed() {
+    let api = ApiConfig {
+        headers: BTreeMap::from([("x-api-key".to_string(), Secret::new(PROXY_KEY))]),
+        bearer_token: Some(Secret::new(CONFIG_TOKEN)),
+        ..ApiConfig::default()
+    };
+    let described = api.describe();
+    assert!(described.contains("x-api-key") && described.contains("a bearer token"));
+    for printed in [described, format!("{:?}", api)] {
+        assert!(!printed.contains(PROXY_KEY) && !printed.contains(CONFIG_TOKEN));
+    }
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use common::{at, record};
+use f1_led_circuit_master_simulation::battles::{BattleConfig, BattleDetector};
+use f1_led_circuit_master_simulation::data::LapData;
+use f1_led_circuit_master_simulation::laps::RaceProgress;
+use f1_led_circuit_master_simulation::mapping::RunRace;
+use f1_led_circuit_master_simulation::space::TelemetryPoint;
+use f1_led_circuit_master_simulation::timeline::{DriverTimelines, SpeedConfig};
+
+// One sample per second; x in decimeters
+fn sample(seconds: i64, driver_number: u32, x: f64) -> RunRace {
+    RunRace {
+        point: TelemetryPoint::new(x, 0.0),
+        ..record(seconds * 1000, driver_number, 0)
+    }
+}
+
+// Driver 44 closes from 50m to 15m behind driver 1, drops back to 25m, then to 40m.
+// Driver 63 stopped far away at the start
+fn race() -> DriverTimelines {
+    let mut samples = vec![sample(0, 63, 5000.0)];
+    for (second, gap) in [(0, 500.0), (1, 150.0), (2, 250.0), (3, 400.0)] {
+        samples.push(sample(second, 1, 1000.0 + second as f64 * 500.0));
+        samples.push(sample(second, 44, 1000.0 + second as f64 * 500.0 - gap));
+    }
+    DriverTimelines::new(&samples)
+}
+
+#[test]
+fn pairs_cars_within_the_distance() {
+    let mut detector = BattleDetector::new(&BattleConfig::default());
+    let speed = SpeedConfig::default();
+
+    assert!(detector.update(&race(), at(0), None, &speed).is_empty());
+    let battles = detector.update(&race(), at(1), None, &speed).to_vec();
+
+    assert_eq!(battles.len(), 1);
+    assert_eq!(battles[0].drivers, (1, 44));
+    assert!((battles[0].distance_m - 15.0).abs() < 1e-9);
+    // 15m at the average of 50 and 85 m/s
+    assert!((battles[0].gap_secs.unwrap() - 15.0 / 67.5).abs() < 1e-9);
+    assert!(detector.is_battling(44));
+    assert!(!detector.is_battling(63));
+}
+
+#[test]
+fn keeps_a_battle_until_the_release_distance() {
+    let mut detector = BattleDetector::new(&BattleConfig::default());
+    let speed = SpeedConfig::default();
+
+    // 25m is too far to start a battle, but not to end one
+    assert!(detector.update(&race(), at(2), None, &speed).is_empty());
+    detector.update(&race(), at(1), None, &speed);
+    assert_eq!(detector.update(&race(), at(2), None, &speed).len(), 1);
+    assert!(detector.update(&race(), at(3), None, &speed).is_empty());
+}
+
+#[test]
+fn leaves_out_cars_being_lapped() {
+    let lap = |driver_number, lap_number, start_secs| LapData {
+        lap_duration: Some(90.0),
+        ..common::lap(driver_number, lap_number, start_secs)
+    };
+    // Driver 1 is a lap ahead of driver 44
+    let progress = RaceProgress::new(
+        vec![lap(1, 1, -180), lap(1, 2, -90), lap(1, 3, 0), lap(44, 2, 0)],
+        Vec::new(),
+    );
+    let mut detector = BattleDetector::new(&BattleConfig::default());
+
+    assert!(detector
+        .update(&race(), at(1), Some(&progress), &SpeedConfig::default())
+        .is_empty());
+}
+
+#[test]
+fn ignores_cars_without_recent_samples() {
+    // Driver 63 never moves, and a car parked next to them is no battle
+    let timelines = DriverTimelines::new(&[sample(0, 63, 0.0), sample(10, 1, 50.0)]);
+    let mut detector = BattleDetector::new(&BattleConfig::default());
+
+    assert!(detector
+        .update(&timelines, at(10), None, &SpeedConfig::default())
+        .is_empty());
+}
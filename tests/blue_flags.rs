@@ -0,0 +1,70 @@
+mod common;
+
+use common::{at_millis, record};
+use f1_led_circuit_master_simulation::blue_flags::{about_to_be_lapped, blue_tint, BlueFlagConfig};
+use f1_led_circuit_master_simulation::mapping::RunRace;
+use f1_led_circuit_master_simulation::simulation::Simulation;
+use f1_led_circuit_master_simulation::track_progress::TrackProgress;
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[test]
+fn flags_a_lapped_car_with_the_leader_close_behind() {
+    let mut track = TrackProgress::new(10);
+    // Driver 1 has gone round once and sits two LEDs behind 2, who hasn't
+    for led in [0, 3, 6, 9, 2, 6] {
+        track.advance(1, led);
+    }
+    track.advance(2, 0);
+    track.advance(2, 8);
+    track.advance(44, 4);
+    assert_eq!(about_to_be_lapped(&track, None, None, 2), [2]);
+    assert!(about_to_be_lapped(&track, None, None, 1).is_empty());
+
+    // Cars starting behind the line, like the back of the grid, aren't a lap up crossing it
+    let mut track = TrackProgress::new(10);
+    track.advance(1, 1);
+    track.advance(16, 8);
+    track.advance(16, 0);
+    track.advance(1, 2);
+    assert_eq!(track.laps_covered(16), Some(0.0));
+    assert!(about_to_be_lapped(&track, None, None, 3).is_empty());
+}
+
+#[test]
+fn follows_seeks_both_ways() {
+    // Round ten LEDs, driver 1 at an LED a second and driver 2 at half that
+    let run_race_data: Vec<RunRace> = (0..30)
+        .flat_map(|second: i64| {
+            [(1, second), (2, second / 2)].map(|(driver_number, leds)| {
+                record(second * 1000, driver_number, leds as usize % 10)
+            })
+        })
+        .collect();
+    let mut simulation = Simulation::new(run_race_data, 10, HashMap::new());
+    simulation.set_blue_flags(&BlueFlagConfig {
+        enabled: true,
+        leds_behind: 2,
+    });
+    simulation.start();
+
+    simulation.seek(Duration::from_millis(16_500));
+    assert_eq!(simulation.lapped(), [2]);
+    simulation.seek(Duration::from_millis(12_500));
+    assert!(simulation.lapped().is_empty());
+    simulation.seek(Duration::from_millis(16_500));
+    assert_eq!(simulation.lapped(), [2]);
+
+    simulation.set_blue_flags(&BlueFlagConfig {
+        enabled: false,
+        ..BlueFlagConfig::default()
+    });
+    assert!(simulation.lapped().is_empty());
+}
+
+#[test]
+fn pulses_between_the_color_and_blue() {
+    let white = [255, 255, 255];
+    assert_eq!(blue_tint(white, at_millis(0)), white);
+    assert_eq!(blue_tint(white, at_millis(333)), [0, 80, 255]);
+}
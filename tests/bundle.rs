@@ -0,0 +1,143 @@
+mod common;
+
+use chrono::{DateTime, Utc};
+use common::{sample, start};
+use eframe::egui;
+use f1_led_circuit_master_simulation::bundle::{read_manifest, Bundle, BUNDLE_VERSION};
+use f1_led_circuit_master_simulation::config::SessionConfig;
+use f1_led_circuit_master_simulation::data::LocationData;
+use f1_led_circuit_master_simulation::driver_info::{driver_colors, DriverInfo};
+use f1_led_circuit_master_simulation::error::AppError;
+use f1_led_circuit_master_simulation::laps::RaceProgress;
+use f1_led_circuit_master_simulation::mapping::{
+    generate_run_race_data, MappingOptions, RunRace, MAPPING_VERSION,
+};
+use f1_led_circuit_master_simulation::simulation::{Rgb, Simulation};
+use f1_led_circuit_master_simulation::space::LedPoint;
+
+// LEDs every 10 units along a line
+fn layout(count: usize, offset: f64) -> Vec<LedPoint> {
+    (0..count)
+        .map(|index| LedPoint::new(offset + index as f64 * 10.0, 0.0))
+        .collect()
+}
+
+// Two cars going opposite ways, an LED every 250 ms
+fn samples() -> Vec<LocationData> {
+    (0..10)
+        .flat_map(|step| {
+            [(1, step), (44, 9 - step)].map(|(driver_number, led)| {
+                sample(step * 250, driver_number, led as f64 * 10.0 + 1.0, 0.5)
+            })
+        })
+        .collect()
+}
+
+fn led_records(run_race_data: &[RunRace]) -> Vec<(DateTime<Utc>, u32, usize)> {
+    run_race_data
+        .iter()
+        .map(|run| (run.date, run.driver_number, run.led_index))
+        .collect()
+}
+
+fn bundle() -> Bundle {
+    let coordinates = layout(10, 0.0);
+    let (run_race_data, mapping_stats) =
+        generate_run_race_data(&samples(), &coordinates, 5.0).unwrap();
+    let driver_info = [1, 44]
+        .map(|number| DriverInfo {
+            color: egui::Color32::from_rgb(number as u8, 100, 200),
+            ..DriverInfo::placeholder(number)
+        })
+        .to_vec();
+    Bundle {
+        created: start(),
+        mapping_version: MAPPING_VERSION,
+        session: SessionConfig {
+            key: "9149".to_string(),
+            name: Some("zandvoort-2023".to_string()),
+            ..SessionConfig::default()
+        },
+        coordinates,
+        driver_info,
+        mapping_options: MappingOptions::default(),
+        run_race_data,
+        mapping_stats,
+        race_progress: Some(RaceProgress::default()),
+    }
+}
+
+fn frames(bundle: &Bundle) -> Vec<Vec<Option<Rgb>>> {
+    let mut simulation = Simulation::new(
+        bundle.run_race_data.clone(),
+        bundle.coordinates.len(),
+        driver_colors(&bundle.driver_info),
+    );
+    simulation.start();
+    (0..12)
+        .map(|_| {
+            simulation.tick(std::time::Duration::from_millis(250));
+            simulation.frame().leds.clone()
+        })
+        .collect()
+}
+
+#[test]
+fn plays_the_same_frames_after_a_round_trip() {
+    let original = bundle();
+    let mut bytes = Vec::new();
+    original.write(&mut bytes).unwrap();
+
+    assert_eq!(
+        read_manifest(bytes.as_slice()).unwrap(),
+        original.manifest()
+    );
+    let mut read = Bundle::read(bytes.as_slice()).unwrap();
+    assert_eq!(read.session.label(), "zandvoort-2023");
+    assert_eq!(
+        read.driver_info[1].color,
+        egui::Color32::from_rgb(44, 100, 200)
+    );
+    assert!(read.race_progress.is_some());
+    assert_eq!(frames(&read), frames(&original));
+
+    // Bundled for this layout already, so nothing is mapped again
+    assert!(!read.map_onto(&layout(10, 0.0)).unwrap());
+    assert_eq!(frames(&read), frames(&original));
+}
+
+#[test]
+fn maps_again_for_another_layout_or_mapping_version() {
+    // The LEDs moved along, and one more at the end
+    let mut moved = bundle();
+    let coordinates = layout(11, 3.0);
+    assert!(moved.needs_mapping(&coordinates));
+    assert!(moved.map_onto(&coordinates).unwrap());
+    assert_eq!(moved.manifest().led_count, 11);
+    let (fresh, _) = generate_run_race_data(&samples(), &coordinates, 15.0).unwrap();
+    assert_eq!(led_records(&moved.run_race_data), led_records(&fresh));
+
+    let mut outdated = bundle();
+    outdated.mapping_version = MAPPING_VERSION + 1;
+    let records = outdated.run_race_data.len();
+    assert!(outdated.map_onto(&layout(10, 0.0)).unwrap());
+    assert_eq!(outdated.mapping_version, MAPPING_VERSION);
+    assert_eq!(outdated.run_race_data.len(), records);
+    assert_eq!(frames(&outdated), frames(&bundle()));
+}
+
+#[test]
+fn rejects_other_files_and_versions() {
+    let mut bytes = Vec::new();
+    bundle().write(&mut bytes).unwrap();
+
+    let mut newer = bytes.clone();
+    newer[4..6].copy_from_slice(&(BUNDLE_VERSION + 1).to_le_bytes());
+    let Err(AppError::Decode { context }) = Bundle::read(newer.as_slice()) else {
+        panic!("read a bundle of another version");
+    };
+    assert!(context.contains("unsupported version"), "{}", context);
+
+    assert!(Bundle::read(&b"LREC\x01\x00"[..]).is_err());
+    assert!(Bundle::read(&bytes[..bytes.len() - 10]).is_err());
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use common::{at, record, start};
+use f1_led_circuit_master_simulation::cache::{
+    drivers_cache_key, load_mapping, mapping_cache_key, progress_cache_key, store_mapping,
+};
+use f1_led_circuit_master_simulation::data::TimeWindow;
+use f1_led_circuit_master_simulation::mapping::{MappingOptions, MappingStats, RunRace};
+use f1_led_circuit_master_simulation::space::{LedPoint, TelemetryPoint};
+
+fn window() -> TimeWindow {
+    TimeWindow {
+        start: Some(start()),
+        end: Some(at(2 * 3600)),
+    }
+}
+
+fn layout() -> Vec<LedPoint> {
+    vec![LedPoint::new(0.0, 0.0), LedPoint::new(10.0, 0.0)]
+}
+
+fn mapping_key(options: &MappingOptions) -> u64 {
+    mapping_cache_key("9149", &[1, 44], &window(), &layout(), options)
+}
+
+#[test]
+fn keys_stay_the_same_in_every_build() {
+    // Golden values: a change orphans every cache file, so it needs a new cache version
+    assert_eq!(drivers_cache_key("9149"), 0x7a7c_3ce8_b215_fd25);
+    assert_eq!(progress_cache_key("9149", &[1, 44]), 0x62cd_e6e4_77b4_04ea);
+    assert_eq!(
+        mapping_key(&MappingOptions::default()),
+        0xe9b1_4286_b313_b53e
+    );
+}
+
+#[test]
+fn keys_change_with_what_the_data_depends_on() {
+    let options = MappingOptions::default();
+    let key = mapping_key(&options);
+    assert_eq!(mapping_key(&options), key);
+
+    let finer = MappingOptions {
+        snap_distance_factor: options.snap_distance_factor * 2.0,
+        ..options.clone()
+    };
+    assert_ne!(mapping_key(&finer), key);
+    assert_ne!(
+        mapping_cache_key("9149", &[1], &window(), &layout(), &options),
+        key
+    );
+    assert_ne!(drivers_cache_key("9150"), drivers_cache_key("9149"));
+}
+
+#[test]
+fn stored_mappings_load_back_under_their_key() {
+    let dir = std::env::temp_dir().join(format!("f1-led-cache-{}", std::process::id()));
+    let key = mapping_key(&MappingOptions::default());
+    let run_race_data = vec![RunRace {
+        point: TelemetryPoint::new(9.0, 1.0),
+        ..record(0, 44, 1)
+    }];
+    let stats = MappingStats {
+        dropped_samples: 3,
+        ..MappingStats::default()
+    };
+
+    store_mapping(&dir, key, &run_race_data, &stats).unwrap();
+    let (loaded, loaded_stats) = load_mapping(&dir, key).unwrap();
+    assert_eq!(loaded.len(), 1);
+    assert_eq!(loaded[0].date, start());
+    assert_eq!(loaded[0].driver_number, 44);
+    assert_eq!(loaded[0].led_index, 1);
+    assert_eq!(loaded_stats.dropped_samples, 3);
+    assert!(load_mapping(&dir, key ^ 1).is_none());
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,71 @@
+use f1_led_circuit_master_simulation::camera::{ease, FollowCamera, MAX_ZOOM};
+use f1_led_circuit_master_simulation::space::LedPoint;
+use f1_led_circuit_master_simulation::viewport::{Bounds, Camera};
+
+fn bounds() -> Bounds {
+    Bounds::from_coordinates(&[LedPoint::new(0.0, 0.0), LedPoint::new(100.0, 50.0)])
+}
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+#[test]
+fn eases_the_same_at_any_frame_rate() {
+    let start = Camera::full(&bounds());
+    let target = Camera {
+        center: LedPoint::new(90.0, 10.0),
+        zoom: 5.0,
+    };
+
+    let once = ease(start, target, 0.2);
+    let mut stepped = start;
+    for _ in 0..20 {
+        stepped = ease(stepped, target, 0.01);
+    }
+    assert!(close(once.center.x, stepped.center.x));
+    assert!(close(once.zoom, stepped.zoom));
+    // Partway there, not snapped
+    assert!(once.center.x > 50.0 && once.center.x < 90.0);
+    assert_eq!(ease(start, target, 0.0), start);
+}
+
+#[test]
+fn follows_until_panned_by_hand() {
+    let bounds = bounds();
+    let mut camera = FollowCamera::new(&bounds);
+    let car = LedPoint::new(90.0, 10.0);
+
+    // Not following, the view stays put
+    camera.update(Some(car), &bounds, 1.0);
+    assert_eq!(camera.camera(), Camera::full(&bounds));
+
+    camera.set_following(true);
+    for _ in 0..100 {
+        camera.update(Some(car), &bounds, 0.1);
+    }
+    assert!(close(camera.camera().center.x, 90.0));
+    assert!(close(camera.camera().zoom, camera.follow_zoom));
+
+    camera.pan(-5.0, 0.0);
+    assert!(!camera.is_following());
+    camera.update(Some(LedPoint::new(10.0, 10.0)), &bounds, 1.0);
+    assert!(close(camera.camera().center.x, 85.0));
+}
+
+#[test]
+fn eases_back_out_without_the_driver() {
+    let bounds = bounds();
+    let mut camera = FollowCamera::new(&bounds);
+    camera.set_following(true);
+    camera.update(Some(LedPoint::new(90.0, 10.0)), &bounds, 10.0);
+
+    camera.update(None, &bounds, 0.1);
+    assert!(camera.camera().zoom > 1.0);
+    camera.update(None, &bounds, 10.0);
+    assert!(close(camera.camera().zoom, 1.0));
+    assert!(close(camera.camera().center.x, 50.0));
+
+    camera.zoom_by(100.0);
+    assert_eq!(camera.follow_zoom, MAX_ZOOM);
+}
@@ -0,0 +1,83 @@
+#![cfg(feature = "native")]
+
+use f1_led_circuit_master_simulation::cli::{CliArgs, Command, DataFormat};
+use std::path::PathBuf;
+
+fn parse(args: &[&str]) -> Result<CliArgs, clap::Error> {
+    CliArgs::parse(args.iter().map(|arg| arg.to_string()))
+}
+
+#[test]
+fn simulates_without_a_command() {
+    let args = parse(&["--session", "9158", "--headless"]).unwrap();
+    assert_eq!(args.command, Command::Simulate);
+    assert_eq!(args.session_key.as_deref(), Some("9158"));
+    assert!(args.headless);
+    assert_eq!(
+        parse(&["simulate", "--headless"]).unwrap().command,
+        Command::Simulate
+    );
+}
+
+#[test]
+fn takes_shared_options_on_either_side_of_the_command() {
+    let args = parse(&["--config", "track.toml", "fetch", "--session", "9158", "-v"]).unwrap();
+    assert_eq!(args.command, Command::Fetch);
+    assert_eq!(args.config, Some(PathBuf::from("track.toml")));
+    assert_eq!(args.session_key.as_deref(), Some("9158"));
+    assert!(args.verbose);
+
+    let args = parse(&["map", "--bundle", "old.f1led", "--output", "new.f1led"]).unwrap();
+    assert_eq!(args.command, Command::Map);
+    assert_eq!(args.output, Some(PathBuf::from("new.f1led")));
+}
+
+#[test]
+fn export_defaults_to_parquet() {
+    assert_eq!(
+        parse(&["export"]).unwrap().command,
+        Command::Export(DataFormat::Parquet)
+    );
+    let args = parse(&["export", "--format", "gif", "--bundle", "race.f1led"]).unwrap();
+    assert_eq!(args.command, Command::Export(DataFormat::Gif));
+    assert!(parse(&["export", "--format", "mp4"]).is_err());
+}
+
+#[test]
+fn rejects_options_of_other_commands() {
+    for args in [
+        &["fetch", "--headless"][..],
+        &["map", "--play", "race.ledrec"],
+        &["export", "--tui"],
+        &["fetch", "--bundle", "race.f1led"],
+        &["fetch", "--output", "race.f1led"],
+        &["map", "--format", "csv"],
+        &["--output", "race.parquet"],
+        &["fetch", "map"],
+        &["export", "--check"],
+    ] {
+        assert!(parse(args).is_err(), "{:?} parsed", args);
+    }
+}
+
+#[test]
+fn loads_a_snapshot_instead_of_a_session() {
+    let args = parse(&["--load-snapshot", "snapshot.json", "--headless"]).unwrap();
+    assert_eq!(args.load_snapshot, Some(PathBuf::from("snapshot.json")));
+    for args in [
+        &["--load-snapshot", "snapshot.json", "--session", "9158"][..],
+        &["--load-snapshot", "snapshot.json", "--play", "race.ledrec"],
+        &["map", "--load-snapshot", "snapshot.json"],
+    ] {
+        assert!(parse(args).is_err(), "{:?} parsed", args);
+    }
+}
+
+#[test]
+fn asks_for_help_on_stdout() {
+    let err = parse(&["--help"]).unwrap_err();
+    assert!(!err.use_stderr());
+    assert!(err.to_string().contains("--test-pattern"));
+    let err = parse(&["--tui", "--test-pattern", "red"]).unwrap_err();
+    assert!(err.use_stderr());
+}
@@ -0,0 +1,125 @@
+use eframe::egui::Color32;
+use f1_led_circuit_master_simulation::color::{self, parse_hex, to_hex, ColorParseError};
+use serde::{Deserialize, Serialize};
+
+#[test]
+fn parses_with_or_without_the_hash_in_any_case() {
+    let orange = Color32::from_rgb(255, 128, 0);
+    for text in ["FF8000", "#FF8000", "ff8000", "#fF8000", " #ff8000\n"] {
+        assert_eq!(parse_hex(text), Ok(orange), "{:?}", text);
+    }
+    // The drivers endpoint's team colors come without the hash
+    assert_eq!(parse_hex("3671C6"), Ok(Color32::from_rgb(0x36, 0x71, 0xC6)));
+    assert_eq!(parse_hex("#000000"), Ok(Color32::BLACK));
+}
+
+#[test]
+fn reads_the_alpha_after_the_color() {
+    assert_eq!(parse_hex("#FF8000FF"), Ok(Color32::from_rgb(255, 128, 0)));
+    assert_eq!(parse_hex("#FF800000"), Ok(Color32::TRANSPARENT));
+    let translucent = parse_hex("#ffffff80").unwrap();
+    assert_eq!(translucent.a(), 0x80);
+    assert_eq!(
+        translucent,
+        Color32::from_rgba_unmultiplied(255, 255, 255, 0x80)
+    );
+}
+
+#[test]
+fn rejects_bad_lengths_and_digits() {
+    for (text, digits) in [
+        ("", 0),
+        ("#", 0),
+        ("#FF800", 5),
+        ("FF80000", 7),
+        ("#FF8000FF0", 9),
+    ] {
+        assert_eq!(
+            parse_hex(text),
+            Err(ColorParseError::Length {
+                text: text.to_string(),
+                digits
+            })
+        );
+    }
+    assert_eq!(
+        parse_hex("#GG8000"),
+        Err(ColorParseError::Digit {
+            text: "#GG8000".to_string(),
+            digit: 'G'
+        })
+    );
+    // Not a color name, and not a hash in the middle
+    assert!(matches!(
+        parse_hex("orange"),
+        Err(ColorParseError::Digit { digit: 'o', .. })
+    ));
+    assert!(matches!(
+        parse_hex("FF#8000"),
+        Err(ColorParseError::Digit { digit: '#', .. })
+    ));
+    assert_eq!(
+        parse_hex("#FF800").unwrap_err().to_string(),
+        "\"#FF800\" has 5 digits, not 6 or 8 like \"#FF8000\""
+    );
+}
+
+#[test]
+fn writes_capitals_and_the_alpha_only_when_translucent() {
+    assert_eq!(to_hex(Color32::from_rgb(255, 128, 0)), "#FF8000");
+    assert_eq!(to_hex(Color32::from_rgb(0x0a, 0, 0xbe)), "#0A00BE");
+    assert_eq!(to_hex(Color32::TRANSPARENT), "#00000000");
+    assert_eq!(to_hex(parse_hex("#FFFFFF80").unwrap()), "#FFFFFF80");
+}
+
+#[test]
+fn round_trips_through_text() {
+    for text in [
+        "#FF8000",
+        "#000000",
+        "#FFFFFF",
+        "#3671C6",
+        "#0A00BE",
+        "#FFFFFF80",
+    ] {
+        assert_eq!(to_hex(parse_hex(text).unwrap()), text);
+    }
+    for color in [
+        Color32::RED,
+        Color32::from_rgb(1, 2, 3),
+        Color32::TRANSPARENT,
+    ] {
+        assert_eq!(parse_hex(&to_hex(color)), Ok(color));
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Styled {
+    #[serde(with = "color::hex")]
+    color: Color32,
+    #[serde(default, with = "color::hex_option")]
+    outline: Option<Color32>,
+}
+
+#[test]
+fn serializes_color_fields_as_hex() {
+    let styled: Styled = toml::from_str("color = \"00d2be\"\noutline = \"#FFFFFF\"").unwrap();
+    assert_eq!(
+        styled,
+        Styled {
+            color: Color32::from_rgb(0, 210, 190),
+            outline: Some(Color32::WHITE),
+        }
+    );
+    assert_eq!(
+        toml::to_string(&styled).unwrap(),
+        "color = \"#00D2BE\"\noutline = \"#FFFFFF\"\n"
+    );
+
+    let plain: Styled = toml::from_str("color = \"#FF8000\"").unwrap();
+    assert_eq!(plain.outline, None);
+    assert_eq!(toml::to_string(&plain).unwrap(), "color = \"#FF8000\"\n");
+
+    let err = toml::from_str::<Styled>("color = \"orange\"").unwrap_err();
+    assert!(err.to_string().contains("\"orange\" has 'o'"), "{}", err);
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use common::{at, lap, record};
+use eframe::egui::Color32;
+use f1_led_circuit_master_simulation::color_scheme::{ColorSchemeKind, SchemeContext, SchemeData};
+use f1_led_circuit_master_simulation::data::{LapData, PositionData, StintData};
+use f1_led_circuit_master_simulation::laps::RaceProgress;
+use f1_led_circuit_master_simulation::simulation::Simulation;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+fn position(driver_number: u32, secs: i64, position: u32) -> PositionData {
+    PositionData {
+        date: at(secs),
+        driver_number,
+        position,
+    }
+}
+
+fn stint(stint_number: u32, lap_start: u32, lap_end: Option<u32>, compound: &str) -> StintData {
+    StintData {
+        driver_number: 1,
+        stint_number,
+        lap_start: Some(lap_start),
+        lap_end,
+        compound: Some(compound.to_string()),
+    }
+}
+
+// Driver 1 leads, then loses the lead to 44 after 100 seconds; they change from softs to
+// hards after two 90 second laps
+fn progress() -> RaceProgress {
+    let laps = (1..=3)
+        .map(|lap_number| LapData {
+            lap_duration: Some(90.0),
+            ..lap(1, lap_number, (lap_number as i64 - 1) * 90)
+        })
+        .collect();
+    let positions = vec![
+        position(1, 0, 1),
+        position(44, 0, 2),
+        position(1, 100, 2),
+        position(44, 100, 1),
+        position(63, 0, 4),
+        position(16, 0, 20),
+    ];
+    RaceProgress::new(laps, positions).with_stints(vec![
+        stint(2, 3, None, "HARD"),
+        stint(1, 1, Some(2), "SOFT"),
+    ])
+}
+
+fn team_colors() -> HashMap<u32, [u8; 3]> {
+    HashMap::from([(1, [0, 0, 255]), (44, [0, 210, 190])])
+}
+
+fn color(kind: ColorSchemeKind, driver: u32, progress: &RaceProgress, secs: i64) -> Color32 {
+    let team_colors = team_colors();
+    let context = SchemeContext {
+        team_colors: &team_colors,
+        progress: Some(progress),
+        time: Some(at(secs)),
+    };
+    kind.scheme().color_for(driver, &context)
+}
+
+#[test]
+fn colors_by_running_position() {
+    let progress = progress();
+    let leader = color(ColorSchemeKind::Position, 1, &progress, 10);
+    assert_eq!(color(ColorSchemeKind::Position, 44, &progress, 110), leader);
+    assert_eq!(
+        color(ColorSchemeKind::Position, 1, &progress, 110),
+        color(ColorSchemeKind::Position, 44, &progress, 10)
+    );
+    assert_ne!(color(ColorSchemeKind::Position, 63, &progress, 10), leader);
+    assert_ne!(
+        color(ColorSchemeKind::Position, 63, &progress, 10),
+        color(ColorSchemeKind::Position, 16, &progress, 10)
+    );
+    // Without a position yet, the same neutral color for everybody
+    assert_eq!(
+        color(ColorSchemeKind::Position, 1, &progress, -10),
+        color(ColorSchemeKind::Position, 55, &progress, 10)
+    );
+
+    assert_eq!(
+        color(ColorSchemeKind::Team, 1, &progress, 10),
+        Color32::from_rgb(0, 0, 255)
+    );
+}
+
+#[test]
+fn colors_by_tyre_compound() {
+    let progress = progress();
+    assert_eq!(progress.compound_at(1, at(10)), Some("SOFT"));
+    assert_eq!(progress.compound_at(1, at(170)), Some("SOFT"));
+    assert_eq!(progress.compound_at(1, at(190)), Some("HARD"));
+    assert_eq!(progress.compound_at(44, at(190)), None);
+
+    let softs = color(ColorSchemeKind::Tyre, 1, &progress, 10);
+    assert_ne!(softs, color(ColorSchemeKind::Tyre, 1, &progress, 190));
+    assert_ne!(softs, color(ColorSchemeKind::Tyre, 44, &progress, 10));
+}
+
+#[test]
+fn the_simulation_shows_the_scheme_when_its_data_is_there() {
+    let run_race_data = vec![record(10_000, 1, 3)];
+    let mut simulation = Simulation::new(run_race_data, 10, team_colors());
+    simulation.start();
+    simulation.tick(std::time::Duration::from_millis(100));
+    simulation.set_color_scheme(ColorSchemeKind::Tyre);
+
+    // No stints, so the LEDs keep the team colors
+    assert_eq!(
+        ColorSchemeKind::Tyre.missing(&simulation.scheme_context()),
+        Some(SchemeData::Stints)
+    );
+    assert_eq!(simulation.frame().leds[3], Some([0, 0, 255]));
+
+    simulation.set_race_progress(Some(Arc::new(progress())));
+    assert_eq!(
+        ColorSchemeKind::Tyre.missing(&simulation.scheme_context()),
+        None
+    );
+    let softs = color(ColorSchemeKind::Tyre, 1, &progress(), 10);
+    assert_eq!(
+        simulation.frame().leds[3],
+        Some([softs.r(), softs.g(), softs.b()])
+    );
+    assert_eq!(
+        simulation.driver_color(1),
+        [softs.r(), softs.g(), softs.b()]
+    );
+}
@@ -0,0 +1,59 @@
+use eframe::egui::Color32;
+use f1_led_circuit_master_simulation::config::Config;
+use f1_led_circuit_master_simulation::driver_info::{
+    apply_color_overrides, color_overrides, get_driver_info,
+};
+use f1_led_circuit_master_simulation::error::AppError;
+
+#[test]
+fn overrides_roster_colors_from_the_config() {
+    let config = Config::parse(
+        r##"
+        [colors]
+        "1" = "#FF8000"
+        "44" = "00d2be"
+        "##,
+    )
+    .unwrap();
+    let overrides = color_overrides(&config.colors).unwrap();
+    assert_eq!(overrides[&1], Color32::from_rgb(255, 128, 0));
+    assert_eq!(overrides[&44], Color32::from_rgb(0, 210, 190));
+
+    let mut driver_info = get_driver_info();
+    let perez = driver_info
+        .iter()
+        .find(|driver| driver.number == 11)
+        .unwrap();
+    let perez_color = perez.color;
+    apply_color_overrides(&mut driver_info, &overrides);
+    let color = |number: u32| {
+        driver_info
+            .iter()
+            .find(|driver| driver.number == number)
+            .unwrap()
+            .color
+    };
+    assert_eq!(color(1), Color32::from_rgb(255, 128, 0));
+    assert_eq!(color(11), perez_color);
+}
+
+#[test]
+fn names_every_bad_entry() {
+    let config = Config::parse(
+        r##"
+        [colors]
+        "1" = "orange"
+        "max" = "#FF8000"
+        "4" = "#FF800"
+        "11" = "#0600EF"
+        "##,
+    )
+    .unwrap();
+    let Err(AppError::Config { reason }) = color_overrides(&config.colors) else {
+        panic!("bad colors were accepted");
+    };
+    assert!(reason.contains("colors.1:"));
+    assert!(reason.contains("colors.max:"));
+    assert!(reason.contains("colors.4:"));
+    assert!(!reason.contains("colors.11"));
+}
@@ -0,0 +1,53 @@
+// Helpers shared by the integration tests; each test crate uses only some of them
+#![allow(dead_code)]
+
+use chrono::{DateTime, Duration, Utc};
+use f1_led_circuit_master_simulation::data::{LapData, LocationData};
+use f1_led_circuit_master_simulation::mapping::RunRace;
+use f1_led_circuit_master_simulation::space::TelemetryPoint;
+
+/// When the race in the tests starts, the 2023 Dutch Grand Prix.
+pub fn start() -> DateTime<Utc> {
+    "2023-08-27T13:00:00Z".parse().unwrap()
+}
+
+/// `secs` into the race.
+pub fn at(secs: i64) -> DateTime<Utc> {
+    start() + Duration::seconds(secs)
+}
+
+/// `millis` into the race.
+pub fn at_millis(millis: i64) -> DateTime<Utc> {
+    start() + Duration::milliseconds(millis)
+}
+
+/// The driver on an LED `millis` into the race, snapped to the origin.
+pub fn record(millis: i64, driver_number: u32, led_index: usize) -> RunRace {
+    RunRace {
+        date: at_millis(millis),
+        driver_number,
+        led_index,
+        point: TelemetryPoint::new(0.0, 0.0),
+    }
+}
+
+/// A location sample of the driver `millis` into the race.
+pub fn sample(millis: i64, driver_number: u32, x: f64, y: f64) -> LocationData {
+    LocationData {
+        point: TelemetryPoint::new(x, y),
+        z: None,
+        date: at_millis(millis),
+        driver_number,
+        synthetic: false,
+    }
+}
+
+/// A lap starting `start_secs` into the race, without times or flags.
+pub fn lap(driver_number: u32, lap_number: u32, start_secs: i64) -> LapData {
+    LapData {
+        driver_number,
+        lap_number,
+        date_start: Some(at(start_secs)),
+        ..LapData::default()
+    }
+}
@@ -0,0 +1,112 @@
+#![cfg(feature = "native")]
+
+mod common;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use common::at;
+use f1_led_circuit_master_simulation::config::ApiConfig;
+use f1_led_circuit_master_simulation::coverage::{cover, uncovered, CachedLocations, Span};
+use f1_led_circuit_master_simulation::data::{fetch_driver_data_cached, LocationData};
+use serde_json::json;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn minute(minutes: i64) -> DateTime<Utc> {
+    at(minutes * 60)
+}
+
+fn span(start: i64, end: i64) -> Span {
+    Span::new(minute(start), minute(end))
+}
+
+fn sample(minutes: i64) -> LocationData {
+    common::sample(minutes * 60_000, 1, minutes as f64, 1.0)
+}
+
+#[test]
+fn finds_what_a_wider_window_lacks() {
+    let first_hour = [span(0, 60)];
+    // Forward, backward and both
+    assert_eq!(uncovered(&first_hour, span(0, 90)), [span(60, 90)]);
+    assert_eq!(uncovered(&first_hour, span(-30, 60)), [span(-30, 0)]);
+    assert_eq!(
+        uncovered(&first_hour, span(-30, 90)),
+        [span(-30, 0), span(60, 90)]
+    );
+    assert!(uncovered(&first_hour, span(10, 50)).is_empty());
+
+    // A disjoint window stays a segment of its own, until the gap is filled
+    let covered = cover(&first_hour, span(120, 150));
+    assert_eq!(covered, [span(0, 60), span(120, 150)]);
+    assert_eq!(uncovered(&covered, span(30, 140)), [span(60, 120)]);
+    assert_eq!(cover(&covered, span(60, 120)), [span(0, 150)]);
+    assert_eq!(cover(&covered, span(50, 130)), [span(0, 150)]);
+}
+
+#[test]
+fn merges_fetched_samples_once() {
+    let mut cached = CachedLocations::default();
+    cached.add(span(0, 60), &[sample(0), sample(30)]);
+    // The row on the boundary arrives with the next part too
+    cached.add(span(30, 90), &[sample(30), sample(60), sample(89)]);
+
+    assert_eq!(cached.covered(), [span(0, 90)]);
+    let dates: Vec<DateTime<Utc>> = cached
+        .samples(1, span(0, 90))
+        .iter()
+        .map(|sample| sample.date)
+        .collect();
+    assert_eq!(dates, [minute(0), minute(30), minute(60), minute(89)]);
+    // Only what's inside the window asked for, the end excluded
+    assert_eq!(cached.samples(1, span(30, 89)).len(), 2);
+}
+
+fn row(minutes: i64) -> serde_json::Value {
+    json!({
+        "driver_number": 1,
+        "date": minute(minutes).to_rfc3339_opts(SecondsFormat::Millis, true),
+        "x": 10.0 + minutes as f64,
+        "y": 20.0
+    })
+}
+
+#[tokio::test]
+async fn fetches_only_the_time_not_cached_yet() {
+    let server = MockServer::start().await;
+    let since = |minutes: i64| minute(minutes).to_rfc3339_opts(SecondsFormat::Millis, true);
+    for (start, rows) in [(0, vec![row(0), row(30)]), (60, vec![row(60)])] {
+        Mock::given(method("GET"))
+            .and(path("/location"))
+            .and(query_param("date>", since(start)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!(rows)))
+            .mount(&server)
+            .await;
+    }
+    let api = ApiConfig {
+        base_url: server.uri(),
+        retries: 0,
+        ..ApiConfig::default()
+    };
+    let cache_dir = std::env::temp_dir().join(format!("f1-led-coverage-{}", std::process::id()));
+
+    let first_hour = span(0, 60).window();
+    let (per_driver, _) = fetch_driver_data_cached(&api, "9149", &[1], &first_hour, &cache_dir)
+        .await
+        .unwrap();
+    assert_eq!(per_driver[0].1.len(), 2);
+
+    let longer = span(0, 90).window();
+    let (per_driver, _) = fetch_driver_data_cached(&api, "9149", &[1], &longer, &cache_dir)
+        .await
+        .unwrap();
+    std::fs::remove_dir_all(&cache_dir).unwrap();
+
+    assert_eq!(per_driver[0].1.len(), 3);
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 2);
+    // Just the half hour the first window didn't cover
+    assert!(requests[1]
+        .url
+        .query_pairs()
+        .any(|(key, value)| key == "date>" && value == since(60)));
+}
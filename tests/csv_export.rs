@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use f1_led_circuit_master_simulation::csv_export::{write_csv, CSV_HEADER};
+use f1_led_circuit_master_simulation::mapping::RunRace;
+use f1_led_circuit_master_simulation::space::TelemetryPoint;
+
+#[test]
+fn export_writes_a_row_per_record() {
+    let date: DateTime<Utc> = "2023-08-27T13:00:00.125Z".parse().unwrap();
+    let rows = vec![
+        RunRace {
+            date,
+            driver_number: 1,
+            led_index: 3,
+            point: TelemetryPoint::new(12.5, -4.0),
+        },
+        RunRace {
+            date,
+            driver_number: 44,
+            led_index: 95,
+            point: TelemetryPoint::new(-800.25, 1200.0),
+        },
+    ];
+    let mut written = Vec::new();
+    write_csv(&rows, &mut written).unwrap();
+
+    let mut reader = csv::Reader::from_reader(written.as_slice());
+    assert_eq!(reader.headers().unwrap(), &CSV_HEADER[..]);
+    let records: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+    assert_eq!(
+        records[0],
+        vec!["2023-08-27T13:00:00.125000Z", "1", "3", "12.5", "-4"]
+    );
+    assert_eq!(
+        records[1],
+        vec!["2023-08-27T13:00:00.125000Z", "44", "95", "-800.25", "1200"]
+    );
+}
+
+#[test]
+fn export_of_nothing_is_just_the_header() {
+    let mut written = Vec::new();
+    write_csv(&[], &mut written).unwrap();
+    assert_eq!(
+        String::from_utf8(written).unwrap(),
+        "timestamp,driver_number,led_index,x,y\n"
+    );
+}
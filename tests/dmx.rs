@@ -0,0 +1,120 @@
+use f1_led_circuit_master_simulation::dmx::{split_universes, DmxConfig, DmxProtocol, DmxSink};
+use f1_led_circuit_master_simulation::error::AppError;
+use f1_led_circuit_master_simulation::simulation::Rgb;
+use std::net::UdpSocket;
+use std::time::Duration;
+
+fn receiver() -> UdpSocket {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .unwrap();
+    socket
+}
+
+fn config(protocol: DmxProtocol, receiver: &UdpSocket) -> DmxConfig {
+    DmxConfig {
+        enabled: true,
+        protocol,
+        target: Some(receiver.local_addr().unwrap().to_string()),
+        universe: 3,
+        start_address: 1,
+        ..DmxConfig::default()
+    }
+}
+
+const FRAME: [Rgb; 3] = [[255, 0, 0], [0, 0, 0], [1, 2, 3]];
+
+#[test]
+fn sends_artnet_dmx_packet() {
+    let receiver = receiver();
+    let mut sink = DmxSink::open(&config(DmxProtocol::ArtNet, &receiver)).unwrap();
+
+    sink.show(&FRAME).unwrap();
+
+    let mut packet = [0u8; 1024];
+    let len = receiver.recv(&mut packet).unwrap();
+    let packet = &packet[..len];
+    assert_eq!(&packet[..8], b"Art-Net\0");
+    assert_eq!(&packet[8..10], &[0x00, 0x50]); // OpDmx, little endian
+    assert_eq!(&packet[10..12], &[0, 14]); // Protocol version
+    assert_eq!(packet[12], 1); // First sequence number
+    assert_eq!(&packet[14..16], &[3, 0]); // Universe
+    assert_eq!(&packet[16..18], &[0, 10]); // 9 channels padded to an even length
+    assert_eq!(&packet[18..], &[255, 0, 0, 0, 0, 0, 1, 2, 3, 0]);
+}
+
+#[test]
+fn sends_sacn_data_packet() {
+    let receiver = receiver();
+    let mut sink = DmxSink::open(&config(DmxProtocol::Sacn, &receiver)).unwrap();
+
+    sink.show(&FRAME).unwrap();
+
+    let mut packet = [0u8; 1024];
+    let len = receiver.recv(&mut packet).unwrap();
+    let packet = &packet[..len];
+    assert_eq!(len, 126 + 10);
+    assert_eq!(&packet[4..16], b"ASC-E1.17\0\0\0");
+    assert_eq!(
+        u16::from_be_bytes([packet[16], packet[17]]),
+        0x7000 | (len as u16 - 16)
+    );
+    assert_eq!(&packet[44..58], b"F1-LED-CIRCUIT");
+    assert_eq!(packet[108], 100); // Priority
+    assert_eq!(packet[111], 1); // Sequence number
+    assert_eq!(&packet[113..115], &[0, 3]); // Universe
+    assert_eq!(&packet[123..125], &[0, 11]); // Start code plus 10 slots
+    assert_eq!(packet[125], 0); // DMX start code
+    assert_eq!(&packet[126..], &[255, 0, 0, 0, 0, 0, 1, 2, 3, 0]);
+}
+
+#[test]
+fn spills_pixels_into_the_next_universe() {
+    let channels = vec![7u8; 171 * 3];
+
+    let universes = split_universes(&channels, 1);
+
+    assert_eq!(universes.len(), 2);
+    assert_eq!(universes[0].len(), 510);
+    assert_eq!(universes[1], [7, 7, 7, 0]);
+}
+
+#[test]
+fn opens_targets_with_and_without_a_port() {
+    let open = |target: &str| {
+        DmxSink::open(&DmxConfig {
+            target: Some(target.to_string()),
+            ..DmxConfig::default()
+        })
+    };
+    for target in [
+        "127.0.0.1",
+        "127.0.0.1:6455",
+        "::1",
+        "[::1]:6455",
+        "localhost",
+    ] {
+        assert!(open(target).is_ok(), "{}", target);
+    }
+    assert!(matches!(
+        open("no such host.invalid"),
+        Err(AppError::Config { .. })
+    ));
+}
+
+#[test]
+fn refuses_frames_past_the_last_universe() {
+    let receiver = receiver();
+    let mut sink = DmxSink::open(&DmxConfig {
+        universe: u16::MAX,
+        ..config(DmxProtocol::ArtNet, &receiver)
+    })
+    .unwrap();
+
+    sink.show(&FRAME).unwrap();
+    assert!(matches!(
+        sink.show(&[[1, 2, 3]; 171]),
+        Err(AppError::Config { .. })
+    ));
+}
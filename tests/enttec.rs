@@ -0,0 +1,59 @@
+#![cfg(feature = "native")]
+
+use f1_led_circuit_master_simulation::enttec::{
+    dmx_channels, enttec_packet, patch, EnttecConfig, DMX_CHANNELS,
+};
+use f1_led_circuit_master_simulation::simulation::Rgb;
+
+const FRAME: [Rgb; 3] = [[255, 0, 0], [0, 0, 0], [1, 2, 3]];
+
+#[test]
+fn encodes_output_only_send_dmx_message() {
+    let config = EnttecConfig {
+        start_channel: 4,
+        ..EnttecConfig::default()
+    };
+    let packet = enttec_packet(&dmx_channels(&FRAME, &patch(&config, 3).unwrap()));
+
+    assert_eq!(packet.len(), 518);
+    // Start of message, label 6, data length 513 little endian, DMX start code
+    assert_eq!(&packet[..5], &[0x7e, 0x06, 0x01, 0x02, 0x00]);
+    assert_eq!(
+        &packet[5..17],
+        &[0, 0, 0, 255, 0, 0, 0, 0, 0, 1, 2, 3] // Channels 1-12
+    );
+    assert!(packet[17..517].iter().all(|&value| value == 0));
+    assert_eq!(packet[517], 0xe7);
+}
+
+#[test]
+fn places_leds_at_patched_channels() {
+    let config = EnttecConfig {
+        patch: Some(vec![10, 1, 510]),
+        ..EnttecConfig::default()
+    };
+    let channels = dmx_channels(&FRAME, &patch(&config, 3).unwrap());
+
+    assert_eq!(&channels[9..12], &[255, 0, 0]);
+    assert_eq!(&channels[0..3], &[0, 0, 0]);
+    assert_eq!(&channels[509..512], &[1, 2, 3]);
+}
+
+#[test]
+fn rejects_layouts_past_one_universe() {
+    let config = EnttecConfig::default();
+    assert!(patch(&config, DMX_CHANNELS / 3).is_ok());
+    assert!(patch(&config, DMX_CHANNELS / 3 + 1).is_err());
+
+    let offset = EnttecConfig {
+        start_channel: 300,
+        ..EnttecConfig::default()
+    };
+    assert!(patch(&offset, 96).is_err());
+
+    let patched = EnttecConfig {
+        patch: Some(vec![1, 511]),
+        ..EnttecConfig::default()
+    };
+    assert!(patch(&patched, 2).is_err());
+}
@@ -0,0 +1,279 @@
+mod common;
+
+use common::record;
+use f1_led_circuit_master_simulation::export::{
+    ffmpeg_available, save_screenshot, screenshot_name, ExportFormat, ExportJob, ExportOptions,
+    ExportOutcome,
+};
+use f1_led_circuit_master_simulation::render::{format_duration, format_race_time, FrameRenderer};
+use f1_led_circuit_master_simulation::simulation::{Rgb, Simulation};
+use f1_led_circuit_master_simulation::space::LedPoint;
+use f1_led_circuit_master_simulation::viewport::{Bounds, Camera};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const RED: Rgb = [255, 0, 0];
+
+// Four LEDs on the corners of a square
+fn coordinates() -> Vec<LedPoint> {
+    [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]
+        .into_iter()
+        .map(|(x, y)| LedPoint::new(x, y))
+        .collect()
+}
+
+// One driver going round the square, one LED per second
+fn lap() -> Simulation {
+    let run_race_data = (0..8)
+        .map(|second| record(second * 1000, 1, second as usize % 4))
+        .collect();
+    Simulation::new(run_race_data, 4, HashMap::from([(1, RED)]))
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("{}-{}", std::process::id(), name))
+}
+
+#[test]
+fn renders_leds_at_their_layout_position() {
+    let renderer = FrameRenderer::new(&coordinates(), 200, 100, 4.0);
+    let image = renderer.render(&[RED, [0, 0, 0], [0, 0, 0], [0, 0, 255]], None);
+
+    assert_eq!(image.dimensions(), (200, 100));
+    // Larger y values are drawn higher up, inside the 30 pixel margin
+    assert_eq!(image.get_pixel(31, 71).0, [255, 0, 0, 255]);
+    assert_eq!(image.get_pixel(31, 31).0, [0, 0, 255, 255]);
+    assert_eq!(image.get_pixel(171, 71).0, [0, 0, 0, 255]);
+    assert_ne!(image.get_pixel(100, 50).0, [255, 0, 0, 255]);
+}
+
+#[test]
+fn renders_through_the_camera() {
+    let coordinates = coordinates();
+    let bounds = Bounds::from_coordinates(&coordinates);
+    let colors = [RED, [0, 0, 0], [0, 0, 0], [0, 0, 255]];
+
+    let full = FrameRenderer::with_camera(&coordinates, 200, 100, 4.0, Camera::full(&bounds));
+    assert_eq!(
+        full.render(&colors, None),
+        FrameRenderer::new(&coordinates, 200, 100, 4.0).render(&colors, None)
+    );
+
+    // Zoomed in on the first LED, it moves to the middle and the last one leaves the picture
+    let zoomed = FrameRenderer::with_camera(
+        &coordinates,
+        200,
+        100,
+        4.0,
+        Camera {
+            center: coordinates[0],
+            zoom: 2.0,
+        },
+    );
+    let image = zoomed.render(&colors, None);
+    assert_eq!(image.get_pixel(101, 51).0, [255, 0, 0, 255]);
+    assert_ne!(image.get_pixel(31, 71).0, [255, 0, 0, 255]);
+    assert!(!image.pixels().any(|pixel| pixel.0 == [0, 0, 255, 255]));
+}
+
+#[test]
+fn burns_in_the_race_clock() {
+    let renderer = FrameRenderer::new(&coordinates(), 200, 100, 4.0);
+    let plain = renderer.render(&[], None);
+    let with_clock = renderer.render(&[], Some(3725.5));
+
+    assert_eq!(format_race_time(3725.5), "01:02:05.50");
+    assert_ne!(plain, with_clock);
+}
+
+#[test]
+fn formats_durations_in_whole_seconds() {
+    assert_eq!(format_duration(2533.9), "00:42:13");
+    assert_eq!(format_duration(5397.0), "01:29:57");
+    assert_eq!(format_duration(-3.0), "00:00:00");
+    assert_eq!(format_duration(360_000.0), "100:00:00");
+}
+
+#[test]
+fn exports_a_gif_clip() {
+    let path = temp_path("clip.gif");
+    let options = ExportOptions {
+        format: ExportFormat::Gif,
+        start: Duration::from_secs(1),
+        length: Duration::from_secs(2),
+        speed: 2.0,
+        width: 64,
+        height: 48,
+        fps: 10,
+        led_size: 3.0,
+        race_clock: true,
+        labels: true,
+        path: path.clone(),
+    };
+    let job = ExportJob::start(&lap(), &coordinates(), &[], 1.0, options).unwrap();
+
+    assert_eq!(job.finish().unwrap(), ExportOutcome::Written(path.clone()));
+    let mut decoder = gif::DecodeOptions::new()
+        .read_info(File::open(&path).unwrap())
+        .unwrap();
+    assert_eq!((decoder.width(), decoder.height()), (64, 48));
+    let mut frames = 0;
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        assert_eq!(frame.delay, 10);
+        frames += 1;
+    }
+    assert_eq!(frames, 20);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn draws_driver_numbers_next_to_their_led() {
+    let renderer = FrameRenderer::new(&coordinates(), 200, 100, 4.0);
+    let mut image = renderer.render(&[], None);
+    renderer.draw_labels(&mut image, &[(1, 0)]);
+
+    // The "1" starts one pixel right of LED 0's square
+    assert_eq!(image.get_pixel(36, 70).0, [255, 255, 255, 255]);
+    assert_eq!(image.get_pixel(36, 74).0, [255, 255, 255, 255]);
+}
+
+#[test]
+fn exports_numbered_png_frames() {
+    let directory = temp_path("frames");
+    let options = ExportOptions {
+        format: ExportFormat::PngSequence,
+        length: Duration::from_secs(1),
+        width: 40,
+        height: 30,
+        fps: 5,
+        path: directory.clone(),
+        ..ExportOptions::default()
+    };
+    let job = ExportJob::start(&lap(), &coordinates(), &[], 1.0, options).unwrap();
+
+    assert_eq!(
+        job.finish().unwrap(),
+        ExportOutcome::Written(directory.clone())
+    );
+    let mut names: Vec<_> = std::fs::read_dir(&directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "frame_000000.png",
+            "frame_000001.png",
+            "frame_000002.png",
+            "frame_000003.png",
+            "frame_000004.png"
+        ]
+    );
+    let first = image::open(directory.join("frame_000000.png")).unwrap();
+    assert_eq!((first.width(), first.height()), (40, 30));
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn exports_a_video_when_ffmpeg_is_installed() {
+    if !ffmpeg_available() {
+        return;
+    }
+    let path = temp_path("clip.mp4");
+    let options = ExportOptions {
+        format: ExportFormat::Video,
+        length: Duration::from_secs(1),
+        width: 64,
+        height: 48,
+        path: path.clone(),
+        ..ExportOptions::default()
+    };
+    let job = ExportJob::start(&lap(), &coordinates(), &[], 1.0, options).unwrap();
+
+    assert_eq!(job.finish().unwrap(), ExportOutcome::Written(path.clone()));
+    assert!(std::fs::metadata(&path).unwrap().len() > 0);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn cancelling_removes_the_partial_file() {
+    let path = temp_path("cancelled.gif");
+    let options = ExportOptions {
+        length: Duration::from_secs(600),
+        width: 320,
+        height: 240,
+        path: path.clone(),
+        ..ExportOptions::default()
+    };
+    let job = ExportJob::start(&lap(), &coordinates(), &[], 1.0, options).unwrap();
+    job.cancel();
+
+    assert_eq!(job.finish().unwrap(), ExportOutcome::Cancelled);
+    assert!(!path.exists());
+}
+
+#[test]
+fn rejects_an_empty_clip() {
+    let options = ExportOptions {
+        length: Duration::ZERO,
+        path: temp_path("empty.gif"),
+        ..ExportOptions::default()
+    };
+
+    assert!(ExportJob::start(&lap(), &coordinates(), &[], 1.0, options).is_err());
+}
+
+#[test]
+fn rejects_an_odd_video_size() {
+    let options = ExportOptions {
+        format: ExportFormat::Video,
+        width: 641,
+        path: temp_path("odd.mp4"),
+        ..ExportOptions::default()
+    };
+
+    assert!(ExportJob::start(&lap(), &coordinates(), &[], 1.0, options).is_err());
+}
+
+#[test]
+fn names_screenshots_after_session_and_race_time() {
+    assert_eq!(
+        screenshot_name("zandvoort-2023", 4350.7),
+        "zandvoort-2023_01-12-30.700.png"
+    );
+    assert_eq!(
+        screenshot_name("Spa 2023/R", 5.0),
+        "Spa-2023-R_00-00-05.000.png"
+    );
+}
+
+#[test]
+fn saves_a_screenshot_into_a_new_directory() {
+    let directory = temp_path("screenshots");
+    let image = FrameRenderer::new(&coordinates(), 80, 60, 4.0).render(&[RED], Some(12.0));
+
+    let path = save_screenshot(&image, &directory, "monza", 12.0).unwrap();
+
+    assert_eq!(path, directory.join("monza_00-00-12.000.png"));
+    assert_eq!(image::open(&path).unwrap().to_rgba8(), image);
+
+    // A second one at the same moment doesn't overwrite the first
+    let again = save_screenshot(&image, &directory, "monza", 12.0).unwrap();
+    assert_eq!(again, directory.join("monza_00-00-12.000_2.png"));
+    assert!(path.exists());
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn reports_an_unwritable_screenshot_directory() {
+    // A directory can't be created below a file
+    let file = temp_path("not-a-directory");
+    std::fs::write(&file, b"").unwrap();
+    let image = FrameRenderer::new(&coordinates(), 80, 60, 4.0).render(&[], None);
+
+    assert!(save_screenshot(&image, &file.join("screenshots"), "monza", 0.0).is_err());
+    std::fs::remove_file(file).unwrap();
+}
@@ -0,0 +1,499 @@
+#![cfg(feature = "native")]
+
+mod common;
+
+use common::start;
+use eframe::egui::Color32;
+use f1_led_circuit_master_simulation::config::ApiConfig;
+use f1_led_circuit_master_simulation::data::{
+    fetch_car_data, fetch_data, fetch_drivers, fetch_intervals, fetch_laps, fetch_positions,
+    fetch_race_control, fetch_session, fetch_stints, SessionInfo, TimeWindow,
+};
+use f1_led_circuit_master_simulation::driver_info::{unknown_drivers, DriverInfo};
+use f1_led_circuit_master_simulation::error::AppError;
+use f1_led_circuit_master_simulation::notices;
+use serde_json::json;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn api(server: &MockServer) -> ApiConfig {
+    ApiConfig {
+        base_url: server.uri(),
+        timeout_secs: 5,
+        retries: 2,
+        retry_delay_ms: 1,
+        session_margin_secs: 60,
+        ..ApiConfig::default()
+    }
+}
+
+fn sample(driver_number: u32, date: &str, x: f64, y: f64) -> serde_json::Value {
+    json!({
+        "session_key": 9149,
+        "meeting_key": 1217,
+        "driver_number": driver_number,
+        "date": date,
+        "x": x,
+        "y": y,
+        "z": 0
+    })
+}
+
+async fn mock_driver(server: &MockServer, driver_number: u32, response: ResponseTemplate) {
+    Mock::given(method("GET"))
+        .and(path("/location"))
+        .and(query_param("session_key", "9149"))
+        .and(query_param("driver_number", driver_number.to_string()))
+        .respond_with(response)
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn merges_drivers_sorted_by_date() {
+    let server = MockServer::start().await;
+    mock_driver(
+        &server,
+        1,
+        ResponseTemplate::new(200).set_body_json(json!([
+            sample(1, "2023-08-27T12:58:56.234000+00:00", 10.0, 20.0),
+            sample(1, "2023-08-27T12:58:58.100000+00:00", 11.0, 21.0),
+            sample(1, "2023-08-27T12:58:59.000000+00:00", 0.0, 0.0),
+        ])),
+    )
+    .await;
+    mock_driver(
+        &server,
+        44,
+        ResponseTemplate::new(200).set_body_json(json!([sample(
+            44,
+            "2023-08-27T12:58:57.000000+00:00",
+            30.0,
+            40.0
+        )])),
+    )
+    .await;
+
+    let (data, stats) = fetch_data(&api(&server), "9149", &[1, 44], &TimeWindow::default())
+        .await
+        .unwrap();
+
+    // The (0, 0) placeholder sample is dropped
+    assert_eq!(data.len(), 3);
+    assert_eq!(stats.fetched_per_driver[&1], 3);
+    assert_eq!(stats.fetched_per_driver[&44], 1);
+    assert_eq!(stats.zero_coordinate_samples, 1);
+    assert!(data.windows(2).all(|pair| pair[0].date <= pair[1].date));
+    let drivers: Vec<u32> = data.iter().map(|d| d.driver_number).collect();
+    assert_eq!(drivers, [1, 44, 1]);
+    assert!(data.iter().all(|sample| sample.z == Some(0.0)));
+}
+
+#[tokio::test]
+async fn retries_server_errors() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/location"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    mock_driver(
+        &server,
+        1,
+        ResponseTemplate::new(200).set_body_json(json!([sample(
+            1,
+            "2023-08-27T12:58:56.234000+00:00",
+            10.0,
+            20.0
+        )])),
+    )
+    .await;
+
+    let (data, _) = fetch_data(&api(&server), "9149", &[1], &TimeWindow::default())
+        .await
+        .unwrap();
+
+    assert_eq!(data.len(), 1);
+}
+
+#[tokio::test]
+async fn skips_missing_driver() {
+    let server = MockServer::start().await;
+    mock_driver(
+        &server,
+        1,
+        ResponseTemplate::new(200).set_body_json(json!([sample(
+            1,
+            "2023-08-27T12:58:56.234000+00:00",
+            10.0,
+            20.0
+        )])),
+    )
+    .await;
+    // A 404 is not retried
+    Mock::given(method("GET"))
+        .and(path("/location"))
+        .and(query_param("driver_number", "99"))
+        .respond_with(ResponseTemplate::new(404))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let (data, _) = fetch_data(&api(&server), "9149", &[1, 99], &TimeWindow::default())
+        .await
+        .unwrap();
+
+    assert_eq!(data.len(), 1);
+    assert_eq!(data[0].driver_number, 1);
+    // The window hears about the missing driver
+    assert!(notices::take()
+        .iter()
+        .any(|message| message.contains("404 Not Found for driver 99")));
+}
+
+#[tokio::test]
+async fn accepts_naive_timestamps_as_utc() {
+    let server = MockServer::start().await;
+    mock_driver(
+        &server,
+        1,
+        ResponseTemplate::new(200).set_body_json(json!([
+            sample(1, "2023-08-27T12:58:57", 10.0, 20.0),
+            sample(1, "2023-08-27T12:58:56.5", 11.0, 21.0),
+        ])),
+    )
+    .await;
+
+    let (data, _) = fetch_data(&api(&server), "9149", &[1], &TimeWindow::default())
+        .await
+        .unwrap();
+
+    assert_eq!(data.len(), 2);
+    assert_eq!(data[0].date.to_rfc3339(), "2023-08-27T12:58:56.500+00:00");
+    assert_eq!(data[1].date.to_rfc3339(), "2023-08-27T12:58:57+00:00");
+}
+
+#[tokio::test]
+async fn empty_response_is_empty_data() {
+    let server = MockServer::start().await;
+    mock_driver(
+        &server,
+        1,
+        ResponseTemplate::new(200).set_body_json(json!([])),
+    )
+    .await;
+
+    let err = fetch_data(&api(&server), "9149", &[1], &TimeWindow::default())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, AppError::EmptyData { ref drivers } if drivers == &[1]));
+    assert_eq!(err.exit_code(), 5);
+}
+
+#[tokio::test]
+async fn fetches_laps_with_a_missing_start() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/laps"))
+        .and(query_param("driver_number", "1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {"driver_number": 1, "lap_number": 2, "date_start": "2023-08-27T13:05:01.500000+00:00", "lap_duration": 75.2},
+            {"driver_number": 1, "lap_number": 1, "date_start": null, "lap_duration": null},
+        ])))
+        .mount(&server)
+        .await;
+
+    let laps = fetch_laps(&api(&server), "9149", &[1]).await.unwrap();
+
+    assert_eq!(laps.len(), 2);
+    assert_eq!((laps[0].lap_number, laps[0].date_start), (1, None));
+    assert_eq!(laps[1].lap_number, 2);
+    assert_eq!(
+        laps[1].date_start.unwrap().to_rfc3339(),
+        "2023-08-27T13:05:01.500+00:00"
+    );
+    assert_eq!(laps[1].lap_duration, Some(75.2));
+}
+
+#[tokio::test]
+async fn fetches_positions_sorted_by_driver_and_date() {
+    let server = MockServer::start().await;
+    for (driver_number, body) in [
+        (
+            44,
+            json!([{"driver_number": 44, "date": "2023-08-27T13:00:00+00:00", "position": 2}]),
+        ),
+        (
+            1,
+            json!([
+                {"driver_number": 1, "date": "2023-08-27T13:10:00+00:00", "position": 1},
+                {"driver_number": 1, "date": "2023-08-27T13:00:00+00:00", "position": 3},
+            ]),
+        ),
+    ] {
+        Mock::given(method("GET"))
+            .and(path("/position"))
+            .and(query_param("driver_number", driver_number.to_string()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(&server)
+            .await;
+    }
+
+    let positions = fetch_positions(&api(&server), "9149", &[44, 1])
+        .await
+        .unwrap();
+
+    let summary: Vec<(u32, u32)> = positions
+        .iter()
+        .map(|position| (position.driver_number, position.position))
+        .collect();
+    assert_eq!(summary, [(1, 3), (1, 1), (44, 2)]);
+}
+
+#[tokio::test]
+async fn fetches_intervals_without_the_gaps_of_lapped_cars() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/intervals"))
+        .and(query_param("driver_number", "1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {"driver_number": 1, "date": "2023-08-27T14:00:00+00:00", "gap_to_leader": "+1 LAP"},
+            {"driver_number": 1, "date": "2023-08-27T13:10:00+00:00", "gap_to_leader": 4.25},
+            {"driver_number": 1, "date": "2023-08-27T13:00:00+00:00", "gap_to_leader": null},
+        ])))
+        .mount(&server)
+        .await;
+
+    let intervals = fetch_intervals(&api(&server), "9149", &[1]).await.unwrap();
+
+    let gaps: Vec<Option<f64>> = intervals
+        .iter()
+        .map(|interval| interval.gap_to_leader)
+        .collect();
+    assert_eq!(gaps, [None, Some(4.25), None]);
+}
+
+#[tokio::test]
+async fn fetches_stints_in_order() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/stints"))
+        .and(query_param("driver_number", "1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {"driver_number": 1, "stint_number": 2, "lap_start": 24, "lap_end": null, "compound": "HARD", "tyre_age_at_start": 0},
+            {"driver_number": 1, "stint_number": 1, "lap_start": 1, "lap_end": 23, "compound": "MEDIUM", "tyre_age_at_start": 3},
+        ])))
+        .mount(&server)
+        .await;
+
+    let stints = fetch_stints(&api(&server), "9149", &[1]).await.unwrap();
+
+    let summary: Vec<(u32, Option<u32>, Option<&str>)> = stints
+        .iter()
+        .map(|stint| (stint.stint_number, stint.lap_end, stint.compound.as_deref()))
+        .collect();
+    assert_eq!(
+        summary,
+        [(1, Some(23), Some("MEDIUM")), (2, None, Some("HARD"))]
+    );
+}
+
+#[tokio::test]
+async fn fetches_car_data_within_the_window() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/car_data"))
+        .and(query_param("driver_number", "1"))
+        .and(query_param("date>", "2023-08-27T13:00:00.000Z"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {"driver_number": 1, "date": "2023-08-27T13:00:01.000Z", "throttle": 0, "brake": 100, "speed": 120, "n_gear": 3},
+            {"driver_number": 1, "date": "2023-08-27T13:00:00.500Z", "throttle": 99, "brake": 0, "speed": 280, "n_gear": 8},
+        ])))
+        .mount(&server)
+        .await;
+
+    let window = TimeWindow {
+        start: Some(start()),
+        end: None,
+    };
+    let samples = fetch_car_data(&api(&server), "9149", 1, &window)
+        .await
+        .unwrap();
+
+    let pedals: Vec<(Option<f64>, Option<f64>)> = samples
+        .iter()
+        .map(|sample| (sample.throttle, sample.brake))
+        .collect();
+    assert_eq!(pedals, [(Some(99.0), Some(0.0)), (Some(0.0), Some(100.0))]);
+    let gears: Vec<(Option<f64>, Option<u8>)> = samples
+        .iter()
+        .map(|sample| (sample.speed, sample.n_gear))
+        .collect();
+    assert_eq!(gears, [(Some(280.0), Some(8)), (Some(120.0), Some(3))]);
+}
+
+#[tokio::test]
+async fn fetches_race_control_in_order() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/race_control"))
+        .and(query_param("session_key", "9149"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {"date": "2023-08-27T13:12:00+00:00", "category": "Flag", "flag": "CLEAR", "scope": "Sector", "sector": 4, "driver_number": null, "message": "CLEAR IN TRACK SECTOR 4"},
+            {"date": "2023-08-27T13:10:00+00:00", "category": "Flag", "flag": "YELLOW", "scope": "Sector", "sector": 4, "driver_number": null, "message": "YELLOW IN TRACK SECTOR 4"},
+            {"date": "2023-08-27T13:05:00+00:00", "category": "Other", "flag": null, "scope": null, "sector": null, "driver_number": null, "message": "DRS ENABLED"},
+        ])))
+        .mount(&server)
+        .await;
+
+    let messages = fetch_race_control(&api(&server), "9149").await.unwrap();
+
+    let summary: Vec<(Option<&str>, Option<u32>)> = messages
+        .iter()
+        .map(|message| (message.flag.as_deref(), message.sector))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (None, None),
+            (Some("YELLOW"), Some(4)),
+            (Some("CLEAR"), Some(4))
+        ]
+    );
+}
+
+#[tokio::test]
+async fn fetches_when_the_session_ran() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/sessions"))
+        .and(query_param("session_key", "9149"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+            "session_key": 9149,
+            "session_name": "Race",
+            "date_start": "2023-08-27T13:00:00+00:00",
+            "date_end": "2023-08-27T15:00:00+00:00"
+        }])))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/sessions"))
+        .and(query_param("session_key", "1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+        .mount(&server)
+        .await;
+
+    let info = fetch_session(&api(&server), "9149").await.unwrap().unwrap();
+    assert_eq!(info.date_start.to_rfc3339(), "2023-08-27T13:00:00+00:00");
+    assert_eq!(info.date_end.to_rfc3339(), "2023-08-27T15:00:00+00:00");
+    assert_eq!(fetch_session(&api(&server), "1").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn blames_no_driver_for_a_missing_session() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/sessions"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+
+    let err = fetch_session(&api(&server), "9149").await.unwrap_err();
+    assert!(matches!(err, AppError::Http { driver: None, .. }));
+    assert_eq!(
+        err.user_message(),
+        "The OpenF1 API answered 404 Not Found. Check the session key or try again later."
+    );
+}
+
+#[tokio::test]
+async fn fetches_the_whole_session_without_a_window() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/sessions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+            "date_start": "2023-08-27T13:00:00+00:00",
+            "date_end": "2023-08-27T15:00:00+00:00"
+        }])))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/location"))
+        .and(query_param("date>", "2023-08-27T12:59:00.000Z"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([sample(
+            1,
+            "2023-08-27T13:00:00+00:00",
+            10.0,
+            20.0
+        )])))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let (data, stats) = fetch_data(&api(&server), "9149", &[1], &TimeWindow::default())
+        .await
+        .unwrap();
+    assert_eq!(data.len(), 1);
+    assert_eq!(
+        stats.window.end.unwrap().to_rfc3339(),
+        "2023-08-27T15:01:00+00:00"
+    );
+
+    // A bound that's set wins
+    let end = "2023-08-27T14:00:00Z".parse().unwrap();
+    let window = TimeWindow {
+        start: None,
+        end: Some(end),
+    };
+    let filled = window.within_session(
+        &SessionInfo {
+            date_start: stats.window.start.unwrap(),
+            date_end: stats.window.end.unwrap(),
+        },
+        chrono::Duration::zero(),
+    );
+    assert_eq!(filled.start, stats.window.start);
+    assert_eq!(filled.end, Some(end));
+}
+
+#[tokio::test]
+async fn resolves_drivers_missing_from_the_roster() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/drivers"))
+        .and(query_param("session_key", "9149"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {
+                "driver_number": 3,
+                "full_name": "Daniel RICCIARDO",
+                "name_acronym": "RIC",
+                "team_name": "AlphaTauri",
+                "team_colour": "5E8FAA"
+            },
+            { "driver_number": 99, "full_name": null, "team_colour": null }
+        ])))
+        .mount(&server)
+        .await;
+
+    let listed = fetch_drivers(&api(&server), "9149").await.unwrap();
+
+    let roster = [1, 3, 44];
+    assert_eq!(unknown_drivers(&roster, [44, 3, 99, 7, 99]), [7, 99]);
+    let ricciardo = DriverInfo::listed(&listed[0]);
+    assert_eq!(
+        (ricciardo.code.as_str(), ricciardo.color),
+        ("RIC", Color32::from_rgb(0x5e, 0x8f, 0xaa))
+    );
+    // Whatever the list lacks is made up, the same way every time
+    let unnamed = DriverInfo::listed(&listed[1]);
+    assert_eq!(unnamed.name, "Driver 99");
+    assert_eq!(unnamed.color, DriverInfo::placeholder(99).color);
+    assert_ne!(
+        DriverInfo::placeholder(7).color,
+        DriverInfo::placeholder(8).color
+    );
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use common::{at, at_millis};
+use f1_led_circuit_master_simulation::data::RaceControlData;
+use f1_led_circuit_master_simulation::flags::{
+    FlagPanel, FlagPanelConfig, FlagPanels, FlagState, FlagTimeline,
+};
+
+fn message(secs: i64, flag: &str, scope: &str, sector: Option<u32>) -> RaceControlData {
+    RaceControlData {
+        date: at(secs),
+        category: "Flag".to_string(),
+        flag: Some(flag.to_string()),
+        scope: Some(scope.to_string()),
+        sector,
+        driver_number: None,
+        message: String::new(),
+    }
+}
+
+fn timeline(messages: &[RaceControlData]) -> FlagTimeline {
+    FlagTimeline::new(messages, &FlagPanelConfig::default())
+}
+
+#[test]
+fn holds_sector_flags_until_cleared() {
+    let timeline = timeline(&[
+        message(10, "YELLOW", "Sector", Some(4)),
+        message(20, "DOUBLE YELLOW", "Sector", Some(4)),
+        message(30, "CLEAR", "Sector", Some(4)),
+        message(40, "YELLOW", "Sector", Some(7)),
+        message(50, "GREEN", "Track", None),
+    ]);
+    assert_eq!(timeline.flag_at(4, at(5)), FlagState::Green);
+    assert_eq!(timeline.flag_at(4, at(10)), FlagState::Yellow);
+    assert_eq!(timeline.flag_at(4, at(25)), FlagState::DoubleYellow);
+    assert_eq!(timeline.flag_at(4, at(30)), FlagState::Green);
+    // Other sectors aren't touched, until a track green clears them all
+    assert_eq!(timeline.flag_at(5, at(25)), FlagState::Green);
+    assert_eq!(timeline.flag_at(7, at(45)), FlagState::Yellow);
+    assert_eq!(timeline.flag_at(7, at(50)), FlagState::Green);
+}
+
+#[test]
+fn shows_a_red_flag_everywhere_and_lets_blue_flags_pass() {
+    let timeline = timeline(&[
+        message(10, "YELLOW", "Sector", Some(2)),
+        message(20, "RED", "Track", None),
+        message(60, "GREEN", "Track", None),
+        message(70, "BLUE", "Sector", Some(2)),
+        message(75, "BLUE", "Driver", None),
+    ]);
+    assert_eq!(timeline.flag_at(2, at(15)), FlagState::Yellow);
+    assert_eq!(timeline.flag_at(2, at(30)), FlagState::Red);
+    assert_eq!(timeline.flag_at(9, at(30)), FlagState::Red);
+    assert_eq!(timeline.flag_at(2, at(60)), FlagState::Green);
+    assert_eq!(timeline.flag_at(2, at(75)), FlagState::Blue);
+    assert_eq!(timeline.flag_at(2, at(80)), FlagState::Green);
+}
+
+#[test]
+fn lists_the_flags_out() {
+    let timeline = timeline(&[
+        message(10, "YELLOW", "Sector", Some(7)),
+        message(15, "DOUBLE YELLOW", "Sector", Some(4)),
+        message(20, "RED", "Track", None),
+        message(60, "GREEN", "Track", None),
+    ]);
+    assert!(timeline.active_at(at(5)).is_empty());
+    assert_eq!(
+        timeline.active_at(at(15)),
+        [
+            (Some(4), FlagState::DoubleYellow),
+            (Some(7), FlagState::Yellow)
+        ]
+    );
+    // A red flag is the only one out, and the green after it clears the sectors
+    assert_eq!(timeline.active_at(at(30)), [(None, FlagState::Red)]);
+    assert!(timeline.active_at(at(60)).is_empty());
+}
+
+#[test]
+fn lights_the_panel_leds_and_skips_unplaceable_panels() {
+    let config = FlagPanelConfig {
+        panels: vec![
+            FlagPanel {
+                sector: 4,
+                led: Some(1),
+                x: None,
+                y: None,
+            },
+            FlagPanel {
+                sector: 4,
+                led: None,
+                x: Some(10.0),
+                y: Some(20.0),
+            },
+            // Past the end of the layout, and nowhere at all
+            FlagPanel {
+                sector: 4,
+                led: Some(3),
+                x: None,
+                y: None,
+            },
+            FlagPanel {
+                sector: 4,
+                led: None,
+                x: Some(10.0),
+                y: None,
+            },
+        ],
+        ..FlagPanelConfig::default()
+    };
+    let messages = [
+        message(10, "YELLOW", "Sector", Some(4)),
+        message(20, "DOUBLE YELLOW", "Sector", Some(4)),
+    ];
+    let panels = FlagPanels::new(&config, FlagTimeline::new(&messages, &config), 3);
+    assert_eq!(panels.states(at(0)).count(), 2);
+
+    let mut leds = vec![Some([1, 2, 3]); 3];
+    panels.overlay(&mut leds, at(15));
+    assert_eq!(
+        leds,
+        [Some([1, 2, 3]), Some([255, 200, 0]), Some([1, 2, 3])]
+    );
+
+    // A double yellow blinks
+    panels.overlay(&mut leds, at(20));
+    assert_eq!(leds[1], Some([255, 200, 0]));
+    panels.overlay(&mut leds, at_millis(20_300));
+    assert_eq!(leds[1], None);
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use common::{at_millis, record};
+use f1_led_circuit_master_simulation::data::{LapData, PositionData, RaceControlData};
+use f1_led_circuit_master_simulation::formation::{
+    grid_slot, lights_out, FormationConfig, GridFormation,
+};
+use f1_led_circuit_master_simulation::laps::RaceProgress;
+use f1_led_circuit_master_simulation::simulation::Simulation;
+use std::collections::HashMap;
+use std::time::Duration;
+
+const LED_COUNT: usize = 100;
+
+fn green_light(millis: i64) -> RaceControlData {
+    RaceControlData {
+        date: at_millis(millis),
+        category: "Other".to_string(),
+        flag: None,
+        scope: None,
+        sector: None,
+        driver_number: None,
+        message: "GREEN LIGHT - PIT EXIT OPEN".to_string(),
+    }
+}
+
+// Driver 44 starts from pole ahead of driver 1 and 11; lights go out after 10s
+fn progress() -> RaceProgress {
+    let position = |driver_number, position| PositionData {
+        date: at_millis(0),
+        driver_number,
+        position,
+    };
+    let first_lap = |driver_number, date_start| LapData {
+        driver_number,
+        lap_number: 1,
+        date_start,
+        ..LapData::default()
+    };
+    RaceProgress::new(
+        vec![first_lap(44, Some(at_millis(10_000))), first_lap(1, None)],
+        vec![position(44, 1), position(1, 2), position(11, 3)],
+    )
+    .with_race_control(vec![green_light(-600_000)])
+}
+
+#[test]
+fn finds_lights_out() {
+    assert_eq!(lights_out(&progress()), Some(at_millis(10_000)));
+    // Without a first lap start, race control tells
+    let progress = RaceProgress::new(Vec::new(), Vec::new())
+        .with_race_control(vec![green_light(5000), green_light(90_000)]);
+    assert_eq!(lights_out(&progress), Some(at_millis(5000)));
+    assert_eq!(lights_out(&RaceProgress::default()), None);
+}
+
+#[test]
+fn lines_the_grid_up_behind_the_line_in_rows_of_two() {
+    let slots: Vec<usize> = (1..=5)
+        .map(|position| grid_slot(position, LED_COUNT, true))
+        .collect();
+    assert_eq!(slots, [99, 98, 96, 95, 93]);
+    assert_eq!(grid_slot(3, LED_COUNT, false), 97);
+
+    let formation =
+        GridFormation::new(&progress(), LED_COUNT, &FormationConfig::default()).unwrap();
+    assert_eq!(formation.slot(44), Some(99));
+    assert_eq!(formation.slot(11), Some(96));
+    // Too many cars for gaps between the rows
+    let formation = GridFormation::new(&progress(), 8, &FormationConfig::default()).unwrap();
+    assert_eq!(formation.slot(11), Some(5));
+    assert!(GridFormation::new(
+        &RaceProgress::default(),
+        LED_COUNT,
+        &FormationConfig::default()
+    )
+    .is_none());
+}
+
+#[test]
+fn hands_over_to_the_mapped_leds_after_lights_out() {
+    let formation =
+        GridFormation::new(&progress(), LED_COUNT, &FormationConfig::default()).unwrap();
+
+    assert_eq!(formation.led(44, 3, at_millis(9000)), 99);
+    // Halfway through the handover, the short way across the line
+    assert_eq!(formation.led(44, 3, at_millis(11_500)), 1);
+    assert_eq!(formation.led(44, 3, at_millis(13_000)), 3);
+    assert!(!formation.is_active(at_millis(13_000)));
+    // A car without a slot is shown where it is
+    assert_eq!(formation.led(63, 50, at_millis(0)), 50);
+}
+
+#[test]
+fn shows_the_cars_on_their_slots_before_the_start() {
+    // Both cars form up on LED 97 and drive off at lights out
+    let run_race_data = vec![
+        record(0, 1, 97),
+        record(0, 44, 97),
+        record(20_000, 1, 10),
+        record(20_000, 44, 12),
+    ];
+    let mut simulation = Simulation::new(run_race_data, LED_COUNT, HashMap::new());
+    let formation = GridFormation::new(&progress(), LED_COUNT, &FormationConfig::default());
+    simulation.set_grid_formation(formation);
+    simulation.start();
+
+    simulation.seek(Duration::from_secs(5));
+    let mut leds: Vec<(u32, usize)> = simulation.driver_leds().collect();
+    leds.sort();
+    assert_eq!(leds, [(1, 98), (44, 99)]);
+    assert!(simulation.frame().leds[98].is_some());
+    assert!(simulation.frame().leds[97].is_none());
+
+    simulation.seek(Duration::from_secs(25));
+    let mut leds: Vec<(u32, usize)> = simulation.driver_leds().collect();
+    leds.sort();
+    assert_eq!(leds, [(1, 10), (44, 12)]);
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use common::{at_millis, start};
+use f1_led_circuit_master_simulation::data::LapData;
+use f1_led_circuit_master_simulation::ghost::{Ghost, GhostAlignment, GhostConfig, GhostRun};
+use f1_led_circuit_master_simulation::laps::RaceProgress;
+use f1_led_circuit_master_simulation::mapping::RunRace;
+use f1_led_circuit_master_simulation::space::TelemetryPoint;
+
+fn ghost_start() -> DateTime<Utc> {
+    "2023-08-26T14:00:00Z".parse().unwrap()
+}
+
+fn lap(start: DateTime<Utc>, lap_number: u32, start_secs: i64) -> LapData {
+    LapData {
+        driver_number: 1,
+        lap_number,
+        date_start: Some(start + ChronoDuration::seconds(start_secs)),
+        lap_duration: Some(80.0),
+        ..LapData::default()
+    }
+}
+
+// Driver 1 on LED n after n seconds of their session, for 100 seconds; their second lap
+// starts 60s in
+fn ghost() -> Ghost {
+    let samples = (0..100)
+        .map(|second| RunRace {
+            date: ghost_start() + ChronoDuration::seconds(second),
+            driver_number: 1,
+            led_index: second as usize,
+            point: TelemetryPoint::new(0.0, 0.0),
+        })
+        .collect();
+    Ghost::new(GhostRun {
+        driver_number: 1,
+        samples,
+        progress: RaceProgress::new(
+            vec![lap(ghost_start(), 1, 0), lap(ghost_start(), 2, 60)],
+            Vec::new(),
+        ),
+    })
+}
+
+fn race_time(seconds: f64) -> DateTime<Utc> {
+    at_millis((seconds * 1000.0) as i64)
+}
+
+#[test]
+fn starts_together_with_the_session() {
+    let mut ghost = ghost();
+    ghost.align(&GhostConfig::default(), start(), None).unwrap();
+
+    assert_eq!(ghost.led_at(race_time(-1.0)), None);
+    assert_eq!(ghost.led_at(race_time(0.0)), Some(0));
+    assert_eq!(ghost.led_at(race_time(12.5)), Some(12));
+    assert_eq!(ghost.led_at(race_time(99.0)), Some(99));
+    assert_eq!(ghost.led_at(race_time(500.0)), None);
+}
+
+#[test]
+fn follows_the_clock_backwards() {
+    let mut ghost = ghost();
+    ghost.align(&GhostConfig::default(), start(), None).unwrap();
+
+    assert_eq!(ghost.led_at(race_time(50.0)), Some(50));
+    assert_eq!(ghost.led_at(race_time(20.0)), Some(20));
+    assert_eq!(ghost.led_at(race_time(21.0)), Some(21));
+}
+
+#[test]
+fn runs_ahead_by_a_manual_offset() {
+    let config = GhostConfig {
+        alignment: GhostAlignment::Manual,
+        offset_secs: 5.0,
+        ..GhostConfig::default()
+    };
+    let mut ghost = ghost();
+    ghost.align(&config, start(), None).unwrap();
+
+    assert_eq!(ghost.led_at(race_time(10.0)), Some(15));
+}
+
+#[test]
+fn lines_up_lap_starts() {
+    // The driver's third lap in the played session starts 200s in
+    let progress = RaceProgress::new(
+        vec![
+            lap(start(), 1, 0),
+            lap(start(), 2, 100),
+            lap(start(), 3, 200),
+        ],
+        Vec::new(),
+    );
+    let config = GhostConfig {
+        alignment: GhostAlignment::LapStart,
+        lap: 3,
+        ghost_lap: 2,
+        ..GhostConfig::default()
+    };
+    let mut ghost = ghost();
+    ghost.align(&config, start(), Some(&progress)).unwrap();
+
+    assert_eq!(ghost.led_at(race_time(200.0)), Some(60));
+    assert_eq!(ghost.led_at(race_time(230.0)), Some(90));
+}
+
+#[test]
+fn lap_alignment_needs_the_lap() {
+    let config = GhostConfig {
+        alignment: GhostAlignment::LapStart,
+        ..GhostConfig::default()
+    };
+
+    assert!(ghost().align(&config, start(), None).is_err());
+}
@@ -0,0 +1,127 @@
+//! Compares LED frames of a fixed synthetic race against `tests/golden/frames.json`.
+//! Run with `UPDATE_GOLDENS=1` to rewrite the golden file after an intended change.
+
+mod common;
+
+use common::sample;
+use eframe::egui::Color32;
+use f1_led_circuit_master_simulation::calibration::apply_brightness;
+use f1_led_circuit_master_simulation::data::LocationData;
+use f1_led_circuit_master_simulation::driver_info::{driver_colors, get_driver_info};
+use f1_led_circuit_master_simulation::led_coords::read_coordinates;
+use f1_led_circuit_master_simulation::mapping::{
+    collapse_duplicate_positions, generate_run_race_data, median_led_spacing, SNAP_DISTANCE_FACTOR,
+};
+use f1_led_circuit_master_simulation::simulation::{LedFrame, Simulation};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+const GOLDEN_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/frames.json");
+const FRAME_TIMES: [f64; 5] = [0.0, 3.2, 7.5, 11.0, 14.9];
+const BRIGHTNESS: f32 = 0.8;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct GoldenFrame {
+    time: f64,
+    leds: Vec<Option<String>>, // "#rrggbb", or null for an LED that's off
+}
+
+// Twelve drivers lapping the layout at different paces, sampled every half second for 15 s,
+// with one off-track excursion
+fn synthetic_samples(led_count: usize) -> Vec<LocationData> {
+    let coordinates = read_coordinates().unwrap();
+    let mut samples = Vec::new();
+    for (driver_index, driver) in get_driver_info().iter().take(12).enumerate() {
+        for step in 0..30 {
+            let led = (driver_index * 7 + step * (1 + driver_index % 3) / 2) % led_count;
+            let off_track = driver_index == 5 && (10..14).contains(&step);
+            let coord = &coordinates[led];
+            samples.push(sample(
+                step as i64 * 500,
+                driver.number,
+                coord.x + if off_track { 5000.0 } else { 3.0 },
+                coord.y - 2.0,
+            ));
+        }
+    }
+    samples.sort_by_key(|sample| sample.date);
+    samples
+}
+
+fn hex(frame: &LedFrame) -> Vec<Option<String>> {
+    frame
+        .leds
+        .iter()
+        .map(|color| {
+            color.map(|[r, g, b]| {
+                let color = apply_brightness(Color32::from_rgb(r, g, b), BRIGHTNESS);
+                format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+            })
+        })
+        .collect()
+}
+
+fn render_frames() -> Vec<GoldenFrame> {
+    let coordinates = read_coordinates().unwrap();
+    let samples = synthetic_samples(coordinates.len());
+    let max_snap_distance = median_led_spacing(&coordinates) * SNAP_DISTANCE_FACTOR;
+    let (mut run_race_data, _) =
+        generate_run_race_data(&samples, &coordinates, max_snap_distance).unwrap();
+    collapse_duplicate_positions(&mut run_race_data);
+
+    let mut simulation = Simulation::new(
+        run_race_data,
+        coordinates.len(),
+        driver_colors(&get_driver_info()),
+    );
+    simulation.start();
+    FRAME_TIMES
+        .iter()
+        .map(|&time| GoldenFrame {
+            time,
+            leds: hex(simulation.seek(Duration::from_secs_f64(time))),
+        })
+        .collect()
+}
+
+fn describe(color: &Option<String>) -> &str {
+    color.as_deref().unwrap_or("off")
+}
+
+#[test]
+fn frames_match_golden() {
+    let frames = render_frames();
+    let path = Path::new(GOLDEN_FILE);
+
+    if std::env::var_os("UPDATE_GOLDENS").is_some() {
+        std::fs::write(path, serde_json::to_string_pretty(&frames).unwrap() + "\n").unwrap();
+        return;
+    }
+
+    let golden: Vec<GoldenFrame> = serde_json::from_str(
+        &std::fs::read_to_string(path).expect("missing golden file, run with UPDATE_GOLDENS=1"),
+    )
+    .unwrap();
+
+    let mut differences = Vec::new();
+    for (expected, actual) in golden.iter().zip(&frames) {
+        for (led, (want, got)) in expected.leds.iter().zip(&actual.leds).enumerate() {
+            if want != got {
+                differences.push(format!(
+                    "  t={}s LED {}: expected {}, got {}",
+                    expected.time,
+                    led,
+                    describe(want),
+                    describe(got)
+                ));
+            }
+        }
+    }
+    assert!(
+        golden.len() == frames.len() && differences.is_empty(),
+        "frames differ from {} (rerun with UPDATE_GOLDENS=1 if intended):\n{}",
+        GOLDEN_FILE,
+        differences.join("\n")
+    );
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use common::record;
+use f1_led_circuit_master_simulation::heatmap::Heatmap;
+use f1_led_circuit_master_simulation::mapping::RunRace;
+use f1_led_circuit_master_simulation::occupancy::Occupancy;
+use f1_led_circuit_master_simulation::simulation::Simulation;
+use std::collections::HashMap;
+use std::time::Duration;
+
+// Driver 1 sits on LED 0 for 3s, driver 44 crosses LEDs 1 and 2 a second each
+fn race() -> Vec<RunRace> {
+    vec![
+        record(0, 1, 0),
+        record(0, 44, 1),
+        record(1000, 44, 2),
+        record(2000, 44, 3),
+        record(3000, 1, 1),
+        record(4000, 1, 2),
+    ]
+}
+
+#[test]
+fn adds_up_like_the_occupancy_in_steps() {
+    let race = race();
+    let mut heatmap = Heatmap::new(4, None, 5.0);
+    for index in 0..=race.len() {
+        heatmap.advance(&race, index);
+    }
+
+    let occupancy = Occupancy::compute(&race, 4, 5.0);
+    for led_index in 0..4 {
+        let total: f64 = occupancy
+            .drivers()
+            .iter()
+            .map(|&driver_number| occupancy.seconds(led_index, driver_number))
+            .sum();
+        assert_eq!(heatmap.seconds(led_index), total);
+    }
+    assert_eq!(heatmap.seconds(0), 3.0);
+    assert_eq!(heatmap.seconds(1), 2.0);
+}
+
+#[test]
+fn starts_over_when_going_backwards() {
+    let race = race();
+    let mut heatmap = Heatmap::new(4, Some(44), 5.0);
+    heatmap.advance(&race, race.len());
+    assert_eq!(heatmap.seconds(0), 0.0);
+    assert_eq!(heatmap.seconds(2), 1.0);
+
+    heatmap.advance(&race, 3);
+    assert_eq!(heatmap.seconds(1), 1.0);
+    assert_eq!(heatmap.seconds(2), 0.0);
+}
+
+#[test]
+fn colors_the_busiest_led_red() {
+    let race = race();
+    let mut heatmap = Heatmap::new(4, None, 5.0);
+    heatmap.advance(&race, race.len());
+
+    let colors = heatmap.colors();
+    assert_eq!(colors[0], Some([255, 0, 0]));
+    assert_eq!(colors[1], Some([170, 0, 85]));
+    assert_eq!(colors[3], None);
+}
+
+#[test]
+fn the_simulation_shows_the_heatmap_up_to_the_clock() {
+    let mut simulation = Simulation::new(race(), 4, HashMap::new());
+    simulation.set_heatmap(Some(Heatmap::new(4, None, 5.0)));
+    simulation.start();
+
+    let frame = simulation.seek(Duration::from_millis(2500)).clone();
+    // Driver 1 hasn't left LED 0 yet, so only driver 44's LEDs have time
+    assert_eq!(
+        frame.leds,
+        vec![None, Some([255, 0, 0]), Some([255, 0, 0]), None]
+    );
+
+    simulation.seek(Duration::from_millis(500));
+    assert_eq!(simulation.heatmap().unwrap().seconds(1), 0.0);
+}
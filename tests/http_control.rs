@@ -0,0 +1,147 @@
+#![cfg(feature = "http-control")]
+
+use f1_led_circuit_master_simulation::control::{ControlConfig, PlaybackCommand};
+use f1_led_circuit_master_simulation::http_control::{ControlServer, ControlStatus};
+use f1_led_circuit_master_simulation::simulation::PlaybackState;
+use f1_led_circuit_master_simulation::sink::{LedSink, OutputFrame};
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use serde_json::json;
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+fn server(token: Option<&str>) -> (ControlServer, Receiver<PlaybackCommand>, String) {
+    let config = ControlConfig {
+        enabled: true,
+        bind: "127.0.0.1".to_string(),
+        port: 0,
+        token: token.map(str::to_string),
+    };
+    let (sender, commands) = mpsc::channel();
+    let server = ControlServer::open(&config, sender, 1234, 96).unwrap();
+    let url = format!("http://{}", server.address());
+    (server, commands, url)
+}
+
+#[test]
+fn queues_commands_in_order() {
+    let (mut server, commands, url) = server(None);
+    let client = Client::new();
+
+    let post = |path: &str, body: Option<serde_json::Value>| {
+        let request = client.post(format!("{}{}", url, path));
+        match body {
+            Some(body) => request.json(&body),
+            None => request,
+        }
+        .send()
+        .unwrap()
+        .status()
+    };
+    assert_eq!(post("/start", None), StatusCode::ACCEPTED);
+    assert_eq!(post("/pause", None), StatusCode::ACCEPTED);
+    assert_eq!(
+        post("/speed", Some(json!({ "value": 4 }))),
+        StatusCode::ACCEPTED
+    );
+    assert_eq!(
+        post("/seek", Some(json!({ "seconds": 90.5 }))),
+        StatusCode::ACCEPTED
+    );
+    assert_eq!(post("/stop", None), StatusCode::ACCEPTED);
+
+    assert_eq!(
+        commands.try_iter().collect::<Vec<_>>(),
+        [
+            PlaybackCommand::Start,
+            PlaybackCommand::Pause,
+            PlaybackCommand::SetSpeed(4.0),
+            PlaybackCommand::Seek(Duration::from_secs_f64(90.5)),
+            PlaybackCommand::Stop,
+        ]
+    );
+    server.shutdown();
+}
+
+#[test]
+fn rejects_bad_bodies() {
+    let (mut server, commands, url) = server(None);
+    let client = Client::new();
+
+    let seek = client
+        .post(format!("{}/seek", url))
+        .json(&json!({ "seconds": -5.0 }))
+        .send()
+        .unwrap();
+    assert_eq!(seek.status(), StatusCode::BAD_REQUEST);
+    let speed = client
+        .post(format!("{}/speed", url))
+        .json(&json!({ "speed": 4 }))
+        .send()
+        .unwrap();
+    assert!(speed.status().is_client_error());
+
+    assert!(commands.try_iter().next().is_none());
+    server.shutdown();
+}
+
+#[test]
+fn reports_the_latest_frame() {
+    let (mut server, _commands, url) = server(None);
+    server
+        .submit(&OutputFrame {
+            leds: vec![[0, 0, 0]; 96],
+            brightness: 1.0,
+            timestamp: Duration::from_millis(61_500),
+            state: PlaybackState::Paused,
+            speed: 3.0,
+            drivers: Vec::new(),
+        })
+        .unwrap();
+
+    let status: ControlStatus = Client::new()
+        .get(format!("{}/status", url))
+        .send()
+        .unwrap()
+        .json()
+        .unwrap();
+    assert_eq!(
+        status,
+        ControlStatus {
+            race_time: 61.5,
+            state: PlaybackState::Paused,
+            speed: 3.0,
+            records: 1234,
+            leds: 96,
+        }
+    );
+    server.shutdown();
+}
+
+#[test]
+fn requires_the_bearer_token() {
+    let (mut server, commands, url) = server(Some("secret"));
+    let client = Client::new();
+    let start = |token: Option<&str>| {
+        let request = client.post(format!("{}/start", url));
+        match token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+        .send()
+        .unwrap()
+        .status()
+    };
+
+    assert_eq!(start(None), StatusCode::UNAUTHORIZED);
+    assert_eq!(start(Some("wrong")), StatusCode::UNAUTHORIZED);
+    let status = client.get(format!("{}/status", url)).send().unwrap();
+    assert_eq!(status.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(start(Some("secret")), StatusCode::ACCEPTED);
+
+    assert_eq!(
+        commands.try_iter().collect::<Vec<_>>(),
+        [PlaybackCommand::Start]
+    );
+    server.shutdown();
+}
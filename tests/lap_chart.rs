@@ -0,0 +1,112 @@
+mod common;
+
+use common::at;
+use f1_led_circuit_master_simulation::data::{LapData, PositionData};
+use f1_led_circuit_master_simulation::lap_chart::LapChart;
+use f1_led_circuit_master_simulation::laps::RaceProgress;
+use std::collections::HashMap;
+
+fn lap(driver_number: u32, lap_number: u32, start_secs: i64, duration: Option<f64>) -> LapData {
+    LapData {
+        lap_duration: duration,
+        ..common::lap(driver_number, lap_number, start_secs)
+    }
+}
+
+fn position(driver_number: u32, secs: i64, position: u32) -> PositionData {
+    PositionData {
+        date: at(secs),
+        driver_number,
+        position,
+    }
+}
+
+// Driver 1 starts second and passes driver 44 during lap 2; driver 44 retires on lap 3
+fn race() -> RaceProgress {
+    RaceProgress::new(
+        vec![
+            lap(1, 1, 0, Some(90.0)),
+            lap(1, 2, 90, Some(90.0)),
+            lap(1, 3, 180, Some(90.0)),
+            lap(44, 1, 0, Some(89.0)),
+            lap(44, 2, 89, Some(92.0)),
+            lap(44, 3, 181, None),
+        ],
+        vec![
+            position(1, -60, 2),
+            position(44, -60, 1),
+            position(1, 150, 1),
+            position(44, 150, 2),
+        ],
+    )
+}
+
+#[test]
+fn takes_positions_at_the_end_of_each_lap() {
+    let chart = LapChart::new(&race());
+
+    assert_eq!(chart.drivers(), [1, 44]);
+    assert_eq!(chart.laps(), 3);
+    assert_eq!(chart.position(1, 0), Some(2));
+    assert_eq!(chart.position(1, 1), Some(2));
+    assert_eq!(chart.position(1, 2), Some(1));
+    assert_eq!(chart.position(44, 1), Some(1));
+    assert_eq!(chart.position(44, 2), Some(2));
+}
+
+#[test]
+fn stops_a_retired_drivers_line_at_the_last_completed_lap() {
+    let chart = LapChart::new(&race());
+
+    assert_eq!(chart.position(1, 3), Some(1));
+    assert_eq!(chart.position(44, 3), None);
+}
+
+#[test]
+fn writes_one_row_per_lap() {
+    let mut csv = Vec::new();
+    LapChart::new(&race()).write_csv(&mut csv).unwrap();
+
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "lap,1,44\n0,2,1\n1,2,1\n2,1,2\n3,1,\n"
+    );
+}
+
+#[test]
+fn renders_at_the_requested_size() {
+    let colors = HashMap::from([(1, [255, 0, 0]), (44, [0, 255, 0])]);
+    let image = LapChart::new(&race()).render(320, 200, &colors);
+
+    assert_eq!(image.dimensions(), (320, 200));
+    assert!(image.pixels().any(|pixel| pixel.0 == [255, 0, 0, 255]));
+    assert!(image.pixels().any(|pixel| pixel.0 == [0, 255, 0, 255]));
+}
+
+#[test]
+fn keeps_later_laps_when_a_lap_is_listed_twice() {
+    let progress = RaceProgress::new(
+        vec![
+            lap(1, 1, 0, Some(90.0)),
+            lap(1, 2, 90, Some(90.0)),
+            lap(1, 3, 180, Some(90.0)),
+            lap(1, 2, 90, Some(90.0)),
+        ],
+        vec![position(1, -60, 1)],
+    );
+    let chart = LapChart::new(&progress);
+
+    assert_eq!(chart.laps(), 3);
+    assert_eq!(chart.position(1, 3), Some(1));
+}
+
+#[test]
+fn renders_a_position_of_zero_on_the_top_line() {
+    let progress = RaceProgress::new(
+        vec![lap(1, 1, 0, Some(90.0)), lap(44, 1, 0, Some(91.0))],
+        vec![position(1, -60, 0), position(44, -60, 2)],
+    );
+    let image = LapChart::new(&progress).render(320, 200, &HashMap::new());
+
+    assert_eq!(image.dimensions(), (320, 200));
+}
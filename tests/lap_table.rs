@@ -0,0 +1,59 @@
+mod common;
+
+use common::at;
+use f1_led_circuit_master_simulation::data::LapData;
+use f1_led_circuit_master_simulation::lap_table::{format_lap_time, lap_rows};
+use f1_led_circuit_master_simulation::laps::RaceProgress;
+
+fn lap(lap_number: u32, start_secs: i64, duration: f64, sectors: [Option<f64>; 3]) -> LapData {
+    LapData {
+        lap_duration: Some(duration),
+        duration_sector_1: sectors[0],
+        duration_sector_2: sectors[1],
+        duration_sector_3: sectors[2],
+        ..common::lap(1, lap_number, start_secs)
+    }
+}
+
+fn progress() -> RaceProgress {
+    let mut out_lap = lap(3, 170, 95.0, [None, Some(30.0), Some(35.0)]);
+    out_lap.is_pit_out_lap = true;
+    RaceProgress::new(
+        vec![
+            lap(1, 0, 90.0, [Some(30.0), Some(30.0), Some(30.0)]),
+            lap(2, 90, 80.0, [Some(26.0), Some(27.0), Some(27.0)]),
+            out_lap,
+            lap(4, 265, 78.5, [Some(26.0), Some(26.0), Some(26.5)]),
+        ],
+        Vec::new(),
+    )
+}
+
+#[test]
+fn reveals_laps_as_they_are_completed() {
+    let progress = progress();
+    assert!(lap_rows(&progress, 1, at(89)).is_empty());
+    assert_eq!(lap_rows(&progress, 1, at(90)).len(), 1);
+
+    let rows = lap_rows(&progress, 1, at(300));
+    let numbers: Vec<u32> = rows.iter().map(|row| row.lap_number).collect();
+    assert_eq!(numbers, [1, 2, 3]);
+    let bests: Vec<bool> = rows.iter().map(|row| row.personal_best).collect();
+    assert_eq!(bests, [true, true, false]);
+    assert!(rows[2].pit_out);
+    assert_eq!(rows[2].sectors[0], None);
+    assert_eq!(rows[1].start, Some(at(90)));
+
+    // Going back in time hides the later laps again
+    assert_eq!(lap_rows(&progress, 1, at(200)).len(), 2);
+    assert!(lap_rows(&progress, 44, at(300)).is_empty());
+}
+
+#[test]
+fn formats_lap_and_sector_times() {
+    assert_eq!(format_lap_time(Some(73.847)), "1:13.847");
+    assert_eq!(format_lap_time(Some(26.5)), "26.500");
+    assert_eq!(format_lap_time(Some(59.9996)), "1:00.000");
+    assert_eq!(format_lap_time(Some(605.0)), "10:05.000");
+    assert_eq!(format_lap_time(None), "-");
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use f1_led_circuit_master_simulation::led_decay::{LedDecay, LedDecayConfig};
+use f1_led_circuit_master_simulation::simulation::{Rgb, Simulation};
+use std::collections::HashMap;
+use std::time::Duration;
+
+const RED: Rgb = [255, 0, 0];
+const BLUE: Rgb = [0, 0, 255];
+
+fn config(fade_ms: u64) -> LedDecayConfig {
+    LedDecayConfig {
+        enabled: true,
+        fade_ms,
+    }
+}
+
+#[test]
+fn fades_a_left_led_unless_someone_takes_it() {
+    let mut decay = LedDecay::new(&config(250));
+    let frame = |decay: &mut LedDecay, leds: [Option<Rgb>; 3], race_time: f64| {
+        let mut leds = leds.to_vec();
+        decay.apply(&mut leds, race_time);
+        leds
+    };
+    frame(&mut decay, [Some(RED), None, Some(BLUE)], 0.0);
+    assert_eq!(
+        frame(&mut decay, [None, Some(RED), Some(BLUE)], 1.0),
+        [Some(RED), Some(RED), Some(BLUE)]
+    );
+    assert_eq!(
+        frame(&mut decay, [None, Some(RED), None], 1.125),
+        [Some([128, 0, 0]), Some(RED), Some(BLUE)]
+    );
+    // Blue's LED is taken over before it's dark; red's has faded out
+    assert_eq!(
+        frame(&mut decay, [None, None, Some(RED)], 1.25),
+        [None, Some(RED), Some(RED)]
+    );
+    assert!(decay.is_active());
+    frame(&mut decay, [None, None, Some(RED)], 1.5);
+    assert!(!decay.is_active());
+
+    assert_eq!(config(50).fade_secs(), 0.1);
+    assert_eq!(config(2000).fade_secs(), 0.5);
+}
+
+#[test]
+fn stops_fading_across_a_seek() {
+    // Driver 1 moves from LED 0 to LED 1 at 2s
+    let record = |millis, led_index| common::record(millis, 1, led_index);
+    let run_race_data = vec![record(0, 0), record(2000, 1), record(4000, 2)];
+    let mut simulation = Simulation::new(run_race_data, 3, HashMap::from([(1, RED)]));
+    simulation.set_led_decay(&config(250));
+    simulation.start();
+
+    simulation.tick(Duration::from_millis(1000));
+    simulation.tick(Duration::from_millis(1000));
+    assert_eq!(simulation.frame().leds[0], Some(RED));
+    simulation.tick(Duration::from_millis(125));
+    assert_eq!(simulation.frame().leds[0], Some([128, 0, 0]));
+
+    simulation.seek(Duration::from_millis(2125));
+    assert_eq!(simulation.frame().leds, [None, Some(RED), None]);
+}
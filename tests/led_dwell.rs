@@ -0,0 +1,82 @@
+mod common;
+
+use f1_led_circuit_master_simulation::led_dwell::{LedDwell, LedDwellConfig};
+use f1_led_circuit_master_simulation::simulation::{Rgb, Simulation};
+use std::collections::HashMap;
+use std::time::Duration;
+
+const RED: Rgb = [255, 0, 0];
+
+fn config(max_skip: usize) -> LedDwellConfig {
+    LedDwellConfig {
+        enabled: true,
+        min_ms: 40,
+        max_skip,
+    }
+}
+
+fn lit(dwell: &LedDwell) -> Vec<usize> {
+    let mut leds: Vec<usize> = dwell.leds().map(|(_, led_index)| led_index).collect();
+    leds.sort();
+    leds
+}
+
+#[test]
+fn lights_passed_leds_for_the_minimum_time_in_track_order() {
+    let mut dwell = LedDwell::new(&config(20));
+    dwell.advance(Duration::from_millis(16));
+    dwell.moved(1, 0, 4, 20);
+    assert_eq!(lit(&dwell), [1, 2, 3]);
+    assert!(dwell.is_active());
+
+    // The LED furthest behind goes off first
+    dwell.advance(Duration::from_millis(46));
+    assert_eq!(lit(&dwell), [2, 3]);
+    dwell.advance(Duration::from_millis(10));
+    assert!(lit(&dwell).is_empty());
+    assert!(!dwell.is_active());
+
+    // By way of the line
+    dwell.moved(1, 18, 2, 20);
+    assert_eq!(lit(&dwell), [0, 1, 19]);
+}
+
+#[test]
+fn forgets_passed_leds_going_backwards_or_jumping() {
+    let mut dwell = LedDwell::new(&config(2));
+    dwell.advance(Duration::from_millis(16));
+    dwell.moved(1, 0, 3, 40);
+    assert_eq!(lit(&dwell), [1, 2]);
+    dwell.moved(1, 3, 2, 40);
+    assert!(lit(&dwell).is_empty());
+
+    // Further than max_skip, like through the pit lane
+    dwell.moved(1, 2, 10, 40);
+    assert!(lit(&dwell).is_empty());
+
+    let mut off = LedDwell::new(&LedDwellConfig::default());
+    off.moved(1, 0, 3, 40);
+    assert!(!off.is_active());
+}
+
+#[test]
+fn fills_in_a_skip_during_playback_but_not_across_a_seek() {
+    // Driver 1 skips from LED 0 to LED 4 at 100ms
+    let record = |millis, led_index| common::record(millis, 1, led_index);
+    let run_race_data = vec![record(0, 0), record(100, 4), record(10_000, 5)];
+    let mut simulation = Simulation::new(run_race_data, 10, HashMap::from([(1, RED)]));
+    simulation.set_led_dwell(&config(20));
+    simulation.start();
+
+    simulation.tick(Duration::from_millis(50));
+    simulation.tick(Duration::from_millis(50));
+    let leds = &simulation.frame().leds;
+    assert_eq!(
+        leds[..5],
+        [None, Some(RED), Some(RED), Some(RED), Some(RED)]
+    );
+
+    simulation.seek(Duration::from_millis(200));
+    let leds = &simulation.frame().leds;
+    assert_eq!(leds[..5], [None, None, None, None, Some(RED)]);
+}
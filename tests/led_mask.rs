@@ -0,0 +1,51 @@
+use f1_led_circuit_master_simulation::led_mask::{read_mask, LedFault};
+use f1_led_circuit_master_simulation::space::LedPoint;
+
+const RED: [u8; 3] = [255, 0, 0];
+const WHITE: [u8; 3] = [255, 255, 255];
+const OFF: [u8; 3] = [0, 0, 0];
+
+// Five LEDs in a row, the fourth closer to the third than to the fifth
+fn coordinates() -> Vec<LedPoint> {
+    [0.0, 1.0, 2.0, 2.5, 4.0]
+        .into_iter()
+        .map(|x| LedPoint::new(x, 0.0))
+        .collect()
+}
+
+#[test]
+fn moves_dead_leds_to_a_neighbor_and_blanks_broken_channels() {
+    let path = std::env::temp_dir().join(format!("{}-led_mask.csv", std::process::id()));
+    std::fs::write(&path, "led,fault\nU2,dead\nU4,dead\nU5,b\nU9,dead\n").unwrap();
+    let mask = read_mask(&path, &coordinates()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(mask.fault(1), Some(LedFault::Dead));
+    assert_eq!(
+        mask.fault(4),
+        Some(LedFault::Channels([false, false, true]))
+    );
+    assert_eq!(mask.fault(0), None);
+    // U4 goes to U3 right next to it; U2 is as close to U1 as to U3 and takes the first
+    assert_eq!(mask.rerouted(3), 2);
+    assert_eq!(mask.rerouted(1), 0);
+    assert_eq!(mask.rerouted(2), 2);
+
+    let mut leds = [OFF, RED, OFF, RED, WHITE];
+    mask.apply(&mut leds);
+    assert_eq!(leds, [RED, OFF, RED, OFF, [255, 255, 0]]);
+
+    // A lit neighbor keeps its own car
+    let mut leds = [WHITE, RED, OFF, OFF, OFF];
+    mask.apply(&mut leds);
+    assert_eq!(leds, [WHITE, OFF, OFF, OFF, OFF]);
+}
+
+#[test]
+fn rejects_an_unknown_fault() {
+    let path = std::env::temp_dir().join(format!("{}-bad_mask.csv", std::process::id()));
+    std::fs::write(&path, "led,fault\nU1,flickers\n").unwrap();
+    let result = read_mask(&path, &coordinates());
+    std::fs::remove_file(&path).unwrap();
+    assert!(result.is_err());
+}
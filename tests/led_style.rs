@@ -0,0 +1,140 @@
+use eframe::egui::{pos2, Color32, Pos2, Rect, Shape};
+use f1_led_circuit_master_simulation::led_style::{elevation_shades, led_shapes, LedStyle};
+use f1_led_circuit_master_simulation::simulation::Rgb;
+use std::time::{Duration, Instant};
+
+const LED_COUNT: usize = 96;
+
+fn positions() -> Vec<Pos2> {
+    (0..LED_COUNT)
+        .map(|index| pos2(index as f32 * 10.0, 0.0))
+        .collect()
+}
+
+// Every other LED lit red
+fn colors() -> Vec<Rgb> {
+    (0..LED_COUNT)
+        .map(|index| {
+            if index & 1 == 0 {
+                [255, 0, 0]
+            } else {
+                [0, 0, 0]
+            }
+        })
+        .collect()
+}
+
+fn fill(shape: &Shape) -> Color32 {
+    match shape {
+        Shape::Circle(circle) => circle.fill,
+        Shape::Rect(rect) => rect.fill,
+        _ => panic!("unexpected shape {:?}", shape),
+    }
+}
+
+#[test]
+fn squares_draw_every_led() {
+    let shapes = led_shapes(
+        LedStyle::Squares,
+        &positions(),
+        &colors(),
+        &[],
+        20.0,
+        1.0,
+        Rect::EVERYTHING,
+    );
+    assert_eq!(shapes.len(), LED_COUNT);
+    assert_eq!(fill(&shapes[0]), Color32::RED);
+    assert_eq!(fill(&shapes[1]), Color32::BLACK);
+}
+
+#[test]
+fn glow_draws_halos_before_the_cores() {
+    let shapes = led_shapes(
+        LedStyle::Glow,
+        &positions(),
+        &colors(),
+        &[],
+        20.0,
+        1.0,
+        Rect::EVERYTHING,
+    );
+    let lit = LED_COUNT / 2;
+    assert_eq!(shapes.len(), lit * 3 + (LED_COUNT - lit) + lit);
+
+    // Halos are additive and reach a full LED size beyond the core...
+    let Shape::Circle(outer) = &shapes[2] else {
+        panic!("halo isn't a circle");
+    };
+    assert_eq!(outer.radius, 30.0);
+    assert_eq!(outer.fill.a(), 0);
+    // ... and the opaque cores come last
+    assert!(shapes[shapes.len() - lit..]
+        .iter()
+        .all(|shape| fill(shape) == Color32::RED));
+}
+
+#[test]
+fn glow_of_the_whole_track_is_cheap() {
+    let (positions, colors) = (positions(), vec![[255, 128, 0]; LED_COUNT]);
+    let runs = 1000;
+    let start = Instant::now();
+    for _ in 0..runs {
+        let shapes = led_shapes(
+            LedStyle::Glow,
+            &positions,
+            &colors,
+            &[],
+            20.0,
+            1.0,
+            Rect::EVERYTHING,
+        );
+        assert_eq!(shapes.len(), LED_COUNT * 4);
+    }
+    let per_frame = start.elapsed() / runs;
+    assert!(
+        per_frame < Duration::from_millis(1),
+        "{:?} per frame",
+        per_frame
+    );
+}
+
+#[test]
+fn shades_unlit_leds_lighter_the_higher_they_are() {
+    let shades: Vec<f32> = (0..LED_COUNT)
+        .map(|index| index as f32 / (LED_COUNT - 1) as f32)
+        .collect();
+    let shapes = led_shapes(
+        LedStyle::Squares,
+        &positions(),
+        &colors(),
+        &shades,
+        20.0,
+        1.0,
+        Rect::EVERYTHING,
+    );
+
+    // Lit LEDs keep their color
+    assert_eq!(fill(&shapes[0]), Color32::RED);
+    let low = fill(&shapes[1]);
+    let high = fill(&shapes[LED_COUNT - 1]);
+    assert_ne!(low, Color32::BLACK);
+    assert!(
+        high.r() > low.r(),
+        "{:?} isn't lighter than {:?}",
+        high,
+        low
+    );
+}
+
+#[test]
+fn shades_only_sessions_with_elevation() {
+    assert_eq!(
+        elevation_shades(&[Some(10.0), None, Some(30.0), Some(20.0)]),
+        Some(vec![0.0, 0.5, 1.0, 0.5])
+    );
+    // Flat or missing, it's left off rather than tinting every LED the same
+    assert_eq!(elevation_shades(&[Some(12.0), Some(12.2), None]), None);
+    assert_eq!(elevation_shades(&[None, None]), None);
+    assert_eq!(elevation_shades(&[]), None);
+}
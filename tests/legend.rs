@@ -0,0 +1,136 @@
+mod common;
+
+use common::at;
+use eframe::egui::Color32;
+use f1_led_circuit_master_simulation::cache::{drivers_cache_key, load_drivers, store_drivers};
+use f1_led_circuit_master_simulation::data::{PositionData, SessionDriver};
+use f1_led_circuit_master_simulation::driver_info::{
+    get_driver_info, legend_order, session_roster, DriverInfo,
+};
+use f1_led_circuit_master_simulation::laps::RaceProgress;
+use f1_led_circuit_master_simulation::prefs::LegendOrder;
+
+fn driver(number: u32, name: &str, team: &str) -> DriverInfo {
+    DriverInfo {
+        number,
+        name: name.to_string(),
+        code: name[..3].to_string(),
+        team: team.to_string(),
+        color: Color32::WHITE,
+    }
+}
+
+fn roster() -> Vec<DriverInfo> {
+    vec![
+        driver(1, "Max Verstappen", "Red Bull"),
+        driver(11, "Sergio Perez", "Red Bull"),
+        driver(14, "Fernando Alonso", "Aston Martin"),
+        driver(44, "Lewis Hamilton", "Mercedes"),
+    ]
+}
+
+fn position(driver_number: u32, secs: i64, position: u32) -> PositionData {
+    PositionData {
+        date: at(secs),
+        driver_number,
+        position,
+    }
+}
+
+#[test]
+fn sorts_by_number_name_and_team() {
+    let no_position = |_| None;
+    assert_eq!(
+        legend_order(&roster(), LegendOrder::Number, no_position),
+        [1, 11, 14, 44]
+    );
+    assert_eq!(
+        legend_order(&roster(), LegendOrder::Name, no_position),
+        [14, 44, 1, 11]
+    );
+    assert_eq!(
+        legend_order(&roster(), LegendOrder::Team, no_position),
+        [14, 44, 1, 11]
+    );
+}
+
+#[test]
+fn sorts_by_position_with_unplaced_drivers_last() {
+    // Driver 14 has no position; 44 passes 1 at 60s
+    let progress = RaceProgress::new(
+        Vec::new(),
+        vec![
+            position(1, 0, 1),
+            position(44, 0, 2),
+            position(11, 0, 3),
+            position(1, 60, 2),
+            position(44, 60, 1),
+        ],
+    );
+    let order_at = |secs: i64| {
+        let date = at(secs);
+        legend_order(&roster(), LegendOrder::Position, |driver_number| {
+            progress.position_at(driver_number, date)
+        })
+    };
+
+    assert_eq!(order_at(30), [1, 44, 11, 14]);
+    assert_eq!(order_at(60), [44, 1, 11, 14]);
+
+    // The legend only sorts again when this count moves
+    let changes_until = |secs| progress.position_changes_until(at(secs));
+    assert_eq!(changes_until(-1), 0);
+    assert_eq!(changes_until(30), 3);
+    assert_eq!(changes_until(59), 3);
+    assert_eq!(changes_until(60), 5);
+}
+
+fn listed(driver_number: u32, full_name: &str, acronym: &str) -> SessionDriver {
+    SessionDriver {
+        driver_number,
+        full_name: Some(full_name.to_string()),
+        name_acronym: Some(acronym.to_string()),
+        team_name: Some("AlphaTauri".to_string()),
+        team_colour: Some("5E8FAA".to_string()),
+    }
+}
+
+#[test]
+fn shows_who_drove_a_number_in_each_session() {
+    let dir = std::env::temp_dir().join(format!("legend-test-{}", std::process::id()));
+    store_drivers(
+        &dir,
+        drivers_cache_key("9149"),
+        &[listed(40, "Liam LAWSON", "LAW")],
+    )
+    .unwrap();
+    store_drivers(
+        &dir,
+        drivers_cache_key("9165"),
+        &[listed(40, "Daniel RICCIARDO", "RIC")],
+    )
+    .unwrap();
+
+    let lawson_color = get_driver_info()
+        .into_iter()
+        .find(|driver| driver.number == 40)
+        .unwrap()
+        .color;
+    for (session_key, name, code) in [
+        ("9149", "Liam LAWSON", "LAW"),
+        ("9165", "Daniel RICCIARDO", "RIC"),
+    ] {
+        let listed = load_drivers(&dir, drivers_cache_key(session_key)).unwrap();
+        let (roster, made_up) = session_roster(get_driver_info(), &listed, [1, 40]);
+        let driver = roster.iter().find(|driver| driver.number == 40).unwrap();
+        assert_eq!((driver.name.as_str(), driver.code.as_str()), (name, code));
+        // Still the car's color, and no second entry for the number
+        assert_eq!(driver.color, lawson_color);
+        assert_eq!(
+            roster.iter().filter(|driver| driver.number == 40).count(),
+            1
+        );
+        assert!(made_up.is_empty());
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,512 @@
+mod common;
+
+use chrono::Duration;
+use common::start;
+use f1_led_circuit_master_simulation::data::{LocationData, PipelineStats};
+use f1_led_circuit_master_simulation::error::AppError;
+use f1_led_circuit_master_simulation::led_coords::read_coordinates;
+use f1_led_circuit_master_simulation::mapping::{
+    align_to_grid, blend_weights, collapse_duplicate_positions, downsample, fill_gaps,
+    generate_run_race_data, map_drivers, median_led_spacing, nearest_led, smooth, snap_to_leds,
+    MappingOptions, NearestLedCache, NearestLedIndex, Smoothing, PARALLEL_MAPPING_THRESHOLD,
+    SNAP_DISTANCE_FACTOR,
+};
+use f1_led_circuit_master_simulation::simulation::{Rgb, Simulation};
+use f1_led_circuit_master_simulation::space::{LedPoint, TelemetryPoint};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+
+const RED: Rgb = [255, 0, 0];
+
+// Samples scattered over and around the layout, including far off-track ones
+fn synthetic_samples(count: usize) -> Vec<LocationData> {
+    let mut rng = StdRng::seed_from_u64(859);
+    (0..count)
+        .map(|index| {
+            common::sample(
+                index as i64 * 270,
+                [1, 11, 44, 63][index % 4],
+                rng.gen_range(-2000.0..10000.0),
+                rng.gen_range(-2000.0..8000.0),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn parallel_snapping_matches_sequential() {
+    let coordinates = read_coordinates().unwrap();
+    let samples = synthetic_samples(PARALLEL_MAPPING_THRESHOLD * 2);
+
+    let sequential = snap_to_leds(&samples, &coordinates, false);
+    let parallel = snap_to_leds(&samples, &coordinates, true);
+
+    assert_eq!(sequential.len(), samples.len());
+    assert_eq!(
+        bincode::serialize(&sequential).unwrap(),
+        bincode::serialize(&parallel).unwrap()
+    );
+}
+
+fn sample(driver_number: u32, millis: i64) -> LocationData {
+    common::sample(millis, driver_number, 0.0, 0.0)
+}
+
+#[test]
+fn downsampling_keeps_the_samples_closest_to_each_second() {
+    // Driver 1 at about 4 Hz, driver 44 with two samples close together
+    let mut samples = vec![
+        sample(1, 0),
+        sample(1, 270),
+        sample(1, 540),
+        sample(1, 810),
+        sample(44, 900),
+        sample(1, 1080),
+        sample(44, 950),
+        sample(1, 1350),
+        sample(1, 1620),
+        sample(1, 1890),
+        sample(1, 2160),
+        sample(1, 2300),
+    ];
+    samples.sort_by_key(|sample| sample.date);
+
+    let removed = downsample(&mut samples, Duration::seconds(1));
+
+    let kept: Vec<(u32, i64)> = samples
+        .iter()
+        .map(|sample| {
+            (
+                sample.driver_number,
+                (sample.date - start()).num_milliseconds(),
+            )
+        })
+        .collect();
+    // 1080 is closest to 1s and 1890 to 2s, but the last sample always stays and takes the
+    // 2s slot; both of 44's samples are its first and last
+    assert_eq!(kept, [(1, 0), (44, 900), (44, 950), (1, 1080), (1, 2300)]);
+    assert_eq!(removed, 7);
+}
+
+fn at(driver_number: u32, millis: i64, x: f64, y: f64) -> LocationData {
+    common::sample(millis, driver_number, x, y)
+}
+
+#[test]
+fn fills_a_short_gap_by_interpolation() {
+    let mut samples = vec![at(1, 0, 100.0, 200.0), at(1, 2000, 300.0, 600.0)];
+
+    let added = fill_gaps(
+        &mut samples,
+        Duration::seconds(1),
+        Duration::seconds(5),
+        Duration::milliseconds(500),
+    );
+
+    assert_eq!(added, 3);
+    let filled: Vec<(i64, f64, f64, bool)> = samples
+        .iter()
+        .map(|sample| {
+            (
+                (sample.date - start()).num_milliseconds(),
+                sample.point.x,
+                sample.point.y,
+                sample.synthetic,
+            )
+        })
+        .collect();
+    assert_eq!(
+        filled,
+        [
+            (0, 100.0, 200.0, false),
+            (500, 150.0, 300.0, true),
+            (1000, 200.0, 400.0, true),
+            (1500, 250.0, 500.0, true),
+            (2000, 300.0, 600.0, false),
+        ]
+    );
+}
+
+#[test]
+fn leaves_a_red_flag_gap_alone() {
+    // Driver 44 keeps sending in between, which mustn't hide driver 1's gap
+    let mut samples = vec![
+        at(1, 0, 100.0, 200.0),
+        at(44, 60_000, 0.0, 0.0),
+        at(1, 120_000, 300.0, 600.0),
+    ];
+
+    let added = fill_gaps(
+        &mut samples,
+        Duration::seconds(1),
+        Duration::seconds(5),
+        Duration::milliseconds(270),
+    );
+
+    assert_eq!(added, 0);
+    assert_eq!(samples.len(), 3);
+}
+
+#[test]
+fn aligns_skewed_drivers_onto_one_grid() {
+    // Driver 11 runs 130 ms behind driver 1, and stops sending for two minutes
+    let mut samples = vec![
+        at(1, 0, 0.0, 0.0),
+        at(11, 130, 0.0, 0.0),
+        at(1, 500, 50.0, 0.0),
+        at(11, 630, 100.0, 0.0),
+        at(1, 1000, 100.0, 0.0),
+        at(11, 120_630, 200.0, 0.0),
+    ];
+    samples.sort_by_key(|sample| sample.date);
+
+    let aligned = align_to_grid(
+        &samples,
+        start(),
+        Duration::milliseconds(250),
+        Duration::seconds(5),
+    );
+
+    let grid: Vec<(i64, u32, f64)> = aligned
+        .iter()
+        .map(|sample| {
+            (
+                (sample.date - start()).num_milliseconds(),
+                sample.driver_number,
+                sample.point.x,
+            )
+        })
+        .collect();
+    assert_eq!(
+        grid,
+        [
+            (0, 1, 0.0),
+            (250, 1, 25.0),
+            (250, 11, 24.0),
+            (500, 1, 50.0),
+            (500, 11, 74.0),
+            (750, 1, 75.0),
+            (1000, 1, 100.0),
+        ]
+    );
+    assert!(aligned.iter().all(|sample| sample.synthetic));
+}
+
+#[test]
+fn mapping_a_driver_at_a_time_matches_mapping_all_at_once() {
+    let coordinates = read_coordinates().unwrap();
+    let samples = synthetic_samples(PARALLEL_MAPPING_THRESHOLD * 2);
+    let max_snap_distance = median_led_spacing(&coordinates) * SNAP_DISTANCE_FACTOR;
+    let (mut all_at_once, stats) =
+        generate_run_race_data(&samples, &coordinates, max_snap_distance).unwrap();
+    let mapped = all_at_once.len();
+    let collapsed = collapse_duplicate_positions(&mut all_at_once);
+
+    let mut per_driver: Vec<(u32, Vec<LocationData>)> = [1, 11, 44, 63]
+        .into_iter()
+        .map(|driver_number| (driver_number, Vec::new()))
+        .collect();
+    for sample in synthetic_samples(PARALLEL_MAPPING_THRESHOLD * 2) {
+        let (_, driver_samples) = per_driver
+            .iter_mut()
+            .find(|(driver_number, _)| *driver_number == sample.driver_number)
+            .unwrap();
+        driver_samples.push(sample);
+    }
+    let mut pipeline = PipelineStats::default();
+    let (per_driver_records, per_driver_stats) = map_drivers(
+        per_driver,
+        &coordinates,
+        &MappingOptions::default(),
+        &mut pipeline,
+    )
+    .unwrap();
+
+    assert_eq!(
+        bincode::serialize(&all_at_once).unwrap(),
+        bincode::serialize(&per_driver_records).unwrap()
+    );
+    assert_eq!(pipeline.mapped_samples, mapped);
+    assert_eq!(per_driver_stats.collapsed_samples, collapsed);
+    assert_eq!(per_driver_stats.dropped_samples, stats.dropped_samples);
+    assert_eq!(per_driver_stats.off_track_since, stats.off_track_since);
+}
+
+#[test]
+fn refuses_to_map_onto_a_layout_without_leds() {
+    let samples = synthetic_samples(4);
+    assert_eq!(
+        nearest_led(&[], LedPoint::new(1.0, 2.0)),
+        (0, f64::INFINITY)
+    );
+    assert!(matches!(
+        generate_run_race_data(&samples, &[], 10.0),
+        Err(AppError::LayoutInvalid { .. })
+    ));
+    let result = map_drivers(
+        vec![(1, samples)],
+        &[],
+        &MappingOptions::default(),
+        &mut PipelineStats::default(),
+    );
+    assert!(matches!(result, Err(AppError::LayoutInvalid { .. })));
+}
+
+#[test]
+fn cached_lookups_match_uncached_ones() {
+    let coordinates = read_coordinates().unwrap();
+    let index = NearestLedIndex::new(&coordinates);
+    let mut cache = NearestLedCache::new(&index);
+    // Scattered samples, each followed by a few close by that fall in the same cache cell or
+    // the next one
+    let mut rng = StdRng::seed_from_u64(848);
+    let points: Vec<TelemetryPoint> =
+        synthetic_samples(5000)
+            .into_iter()
+            .flat_map(|sample| {
+                let jitter: Vec<(f64, f64)> = (0..3)
+                    .map(|_| (rng.gen_range(-4.0..4.0), rng.gen_range(-4.0..4.0)))
+                    .collect();
+                std::iter::once(sample.point).chain(jitter.into_iter().map(move |(dx, dy)| {
+                    TelemetryPoint::new(sample.point.x + dx, sample.point.y + dy)
+                }))
+            })
+            .collect();
+
+    for point in points {
+        let led_point = LedPoint::new(point.x, point.y);
+        let uncached = nearest_led(&coordinates, led_point);
+        assert_eq!(index.nearest(led_point), uncached, "index at {:?}", point);
+        assert_eq!(cache.nearest(point), uncached, "cache at {:?}", point);
+    }
+}
+
+#[test]
+fn drops_samples_just_beyond_the_snap_distance() {
+    let coordinates = [LedPoint::new(0.0, 0.0), LedPoint::new(1000.0, 0.0)];
+    let samples = [
+        at(1, 0, 52.499_999, 0.0),
+        at(44, 0, 0.0, -52.499_999),
+        at(1, 100, 52.500_001, 0.0),
+        at(44, 100, 0.0, -52.500_001),
+    ];
+
+    let (records, stats) = generate_run_race_data(&samples, &coordinates, 52.5).unwrap();
+
+    let kept: Vec<(u32, usize)> = records
+        .iter()
+        .map(|record| (record.driver_number, record.led_index))
+        .collect();
+    assert_eq!(kept, [(1, 0), (44, 0)]);
+    assert_eq!(stats.max_snap_distance, 52.5);
+    assert_eq!(stats.dropped_samples, 2);
+    assert_eq!(stats.dropped_per_driver, HashMap::from([(1, 1), (44, 1)]));
+    assert_eq!(stats.off_track_since.len(), 2);
+}
+
+#[test]
+fn nearly_identical_positions_light_the_same_led() {
+    // Either side of zero, where scaling and truncating the coordinates used to split them
+    let coordinates = [
+        LedPoint::new(0.0, 0.0),
+        LedPoint::new(100.0, 0.0),
+        LedPoint::new(100.0, 100.0),
+    ];
+    let samples = [
+        at(1, 0, -1e-9, 1e-9),
+        at(1, 100, 1e-9, -1e-9),
+        at(1, 200, 0.0, 0.0),
+    ];
+
+    let (records, _) = generate_run_race_data(&samples, &coordinates, 50.0).unwrap();
+    let leds: Vec<usize> = records.iter().map(|record| record.led_index).collect();
+    assert_eq!(leds, [0, 0, 0]);
+
+    let mut simulation = Simulation::new(records, coordinates.len(), HashMap::from([(1, RED)]));
+    simulation.start();
+    simulation.tick(std::time::Duration::from_secs(1));
+    assert_eq!(simulation.frame().lit().collect::<Vec<_>>(), [(0, RED)]);
+}
+
+// LED changes of a car crawling from x 400 to 600 across the boundary between the LEDs at 0 and
+// 1000, its position jittering 150 either way
+fn led_changes(smoothing: Smoothing) -> usize {
+    let coordinates: Vec<LedPoint> = (0..10)
+        .map(|index| LedPoint::new(index as f64 * 1000.0, 0.0))
+        .collect();
+    let samples = (0..40)
+        .map(|index| {
+            let jitter = if index % 2 == 0 { 150.0 } else { -150.0 };
+            at(1, index * 270, 400.0 + index as f64 * 5.0 + jitter, 0.0)
+        })
+        .collect();
+    let options = MappingOptions {
+        smoothing,
+        smoothing_window: 6,
+        ..MappingOptions::default()
+    };
+    let (records, _) = map_drivers(
+        vec![(1, samples)],
+        &coordinates,
+        &options,
+        &mut PipelineStats::default(),
+    )
+    .unwrap();
+    // Repeated positions are collapsed, so every record after the first is a change
+    records.len() - 1
+}
+
+#[test]
+fn smoothing_stops_a_car_flickering_between_two_leds() {
+    let raw = led_changes(Smoothing::Off);
+    assert!(raw > 10, "{} changes", raw);
+    assert_eq!(led_changes(Smoothing::MovingAverage), 1);
+    assert!(led_changes(Smoothing::Exponential) < raw);
+}
+
+// Two minutes without data, after which the car shows up on the other side of the track
+fn red_flag_trace() -> Vec<LocationData> {
+    vec![
+        at(1, 0, 100.0, 0.0),
+        at(1, 270, 110.0, 0.0),
+        at(1, 540, 120.0, 0.0),
+        at(1, 120_540, 900.0, 500.0),
+        at(1, 120_810, 910.0, 500.0),
+    ]
+}
+
+#[test]
+fn smoothing_starts_afresh_after_a_red_flag() {
+    let raw = red_flag_trace();
+    for smoothing in [Smoothing::MovingAverage, Smoothing::Exponential] {
+        let mut samples = red_flag_trace();
+        smooth(&mut samples, smoothing, 5, 0.3, Duration::seconds(5));
+
+        let dates: Vec<_> = samples.iter().map(|sample| sample.date).collect();
+        let raw_dates: Vec<_> = raw.iter().map(|sample| sample.date).collect();
+        assert_eq!(dates, raw_dates);
+        // Nothing before the gap is pulled towards after it, nor the other way
+        assert!(samples[..3].iter().all(|sample| sample.point.x <= 120.0));
+        assert!(samples[3..].iter().all(|sample| sample.point.x >= 900.0));
+    }
+    let mut samples = red_flag_trace();
+    smooth(
+        &mut samples,
+        Smoothing::Exponential,
+        5,
+        0.3,
+        Duration::seconds(5),
+    );
+    assert_eq!(samples[3].point, raw[3].point);
+}
+
+// LEDs a car sitting between two of them is put on, its position flickering a unit either way
+// of the boundary
+fn boundary_leds(hysteresis_margin: Option<f64>, hysteresis_ratio: Option<f64>) -> Vec<usize> {
+    let coordinates = vec![LedPoint::new(0.0, 0.0), LedPoint::new(1005.0, 0.0)];
+    let samples = (0..20)
+        .map(|index| {
+            let x = if index % 2 == 0 { 501.5 } else { 503.5 };
+            at(1, index * 270, x, 0.0)
+        })
+        .collect();
+    let options = MappingOptions {
+        collapse_duplicates: false,
+        hysteresis_margin,
+        hysteresis_ratio,
+        ..MappingOptions::default()
+    };
+    let (records, _) = map_drivers(
+        vec![(1, samples)],
+        &coordinates,
+        &options,
+        &mut PipelineStats::default(),
+    )
+    .unwrap();
+    records.iter().map(|record| record.led_index).collect()
+}
+
+#[test]
+fn hysteresis_keeps_a_car_on_one_led_at_a_boundary() {
+    let flapping = boundary_leds(None, None);
+    assert!(flapping.windows(2).all(|pair| pair[0] != pair[1]));
+
+    for (margin, ratio) in [
+        (Some(5.0), None),
+        (None, Some(1.01)),
+        (Some(5.0), Some(1.01)),
+    ] {
+        let leds = boundary_leds(margin, ratio);
+        assert_eq!(leds.len(), 20);
+        assert!(leds.iter().all(|&led| led == leds[0]), "{:?}", leds);
+    }
+    // A margin smaller than the flicker doesn't hold the car
+    assert_eq!(boundary_leds(Some(1.0), None), flapping);
+}
+
+#[test]
+fn averages_the_elevation_snapped_to_each_led() {
+    let coordinates = vec![
+        LedPoint::new(0.0, 0.0),
+        LedPoint::new(1000.0, 0.0),
+        LedPoint::new(2000.0, 0.0),
+    ];
+    let with_z = |millis: i64, x: f64, z: Option<f64>| LocationData {
+        z,
+        ..at(1, millis, x, 0.0)
+    };
+    let samples = vec![
+        with_z(0, 10.0, Some(4.0)),
+        with_z(250, 20.0, Some(6.0)),
+        with_z(500, 990.0, None),
+        with_z(750, 1010.0, Some(-3.0)),
+    ];
+    let (_, stats) = map_drivers(
+        vec![(1, samples)],
+        &coordinates,
+        &MappingOptions::default(),
+        &mut PipelineStats::default(),
+    )
+    .unwrap();
+
+    assert_eq!(stats.led_elevation, [Some(5.0), Some(-3.0), None]);
+}
+
+#[test]
+fn blend_weights_share_a_car_between_its_two_nearest_leds() {
+    let coordinates: Vec<LedPoint> = (0..96)
+        .map(|index| LedPoint::new(index as f64 * 10.0, 0.0))
+        .collect();
+
+    let weights = blend_weights(&coordinates, 12, TelemetryPoint::new(123.0, 0.0));
+    assert_eq!(weights.len(), 2);
+    assert_eq!((weights[0].0, weights[1].0), (12, 13));
+    assert!((weights[0].1 - 0.7).abs() < 1e-6, "{:?}", weights);
+
+    // Whichever side the car is on and however far off the strip, the shares add up to 1
+    let mut rng = StdRng::seed_from_u64(7);
+    for _ in 0..1000 {
+        let led_index = rng.gen_range(0..coordinates.len());
+        let point = TelemetryPoint::new(
+            led_index as f64 * 10.0 + rng.gen_range(-5.0..5.0),
+            rng.gen_range(-3.0..3.0),
+        );
+        let weights = blend_weights(&coordinates, led_index, point);
+        let total: f32 = weights.iter().map(|&(_, share)| share).sum();
+        assert!((total - 1.0).abs() < 1e-5, "{:?}", weights);
+        assert!(weights
+            .iter()
+            .all(|&(_, share)| (0.0..=1.0).contains(&share)));
+    }
+
+    // A car right on its LED lights just that one
+    assert_eq!(
+        blend_weights(&coordinates, 40, TelemetryPoint::new(400.0, 0.0)),
+        [(40, 1.0)]
+    );
+    assert_eq!(
+        blend_weights(&coordinates[..1], 0, TelemetryPoint::new(4.0, 0.0)),
+        [(0, 1.0)]
+    );
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use common::{at, lap};
+use f1_led_circuit_master_simulation::data::{LapData, RaceControlData};
+use f1_led_circuit_master_simulation::laps::RaceProgress;
+use f1_led_circuit_master_simulation::markers::{event_markers, timeline_x, MarkerKind};
+use f1_led_circuit_master_simulation::overtakes::Overtake;
+
+fn message(secs: i64, category: &str, flag: Option<&str>, sector: Option<u32>) -> RaceControlData {
+    RaceControlData {
+        date: at(secs),
+        category: category.to_string(),
+        flag: flag.map(str::to_string),
+        scope: Some(if sector.is_some() { "Sector" } else { "Track" }.to_string()),
+        sector,
+        driver_number: None,
+        message: String::new(),
+    }
+}
+
+fn safety_car(secs: i64, text: &str) -> RaceControlData {
+    RaceControlData {
+        message: text.to_string(),
+        scope: None,
+        ..message(secs, "SafetyCar", None, None)
+    }
+}
+
+#[test]
+fn marks_the_events_and_spans_the_periods() {
+    let progress = RaceProgress::new(
+        vec![LapData {
+            is_pit_out_lap: true,
+            ..lap(44, 20, 1500)
+        }],
+        Vec::new(),
+    )
+    .with_race_control(vec![
+        message(100, "Flag", Some("YELLOW"), Some(7)),
+        message(130, "Flag", Some("CLEAR"), Some(7)),
+        message(200, "Flag", Some("DOUBLE YELLOW"), Some(3)),
+        safety_car(210, "SAFETY CAR DEPLOYED"),
+        message(400, "Flag", Some("RED"), None),
+        safety_car(410, "SAFETY CAR IN THIS LAP"),
+        message(900, "Flag", Some("GREEN"), None),
+        safety_car(1200, "VIRTUAL SAFETY CAR DEPLOYED"),
+    ]);
+    let overtakes = [Overtake {
+        date: at(1600),
+        driver_number: 1,
+        overtaken: 11,
+        position: 2,
+        led_index: 40,
+    }];
+
+    // Seconds from a first record a minute in
+    let markers = event_markers(&progress, &overtakes, at(60));
+    let summary: Vec<(MarkerKind, f64, f64, &str)> = markers
+        .iter()
+        .map(|marker| (marker.kind, marker.start, marker.end, marker.label.as_str()))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (MarkerKind::Yellow, 40.0, 70.0, "Yellow flag in sector 7"),
+            (MarkerKind::Yellow, 140.0, 840.0, "Yellow flag in sector 3"),
+            (MarkerKind::Yellow, 150.0, 350.0, "Safety car"),
+            (MarkerKind::Red, 340.0, 840.0, "Red flag"),
+            (
+                MarkerKind::Yellow,
+                1140.0,
+                f64::INFINITY,
+                "Virtual safety car"
+            ),
+            (MarkerKind::PitStop, 1440.0, 1440.0, "44 left the pits"),
+            (MarkerKind::Overtake, 1540.0, 1540.0, "1 passed 11 for P2"),
+        ]
+    );
+}
+
+#[test]
+fn places_times_along_the_bar() {
+    assert_eq!(timeline_x(30.0, 120.0, 100.0, 500.0), 200.0);
+    // Past either end sits on that end; an empty session puts everything at the start
+    assert_eq!(timeline_x(-5.0, 120.0, 100.0, 500.0), 100.0);
+    assert_eq!(timeline_x(f64::INFINITY, 120.0, 100.0, 500.0), 500.0);
+    assert_eq!(timeline_x(30.0, 0.0, 100.0, 500.0), 100.0);
+}
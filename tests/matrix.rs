@@ -0,0 +1,111 @@
+use f1_led_circuit_master_simulation::error::AppError;
+use f1_led_circuit_master_simulation::matrix::{LedGrid, MatrixConfig, MatrixSink};
+use f1_led_circuit_master_simulation::simulation::{PlaybackState, Rgb};
+use f1_led_circuit_master_simulation::sink::{LedSink, OutputFrame};
+use f1_led_circuit_master_simulation::space::LedPoint;
+use std::sync::mpsc::{self, Sender};
+use std::time::Duration;
+
+const RED: Rgb = [255, 0, 0];
+const DIM: Rgb = [10, 10, 10];
+
+fn coord(x: f64, y: f64) -> LedPoint {
+    LedPoint::new(x, y)
+}
+
+// The corners of a 10x10 square and two LEDs close to its center
+fn layout() -> Vec<LedPoint> {
+    vec![
+        coord(0.0, 10.0),
+        coord(10.0, 10.0),
+        coord(0.0, 0.0),
+        coord(10.0, 0.0),
+        coord(5.0, 5.0),
+        coord(5.2, 4.9),
+    ]
+}
+
+fn config(serpentine: bool) -> MatrixConfig {
+    MatrixConfig {
+        enabled: true,
+        width: 3,
+        height: 3,
+        serpentine,
+    }
+}
+
+#[test]
+fn projects_the_layout_onto_the_nearest_cells() {
+    let grid = LedGrid::new(&layout(), &config(false)).unwrap();
+
+    assert_eq!(
+        grid.cells(),
+        [(0, 0), (2, 0), (0, 2), (2, 2), (1, 1), (1, 1)]
+    );
+    assert_eq!(grid.pixel_count(), 9);
+}
+
+#[test]
+fn fills_the_buffer_row_by_row_or_serpentine() {
+    let leds = [RED, [0, 0, 0], [0, 0, 0], [0, 0, 0], DIM, RED];
+
+    let pixels = LedGrid::new(&layout(), &config(false)).unwrap().map(&leds);
+    assert_eq!(pixels[0], RED);
+    // The brighter of the two center LEDs wins the shared cell
+    assert_eq!(pixels[4], RED);
+    assert_eq!(
+        pixels.iter().filter(|&&pixel| pixel != [0, 0, 0]).count(),
+        2
+    );
+
+    let serpentine = LedGrid::new(&layout(), &config(true)).unwrap();
+    // The middle row runs backwards, the others don't
+    assert_eq!(serpentine.pixel(0, 1), 5);
+    assert_eq!(serpentine.pixel(2, 1), 3);
+    assert_eq!(serpentine.pixel(2, 2), 8);
+}
+
+#[test]
+fn rejects_an_empty_grid() {
+    let config = MatrixConfig {
+        width: 0,
+        ..config(false)
+    };
+    assert!(matches!(
+        LedGrid::new(&layout(), &config),
+        Err(AppError::Config { .. })
+    ));
+}
+
+struct Capture(Sender<Vec<Rgb>>);
+
+impl LedSink for Capture {
+    fn name(&self) -> &str {
+        "capture"
+    }
+
+    fn submit(&mut self, frame: &OutputFrame) -> Result<(), AppError> {
+        self.0.send(frame.leds.clone()).unwrap();
+        Ok(())
+    }
+}
+
+#[test]
+fn passes_the_pixel_buffer_on() {
+    let (sender, receiver) = mpsc::channel();
+    let grid = LedGrid::new(&layout(), &config(false)).unwrap();
+    let mut sink = MatrixSink::new(Box::new(Capture(sender)), grid);
+
+    let frame = OutputFrame {
+        leds: vec![RED; 6],
+        brightness: 1.0,
+        timestamp: Duration::ZERO,
+        state: PlaybackState::Playing,
+        speed: 1.0,
+        drivers: Vec::new(),
+    };
+    sink.submit(&frame).unwrap();
+    let pixels = receiver.try_recv().unwrap();
+    assert_eq!(pixels.len(), 9);
+    assert_eq!(sink.name(), "capture");
+}
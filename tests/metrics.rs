@@ -0,0 +1,96 @@
+#![cfg(feature = "metrics")]
+
+use f1_led_circuit_master_simulation::metrics::{
+    self, MetricsConfig, MetricsServer, API_FAILURES, API_REQUESTS, FRAMES_RENDERED,
+    SINK_FRAMES_SENT, SINK_FRAMES_SKIPPED, TICK_DURATION, UPTIME,
+};
+use f1_led_circuit_master_simulation::simulation::{PlaybackState, Simulation};
+use f1_led_circuit_master_simulation::sink::{FrameDispatcher, OutputFrame};
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use std::collections::HashMap;
+use std::time::Duration;
+
+fn config() -> MetricsConfig {
+    MetricsConfig {
+        enabled: true,
+        bind: "127.0.0.1".to_string(),
+        port: 0,
+    }
+}
+
+#[test]
+fn serves_the_metrics_for_scraping() {
+    let handle = metrics::install().unwrap();
+    let server = MetricsServer::open(&config(), metrics::routes(handle)).unwrap();
+
+    let mut simulation = Simulation::new(Vec::new(), 4, HashMap::new());
+    simulation.start();
+    simulation.tick(Duration::from_millis(16));
+    FrameDispatcher::default().dispatch(OutputFrame {
+        leds: vec![[0, 0, 0]; 4],
+        brightness: 1.0,
+        timestamp: Duration::ZERO,
+        state: PlaybackState::Playing,
+        speed: 1.0,
+        drivers: Vec::new(),
+    });
+    metrics::sink_frame("wled", true);
+    metrics::sink_frame("wled", false);
+    metrics::api_request(true);
+    metrics::api_request(false);
+
+    let response = Client::new()
+        .get(format!("http://{}/metrics", server.address()))
+        .send()
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.text().unwrap();
+    for name in [
+        FRAMES_RENDERED,
+        SINK_FRAMES_SENT,
+        SINK_FRAMES_SKIPPED,
+        API_REQUESTS,
+        API_FAILURES,
+        UPTIME,
+    ] {
+        assert!(body.contains(name), "{} missing from:\n{}", name, body);
+    }
+    // A histogram, not a summary
+    assert!(
+        body.contains(&format!("{}_bucket", TICK_DURATION)),
+        "{}",
+        body
+    );
+    assert!(body.contains(r#"sink="wled""#), "{}", body);
+}
+
+#[cfg(feature = "http-control")]
+#[test]
+fn shares_the_control_api_and_its_token() {
+    use f1_led_circuit_master_simulation::control::ControlConfig;
+    use f1_led_circuit_master_simulation::http_control::ControlServer;
+    use f1_led_circuit_master_simulation::sink::LedSink;
+    use std::sync::mpsc;
+
+    let control = ControlConfig {
+        enabled: true,
+        bind: "127.0.0.1".to_string(),
+        port: 0,
+        token: Some("secret".to_string()),
+    };
+    let routes = metrics::routes(metrics::install().unwrap());
+    let (sender, _commands) = mpsc::channel();
+    let mut server = ControlServer::open_with_routes(&control, sender, 10, 96, routes).unwrap();
+    let url = format!("http://{}/metrics", server.address());
+    let client = Client::new();
+
+    assert_eq!(
+        client.get(&url).send().unwrap().status(),
+        StatusCode::UNAUTHORIZED
+    );
+    let response = client.get(&url).bearer_auth("secret").send().unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.text().unwrap().contains(UPTIME));
+    server.shutdown();
+}
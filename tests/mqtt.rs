@@ -0,0 +1,104 @@
+#![cfg(feature = "native")]
+
+use chrono::{DateTime, Utc};
+use f1_led_circuit_master_simulation::mqtt::{MqttConfig, MqttPublisher};
+use f1_led_circuit_master_simulation::overtakes::Overtake;
+use f1_led_circuit_master_simulation::race_events::RaceEvent;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+// Reads one MQTT packet: its type and flags byte and its body
+fn read_packet(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+    let mut header = [0u8; 1];
+    stream.read_exact(&mut header).unwrap();
+    let mut length = 0;
+    for shift in (0..).step_by(7) {
+        let mut byte = [0u8; 1];
+        stream.read_exact(&mut byte).unwrap();
+        length |= ((byte[0] & 0x7f) as usize) << shift;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+    }
+    let mut body = vec![0; length];
+    stream.read_exact(&mut body).unwrap();
+    (header[0], body)
+}
+
+// Accepts the publisher's connection like a broker and returns the topic and payload of the
+// first message published at QoS 0
+fn receive_publish(listener: &TcpListener) -> (String, serde_json::Value) {
+    let (mut stream, _) = listener.accept().unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    loop {
+        match read_packet(&mut stream) {
+            (0x10, _) => stream.write_all(&[0x20, 0x02, 0x00, 0x00]).unwrap(), // CONNACK
+            (0xc0, _) => stream.write_all(&[0xd0, 0x00]).unwrap(),             // PINGRESP
+            (header, body) if header & 0xf0 == 0x30 => {
+                let topic_len = u16::from_be_bytes([body[0], body[1]]) as usize;
+                let topic = String::from_utf8(body[2..2 + topic_len].to_vec()).unwrap();
+                let payload = serde_json::from_slice(&body[2 + topic_len..]).unwrap();
+                return (topic, payload);
+            }
+            (header, _) => panic!("unexpected packet {:#04x}", header),
+        }
+    }
+}
+
+#[test]
+fn publishes_race_events_on_the_events_topic() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let publisher = MqttPublisher::connect(&MqttConfig {
+        enabled: true,
+        broker_url: format!("mqtt://{}", listener.local_addr().unwrap()),
+        client_id: "f1-led-test".to_string(),
+        ..MqttConfig::default()
+    })
+    .unwrap();
+
+    let date: DateTime<Utc> = "2023-08-27T13:00:07Z".parse().unwrap();
+    publisher.publish_event(&RaceEvent::PitStop {
+        driver_number: 44,
+        date,
+    });
+
+    let (topic, payload) = receive_publish(&listener);
+    assert_eq!(topic, "f1led/events");
+    assert_eq!(payload["event"], "pit_stop");
+    assert_eq!(payload["driver_number"], 44);
+    assert_eq!(
+        payload["date"]
+            .as_str()
+            .unwrap()
+            .parse::<DateTime<Utc>>()
+            .unwrap(),
+        date
+    );
+}
+
+#[test]
+fn names_each_kind_of_event() {
+    let kind = |event: &RaceEvent| serde_json::to_value(event).unwrap()["event"].clone();
+    assert_eq!(kind(&RaceEvent::Start), "start");
+    let overtake = serde_json::to_value(RaceEvent::Overtake(Overtake {
+        date: Utc::now(),
+        driver_number: 44,
+        overtaken: 1,
+        position: 2,
+        led_index: 17,
+    }))
+    .unwrap();
+    assert_eq!(overtake["event"], "overtake");
+    assert_eq!(overtake["overtaken"], 1);
+    assert_eq!(overtake["led_index"], 17);
+    assert_eq!(
+        kind(&RaceEvent::PitStop {
+            driver_number: 1,
+            date: Utc::now()
+        }),
+        "pit_stop"
+    );
+}
@@ -0,0 +1,52 @@
+use chrono::NaiveTime;
+use f1_led_circuit_master_simulation::night::{NightDimmer, NightSchedule};
+use std::time::{Duration, Instant};
+
+fn at(time: &str) -> NaiveTime {
+    time.parse().unwrap()
+}
+
+// From 22:00 to 07:00 at a quarter of the brightness
+fn schedule() -> NightSchedule {
+    NightSchedule {
+        start: at("22:00"),
+        end: at("07:00"),
+        brightness: 0.25,
+    }
+}
+
+#[test]
+fn a_night_runs_past_midnight() {
+    let schedule = schedule();
+    for time in ["22:00", "23:59:59", "00:00", "03:30", "06:59:59"] {
+        assert!(schedule.is_night(at(time)), "{} is at night", time);
+    }
+    for time in ["07:00", "12:00", "21:59:59"] {
+        assert!(!schedule.is_night(at(time)), "{} is during the day", time);
+    }
+    // Within one day too
+    let afternoon = NightSchedule {
+        start: at("13:00"),
+        end: at("15:00"),
+        ..schedule
+    };
+    assert!(afternoon.is_night(at("14:00")));
+    assert!(!afternoon.is_night(at("23:00")));
+}
+
+#[test]
+fn ramps_down_over_a_minute_and_back_when_overridden() {
+    let mut dimmer = NightDimmer::new(schedule());
+    let start = Instant::now();
+    let secs = |secs: u64| start + Duration::from_secs(secs);
+
+    assert_eq!(dimmer.level(at("21:59:59"), secs(0)), 1.0);
+    // Half a minute later it's half way down
+    assert!((dimmer.level(at("22:00:29"), secs(30)) - 0.625).abs() < 1e-6);
+    assert_eq!(dimmer.level(at("22:00:59"), secs(60)), 0.25);
+    assert_eq!(dimmer.level(at("23:00"), secs(120)), 0.25);
+
+    dimmer.set_overridden(true);
+    assert!((dimmer.level(at("23:00:15"), secs(135)) - 0.4375).abs() < 1e-6);
+    assert_eq!(dimmer.level(at("23:02"), secs(240)), 1.0);
+}
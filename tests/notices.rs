@@ -0,0 +1,28 @@
+use f1_led_circuit_master_simulation::cache::load_mapping;
+use f1_led_circuit_master_simulation::error::AppError;
+use f1_led_circuit_master_simulation::notices;
+
+// One test, so no other test takes its notices from the shared queue first
+#[test]
+fn queues_problems_for_the_window() {
+    let dir = std::env::temp_dir().join(format!("notices-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    // The current cache version followed by garbage
+    std::fs::write(
+        dir.join(format!("run_race_{:016x}.bin", 7)),
+        [10, 0xff, 0xff],
+    )
+    .unwrap();
+
+    assert!(load_mapping(&dir, 7).is_none());
+    notices::report(&AppError::Ghost {
+        reason: "driver 1 has no samples".to_string(),
+    });
+
+    let messages = notices::take();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(messages.len(), 2);
+    assert!(messages[0].starts_with("Cache problem: ignored the unreadable"));
+    assert!(messages[1].contains("driver 1 has no samples"));
+    assert!(notices::take().is_empty());
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use f1_led_circuit_master_simulation::mapping::RunRace;
+use f1_led_circuit_master_simulation::occupancy::Occupancy;
+use f1_led_circuit_master_simulation::space::TelemetryPoint;
+use f1_led_circuit_master_simulation::timeline::{DriverDistances, DriverTimelines, SpeedConfig};
+
+// Driver 44 sits on LED 0 for 2s and LED 1 for 30s before reaching LED 2; driver 1 moves
+// 0 -> 2 after 1.5s. LED 3 is never reached
+fn samples() -> Vec<RunRace> {
+    let record = |millis, driver_number, led_index: usize| RunRace {
+        point: TelemetryPoint::new(led_index as f64, 0.0),
+        ..common::record(millis, driver_number, led_index)
+    };
+    vec![
+        record(0, 44, 0),
+        record(0, 1, 0),
+        record(1_500, 1, 2),
+        record(2_000, 44, 1),
+        record(32_000, 44, 2),
+    ]
+}
+
+#[test]
+fn attributes_each_gap_to_the_earlier_sample() {
+    let occupancy = Occupancy::compute(&samples(), 4, 5.0);
+
+    assert_eq!(occupancy.drivers(), [1, 44]);
+    assert_eq!(occupancy.seconds(0, 44), 2.0);
+    assert_eq!(occupancy.seconds(0, 1), 1.5);
+    // The last sample of each driver has no successor
+    assert_eq!(occupancy.seconds(2, 1), 0.0);
+    assert_eq!(occupancy.seconds(2, 44), 0.0);
+}
+
+#[test]
+fn caps_long_gaps() {
+    let occupancy = Occupancy::compute(&samples(), 4, 5.0);
+
+    assert_eq!(occupancy.seconds(1, 44), 5.0);
+}
+
+#[test]
+fn lists_never_lit_leds() {
+    let occupancy = Occupancy::compute(&samples(), 4, 5.0);
+
+    // LED 2 only has last samples, but it was lit
+    assert_eq!(occupancy.never_lit(), [3]);
+}
+
+#[test]
+fn writes_a_row_per_led_and_a_column_per_driver() {
+    let mut csv = Vec::new();
+    Occupancy::compute(&samples(), 4, 5.0)
+        .write_csv(&mut csv)
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "led,1,44,total\n\
+         U1,1.500,2.000,3.500\n\
+         U2,0.000,5.000,5.000\n\
+         U3,0.000,0.000,0.000\n\
+         U4,0.000,0.000,0.000\n\
+         never_lit,2,1,1\n"
+    );
+}
+
+#[test]
+fn writes_the_distance_of_each_driver_after_the_leds() {
+    // LEDs 10m apart, round a 40m layout
+    let config = SpeedConfig {
+        meters_per_unit: 10.0,
+        ..SpeedConfig::default()
+    };
+    let distances = DriverDistances::new(&DriverTimelines::new(&samples()), &config);
+    let mut occupancy = Occupancy::compute(&samples(), 4, 5.0);
+    occupancy.set_distances(&distances, 4.0, &config);
+    let mut csv = Vec::new();
+    occupancy.write_csv(&mut csv).unwrap();
+
+    let csv = String::from_utf8(csv).unwrap();
+    assert!(
+        csv.ends_with("never_lit,2,1,1\ndistance_m,20,20,40\nlaps,0.5,0.5,\n"),
+        "{}",
+        csv
+    );
+}
@@ -0,0 +1,73 @@
+use f1_led_circuit_master_simulation::osc::{OscConfig, OscLedMode, OscSink};
+use f1_led_circuit_master_simulation::simulation::Rgb;
+use rosc::{decoder, OscPacket, OscType};
+use std::net::UdpSocket;
+use std::time::Duration;
+
+const FRAME: [Rgb; 2] = [[255, 0, 51], [0, 0, 0]];
+
+fn receive_bundle(mode: OscLedMode) -> Vec<(String, Vec<OscType>)> {
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    receiver
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .unwrap();
+    let mut sink = OscSink::open(&OscConfig {
+        enabled: true,
+        port: receiver.local_addr().unwrap().port(),
+        mode,
+        ..OscConfig::default()
+    })
+    .unwrap();
+
+    sink.show(&FRAME, 12.5, 3.0).unwrap();
+
+    let mut buffer = [0u8; 4096];
+    let len = receiver.recv(&mut buffer).unwrap();
+    match decoder::decode_udp(&buffer[..len]).unwrap().1 {
+        OscPacket::Bundle(bundle) => bundle
+            .content
+            .into_iter()
+            .map(|packet| match packet {
+                OscPacket::Message(message) => (message.addr, message.args),
+                OscPacket::Bundle(_) => panic!("nested bundle"),
+            })
+            .collect(),
+        OscPacket::Message(_) => panic!("expected a bundle"),
+    }
+}
+
+#[test]
+fn sends_one_message_per_led() {
+    let messages = receive_bundle(OscLedMode::PerLed);
+
+    assert_eq!(
+        messages,
+        [
+            ("/f1led/racetime".to_string(), vec![OscType::Float(12.5)]),
+            ("/f1led/speed".to_string(), vec![OscType::Float(3.0)]),
+            (
+                "/f1led/led/0".to_string(),
+                vec![
+                    OscType::Float(1.0),
+                    OscType::Float(0.0),
+                    OscType::Float(0.2)
+                ]
+            ),
+            ("/f1led/led/1".to_string(), vec![OscType::Float(0.0); 3]),
+        ]
+    );
+}
+
+#[test]
+fn sends_all_pixels_as_a_blob() {
+    let messages = receive_bundle(OscLedMode::Blob);
+
+    assert_eq!(messages.len(), 3);
+    assert_eq!(
+        messages[2],
+        (
+            "/f1led/leds".to_string(),
+            vec![OscType::Blob(vec![255, 0, 51, 0, 0, 0])]
+        )
+    );
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use common::{at_millis, record};
+use f1_led_circuit_master_simulation::data::PositionData;
+use f1_led_circuit_master_simulation::laps::RaceProgress;
+use f1_led_circuit_master_simulation::mapping::RunRace;
+use f1_led_circuit_master_simulation::overtakes::{detect_overtakes, OvertakeConfig};
+use f1_led_circuit_master_simulation::simulation::{Rgb, Simulation};
+use f1_led_circuit_master_simulation::space::TelemetryPoint;
+use f1_led_circuit_master_simulation::timeline::DriverTimelines;
+use std::collections::HashMap;
+use std::time::Duration;
+
+const RED: Rgb = [255, 0, 0];
+const BLUE: Rgb = [0, 0, 255];
+const WHITE: Rgb = [255, 255, 255];
+
+// Drivers 1 and 44 run side by side one LED per second over ten LEDs; driver 63 stays far
+// away on LED 9
+fn samples() -> Vec<RunRace> {
+    let mut samples = Vec::new();
+    for second in 0..10 {
+        for (driver_number, x) in [(1, 0.0), (44, 50.0), (63, 90_000.0)] {
+            let led_index = if driver_number == 63 { 9 } else { second };
+            samples.push(RunRace {
+                point: TelemetryPoint::new(x + second as f64 * 100.0, 0.0),
+                ..record(second as i64 * 1000, driver_number, led_index)
+            });
+        }
+    }
+    samples
+}
+
+fn position(millis: i64, driver_number: u32, position: u32) -> PositionData {
+    PositionData {
+        date: at_millis(millis),
+        driver_number,
+        position,
+    }
+}
+
+// Driver 1 passes driver 44 at 4s; driver 63 takes second place from driver 44 at 7s while far
+// away, as if 44 had pitted
+fn progress() -> RaceProgress {
+    RaceProgress::new(
+        Vec::new(),
+        vec![
+            position(0, 44, 1),
+            position(0, 1, 2),
+            position(0, 63, 3),
+            position(4000, 1, 1),
+            position(4000, 44, 2),
+            position(7000, 63, 2),
+            position(7000, 44, 3),
+        ],
+    )
+}
+
+fn simulation() -> Simulation {
+    let samples = samples();
+    let overtakes = detect_overtakes(
+        &progress(),
+        &DriverTimelines::new(&samples),
+        &OvertakeConfig::default(),
+        0.1,
+    );
+    let mut simulation = Simulation::new(samples, 10, HashMap::from([(1, RED), (44, BLUE)]));
+    simulation.set_overtakes(overtakes, 10);
+    simulation
+}
+
+fn led(simulation: &Simulation, index: usize) -> Option<Rgb> {
+    simulation.frame().leds[index]
+}
+
+#[test]
+fn detects_passes_between_close_cars() {
+    let samples = samples();
+    let overtakes = detect_overtakes(
+        &progress(),
+        &DriverTimelines::new(&samples),
+        &OvertakeConfig::default(),
+        0.1,
+    );
+
+    assert_eq!(overtakes.len(), 1);
+    assert_eq!(
+        (overtakes[0].driver_number, overtakes[0].overtaken),
+        (1, 44)
+    );
+    assert_eq!(overtakes[0].position, 1);
+    assert_eq!(overtakes[0].led_index, 4);
+}
+
+#[test]
+fn blinks_three_times_over_a_second_of_race_time() {
+    let mut simulation = simulation();
+    simulation.start();
+    simulation.seek(Duration::from_millis(4050));
+    assert_eq!(led(&simulation, 4), Some(WHITE));
+
+    // The animation follows race time, so at double speed it takes half a real second.
+    // Without it the LED would show driver 44, who shares it
+    simulation.set_speed(2.0);
+    simulation.tick(Duration::from_millis(100));
+    assert_eq!(led(&simulation, 4), Some(RED));
+    simulation.tick(Duration::from_millis(75));
+    assert_eq!(led(&simulation, 4), Some(WHITE));
+    simulation.tick(Duration::from_millis(350));
+    assert_eq!(led(&simulation, 4), None);
+    assert_eq!(simulation.overtakes().recent().count(), 1);
+}
+
+#[test]
+fn does_not_replay_after_seeking_back() {
+    let mut simulation = simulation();
+    simulation.start();
+    simulation.seek(Duration::from_millis(4900));
+    simulation.seek(Duration::from_millis(3000));
+    simulation.seek(Duration::from_millis(3500));
+    assert_eq!(led(&simulation, 4), None);
+
+    // Crossing it again plays it again
+    simulation.seek(Duration::from_millis(4050));
+    assert_eq!(led(&simulation, 4), Some(WHITE));
+}
+
+#[test]
+fn reset_clears_the_animations() {
+    let mut simulation = simulation();
+    simulation.start();
+    simulation.seek(Duration::from_millis(4050));
+    simulation.reset();
+
+    assert_eq!(led(&simulation, 4), None);
+    assert_eq!(simulation.overtakes().recent().count(), 0);
+}
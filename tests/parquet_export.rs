@@ -0,0 +1,91 @@
+#![cfg(feature = "native")]
+
+use arrow::array::{Float64Array, TimestampMicrosecondArray, UInt32Array};
+use arrow::datatypes::{DataType, TimeUnit};
+use chrono::{DateTime, Duration, Utc};
+use f1_led_circuit_master_simulation::mapping::RunRace;
+use f1_led_circuit_master_simulation::parquet_export::{ParquetJob, ROW_GROUP_ROWS};
+use f1_led_circuit_master_simulation::space::TelemetryPoint;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::basic::Compression;
+use std::fs::File;
+
+// Three drivers a quarter of a second apart, enough for a second row group
+fn samples() -> Vec<RunRace> {
+    let start: DateTime<Utc> = "2023-08-27T13:00:00.125Z".parse().unwrap();
+    (0..ROW_GROUP_ROWS + 1000)
+        .map(|index| RunRace {
+            date: start + Duration::milliseconds(250 * index as i64),
+            driver_number: [1, 11, 44][index % 3],
+            led_index: index % 96,
+            point: TelemetryPoint::new(index as f64 * 0.5, -(index as f64)),
+        })
+        .collect()
+}
+
+#[test]
+fn writes_typed_columns_that_read_back() {
+    let path = std::env::temp_dir().join(format!("{}-race.parquet", std::process::id()));
+    let samples = samples();
+    let job = ParquetJob::start(samples.clone(), path.clone()).unwrap();
+    assert_eq!(job.finish().unwrap(), path);
+
+    let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap()).unwrap();
+    assert_eq!(reader.metadata().num_row_groups(), 2);
+    assert!(matches!(
+        reader.metadata().row_group(0).column(0).compression(),
+        Compression::ZSTD(_)
+    ));
+    assert_eq!(
+        reader
+            .schema()
+            .field_with_name("timestamp")
+            .unwrap()
+            .data_type(),
+        &DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()))
+    );
+    let batches: Vec<_> = reader.build().unwrap().map(Result::unwrap).collect();
+    assert_eq!(
+        batches.iter().map(|batch| batch.num_rows()).sum::<usize>(),
+        samples.len()
+    );
+
+    // The first rows and one from the second row group
+    let last = batches.last().unwrap();
+    for (batch, row, sample) in [
+        (&batches[0], 0, &samples[0]),
+        (&batches[0], 5, &samples[5]),
+        (last, last.num_rows() - 1, samples.last().unwrap()),
+    ] {
+        let timestamps = batch
+            .column_by_name("timestamp")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<TimestampMicrosecondArray>()
+            .unwrap();
+        assert_eq!(timestamps.value(row), sample.date.timestamp_micros());
+        let u32s = |name: &str| {
+            batch
+                .column_by_name(name)
+                .unwrap()
+                .as_any()
+                .downcast_ref::<UInt32Array>()
+                .unwrap()
+                .value(row)
+        };
+        assert_eq!(u32s("driver_number"), sample.driver_number);
+        assert_eq!(u32s("led_index") as usize, sample.led_index);
+        let f64s = |name: &str| {
+            batch
+                .column_by_name(name)
+                .unwrap()
+                .as_any()
+                .downcast_ref::<Float64Array>()
+                .unwrap()
+                .value(row)
+        };
+        assert_eq!(f64s("x"), sample.point.x);
+        assert_eq!(f64s("y"), sample.point.y);
+    }
+    std::fs::remove_file(&path).unwrap();
+}
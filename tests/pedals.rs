@@ -0,0 +1,102 @@
+mod common;
+
+use common::{at_millis, record};
+use f1_led_circuit_master_simulation::data::CarData;
+use f1_led_circuit_master_simulation::pedals::{
+    tint_trail, PedalConfig, PedalTrace, BRAKE_COLOR, THROTTLE_COLOR,
+};
+
+const LED_COUNT: usize = 10;
+
+fn car_data(millis: i64, throttle: f64, brake: f64) -> CarData {
+    CarData {
+        date: at_millis(millis),
+        driver_number: 1,
+        speed: None,
+        n_gear: None,
+        throttle: Some(throttle),
+        brake: Some(brake),
+    }
+}
+
+// Driver 1 accelerates over LEDs 0 and 1, brakes jumping to LED 4, coasts onto 5 and is back on
+// the throttle on 6; their last record comes long after the car data ends
+fn trace() -> PedalTrace {
+    let run_race_data = vec![
+        record(0, 1, 0),
+        record(500, 2, 7),
+        record(1000, 1, 1),
+        record(2000, 1, 4),
+        record(3000, 1, 5),
+        record(4000, 1, 6),
+        record(10_000, 1, 9),
+    ];
+    let car_data = vec![
+        car_data(-500, 100.0, 0.0),
+        car_data(800, 100.0, 0.0),
+        car_data(1500, 0.0, 100.0),
+        car_data(2500, 10.0, 0.0),
+        car_data(3500, 50.0, 0.0),
+    ];
+    PedalTrace::new(1, &run_race_data, &car_data, LED_COUNT)
+}
+
+#[test]
+fn matches_each_record_to_the_car_data_before_it() {
+    let trace = trace();
+
+    assert_eq!(trace.at(at_millis(-1000)), None);
+    let sample = trace.at(at_millis(2500)).unwrap();
+    assert_eq!((sample.led_index, sample.braking), (4, true));
+    let sample = trace.at(at_millis(3000)).unwrap();
+    assert_eq!((sample.led_index, sample.throttle), (5, 10.0));
+    // Without car data for seconds, the last record isn't matched to stale pedals
+    assert_eq!(trace.at(at_millis(20_000)).unwrap().led_index, 6);
+}
+
+#[test]
+fn trails_behind_the_car_colored_by_the_pedals() {
+    let trace = trace();
+    let config = PedalConfig::default();
+
+    // The LEDs jumped over on the way to LED 4 were braked over too
+    assert_eq!(
+        trace.trail(at_millis(4000), 10, &config),
+        [
+            (5, None),
+            (4, Some(BRAKE_COLOR)),
+            (3, Some(BRAKE_COLOR)),
+            (2, Some(BRAKE_COLOR)),
+            (1, Some(THROTTLE_COLOR)),
+            (0, Some(THROTTLE_COLOR)),
+        ]
+    );
+    assert_eq!(
+        trace.trail(at_millis(1000), 10, &config),
+        [(0, Some(THROTTLE_COLOR))]
+    );
+    assert_eq!(trace.trail(at_millis(4000), 2, &config).len(), 2);
+    assert!(PedalTrace::default()
+        .trail(at_millis(0), 10, &config)
+        .is_empty());
+}
+
+#[test]
+fn tints_only_leds_without_a_car() {
+    let blue = [0, 0, 255];
+    let mut leds = vec![None; LED_COUNT];
+    leds[3] = Some(blue);
+    let trail = [
+        (5, None),
+        (4, Some(BRAKE_COLOR)),
+        (3, Some(BRAKE_COLOR)),
+        (2, Some(THROTTLE_COLOR)),
+    ];
+
+    tint_trail(&mut leds, &trail);
+
+    assert_eq!(leds[5], None);
+    assert_eq!(leds[4], Some([165, 0, 0]));
+    assert_eq!(leds[3], Some(blue));
+    assert_eq!(leds[2], Some([0, 50, 0]));
+}
@@ -0,0 +1,168 @@
+mod common;
+
+use common::record;
+use f1_led_circuit_master_simulation::mapping::RunRace;
+use f1_led_circuit_master_simulation::playback::{seek_index_for_time, Playback};
+use std::time::Duration;
+
+// One record a second for `count` seconds
+fn records(count: usize) -> Vec<RunRace> {
+    (0..count)
+        .map(|index| record(index as i64 * 1000, 1, index))
+        .collect()
+}
+
+fn secs(seconds: f64) -> Duration {
+    Duration::from_secs_f64(seconds)
+}
+
+#[test]
+fn plays_only_once_started() {
+    let data = records(5);
+    let mut playback = Playback::default();
+
+    assert!(!playback.update(secs(2.0), &data));
+    assert_eq!(playback.race_time, 0.0);
+    assert_eq!(playback.current_index, 0);
+    assert!(!playback.is_finished(&data));
+
+    playback.start();
+    assert!(playback.update(secs(0.0), &data));
+    assert_eq!(playback.current_index, 1);
+    assert!(playback.update(secs(1.5), &data));
+    assert_eq!(playback.race_time, 1.5);
+    assert_eq!(playback.current_index, 2);
+
+    // Nothing new until the next record is due
+    assert!(!playback.update(secs(0.25), &data));
+    assert_eq!(playback.current_index, 2);
+
+    assert!(playback.update(secs(10.0), &data));
+    assert_eq!(playback.current_index, 5);
+    assert!(playback.is_finished(&data));
+}
+
+#[test]
+fn pausing_stops_the_clock_until_resumed() {
+    let data = records(5);
+    let mut playback = Playback::default();
+    playback.start();
+    playback.update(secs(1.0), &data);
+
+    playback.paused = true;
+    assert!(!playback.update(secs(3.0), &data));
+    assert_eq!(playback.race_time, 1.0);
+    assert_eq!(playback.current_index, 2);
+
+    playback.paused = false;
+    assert!(playback.update(secs(1.0), &data));
+    assert_eq!(playback.race_time, 2.0);
+    assert_eq!(playback.current_index, 3);
+
+    // Starting again rewinds and resumes
+    playback.paused = true;
+    playback.start();
+    assert!(!playback.paused);
+    assert_eq!(playback.race_time, 0.0);
+    assert_eq!(playback.current_index, 0);
+}
+
+#[test]
+fn speed_scales_the_clock() {
+    let data = records(10);
+    let mut playback = Playback {
+        speed: 4.0,
+        ..Playback::default()
+    };
+    playback.start();
+
+    playback.update(secs(0.5), &data);
+    assert_eq!(playback.race_time, 2.0);
+    assert_eq!(playback.current_index, 3);
+
+    // A one-off speed leaves the set one alone
+    playback.update_at(secs(0.5), 2.0, &data);
+    assert_eq!(playback.race_time, 3.0);
+    assert_eq!(playback.current_index, 4);
+    assert_eq!(playback.speed, 4.0);
+}
+
+#[test]
+fn seeks_forwards_and_backwards() {
+    let data = records(1000);
+    let mut playback = Playback::default();
+    playback.start();
+
+    // Far past the walk limit, so binary searched
+    playback.advance_to(700.5, &data);
+    assert_eq!(playback.current_index, 701);
+
+    playback.advance_to(3.0, &data);
+    assert_eq!(playback.current_index, 4);
+
+    playback.advance_to(-1.0, &data);
+    assert_eq!(playback.current_index, 0);
+
+    playback.advance_to(5000.0, &data);
+    assert!(playback.is_finished(&data));
+}
+
+#[test]
+fn reset_rewinds_to_a_stopped_clock() {
+    let data = records(5);
+    let mut playback = Playback::default();
+    playback.start();
+    playback.update(secs(3.0), &data);
+    playback.paused = true;
+
+    playback.reset();
+    assert!(!playback.race_started);
+    assert!(!playback.paused);
+    assert_eq!(playback.race_time, 0.0);
+    assert_eq!(playback.current_index, 0);
+    assert!(!playback.update(secs(1.0), &data));
+    assert!(!playback.is_finished(&data));
+}
+
+#[test]
+fn seeking_agrees_with_the_linear_walk_on_boundaries() {
+    // Uneven gaps, with two records sharing a timestamp
+    let offsets_ms = [0, 270, 270, 540, 1_000, 1_001, 2_750, 4_000];
+    let data: Vec<RunRace> = offsets_ms
+        .iter()
+        .enumerate()
+        .map(|(index, &offset_ms)| record(offset_ms, 1, index))
+        .collect();
+    let linear_walk = |race_time_ms: i64| {
+        let mut index = 0;
+        while index < data.len() && offsets_ms[index] <= race_time_ms {
+            index += 1;
+        }
+        index
+    };
+
+    for &offset_ms in &offsets_ms {
+        for race_time_ms in [offset_ms - 1, offset_ms, offset_ms + 1] {
+            let race_time = Duration::from_millis(race_time_ms.max(0) as u64);
+            let expected = linear_walk(race_time_ms.max(0));
+            assert_eq!(
+                seek_index_for_time(&data, race_time),
+                expected,
+                "seeking to {}ms",
+                race_time_ms
+            );
+
+            // The playback walks there from the start
+            let mut playback = Playback::default();
+            playback.start();
+            playback.advance_to(race_time.as_secs_f64(), &data);
+            assert_eq!(
+                playback.current_index, expected,
+                "walking to {}ms",
+                race_time_ms
+            );
+        }
+    }
+    assert_eq!(seek_index_for_time(&data, Duration::from_millis(270)), 3);
+    assert_eq!(seek_index_for_time(&[], Duration::from_secs(1)), 0);
+}
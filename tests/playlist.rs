@@ -0,0 +1,69 @@
+use f1_led_circuit_master_simulation::config::SessionConfig;
+use f1_led_circuit_master_simulation::playlist::{session_title, Playlist};
+
+fn session(key: &str) -> SessionConfig {
+    SessionConfig {
+        key: key.to_string(),
+        ..SessionConfig::default()
+    }
+}
+
+fn playlist(playing: &str) -> Playlist {
+    Playlist::new(
+        ["9149", "9157", "9165", "9173"].map(session).to_vec(),
+        playing,
+    )
+}
+
+#[test]
+fn moves_through_the_entries_past_skipped_ones() {
+    // Something off the list plays first, then the list from the top
+    let mut playlist = playlist("9000");
+    assert_eq!(playlist.position(), None);
+    assert_eq!(playlist.next(), Some(0));
+    assert_eq!(playlist.previous(), None);
+
+    playlist.play(1);
+    playlist.skip(2);
+    assert_eq!(playlist.next(), Some(3));
+    playlist.play(3);
+    assert_eq!(playlist.next(), None);
+    assert_eq!(playlist.previous(), Some(1));
+
+    // Playing a skipped entry on purpose counts it in again
+    playlist.play(2);
+    assert!(!playlist.is_skipped(2));
+    assert_eq!(playlist.previous(), Some(1));
+
+    let playlist = self::playlist("9165");
+    assert_eq!(playlist.position(), Some(2));
+    assert_eq!(playlist.next(), Some(3));
+}
+
+#[test]
+fn keeps_the_position_through_edits() {
+    let mut playlist = playlist("9165");
+    playlist.skip(3);
+    playlist.move_up(2);
+    assert_eq!(playlist.position(), Some(1));
+    assert_eq!(playlist.get(1).unwrap().key, "9165");
+    // Edits give the skipped entries another chance
+    assert_eq!(playlist.next(), Some(2));
+
+    playlist.remove(0);
+    assert_eq!(playlist.position(), Some(0));
+    playlist.remove(0);
+    assert_eq!(playlist.position(), None);
+    assert_eq!(playlist.next(), Some(0));
+    assert_eq!(playlist.get(0).unwrap().key, "9157");
+}
+
+#[test]
+fn titles_a_session_by_name_or_key() {
+    assert_eq!(session_title(&session("9149")), "Session 9149");
+    let named = SessionConfig {
+        name: Some("Zandvoort 2023".to_string()),
+        ..session("9149")
+    };
+    assert_eq!(session_title(&named), "Zandvoort 2023");
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use common::{at, record};
+use f1_led_circuit_master_simulation::data::PositionData;
+use f1_led_circuit_master_simulation::laps::RaceProgress;
+use f1_led_circuit_master_simulation::position_deltas::{
+    format_position_delta, PositionDeltas, FLASH_DURATION,
+};
+use f1_led_circuit_master_simulation::retirements::{RetirementConfig, Retirements};
+use f1_led_circuit_master_simulation::timeline::DriverTimelines;
+use std::time::{Duration, Instant};
+
+fn position(secs: i64, driver_number: u32, position: u32) -> PositionData {
+    PositionData {
+        date: at(secs),
+        driver_number,
+        position,
+    }
+}
+
+// Driver 11 passes driver 1 after 3s and driver 44 after 100s; the feed puts driver 11 back to
+// P3 after they stopped
+fn progress() -> RaceProgress {
+    RaceProgress::new(
+        Vec::new(),
+        vec![
+            position(0, 1, 1),
+            position(0, 11, 2),
+            position(0, 44, 3),
+            position(3, 11, 1),
+            position(3, 1, 2),
+            position(100, 44, 2),
+            position(100, 1, 3),
+            position(200, 11, 3),
+            position(200, 44, 1),
+        ],
+    )
+}
+
+// Driver 11's data ends after 100s, so they retire 60s later
+fn retirements() -> Retirements {
+    let mut race = Vec::new();
+    for secs in (0..=300).step_by(10) {
+        for driver_number in [1, 11, 44] {
+            if driver_number != 11 || secs <= 100 {
+                race.push(record(secs * 1000, driver_number, 0));
+            }
+        }
+    }
+    Retirements::new(&DriverTimelines::new(&race), &RetirementConfig::default())
+}
+
+#[test]
+fn counts_places_from_the_first_positions() {
+    let progress = progress();
+    let deltas = PositionDeltas::new(&progress);
+    let retirements = Retirements::default();
+
+    assert_eq!(deltas.starting_position(44), Some(3));
+    assert_eq!(deltas.delta(&progress, &retirements, 11, at(1)), Some(0));
+    assert_eq!(deltas.delta(&progress, &retirements, 11, at(50)), Some(1));
+    assert_eq!(deltas.delta(&progress, &retirements, 1, at(150)), Some(-2));
+    assert_eq!(deltas.delta(&progress, &retirements, 63, at(150)), None);
+
+    assert_eq!(format_position_delta(3), "▲3");
+    assert_eq!(format_position_delta(-2), "▼2");
+    assert_eq!(format_position_delta(0), "–");
+}
+
+#[test]
+fn keeps_the_position_a_driver_retired_in() {
+    let progress = progress();
+    let deltas = PositionDeltas::new(&progress);
+    let retirements = retirements();
+
+    assert_eq!(deltas.delta(&progress, &retirements, 11, at(250)), Some(1));
+    assert_eq!(deltas.delta(&progress, &retirements, 44, at(250)), Some(2));
+}
+
+#[test]
+fn flashes_rows_on_changes_while_playing_only() {
+    let progress = progress();
+    let mut deltas = PositionDeltas::new(&progress);
+    let retirements = Retirements::default();
+    let now = Instant::now();
+
+    deltas.update(&progress, &retirements, at(2), now);
+    assert_eq!(deltas.flash(11, now), None);
+    deltas.update(&progress, &retirements, at(4), now);
+    assert_eq!(deltas.flash(11, now), Some((true, 1.0)));
+    assert_eq!(deltas.flash(1, now), Some((false, 1.0)));
+    assert_eq!(deltas.flash(1, now + FLASH_DURATION), None);
+
+    // Seeking over the next change doesn't flash it, nor does going back
+    let later = now + FLASH_DURATION;
+    deltas.update(&progress, &retirements, at(150), later);
+    assert_eq!(deltas.flash(44, later), None);
+    deltas.update(&progress, &retirements, at(99), later);
+    assert_eq!(deltas.flash(44, later), None);
+    deltas.update(&progress, &retirements, at(101), later);
+    let (gained, strength) = deltas
+        .flash(44, later + Duration::from_millis(400))
+        .unwrap();
+    assert!(gained && strength > 0.0 && strength < 1.0);
+}
@@ -0,0 +1,208 @@
+#![cfg(feature = "native")]
+
+use f1_led_circuit_master_simulation::config::{ApiConfig, SessionConfig};
+use f1_led_circuit_master_simulation::driver_info::RosterConfig;
+use f1_led_circuit_master_simulation::error::AppError;
+use f1_led_circuit_master_simulation::mapping::MappingOptions;
+use f1_led_circuit_master_simulation::preflight::{
+    check_bundle, check_layout, check_session_data, CheckReport,
+};
+use f1_led_circuit_master_simulation::session_data::DataSource;
+use f1_led_circuit_master_simulation::space::LedPoint;
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn source(base_url: String) -> DataSource {
+    DataSource {
+        api: ApiConfig {
+            base_url,
+            timeout_secs: 5,
+            retries: 2,
+            retry_delay_ms: 1,
+            session_margin_secs: 60,
+            ..ApiConfig::default()
+        },
+        mapping: MappingOptions::default(),
+        cache_dir: std::env::temp_dir().join(format!("f1-led-preflight-{}", std::process::id())),
+        coordinates: vec![LedPoint::new(0.0, 0.0), LedPoint::new(10.0, 0.0)],
+        roster: RosterConfig::default(),
+    }
+}
+
+fn session() -> SessionConfig {
+    SessionConfig {
+        key: "9149".to_string(),
+        drivers: Some(vec![1]),
+        ..SessionConfig::default()
+    }
+}
+
+// Every endpoint answers; those in `broken` with a server error, and position with rows that
+// don't decode
+fn serve(broken: &[&str], bad_positions: bool) -> (tokio::runtime::Runtime, MockServer) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let server = runtime.block_on(async {
+        let server = MockServer::start().await;
+        let rows = [
+            (
+                "/sessions",
+                json!([{
+                    "date_start": "2023-08-27T13:00:00+00:00",
+                    "date_end": "2023-08-27T15:00:00+00:00"
+                }]),
+            ),
+            (
+                "/location",
+                json!([{
+                    "driver_number": 1,
+                    "date": "2023-08-27T13:00:01.000000+00:00",
+                    "x": 10.0,
+                    "y": 20.0,
+                    "z": 0
+                }]),
+            ),
+            (
+                "/position",
+                if bad_positions {
+                    json!([{ "position": "first" }])
+                } else {
+                    json!([])
+                },
+            ),
+            ("/drivers", json!([])),
+            ("/laps", json!([])),
+            ("/intervals", json!([])),
+            ("/stints", json!([])),
+            ("/race_control", json!([])),
+        ];
+        for (endpoint, body) in rows {
+            let response = if broken.contains(&endpoint) {
+                ResponseTemplate::new(500)
+            } else {
+                ResponseTemplate::new(200).set_body_json(body)
+            };
+            Mock::given(method("GET"))
+                .and(path(endpoint))
+                .respond_with(response)
+                .mount(&server)
+                .await;
+        }
+        server
+    });
+    (runtime, server)
+}
+
+fn failed(report: &CheckReport) -> Vec<&str> {
+    report
+        .checks
+        .iter()
+        .filter(|check| check.result.is_err())
+        .map(|check| check.name.as_str())
+        .collect()
+}
+
+#[test]
+fn passes_a_reachable_session() {
+    let (_runtime, server) = serve(&[], false);
+    let mut report = CheckReport::default();
+    check_session_data(&mut report, &source(server.uri()), &session());
+
+    let names: Vec<&str> = report
+        .checks
+        .iter()
+        .map(|check| check.name.as_str())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "session 9149",
+            "API drivers",
+            "API location",
+            "API laps",
+            "API position",
+            "API intervals",
+            "API stints",
+            "API race_control",
+            "cache mapping",
+            "cache drivers",
+            "cache laps",
+        ]
+    );
+    assert!(failed(&report).is_empty(), "{}", report);
+    assert_eq!(
+        report.checks[2].result.as_deref(),
+        Ok("1 rows for driver 1")
+    );
+    assert!(report.outcome().is_ok());
+    assert!(report.to_string().ends_with("All 11 checks passed"));
+}
+
+#[test]
+fn one_failure_does_not_hide_the_others() {
+    let (_runtime, server) = serve(&["/sessions", "/laps"], true);
+    let mut report = CheckReport::default();
+    check_session_data(&mut report, &source(server.uri()), &session());
+
+    assert_eq!(
+        failed(&report),
+        ["session 9149", "API laps", "API position"]
+    );
+    assert!(report
+        .to_string()
+        .contains("FAIL  API position: could not decode"));
+    assert!(matches!(
+        report.outcome(),
+        Err(AppError::CheckFailed {
+            failed: 3,
+            total: 11
+        })
+    ));
+}
+
+#[test]
+fn flags_broken_layouts() {
+    let layouts = [
+        (vec![], "the layout has no LEDs"),
+        (
+            vec![LedPoint::new(0.0, 0.0), LedPoint::new(f64::NAN, 1.0)],
+            "LED 1 has no finite position",
+        ),
+        (
+            vec![
+                LedPoint::new(5.0, 5.0),
+                LedPoint::new(0.0, 0.0),
+                LedPoint::new(5.0, 5.0),
+            ],
+            "LEDs 0 and 2 are in the same spot",
+        ),
+    ];
+    for (coordinates, reason) in layouts {
+        let mut report = CheckReport::default();
+        check_layout(&mut report, &coordinates);
+        let result = report.checks[0].result.as_ref();
+        assert!(
+            result.is_err_and(|err| err.contains(reason)),
+            "{:?}",
+            result
+        );
+    }
+
+    let mut report = CheckReport::default();
+    check_layout(
+        &mut report,
+        &[LedPoint::new(0.0, 0.0), LedPoint::new(1.0, 0.0)],
+    );
+    assert_eq!(report.checks[0].result.as_deref(), Ok("2 LEDs"));
+}
+
+#[test]
+fn fails_a_bundle_that_does_not_read_back() {
+    let path = std::env::temp_dir().join(format!("f1-led-preflight-{}.f1led", std::process::id()));
+    std::fs::write(&path, b"not a bundle").unwrap();
+    let mut report = CheckReport::default();
+    check_bundle(&mut report, &path, &[LedPoint::new(0.0, 0.0)]);
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(report.failed(), 1);
+}
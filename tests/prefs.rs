@@ -0,0 +1,53 @@
+mod common;
+
+use common::at;
+use f1_led_circuit_master_simulation::config::Config;
+use f1_led_circuit_master_simulation::data::TimeWindow;
+use f1_led_circuit_master_simulation::prefs::{LastSession, UiPrefs};
+
+fn remembering(key: &str, window: TimeWindow) -> UiPrefs {
+    UiPrefs {
+        last_session: Some(LastSession {
+            key: key.to_string(),
+            window,
+        }),
+        ..UiPrefs::default()
+    }
+}
+
+fn last_half_hour() -> TimeWindow {
+    TimeWindow {
+        start: Some(at(1800)),
+        end: Some(at(3600)),
+    }
+}
+
+#[test]
+fn remembers_the_window_of_the_last_session() {
+    let prefs = remembering("9161", last_half_hour());
+    let stored = serde_json::to_string(&prefs).unwrap();
+    let restored: UiPrefs = serde_json::from_str(&stored).unwrap();
+    assert_eq!(restored.last_session, prefs.last_session);
+
+    let config = Config::parse("[session]\nkey = \"9161\"\n").unwrap();
+    assert_eq!(restored.session_window(&config), Some(last_half_hour()));
+}
+
+#[test]
+fn prefs_stored_without_a_window_restore_none() {
+    let restored: UiPrefs = serde_json::from_str("{\"speed\": 2.0}").unwrap();
+    assert_eq!(restored.last_session, None);
+}
+
+#[test]
+fn keeps_the_window_of_the_config_file_and_other_sessions() {
+    let prefs = remembering("9161", last_half_hour());
+
+    let other_session = Config::parse("[session]\nkey = \"9158\"\n").unwrap();
+    assert_eq!(prefs.session_window(&other_session), None);
+
+    let window_set =
+        Config::parse("[session]\nkey = \"9161\"\nstart_time = \"2023-08-27T13:00:00Z\"\n")
+            .unwrap();
+    assert_eq!(prefs.session_window(&window_set), None);
+}
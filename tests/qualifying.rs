@@ -0,0 +1,123 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use f1_led_circuit_master_simulation::data::LapData;
+use f1_led_circuit_master_simulation::laps::RaceProgress;
+use f1_led_circuit_master_simulation::mapping::RunRace;
+use f1_led_circuit_master_simulation::qualifying::{
+    best_laps, flying_laps, garage_segments, overlay_best_laps,
+};
+use f1_led_circuit_master_simulation::space::TelemetryPoint;
+use f1_led_circuit_master_simulation::speed_plan::SpeedSegment;
+
+fn at(secs: i64) -> DateTime<Utc> {
+    let start: DateTime<Utc> = "2023-08-26T14:00:00Z".parse().unwrap();
+    start + ChronoDuration::seconds(secs)
+}
+
+fn lap(driver_number: u32, lap_number: u32, start: i64, duration: f64, out: bool) -> LapData {
+    LapData {
+        driver_number,
+        lap_number,
+        date_start: Some(at(start)),
+        lap_duration: Some(duration),
+        is_pit_out_lap: out,
+        ..LapData::default()
+    }
+}
+
+// Two runs each: out, flying, in. Driver 1's second flying lap is the faster, driver 44 has
+// only one flying lap with a time
+fn progress() -> RaceProgress {
+    RaceProgress::new(
+        vec![
+            lap(1, 1, 100, 100.0, true),
+            lap(1, 2, 200, 72.0, false),
+            lap(1, 3, 272, 110.0, false),
+            lap(1, 4, 600, 100.0, true),
+            lap(1, 5, 700, 71.5, false),
+            lap(1, 6, 772, 110.0, false),
+            lap(44, 1, 150, 100.0, true),
+            lap(44, 2, 250, 73.0, false),
+            lap(44, 3, 323, 110.0, false),
+            lap(44, 4, 650, 100.0, true),
+            LapData {
+                lap_duration: None,
+                ..lap(44, 5, 750, 0.0, false)
+            },
+        ],
+        Vec::new(),
+    )
+}
+
+#[test]
+fn leaves_out_in_and_out_laps() {
+    let progress = progress();
+    let laps: Vec<u32> = flying_laps(&progress, 1)
+        .iter()
+        .map(|lap| lap.lap_number)
+        .collect();
+    // Lap 6 is the last, so it isn't known to be an in lap
+    assert_eq!(laps, [2, 5, 6]);
+    let laps: Vec<u32> = flying_laps(&progress, 44)
+        .iter()
+        .map(|lap| lap.lap_number)
+        .collect();
+    assert_eq!(laps, [2]);
+
+    let best: Vec<(u32, u32, f64)> = best_laps(&progress)
+        .iter()
+        .map(|best| (best.driver_number, best.lap_number, best.lap_duration))
+        .collect();
+    assert_eq!(best, [(1, 5, 71.5), (44, 2, 73.0)]);
+}
+
+#[test]
+fn starts_the_best_laps_together() {
+    let record = |secs: i64, driver_number: u32, led_index: usize| RunRace {
+        date: at(secs),
+        driver_number,
+        led_index,
+        point: TelemetryPoint::new(0.0, 0.0),
+    };
+    let run_race_data = vec![
+        record(200, 1, 0),
+        record(250, 44, 0),
+        record(260, 44, 5),
+        record(400, 44, 9), // Past the end of 44's best lap
+        record(700, 1, 0),
+        record(730, 1, 5),
+    ];
+    let overlaid = overlay_best_laps(&run_race_data, &best_laps(&progress()));
+    let summary: Vec<(DateTime<Utc>, u32, usize)> = overlaid
+        .iter()
+        .map(|run_data| (run_data.date, run_data.driver_number, run_data.led_index))
+        .collect();
+    // Both laps start when 44's did, the earlier of the two
+    assert_eq!(
+        summary,
+        [
+            (at(250), 1, 0),
+            (at(250), 44, 0),
+            (at(260), 44, 5),
+            (at(280), 1, 5)
+        ]
+    );
+}
+
+#[test]
+fn hurries_through_the_garage_time() {
+    let segments = garage_segments(&progress(), at(0), at(1000), 20.0);
+    // Someone's out from 100 until 44 comes in at 433, and from 600 until 1 comes in at 882
+    let segment = |start: f64, end: f64| SpeedSegment {
+        start,
+        end,
+        speed: 20.0,
+    };
+    assert_eq!(
+        segments,
+        [
+            segment(0.0, 100.0),
+            segment(433.0, 600.0),
+            segment(882.0, 1000.0)
+        ]
+    );
+}
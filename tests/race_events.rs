@@ -0,0 +1,125 @@
+mod common;
+
+use common::{at, record};
+use f1_led_circuit_master_simulation::audio::{AudioConfig, AudioPlayer, Cue, SAMPLE_RATE};
+use f1_led_circuit_master_simulation::data::LapData;
+use f1_led_circuit_master_simulation::laps::RaceProgress;
+use f1_led_circuit_master_simulation::overtakes::Overtake;
+use f1_led_circuit_master_simulation::race_events::{pit_exits, RaceEvent};
+use f1_led_circuit_master_simulation::simulation::Simulation;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn lap(driver_number: u32, lap_number: u32, start_secs: i64, is_pit_out_lap: bool) -> LapData {
+    LapData {
+        is_pit_out_lap,
+        ..common::lap(driver_number, lap_number, start_secs)
+    }
+}
+
+// Drivers 1 and 44 go round ten LEDs, one a second; 1 passes 44 at 4s and 44 leaves the pits
+// at 7s
+fn simulation() -> Simulation {
+    let samples = (0..10)
+        .flat_map(|second| {
+            [1, 44].map(|driver_number| record(second * 1000, driver_number, second as usize))
+        })
+        .collect();
+    let mut simulation = Simulation::new(samples, 10, HashMap::new());
+    let overtake = Overtake {
+        date: at(4),
+        driver_number: 1,
+        overtaken: 44,
+        position: 1,
+        led_index: 4,
+    };
+    simulation.set_overtakes(vec![overtake], 10);
+    let laps = vec![
+        lap(1, 1, 0, false),
+        lap(44, 1, 0, false),
+        lap(44, 2, 7, true),
+    ];
+    simulation.set_race_progress(Some(Arc::new(RaceProgress::new(laps, Vec::new()))));
+    simulation
+}
+
+fn kinds(events: &[RaceEvent]) -> Vec<&'static str> {
+    events
+        .iter()
+        .map(|event| Cue::for_event(event).label())
+        .collect()
+}
+
+#[test]
+fn finds_the_pit_exits_in_the_laps() {
+    let progress = RaceProgress::new(
+        vec![lap(44, 2, 7, true), lap(1, 3, 5, true), lap(1, 2, 2, false)],
+        Vec::new(),
+    );
+    assert_eq!(pit_exits(&progress), vec![(at(5), 1), (at(7), 44)]);
+}
+
+#[test]
+fn emits_the_events_as_the_clock_passes_them() {
+    let mut simulation = simulation();
+    simulation.start();
+    assert_eq!(simulation.take_race_events(), vec![RaceEvent::Start]);
+
+    let mut events = Vec::new();
+    for _ in 0..100 {
+        simulation.tick(Duration::from_millis(100));
+        events.extend(simulation.take_race_events());
+    }
+    assert_eq!(kinds(&events), ["overtake", "pit stop"]);
+    assert_eq!(
+        events[1],
+        RaceEvent::PitStop {
+            driver_number: 44,
+            date: at(7)
+        }
+    );
+    assert!(simulation.take_race_events().is_empty());
+}
+
+#[test]
+fn follows_seeks_like_the_animations() {
+    let mut simulation = simulation();
+    simulation.start();
+    simulation.take_race_events();
+
+    // Jumping far past them skips them, as it does their animations
+    simulation.seek(Duration::from_millis(8500));
+    assert!(simulation.take_race_events().is_empty());
+    assert!(!simulation.overtakes().is_active());
+
+    // Seeking back plays nothing; crossing them again plays them again
+    simulation.seek(Duration::from_millis(3000));
+    assert!(simulation.take_race_events().is_empty());
+    simulation.seek(Duration::from_millis(4200));
+    assert_eq!(kinds(&simulation.take_race_events()), ["overtake"]);
+    simulation.seek(Duration::from_millis(7500));
+    assert_eq!(kinds(&simulation.take_race_events()), ["pit stop"]);
+}
+
+#[test]
+fn plays_nothing_when_disabled() {
+    let player = AudioPlayer::open(&AudioConfig::default());
+    assert!(!player.is_enabled());
+    player.play(&[RaceEvent::Start]);
+
+    // A beep as each of the five lights comes on, a second apart
+    let samples = Cue::StartLights.samples();
+    let loud = |secs: f32| {
+        let at = (secs * SAMPLE_RATE as f32) as usize;
+        samples[at..at + 100]
+            .iter()
+            .any(|sample| sample.abs() > 0.1)
+    };
+    for light in 0..5 {
+        assert!(loud(light as f32 + 0.1), "light {}", light);
+    }
+    for gap in 0..4 {
+        assert!(!loud(gap as f32 + 0.5), "after light {}", gap);
+    }
+}
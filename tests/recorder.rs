@@ -0,0 +1,278 @@
+use f1_led_circuit_master_simulation::error::AppError;
+use f1_led_circuit_master_simulation::recorder::{
+    layout_hash, read_recording, FrameRecorder, RecordedFrame, RecorderConfig, Recording,
+    RecordingHeader, RecordingWriter, RECORDING_VERSION,
+};
+use f1_led_circuit_master_simulation::simulation::{PlaybackState, Rgb, Simulation};
+use f1_led_circuit_master_simulation::sink::{FrameDispatcher, LedSink, OutputFrame, SinkOptions};
+use f1_led_circuit_master_simulation::space::LedPoint;
+use std::time::Duration;
+
+const LED_COUNT: usize = 100;
+
+fn header() -> RecordingHeader {
+    RecordingHeader {
+        version: RECORDING_VERSION,
+        led_count: LED_COUNT as u16,
+        layout_hash: 0x1234_5678_9abc_def0,
+        tick_rate: 30,
+    }
+}
+
+// Twenty "cars" moving one LED per frame around the layout
+fn synthetic_frame(tick: usize) -> Vec<Rgb> {
+    let mut leds = vec![[0, 0, 0]; LED_COUNT];
+    for car in 0..20 {
+        leds[(car * 5 + tick) % LED_COUNT] = [car as u8 * 10, 255 - car as u8, 7];
+    }
+    leds
+}
+
+fn tick_time(tick: usize) -> Duration {
+    Duration::from_millis(tick as u64 * 1000 / 30)
+}
+
+#[test]
+fn round_trips_frames() {
+    let mut bytes = Vec::new();
+    let mut writer = RecordingWriter::new(&mut bytes, header()).unwrap();
+    for tick in 0..90 {
+        writer
+            .write_frame(tick_time(tick), &synthetic_frame(tick))
+            .unwrap();
+    }
+    drop(writer);
+
+    let (read_header, frames) = read_recording(bytes.as_slice()).unwrap();
+    assert_eq!(read_header, header());
+    let expected: Vec<RecordedFrame> = (0..90)
+        .map(|tick| RecordedFrame {
+            time: tick_time(tick),
+            leds: synthetic_frame(tick),
+        })
+        .collect();
+    assert_eq!(frames, expected);
+}
+
+#[test]
+fn stores_only_changed_leds() {
+    let mut bytes = Vec::new();
+    let mut writer = RecordingWriter::new(&mut bytes, header()).unwrap();
+    let header_len = 18;
+
+    writer
+        .write_frame(tick_time(0), &synthetic_frame(0))
+        .unwrap();
+    writer
+        .write_frame(tick_time(1), &synthetic_frame(0))
+        .unwrap(); // Unchanged: no record
+    writer
+        .write_frame(tick_time(2), &synthetic_frame(1))
+        .unwrap();
+    drop(writer);
+
+    // 20 lit LEDs, then each car lights one LED and darkens another
+    assert_eq!(bytes.len(), header_len + (6 + 20 * 5) + (6 + 40 * 5));
+    let (_, frames) = read_recording(bytes.as_slice()).unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[1].time, tick_time(2));
+
+    // A 90 minute race at 30 fps with this much movement stays in the tens of megabytes
+    let per_frame = (bytes.len() - header_len) as f64 / 2.0;
+    assert!(per_frame * 90.0 * 60.0 * 30.0 < 40e6);
+}
+
+#[test]
+fn rejects_unknown_versions() {
+    let mut bytes = Vec::new();
+    RecordingWriter::new(
+        &mut bytes,
+        RecordingHeader {
+            version: RECORDING_VERSION + 1,
+            ..header()
+        },
+    )
+    .unwrap();
+
+    assert!(read_recording(bytes.as_slice()).is_err());
+    assert!(read_recording(&b"not a recording"[..]).is_err());
+}
+
+#[test]
+fn recorder_sink_writes_a_readable_file() {
+    let coordinates: Vec<LedPoint> = (0..LED_COUNT)
+        .map(|index| LedPoint {
+            x: index as f64,
+            y: 0.0,
+        })
+        .collect();
+    let path = std::env::temp_dir().join(format!("recorder-test-{}.ledrec", std::process::id()));
+    let config = RecorderConfig {
+        enabled: true,
+        path: path.clone(),
+        fps: 30.0,
+    };
+
+    let mut dispatcher = FrameDispatcher::new();
+    let recorder = FrameRecorder::new(&config, &coordinates).unwrap();
+    dispatcher
+        .register(Box::new(recorder), SinkOptions::default())
+        .unwrap();
+    dispatcher.dispatch(OutputFrame {
+        leds: synthetic_frame(3),
+        brightness: 1.0,
+        timestamp: Duration::from_secs(12),
+        state: PlaybackState::Playing,
+        speed: 1.0,
+        drivers: Vec::new(),
+    });
+    dispatcher.shutdown();
+
+    let file = std::fs::File::open(&path).unwrap();
+    let (header, frames) = read_recording(file).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(header.led_count as usize, LED_COUNT);
+    assert_eq!(header.layout_hash, layout_hash(&coordinates));
+    assert_eq!(header.tick_rate, 30);
+    assert_eq!(
+        frames,
+        [RecordedFrame {
+            time: Duration::from_secs(12),
+            leds: synthetic_frame(3),
+        }]
+    );
+}
+
+#[test]
+fn refuses_frames_that_go_back_in_time() {
+    let mut bytes = Vec::new();
+    let mut writer = RecordingWriter::new(&mut bytes, header()).unwrap();
+    writer
+        .write_frame(tick_time(5), &synthetic_frame(5))
+        .unwrap();
+    assert!(matches!(
+        writer.write_frame(tick_time(3), &synthetic_frame(3)),
+        Err(AppError::Output { .. })
+    ));
+    // The same time again is fine
+    writer
+        .write_frame(tick_time(5), &synthetic_frame(6))
+        .unwrap();
+    assert_eq!(writer.last_time(), tick_time(5));
+    drop(writer);
+
+    let (_, frames) = read_recording(bytes.as_slice()).unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[1].leds, synthetic_frame(6));
+}
+
+#[test]
+fn rejects_records_that_go_back_in_time() {
+    let mut bytes = Vec::new();
+    let mut writer = RecordingWriter::new(&mut bytes, header()).unwrap();
+    writer
+        .write_frame(tick_time(5), &synthetic_frame(5))
+        .unwrap();
+    drop(writer);
+    // A record at 10ms, before the one at 166ms, lighting LED 0
+    bytes.extend_from_slice(&10u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&0u16.to_le_bytes());
+    bytes.extend_from_slice(&[1, 2, 3]);
+
+    assert!(matches!(
+        read_recording(bytes.as_slice()),
+        Err(AppError::Decode { .. })
+    ));
+    assert!(matches!(
+        Recording::read(bytes.as_slice()),
+        Err(AppError::Decode { .. })
+    ));
+}
+
+#[test]
+fn recorder_sink_leaves_out_frames_after_seeking_back() {
+    let coordinates: Vec<LedPoint> = (0..LED_COUNT)
+        .map(|index| LedPoint::new(index as f64, 0.0))
+        .collect();
+    let path = std::env::temp_dir().join(format!("recorder-seek-{}.ledrec", std::process::id()));
+    let config = RecorderConfig {
+        enabled: true,
+        path: path.clone(),
+        fps: 30.0,
+    };
+    let frame = |secs: u64, tick: usize| OutputFrame {
+        leds: synthetic_frame(tick),
+        brightness: 1.0,
+        timestamp: Duration::from_secs(secs),
+        state: PlaybackState::Playing,
+        speed: 1.0,
+        drivers: Vec::new(),
+    };
+
+    let mut recorder = FrameRecorder::new(&config, &coordinates).unwrap();
+    recorder.start().unwrap();
+    for (secs, tick) in [(12, 0), (4, 1), (8, 2), (13, 3)] {
+        recorder.submit(&frame(secs, tick)).unwrap();
+    }
+    recorder.shutdown();
+
+    let (_, frames) = read_recording(std::fs::File::open(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let times: Vec<Duration> = frames.iter().map(|frame| frame.time).collect();
+    assert_eq!(times, [Duration::from_secs(12), Duration::from_secs(13)]);
+    assert_eq!(frames[1].leds, synthetic_frame(3));
+}
+
+fn recording_bytes(ticks: usize) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut writer = RecordingWriter::new(&mut bytes, header()).unwrap();
+    for tick in 0..ticks {
+        writer
+            .write_frame(tick_time(tick), &synthetic_frame(tick))
+            .unwrap();
+    }
+    drop(writer);
+    bytes
+}
+
+fn shown(simulation: &Simulation) -> Vec<Rgb> {
+    simulation
+        .frame()
+        .leds
+        .iter()
+        .map(|color| color.unwrap_or([0, 0, 0]))
+        .collect()
+}
+
+#[test]
+fn plays_and_seeks_a_recording() {
+    let recording = Recording::read(recording_bytes(1000).as_slice()).unwrap();
+    assert_eq!(recording.len(), 1000);
+    assert_eq!(recording.duration(), tick_time(999));
+
+    let mut simulation = Simulation::from_recording(recording);
+    assert_eq!(simulation.duration(), tick_time(999).as_secs_f64());
+    simulation.start();
+    simulation.tick(tick_time(10));
+    assert_eq!(shown(&simulation), synthetic_frame(10));
+
+    // Far forward and back past several keyframes, then a short step forward
+    for tick in [900, 300, 5, 6] {
+        simulation.seek(tick_time(tick));
+        assert_eq!(shown(&simulation), synthetic_frame(tick), "tick {}", tick);
+    }
+    assert!(!simulation.is_finished());
+    simulation.seek(tick_time(999));
+    assert!(simulation.is_finished());
+}
+
+#[test]
+fn drops_a_truncated_last_record() {
+    let mut bytes = recording_bytes(3);
+    bytes.truncate(bytes.len() - 2);
+
+    let (_, frames) = read_recording(bytes.as_slice()).unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[1].leds, synthetic_frame(1));
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use common::at;
+use f1_led_circuit_master_simulation::mapping::RunRace;
+use f1_led_circuit_master_simulation::retirements::{stale_alpha, RetirementConfig, Retirements};
+use f1_led_circuit_master_simulation::simulation::Simulation;
+use f1_led_circuit_master_simulation::timeline::DriverTimelines;
+use std::collections::HashMap;
+use std::time::Duration;
+
+const LED_COUNT: usize = 10;
+
+// Driver 1 runs for 300s, driver 11 stops on LED 4 after 100s and driver 44 sits still from
+// 100s to 200s, e.g. in the pit lane under a red flag
+fn race() -> Vec<RunRace> {
+    let record = |seconds: i64, driver_number, led_index| {
+        common::record(seconds * 1000, driver_number, led_index)
+    };
+    let mut race = Vec::new();
+    for seconds in (0..=300).step_by(10) {
+        race.push(record(seconds, 1, (seconds / 10) as usize % LED_COUNT));
+        if seconds <= 100 {
+            race.push(record(seconds, 11, 4));
+        }
+        if !(101..200).contains(&seconds) {
+            race.push(record(seconds, 44, 7));
+        }
+    }
+    race.sort_by_key(|run_data| (run_data.date, run_data.driver_number));
+    race
+}
+
+fn config() -> RetirementConfig {
+    RetirementConfig {
+        inactivity_secs: 60.0,
+        keep_last_led: false,
+        stale_secs: 20.0,
+    }
+}
+
+#[test]
+fn retires_drivers_whose_data_ends_early() {
+    let retirements = Retirements::new(&DriverTimelines::new(&race()), &config());
+
+    assert!(!retirements.is_retired(11, at(159)));
+    assert!(retirements.is_retired(11, at(160)));
+    // Rewinding brings them back
+    assert!(!retirements.is_retired(11, at(50)));
+    // A long stop the car comes back from isn't a retirement, nor is running to the end
+    assert!(!retirements.is_retired(44, at(190)));
+    assert!(!retirements.is_retired(1, at(300)));
+}
+
+#[test]
+fn retired_drivers_go_dark_unless_kept() {
+    let run_race_data = race();
+    let retirements = Retirements::new(&DriverTimelines::new(&run_race_data), &config());
+    let mut simulation = Simulation::new(run_race_data, LED_COUNT, HashMap::new());
+    simulation.set_retirements(retirements);
+    simulation.start();
+
+    simulation.seek(Duration::from_secs(150));
+    assert!(simulation.frame().leds[4].is_some());
+    simulation.seek(Duration::from_secs(250));
+    assert!(simulation.frame().leds[4].is_none());
+
+    simulation.set_show_retired(true);
+    assert!(simulation.frame().leds[4].is_some());
+    simulation.set_show_retired(false);
+    simulation.seek(Duration::from_secs(120));
+    assert!(simulation.frame().leds[4].is_some());
+}
+
+#[test]
+fn stale_drivers_fade_out_until_newer_data() {
+    assert_eq!(stale_alpha(20.0, 20.0), 1.0);
+    assert_eq!(stale_alpha(20.5, 20.0), 0.5);
+    assert_eq!(stale_alpha(60.0, 20.0), 0.0);
+
+    let mut simulation = Simulation::new(race(), LED_COUNT, HashMap::from([(44, [200, 100, 0])]));
+    simulation.set_stale_secs(config().stale_secs);
+    simulation.start();
+
+    // 44's last sample before the gap is at 100s
+    simulation.seek(Duration::from_millis(120_500));
+    assert_eq!(simulation.frame().leds[7], Some([100, 50, 0]));
+    assert!(simulation.is_stale(44));
+    simulation.seek(Duration::from_secs(150));
+    assert_eq!(simulation.frame().leds[7], None);
+    assert!(!simulation.is_stale(1));
+
+    // Newer data, or rewinding to before the gap, brings them back
+    simulation.seek(Duration::from_secs(200));
+    assert_eq!(simulation.frame().leds[7], Some([200, 100, 0]));
+    simulation.seek(Duration::from_secs(150));
+    simulation.seek(Duration::from_secs(110));
+    assert_eq!(simulation.frame().leds[7], Some([200, 100, 0]));
+    assert!(!simulation.is_stale(44));
+
+    simulation.seek(Duration::from_secs(150));
+    simulation.set_show_retired(true);
+    assert_eq!(simulation.frame().leds[7], Some([200, 100, 0]));
+}
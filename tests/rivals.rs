@@ -0,0 +1,96 @@
+mod common;
+
+use common::at;
+use f1_led_circuit_master_simulation::data::{IntervalData, LapData};
+use f1_led_circuit_master_simulation::laps::RaceProgress;
+use f1_led_circuit_master_simulation::rivals::{nearest_rivals, GapSource, RivalTracker};
+use f1_led_circuit_master_simulation::track_progress::TrackProgress;
+
+fn track(leds: &[(u32, usize)]) -> TrackProgress {
+    let mut track = TrackProgress::new(100);
+    for &(driver_number, led) in leds {
+        track.advance(driver_number, led);
+    }
+    track
+}
+
+#[test]
+fn finds_the_nearest_cars_across_the_line() {
+    let track = track(&[(1, 95), (44, 3), (16, 90), (55, 50)]);
+    let rivals = nearest_rivals(&track, 1, None, None);
+    let ahead = rivals.ahead.unwrap();
+    assert_eq!((ahead.driver_number, ahead.leds), (44, 8));
+    assert_eq!(ahead.gap_secs, None);
+    let behind = rivals.behind.unwrap();
+    assert_eq!((behind.driver_number, behind.leds), (16, 5));
+
+    // A car on the same LED is ahead, and not behind as well
+    let track = self::track(&[(1, 10), (44, 10)]);
+    let rivals = nearest_rivals(&track, 1, None, None);
+    assert_eq!(rivals.ahead.unwrap().leds, 0);
+    assert!(rivals.behind.is_none());
+    assert!(nearest_rivals(&track, 63, None, None).ahead.is_none());
+}
+
+#[test]
+fn prefers_the_gaps_to_the_leader_over_the_estimate() {
+    let track = track(&[(1, 20), (44, 30)]);
+    let lap = LapData {
+        lap_duration: Some(80.0),
+        ..common::lap(1, 1, 0)
+    };
+    let progress = RaceProgress::new(vec![lap], Vec::new());
+
+    // Before the first lap is done there's nothing to estimate from
+    let rivals = nearest_rivals(&track, 1, Some(&progress), Some(at(60)));
+    assert_eq!(rivals.ahead.unwrap().gap_secs, None);
+    let rivals = nearest_rivals(&track, 1, Some(&progress), Some(at(100)));
+    let ahead = rivals.ahead.unwrap();
+    assert_eq!(
+        (ahead.gap_secs, ahead.source),
+        (Some(8.0), GapSource::Estimate)
+    );
+
+    let interval = |driver_number, gap_to_leader| IntervalData {
+        date: at(90),
+        driver_number,
+        gap_to_leader,
+    };
+    let progress = progress.with_intervals(vec![
+        interval(44, Some(2.5)),
+        interval(1, Some(4.0)),
+        interval(16, None),
+    ]);
+    let ahead = nearest_rivals(&track, 1, Some(&progress), Some(at(100)))
+        .ahead
+        .unwrap();
+    assert_eq!(
+        (ahead.gap_secs, ahead.source),
+        (Some(1.5), GapSource::Intervals)
+    );
+    // Not yet known at the time
+    let ahead = nearest_rivals(&track, 1, Some(&progress), Some(at(89)))
+        .ahead
+        .unwrap();
+    assert_eq!(ahead.source, GapSource::Estimate);
+}
+
+#[test]
+fn works_the_rivals_out_again_only_when_a_car_moves() {
+    let mut track = track(&[(1, 20), (44, 30)]);
+    let mut tracker = RivalTracker::default();
+    assert_eq!(tracker.update(&track, None, None, None).ahead, None);
+    let first = tracker.update(&track, Some(1), None, None);
+    assert_eq!(first.ahead.unwrap().driver_number, 44);
+
+    // Staying on the same LED changes nothing
+    track.advance(44, 30);
+    let version = track.version();
+    assert_eq!(tracker.update(&track, Some(1), None, None), first);
+    track.advance(16, 25);
+    assert_ne!(track.version(), version);
+    let rivals = tracker.update(&track, Some(1), None, None);
+    assert_eq!(rivals.ahead.unwrap().driver_number, 16);
+    let rivals = tracker.update(&track, Some(44), None, None);
+    assert_eq!(rivals.behind.unwrap().driver_number, 16);
+}
@@ -0,0 +1,77 @@
+use f1_led_circuit_master_simulation::driver_info::{
+    get_driver_info_for_year, read_roster, season_roster, DriverInfo, RosterConfig,
+};
+use f1_led_circuit_master_simulation::error::AppError;
+
+fn find(roster: &[DriverInfo], number: u32) -> Option<(&str, &str)> {
+    roster
+        .iter()
+        .find(|driver| driver.number == number)
+        .map(|driver| (driver.name.as_str(), driver.team.as_str()))
+}
+
+#[test]
+fn picks_the_built_in_roster_of_the_season() {
+    let roster_2023 = get_driver_info_for_year(2023);
+    assert_eq!(find(&roster_2023, 3), None);
+    assert_eq!(find(&roster_2023, 40), Some(("Liam Lawson", "AlphaTauri")));
+
+    let roster_2024 = get_driver_info_for_year(2024);
+    assert_eq!(find(&roster_2024, 3), Some(("Daniel Ricciardo", "RB")));
+    assert_eq!(find(&roster_2024, 38), Some(("Oliver Bearman", "Ferrari")));
+    assert_eq!(
+        find(&roster_2024, 43),
+        Some(("Franco Colapinto", "Williams"))
+    );
+    assert_eq!(find(&roster_2024, 40), None);
+
+    // Seasons around the built-in ones get the closest of them
+    assert_eq!(
+        find(&get_driver_info_for_year(2022), 40),
+        find(&roster_2023, 40)
+    );
+    assert_eq!(
+        find(&get_driver_info_for_year(2025), 3),
+        find(&roster_2024, 3)
+    );
+}
+
+#[test]
+fn loads_a_season_from_its_roster_file() {
+    let dir = std::env::temp_dir().join(format!("roster-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("2025.toml"),
+        r##"
+[[drivers]]
+number = 5
+name = "Gabriel Bortoleto"
+code = "BOR"
+team = "Kick Sauber"
+color = "#52E252"
+"##,
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("2026.toml"),
+        "[[drivers]]\nnumber = 1\nname = \"A\"\ncode = \"A\"\nteam = \"B\"\ncolor = \"green\"\n",
+    )
+    .unwrap();
+    let config = RosterConfig {
+        season: None,
+        dir: dir.clone(),
+    };
+
+    let roster = season_roster(2025, &config);
+    assert_eq!(roster.len(), 1);
+    assert_eq!(find(&roster, 5), Some(("Gabriel Bortoleto", "Kick Sauber")));
+    assert_eq!(roster[0].color.r(), 0x52);
+    // Without a file of its own, or with a broken one, a season gets the built-in roster
+    assert_eq!(season_roster(2024, &config).len(), 24);
+    assert!(matches!(
+        read_roster(&dir.join("2026.toml")),
+        Err(AppError::Decode { context }) if context.contains("\"green\"")
+    ));
+    assert_eq!(season_roster(2026, &config).len(), 24);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
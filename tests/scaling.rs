@@ -0,0 +1,135 @@
+mod common;
+
+use common::sample;
+use f1_led_circuit_master_simulation::blue_flags::BlueFlagConfig;
+use f1_led_circuit_master_simulation::data::LocationData;
+use f1_led_circuit_master_simulation::led_decay::LedDecayConfig;
+use f1_led_circuit_master_simulation::mapping::{
+    generate_run_race_data, nearest_led, NearestLedIndex,
+};
+use f1_led_circuit_master_simulation::simulation::{Simulation, FIXED_STEP};
+use f1_led_circuit_master_simulation::space::LedPoint;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+const LED_COUNT: usize = 1000;
+const DRIVERS: u32 = 20;
+const RACE_SECS: i64 = 5400;
+const SAMPLES_PER_SEC: i64 = 4;
+
+// An oval of LEDs a meter apart, in decimeters like the telemetry
+fn layout() -> Vec<LedPoint> {
+    (0..LED_COUNT)
+        .map(|index| {
+            let angle = index as f64 / LED_COUNT as f64 * std::f64::consts::TAU;
+            LedPoint::new(2400.0 * angle.cos(), 1200.0 * angle.sin())
+        })
+        .collect()
+}
+
+// Every driver lapping the oval for a whole race, each at a pace of their own and a little
+// off the racing line
+fn full_race() -> Vec<LocationData> {
+    let mut rng = StdRng::seed_from_u64(943);
+    let mut samples = Vec::new();
+    for tick in 0..RACE_SECS * SAMPLES_PER_SEC {
+        let secs = tick as f64 / SAMPLES_PER_SEC as f64;
+        for driver_number in 1..=DRIVERS {
+            let lap_secs = 90.0 + driver_number as f64 * 0.3;
+            let angle = secs / lap_secs * std::f64::consts::TAU;
+            samples.push(sample(
+                tick * 1000 / SAMPLES_PER_SEC,
+                driver_number,
+                2400.0 * angle.cos() + rng.gen_range(-5.0..5.0),
+                1200.0 * angle.sin() + rng.gen_range(-5.0..5.0),
+            ));
+        }
+    }
+    samples
+}
+
+#[test]
+fn indexed_lookups_match_checking_every_led() {
+    let coordinates = layout();
+    let index = NearestLedIndex::new(&coordinates);
+    let mut rng = StdRng::seed_from_u64(1000);
+    for _ in 0..10_000 {
+        // Over and well around the layout
+        let point = LedPoint::new(
+            rng.gen_range(-4000.0..4000.0),
+            rng.gen_range(-3000.0..3000.0),
+        );
+        assert_eq!(index.nearest(point), nearest_led(&coordinates, point));
+    }
+    // Ties go to the lower index, and a layout of one LED or none still answers
+    let pair = [0.0, 10.0].map(|x| LedPoint::new(x, 0.0));
+    let between = LedPoint::new(5.0, 3.0);
+    assert_eq!(
+        NearestLedIndex::new(&pair).nearest(between),
+        nearest_led(&pair, between)
+    );
+    let beside = LedPoint::new(5.0, 0.0);
+    assert_eq!(NearestLedIndex::new(&pair[..1]).nearest(beside), (0, 5.0));
+    assert_eq!(
+        NearestLedIndex::new(&[]).nearest(beside),
+        (0, f64::INFINITY)
+    );
+}
+
+// The whole race mapped onto the layout, with the effects that cost the most per tick
+fn full_race_simulation() -> Simulation {
+    let coordinates = layout();
+    let (run_race_data, stats) = generate_run_race_data(&full_race(), &coordinates, 50.0).unwrap();
+    assert_eq!(stats.dropped_samples, 0);
+    assert_eq!(
+        run_race_data.len(),
+        (DRIVERS as i64 * RACE_SECS * SAMPLES_PER_SEC) as usize
+    );
+
+    let mut simulation = Simulation::new(run_race_data, LED_COUNT, HashMap::new());
+    simulation.set_blue_flags(&BlueFlagConfig::default());
+    simulation.set_led_decay(&LedDecayConfig {
+        enabled: true,
+        ..LedDecayConfig::default()
+    });
+    simulation.set_stale_secs(20.0);
+    simulation.set_speed(10.0);
+    simulation
+}
+
+#[test]
+fn ticks_a_full_race_on_a_thousand_leds() {
+    let mut simulation = full_race_simulation();
+    simulation.start();
+    while !simulation.is_finished() {
+        simulation.advance(FIXED_STEP);
+    }
+    // Each driver lit, and the LEDs some of them just left still fading
+    assert!(simulation.frame().lit().count() >= DRIVERS as usize);
+}
+
+// Wall-clock limits only hold for an optimized build on an idle machine; run with
+// `cargo test --release --test scaling -- --ignored`
+#[test]
+#[ignore]
+fn ticks_a_full_race_on_a_thousand_leds_quickly() {
+    let mut simulation = full_race_simulation();
+    simulation.start();
+    let mut ticks = Vec::new();
+    while !simulation.is_finished() {
+        let started = Instant::now();
+        simulation.advance(FIXED_STEP);
+        ticks.push(started.elapsed());
+    }
+    ticks.sort();
+    let median = ticks[ticks.len() / 2];
+    let slowest = ticks[ticks.len() * 99 / 100];
+    assert!(
+        median < Duration::from_millis(2) && slowest < Duration::from_millis(5),
+        "{:?} median, {:?} at the 99th percentile",
+        median,
+        slowest
+    );
+}
@@ -0,0 +1,49 @@
+use chrono::{Duration as ChronoDuration, Local, NaiveDate, TimeZone, Timelike};
+use f1_led_circuit_master_simulation::schedule::{parse_start_time, ScheduledStart};
+use std::time::Duration;
+
+fn today() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 5, 26).unwrap()
+}
+
+#[test]
+fn parses_a_time_today_or_a_full_date() {
+    let at = parse_start_time("15:00", today()).unwrap();
+    assert_eq!(at.date_naive(), today());
+    assert_eq!((at.hour(), at.minute(), at.second()), (15, 0, 0));
+
+    let at = parse_start_time(" 15:00:30 ", today()).unwrap();
+    assert_eq!(at.second(), 30);
+
+    let at = parse_start_time("2024-06-09 13:05", today()).unwrap();
+    assert_eq!(
+        at.date_naive(),
+        NaiveDate::from_ymd_opt(2024, 6, 9).unwrap()
+    );
+    assert_eq!((at.hour(), at.minute()), (13, 5));
+
+    let at = parse_start_time("2024-05-26T13:00:00Z", today()).unwrap();
+    assert_eq!(
+        at,
+        chrono::Utc
+            .with_ymd_and_hms(2024, 5, 26, 13, 0, 0)
+            .unwrap()
+            .with_timezone(&Local)
+    );
+
+    assert!(parse_start_time("3pm", today()).is_err());
+    assert!(parse_start_time("25:00", today()).is_err());
+}
+
+#[test]
+fn counts_down_then_reports_how_late_it_is() {
+    let now = Local::now();
+    let start = ScheduledStart::new(now + ChronoDuration::seconds(90));
+    assert_eq!(start.remaining(now), Duration::from_secs(90));
+    assert_eq!(start.lateness(now), None);
+
+    let later = now + ChronoDuration::seconds(95);
+    assert_eq!(start.remaining(later), Duration::ZERO);
+    assert_eq!(start.lateness(later), Some(Duration::from_secs(5)));
+    assert_eq!(start.lateness(start.at()), Some(Duration::ZERO));
+}
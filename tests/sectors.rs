@@ -0,0 +1,103 @@
+mod common;
+
+use common::{at, at_millis, record};
+use f1_led_circuit_master_simulation::mapping::RunRace;
+use f1_led_circuit_master_simulation::sectors::{sector_times, SectorConfig, SectorTimes};
+use f1_led_circuit_master_simulation::timeline::{DriverTimelines, LedCrossings};
+
+const LED_COUNT: usize = 30;
+
+fn config() -> SectorConfig {
+    SectorConfig {
+        starts: vec![0, 10, 20],
+    }
+}
+
+// A car crossing an LED every `millis_per_led` from `first_led` on, sampled only on every
+// fourth LED, so most sector starts fall between two samples
+fn constant_speed_lap(
+    driver_number: u32,
+    first_led: usize,
+    millis_per_led: i64,
+    samples: usize,
+) -> Vec<RunRace> {
+    (0..samples)
+        .map(|sample| {
+            record(
+                sample as i64 * 4 * millis_per_led,
+                driver_number,
+                (first_led + sample * 4) % LED_COUNT,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn times_every_sector_of_a_constant_speed_lap() {
+    // Two and a half laps at 100ms per LED: 1s per sector
+    let crossings = LedCrossings::new(&constant_speed_lap(1, 0, 100, 20), LED_COUNT);
+    let times = sector_times(&crossings, &config().boundaries(LED_COUNT), LED_COUNT);
+
+    let laps_and_sectors: Vec<(u32, u32)> =
+        times.iter().map(|time| (time.lap, time.sector)).collect();
+    assert_eq!(
+        laps_and_sectors,
+        vec![(1, 1), (1, 2), (1, 3), (2, 1), (2, 2), (2, 3), (3, 1)]
+    );
+    assert!(times.iter().all(|time| time.seconds == 1.0));
+    assert_eq!(times[0].end, at(1));
+    assert_eq!(times[6].end, at(7));
+}
+
+#[test]
+fn data_starting_mid_lap_times_only_complete_sectors() {
+    // From LED 14 in sector 2: the first complete sector is the third
+    let crossings = LedCrossings::new(&constant_speed_lap(1, 14, 100, 10), LED_COUNT);
+    let times = sector_times(&crossings, &config().boundaries(LED_COUNT), LED_COUNT);
+
+    assert_eq!((times[0].lap, times[0].sector), (1, 3));
+    assert_eq!(times[0].end, at_millis(1600));
+    assert_eq!((times[1].lap, times[1].sector), (2, 1));
+}
+
+#[test]
+fn reveals_times_as_the_clock_passes_them() {
+    let timelines = DriverTimelines::new(&constant_speed_lap(1, 0, 100, 20));
+    let times = SectorTimes::new(&timelines, LED_COUNT, &config());
+
+    let before = at_millis(999);
+    assert_eq!(times.last(1, 1, before), None);
+    let after = at(1);
+    assert_eq!(times.last(1, 1, after).map(|time| time.seconds), Some(1.0));
+    assert_eq!(times.last(1, 2, after), None);
+}
+
+#[test]
+fn keeps_personal_and_overall_bests() {
+    let mut race = constant_speed_lap(1, 0, 100, 20);
+    race.extend(constant_speed_lap(44, 0, 80, 20));
+    race.sort_by_key(|run_data| run_data.date);
+    let timelines = DriverTimelines::new(&race);
+    let times = SectorTimes::new(&timelines, LED_COUNT, &config());
+
+    let date = at(10);
+    assert_eq!(
+        times.personal_best(1, 2, date).map(|time| time.seconds),
+        Some(1.0)
+    );
+    assert_eq!(
+        times.overall_best(2, date).map(|time| time.seconds),
+        Some(0.8)
+    );
+    // Nobody has finished a sector yet
+    assert_eq!(times.overall_best(1, at_millis(700)), None);
+}
+
+#[test]
+fn splits_the_track_in_three_by_default() {
+    assert_eq!(SectorConfig::default().boundaries(30), vec![0, 10, 20]);
+    let config = SectorConfig {
+        starts: vec![20, 5, 40],
+    };
+    assert_eq!(config.boundaries(30), vec![5, 20]);
+}
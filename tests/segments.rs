@@ -0,0 +1,132 @@
+mod common;
+
+use common::sample;
+use f1_led_circuit_master_simulation::data::PipelineStats;
+use f1_led_circuit_master_simulation::error::AppError;
+use f1_led_circuit_master_simulation::mapping::{map_drivers, MappingOptions};
+use f1_led_circuit_master_simulation::segments::{
+    Layout, LayoutConfig, SegmentConfig, SegmentRole,
+};
+use f1_led_circuit_master_simulation::space::LedPoint;
+
+fn segment(
+    name: &str,
+    role: SegmentRole,
+    chain_offset: usize,
+    points: &[[f64; 2]],
+) -> SegmentConfig {
+    SegmentConfig {
+        name: name.to_string(),
+        role,
+        coordinates: Some(points.to_vec()),
+        chain_offset,
+        ..SegmentConfig::default()
+    }
+}
+
+// A straight of ten LEDs 100 apart, with a pit lane of three LEDs 20 apart below it
+fn straight_and_pit_lane() -> Layout {
+    let track: Vec<[f64; 2]> = (0..10).map(|index| [index as f64 * 100.0, 0.0]).collect();
+    let config = LayoutConfig {
+        segments: vec![
+            segment(
+                "pit lane",
+                SegmentRole::Pit,
+                10,
+                &[[300.0, -100.0], [320.0, -100.0], [340.0, -100.0]],
+            ),
+            segment("circuit", SegmentRole::Track, 0, &track),
+        ],
+    };
+    Layout::new(&config, Vec::new()).unwrap()
+}
+
+#[test]
+fn chains_the_segments_at_their_offsets() {
+    // The pit board comes first on the chain, then a dark pixel, then the circuit
+    let config = LayoutConfig {
+        segments: vec![
+            segment(
+                "circuit",
+                SegmentRole::Track,
+                3,
+                &[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]],
+            ),
+            segment("pit lane", SegmentRole::Pit, 0, &[[0.0, -1.0], [1.0, -1.0]]),
+        ],
+    };
+    let layout = Layout::new(&config, Vec::new()).unwrap();
+
+    assert_eq!(layout.coordinates().len(), 6);
+    assert_eq!(layout.coordinates()[4], LedPoint::new(0.0, -1.0));
+    assert_eq!(layout.pit_leds(), vec![4..6]);
+    assert!(layout.is_chained());
+    assert_eq!(layout.chain_length(), 7);
+    let leds = [
+        [1, 0, 0],
+        [2, 0, 0],
+        [3, 0, 0],
+        [4, 0, 0],
+        [0, 0, 1],
+        [0, 0, 2],
+    ];
+    assert_eq!(
+        layout.chain_frame(&leds),
+        vec![
+            [0, 0, 1],
+            [0, 0, 2],
+            [0, 0, 0],
+            [1, 0, 0],
+            [2, 0, 0],
+            [3, 0, 0],
+            [4, 0, 0]
+        ]
+    );
+}
+
+#[test]
+fn rejects_overlapping_chain_ranges() {
+    let config = LayoutConfig {
+        segments: vec![
+            segment(
+                "circuit",
+                SegmentRole::Track,
+                0,
+                &[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]],
+            ),
+            segment("pit lane", SegmentRole::Pit, 3, &[[0.0, -1.0], [1.0, -1.0]]),
+        ],
+    };
+    assert!(matches!(
+        Layout::new(&config, Vec::new()),
+        Err(AppError::LayoutInvalid { .. })
+    ));
+
+    // Without segments the built-in circuit is the whole chain
+    let builtin = vec![LedPoint::new(0.0, 0.0), LedPoint::new(1.0, 0.0)];
+    let layout = Layout::new(&LayoutConfig::default(), builtin.clone()).unwrap();
+    assert_eq!(layout.coordinates(), builtin.as_slice());
+    assert!(!layout.is_chained());
+}
+
+#[test]
+fn only_samples_close_to_the_pit_lane_go_on_it() {
+    let layout = straight_and_pit_lane();
+    let at = |millis, x, y| sample(millis, 1, x, y);
+    let options = MappingOptions {
+        collapse_duplicates: false,
+        pit_leds: layout.pit_leds(),
+        ..MappingOptions::default()
+    };
+
+    // Nearest the middle pit LED both times, but only the second is within its spacing
+    let (records, _) = map_drivers(
+        vec![(1, vec![at(0, 320.0, -60.0), at(1000, 320.0, -90.0)])],
+        layout.coordinates(),
+        &options,
+        &mut PipelineStats::default(),
+    )
+    .unwrap();
+    let leds: Vec<usize> = records.iter().map(|record| record.led_index).collect();
+    assert_eq!(leds, vec![3, 11]);
+}
@@ -0,0 +1,197 @@
+#![cfg(feature = "native")]
+
+mod common;
+
+use common::{at, start};
+use f1_led_circuit_master_simulation::bundle::Bundle;
+use f1_led_circuit_master_simulation::config::{ApiConfig, SessionConfig};
+use f1_led_circuit_master_simulation::driver_info::{
+    driver_colors, driver_numbers, get_driver_info, DriverInfo, RosterConfig,
+};
+use f1_led_circuit_master_simulation::mapping::{MappingOptions, RunRace};
+use f1_led_circuit_master_simulation::session_data::{
+    load_session_data, session_bundle, session_drivers, DataSource,
+};
+use f1_led_circuit_master_simulation::simulation::Simulation;
+use f1_led_circuit_master_simulation::space::LedPoint;
+use serde_json::json;
+use std::path::PathBuf;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const DRIVER: u32 = 99; // Not on any built-in roster, so the name comes from the driver list
+
+fn session() -> SessionConfig {
+    SessionConfig {
+        key: "9999".to_string(),
+        start_time: Some(start()),
+        end_time: Some(at(10)),
+        drivers: Some(vec![DRIVER]),
+        fetch_laps: false,
+        ..SessionConfig::default()
+    }
+}
+
+// LEDs every 10 units along a line
+fn layout() -> Vec<LedPoint> {
+    (0..10)
+        .map(|index| LedPoint::new(index as f64 * 10.0, 0.0))
+        .collect()
+}
+
+fn source(base_url: String, name: &str) -> DataSource {
+    DataSource {
+        api: ApiConfig {
+            base_url,
+            timeout_secs: 5,
+            retries: 0,
+            retry_delay_ms: 1,
+            session_margin_secs: 60,
+            ..ApiConfig::default()
+        },
+        mapping: MappingOptions::default(),
+        cache_dir: std::env::temp_dir().join(format!(
+            "f1-led-session-data-{}-{}",
+            name,
+            std::process::id()
+        )),
+        coordinates: layout(),
+        roster: RosterConfig::default(),
+    }
+}
+
+// The driver going along the LEDs, one a second
+fn serve_session() -> (tokio::runtime::Runtime, MockServer) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let server = runtime.block_on(async {
+        let server = MockServer::start().await;
+        let samples: Vec<_> = (0..10)
+            .map(|step| {
+                json!({
+                    "driver_number": DRIVER,
+                    "date": at(step).to_rfc3339(),
+                    "x": step as f64 * 10.0 + 1.0,
+                    "y": 0.5,
+                    "z": 0
+                })
+            })
+            .collect();
+        Mock::given(method("GET"))
+            .and(path("/location"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(samples))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/drivers"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+                "driver_number": DRIVER,
+                "full_name": "Test Driver",
+                "name_acronym": "TST",
+                "team_name": "Test Team",
+                "team_colour": "FF8000"
+            }])))
+            .mount(&server)
+            .await;
+        server
+    });
+    (runtime, server)
+}
+
+fn name(driver_info: &[DriverInfo]) -> &str {
+    let driver = driver_info.iter().find(|driver| driver.number == DRIVER);
+    &driver.unwrap().name
+}
+
+fn cleanup(source: &DataSource) {
+    std::fs::remove_dir_all(&source.cache_dir).ok();
+}
+
+#[test]
+fn fetch_caches_the_session_for_loading_it_offline() {
+    let (_runtime, server) = serve_session();
+    let online = source(server.uri(), "fetch");
+    cleanup(&online);
+
+    let fetched = load_session_data(&online, &session()).unwrap();
+    assert_eq!(fetched.run_race_data.len(), 10);
+    assert_eq!(name(&fetched.driver_info), "Test Driver");
+    assert!(fetched.made_up_drivers.is_empty());
+
+    // Nothing listens there; everything comes from the cache
+    let offline = DataSource {
+        api: ApiConfig {
+            base_url: "http://127.0.0.1:1".to_string(),
+            ..online.api.clone()
+        },
+        ..online.clone()
+    };
+    let cached = load_session_data(&offline, &session()).unwrap();
+    let leds =
+        |records: &[RunRace]| -> Vec<usize> { records.iter().map(|run| run.led_index).collect() };
+    assert_eq!(leds(&cached.run_race_data), leds(&fetched.run_race_data));
+    assert_eq!(name(&cached.driver_info), "Test Driver");
+    cleanup(&online);
+}
+
+#[test]
+fn map_writes_a_bundle_of_the_session() {
+    let (_runtime, server) = serve_session();
+    let source = source(server.uri(), "map");
+    cleanup(&source);
+    let path: PathBuf = source.cache_dir.join("bundles").join("session.f1led");
+
+    let loaded = load_session_data(&source, &session()).unwrap();
+    let records = loaded.run_race_data.len();
+    let bundle = session_bundle(&source, &session(), loaded);
+    bundle.save(&path).unwrap();
+
+    let opened = Bundle::open(&path).unwrap();
+    assert_eq!(opened.manifest(), bundle.manifest());
+    assert_eq!(opened.run_race_data.len(), records);
+    assert_eq!(opened.session.key, "9999");
+    assert!(!opened.needs_mapping(&source.coordinates));
+    cleanup(&source);
+}
+
+#[test]
+fn simulate_plays_a_bundle_mapped_onto_another_layout() {
+    let (_runtime, server) = serve_session();
+    let source = source(server.uri(), "simulate");
+    cleanup(&source);
+    let loaded = load_session_data(&source, &session()).unwrap();
+    let mut bundle = session_bundle(&source, &session(), loaded);
+
+    // Half as many LEDs, 20 units apart
+    let coarse: Vec<LedPoint> = (0..5)
+        .map(|index| LedPoint::new(index as f64 * 20.0, 0.0))
+        .collect();
+    assert!(bundle.map_onto(&coarse).unwrap());
+    let mut simulation = Simulation::new(
+        bundle.run_race_data,
+        coarse.len(),
+        driver_colors(&bundle.driver_info),
+    );
+    simulation.start();
+    simulation.tick(std::time::Duration::from_secs(4));
+
+    let lit: Vec<usize> = simulation.frame().lit().map(|(led, _)| led).collect();
+    assert!(lit.contains(&2), "lit {:?}", lit);
+    cleanup(&source);
+}
+
+#[test]
+fn fetches_the_whole_roster_unless_the_session_lists_drivers() {
+    let roster = get_driver_info();
+    let fetched = session_drivers(&SessionConfig::default(), &roster);
+    assert_eq!(fetched, driver_numbers(&roster));
+
+    // The drivers of the default season, once fetched from a list of their own
+    let mut sorted = fetched.clone();
+    sorted.sort_unstable();
+    assert_eq!(
+        sorted,
+        [1, 2, 4, 10, 11, 14, 16, 18, 20, 22, 23, 24, 27, 31, 40, 44, 55, 63, 77, 81]
+    );
+
+    assert_eq!(session_drivers(&session(), &roster), [DRIVER]);
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use common::{at, start};
+use f1_led_circuit_master_simulation::config::SessionConfig;
+use f1_led_circuit_master_simulation::data::SessionInfo;
+use f1_led_circuit_master_simulation::settings::{check_window, SessionForm, WindowProblem};
+
+const ROSTER: [u32; 3] = [1, 11, 44];
+
+fn session() -> SessionConfig {
+    SessionConfig {
+        key: "9157".to_string(),
+        name: Some("zandvoort-2023".to_string()),
+        ..SessionConfig::default()
+    }
+}
+
+#[test]
+fn applies_a_valid_form() {
+    let mut form = SessionForm::new(&session(), &ROSTER);
+    form.start_time = "2023-08-27T13:00:00Z".to_string();
+    form.end_time = "2023-08-27T16:00:00+02:00".to_string();
+    form.drivers.remove(&11);
+
+    let applied = form.apply(&session(), &ROSTER).unwrap();
+    assert_eq!(applied.key, "9157");
+    assert_eq!(applied.name.as_deref(), Some("zandvoort-2023"));
+    assert_eq!(applied.start_time, Some(start()));
+    assert_eq!(
+        applied.end_time,
+        Some("2023-08-27T14:00:00Z".parse().unwrap())
+    );
+    assert_eq!(applied.drivers, Some(vec![1, 44]));
+}
+
+#[test]
+fn the_whole_roster_is_no_driver_list() {
+    let form = SessionForm::new(&session(), &ROSTER);
+    assert_eq!(form.drivers.len(), 3);
+
+    let applied = form.apply(&session(), &ROSTER).unwrap();
+    assert_eq!(applied.drivers, None);
+    assert_eq!(applied.start_time, None);
+}
+
+#[test]
+fn a_new_key_drops_the_session_name() {
+    let mut form = SessionForm::new(&session(), &ROSTER);
+    form.key = " 9140 ".to_string();
+
+    let applied = form.apply(&session(), &ROSTER).unwrap();
+    assert_eq!(applied.key, "9140");
+    assert_eq!(applied.name, None);
+}
+
+#[test]
+fn reports_every_bad_field() {
+    let mut form = SessionForm::new(&session(), &ROSTER);
+    form.key = String::new();
+    form.start_time = "yesterday".to_string();
+    form.drivers.clear();
+
+    let errors = form.apply(&session(), &ROSTER).unwrap_err();
+    assert!(errors.key.is_some());
+    assert!(errors.start_time.is_some());
+    assert_eq!(errors.end_time, None);
+    assert!(errors.drivers.is_some());
+}
+
+#[test]
+fn the_end_must_follow_the_start() {
+    let mut form = SessionForm::new(&session(), &ROSTER);
+    form.start_time = "2023-08-27T14:00:00Z".to_string();
+    form.end_time = "2023-08-27T13:00:00Z".to_string();
+
+    let errors = form.apply(&session(), &ROSTER).unwrap_err();
+    assert_eq!(errors.start_time, None);
+    assert!(errors.end_time.is_some());
+}
+
+fn ran_from_13_to_15_30() -> SessionInfo {
+    SessionInfo {
+        date_start: start(),
+        date_end: at(9000),
+    }
+}
+
+fn windowed(start: &str, end: Option<&str>) -> SessionConfig {
+    SessionConfig {
+        key: "9149".to_string(),
+        start_time: Some(start.parse().unwrap()),
+        end_time: end.map(|end| end.parse().unwrap()),
+        ..SessionConfig::default()
+    }
+}
+
+#[test]
+fn catches_a_window_outside_the_session() {
+    let info = ran_from_13_to_15_30();
+    let session = windowed("2023-08-26T12:58:00Z", Some("2023-08-26T14:00:00Z"));
+    let problem = check_window(&session, Some(&info)).unwrap_err();
+    assert_eq!(
+        problem.message(),
+        "window 2023-08-26 12:58–2023-08-26 14:00 is outside session 9149 which ran \
+         2023-08-27 13:00–15:30"
+    );
+    // Without the session's bounds there's nothing to compare with
+    assert_eq!(check_window(&session, None), Ok(()));
+
+    // Using the session bounds fixes it
+    let mut form = SessionForm::new(&session, &ROSTER);
+    form.use_session_bounds(&info);
+    let fixed = form.apply(&session, &ROSTER).unwrap();
+    assert_eq!(check_window(&fixed, Some(&info)), Ok(()));
+
+    // Overlapping a part is fine
+    let session = windowed("2023-08-27T15:00:00Z", None);
+    assert_eq!(check_window(&session, Some(&info)), Ok(()));
+}
+
+#[test]
+fn catches_reversed_and_long_windows() {
+    let session = windowed("2023-08-27T14:00:00Z", Some("2023-08-27T13:00:00Z"));
+    assert_eq!(check_window(&session, None), Err(WindowProblem::Reversed));
+
+    let session = windowed("2023-08-27T06:00:00Z", Some("2023-08-27T15:00:00Z"));
+    assert_eq!(
+        check_window(&session, Some(&ran_from_13_to_15_30())),
+        Err(WindowProblem::TooLong { hours: 9.0 })
+    );
+}
@@ -0,0 +1,121 @@
+use f1_led_circuit_master_simulation::atomic_file::{temporary_path, write_atomically};
+use f1_led_circuit_master_simulation::error::AppError;
+use f1_led_circuit_master_simulation::recorder::{read_recording, FrameRecorder, RecorderConfig};
+use f1_led_circuit_master_simulation::simulation::{PlaybackState, Rgb};
+use f1_led_circuit_master_simulation::sink::{FrameDispatcher, LedSink, OutputFrame, SinkOptions};
+use f1_led_circuit_master_simulation::space::LedPoint;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+const LED_COUNT: usize = 4;
+
+// Stands in for a hardware output, reporting the colors of every frame it gets
+struct MockStrip {
+    frames: Sender<Vec<Rgb>>,
+}
+
+impl LedSink for MockStrip {
+    fn name(&self) -> &str {
+        "strip"
+    }
+
+    fn submit(&mut self, frame: &OutputFrame) -> Result<(), AppError> {
+        self.frames.send(frame.dimmed()).unwrap();
+        Ok(())
+    }
+}
+
+fn mock_strip() -> (MockStrip, Receiver<Vec<Rgb>>) {
+    let (frames, receiver) = mpsc::channel();
+    (MockStrip { frames }, receiver)
+}
+
+fn frame(second: u64) -> OutputFrame {
+    OutputFrame {
+        leds: vec![[255, 0, 0]; LED_COUNT],
+        brightness: 1.0,
+        timestamp: Duration::from_secs(second),
+        state: PlaybackState::Playing,
+        speed: 1.0,
+        drivers: Vec::new(),
+    }
+}
+
+fn temp_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("shutdown-test-{}-{}", std::process::id(), name))
+}
+
+fn recorder(path: &Path) -> FrameRecorder {
+    let coordinates: Vec<LedPoint> = (0..LED_COUNT)
+        .map(|index| LedPoint {
+            x: index as f64,
+            y: 0.0,
+        })
+        .collect();
+    let config = RecorderConfig {
+        enabled: true,
+        path: path.to_path_buf(),
+        fps: 30.0,
+    };
+    FrameRecorder::new(&config, &coordinates).unwrap()
+}
+
+#[test]
+fn dropping_the_outputs_blanks_the_strip_and_finishes_the_recording() {
+    let path = temp_file("race.ledrec");
+    let (strip, frames) = mock_strip();
+    let mut dispatcher = FrameDispatcher::new();
+    dispatcher
+        .register(Box::new(strip), SinkOptions::hardware(30.0))
+        .unwrap();
+    dispatcher
+        .register(Box::new(recorder(&path)), SinkOptions::default())
+        .unwrap();
+
+    dispatcher.dispatch(frame(1));
+    // Written under the temporary name until the recording ends
+    std::thread::sleep(Duration::from_millis(100));
+    assert!(temporary_path(&path).exists());
+    assert!(!path.exists());
+    drop(dispatcher);
+
+    let frames: Vec<Vec<Rgb>> = frames.try_iter().collect();
+    assert_eq!(frames.first(), Some(&vec![[255, 0, 0]; LED_COUNT]));
+    assert_eq!(frames.last(), Some(&vec![[0, 0, 0]; LED_COUNT]));
+    assert!(!temporary_path(&path).exists());
+    let (_, recorded) = read_recording(std::fs::File::open(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    // The recording keeps the race as shown, without the blank frame
+    assert_eq!(recorded.len(), 1);
+    assert_eq!(recorded[0].leds, vec![[255, 0, 0]; LED_COUNT]);
+}
+
+#[test]
+fn nothing_is_blanked_before_the_first_frame() {
+    let (strip, frames) = mock_strip();
+    let mut dispatcher = FrameDispatcher::new();
+    dispatcher
+        .register(Box::new(strip), SinkOptions::default())
+        .unwrap();
+
+    dispatcher.blank_and_shutdown(Duration::from_secs(1));
+
+    assert_eq!(frames.try_iter().count(), 0);
+}
+
+#[test]
+fn replaces_a_file_in_one_step() {
+    let path = temp_file("cache.bin");
+    std::fs::write(&path, b"old").unwrap();
+
+    write_atomically(&path, b"new").unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), b"new");
+    assert!(!temporary_path(&path).exists());
+    assert_eq!(
+        temporary_path(Path::new("cache/session.bin")),
+        Path::new("cache/session.bin.tmp")
+    );
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,361 @@
+mod common;
+
+use f1_led_circuit_master_simulation::control::PlaybackCommand;
+use f1_led_circuit_master_simulation::mapping::{LedPositioning, RunRace};
+use f1_led_circuit_master_simulation::simulation::{PlaybackState, Rgb, Simulation};
+use f1_led_circuit_master_simulation::space::{LedPoint, TelemetryPoint};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+use std::time::Duration;
+
+const RED: Rgb = [255, 0, 0];
+const BLUE: Rgb = [0, 0, 255];
+const LED_COUNT: usize = 10;
+
+// Driver 1 starts on LED 0 and moves to LED 1 at 2s; driver 2 appears on LED 5 at 1s and
+// moves to LED 6 at 3s
+fn scripted_race() -> Simulation {
+    let record = |seconds: i64, driver_number, led_index| {
+        common::record(seconds * 1000, driver_number, led_index)
+    };
+    let run_race_data = vec![
+        record(0, 1, 0),
+        record(1, 2, 5),
+        record(2, 1, 1),
+        record(3, 2, 6),
+    ];
+    let colors = HashMap::from([(1, RED), (2, BLUE)]);
+    Simulation::new(run_race_data, LED_COUNT, colors)
+}
+
+fn lit(simulation: &Simulation) -> Vec<(usize, Rgb)> {
+    simulation.frame().lit().collect()
+}
+
+fn secs(seconds: f64) -> Duration {
+    Duration::from_secs_f64(seconds)
+}
+
+#[test]
+fn stays_dark_until_started() {
+    let mut simulation = scripted_race();
+
+    assert!(simulation.tick(secs(5.0)).lit().next().is_none());
+    assert_eq!(simulation.race_time(), 0.0);
+    assert!(!simulation.is_running());
+}
+
+#[test]
+fn plays_positions_at_known_times() {
+    let mut simulation = scripted_race();
+    simulation.start();
+
+    simulation.tick(secs(0.0));
+    assert_eq!(lit(&simulation), [(0, RED)]);
+
+    simulation.tick(secs(1.0));
+    assert_eq!(lit(&simulation), [(0, RED), (5, BLUE)]);
+
+    simulation.tick(secs(1.5));
+    assert_eq!(simulation.race_time(), 2.5);
+    assert_eq!(lit(&simulation), [(1, RED), (5, BLUE)]);
+    assert!(!simulation.is_finished());
+
+    simulation.tick(secs(1.0));
+    assert_eq!(lit(&simulation), [(1, RED), (6, BLUE)]);
+    assert!(simulation.is_finished());
+}
+
+#[test]
+fn lasts_from_the_first_record_to_the_last() {
+    assert_eq!(scripted_race().duration(), 3.0);
+    assert_eq!(
+        Simulation::new(Vec::new(), LED_COUNT, HashMap::new()).duration(),
+        0.0
+    );
+}
+
+#[test]
+fn speed_scales_the_clock() {
+    let mut simulation = scripted_race();
+    simulation.set_speed(2.0);
+    simulation.start();
+
+    simulation.tick(secs(1.0));
+
+    assert_eq!(simulation.race_time(), 2.0);
+    assert_eq!(lit(&simulation), [(1, RED), (5, BLUE)]);
+}
+
+#[test]
+fn changing_speed_mid_playback_keeps_the_clock() {
+    let mut simulation = scripted_race();
+    simulation.start();
+    simulation.tick(secs(1.0));
+
+    simulation.set_speed(0.5);
+    assert_eq!(simulation.race_time(), 1.0);
+    simulation.tick(secs(1.0));
+    assert_eq!(simulation.race_time(), 1.5);
+    assert_eq!(lit(&simulation), [(0, RED), (5, BLUE)]);
+}
+
+#[test]
+fn steps_the_same_however_the_time_is_split() {
+    // Frames after every call, with the time split unevenly and a hitch that's capped
+    let run = |elapsed: &[u64]| {
+        let mut simulation = scripted_race();
+        simulation.set_speed(2.5);
+        simulation.start();
+        elapsed
+            .iter()
+            .map(|&millis| {
+                simulation.advance(Duration::from_millis(millis));
+                (simulation.race_time(), lit(&simulation))
+            })
+            .collect::<Vec<_>>()
+    };
+    let jittery = [10, 70, 20, 16, 34, 2000, 45, 5, 300, 120];
+    assert_eq!(run(&jittery), run(&jittery));
+
+    // Steps of 50ms at 2.5x; the hitch catches up half a second of real time
+    let race_times: Vec<f64> = run(&jittery).iter().map(|(time, _)| *time).collect();
+    assert_eq!(
+        race_times,
+        [0.0, 0.125, 0.25, 0.25, 0.375, 1.625, 1.625, 1.75, 2.5, 2.75]
+    );
+    let smooth = run(&[50; 4]);
+    assert_eq!(run(&[10, 40, 30, 70, 50])[4], smooth[3]);
+}
+
+#[test]
+fn seeks_backwards_and_forwards() {
+    let mut simulation = scripted_race();
+    simulation.start();
+    simulation.tick(secs(3.0));
+
+    let frame = simulation.seek(secs(0.5)).clone();
+    assert_eq!(frame.lit().collect::<Vec<_>>(), [(0, RED)]);
+
+    simulation.seek(secs(2.0));
+    assert_eq!(lit(&simulation), [(1, RED), (5, BLUE)]);
+}
+
+#[test]
+fn incremental_playback_matches_a_full_replay_after_random_seeks() {
+    // Four drivers moving around 40 LEDs at uneven intervals over two minutes
+    let mut rng = StdRng::seed_from_u64(852);
+    let mut run_race_data: Vec<RunRace> = (0..2000)
+        .map(|_| {
+            let led_index = rng.gen_range(0..40);
+            let millis = rng.gen_range(0..120_000);
+            common::record(millis, rng.gen_range(1..=4), led_index)
+        })
+        .collect();
+    run_race_data.sort_by_key(|run| run.date);
+    let colors = HashMap::from([(1, RED), (2, BLUE), (3, [0, 255, 0]), (4, [255, 255, 0])]);
+    let replay = || Simulation::new(run_race_data.clone(), 40, colors.clone());
+
+    let mut incremental = replay();
+    incremental.start();
+    for _ in 0..200 {
+        if rng.gen_bool(0.3) {
+            incremental.seek(secs(rng.gen_range(0.0..130.0)));
+        } else {
+            incremental.tick(secs(rng.gen_range(0.0..3.0)));
+        }
+
+        let mut full = replay();
+        full.start();
+        full.seek(secs(incremental.clock_time()));
+        assert_eq!(
+            lit(&incremental),
+            lit(&full),
+            "at {}s",
+            incremental.clock_time()
+        );
+    }
+}
+
+#[test]
+fn skips_from_the_clock_while_playing_or_paused() {
+    let mut simulation = scripted_race();
+    // Nothing to skip before the start
+    simulation.skip(2.0);
+    assert_eq!(simulation.clock_time(), 0.0);
+
+    simulation.start();
+    simulation.tick(secs(0.5));
+    simulation.skip(2.0);
+    assert_eq!(simulation.clock_time(), 2.5);
+    assert_eq!(lit(&simulation), [(1, RED), (5, BLUE)]);
+
+    simulation.set_paused(true);
+    PlaybackCommand::Skip(-2.0).apply(&mut simulation, &(0.1..=10.0));
+    assert_eq!(simulation.clock_time(), 0.5);
+    assert_eq!(lit(&simulation), [(0, RED)]);
+    assert_eq!(simulation.state(), PlaybackState::Paused);
+
+    // Not back past the start
+    simulation.skip(-10.0);
+    assert_eq!(simulation.clock_time(), 0.0);
+}
+
+#[test]
+fn plays_backwards_down_to_the_start() {
+    let mut simulation = scripted_race();
+    // Nothing to reverse before the start
+    simulation.set_reversed(true);
+    assert!(!simulation.is_reversed());
+
+    simulation.start();
+    simulation.tick(secs(3.5));
+    simulation.set_speed(2.0);
+    simulation.set_reversed(true);
+    simulation.tick(secs(0.5));
+    assert_eq!(simulation.clock_time(), 2.5);
+    assert_eq!(lit(&simulation), [(1, RED), (5, BLUE)]);
+
+    // Driver 2 had no sample yet
+    simulation.tick(secs(1.0));
+    assert_eq!(simulation.clock_time(), 0.5);
+    assert_eq!(lit(&simulation), [(0, RED)]);
+
+    // Reaching the start pauses, facing forwards again
+    simulation.tick(secs(1.0));
+    assert_eq!(simulation.clock_time(), 0.0);
+    assert_eq!(simulation.state(), PlaybackState::Paused);
+    assert!(!simulation.is_reversed());
+    assert_eq!(lit(&simulation), [(0, RED)]);
+
+    simulation.set_paused(false);
+    simulation.tick(secs(1.0));
+    assert_eq!(simulation.clock_time(), 2.0);
+    assert_eq!(lit(&simulation), [(1, RED), (5, BLUE)]);
+}
+
+#[test]
+fn time_offset_shifts_the_data_against_the_clock() {
+    let mut simulation = scripted_race();
+    simulation.set_time_offset(2.0);
+    simulation.start();
+    assert_eq!(simulation.clock_time(), 0.0);
+    assert_eq!(lit(&simulation), [(1, RED), (5, BLUE)]);
+
+    // Nudging it mid-playback keeps the clock and moves the data
+    simulation.tick(secs(0.5));
+    simulation.set_time_offset(-0.5);
+    assert_eq!(simulation.clock_time(), 0.5);
+    assert_eq!(simulation.race_time(), 0.0);
+    assert_eq!(lit(&simulation), [(0, RED)]);
+
+    // Seeking is in clock time too
+    simulation.seek(secs(3.5));
+    assert_eq!(simulation.race_time(), 3.0);
+    assert_eq!(lit(&simulation), [(1, RED), (6, BLUE)]);
+}
+
+#[test]
+fn hidden_drivers_are_not_lit() {
+    let mut simulation = scripted_race();
+    simulation.start();
+    simulation.tick(secs(1.0));
+
+    simulation.set_driver_hidden(2, true);
+    assert_eq!(lit(&simulation), [(0, RED)]);
+
+    simulation.set_driver_hidden(2, false);
+    assert_eq!(lit(&simulation), [(0, RED), (5, BLUE)]);
+}
+
+#[test]
+fn reset_rewinds_to_a_dark_board() {
+    let mut simulation = scripted_race();
+    simulation.start();
+    simulation.tick(secs(2.0));
+
+    simulation.reset();
+
+    assert!(lit(&simulation).is_empty());
+    assert_eq!(simulation.race_time(), 0.0);
+    assert!(!simulation.is_running());
+}
+
+#[test]
+fn commands_pause_resume_and_clamp_the_speed() {
+    let mut simulation = scripted_race();
+    let speeds = 1.0..=8.0;
+
+    PlaybackCommand::TogglePause.apply(&mut simulation, &speeds);
+    assert_eq!(simulation.state(), PlaybackState::Playing);
+    simulation.tick(secs(1.0));
+
+    PlaybackCommand::Pause.apply(&mut simulation, &speeds);
+    simulation.tick(secs(5.0));
+    assert_eq!(simulation.state(), PlaybackState::Paused);
+    assert_eq!(simulation.race_time(), 1.0);
+    assert_eq!(lit(&simulation), [(0, RED), (5, BLUE)]);
+
+    // Starting a paused replay resumes it instead of rewinding
+    PlaybackCommand::Start.apply(&mut simulation, &speeds);
+    simulation.tick(secs(1.0));
+    assert_eq!(simulation.state(), PlaybackState::Playing);
+    assert_eq!(simulation.race_time(), 2.0);
+
+    PlaybackCommand::SetSpeed(20.0).apply(&mut simulation, &speeds);
+    assert_eq!(simulation.speed(), 8.0);
+    PlaybackCommand::AdjustSpeed(-10.0).apply(&mut simulation, &speeds);
+    assert_eq!(simulation.speed(), 1.0);
+
+    PlaybackCommand::Stop.apply(&mut simulation, &speeds);
+    PlaybackCommand::Pause.apply(&mut simulation, &speeds);
+    assert_eq!(simulation.state(), PlaybackState::Stopped);
+}
+
+#[test]
+fn plays_through_empty_data_without_panicking() {
+    for led_count in [0, LED_COUNT] {
+        let mut simulation = Simulation::new(Vec::new(), led_count, HashMap::new());
+        assert_eq!(simulation.record_count(), 0);
+        assert_eq!(simulation.duration(), 0.0);
+        assert_eq!(simulation.race_date(), None);
+
+        simulation.start();
+        simulation.tick(secs(1.0));
+        assert!(simulation.is_finished());
+        simulation.seek(secs(5.0));
+        simulation.set_time_offset(-2.0);
+        simulation.tick(secs(1.0));
+        assert!(lit(&simulation).is_empty());
+        assert!(simulation.track_progress().drivers().is_empty());
+        simulation.reset();
+    }
+}
+
+// Driver 1 sits 30% of the way from LED 2 to LED 3; driver 2 comes later onto LED 3 exactly
+#[test]
+fn blends_cars_between_two_leds() {
+    let coordinates: Vec<LedPoint> = (0..LED_COUNT)
+        .map(|index| LedPoint::new(index as f64 * 10.0, 0.0))
+        .collect();
+    let record = |seconds: i64, driver_number, led_index, x| RunRace {
+        point: TelemetryPoint::new(x, 0.0),
+        ..common::record(seconds * 1000, driver_number, led_index)
+    };
+    let run_race_data = vec![record(0, 1, 2, 23.0), record(1, 2, 3, 30.0)];
+    let colors = HashMap::from([(1, RED), (2, BLUE)]);
+    let mut simulation = Simulation::new(run_race_data, LED_COUNT, colors);
+    simulation.set_positioning(LedPositioning::Blended, &coordinates);
+    simulation.start();
+
+    simulation.tick(secs(0.0));
+    assert_eq!(lit(&simulation), [(2, [179, 0, 0]), (3, [77, 0, 0])]);
+
+    // A car wholly on an LED covers the share of the one before it there
+    simulation.tick(secs(1.0));
+    assert_eq!(lit(&simulation), [(2, [179, 0, 0]), (3, BLUE)]);
+
+    simulation.set_positioning(LedPositioning::Nearest, &coordinates);
+    assert_eq!(lit(&simulation), [(2, RED), (3, BLUE)]);
+}
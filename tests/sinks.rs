@@ -0,0 +1,317 @@
+use f1_led_circuit_master_simulation::config::Config;
+use f1_led_circuit_master_simulation::error::AppError;
+use f1_led_circuit_master_simulation::simulation::PlaybackState;
+use f1_led_circuit_master_simulation::sink::{
+    FrameDispatcher, HardwareSink, LedSink, OutputConfig, OutputFrame, SinkOptions, SinkStats,
+};
+use f1_led_circuit_master_simulation::space::LedPoint;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, PartialEq)]
+enum Event {
+    Start,
+    Frame(u64), // Timestamp in seconds
+    Shutdown,
+}
+
+// Reports everything it sees; with a gate, each frame waits for a go-ahead before returning
+struct TestSink {
+    events: Sender<Event>,
+    gate: Option<Receiver<()>>,
+    fail_on: Option<u64>,
+}
+
+impl LedSink for TestSink {
+    fn name(&self) -> &str {
+        "test"
+    }
+
+    fn start(&mut self) -> Result<(), AppError> {
+        self.events.send(Event::Start).unwrap();
+        Ok(())
+    }
+
+    fn submit(&mut self, frame: &OutputFrame) -> Result<(), AppError> {
+        let second = frame.timestamp.as_secs();
+        self.events.send(Event::Frame(second)).unwrap();
+        if let Some(gate) = &self.gate {
+            gate.recv_timeout(TIMEOUT).unwrap();
+        }
+        if self.fail_on == Some(second) {
+            return Err(AppError::Output {
+                reason: "test failure".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn shutdown(&mut self) {
+        self.events.send(Event::Shutdown).unwrap();
+    }
+}
+
+fn frame(second: u64) -> OutputFrame {
+    frame_with(second, [255, 128, 0])
+}
+
+fn frame_with(second: u64, color: [u8; 3]) -> OutputFrame {
+    OutputFrame {
+        leds: vec![color],
+        brightness: 0.5,
+        timestamp: Duration::from_secs(second),
+        state: PlaybackState::Playing,
+        speed: 1.0,
+        drivers: Vec::new(),
+    }
+}
+
+fn test_sink(gate: Option<Receiver<()>>, fail_on: Option<u64>) -> (TestSink, Receiver<Event>) {
+    let (events, receiver) = mpsc::channel();
+    let sink = TestSink {
+        events,
+        gate,
+        fail_on,
+    };
+    (sink, receiver)
+}
+
+#[test]
+fn every_sink_gets_the_frame_before_shutdown() {
+    let mut dispatcher = FrameDispatcher::new();
+    let (first, first_events) = test_sink(None, None);
+    let (second, second_events) = test_sink(None, None);
+    dispatcher
+        .register(Box::new(first), SinkOptions::default())
+        .unwrap();
+    dispatcher
+        .register(Box::new(second), SinkOptions::default())
+        .unwrap();
+
+    dispatcher.dispatch(frame(1));
+    dispatcher.shutdown();
+
+    for events in [first_events, second_events] {
+        let events: Vec<Event> = events.try_iter().collect();
+        assert_eq!(events, [Event::Start, Event::Frame(1), Event::Shutdown]);
+    }
+    assert!(dispatcher.take_error().is_none());
+}
+
+#[test]
+fn slow_sink_drops_stale_frames() {
+    let mut dispatcher = FrameDispatcher::new();
+    let (go, gate) = mpsc::channel();
+    let (sink, events) = test_sink(Some(gate), None);
+    dispatcher
+        .register(Box::new(sink), SinkOptions::default())
+        .unwrap();
+
+    dispatcher.dispatch(frame(1));
+    assert_eq!(events.recv_timeout(TIMEOUT).unwrap(), Event::Start);
+    assert_eq!(events.recv_timeout(TIMEOUT).unwrap(), Event::Frame(1));
+
+    // The sink is stuck in frame 1, so each of these replaces the one before
+    for second in 2..=5 {
+        dispatcher.dispatch(frame(second));
+    }
+    go.send(()).unwrap();
+    go.send(()).unwrap();
+    dispatcher.shutdown();
+
+    let events: Vec<Event> = events.try_iter().collect();
+    assert_eq!(events, [Event::Frame(5), Event::Shutdown]);
+    assert_eq!(
+        dispatcher.stats(),
+        [(
+            "test",
+            SinkStats {
+                produced: 5,
+                sent: 2,
+                skipped: 0,
+                dropped: 3
+            }
+        )]
+    );
+}
+
+#[test]
+fn failing_sink_stops_without_affecting_others() {
+    let mut dispatcher = FrameDispatcher::new();
+    let (go, gate) = mpsc::channel();
+    let (failing, failing_events) = test_sink(Some(gate), Some(1));
+    let (healthy, healthy_events) = test_sink(None, None);
+    dispatcher
+        .register(Box::new(failing), SinkOptions::default())
+        .unwrap();
+    dispatcher
+        .register(Box::new(healthy), SinkOptions::default())
+        .unwrap();
+
+    dispatcher.dispatch(frame(1));
+    go.send(()).unwrap();
+    assert_eq!(failing_events.recv_timeout(TIMEOUT).unwrap(), Event::Start);
+    assert_eq!(
+        failing_events.recv_timeout(TIMEOUT).unwrap(),
+        Event::Frame(1)
+    );
+    assert_eq!(
+        failing_events.recv_timeout(TIMEOUT).unwrap(),
+        Event::Shutdown
+    );
+
+    dispatcher.dispatch(frame(2));
+    dispatcher.shutdown();
+
+    assert!(matches!(
+        dispatcher.take_error(),
+        Some(AppError::Output { .. })
+    ));
+    assert!(failing_events.try_iter().next().is_none());
+    let events: Vec<Event> = healthy_events.try_iter().collect();
+    assert_eq!(events.first(), Some(&Event::Start));
+    assert_eq!(events.iter().rev().nth(1), Some(&Event::Frame(2)));
+    assert_eq!(events.last(), Some(&Event::Shutdown));
+}
+
+#[test]
+fn dimmed_applies_the_brightness() {
+    assert_eq!(frame(0).dimmed(), [[128, 64, 0]]);
+}
+
+#[test]
+fn corrects_the_gamma_after_the_brightness() {
+    let (sink, _) = test_sink(None, None);
+    let gamma = HardwareSink::new(
+        Box::new(sink),
+        &OutputConfig {
+            gamma: 2.0,
+            ..OutputConfig::default()
+        },
+        &[LedPoint::new(0.0, 0.0)],
+    );
+    // 128 is about half, so a quarter once squared
+    assert_eq!(gamma.correct(&frame(0)), [[64, 16, 0]]);
+
+    let (sink, _) = test_sink(None, None);
+    assert!(OutputConfig::default().is_identity());
+    let linear = HardwareSink::new(
+        Box::new(sink),
+        &OutputConfig::default(),
+        &[LedPoint::new(0.0, 0.0)],
+    );
+    assert_eq!(linear.correct(&frame(0)), frame(0).dimmed());
+}
+
+#[test]
+fn only_the_ws2812_strip_defaults_to_a_gamma() {
+    let config = Config::default();
+    assert_eq!(config.output("ws2812").gamma, 2.8);
+    assert!(config.output("dmx").is_identity());
+
+    let config = Config::parse("[outputs.ws2812]\ngamma = 1.0\n").unwrap();
+    assert!(config.output("ws2812").is_identity());
+}
+
+#[test]
+fn reorders_the_leds_of_a_rotated_and_flipped_board() {
+    // A square's corners counterclockwise from the bottom left, each in its own color
+    let square = [
+        LedPoint::new(0.0, 0.0),
+        LedPoint::new(10.0, 0.0),
+        LedPoint::new(10.0, 10.0),
+        LedPoint::new(0.0, 10.0),
+    ];
+    let frame = OutputFrame {
+        leds: vec![[1, 0, 0], [2, 0, 0], [3, 0, 0], [4, 0, 0]],
+        brightness: 1.0,
+        ..frame(0)
+    };
+    let mounted = |rotation: f64, flip_horizontal: bool, flip_vertical: bool| {
+        let (sink, _) = test_sink(None, None);
+        let config = OutputConfig {
+            rotation,
+            flip_horizontal,
+            flip_vertical,
+            ..OutputConfig::default()
+        };
+        HardwareSink::new(Box::new(sink), &config, &square).correct(&frame)
+    };
+
+    assert_eq!(mounted(0.0, false, false), frame.leds);
+    // A quarter turn clockwise puts the bottom left corner at the top left
+    assert_eq!(
+        mounted(90.0, false, false),
+        [[4, 0, 0], [1, 0, 0], [2, 0, 0], [3, 0, 0]]
+    );
+    // Flipping that sideways takes it to the top right, the same as a flip on the other axis
+    // would from a quarter turn the other way
+    let rotated_and_flipped = mounted(90.0, true, false);
+    assert_eq!(
+        rotated_and_flipped,
+        [[3, 0, 0], [2, 0, 0], [1, 0, 0], [4, 0, 0]]
+    );
+    assert_eq!(mounted(270.0, false, true), rotated_and_flipped);
+    // Both flips are half a turn
+    assert_eq!(mounted(0.0, true, true), mounted(180.0, false, false));
+}
+
+#[test]
+fn skips_unchanged_frames() {
+    let mut dispatcher = FrameDispatcher::new();
+    let (sink, events) = test_sink(None, None);
+    let options = SinkOptions {
+        max_fps: None,
+        skip_unchanged: true,
+    };
+    dispatcher.register(Box::new(sink), options).unwrap();
+
+    dispatcher.dispatch(frame(1));
+    assert_eq!(events.recv_timeout(TIMEOUT).unwrap(), Event::Start);
+    assert_eq!(events.recv_timeout(TIMEOUT).unwrap(), Event::Frame(1));
+    dispatcher.dispatch(frame(2)); // Same LEDs, later race time
+    dispatcher.shutdown();
+
+    let events: Vec<Event> = events.try_iter().collect();
+    assert_eq!(events, [Event::Shutdown]);
+    assert_eq!(dispatcher.stats()[0].1.skipped, 1);
+}
+
+#[test]
+fn resends_unchanged_frame_as_keep_alive() {
+    let mut dispatcher = FrameDispatcher::new();
+    let (sink, events) = test_sink(None, None);
+    dispatcher
+        .register(Box::new(sink), SinkOptions::hardware(30.0))
+        .unwrap();
+
+    dispatcher.dispatch(frame(1));
+    assert_eq!(events.recv_timeout(TIMEOUT).unwrap(), Event::Start);
+    assert_eq!(events.recv_timeout(TIMEOUT).unwrap(), Event::Frame(1));
+    let sent = Instant::now();
+    let keep_alive = events.recv_timeout(Duration::from_secs(3)).unwrap();
+
+    assert_eq!(keep_alive, Event::Frame(1));
+    assert!(sent.elapsed() >= Duration::from_millis(1900));
+}
+
+#[test]
+fn limits_the_frame_rate() {
+    let mut dispatcher = FrameDispatcher::new();
+    let (sink, events) = test_sink(None, None);
+    dispatcher
+        .register(Box::new(sink), SinkOptions::hardware(10.0))
+        .unwrap();
+
+    dispatcher.dispatch(frame_with(1, [1, 1, 1]));
+    assert_eq!(events.recv_timeout(TIMEOUT).unwrap(), Event::Start);
+    assert_eq!(events.recv_timeout(TIMEOUT).unwrap(), Event::Frame(1));
+    let sent = Instant::now();
+    dispatcher.dispatch(frame_with(2, [2, 2, 2]));
+    dispatcher.dispatch(frame_with(3, [3, 3, 3]));
+
+    assert_eq!(events.recv_timeout(TIMEOUT).unwrap(), Event::Frame(3));
+    assert!(sent.elapsed() >= Duration::from_millis(90));
+}
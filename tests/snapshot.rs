@@ -0,0 +1,117 @@
+mod common;
+
+use common::record;
+use f1_led_circuit_master_simulation::color_scheme::ColorSchemeKind;
+use f1_led_circuit_master_simulation::config::SessionConfig;
+use f1_led_circuit_master_simulation::mapping::{MappingOptions, RunRace};
+use f1_led_circuit_master_simulation::qualifying::QualifyingConfig;
+use f1_led_circuit_master_simulation::simulation::{PlaybackState, Simulation};
+use f1_led_circuit_master_simulation::snapshot::{DatasetIds, SnapshotSettings, StateSnapshot};
+use f1_led_circuit_master_simulation::space::LedPoint;
+use std::collections::HashMap;
+use std::time::Duration;
+
+const LED_COUNT: usize = 10;
+
+fn coordinates() -> Vec<LedPoint> {
+    (0..LED_COUNT)
+        .map(|index| LedPoint::new(index as f64, 0.0))
+        .collect()
+}
+
+// Drivers 1 and 44 moving an LED along every second for a minute
+fn race_data() -> Vec<RunRace> {
+    (0..60)
+        .flat_map(|second| {
+            [(1, 0), (44, 5)].map(|(driver_number, offset)| {
+                record(
+                    second * 1000,
+                    driver_number,
+                    (second as usize + offset) % LED_COUNT,
+                )
+            })
+        })
+        .collect()
+}
+
+fn snapshot(simulation: &Simulation) -> StateSnapshot {
+    let dataset = DatasetIds::new(
+        &SessionConfig::default(),
+        &coordinates(),
+        7,
+        simulation.run_race_data(),
+    );
+    let settings = SnapshotSettings {
+        focused_driver: Some(44),
+        brightness: 0.5,
+        mapping: MappingOptions::default(),
+        qualifying: QualifyingConfig::default(),
+    };
+    StateSnapshot::new(simulation, dataset, settings)
+}
+
+#[test]
+fn notes_the_drivers_and_the_frame_shown() {
+    let mut simulation = Simulation::new(race_data(), LED_COUNT, HashMap::new());
+    simulation.start();
+    simulation.seek(Duration::from_secs(12));
+    simulation.set_driver_hidden(1, true);
+    let snapshot = snapshot(&simulation);
+
+    assert_eq!(snapshot.playback.state, PlaybackState::Playing);
+    assert_eq!(snapshot.playback.clock_time, 12.0);
+    assert_eq!(snapshot.playback.hidden_drivers, [1]);
+    assert_eq!(snapshot.dataset.cache_key, "0000000000000007");
+    // Hidden drivers still have a position, but aren't lit
+    let drivers: Vec<(u32, usize)> = snapshot
+        .drivers
+        .iter()
+        .map(|driver| (driver.driver_number, driver.led_index))
+        .collect();
+    assert_eq!(drivers, [(1, 2), (44, 7)]);
+    assert_eq!(snapshot.leds.len(), 1);
+    assert_eq!(snapshot.leds[0].index, 7);
+}
+
+#[test]
+fn restores_the_frame_it_was_taken_on() {
+    let mut simulation = Simulation::new(race_data(), LED_COUNT, HashMap::new());
+    simulation.start();
+    simulation.set_speed(4.0);
+    simulation.set_color_scheme(ColorSchemeKind::Position);
+    simulation.seek(Duration::from_secs(30));
+    simulation.set_driver_hidden(44, true);
+
+    let dir = std::env::temp_dir().join(format!("{}-snapshot", std::process::id()));
+    let path = snapshot(&simulation).write(&dir).unwrap();
+    let snapshot = StateSnapshot::read(&path).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let mut restored = Simulation::new(race_data(), LED_COUNT, HashMap::new());
+    restored.set_driver_hidden(1, true);
+    assert!(snapshot
+        .mismatches(&coordinates(), restored.run_race_data())
+        .is_empty());
+    snapshot.restore(&mut restored);
+
+    assert_eq!(restored.state(), PlaybackState::Paused);
+    assert_eq!(restored.clock_time(), 30.0);
+    assert_eq!(restored.speed(), 4.0);
+    assert_eq!(restored.color_scheme(), ColorSchemeKind::Position);
+    assert!(!restored.is_driver_hidden(1) && restored.is_driver_hidden(44));
+    assert_eq!(restored.frame(), simulation.frame());
+    assert_eq!(snapshot.settings.focused_driver, Some(44));
+}
+
+#[test]
+fn tells_other_data_apart() {
+    let simulation = Simulation::new(race_data(), LED_COUNT, HashMap::new());
+    let snapshot = snapshot(&simulation);
+    let mut refreshed = race_data();
+    refreshed.pop();
+
+    assert_eq!(
+        snapshot.mismatches(&coordinates()[1..], &refreshed),
+        ["the LED layout", "the mapped data"]
+    );
+}
@@ -0,0 +1,59 @@
+use f1_led_circuit_master_simulation::data::LocationData;
+use f1_led_circuit_master_simulation::mapping::generate_run_race_data;
+use f1_led_circuit_master_simulation::space::{LedPoint, TelemetryPoint};
+use serde_json::json;
+
+#[test]
+fn reads_and_writes_samples_with_flat_coordinates() {
+    let sample: LocationData = serde_json::from_value(json!({
+        "x": 5431,
+        "y": -822.5,
+        "z": 12,
+        "date": "2023-08-27T13:03:05.123000+00:00",
+        "driver_number": 44,
+        "session_key": 9165,
+    }))
+    .unwrap();
+    assert_eq!(sample.point, TelemetryPoint::new(5431.0, -822.5));
+
+    let written = serde_json::to_value(&sample).unwrap();
+    assert_eq!(written["x"], 5431.0);
+    assert_eq!(written["y"], -822.5);
+    assert!(written.get("point").is_none());
+}
+
+#[test]
+fn measures_and_interpolates_within_a_space() {
+    let from = TelemetryPoint::new(1.0, 2.0);
+    let to = TelemetryPoint::new(4.0, 6.0);
+    assert_eq!(from.distance(to), 5.0);
+    assert_eq!(from.lerp(to, 0.25), TelemetryPoint::new(1.75, 3.0));
+    assert_eq!(
+        LedPoint::new(0.0, 0.0).distance(LedPoint::new(-3.0, 4.0)),
+        5.0
+    );
+}
+
+#[test]
+fn snaps_onto_the_board_keeping_the_telemetry_location() {
+    let samples: Vec<LocationData> = serde_json::from_value(json!([
+        {"x": 98.0, "y": 1.0, "date": "2023-08-27T13:00:00Z", "driver_number": 1},
+        {"x": 204.0, "y": -3.0, "date": "2023-08-27T13:00:01Z", "driver_number": 1},
+    ]))
+    .unwrap();
+    let layout = [0.0, 100.0, 200.0].map(|x| LedPoint::new(x, 0.0));
+
+    let (run_race_data, stats) = generate_run_race_data(&samples, &layout, 10.0).unwrap();
+    let snapped: Vec<(usize, TelemetryPoint)> = run_race_data
+        .iter()
+        .map(|run| (run.led_index, run.point))
+        .collect();
+    assert_eq!(
+        snapped,
+        [
+            (1, TelemetryPoint::new(98.0, 1.0)),
+            (2, TelemetryPoint::new(204.0, -3.0)),
+        ]
+    );
+    assert_eq!(stats.dropped_samples, 0);
+}
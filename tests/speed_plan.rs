@@ -0,0 +1,56 @@
+use f1_led_circuit_master_simulation::speed_plan::{SlowEvent, SpeedPlan, SpeedSegment};
+
+#[test]
+fn plans_segments_first_then_slows_around_events() {
+    let mut plan = SpeedPlan::default();
+    plan.set_segments(vec![SpeedSegment {
+        start: 100.0,
+        end: 200.0,
+        speed: 20.0,
+    }]);
+    plan.set_events(SlowEvent::Overtake, [150.0, 300.0]);
+
+    assert_eq!(plan.planned_speed(50.0, 10.0), 10.0);
+    assert_eq!(plan.planned_speed(150.0, 10.0), 20.0);
+    // Events only count in auto mode
+    assert_eq!(plan.planned_speed(300.0, 10.0), 10.0);
+    plan.set_auto(true);
+    assert_eq!(plan.planned_speed(290.0, 10.0), 1.0);
+    assert_eq!(plan.planned_speed(320.0, 10.0), 10.0);
+    assert_eq!(plan.planned_speed(150.0, 10.0), 20.0);
+    // Already slower than the slowdown
+    assert_eq!(plan.planned_speed(300.0, 0.5), 0.5);
+
+    plan.set_events(SlowEvent::Overtake, []);
+    assert_eq!(plan.event_count(SlowEvent::Overtake), 0);
+    assert_eq!(plan.planned_speed(300.0, 10.0), 10.0);
+}
+
+#[test]
+fn eases_down_before_a_slow_segment() {
+    let mut plan = SpeedPlan::default();
+    plan.set_segments(vec![SpeedSegment {
+        start: 100.0,
+        end: 200.0,
+        speed: 1.0,
+    }]);
+
+    let dt = 0.1;
+    let mut race_time = 0.0;
+    let mut previous = plan.advance(race_time, 10.0, dt);
+    assert_eq!(previous, 10.0);
+    while race_time < 100.0 {
+        race_time += previous * dt;
+        let speed = plan.advance(race_time, 10.0, dt);
+        // At most a tenfold change a second, here a tenth of that
+        assert!(speed / previous <= 10f64.powf(0.1) + 1e-9);
+        assert!(previous / speed <= 10f64.powf(0.1) + 1e-9);
+        previous = speed;
+    }
+    // Slowed down by the time the segment starts
+    assert!(previous < 1.5, "entered the segment at {}x", previous);
+
+    // Jumping past the segment starts at full speed right away
+    plan.reset();
+    assert_eq!(plan.advance(250.0, 10.0, dt), 10.0);
+}
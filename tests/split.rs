@@ -0,0 +1,68 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use f1_led_circuit_master_simulation::mapping::RunRace;
+use f1_led_circuit_master_simulation::simulation::{PlaybackState, Simulation};
+use f1_led_circuit_master_simulation::space::TelemetryPoint;
+use f1_led_circuit_master_simulation::split::gang;
+use std::collections::HashMap;
+use std::time::Duration;
+
+const LED_COUNT: usize = 10;
+
+// One driver going round for `secs` seconds, a record a second, from `start`
+fn session(start: &str, secs: i64) -> Simulation {
+    let start: DateTime<Utc> = start.parse().unwrap();
+    let run_race_data = (0..=secs)
+        .map(|sec| RunRace {
+            date: start + ChronoDuration::seconds(sec),
+            driver_number: 1,
+            led_index: sec as usize % LED_COUNT,
+            point: TelemetryPoint::new(0.0, 0.0),
+        })
+        .collect();
+    Simulation::new(run_race_data, LED_COUNT, HashMap::new())
+}
+
+#[test]
+fn follows_start_pause_and_speed() {
+    let mut leader = session("2024-08-25T13:00:00Z", 60);
+    let mut follower = session("2023-08-27T13:00:00Z", 20);
+
+    gang(&leader, &mut follower);
+    assert_eq!(follower.state(), PlaybackState::Stopped);
+
+    leader.start();
+    leader.set_speed(2.0);
+    gang(&leader, &mut follower);
+    assert_eq!(follower.state(), PlaybackState::Playing);
+    assert_eq!(follower.speed(), 2.0);
+
+    leader.advance(Duration::from_secs(1));
+    follower.advance(Duration::from_secs(1));
+    leader.set_paused(true);
+    gang(&leader, &mut follower);
+    assert_eq!(follower.state(), PlaybackState::Paused);
+    // Ganged clocks run at the same speed, each through its own session
+    assert_eq!(follower.race_time(), leader.race_time());
+
+    leader.set_paused(false);
+    gang(&leader, &mut follower);
+    assert_eq!(follower.state(), PlaybackState::Playing);
+
+    leader.reset();
+    gang(&leader, &mut follower);
+    assert_eq!(follower.state(), PlaybackState::Stopped);
+}
+
+#[test]
+fn lets_a_shorter_session_end_first() {
+    let mut leader = session("2024-08-25T13:00:00Z", 60);
+    let mut follower = session("2023-08-27T13:00:00Z", 20);
+    leader.start();
+    gang(&leader, &mut follower);
+
+    leader.seek(Duration::from_secs(30));
+    follower.seek(Duration::from_secs(30));
+    gang(&leader, &mut follower);
+    assert_eq!(follower.state(), PlaybackState::Finished);
+    assert_eq!(leader.state(), PlaybackState::Playing);
+}
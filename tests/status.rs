@@ -0,0 +1,85 @@
+use f1_led_circuit_master_simulation::cache::{load_progress, store_progress};
+use f1_led_circuit_master_simulation::error::AppError;
+use f1_led_circuit_master_simulation::laps::RaceProgress;
+use f1_led_circuit_master_simulation::simulation::PlaybackState;
+use f1_led_circuit_master_simulation::sink::{FrameDispatcher, LedSink, OutputFrame, SinkOptions};
+use f1_led_circuit_master_simulation::status::{self, Health};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+// Fails on its first frame
+struct FailingSink;
+
+impl LedSink for FailingSink {
+    fn name(&self) -> &str {
+        "flaky"
+    }
+
+    fn submit(&mut self, _frame: &OutputFrame) -> Result<(), AppError> {
+        Err(AppError::Output {
+            reason: "cable pulled".to_string(),
+        })
+    }
+}
+
+fn wait_for_health(name: &str, health: Health) -> status::Link {
+    let deadline = Instant::now() + TIMEOUT;
+    loop {
+        if let Some(link) = status::snapshot().sinks.get(name) {
+            if link.health == health {
+                return link.clone();
+            }
+        }
+        assert!(
+            Instant::now() < deadline,
+            "{} never became {:?}",
+            name,
+            health
+        );
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+// One test, as the status is shared by the whole process
+#[test]
+fn reports_outputs_and_cache_activity() {
+    let mut dispatcher = FrameDispatcher::new();
+    dispatcher
+        .register(Box::new(FailingSink), SinkOptions::default())
+        .unwrap();
+    wait_for_health("flaky", Health::Active);
+
+    dispatcher.dispatch(OutputFrame {
+        leds: vec![[255, 0, 0]],
+        brightness: 1.0,
+        timestamp: Duration::ZERO,
+        state: PlaybackState::Playing,
+        speed: 1.0,
+        drivers: Vec::new(),
+    });
+    let link = wait_for_health("flaky", Health::Failed);
+    assert!(link.detail.unwrap().contains("cable pulled"));
+
+    // A stopped sink can still be found, to replace it
+    let id = dispatcher.find("flaky").unwrap();
+    assert!(dispatcher.find("other").is_none());
+    dispatcher.remove(id);
+    assert!(!status::snapshot().sinks.contains_key("flaky"));
+
+    let dir = std::env::temp_dir().join(format!("status-test-{}", std::process::id()));
+    let progress = RaceProgress::new(Vec::new(), Vec::new());
+    store_progress(&dir, 3, &progress).unwrap();
+    let cache = status::snapshot().cache.unwrap();
+    assert_eq!(cache.health, Health::Active);
+    assert!(cache.detail.unwrap().starts_with("wrote"));
+    assert!(load_progress(&dir, 3).is_some());
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(status::snapshot()
+        .cache
+        .unwrap()
+        .detail
+        .unwrap()
+        .starts_with("loaded"));
+}
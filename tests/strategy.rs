@@ -0,0 +1,82 @@
+mod common;
+
+use common::at;
+use f1_led_circuit_master_simulation::data::{LapData, StintData};
+use f1_led_circuit_master_simulation::laps::RaceProgress;
+use f1_led_circuit_master_simulation::strategy::{StintBar, StrategyChart};
+
+fn lap(driver_number: u32, lap_number: u32) -> LapData {
+    LapData {
+        lap_duration: Some(100.0),
+        is_pit_out_lap: driver_number == 1 && lap_number == 3,
+        ..common::lap(driver_number, lap_number, (lap_number as i64 - 1) * 100)
+    }
+}
+
+fn stint(stint_number: u32, lap_start: u32, lap_end: Option<u32>, compound: &str) -> StintData {
+    StintData {
+        driver_number: 1,
+        stint_number,
+        lap_start: Some(lap_start),
+        lap_end,
+        compound: Some(compound.to_string()),
+    }
+}
+
+// Driver 1 pits after two laps on softs and is still out on hards; driver 2 has no stints
+fn chart() -> StrategyChart {
+    let laps = (1..=4)
+        .map(|lap_number| lap(1, lap_number))
+        .chain((1..=2).map(|lap_number| lap(2, lap_number)))
+        .collect();
+    let progress = RaceProgress::new(laps, Vec::new()).with_stints(vec![
+        stint(2, 3, None, "HARD"),
+        stint(1, 1, Some(2), "SOFT"),
+    ]);
+    StrategyChart::new(&progress)
+}
+
+fn bar(start: i64, end: i64, compound: &str) -> StintBar {
+    StintBar {
+        start: at(start),
+        end: at(end),
+        compound: Some(compound.to_string()),
+    }
+}
+
+#[test]
+fn charts_each_drivers_stints_and_pit_stops() {
+    let chart = chart();
+    assert_eq!(chart.span(), Some((at(0), at(400))));
+
+    let rows = chart.rows();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].driver_number, 1);
+    // The open stint runs to the end of the last known lap
+    assert_eq!(rows[0].stints, [bar(0, 200, "SOFT"), bar(200, 400, "HARD")]);
+    assert_eq!(rows[0].pit_stops, [at(200)]);
+    assert!(rows[1].stints.is_empty());
+}
+
+#[test]
+fn hides_what_the_clock_has_not_reached() {
+    let chart = chart();
+    let row = chart.rows()[0].until(at(150));
+
+    assert_eq!(row.stints, [bar(0, 150, "SOFT")]);
+    assert!(row.pit_stops.is_empty());
+    assert_eq!(chart.rows()[0].until(at(250)).pit_stops, [at(200)]);
+}
+
+#[test]
+fn maps_between_dates_and_the_chart_width() {
+    let chart = chart();
+
+    assert_eq!(chart.share(at(100)), Some(0.25));
+    assert_eq!(chart.share(at(-50)), Some(0.0));
+    assert_eq!(chart.date_at(0.5), Some(at(200)));
+
+    let empty = StrategyChart::new(&RaceProgress::default());
+    assert_eq!(empty.span(), None);
+    assert_eq!(empty.date_at(0.5), None);
+}
@@ -0,0 +1,162 @@
+mod common;
+
+use common::at;
+use f1_led_circuit_master_simulation::data::{
+    IntervalData, LapData, PositionData, RaceControlData,
+};
+use f1_led_circuit_master_simulation::driver_info::{get_driver_info_for_year, DEFAULT_SEASON};
+use f1_led_circuit_master_simulation::flags::{FlagPanelConfig, FlagTimeline};
+use f1_led_circuit_master_simulation::laps::RaceProgress;
+use f1_led_circuit_master_simulation::summary::{leader_laps, race_summary, SummaryLog};
+
+// Laps of 100 seconds; driver 11 leaves the pits on lap 3
+fn lap(driver_number: u32, lap_number: u32) -> LapData {
+    LapData {
+        lap_duration: Some(100.0),
+        is_pit_out_lap: driver_number == 11 && lap_number == 3,
+        ..common::lap(driver_number, lap_number, (lap_number as i64 - 1) * 100)
+    }
+}
+
+fn position(secs: i64, driver_number: u32, position: u32) -> PositionData {
+    PositionData {
+        date: at(secs),
+        driver_number,
+        position,
+    }
+}
+
+fn interval(secs: i64, driver_number: u32, gap_to_leader: Option<f64>) -> IntervalData {
+    IntervalData {
+        date: at(secs),
+        driver_number,
+        gap_to_leader,
+    }
+}
+
+fn yellow(secs: i64, sector: u32) -> RaceControlData {
+    RaceControlData {
+        date: at(secs),
+        category: "Flag".to_string(),
+        flag: Some("YELLOW".to_string()),
+        scope: Some("Sector".to_string()),
+        sector: Some(sector),
+        driver_number: None,
+        message: String::new(),
+    }
+}
+
+fn race() -> RaceProgress {
+    let laps = [1, 11, 44]
+        .into_iter()
+        .flat_map(|driver_number| (1..=4).map(move |lap_number| lap(driver_number, lap_number)))
+        .collect();
+    let positions = vec![
+        position(0, 1, 2),
+        position(0, 11, 1),
+        position(0, 44, 3),
+        position(150, 1, 1),
+        position(150, 11, 2),
+    ];
+    RaceProgress::new(laps, positions)
+}
+
+#[test]
+fn summarizes_the_order_pit_stops_and_flags() {
+    let progress = race()
+        .with_intervals(vec![
+            interval(250, 1, Some(0.0)),
+            interval(250, 11, Some(21.4)),
+        ])
+        .with_race_control(vec![yellow(240, 4)]);
+    let flags = FlagTimeline::new(progress.race_control(), &FlagPanelConfig::default());
+
+    let summary = race_summary(
+        "zandvoort-2023",
+        300.0,
+        Some(at(300)),
+        Some(&progress),
+        &get_driver_info_for_year(DEFAULT_SEASON),
+        &flags,
+    );
+
+    // Driver 44 is a lap down, so has no gap
+    assert_eq!(
+        summary,
+        "Session zandvoort-2023\n\
+         Race time 00:05:00.00\n\
+         \n \
+         1  VER  #1    Leader\n \
+         2  PER  #11   +21.400\n \
+         3  HAM  #44\n\
+         \n\
+         Pit stops: PER 1\n\
+         \n\
+         Flags: sector 4 yellow"
+    );
+}
+
+#[test]
+fn leaves_out_what_isnt_loaded() {
+    let drivers = get_driver_info_for_year(DEFAULT_SEASON);
+    let flags = FlagTimeline::default();
+
+    assert_eq!(
+        race_summary("zandvoort-2023", 12.5, Some(at(12)), None, &drivers, &flags),
+        "Session zandvoort-2023\nRace time 00:00:12.50"
+    );
+
+    // Positions without intervals, laps or race control: the order without gaps
+    let positions = RaceProgress::new(Vec::new(), vec![position(0, 44, 1), position(0, 1, 2)]);
+    assert_eq!(
+        race_summary(
+            "zandvoort-2023",
+            60.0,
+            Some(at(60)),
+            Some(&positions),
+            &drivers,
+            &flags
+        ),
+        "Session zandvoort-2023\nRace time 00:01:00.00\n\n 1  HAM  #44\n 2  VER  #1"
+    );
+
+    // Laps without positions: no one has stopped yet
+    let laps = RaceProgress::new(vec![lap(11, 1), lap(11, 2)], Vec::new());
+    assert_eq!(
+        race_summary(
+            "zandvoort-2023",
+            60.0,
+            Some(at(60)),
+            Some(&laps),
+            &drivers,
+            &flags
+        ),
+        "Session zandvoort-2023\nRace time 00:01:00.00\n\nPit stops: none"
+    );
+}
+
+#[test]
+fn logs_once_per_lap_of_the_leader() {
+    let progress = race();
+    let path = std::env::temp_dir().join(format!("{}-race_summary.txt", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut log = SummaryLog::new(path.clone());
+
+    // The first check only notes the laps, as does seeking backwards
+    assert!(!log.lap_completed(leader_laps(&progress, at(50))));
+    assert!(!log.lap_completed(leader_laps(&progress, at(90))));
+    assert!(log.lap_completed(leader_laps(&progress, at(110))));
+    assert!(!log.lap_completed(leader_laps(&progress, at(150))));
+    assert!(!log.lap_completed(leader_laps(&progress, at(20))));
+    assert!(log.lap_completed(leader_laps(&progress, at(210))));
+    log.reset();
+    assert!(!log.lap_completed(leader_laps(&progress, at(310))));
+
+    log.append("Lap 1").unwrap();
+    log.append("Lap 2").unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "Lap 1\n\nLap 2\n\n"
+    );
+    std::fs::remove_file(&path).unwrap();
+}
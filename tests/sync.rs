@@ -0,0 +1,98 @@
+mod common;
+
+use common::record;
+use f1_led_circuit_master_simulation::mapping::RunRace;
+use f1_led_circuit_master_simulation::simulation::{PlaybackState, Simulation};
+use f1_led_circuit_master_simulation::sync::{data_hash, follow, SyncMessage};
+use std::collections::HashMap;
+use std::time::Duration;
+
+const SEEK_SECS: f64 = 2.0;
+
+// One driver moving an LED along every second for a minute
+fn race_data() -> Vec<RunRace> {
+    (0..60)
+        .map(|second| record(second * 1000, 1, second as usize % 10))
+        .collect()
+}
+
+fn leader(race_time: f64, speed: f64, state: PlaybackState) -> SyncMessage {
+    SyncMessage {
+        session: "9161".to_string(),
+        data_hash: data_hash(&race_data()),
+        race_time,
+        speed,
+        state,
+    }
+}
+
+#[test]
+fn jumps_to_a_distant_leader_and_slews_towards_a_close_one() {
+    let mut follower = Simulation::new(race_data(), 10, HashMap::new());
+
+    // Far off: starts and jumps to where the leader is by now
+    let message = leader(10.0, 2.0, PlaybackState::Playing);
+    follow(
+        &mut follower,
+        &message,
+        Duration::from_millis(500),
+        SEEK_SECS,
+    );
+    assert_eq!(follower.state(), PlaybackState::Playing);
+    assert_eq!(follower.race_time(), 11.0);
+    assert_eq!(follower.speed(), 2.0);
+
+    // Half a second behind: a little faster until caught up, but no jump
+    follow(
+        &mut follower,
+        &leader(11.5, 2.0, PlaybackState::Playing),
+        Duration::ZERO,
+        SEEK_SECS,
+    );
+    assert_eq!(follower.race_time(), 11.0);
+    assert!(follower.speed() > 2.0 && follower.speed() <= 2.2);
+
+    // Ahead: a little slower
+    follow(
+        &mut follower,
+        &leader(10.8, 2.0, PlaybackState::Playing),
+        Duration::ZERO,
+        SEEK_SECS,
+    );
+    assert!(follower.speed() < 2.0 && follower.speed() >= 1.8);
+}
+
+#[test]
+fn pauses_and_stops_with_the_leader() {
+    let mut follower = Simulation::new(race_data(), 10, HashMap::new());
+    follower.start();
+    follower.tick(Duration::from_secs(5));
+
+    // Paused, even a small drift jumps as there's no playing to even it out with
+    follow(
+        &mut follower,
+        &leader(5.3, 1.0, PlaybackState::Paused),
+        Duration::from_secs(1),
+        SEEK_SECS,
+    );
+    assert_eq!(follower.state(), PlaybackState::Paused);
+    assert_eq!(follower.race_time(), 5.3);
+
+    follow(
+        &mut follower,
+        &leader(0.0, 1.0, PlaybackState::Stopped),
+        Duration::ZERO,
+        SEEK_SECS,
+    );
+    assert_eq!(follower.state(), PlaybackState::Stopped);
+}
+
+#[test]
+fn hashes_tell_different_data_apart() {
+    let data = race_data();
+    assert_eq!(data_hash(&data), data_hash(&race_data()));
+    assert_ne!(data_hash(&data), data_hash(&data[1..]));
+    let mut moved = race_data();
+    moved[3].led_index += 1;
+    assert_ne!(data_hash(&data), data_hash(&moved));
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use f1_led_circuit_master_simulation::simulation::{Rgb, Simulation};
+use f1_led_circuit_master_simulation::team_view::{team_leds, LedView};
+use std::collections::HashMap;
+use std::time::Duration;
+
+const ORANGE: Rgb = [250, 100, 0];
+const BLUE: Rgb = [0, 0, 250];
+
+// McLaren's 4 and 81, Williams' 23 and 2
+fn teams() -> HashMap<u32, String> {
+    HashMap::from([
+        (4, "McLaren".to_string()),
+        (81, "McLaren".to_string()),
+        (23, "Williams".to_string()),
+        (2, "Williams".to_string()),
+    ])
+}
+
+fn colors() -> HashMap<u32, Rgb> {
+    HashMap::from([(4, ORANGE), (81, ORANGE), (23, BLUE), (2, BLUE)])
+}
+
+#[test]
+fn brightens_leds_with_both_of_a_teams_cars_nearby() {
+    // The McLarens nose to tail over the line, the Williams apart
+    let cars = [(4, 9), (81, 0), (23, 3), (2, 6)];
+    let leds = team_leds(&cars, &teams(), &colors(), 10);
+    assert_eq!(leds[9], Some(ORANGE));
+    assert_eq!(leds[0], Some(ORANGE));
+    assert_eq!(leds[3], Some([0, 0, 100]));
+    assert_eq!(leds[6], Some([0, 0, 100]));
+    assert_eq!(leds.iter().flatten().count(), 4);
+}
+
+#[test]
+fn splits_an_led_two_teams_share() {
+    let cars = [(4, 2), (81, 3), (23, 2)];
+    let leds = team_leds(&cars, &teams(), &colors(), 5);
+    // Full orange, a McLaren being next to it, mixed with a lone Williams
+    assert_eq!(leds[2], Some([125, 50, 50]));
+    assert_eq!(leds[3], Some(ORANGE));
+
+    // Without a team, a driver makes up one of their own
+    let leds = team_leds(&[(7, 0), (8, 1)], &teams(), &HashMap::new(), 5);
+    assert_eq!(leds[0], Some([102, 102, 102]));
+}
+
+#[test]
+fn switches_between_driver_and_team_view() {
+    let record =
+        |driver_number, led_index| common::record(driver_number as i64, driver_number, led_index);
+    let run_race_data = vec![record(4, 1), record(23, 4), record(81, 2)];
+    let mut simulation = Simulation::new(run_race_data, 6, colors());
+    simulation.set_driver_teams(teams());
+    simulation.start();
+    simulation.tick(Duration::from_millis(100));
+    assert_eq!(simulation.frame().leds[4], Some(BLUE));
+
+    simulation.set_led_view(LedView::Team);
+    assert_eq!(simulation.led_view(), LedView::Team);
+    let leds = &simulation.frame().leds;
+    assert_eq!(
+        leds[1..5],
+        [Some(ORANGE), Some(ORANGE), None, Some([0, 0, 100])]
+    );
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use common::{at_millis, record, start};
+use f1_led_circuit_master_simulation::data::CarData;
+use f1_led_circuit_master_simulation::mapping::RunRace;
+use f1_led_circuit_master_simulation::space::TelemetryPoint;
+use f1_led_circuit_master_simulation::telemetry_chart::{visible_points, TelemetrySeries};
+use f1_led_circuit_master_simulation::timeline::{DriverTimelines, SpeedConfig, SpeedUnit};
+
+// Driver 1 covers 125 units, 12.5 m, every quarter second: 180 km/h
+fn records() -> Vec<RunRace> {
+    (0..8)
+        .map(|index| RunRace {
+            point: TelemetryPoint::new(index as f64 * 125.0, 0.0),
+            ..record(index * 250, 1, 0)
+        })
+        .collect()
+}
+
+fn car_data(millis: i64, speed: Option<f64>, gear: Option<u8>) -> CarData {
+    CarData {
+        date: at_millis(millis),
+        driver_number: 1,
+        speed,
+        n_gear: gear,
+        throttle: Some(100.0),
+        brake: Some(0.0),
+    }
+}
+
+#[test]
+fn estimates_speed_from_the_second_record_on() {
+    let timelines = DriverTimelines::new(&records());
+    let series = TelemetrySeries::new(&timelines, 1, start(), &SpeedConfig::default());
+
+    assert_eq!(series.estimated_speed.len(), 7);
+    assert_eq!(series.estimated_speed[0][0], 0.25);
+    for point in &series.estimated_speed {
+        assert!((point[1] - 180.0).abs() < 1e-6, "{:?}", point);
+    }
+    assert!(!series.has_car_data());
+    assert!(
+        TelemetrySeries::new(&timelines, 44, start(), &SpeedConfig::default())
+            .estimated_speed
+            .is_empty()
+    );
+}
+
+#[test]
+fn adds_car_data_in_the_speed_unit() {
+    let config = SpeedConfig {
+        unit: SpeedUnit::MetersPerSecond,
+        ..SpeedConfig::default()
+    };
+    let mut series = TelemetrySeries::default();
+    series.add_car_data(
+        &[
+            car_data(500, Some(180.0), Some(6)),
+            car_data(1500, None, Some(7)),
+        ],
+        start(),
+        &config,
+    );
+
+    assert_eq!(series.speed, [[0.5, 50.0]]);
+    assert_eq!(series.gear, [[0.5, 6.0], [1.5, 7.0]]);
+    assert_eq!(series.throttle, [[0.5, 100.0], [1.5, 100.0]]);
+    assert!(series.has_car_data());
+    assert_eq!(series.top_speed(), Some(50.0));
+}
+
+#[test]
+fn shows_the_window_and_a_point_either_side() {
+    let series: Vec<[f64; 2]> = (0..10).map(|second| [second as f64, 0.0]).collect();
+
+    let shown = visible_points(&series, 3.5, 6.0, 100);
+
+    assert_eq!(shown.first(), Some(&[3.0, 0.0]));
+    assert_eq!(shown.last(), Some(&[7.0, 0.0]));
+    assert_eq!(shown.len(), 5);
+    assert!(visible_points(&series, 20.0, 30.0, 100).len() <= 1);
+}
+
+#[test]
+fn thins_long_stretches_keeping_the_peaks() {
+    // A flat line with one spike down and one up
+    let mut series: Vec<[f64; 2]> = (0..100_000)
+        .map(|index| [index as f64 / 100.0, 200.0])
+        .collect();
+    series[12_345][1] = 80.0;
+    series[67_890][1] = 330.0;
+
+    let shown = visible_points(&series, 0.0, 1000.0, 2000);
+
+    assert!(shown.len() <= 2000, "{} points", shown.len());
+    assert!(shown.contains(&series[12_345]));
+    assert!(shown.contains(&series[67_890]));
+    assert!(shown.windows(2).all(|pair| pair[0][0] <= pair[1][0]));
+}
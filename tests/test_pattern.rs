@@ -0,0 +1,62 @@
+use f1_led_circuit_master_simulation::test_pattern::{
+    PatternPlayer, TestPattern, TestPatternConfig,
+};
+use std::time::Duration;
+
+const STEP: Duration = Duration::from_millis(500);
+
+fn player(pattern: TestPattern, led_count: usize) -> PatternPlayer {
+    PatternPlayer::new(pattern, led_count, &TestPatternConfig::default())
+}
+
+fn lit(player: &PatternPlayer) -> Vec<usize> {
+    let frame = player.frame();
+    (0..frame.len())
+        .filter(|&index| frame[index] != [0, 0, 0])
+        .collect()
+}
+
+#[test]
+fn chases_through_the_leds_in_order() {
+    let mut chase = player(TestPattern::Chase, 3);
+    for index in [0, 1, 2, 0] {
+        assert_eq!(chase.active_led(), Some(index));
+        assert_eq!(lit(&chase), vec![index]);
+        chase.tick(STEP);
+    }
+
+    let red = player(TestPattern::Red, 3);
+    assert_eq!(red.frame(), vec![[255, 0, 0]; 3]);
+    assert_eq!(red.active_led(), None);
+}
+
+#[test]
+fn blinks_each_led_as_often_as_its_number() {
+    let mut blink = player(TestPattern::IndexBlink, 3);
+    let mut blinks = [0; 3];
+    let mut was_lit = false;
+    // One round of 1, 2 and 3 blinks, each followed by a pause
+    for _ in 0..(2 + 4 + 6 + 3 * 3) {
+        let lit = lit(&blink);
+        assert!(lit.len() <= 1);
+        if let Some(&index) = lit.first() {
+            assert_eq!(blink.active_led(), Some(index));
+            if !was_lit {
+                blinks[index] += 1;
+            }
+        }
+        was_lit = !lit.is_empty();
+        blink.tick(STEP);
+    }
+    assert_eq!(blinks, [1, 2, 3]);
+    assert_eq!(blink.active_led(), Some(0));
+}
+
+#[test]
+fn parses_pattern_names() {
+    assert_eq!(
+        "index_blink".parse::<TestPattern>(),
+        Ok(TestPattern::IndexBlink)
+    );
+    assert!("plaid".parse::<TestPattern>().is_err());
+}
@@ -0,0 +1,179 @@
+mod common;
+
+use common::{at, at_millis, record, start};
+use f1_led_circuit_master_simulation::mapping::RunRace;
+use f1_led_circuit_master_simulation::space::TelemetryPoint;
+use f1_led_circuit_master_simulation::timeline::{
+    estimated_laps, time_deltas, DriverDistances, DriverTimelines, LedCrossings, SpeedConfig,
+    SpeedUnit,
+};
+
+// A car going straight along x at 50 m/s (180 km/h), sampled every 250ms in decimeters
+fn constant_speed_trace(samples: i64) -> Vec<RunRace> {
+    (0..samples)
+        .map(|index| RunRace {
+            point: TelemetryPoint::new(index as f64 * 125.0, 0.0),
+            ..record(index * 250, 1, 0)
+        })
+        .collect()
+}
+
+fn assert_close(actual: Option<f64>, expected: f64) {
+    let actual = actual.expect("no speed estimate");
+    assert!(
+        (actual - expected).abs() < 1e-6,
+        "expected {}, got {}",
+        expected,
+        actual
+    );
+}
+
+#[test]
+fn estimates_a_constant_speed() {
+    let timelines = DriverTimelines::new(&constant_speed_trace(20));
+    let time = at(3);
+
+    assert_close(timelines.speed_at(1, time, &SpeedConfig::default()), 180.0);
+    let config = SpeedConfig {
+        unit: SpeedUnit::MetersPerSecond,
+        ..SpeedConfig::default()
+    };
+    assert_close(timelines.speed_at(1, time, &config), 50.0);
+}
+
+#[test]
+fn applies_the_coordinate_scale() {
+    let timelines = DriverTimelines::new(&constant_speed_trace(20));
+    let config = SpeedConfig {
+        meters_per_unit: 0.2,
+        ..SpeedConfig::default()
+    };
+
+    assert_close(timelines.speed_at(1, at(3), &config), 360.0);
+}
+
+#[test]
+fn leaves_out_glitches() {
+    let mut trace = constant_speed_trace(20);
+    // One sample jumps 1km sideways and back
+    trace[10].point.y = 10_000.0;
+    let timelines = DriverTimelines::new(&trace);
+
+    assert_close(
+        timelines.speed_at(1, at_millis(2750), &SpeedConfig::default()),
+        180.0,
+    );
+}
+
+#[test]
+fn has_no_estimate_without_recent_samples() {
+    let timelines = DriverTimelines::new(&constant_speed_trace(20));
+    let config = SpeedConfig::default();
+
+    assert_eq!(timelines.speed_at(1, start(), &config), None);
+    assert_eq!(timelines.speed_at(1, at(20), &config), None);
+    assert_eq!(timelines.speed_at(44, at(3), &config), None);
+}
+
+#[test]
+fn has_no_position_before_the_first_sample() {
+    let timelines = DriverTimelines::new(&[record(10_000, 1, 3), record(20_000, 44, 7)]);
+
+    assert!(timelines.position_at(1, start()).is_none());
+    assert!(timelines.positions_at(start()).is_empty());
+    assert!(timelines.samples_between(1, start(), at(10)).is_empty());
+    // Driver 44 only shows up once their first sample is due
+    let positions = timelines.positions_at(at(15));
+    assert_eq!(positions.len(), 1);
+    assert_eq!(positions[&1], 3);
+    assert!(timelines.timeline(63).is_empty());
+}
+
+#[test]
+fn holds_the_last_position_across_gaps() {
+    // A minute without samples, e.g. a dropout, then the car shows up elsewhere
+    let timelines = DriverTimelines::new(&[
+        record(0, 1, 3),
+        record(60_000, 1, 40),
+        record(61_000, 1, 41),
+    ]);
+
+    assert_eq!(timelines.position_at(1, at(30)).unwrap().led_index, 3);
+    assert_eq!(timelines.position_at(1, at(60)).unwrap().led_index, 40);
+    assert_eq!(timelines.position_at(1, at(600)).unwrap().led_index, 41);
+    assert!(timelines.samples_between(1, at(1), at(60)).is_empty());
+    assert_eq!(timelines.samples_between(1, at(0), at(61)).len(), 2);
+    // An empty or reversed range has no samples
+    assert!(timelines.samples_between(1, at(61), at(0)).is_empty());
+}
+
+#[test]
+fn sums_the_distance_up_to_the_clock_without_glitches() {
+    let mut trace = constant_speed_trace(20);
+    trace[10].point.y = 10_000.0;
+    let config = SpeedConfig::default();
+    let distances = DriverDistances::new(&DriverTimelines::new(&trace), &config);
+
+    assert_eq!(distances.at(1, at(-1)), None);
+    assert_close(distances.at(1, start()), 0.0);
+    // Four gaps of 12.5m, whether the clock got there forwards or backwards
+    assert_close(distances.at(1, at_millis(1100)), 50.0);
+    // The two gaps to and from the glitch add nothing
+    assert_close(distances.total(1), 17.0 * 12.5);
+    assert_eq!(distances.at(44, start()), None);
+
+    // A layout 1000 units round is 100m
+    assert_close(estimated_laps(212.5, 1000.0, &config), 2.125);
+    assert_eq!(estimated_laps(212.5, 0.0, &config), None);
+}
+
+// A driver on a 10 LED track reaching each LED in `leds` a second after the previous one
+fn lap_trace(leds: &[usize]) -> Vec<RunRace> {
+    leds.iter()
+        .enumerate()
+        .map(|(index, &led_index)| record(index as i64 * 1000, 1, led_index))
+        .collect()
+}
+
+#[test]
+fn counts_led_crossings_across_laps() {
+    // Skips LED 3, snaps back to 4 and crosses the line
+    let crossings = LedCrossings::new(&lap_trace(&[1, 2, 4, 5, 4, 8, 1]), 10);
+
+    assert_eq!(crossings.start(), 1);
+    assert_eq!(crossings.at(3), Some(at_millis(1500)));
+    assert_eq!(crossings.at(5), Some(at(3)));
+    // The snap back doesn't count, so 5 to 8 takes from 3s to 5s
+    assert_eq!(crossings.at(8), Some(at(5)));
+    assert_eq!(crossings.at(11), Some(at(6)));
+    assert_eq!(crossings.at(12), None);
+    assert_eq!(crossings.at(0), None);
+}
+
+#[test]
+fn a_lapped_driver_falls_further_behind() {
+    // The leader does an LED a second, the other one an LED every two seconds
+    let leader: Vec<usize> = (0..30).map(|step| step % 10).collect();
+    let lapped: Vec<usize> = (0..30).map(|step| (step / 2) % 10).collect();
+    let deltas = time_deltas(
+        &LedCrossings::new(&lap_trace(&lapped), 10),
+        &LedCrossings::new(&lap_trace(&leader), 10),
+        10,
+    );
+
+    // Positions 0 to 14 at 2s against 1s apart
+    assert_eq!(deltas.len(), 15);
+    assert_eq!(deltas[0], (start(), 0.0));
+    assert_eq!(deltas[12], (at(24), 12.0));
+    assert!(deltas.windows(2).all(|pair| pair[0].1 < pair[1].1));
+}
+
+#[test]
+fn lines_up_drivers_either_side_of_the_finish_line() {
+    // One starts just before the line, the other just after it
+    let behind = LedCrossings::new(&lap_trace(&[9, 0, 1, 2]), 10);
+    let ahead = LedCrossings::new(&lap_trace(&[0, 1, 2, 3]), 10);
+    let deltas = time_deltas(&behind, &ahead, 10);
+
+    assert_eq!(deltas, vec![(at(1), 1.0), (at(2), 1.0), (at(3), 1.0),]);
+}
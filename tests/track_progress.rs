@@ -0,0 +1,56 @@
+use f1_led_circuit_master_simulation::track_progress::{DriverProgress, TrackProgress};
+
+#[test]
+fn reads_the_led_as_a_percentage_of_the_lap() {
+    let mut progress = TrackProgress::new(96);
+    assert_eq!(progress.percent(0), 0.0);
+    assert_eq!(progress.percent(48), 50.0);
+    assert!(progress.driver(1).is_none());
+
+    progress.advance(1, 24);
+    assert_eq!(
+        progress.driver(1),
+        Some(DriverProgress {
+            driver_number: 1,
+            percent: 25.0,
+            laps: 0,
+        })
+    );
+    assert_eq!(TrackProgress::new(0).percent(5), 0.0);
+}
+
+#[test]
+fn counts_a_lap_across_the_start_finish_line() {
+    let mut progress = TrackProgress::new(96);
+    progress.advance(1, 90);
+    progress.advance(1, 95); // U96
+    progress.advance(1, 0); // U1
+    let driver = progress.driver(1).unwrap();
+    assert_eq!((driver.percent, driver.laps), (0.0, 1));
+
+    // Skipping past the line between records still counts
+    progress.advance(1, 40);
+    progress.advance(1, 80);
+    progress.advance(1, 30);
+    assert_eq!(progress.driver(1).unwrap().laps, 2);
+}
+
+#[test]
+fn a_short_jump_back_is_not_a_lap() {
+    let mut progress = TrackProgress::new(96);
+    progress.advance(1, 40);
+    progress.advance(1, 38);
+    assert_eq!(progress.driver(1).unwrap().laps, 0);
+
+    // Backwards over the line takes the lap back
+    progress.advance(1, 95);
+    progress.advance(1, 1);
+    progress.advance(1, 94);
+    assert_eq!(progress.driver(1).unwrap().laps, 0);
+
+    progress.advance(44, 10);
+    let numbers: Vec<u32> = progress.drivers().iter().map(|d| d.driver_number).collect();
+    assert_eq!(numbers, [1, 44]);
+    progress.reset();
+    assert!(progress.drivers().is_empty());
+}
@@ -0,0 +1,130 @@
+mod common;
+
+use common::at;
+use f1_led_circuit_master_simulation::data::{IntervalData, PositionData};
+use f1_led_circuit_master_simulation::laps::RaceProgress;
+use f1_led_circuit_master_simulation::trains::{Train, TrainConfig, TrainDetector};
+
+fn interval(secs: i64, driver_number: u32, gap_to_leader: Option<f64>) -> IntervalData {
+    IntervalData {
+        date: at(secs),
+        driver_number,
+        gap_to_leader,
+    }
+}
+
+// The leader has no gap of their own, only their position
+fn progress(intervals: Vec<IntervalData>) -> RaceProgress {
+    let mut drivers: Vec<u32> = intervals
+        .iter()
+        .map(|interval| interval.driver_number)
+        .collect();
+    drivers.sort();
+    drivers.dedup();
+    let positions = drivers
+        .iter()
+        .enumerate()
+        .map(|(index, &driver_number)| PositionData {
+            date: at(0),
+            driver_number,
+            position: index as u32 + 1,
+        })
+        .collect();
+    RaceProgress::new(Vec::new(), positions).with_intervals(intervals)
+}
+
+#[test]
+fn finds_cars_running_nose_to_tail() {
+    // Driver 55 and 63 are close too, but only two of them
+    let progress = progress(vec![
+        interval(0, 1, None),
+        interval(0, 11, Some(0.5)),
+        interval(0, 16, Some(0.9)),
+        interval(0, 44, Some(1.7)),
+        interval(0, 55, Some(10.0)),
+        interval(0, 63, Some(10.4)),
+        interval(0, 81, None),
+    ]);
+    let mut detector = TrainDetector::new(&TrainConfig::default());
+
+    assert_eq!(
+        detector.update(&progress, at(5)),
+        [Train {
+            drivers: vec![1, 11, 16, 44],
+            first_position: 1,
+            last_position: 4,
+        }]
+    );
+
+    let disabled = TrainConfig {
+        enabled: false,
+        ..TrainConfig::default()
+    };
+    assert!(TrainDetector::new(&disabled)
+        .update(&progress, at(5))
+        .is_empty());
+}
+
+#[test]
+fn keeps_a_car_that_drops_back_a_little() {
+    // Driver 16 drops to 1.1s behind driver 11: too far to join, close enough to stay
+    let progress = progress(vec![
+        interval(0, 1, None),
+        interval(0, 11, Some(0.9)),
+        interval(0, 16, Some(1.8)),
+        interval(10, 16, Some(2.0)),
+    ]);
+    let mut detector = TrainDetector::new(&TrainConfig::default());
+
+    assert_eq!(detector.update(&progress, at(5)).len(), 1);
+    assert_eq!(detector.update(&progress, at(15))[0].drivers, [1, 11, 16]);
+    assert!(TrainDetector::new(&TrainConfig::default())
+        .update(&progress, at(15))
+        .is_empty());
+}
+
+#[test]
+fn closes_up_behind_a_car_pitting_out_of_a_train() {
+    let progress = progress(vec![
+        interval(0, 1, None),
+        interval(0, 11, Some(0.5)),
+        interval(0, 16, Some(0.9)),
+        interval(0, 44, Some(1.7)),
+        interval(10, 11, Some(25.0)),
+    ]);
+    let mut detector = TrainDetector::new(&TrainConfig::default());
+
+    assert_eq!(
+        detector.update(&progress, at(5))[0].drivers,
+        [1, 11, 16, 44]
+    );
+    assert_eq!(
+        detector.update(&progress, at(15)),
+        [Train {
+            drivers: vec![1, 16, 44],
+            first_position: 1,
+            last_position: 3,
+        }]
+    );
+}
+
+#[test]
+fn lights_the_leds_from_the_last_car_to_the_first() {
+    let train = Train {
+        drivers: vec![1, 11, 16],
+        first_position: 1,
+        last_position: 3,
+    };
+    let leds = |front: usize, back: usize| {
+        move |driver_number: u32| match driver_number {
+            1 => Some(front),
+            16 => Some(back),
+            _ => None,
+        }
+    };
+
+    assert_eq!(train.leds(leds(2, 98), 100), [98, 99, 0, 1, 2]);
+    // Half a lap apart is more likely a car about to be lapped than a train
+    assert!(train.leds(leds(60, 2), 100).is_empty());
+    assert!(train.leds(|_| None, 100).is_empty());
+}
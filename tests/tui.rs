@@ -0,0 +1,114 @@
+#![cfg(feature = "native")]
+
+mod common;
+
+use common::record;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use f1_led_circuit_master_simulation::control::PlaybackCommand;
+use f1_led_circuit_master_simulation::mapping::RunRace;
+use f1_led_circuit_master_simulation::simulation::Simulation;
+use f1_led_circuit_master_simulation::space::LedPoint;
+use f1_led_circuit_master_simulation::space::TelemetryPoint;
+use f1_led_circuit_master_simulation::tui::{
+    key_action, layout_cells, terminal_color, KeyAction, TrackView,
+};
+use f1_led_circuit_master_simulation::viewport::Bounds;
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::Color;
+use std::collections::HashMap;
+use std::time::Duration;
+
+// The corners of a 200 by 100 rectangle and its middle
+fn layout() -> Vec<LedPoint> {
+    [
+        (0.0, 0.0),
+        (200.0, 0.0),
+        (200.0, 100.0),
+        (0.0, 100.0),
+        (100.0, 50.0),
+    ]
+    .map(|(x, y)| LedPoint::new(x, y))
+    .to_vec()
+}
+
+#[test]
+fn scales_the_layout_into_the_terminal() {
+    let coordinates = layout();
+    let bounds = Bounds::from_coordinates(&coordinates);
+
+    // Wide enough that the height limits it: 100 units over 20 rows of cells twice as tall as
+    // they're wide, so the 200 units across take 80 columns, centered in the 101
+    let cells = layout_cells(&coordinates, &bounds, Rect::new(0, 0, 101, 21));
+    assert_eq!(cells, [(10, 20), (90, 20), (90, 0), (10, 0), (50, 10)]);
+
+    // Narrow, the width limits it instead; the area's offset carries over
+    let cells = layout_cells(&coordinates, &bounds, Rect::new(5, 2, 41, 21));
+    assert_eq!(cells, [(5, 17), (45, 17), (45, 7), (5, 7), (25, 12)]);
+
+    // A single LED goes in the middle
+    let single = &coordinates[..1];
+    let cells = layout_cells(
+        single,
+        &Bounds::from_coordinates(single),
+        Rect::new(0, 0, 11, 5),
+    );
+    assert_eq!(cells, [(5, 2)]);
+}
+
+#[test]
+fn picks_the_nearest_ansi_color_without_true_color() {
+    assert_eq!(terminal_color([250, 10, 20], false), Color::LightRed);
+    assert_eq!(terminal_color([0, 0, 200], false), Color::Blue);
+    assert_eq!(terminal_color([250, 250, 240], false), Color::White);
+    assert_eq!(terminal_color([1, 2, 3], true), Color::Rgb(1, 2, 3));
+}
+
+#[test]
+fn maps_the_keys_to_playback() {
+    let key = |code| key_action(KeyEvent::new(code, KeyModifiers::NONE));
+    assert_eq!(
+        key(KeyCode::Char(' ')),
+        Some(KeyAction::Playback(PlaybackCommand::TogglePause))
+    );
+    assert_eq!(
+        key(KeyCode::Char('-')),
+        Some(KeyAction::Playback(PlaybackCommand::AdjustSpeed(-1.0)))
+    );
+    assert_eq!(key(KeyCode::Char('q')), Some(KeyAction::Quit));
+    assert_eq!(key(KeyCode::Char('c')), None);
+    assert_eq!(
+        key_action(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+        Some(KeyAction::Quit)
+    );
+}
+
+#[test]
+fn draws_lit_leds_over_the_track_and_a_status_line() {
+    let run_race_data = [0, 10]
+        .map(|secs| RunRace {
+            point: TelemetryPoint::new(100.0, 50.0),
+            ..record(secs * 1000, 1, 4)
+        })
+        .to_vec();
+    let mut simulation = Simulation::new(run_race_data, 5, HashMap::from([(1, [0, 0, 255])]));
+    simulation.start();
+    simulation.tick(Duration::from_millis(100));
+
+    let mut view = TrackView::new(&layout(), true);
+    let mut draw = |area: Rect| {
+        let mut buffer = Buffer::empty(area);
+        view.render(&simulation, area, &mut buffer);
+        buffer
+    };
+    let buffer = draw(Rect::new(0, 0, 101, 22));
+    assert_eq!(buffer.get(10, 0).symbol(), "·");
+    assert_eq!(buffer.get(50, 10).symbol(), "█");
+    assert_eq!(buffer.get(50, 10).fg, Color::Rgb(0, 0, 255));
+    let status: String = (0..20).map(|x| buffer.get(x, 21).symbol()).collect();
+    assert!(status.contains("PLAYING"), "{}", status);
+
+    // Laid out again after a resize
+    let buffer = draw(Rect::new(0, 0, 41, 22));
+    assert_eq!(buffer.get(20, 10).symbol(), "█");
+}
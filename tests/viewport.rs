@@ -0,0 +1,151 @@
+use eframe::egui;
+use f1_led_circuit_master_simulation::led_coords::LayoutTransform;
+use f1_led_circuit_master_simulation::space::LedPoint;
+use f1_led_circuit_master_simulation::viewport::{
+    centroid, split_panes, Bounds, Camera, TrackViewport,
+};
+
+fn led(x: f64, y: f64) -> LedPoint {
+    LedPoint::new(x, y)
+}
+
+// A 20 by 10 rectangle with an extra LED pulling the centroid to the right
+fn layout() -> Vec<LedPoint> {
+    vec![
+        led(0.0, 0.0),
+        led(20.0, 0.0),
+        led(20.0, 10.0),
+        led(0.0, 10.0),
+        led(20.0, 5.0),
+    ]
+}
+
+#[test]
+fn boxes_the_layout() {
+    let bounds = Bounds::from_coordinates(&layout());
+
+    assert_eq!(
+        bounds,
+        Bounds {
+            min_x: 0.0,
+            max_x: 20.0,
+            min_y: 0.0,
+            max_y: 10.0,
+        }
+    );
+    assert_eq!((bounds.width(), bounds.height()), (20.0, 10.0));
+    assert_eq!(bounds.center(), led(10.0, 5.0));
+}
+
+#[test]
+fn maps_the_corners_inside_the_margin() {
+    let viewport = TrackViewport::new(
+        Bounds::from_coordinates(&layout()),
+        egui::vec2(400.0, 300.0),
+        10.0,
+    );
+
+    // Larger y is higher up, so the bottom left LED is drawn at the bottom left
+    assert_eq!(viewport.to_screen(led(0.0, 0.0)), egui::pos2(10.0, 290.0));
+    assert_eq!(viewport.to_screen(led(20.0, 10.0)), egui::pos2(390.0, 10.0));
+    assert_eq!(viewport.to_screen(led(10.0, 5.0)), egui::pos2(200.0, 150.0));
+}
+
+#[test]
+fn rotates_a_quarter_turn_clockwise_around_the_centroid() {
+    assert_eq!(centroid(&layout()), led(12.0, 5.0));
+    let transform = LayoutTransform {
+        rotation: 90.0,
+        ..LayoutTransform::default()
+    };
+
+    let rotated = transform.transform_coordinates(&layout());
+    // The top left corner is 12 left of and 5 above the centroid, so it ends up 5 to the right
+    // of and 12 above it
+    assert!((rotated[3].x - 17.0).abs() < 1e-9);
+    assert!((rotated[3].y - 17.0).abs() < 1e-9);
+    // The centroid stays put
+    let center = centroid(&rotated);
+    assert!(center.distance(led(12.0, 5.0)) < 1e-9);
+}
+
+#[test]
+fn keeps_a_rotated_layout_on_screen() {
+    let transform = LayoutTransform {
+        rotation: 45.0,
+        ..LayoutTransform::default()
+    };
+    let rotated = transform.transform_coordinates(&layout());
+    let size = egui::vec2(400.0, 300.0);
+    let viewport = TrackViewport::new(Bounds::from_coordinates(&rotated), size, 10.0);
+
+    let screen = egui::Rect::from_min_size(egui::Pos2::ZERO, size).shrink(10.0 - 1e-3);
+    for coord in &rotated {
+        assert!(screen.contains(viewport.to_screen(*coord)));
+    }
+}
+
+#[test]
+fn draws_degenerate_layouts_in_the_middle() {
+    let size = egui::vec2(400.0, 300.0);
+    let empty = TrackViewport::new(Bounds::from_coordinates(&[]), size, 10.0);
+    assert_eq!(empty.to_screen(led(0.0, 0.0)), egui::pos2(200.0, 150.0));
+
+    // A single LED spans no width or height
+    let single = [led(7.0, 3.0)];
+    let viewport = TrackViewport::new(Bounds::from_coordinates(&single), size, 10.0);
+    assert_eq!(viewport.to_screen(led(7.0, 3.0)), egui::pos2(200.0, 150.0));
+    // A straight line spans only one axis
+    let line = [led(0.0, 5.0), led(10.0, 5.0)];
+    let viewport = TrackViewport::new(Bounds::from_coordinates(&line), size, 10.0);
+    assert_eq!(viewport.to_screen(led(10.0, 5.0)), egui::pos2(390.0, 150.0));
+}
+
+#[test]
+fn names_the_axes_a_layout_does_not_span() {
+    let size = egui::vec2(400.0, 300.0);
+    let single = Bounds::from_coordinates(&[led(7.0, 3.0)]);
+    assert_eq!(single.flat_axes(), ["x", "y"]);
+
+    let horizontal = Bounds::from_coordinates(&[led(0.0, 5.0), led(4.0, 5.0), led(10.0, 5.0)]);
+    assert_eq!(horizontal.flat_axes(), ["y"]);
+
+    let vertical = Bounds::from_coordinates(&[led(2.0, 0.0), led(2.0, 8.0), led(2.0, 20.0)]);
+    assert_eq!(vertical.flat_axes(), ["x"]);
+    let viewport = TrackViewport::new(vertical, size, 10.0);
+    assert_eq!(viewport.to_screen(led(2.0, 0.0)), egui::pos2(200.0, 290.0));
+    assert_eq!(viewport.to_screen(led(2.0, 20.0)), egui::pos2(200.0, 10.0));
+
+    assert!(Bounds::from_coordinates(&layout()).flat_axes().is_empty());
+}
+
+#[test]
+fn maps_each_pane_of_a_split_board_on_its_own() {
+    let board = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(800.0, 300.0));
+    let panes = split_panes(board, 2);
+    assert_eq!(panes[0].max.x, 400.0);
+    assert_eq!(panes[1].min.x, 400.0);
+
+    let bounds = Bounds::from_coordinates(&layout());
+    let left = TrackViewport::in_rect(bounds, panes[0], 10.0);
+    let right = TrackViewport::in_rect(bounds, panes[1], 10.0);
+    assert_eq!(left.to_screen(led(0.0, 10.0)), egui::pos2(10.0, 10.0));
+    assert_eq!(right.to_screen(led(0.0, 10.0)), egui::pos2(410.0, 10.0));
+    assert_eq!(right.to_screen(led(20.0, 0.0)), egui::pos2(790.0, 290.0));
+}
+
+#[test]
+fn zooms_in_around_the_camera() {
+    let size = egui::vec2(220.0, 120.0);
+    let bounds = Bounds::from_coordinates(&layout());
+    let camera = Camera {
+        center: led(20.0, 10.0),
+        zoom: 2.0,
+    };
+    let viewport = TrackViewport::new(bounds, size, 10.0).with_camera(camera);
+
+    // The camera's center goes in the middle, and twice as far from it as without zooming
+    assert_eq!(viewport.to_screen(led(20.0, 10.0)), egui::pos2(110.0, 60.0));
+    assert_eq!(viewport.to_screen(led(15.0, 10.0)), egui::pos2(10.0, 60.0));
+    assert_eq!(viewport.layout_delta(egui::vec2(100.0, 50.0)), (5.0, -2.5));
+}
@@ -0,0 +1,150 @@
+use f1_led_circuit_master_simulation::error::AppError;
+use f1_led_circuit_master_simulation::simulation::{PlaybackState, Simulation};
+use f1_led_circuit_master_simulation::sink::{
+    FrameDispatcher, LedSink, OutputFrame, SinkOptions, SinkQueue,
+};
+use f1_led_circuit_master_simulation::status;
+use f1_led_circuit_master_simulation::watchdog::{DiagnosticDump, Stall, Watchdog, WatchdogConfig};
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+// Takes a frame only when told to
+struct StuckSink {
+    gate: Receiver<()>,
+}
+
+impl LedSink for StuckSink {
+    fn name(&self) -> &str {
+        "stuck"
+    }
+
+    fn submit(&mut self, _frame: &OutputFrame) -> Result<(), AppError> {
+        let _ = self.gate.recv_timeout(Duration::from_secs(2));
+        Ok(())
+    }
+}
+
+fn frame() -> OutputFrame {
+    OutputFrame {
+        leds: vec![[255, 0, 0]],
+        brightness: 1.0,
+        timestamp: Duration::ZERO,
+        state: PlaybackState::Playing,
+        speed: 1.0,
+        drivers: Vec::new(),
+    }
+}
+
+fn watchdog() -> Watchdog {
+    Watchdog::new(&WatchdogConfig {
+        stall_secs: 5.0,
+        ..WatchdogConfig::default()
+    })
+}
+
+fn queue(waiting_secs: u64, alive: bool) -> SinkQueue {
+    SinkQueue {
+        name: "wled".to_string(),
+        depth: 1,
+        waiting: Duration::from_secs(waiting_secs),
+        alive,
+    }
+}
+
+#[test]
+fn reports_a_clock_stuck_while_playing_once() {
+    let mut watchdog = watchdog();
+    let start = Instant::now();
+    let at = |secs: u64| start + Duration::from_secs(secs);
+
+    assert_eq!(watchdog.check(Some(1.0), &[], at(0)), None);
+    assert_eq!(watchdog.check(Some(1.0), &[], at(4)), None);
+    assert_eq!(
+        watchdog.check(Some(1.0), &[], at(5)),
+        Some(Stall::Clock { clock_time: 1.0 })
+    );
+    assert_eq!(watchdog.check(Some(1.0), &[], at(9)), None);
+
+    // Moving again, or pausing, starts over
+    assert_eq!(watchdog.check(Some(1.5), &[], at(10)), None);
+    assert_eq!(watchdog.check(None, &[], at(20)), None);
+    assert_eq!(watchdog.check(Some(1.5), &[], at(24)), None);
+    assert_eq!(
+        watchdog.check(Some(1.5), &[], at(29)),
+        Some(Stall::Clock { clock_time: 1.5 })
+    );
+}
+
+#[test]
+fn reports_an_output_that_stops_taking_frames() {
+    let mut watchdog = watchdog();
+    let now = Instant::now();
+
+    // One that failed has stopped on purpose
+    assert_eq!(watchdog.check(None, &[queue(60, false)], now), None);
+    assert_eq!(watchdog.check(None, &[queue(4, true)], now), None);
+    assert_eq!(
+        watchdog.check(None, &[queue(5, true)], now),
+        Some(Stall::Sink {
+            name: "wled".to_string()
+        })
+    );
+
+    let disabled = WatchdogConfig {
+        enabled: false,
+        ..WatchdogConfig::default()
+    };
+    let mut watchdog = Watchdog::new(&disabled);
+    assert_eq!(watchdog.check(None, &[queue(60, true)], now), None);
+}
+
+#[test]
+fn tells_how_long_a_frame_waited_for_its_sink() {
+    let mut dispatcher = FrameDispatcher::new();
+    let (go, gate) = mpsc::channel();
+    dispatcher
+        .register(Box::new(StuckSink { gate }), SinkOptions::default())
+        .unwrap();
+
+    // The sink sits in the first frame while the second waits; a third replaces it
+    dispatcher.dispatch(frame());
+    std::thread::sleep(Duration::from_millis(50));
+    dispatcher.dispatch(frame());
+    std::thread::sleep(Duration::from_millis(50));
+    dispatcher.dispatch(frame());
+    let queues = dispatcher.queues();
+    assert_eq!(queues.len(), 1);
+    assert_eq!(queues[0].depth, 1);
+    assert!(queues[0].waiting >= Duration::from_millis(50));
+    assert!(queues[0].alive);
+
+    for _ in 0..3 {
+        go.send(()).unwrap();
+    }
+    dispatcher.shutdown();
+    let queues = dispatcher.queues();
+    assert_eq!((queues[0].depth, queues[0].waiting), (0, Duration::ZERO));
+    assert!(!queues[0].alive);
+}
+
+#[test]
+fn writes_the_dump_as_json() {
+    let simulation = Simulation::new(Vec::new(), 10, HashMap::new());
+    let dispatcher = FrameDispatcher::new();
+    let mut dump = DiagnosticDump::new("test", &simulation, &dispatcher, &status::snapshot());
+    dump.error("ghost", "no laps");
+    dump.thread("reload", true);
+
+    let dir = std::env::temp_dir().join(format!("{}-diagnostics", std::process::id()));
+    let path = dump.write(&dir).unwrap();
+    let written: serde_json::Value =
+        serde_json::from_reader(std::fs::File::open(&path).unwrap()).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(written["reason"], "test");
+    assert_eq!(written["playback"]["state"], "stopped");
+    assert_eq!(written["playback"]["record_count"], 0);
+    assert_eq!(written["errors"]["ghost"], "no laps");
+    assert_eq!(written["threads"]["reload"], true);
+}
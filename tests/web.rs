@@ -0,0 +1,35 @@
+#![cfg(feature = "web")]
+
+use f1_led_circuit_master_simulation::web::{WebSource, DEFAULT_BUNDLE};
+
+#[test]
+fn an_empty_query_plays_the_default_bundle() {
+    assert_eq!(
+        WebSource::from_query(""),
+        WebSource::Bundle(DEFAULT_BUNDLE.to_string())
+    );
+    assert_eq!(
+        WebSource::from_query("?"),
+        WebSource::Bundle(DEFAULT_BUNDLE.to_string())
+    );
+}
+
+#[test]
+fn a_bundle_in_the_query_is_played() {
+    assert_eq!(
+        WebSource::from_query("?bundle=races/monza.f1led"),
+        WebSource::Bundle("races/monza.f1led".to_string())
+    );
+}
+
+#[test]
+fn a_session_in_the_query_comes_from_the_api() {
+    assert_eq!(
+        WebSource::from_query("?bundle=races/monza.f1led&session=9161"),
+        WebSource::Api("9161".to_string())
+    );
+    assert_eq!(
+        WebSource::from_query("?session=&bundle=a.f1led"),
+        WebSource::Bundle("a.f1led".to_string())
+    );
+}
@@ -0,0 +1,93 @@
+#![cfg(feature = "native")]
+
+use f1_led_circuit_master_simulation::simulation::PlaybackState;
+use f1_led_circuit_master_simulation::sink::{LedSink, OutputFrame};
+use f1_led_circuit_master_simulation::space::LedPoint;
+use f1_led_circuit_master_simulation::track_progress::DriverProgress;
+use f1_led_circuit_master_simulation::websocket::{
+    running_order, WebSocketConfig, WebSocketServer,
+};
+use std::net::TcpStream;
+use std::time::Duration;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+fn server() -> WebSocketServer {
+    let config = WebSocketConfig {
+        enabled: true,
+        bind: "127.0.0.1".to_string(),
+        port: 0,
+        ..WebSocketConfig::default()
+    };
+    let coordinates = vec![LedPoint::new(0.0, 0.0), LedPoint::new(10.0, 0.0)];
+    WebSocketServer::open(&config, &coordinates).unwrap()
+}
+
+fn connect(server: &WebSocketServer) -> WebSocket<MaybeTlsStream<TcpStream>> {
+    let (client, _) = tungstenite::connect(format!("ws://{}/ws", server.address())).unwrap();
+    if let MaybeTlsStream::Plain(stream) = client.get_ref() {
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+    }
+    client
+}
+
+fn receive(client: &mut WebSocket<MaybeTlsStream<TcpStream>>) -> serde_json::Value {
+    match client.read().unwrap() {
+        Message::Text(text) => serde_json::from_str(&text).unwrap(),
+        other => panic!("unexpected message {:?}", other),
+    }
+}
+
+fn progress(driver_number: u32, laps: u32, percent: f64) -> DriverProgress {
+    DriverProgress {
+        driver_number,
+        percent,
+        laps,
+    }
+}
+
+#[test]
+fn orders_the_leaderboard_by_laps_covered() {
+    let drivers = [
+        progress(1, 2, 10.0),
+        progress(44, 2, 80.0),
+        progress(11, 3, 5.0),
+        progress(16, 2, 10.0),
+    ];
+    assert_eq!(running_order(&drivers), [11, 44, 1, 16]);
+}
+
+#[test]
+fn a_connected_client_receives_frames_and_the_leaderboard() {
+    let mut server = server();
+    let mut client = connect(&server);
+
+    assert_eq!(receive(&mut client)["type"], "layout");
+    let frame = receive(&mut client);
+    assert_eq!(frame["type"], "frame");
+    assert_eq!(frame["leds"], serde_json::json!([[0, 0, 0], [0, 0, 0]]));
+    assert_eq!(receive(&mut client)["type"], "leaderboard");
+
+    server
+        .submit(&OutputFrame {
+            leds: vec![[255, 0, 0], [0, 0, 0]],
+            brightness: 1.0,
+            timestamp: Duration::from_secs(3),
+            state: PlaybackState::Playing,
+            speed: 1.0,
+            drivers: vec![progress(1, 0, 10.0), progress(44, 0, 60.0)],
+        })
+        .unwrap();
+
+    let changes = receive(&mut client);
+    assert_eq!(changes["type"], "changes");
+    assert_eq!(changes["time"], 3.0);
+    assert_eq!(changes["changes"], serde_json::json!([[0, [255, 0, 0]]]));
+    let leaderboard = receive(&mut client);
+    assert_eq!(leaderboard["type"], "leaderboard");
+    assert_eq!(leaderboard["drivers"], serde_json::json!([44, 1]));
+
+    server.shutdown();
+}
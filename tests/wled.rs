@@ -0,0 +1,30 @@
+#![cfg(feature = "native")]
+
+use f1_led_circuit_master_simulation::wled::{json_colors, realtime_packets};
+use serde_json::json;
+
+#[test]
+fn splits_long_strips_into_packets() {
+    let leds = vec![[1, 2, 3]; 1000];
+
+    let packets = realtime_packets(&leds);
+    // Three DNRGB packets of up to 489 LEDs, each with the index of its first LED
+    let starts: Vec<[u8; 2]> = packets
+        .iter()
+        .map(|packet| [packet[2], packet[3]])
+        .collect();
+    assert_eq!(starts, [[0, 0], 489u16.to_be_bytes(), 978u16.to_be_bytes()]);
+    let colors: usize = packets.iter().map(|packet| (packet.len() - 4) / 3).sum();
+    assert_eq!(colors, leds.len());
+
+    let chunks = json_colors(&leds);
+    assert_eq!(chunks.len(), 4);
+    assert_eq!(chunks[1][..2], [json!(256), json!("010203")]);
+    assert_eq!(chunks[3].len(), 1 + 1000 - 768);
+
+    // A short strip is a single DRGB packet
+    assert_eq!(
+        realtime_packets(&leds[..96]),
+        [[&[2, 2][..], &[1, 2, 3].repeat(96)].concat()]
+    );
+}